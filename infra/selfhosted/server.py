@@ -10,7 +10,10 @@ import asyncio
 import io
 import os
 import base64
+import itertools
+import threading
 import time
+import uuid
 from contextlib import asynccontextmanager
 from pathlib import Path
 from typing import Optional
@@ -18,7 +21,7 @@ from typing import Optional
 from fastapi import FastAPI, HTTPException, Request
 from pydantic import BaseModel, Field
 import torch
-from diffusers import StableDiffusionXLPipeline, EulerAncestralDiscreteScheduler
+from diffusers import AutoPipelineForInpainting, StableDiffusionXLPipeline, EulerAncestralDiscreteScheduler
 from PIL import Image
 
 # Paths
@@ -64,6 +67,17 @@ current_model: Optional[str] = None
 ip_adapter_loaded = False
 last_request_time: float = time.time()
 
+# One pipeline call at a time; model loads and generations all take this lock
+gpu_lock = threading.Lock()
+
+
+async def on_gpu(fn, *args):
+    """Run blocking GPU work in a thread (so /jobs polls stay responsive), serialized."""
+    def locked():
+        with gpu_lock:
+            return fn(*args)
+    return await asyncio.get_running_loop().run_in_executor(None, locked)
+
 
 @asynccontextmanager
 async def lifespan(app: FastAPI):
@@ -123,7 +137,12 @@ class GenerateRequest(BaseModel):
     model: str = Field(default=DEFAULT_MODEL, description="Model to use: animagine, pony, noobai")
     # IP-Adapter
     reference_image: Optional[str] = None  # Base64 encoded
+    reference_images: Optional[list[str]] = None  # Base64 encoded, up to 4; supersedes reference_image
     ip_adapter_scale: float = Field(default=0.7, ge=0.0, le=1.0)
+    # Inpainting: source image and mask (white = regenerate), both base64
+    init_image: Optional[str] = None
+    mask_image: Optional[str] = None
+    strength: float = Field(default=0.99, ge=0.0, le=1.0)
     # LoRA
     lora_name: Optional[str] = None
     lora_scale: float = Field(default=0.8, ge=0.0, le=1.5)
@@ -135,10 +154,40 @@ class GenerateResponse(BaseModel):
     parameters: dict
 
 
+class RembgRequest(BaseModel):
+    image: str  # Base64 encoded
+
+
+class RembgResponse(BaseModel):
+    image: str  # Base64 encoded PNG with alpha
+
+
+class DetectRequest(BaseModel):
+    image: str  # Base64 encoded
+    region: str = Field(default="face", description="Region to detect: face, hand, person")
+    confidence: float = Field(default=0.3, ge=0.0, le=1.0)
+
+
+class DetectedBox(BaseModel):
+    x0: float
+    y0: float
+    x1: float
+    y1: float
+    confidence: float
+
+
+class DetectResponse(BaseModel):
+    boxes: list[DetectedBox]
+
+
 class SwitchModelRequest(BaseModel):
     model: str = Field(description="Model to load: animagine, pony, noobai")
 
 
+class WarmupRequest(BaseModel):
+    model: Optional[str] = Field(default=None, description="Model to warm up (default: current, else the default model)")
+
+
 def load_model(model_name: str) -> StableDiffusionXLPipeline:
     """Load a model pipeline, with caching."""
     global loaded_models, current_model
@@ -213,6 +262,7 @@ async def health():
         "vram_allocated_gb": round(torch.cuda.memory_allocated() / 1e9, 2) if torch.cuda.is_available() else None,
         "idle_seconds": round(idle_seconds, 1),
         "idle_timeout_minutes": IDLE_TIMEOUT_MINUTES,
+        "queue_length": sum(1 for j in jobs.values() if j.status in ("queued", "running")),
     }
 
 
@@ -245,7 +295,7 @@ async def list_loras():
 async def switch_model(request: SwitchModelRequest):
     """Pre-load a model without generating an image."""
     try:
-        load_model(request.model)
+        await on_gpu(load_model, request.model)
     except FileNotFoundError as e:
         raise HTTPException(status_code=503, detail=str(e))
     except ValueError as e:
@@ -254,9 +304,54 @@ async def switch_model(request: SwitchModelRequest):
     return {"status": "ok", "model": request.model}
 
 
+@app.post("/warmup")
+async def warmup(request: WarmupRequest):
+    """Load a model and run one tiny pass so CUDA kernels and buffers are ready.
+
+    The first real generation after a cold start otherwise pays for both.
+    """
+    model_name = request.model or current_model or DEFAULT_MODEL
+
+    def run():
+        start = time.time()
+        pipe = load_model(model_name)
+        load_seconds = time.time() - start
+
+        start = time.time()
+        warm_kwargs = {"prompt": "warmup", "width": 512, "height": 512, "num_inference_steps": 1}
+        if ip_adapter_loaded:
+            pipe.set_ip_adapter_scale(0.0)
+            warm_kwargs["ip_adapter_image"] = Image.new("RGB", (224, 224), (0, 0, 0))
+        pipe(**warm_kwargs)
+        return load_seconds, time.time() - start
+
+    try:
+        load_seconds, warmup_seconds = await on_gpu(run)
+    except FileNotFoundError as e:
+        raise HTTPException(status_code=503, detail=str(e))
+    except ValueError as e:
+        raise HTTPException(status_code=400, detail=str(e))
+
+    return {
+        "status": "ok",
+        "model": model_name,
+        "load_seconds": round(load_seconds, 1),
+        "warmup_seconds": round(warmup_seconds, 1),
+    }
+
+
 @app.post("/generate", response_model=GenerateResponse)
 async def generate(request: GenerateRequest):
-    """Generate an image from a prompt."""
+    """Generate an image from a prompt, blocking until done (/jobs reports progress)."""
+    return await on_gpu(run_generation, request)
+
+
+class JobCancelled(Exception):
+    pass
+
+
+def run_generation(request: GenerateRequest, job: Optional["Job"] = None) -> GenerateResponse:
+    """Generate one image. With a job, reports steps on it and stops when it's cancelled."""
     # Load/switch model if needed
     try:
         pipe = load_model(request.model)
@@ -281,16 +376,20 @@ async def generate(request: GenerateRequest):
 
     # IP-Adapter: process reference image if provided
     ip_adapter_image = None
-    if request.reference_image and ip_adapter_loaded:
+    references = request.reference_images or ([request.reference_image] if request.reference_image else [])
+    if len(references) > 4:
+        raise HTTPException(status_code=400, detail=f"At most 4 reference images, got {len(references)}")
+    if references and ip_adapter_loaded:
         try:
-            ref_bytes = base64.b64decode(request.reference_image)
-            ip_adapter_image = Image.open(io.BytesIO(ref_bytes)).convert("RGB")
+            images = [Image.open(io.BytesIO(base64.b64decode(r))).convert("RGB") for r in references]
+            # One IP-Adapter: a single image, or a nested list whose embeddings are combined
+            ip_adapter_image = images[0] if len(images) == 1 else [images]
             pipe.set_ip_adapter_scale(request.ip_adapter_scale)
-            print(f"Using reference image with IP-Adapter scale {request.ip_adapter_scale}")
+            print(f"Using {len(images)} reference image(s) with IP-Adapter scale {request.ip_adapter_scale}")
         except Exception as e:
             print(f"Warning: Could not process reference image: {e}")
             ip_adapter_image = None
-    elif request.reference_image and not ip_adapter_loaded:
+    elif references and not ip_adapter_loaded:
         print("Warning: Reference image provided but IP-Adapter not loaded")
 
     # When IP-Adapter is loaded but no reference provided, use a blank dummy at scale 0
@@ -323,13 +422,39 @@ async def generate(request: GenerateRequest):
     if ip_adapter_image is not None:
         gen_kwargs["ip_adapter_image"] = ip_adapter_image
 
-    image = pipe(**gen_kwargs).images[0]
+    if job is not None:
+        job.total_steps = steps
+
+        def on_step_end(pipeline, step, timestep, callback_kwargs):
+            job.step = step + 1
+            if job.cancel_requested:
+                pipeline._interrupt = True
+            return callback_kwargs
+
+        gen_kwargs["callback_on_step_end"] = on_step_end
+
+    if request.init_image and request.mask_image:
+        size = (request.width, request.height)
+        try:
+            init_image = Image.open(io.BytesIO(base64.b64decode(request.init_image))).convert("RGB").resize(size)
+            mask_image = Image.open(io.BytesIO(base64.b64decode(request.mask_image))).convert("L").resize(size)
+        except Exception as e:
+            raise HTTPException(status_code=400, detail=f"Could not decode inpainting images: {e}")
+        # Shares the loaded weights; no extra VRAM for a second pipeline
+        inpaint_pipe = AutoPipelineForInpainting.from_pipe(pipe)
+        gen_kwargs.update(image=init_image, mask_image=mask_image, strength=request.strength)
+        image = inpaint_pipe(**gen_kwargs).images[0]
+    else:
+        image = pipe(**gen_kwargs).images[0]
 
     # Unfuse LoRA after generation to avoid affecting subsequent requests
     if request.lora_name:
         pipe.unfuse_lora()
         pipe.unload_lora_weights()
 
+    if job is not None and job.cancel_requested:
+        raise JobCancelled()
+
     # Encode to base64
     buffer = io.BytesIO()
     image.save(buffer, format="PNG")
@@ -350,6 +475,191 @@ async def generate(request: GenerateRequest):
     )
 
 
+# -- Job queue ------------------------------------------------------------------
+#
+# POST /jobs queues a generation and returns at once; GET /jobs/{id} reports queue
+# position, denoising step and finally the result; DELETE /jobs/{id} cancels it
+# (a running job stops at the next step). pixery uses these when available and
+# falls back to the blocking /generate on older servers.
+
+# Finished jobs are kept this long for the client to collect
+JOB_RETENTION_SECONDS = 600
+
+
+class Job:
+    def __init__(self, request: GenerateRequest):
+        self.id = uuid.uuid4().hex
+        self.seq = next(job_sequence)
+        self.request = request
+        self.status = "queued"  # queued, running, done, failed, cancelled
+        self.step = 0
+        self.total_steps: Optional[int] = None
+        self.result: Optional[GenerateResponse] = None
+        self.error: Optional[str] = None
+        self.cancel_requested = False
+        self.finished_at: Optional[float] = None
+
+    def finish(self, status: str):
+        self.status = status
+        self.finished_at = time.time()
+
+
+class JobSubmitted(BaseModel):
+    job_id: str
+    position: int
+
+
+class JobStatus(BaseModel):
+    job_id: str
+    status: str
+    position: Optional[int] = None  # 1-based, while queued
+    step: int = 0
+    total_steps: Optional[int] = None
+    error: Optional[str] = None
+    result: Optional[GenerateResponse] = None
+
+
+jobs: dict[str, Job] = {}
+job_sequence = itertools.count()
+job_queue: Optional[asyncio.Queue] = None
+job_worker_task: Optional[asyncio.Task] = None
+
+
+def queue_position(job: Job) -> Optional[int]:
+    if job.status != "queued":
+        return None
+    return 1 + sum(1 for j in jobs.values() if j.status == "queued" and j.seq < job.seq)
+
+
+async def job_worker():
+    """Run queued jobs one at a time."""
+    while True:
+        job = await job_queue.get()
+        if job.status != "queued":
+            continue  # cancelled while waiting
+        job.status = "running"
+        try:
+            job.result = await on_gpu(run_generation, job.request, job)
+            job.finish("done")
+        except JobCancelled:
+            job.finish("cancelled")
+        except HTTPException as e:
+            job.error = str(e.detail)
+            job.finish("failed")
+        except Exception as e:
+            job.error = str(e)
+            job.finish("failed")
+
+
+@app.post("/jobs", response_model=JobSubmitted)
+async def submit_job(request: GenerateRequest):
+    """Queue a generation; poll GET /jobs/{id} for progress and the result."""
+    global job_queue, job_worker_task
+    if job_queue is None:
+        job_queue = asyncio.Queue()
+        job_worker_task = asyncio.create_task(job_worker())
+
+    now = time.time()
+    for job_id in [j.id for j in jobs.values() if j.finished_at and now - j.finished_at > JOB_RETENTION_SECONDS]:
+        del jobs[job_id]
+
+    job = Job(request)
+    jobs[job.id] = job
+    await job_queue.put(job)
+    return JobSubmitted(job_id=job.id, position=queue_position(job))
+
+
+@app.get("/jobs/{job_id}", response_model=JobStatus)
+async def get_job(job_id: str):
+    job = jobs.get(job_id)
+    if job is None:
+        raise HTTPException(status_code=404, detail=f"Unknown job: {job_id}")
+    return JobStatus(
+        job_id=job.id,
+        status=job.status,
+        position=queue_position(job),
+        step=job.step,
+        total_steps=job.total_steps,
+        error=job.error,
+        result=job.result,
+    )
+
+
+@app.delete("/jobs/{job_id}")
+async def cancel_job(job_id: str):
+    """Cancel a queued job, or stop a running one at its next step."""
+    job = jobs.get(job_id)
+    if job is None:
+        raise HTTPException(status_code=404, detail=f"Unknown job: {job_id}")
+    if job.status == "queued":
+        job.finish("cancelled")
+    elif job.status == "running":
+        job.cancel_requested = True
+    return {"status": job.status, "cancel_requested": job.cancel_requested}
+
+
+@app.post("/rembg", response_model=RembgResponse)
+async def rembg_endpoint(request: RembgRequest):
+    """Remove the background from a reference image (pixery --ref-rembg)."""
+    try:
+        from rembg import remove
+    except ImportError:
+        raise HTTPException(status_code=503, detail="rembg is not installed on this server")
+
+    try:
+        source = Image.open(io.BytesIO(base64.b64decode(request.image)))
+    except Exception as e:
+        raise HTTPException(status_code=400, detail=f"Could not decode image: {e}")
+
+    result = remove(source)
+    buffer = io.BytesIO()
+    result.save(buffer, format="PNG")
+    return RembgResponse(image=base64.b64encode(buffer.getvalue()).decode("utf-8"))
+
+
+# YOLOv8 detectors from the ADetailer weights repo, loaded on first use
+DETECTOR_REPO = "Bingsu/adetailer"
+DETECTOR_WEIGHTS = {
+    "face": "face_yolov8n.pt",
+    "hand": "hand_yolov8n.pt",
+    "person": "person_yolov8n-seg.pt",
+}
+loaded_detectors = {}
+
+
+@app.post("/detect", response_model=DetectResponse)
+async def detect_endpoint(request: DetectRequest):
+    """Detect regions to re-detail (pixery fix). Boxes are in source pixels, best first."""
+    try:
+        from ultralytics import YOLO
+    except ImportError:
+        raise HTTPException(status_code=503, detail="ultralytics is not installed on this server")
+
+    weights = DETECTOR_WEIGHTS.get(request.region)
+    if weights is None:
+        raise HTTPException(
+            status_code=400,
+            detail=f"Unknown region: {request.region}. Available: {list(DETECTOR_WEIGHTS.keys())}",
+        )
+
+    try:
+        source = Image.open(io.BytesIO(base64.b64decode(request.image))).convert("RGB")
+    except Exception as e:
+        raise HTTPException(status_code=400, detail=f"Could not decode image: {e}")
+
+    if request.region not in loaded_detectors:
+        from huggingface_hub import hf_hub_download
+        loaded_detectors[request.region] = YOLO(hf_hub_download(DETECTOR_REPO, weights))
+
+    results = loaded_detectors[request.region](source, conf=request.confidence, verbose=False)
+    boxes = []
+    for box in results[0].boxes:
+        x0, y0, x1, y1 = box.xyxy[0].tolist()
+        boxes.append(DetectedBox(x0=x0, y0=y0, x1=x1, y1=y1, confidence=float(box.conf[0])))
+    boxes.sort(key=lambda b: b.confidence, reverse=True)
+    return DetectResponse(boxes=boxes)
+
+
 if __name__ == "__main__":
     import uvicorn
     uvicorn.run(app, host="0.0.0.0", port=8000)
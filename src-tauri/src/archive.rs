@@ -1,15 +1,22 @@
 use anyhow::{Context, Result};
 use image::GenericImageView;
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread::JoinHandle;
 
-/// Root directory for all image generation data
+use crate::models::Generation;
+use crate::profiles;
+use crate::template::{NameContext, NameTemplate};
+use crate::video;
+
+/// Root directory for all image generation data: ~/media/image-gen, or the
+/// root of the archive profile in use
 pub fn archive_root() -> PathBuf {
-    dirs::home_dir()
-        .expect("Could not find home directory")
-        .join("media")
-        .join("image-gen")
+    profiles::current_root()
 }
 
 /// Directory for generated images
@@ -22,11 +29,25 @@ pub fn references_dir() -> PathBuf {
     archive_root().join("references")
 }
 
+/// Directory for files generations own besides their image (`pixery assets`)
+pub fn assets_dir() -> PathBuf {
+    archive_root().join("assets")
+}
+
 /// Path to the SQLite database
 pub fn db_path() -> PathBuf {
     archive_root().join("index.sqlite")
 }
 
+/// Temp directory for resized CLI previews (`pixery view --width`), one per
+/// archive profile so maintenance in one doesn't prune another's
+pub fn preview_dir() -> PathBuf {
+    match profiles::current_name() {
+        profiles::DEFAULT_PROFILE => PathBuf::from("/tmp/pixery-preview"),
+        name => PathBuf::from(format!("/tmp/pixery-preview-{}", name)),
+    }
+}
+
 /// Ensure all archive directories exist
 pub fn ensure_dirs() -> Result<()> {
     fs::create_dir_all(generations_dir()).context("Failed to create generations directory")?;
@@ -74,7 +95,47 @@ pub fn generate_filename(slug: &str, timestamp: &str, extension: &str) -> String
     format!("{}-{}.{}", slug, time_part, extension)
 }
 
-/// Save image data to the archive
+/// Write a new file to a date directory, with a counter appended if another
+/// generation already has its name. Names are claimed with create_new, so two
+/// processes saving at once (GUI and CLI) never overwrite each other's file.
+fn write_unique(dir: &Path, slug: &str, timestamp: &str, extension: &str, data: &[u8]) -> Result<PathBuf> {
+    use std::io::Write;
+
+    let stem = format!("{}-{}", slug, timestamp.split('T').nth(1).unwrap_or("000000").replace(':', "").chars().take(6).collect::<String>());
+    let mut path = dir.join(generate_filename(slug, timestamp, extension));
+    let mut counter = 1;
+    loop {
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(data)?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                path = dir.join(format!("{}-{}.{}", stem, counter, extension));
+                counter += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Save a video clip to the archive, returning (path, grid thumbnail path,
+/// file size). Its thumbnails are of the first frame, written later by
+/// `queue_thumbnails` like an image's.
+pub fn save_video(data: &[u8], date: &str, slug: &str, timestamp: &str) -> Result<(PathBuf, PathBuf, i64)> {
+    let extension = video::extension_for(data).context("Provider returned something that isn't a video")?;
+    let dir = date_dir(date);
+    fs::create_dir_all(&dir).context("Failed to create date directory")?;
+
+    let video_path = write_unique(&dir, slug, timestamp, extension, data).context("Failed to write video file")?;
+    let thumb_path = thumbnail_path(&video_path, ThumbSize::Grid);
+    Ok((video_path, thumb_path, data.len() as i64))
+}
+
+/// Save image data to the archive. The bytes are written as given, so
+/// animated GIF/WebP/APNG stay animated; only the dimensions are decoded.
+/// The returned thumbnail path is where the grid thumbnail goes; it is
+/// written later by `queue_thumbnails`.
 pub fn save_image(
     data: &[u8],
     date: &str,
@@ -89,57 +150,170 @@ pub fn save_image(
     let extension = match format {
         image::ImageFormat::Jpeg => "jpg",
         image::ImageFormat::WebP => "webp",
+        image::ImageFormat::Gif => "gif",
         _ => "png",
     };
 
-    let base_filename = generate_filename(slug, timestamp, extension);
-    let mut image_path = dir.join(&base_filename);
-
-    // Handle filename collisions by appending a counter
-    if image_path.exists() {
-        let stem = format!("{}-{}", slug, timestamp.split('T').nth(1).unwrap_or("000000").replace(':', "").chars().take(6).collect::<String>());
-        let mut counter = 1;
-        loop {
-            let filename = format!("{}-{}.{}", stem, counter, extension);
-            image_path = dir.join(&filename);
-            if !image_path.exists() {
-                break;
-            }
-            counter += 1;
-        }
-    }
-
-    fs::write(&image_path, data).context("Failed to write image file")?;
-
-    // Get dimensions
+    // Get dimensions, before writing so an undecodable file isn't left behind
     let img = image::load_from_memory(data).context("Failed to decode image")?;
     let (width, height) = img.dimensions();
     let file_size = data.len() as i64;
 
-    // Generate thumbnail
-    let thumb_path = generate_thumbnail(&image_path, &img)?;
+    let image_path = write_unique(&dir, slug, timestamp, extension, data).context("Failed to write image file")?;
+
+    let thumb_path = thumbnail_path(&image_path, ThumbSize::Grid);
+    Ok((image_path, Some(thumb_path), width as i32, height as i32, file_size))
+}
+
+/// Animated GIF, WebP or PNG (APNG), from the image's bytes. Only headers are
+/// read, plus a GIF's first two frames. `save_image` writes these untouched;
+/// their thumbnails are of the first frame.
+pub fn is_animated(data: &[u8]) -> bool {
+    use image::codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder};
+    use image::AnimationDecoder;
+
+    let cursor = || std::io::Cursor::new(data);
+    match image::guess_format(data) {
+        Ok(image::ImageFormat::Gif) => GifDecoder::new(cursor())
+            .is_ok_and(|d| d.into_frames().take(2).filter(|frame| frame.is_ok()).count() == 2),
+        Ok(image::ImageFormat::WebP) => WebPDecoder::new(cursor()).is_ok_and(|d| d.has_animation()),
+        Ok(image::ImageFormat::Png) => PngDecoder::new(cursor()).is_ok_and(|d| d.is_apng().unwrap_or(false)),
+        _ => false,
+    }
+}
 
-    Ok((image_path, thumb_path, width as i32, height as i32, file_size))
+/// Thumbnail sizes, named after their image ("x.thumb.jpg", "x.thumb.800.jpg")
+/// so either can be rebuilt from it. The grid one is the stored thumb_path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbSize {
+    /// Gallery grid: 400px, sharp on Retina at the largest grid size
+    Grid,
+    /// Details panel: 800px
+    Preview,
 }
 
-/// Thumbnail size in pixels (400px for Retina display support)
-pub const THUMBNAIL_SIZE: u32 = 400;
+impl ThumbSize {
+    pub const ALL: [ThumbSize; 2] = [ThumbSize::Grid, ThumbSize::Preview];
 
-/// Generate a thumbnail for an image
-fn generate_thumbnail(image_path: &Path, img: &image::DynamicImage) -> Result<Option<PathBuf>> {
-    let thumb = img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    /// Longest side in pixels
+    pub fn pixels(self) -> u32 {
+        match self {
+            ThumbSize::Grid => 400,
+            ThumbSize::Preview => 800,
+        }
+    }
+}
 
+pub fn thumbnail_path(image_path: &Path, size: ThumbSize) -> PathBuf {
     let stem = image_path
         .file_stem()
         .and_then(|s| s.to_str())
         .unwrap_or("image");
+    let filename = match size {
+        ThumbSize::Grid => format!("{}.thumb.jpg", stem),
+        ThumbSize::Preview => format!("{}.thumb.{}.jpg", stem, size.pixels()),
+    };
+    image_path.with_file_name(filename)
+}
+
+/// Write one thumbnail size. It goes through a temporary file, so a reader
+/// (or a second writer) never sees a partial image.
+pub fn write_thumbnail(image_path: &Path, img: &image::DynamicImage, size: ThumbSize) -> Result<PathBuf> {
+    let thumb_path = thumbnail_path(image_path, size);
+    static STAGED: AtomicU64 = AtomicU64::new(0);
+    let staged = thumb_path.with_extension(format!(
+        "{}-{}.tmp",
+        std::process::id(),
+        STAGED.fetch_add(1, Ordering::Relaxed)
+    ));
+    // Never upscale: an image smaller than the size is used as-is
+    let (width, height) = img.dimensions();
+    let thumb = if width.max(height) > size.pixels() {
+        img.thumbnail(size.pixels(), size.pixels())
+    } else {
+        img.clone()
+    };
+    let rgb = if thumb.color().has_alpha() { over_checkerboard(&thumb) } else { thumb.to_rgb8() };
+    rgb.save_with_format(&staged, image::ImageFormat::Jpeg)
+        .context("Failed to save thumbnail")?;
+    fs::rename(&staged, &thumb_path).context("Failed to save thumbnail")?;
+    Ok(thumb_path)
+}
 
-    let thumb_filename = format!("{}.thumb.jpg", stem);
-    let thumb_path = image_path.with_file_name(thumb_filename);
+/// Flatten an image with alpha onto a light checkerboard, so JPEG thumbnails
+/// of transparent images show where the transparency is
+fn over_checkerboard(img: &image::DynamicImage) -> image::RgbImage {
+    const SQUARE: u32 = 8;
+    let rgba = img.to_rgba8();
+    image::RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let background = if (x / SQUARE + y / SQUARE).is_multiple_of(2) { 255 } else { 204 };
+        let blend = |c: u8| ((c as u32 * a as u32 + background * (255 - a as u32)) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// The picture a file's thumbnails are made from: the image itself, or a
+/// video's first frame
+pub fn thumbnail_source(path: &Path) -> Result<image::DynamicImage> {
+    if video::is_video(path) {
+        return video::poster_frame(path);
+    }
+    image::open(path).with_context(|| format!("Failed to open {}", path.display()))
+}
 
-    thumb.save(&thumb_path).context("Failed to save thumbnail")?;
+/// A thumbnail's path, creating it from the image first if it's missing
+/// (still queued, deleted, or from before that size existed)
+pub fn ensure_thumbnail(image_path: &Path, size: ThumbSize) -> Result<PathBuf> {
+    let thumb_path = thumbnail_path(image_path, size);
+    if thumb_path.exists() {
+        return Ok(thumb_path);
+    }
+    write_thumbnail(image_path, &thumbnail_source(image_path)?, size)
+}
 
-    Ok(Some(thumb_path))
+/// The thumbnail to show for an image, created if missing; the image itself
+/// if that fails
+pub fn display_path(image_path: &str, size: ThumbSize) -> String {
+    ensure_thumbnail(Path::new(image_path), size)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| image_path.to_string())
+}
+
+/// Background thumbnail worker: one thread, fed in order, started on first use
+static THUMBNAIL_WORKER: Mutex<Option<(mpsc::Sender<PathBuf>, JoinHandle<()>)>> = Mutex::new(None);
+
+/// Write every thumbnail size for an image on the background worker, so
+/// saving doesn't wait for them. Failures are only logged: `ensure_thumbnail`
+/// fills in whatever is missing when it's asked for.
+pub fn queue_thumbnails(image_path: PathBuf) {
+    let mut worker = THUMBNAIL_WORKER.lock().unwrap();
+    let (sender, _) = worker.get_or_insert_with(|| {
+        let (sender, receiver) = mpsc::channel::<PathBuf>();
+        let handle = std::thread::spawn(move || {
+            for path in receiver {
+                let written = thumbnail_source(&path)
+                    .and_then(|img| ThumbSize::ALL.iter().try_for_each(|&size| write_thumbnail(&path, &img, size).map(|_| ())));
+                if let Err(e) = written {
+                    eprintln!("Thumbnails for {} failed: {}", path.display(), e);
+                }
+            }
+        });
+        (sender, handle)
+    });
+    let _ = sender.send(image_path);
+}
+
+/// Wait for queued thumbnails to be written. The CLI calls this before
+/// exiting; the GUI's worker just keeps running.
+pub fn wait_for_thumbnails() {
+    let worker = THUMBNAIL_WORKER.lock().unwrap().take();
+    if let Some((sender, handle)) = worker {
+        // The worker stops once the queue is drained and the sender is gone
+        drop(sender);
+        let _ = handle.join();
+    }
 }
 
 /// Compute SHA-256 hash of file contents
@@ -148,6 +322,24 @@ pub fn hash_file(path: &Path) -> Result<String> {
     hash_bytes(&data)
 }
 
+/// Image file (by extension) that isn't one of our thumbnails
+pub fn is_image(path: &Path) -> bool {
+    let is_image = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            let ext = ext.to_ascii_lowercase();
+            ext == "png" || ext == "jpg" || ext == "jpeg" || ext == "webp" || ext == "gif"
+        })
+        .unwrap_or(false);
+    let is_thumb = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.contains(".thumb."))
+        .unwrap_or(false);
+    is_image && !is_thumb
+}
+
 /// Compute SHA-256 hash of bytes
 pub fn hash_bytes(data: &[u8]) -> Result<String> {
     let mut hasher = Sha256::new();
@@ -160,7 +352,6 @@ pub fn hash_bytes(data: &[u8]) -> Result<String> {
 /// Returns (hash, path) - path may be existing if duplicate
 pub fn store_reference(source_path: &Path) -> Result<(String, PathBuf)> {
     let data = fs::read(source_path).context("Failed to read reference image")?;
-    let hash = hash_bytes(&data)?;
 
     // Determine extension from source
     let extension = source_path
@@ -168,16 +359,32 @@ pub fn store_reference(source_path: &Path) -> Result<(String, PathBuf)> {
         .and_then(|e| e.to_str())
         .unwrap_or("png");
 
+    store_reference_bytes(&data, extension)
+}
+
+/// Store reference image bytes that have no source file (clipboard, drag-and-drop).
+/// Same deduplication and naming as `store_reference`.
+pub fn store_reference_bytes(data: &[u8], extension: &str) -> Result<(String, PathBuf)> {
+    let hash = hash_bytes(data)?;
     let dest_path = references_dir().join(format!("{}.{}", hash, extension));
 
     if !dest_path.exists() {
         fs::create_dir_all(references_dir()).context("Failed to create references directory")?;
-        fs::copy(source_path, &dest_path).context("Failed to copy reference image")?;
+        fs::write(&dest_path, data).context("Failed to write reference image")?;
     }
 
     Ok((hash, dest_path))
 }
 
+/// Store an image handed over as bytes (GUI drag-and-drop) as a reference.
+/// The format is read from the data, so anything that isn't an image is rejected.
+pub fn stage_reference(data: &[u8]) -> Result<PathBuf> {
+    let format = image::guess_format(data).context("Not a supported image")?;
+    let extension = format.extensions_str().first().copied().unwrap_or("png");
+    let (_, path) = store_reference_bytes(data, extension)?;
+    Ok(path)
+}
+
 /// Copy an image to a destination path
 pub fn copy_to(source: &Path, dest: &Path) -> Result<()> {
     if let Some(parent) = dest.parent() {
@@ -187,16 +394,25 @@ pub fn copy_to(source: &Path, dest: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Delete an image and its thumbnail
+/// Copy a generation's image into `dir` under a templated name; returns the path
+pub fn copy_named(generation: &Generation, dir: &Path, template: &NameTemplate) -> Result<PathBuf> {
+    let source = Path::new(&generation.image_path);
+    let extension = source.extension().and_then(|e| e.to_str()).unwrap_or("png");
+    let ctx = NameContext { generation, collection: None, tag: None, counter: 1 };
+    let dest = template.path_in(dir, &ctx, extension);
+    copy_to(source, &dest)?;
+    Ok(dest)
+}
+
+/// Delete an image and its thumbnails
 pub fn delete_image(image_path: &Path) -> Result<()> {
     // Delete main image
     if image_path.exists() {
         fs::remove_file(image_path).context("Failed to delete image")?;
     }
 
-    // Delete thumbnail if it exists
-    if let Some(stem) = image_path.file_stem().and_then(|s| s.to_str()) {
-        let thumb_path = image_path.with_file_name(format!("{}.thumb.jpg", stem));
+    for size in ThumbSize::ALL {
+        let thumb_path = thumbnail_path(image_path, size);
         if thumb_path.exists() {
             let _ = fs::remove_file(thumb_path);
         }
@@ -205,3 +421,57 @@ pub fn delete_image(image_path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Copy a file into a generation's asset directory, keeping its name (numbered
+/// when another asset has it). Returns the stored path and its size.
+pub fn store_asset(generation_id: i64, source: &Path) -> Result<(PathBuf, i64)> {
+    let dir = assets_dir().join(generation_id.to_string());
+    fs::create_dir_all(&dir).context("Failed to create asset directory")?;
+    let stem = source.file_stem().and_then(|s| s.to_str()).unwrap_or("asset");
+    let extension = source.extension().and_then(|e| e.to_str());
+    let name = |n: usize| {
+        let stem = if n == 1 { stem.to_string() } else { format!("{}-{}", stem, n) };
+        match extension {
+            Some(ext) => format!("{}.{}", stem, ext),
+            None => stem,
+        }
+    };
+    let mut n = 1;
+    while dir.join(name(n)).exists() {
+        n += 1;
+    }
+    let dest = dir.join(name(n));
+    let size = fs::copy(source, &dest).with_context(|| format!("Failed to copy {}", source.display()))?;
+    Ok((dest, size as i64))
+}
+
+/// Delete a generation's asset files (after its rows went with the generation)
+pub fn delete_assets(generation_id: i64) -> Result<()> {
+    let dir = assets_dir().join(generation_id.to_string());
+    if dir.exists() {
+        fs::remove_dir_all(&dir).context("Failed to delete assets")?;
+    }
+    Ok(())
+}
+
+/// Remove preview files older than `max_age`. Returns the number removed.
+pub fn prune_previews(max_age: std::time::Duration) -> Result<usize> {
+    let dir = preview_dir();
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let mut removed = 0;
+    for entry in fs::read_dir(&dir).context("Failed to read preview directory")? {
+        let entry = entry?;
+        let modified = entry.metadata().and_then(|m| m.modified());
+        let expired = modified
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .map(|age| age > max_age)
+            .unwrap_or(false);
+        if expired && fs::remove_file(entry.path()).is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
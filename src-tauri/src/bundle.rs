@@ -0,0 +1,389 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use crate::archive;
+use crate::db::Database;
+use crate::models::{AssetKind, Generation, ListFilter, MediaType};
+use crate::palette;
+
+/// Bump when the manifest layout changes incompatibly. Version 2 added
+/// videos; bundles without any are still written as version 1, which older
+/// pixery can read.
+const BUNDLE_VERSION: u32 = 2;
+const IMAGES_ONLY_VERSION: u32 = 1;
+const MANIFEST_NAME: &str = "manifest.json";
+
+/// Bundle contents index. IDs are the exporting archive's; import remaps them.
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    exported_at: String,
+    generations: Vec<BundleGeneration>,
+    #[serde(default)]
+    collections: Vec<BundleCollection>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BundleGeneration {
+    id: i64,
+    slug: String,
+    prompt: String,
+    model: String,
+    provider: String,
+    timestamp: String,
+    date: String,
+    /// Image (or video) path inside the bundle
+    image: String,
+    #[serde(default)]
+    media_type: MediaType,
+    /// Recorded for videos only; an image's are read from the file on import
+    #[serde(default)]
+    width: Option<i32>,
+    #[serde(default)]
+    height: Option<i32>,
+    #[serde(default)]
+    duration_seconds: Option<f64>,
+    #[serde(default)]
+    fps: Option<f64>,
+    generation_time_seconds: Option<f64>,
+    cost_estimate_usd: Option<f64>,
+    seed: Option<String>,
+    parent_id: Option<i64>,
+    #[serde(default)]
+    starred: bool,
+    #[serde(default)]
+    locked: bool,
+    #[serde(default)]
+    nsfw: bool,
+    title: Option<String>,
+    negative_prompt: Option<String>,
+    #[serde(default)]
+    caption: Option<String>,
+    #[serde(default)]
+    requested_by: Option<String>,
+    #[serde(default)]
+    params: Option<serde_json::Value>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    collections: Vec<String>,
+    /// Reference image paths inside the bundle
+    #[serde(default)]
+    references: Vec<String>,
+    #[serde(default)]
+    assets: Vec<BundleAsset>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BundleAsset {
+    kind: AssetKind,
+    /// File path inside the bundle
+    file: String,
+    note: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BundleCollection {
+    name: String,
+    description: Option<String>,
+}
+
+/// Outcome of an import
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Already present (same timestamp, model and prompt)
+    pub skipped: usize,
+}
+
+fn file_name(path: &Path) -> Result<String> {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.to_string())
+        .ok_or_else(|| anyhow::anyhow!("Invalid file path: {}", path.display()))
+}
+
+/// Write non-trashed generations (optionally since a date) with their images,
+/// references, assets, tags and collections to a zstd-compressed tar bundle.
+/// Returns the number of generations written.
+pub fn export(db: &Database, since: Option<String>, output: &Path) -> Result<usize> {
+    let generations: Vec<Generation> = db
+        .list_generations(&ListFilter {
+            since,
+            ..Default::default()
+        })?
+        .into_iter()
+        .filter(|g| {
+            let exists = Path::new(&g.image_path).exists();
+            if !exists {
+                eprintln!("Image file missing for ID {}, skipping", g.id);
+            }
+            exists
+        })
+        .collect();
+
+    if generations.is_empty() {
+        anyhow::bail!("No generations to export");
+    }
+
+    let file = File::create(output)
+        .with_context(|| format!("Failed to create {}", output.display()))?;
+    let encoder = zstd::Encoder::new(file, 3)?;
+    let mut tar = tar::Builder::new(encoder);
+
+    let mut entries = vec![];
+    let mut added_refs: HashSet<String> = HashSet::new();
+    let mut used_collections: HashSet<String> = HashSet::new();
+
+    for gen in &generations {
+        let image = format!("images/{}/{}", gen.id, file_name(Path::new(&gen.image_path))?);
+        tar.append_path_with_name(&gen.image_path, &image)
+            .with_context(|| format!("Failed to add image for ID {}", gen.id))?;
+
+        let mut references = vec![];
+        for r in &gen.references {
+            let path = Path::new(&r.path);
+            if !path.exists() {
+                continue;
+            }
+            let name = format!("refs/{}", file_name(path)?);
+            if added_refs.insert(name.clone()) {
+                tar.append_path_with_name(path, &name)
+                    .with_context(|| format!("Failed to add reference {}", r.hash))?;
+            }
+            references.push(name);
+        }
+
+        let mut assets = vec![];
+        for asset in db.get_assets_for_generation(gen.id)? {
+            let path = Path::new(&asset.path);
+            if !path.exists() {
+                eprintln!("Asset file {} missing for ID {}, skipping", asset.id, gen.id);
+                continue;
+            }
+            let file = format!("assets/{}/{}", gen.id, file_name(path)?);
+            tar.append_path_with_name(path, &file)
+                .with_context(|| format!("Failed to add asset {} of ID {}", asset.id, gen.id))?;
+            assets.push(BundleAsset { kind: asset.kind, file, note: asset.note });
+        }
+
+        used_collections.extend(gen.collection_names.iter().cloned());
+
+        entries.push(BundleGeneration {
+            id: gen.id,
+            slug: gen.slug.clone(),
+            prompt: gen.prompt.clone(),
+            model: gen.model.clone(),
+            provider: gen.provider.clone(),
+            timestamp: gen.timestamp.clone(),
+            date: gen.date.clone(),
+            image,
+            media_type: gen.media_type,
+            width: gen.width,
+            height: gen.height,
+            duration_seconds: gen.duration_seconds,
+            fps: gen.fps,
+            generation_time_seconds: gen.generation_time_seconds,
+            cost_estimate_usd: gen.cost_estimate_usd,
+            seed: gen.seed.clone(),
+            parent_id: gen.parent_id,
+            starred: gen.starred,
+            locked: gen.locked,
+            nsfw: gen.nsfw,
+            title: gen.title.clone(),
+            negative_prompt: gen.negative_prompt.clone(),
+            caption: gen.caption.clone(),
+            requested_by: gen.requested_by.clone(),
+            params: gen.params.clone(),
+            tags: gen.tags.clone(),
+            collections: gen.collection_names.clone(),
+            references,
+            assets,
+        });
+    }
+
+    let collections = db
+        .list_collections()?
+        .into_iter()
+        .filter(|c| used_collections.contains(&c.name))
+        .map(|c| BundleCollection {
+            name: c.name,
+            description: c.description,
+        })
+        .collect();
+
+    let has_video = entries.iter().any(|g| g.media_type == MediaType::Video);
+    let manifest = Manifest {
+        version: if has_video { BUNDLE_VERSION } else { IMAGES_ONLY_VERSION },
+        exported_at: chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        generations: entries,
+        collections,
+    };
+    let data = serde_json::to_vec_pretty(&manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    tar.append_data(&mut header, MANIFEST_NAME, data.as_slice())?;
+
+    tar.into_inner()?.finish().context("Failed to finish bundle")?;
+    Ok(generations.len())
+}
+
+/// Merge a bundle into this archive. Generation IDs are remapped (including
+/// parent links); generations already present are skipped, so importing the
+/// same bundle twice is harmless.
+pub fn import(db: &Database, bundle: &Path) -> Result<ImportReport> {
+    let staging = std::env::temp_dir().join(format!("pixery-import-{}", std::process::id()));
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    std::fs::create_dir_all(&staging).context("Failed to create staging directory")?;
+
+    let result = import_staged(db, bundle, &staging);
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+fn import_staged(db: &Database, bundle: &Path, staging: &Path) -> Result<ImportReport> {
+    let file = File::open(bundle).with_context(|| format!("Failed to open {}", bundle.display()))?;
+    tar::Archive::new(zstd::Decoder::new(file)?)
+        .unpack(staging)
+        .context("Failed to unpack bundle")?;
+
+    let manifest: Manifest = serde_json::from_str(
+        &std::fs::read_to_string(staging.join(MANIFEST_NAME)).context("Bundle has no manifest")?,
+    )
+    .context("Invalid bundle manifest")?;
+    if manifest.version > BUNDLE_VERSION {
+        anyhow::bail!(
+            "Bundle version {} is newer than this pixery supports ({})",
+            manifest.version,
+            BUNDLE_VERSION
+        );
+    }
+
+    let existing: HashSet<String> = db.list_collections()?.into_iter().map(|c| c.name).collect();
+    for c in &manifest.collections {
+        if !existing.contains(&c.name) {
+            db.create_collection(&c.name, c.description.as_deref())?;
+        }
+    }
+
+    let mut report = ImportReport::default();
+    // Bundle ID -> local ID, for both imported and already-present generations
+    let mut id_map: HashMap<i64, i64> = HashMap::new();
+    let mut imported: Vec<&BundleGeneration> = vec![];
+
+    for gen in &manifest.generations {
+        if let Some(local_id) = db.find_generation_id(&gen.timestamp, &gen.model, &gen.prompt)? {
+            id_map.insert(gen.id, local_id);
+            report.skipped += 1;
+            continue;
+        }
+
+        let data = std::fs::read(staged_path(staging, &gen.image)?)
+            .with_context(|| format!("Bundle is missing the image for ID {}", gen.id))?;
+        let (image_path, thumb_path, width, height, file_size) = match gen.media_type {
+            MediaType::Video => {
+                let (path, thumb, size) = archive::save_video(&data, &gen.date, &gen.slug, &gen.timestamp)?;
+                (path, Some(thumb), gen.width, gen.height, size)
+            }
+            MediaType::Image => {
+                let (path, thumb, width, height, size) = archive::save_image(&data, &gen.date, &gen.slug, &gen.timestamp)?;
+                (path, thumb, Some(width), Some(height), size)
+            }
+        };
+
+        let new_id = db.insert_generation(
+            &gen.slug,
+            &gen.prompt,
+            &gen.model,
+            &gen.provider,
+            &gen.timestamp,
+            &gen.date,
+            image_path.to_str().unwrap(),
+            thumb_path.as_ref().and_then(|p| p.to_str()),
+            gen.generation_time_seconds,
+            gen.cost_estimate_usd,
+            gen.seed.as_deref(),
+            width,
+            height,
+            Some(file_size),
+            None, // parent_id: remapped below once every generation has a local ID
+            gen.negative_prompt.as_deref(),
+        )?;
+        db.set_content_hash(new_id, &archive::hash_bytes(&data)?)?;
+        match gen.media_type {
+            MediaType::Video => db.set_video_metadata(new_id, gen.duration_seconds, gen.fps)?,
+            MediaType::Image if archive::is_animated(&data) => db.set_animated(new_id)?,
+            MediaType::Image => {}
+        }
+        palette::record_new(db, new_id, &image_path);
+        archive::queue_thumbnails(image_path.clone());
+        id_map.insert(gen.id, new_id);
+
+        if !gen.tags.is_empty() {
+            db.add_tags(new_id, &gen.tags)?;
+        }
+        for name in &gen.collections {
+            db.add_to_collection(new_id, name)?;
+        }
+        for r in &gen.references {
+            let (hash, stored) = archive::store_reference(&staged_path(staging, r)?)?;
+            let ref_id = db.get_or_create_reference(&hash, stored.to_str().unwrap())?;
+            db.link_reference(new_id, ref_id)?;
+        }
+        for asset in &gen.assets {
+            let (path, size) = archive::store_asset(new_id, &staged_path(staging, &asset.file)?)?;
+            db.insert_asset(new_id, asset.kind, path.to_str().unwrap(), Some(size), asset.note.as_deref())?;
+        }
+        if gen.title.is_some() {
+            db.update_title(new_id, gen.title.as_deref())?;
+        }
+        if let Some(caption) = &gen.caption {
+            db.set_caption(new_id, caption)?;
+        }
+        if let Some(requested_by) = &gen.requested_by {
+            db.set_requested_by(new_id, requested_by)?;
+        }
+        if let Some(params) = &gen.params {
+            db.set_generation_params(new_id, params)?;
+        }
+        if gen.starred {
+            db.toggle_starred(new_id)?;
+        }
+        if gen.nsfw {
+            db.set_nsfw(new_id, true)?;
+        }
+
+        imported.push(gen);
+        report.imported += 1;
+    }
+
+    for gen in &imported {
+        let new_id = id_map[&gen.id];
+        // Parents outside the bundle can't be resolved; leave those unlinked
+        if let Some(parent) = gen.parent_id.and_then(|p| id_map.get(&p)) {
+            db.set_parent_id(new_id, *parent)?;
+        }
+        // Lock last so the edits above aren't rejected
+        if gen.locked {
+            db.set_locked(new_id, true)?;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Resolve a manifest path inside the staging directory, rejecting escapes
+fn staged_path(staging: &Path, rel: &str) -> Result<PathBuf> {
+    let rel = Path::new(rel);
+    if rel.is_absolute() || rel.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+        anyhow::bail!("Invalid path in bundle manifest: {}", rel.display());
+    }
+    Ok(staging.join(rel))
+}
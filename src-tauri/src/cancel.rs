@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+/// How long a cancelled job waits for its provider-side cleanup (queue cancel requests)
+const CLEANUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Error for a generation stopped by `cancel` (GUI cancel button, CLI Ctrl-C)
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Generation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// True if `err` is (or wraps) a cancellation rather than a real failure
+pub fn is_cancelled(err: &anyhow::Error) -> bool {
+    err.is::<Cancelled>()
+}
+
+/// Shared flag a running job waits on alongside its provider call
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<TokenState>,
+}
+
+#[derive(Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once `cancel` has been called
+    pub async fn cancelled(&self) {
+        loop {
+            // Register before checking the flag so a cancel in between isn't missed
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+tokio::task_local! {
+    /// Token set by `scope` for the jobs started inside it
+    static SCOPE: CancelToken;
+}
+
+/// Run `fut`, cancelling any job it starts once `token` is cancelled. The GUI
+/// runs jobs forwarded from the CLI this way, so they stop if the CLI goes away.
+pub async fn scope<T>(token: CancelToken, fut: impl Future<Output = T>) -> T {
+    SCOPE.scope(token, fut).await
+}
+
+/// Tokens of the jobs running in this process, by job ID
+fn registry() -> &'static Mutex<HashMap<i64, CancelToken>> {
+    static REGISTRY: OnceLock<Mutex<HashMap<i64, CancelToken>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Cleanup requests spawned by providers while a call was being dropped
+fn cleanups() -> &'static Mutex<Vec<JoinHandle<()>>> {
+    static CLEANUPS: OnceLock<Mutex<Vec<JoinHandle<()>>>> = OnceLock::new();
+    CLEANUPS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Spawn provider cleanup from a Drop impl (e.g. cancelling a queued request).
+/// `run` waits for it, so the CLI doesn't exit before the request is sent.
+pub fn spawn_cleanup(task: impl Future<Output = ()> + Send + 'static) {
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        cleanups()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(handle.spawn(task));
+    }
+}
+
+/// Run a job's provider call until it finishes or the job is cancelled.
+/// Cancelling drops the call, which aborts its HTTP request; providers with a
+/// server-side queue (fal.ai, self-hosted) cancel the queued request as they drop.
+pub async fn run<T>(job_id: i64, call: impl Future<Output = anyhow::Result<T>>) -> anyhow::Result<T> {
+    let token = CancelToken::default();
+    registry()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(job_id, token.clone());
+
+    let scope = SCOPE.try_with(CancelToken::clone).ok();
+    let scope_cancelled = async {
+        match &scope {
+            Some(scope) => scope.cancelled().await,
+            None => std::future::pending().await,
+        }
+    };
+
+    let result = tokio::select! {
+        result = call => result,
+        _ = token.cancelled() => Err(Cancelled.into()),
+        _ = scope_cancelled => Err(Cancelled.into()),
+    };
+
+    registry().lock().unwrap_or_else(|e| e.into_inner()).remove(&job_id);
+
+    let pending: Vec<_> = std::mem::take(&mut *cleanups().lock().unwrap_or_else(|e| e.into_inner()));
+    if !pending.is_empty() {
+        let _ = tokio::time::timeout(CLEANUP_TIMEOUT, async {
+            for task in pending {
+                let _ = task.await;
+            }
+        })
+        .await;
+    }
+    result
+}
+
+/// Cancel a job running in this process. Returns false if it isn't running here.
+pub fn cancel(job_id: i64) -> bool {
+    let map = registry().lock().unwrap_or_else(|e| e.into_inner());
+    match map.get(&job_id) {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Cancel every job running in this process; returns how many there were
+pub fn cancel_all() -> usize {
+    let map = registry().lock().unwrap_or_else(|e| e.into_inner());
+    for token in map.values() {
+        token.cancel();
+    }
+    map.len()
+}
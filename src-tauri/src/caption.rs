@@ -0,0 +1,171 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::providers;
+
+const CAPTION_PROMPT: &str = "Describe this image in one sentence. \
+Then, on a new line starting with \"Keywords:\", list 5-10 comma-separated keywords \
+covering subject, style, medium and mood. Output nothing else.";
+
+/// Vision backend used for captioning
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptionBackend {
+    Ollama,
+    OpenAI,
+}
+
+impl std::str::FromStr for CaptionBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ollama" => Ok(CaptionBackend::Ollama),
+            "openai" => Ok(CaptionBackend::OpenAI),
+            _ => Err(format!("Unknown caption backend: {} (expected ollama or openai)", s)),
+        }
+    }
+}
+
+/// Captioning settings, stored in caption.json in the archive root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptionSettings {
+    pub backend: CaptionBackend,
+    /// Vision model name (backend-specific)
+    pub model: String,
+    /// Ollama server URL (ignored for OpenAI)
+    pub url: String,
+}
+
+impl Default for CaptionSettings {
+    fn default() -> Self {
+        CaptionSettings {
+            backend: CaptionBackend::Ollama,
+            model: "llava".to_string(),
+            url: "http://127.0.0.1:11434".to_string(),
+        }
+    }
+}
+
+impl CaptionSettings {
+    /// Default model for a backend, used when switching backends from the CLI
+    pub fn default_model(backend: CaptionBackend) -> &'static str {
+        match backend {
+            CaptionBackend::Ollama => "llava",
+            CaptionBackend::OpenAI => "gpt-4o-mini",
+        }
+    }
+}
+
+/// Load caption settings, falling back to defaults (local Ollama + llava)
+pub fn load_settings() -> CaptionSettings {
+    let path = crate::archive::archive_root().join("caption.json");
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+#[derive(Deserialize)]
+struct OllamaResponse {
+    response: String,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: Option<String>,
+}
+
+/// Caption an image (pass the thumbnail; vision models downscale anyway)
+pub async fn caption_image(path: &Path, settings: &CaptionSettings) -> Result<String> {
+    let caption = ask(path, CAPTION_PROMPT, settings).await?.trim().to_string();
+    if caption.is_empty() {
+        anyhow::bail!("Vision model returned an empty caption");
+    }
+    Ok(caption)
+}
+
+/// Ask the configured vision model something about an image
+pub async fn ask(path: &Path, prompt: &str, settings: &CaptionSettings) -> Result<String> {
+    let b64 = providers::image_to_base64(path).context("Failed to read image for the vision model")?;
+    match settings.backend {
+        CaptionBackend::Ollama => caption_ollama(&b64, prompt, settings).await,
+        CaptionBackend::OpenAI => caption_openai(&b64, providers::mime_type(path), prompt, settings).await,
+    }
+}
+
+async fn caption_ollama(b64: &str, prompt: &str, settings: &CaptionSettings) -> Result<String> {
+    let url = format!("{}/api/generate", settings.url.trim_end_matches('/'));
+    let response = providers::client()
+        .post(&url)
+        .json(&serde_json::json!({
+            "model": settings.model,
+            "prompt": prompt,
+            "images": [b64],
+            "stream": false,
+        }))
+        .timeout(Duration::from_secs(120)) // first call loads the model
+        .send()
+        .await
+        .context("Failed to connect to Ollama")?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        anyhow::bail!("Ollama error {}: {}", status, text);
+    }
+
+    let data: OllamaResponse = response.json().await.context("Failed to parse Ollama response")?;
+    Ok(data.response)
+}
+
+async fn caption_openai(b64: &str, mime: &str, prompt: &str, settings: &CaptionSettings) -> Result<String> {
+    let api_key = providers::openai::get_api_key()?;
+    let response = providers::client()
+        .post("https://api.openai.com/v1/chat/completions")
+        .header("Authorization", format!("Bearer {}", api_key))
+        .json(&serde_json::json!({
+            "model": settings.model,
+            "max_tokens": 200,
+            "messages": [{
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": prompt },
+                    {
+                        "type": "image_url",
+                        "image_url": { "url": format!("data:{};base64,{}", mime, b64), "detail": "low" }
+                    },
+                ],
+            }],
+        }))
+        .timeout(Duration::from_secs(60))
+        .send()
+        .await
+        .context("Failed to send request to OpenAI API")?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        anyhow::bail!("OpenAI API error {}: {}", status, text);
+    }
+
+    let data: ChatResponse = response.json().await.context("Failed to parse OpenAI response")?;
+    data.choices
+        .into_iter()
+        .next()
+        .and_then(|c| c.message.content)
+        .ok_or_else(|| anyhow::anyhow!("No answer in OpenAI response"))
+}
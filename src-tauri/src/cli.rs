@@ -1,12 +1,51 @@
 use anyhow::{Context, Result};
 use chrono::Local;
 use clap::Subcommand;
+use clap_complete::engine::{ArgValueCandidates, ArgValueCompleter, CompletionCandidate};
+use std::ffi::OsStr;
+use std::io::{IsTerminal, Write};
 use std::path::{Path, PathBuf};
 
 use crate::archive;
-use crate::db::Database;
-use crate::models::{self, Generation, JobSource, ListFilter, ModelInfo, PromptingGuide};
+use crate::bundle;
+use crate::cancel;
+use crate::caption;
+use crate::clipboard;
+use crate::compare;
+use crate::convert::{self, Conversion, ExportFormat};
+use crate::currency;
+use crate::custom_models;
+use crate::gallery;
+use crate::ipc;
+use crate::keys;
+use crate::db::{self, Database};
+use crate::deeplink;
+use crate::detail;
+use crate::editor;
+use crate::discord;
+use crate::enhance;
+use crate::linkfarm;
+use crate::lint;
+use crate::maintenance;
+use crate::notify;
+use crate::nsfw;
+use crate::outpaint;
+use crate::palette;
+use crate::profiles;
+use crate::providers;
+use crate::readonly;
+use crate::refprep;
+use crate::scheduler;
+use crate::slideshow;
+use crate::sync;
+use crate::telegram;
+use crate::template::{NameContext, NameTemplate};
+use crate::transform;
+use crate::tui;
+use crate::watcher::{self, WatchFolder};
+use crate::models::{self, AssetKind, BatchLine, DisplayCurrency, Generated, Generation, GenerationRequest, JobSource, ListFilter, MaintenanceStatus, MediaType, ModelInfo, NewPreset, NewSchedule, Orientation, ProgressStage, PromptingGuide, SortKey, VideoModelInfo, VideoRequest};
 use crate::workflow;
+use crate::xmp;
 
 #[derive(Subcommand, Clone)]
 pub enum Commands {
@@ -22,7 +61,22 @@ pub enum Commands {
         pixery gen -p \"anime girl\" -m animagine --negative \"lowres, bad anatomy\"\n  \
         pixery gen -p \"portrait photo\" --ratio portrait -m gpt-image-1\n  \
         pixery gen -f prompt.txt -m gemini-pro --ref reference.png -t character,fantasy\n  \
-        pixery gen -p \"1girl, cafe\" -m animagine --ref char.png --ip-scale 0.4")]
+        pixery gen -p \"1girl, cafe\" -m animagine --ref char.png --ip-scale 0.4\n  \
+        pixery gen --preset anime-portrait -p \"1girl, cafe\"\n  \
+        pixery gen -p \"1girl, cafe\" -m noobai --auto-negative\n  \
+        pixery gen -p \"same pose\" -m z-image --ref photo.jpg --ref-crop square --ref-resize 1024\n  \
+        pixery gen --prompt-clipboard -m gemini-pro --ref-clipboard\n  \
+        pixery gen -p \"a brass key, product shot\" -m gpt-image-1 --transparent\n\n\
+        --auto-negative uses the model's prompting-guide negative template when no \
+        negative prompt is given; make it the default with {\"auto_negative\": true} in \
+        generation.json in the archive root.\n\n\
+        --auto-title titles the result from the prompt's first descriptive clause; make it the \
+        default with {\"auto_title\": true}, and add \"title_source\": \"llm\" to have the \
+        enhance.json LLM write the title instead.\n\n\
+        --transparent makes a PNG with a transparent background for compositing: gpt-image-1 \
+        draws one itself, and other models' images are cut out by the self-hosted server's \
+        /rembg (without a server they're refused; if removal fails the opaque image is kept). \
+        Transparent results are tagged transparent (`pixery list --tag transparent`).")]
     Generate {
         /// Prompt text
         #[arg(short, long)]
@@ -32,22 +86,38 @@ pub enum Commands {
         #[arg(short = 'f', long)]
         file: Option<PathBuf>,
 
-        /// Model to use
-        #[arg(short, long, default_value = "gemini-flash")]
-        model: String,
+        /// Read prompt from the clipboard
+        #[arg(long, conflicts_with_all = ["prompt", "file"])]
+        prompt_clipboard: bool,
+
+        /// Model to use (default: the preset's model, else gemini-flash)
+        #[arg(short, long, add = ArgValueCandidates::new(complete_models))]
+        model: Option<String>,
+
+        /// Saved preset (see 'pixery preset'); explicit flags override its settings
+        #[arg(long)]
+        preset: Option<String>,
 
         /// Tags (comma-separated)
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
         tags: Option<String>,
 
-        /// Reference image(s)
+        /// Reference image(s): a path, ref:ID (stored reference) or gen:ID (archived generation)
         #[arg(short, long = "ref")]
         reference: Vec<PathBuf>,
 
+        /// Use the image on the clipboard as a reference (after any --ref)
+        #[arg(long)]
+        ref_clipboard: bool,
+
         /// Copy result to path
         #[arg(long)]
         copy_to: Option<PathBuf>,
 
+        /// Name the copy from a template, e.g. "{date}_{model}_{slug}_{id}"; --copy-to is then a directory
+        #[arg(long, requires = "copy_to")]
+        name_template: Option<NameTemplate>,
+
         /// Negative prompt
         #[arg(long)]
         negative: Option<String>,
@@ -59,6 +129,90 @@ pub enum Commands {
         /// IP-Adapter scale for self-hosted models (0.0-1.0, default 0.7)
         #[arg(long)]
         ip_scale: Option<f64>,
+
+        /// CFG scale for self-hosted models (default: per-model)
+        #[arg(long)]
+        cfg: Option<f64>,
+
+        /// Use the model's prompting-guide negative template if --negative isn't given
+        #[arg(long)]
+        auto_negative: bool,
+
+        /// Title the result from the prompt (heuristic or LLM, see generation.json)
+        #[arg(long)]
+        auto_title: bool,
+
+        /// Images from one API call (fal.ai up to 4, gpt-image-1 up to 10; others return 1)
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        count_per_call: Option<u32>,
+
+        /// Preprocess references: shrink so the longest side is at most PX pixels
+        #[arg(long, value_name = "PX")]
+        ref_resize: Option<u32>,
+
+        /// Preprocess references: center-crop to square, portrait (2:3) or landscape (3:2)
+        #[arg(long)]
+        ref_crop: Option<refprep::RefCrop>,
+
+        /// Preprocess references: remove the background (self-hosted server /rembg)
+        #[arg(long)]
+        ref_rembg: bool,
+
+        /// Transparent background PNG, tagged `transparent` (gpt-image-1 natively; other models via self-hosted /rembg)
+        #[arg(long)]
+        transparent: bool,
+    },
+
+    /// Generate a video clip
+    #[command(long_about = "Generate a short video clip from a text prompt, optionally starting from an image.\n\n\
+        Clips are archived as MP4 next to images and show up in list, search and the gallery. \
+        Thumbnails are made from the first frame, and length and frame rate are read with \
+        ffprobe, so install ffmpeg for both. Costs are per second of video.\n\n\
+        Models (see `pixery models`):\n  \
+        kling      fal.ai Kling 2.1 Master, 5 or 10s, 16:9 / 9:16 / 1:1, start frame optional\n  \
+        veo3       Google Veo 3, 8s with audio, 16:9 / 9:16, start frame optional\n  \
+        veo3-fast  Google Veo 3 Fast, 8s with audio, 16:9 / 9:16, start frame optional\n\n\
+        A start frame given as gen:ID also becomes the clip's parent generation.\n\n\
+        Examples:\n  \
+        pixery video -p \"waves rolling onto a beach at dawn\"\n  \
+        pixery video -p \"she turns and smiles\" --image gen:140 --duration 10\n  \
+        pixery video -f scene.txt -m veo3 --ratio 9:16 -t trailer")]
+    Video {
+        /// Prompt text
+        #[arg(short, long)]
+        prompt: Option<String>,
+
+        /// Read prompt from file
+        #[arg(short = 'f', long)]
+        file: Option<PathBuf>,
+
+        /// Video model: kling, veo3, veo3-fast
+        #[arg(short, long, default_value = "kling")]
+        model: String,
+
+        /// Start frame: a path, ref:ID (stored reference) or gen:ID (archived generation)
+        #[arg(short, long)]
+        image: Option<String>,
+
+        /// Clip length in seconds (default: the model's shortest)
+        #[arg(short, long)]
+        duration: Option<u32>,
+
+        /// Aspect ratio, e.g. 16:9 or 9:16 (default 16:9; Kling follows the start frame)
+        #[arg(long)]
+        ratio: Option<String>,
+
+        /// Negative prompt
+        #[arg(long)]
+        negative: Option<String>,
+
+        /// Seed (Veo)
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// Tags (comma-separated)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
     },
 
     /// List recent generations
@@ -68,24 +222,118 @@ pub enum Commands {
         pixery list                       # Last 20 generations\n  \
         pixery list -n 50                 # Last 50 generations\n  \
         pixery list --tag character       # Filter by tag\n  \
+        pixery list --tag 'character:*'   # Any tag in a namespace\n  \
         pixery list --model gemini-flash  # Filter by model\n  \
-        pixery list --starred             # Only starred images")]
+        pixery list --starred             # Only starred images\n  \
+        pixery list --since 2024-06-01 --until 2024-06-30\n  \
+        pixery list --orientation portrait --min-height 1536\n  \
+        pixery list --has-reference       # Made with reference images\n  \
+        pixery list --color \"#ff6600\" --tolerance 20\n  \
+        pixery list --sort cost --desc    # Most expensive first\n\n\
+        --color matches generations with that color in their palette (see `pixery palette`); \
+        give it more than once to require several. --tolerance is how near counts, as a CIE76 \
+        color difference: about 2 is barely visible, 20 (the default) still the same color.\n\n\
+        Sort keys: timestamp, cost, generation-time, file-size, starred (alias rating), \
+        random. Ascending unless --desc; without --sort, newest first.\n\n\
+        NSFW-flagged generations are left out unless --include-nsfw is given or \
+        `pixery nsfw settings --show on` is set.")]
     List {
         /// Number of results
         #[arg(short = 'n', long, default_value = "20")]
         limit: i64,
 
-        /// Filter by tag
-        #[arg(short, long)]
+        /// Filter by tag ("character:*" matches any tag in that namespace)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
         tag: Option<String>,
 
         /// Filter by model
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_models))]
         model: Option<String>,
 
         /// Show only starred
         #[arg(short, long)]
         starred: bool,
+
+        /// From this date: YYYY-MM-DD, today, 7d, 2w
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Up to and including this date: YYYY-MM-DD, today, 7d, 2w
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Minimum width in pixels
+        #[arg(long)]
+        min_width: Option<i32>,
+
+        /// Minimum height in pixels
+        #[arg(long)]
+        min_height: Option<i32>,
+
+        /// portrait, landscape or square
+        #[arg(long)]
+        orientation: Option<Orientation>,
+
+        /// Only generations made with reference images
+        #[arg(long)]
+        has_reference: bool,
+
+        /// image or video
+        #[arg(long)]
+        media: Option<MediaType>,
+
+        /// Sort by: timestamp, cost, generation-time, file-size, starred, random
+        #[arg(long)]
+        sort: Option<SortKey>,
+
+        /// Descending order for --sort
+        #[arg(long, requires = "sort")]
+        desc: bool,
+
+        /// Include NSFW-flagged generations
+        #[arg(long)]
+        include_nsfw: bool,
+
+        /// Has a palette color near this one, e.g. "#ff6600" (repeatable; all must match)
+        #[arg(long)]
+        color: Vec<palette::Color>,
+
+        /// How near --color must be, as a CIE76 difference [default: 20]
+        #[arg(long, requires = "color")]
+        tolerance: Option<f64>,
+    },
+
+    /// Show a random sample of generations
+    #[command(long_about = "Pick random generations, for rediscovering old work or building mood boards.\n\n\
+        Output columns match 'pixery list'. Pipe --paths into an image viewer to browse the sample.\n\n\
+        Examples:\n  \
+        pixery random                          # 5 random generations\n  \
+        pixery random -n 12 --tag character --starred\n  \
+        pixery random -n 9 --paths | xargs imv")]
+    Random {
+        /// Number of results
+        #[arg(short = 'n', long, default_value = "5")]
+        count: i64,
+
+        /// Filter by tag
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tag: Option<String>,
+
+        /// Filter by model
+        #[arg(short, long, add = ArgValueCandidates::new(complete_models))]
+        model: Option<String>,
+
+        /// Only starred
+        #[arg(short, long)]
+        starred: bool,
+
+        /// Print image paths only
+        #[arg(long)]
+        paths: bool,
+
+        /// Include NSFW-flagged generations
+        #[arg(long)]
+        include_nsfw: bool,
     },
 
     /// Search generations by prompt
@@ -96,12 +344,16 @@ pub enum Commands {
         /// Number of results
         #[arg(short = 'n', long, default_value = "20")]
         limit: i64,
+
+        /// Include NSFW-flagged generations
+        #[arg(long)]
+        include_nsfw: bool,
     },
 
     /// Show generation metadata (prompt, model, tags, cost, references)
     #[command(long_about = "Show generation metadata as text output.\n\n\
         Displays: ID, slug, model, date, path, generation time, cost, seed, \
-        dimensions, starred status, tags, references, and full prompt.\n\n\
+        dimensions, starred status, tags, references, assets, and full prompt.\n\n\
         Use 'view' to output the image path for viewing the actual image.")]
     Show {
         /// Generation ID
@@ -134,12 +386,60 @@ pub enum Commands {
         height: Option<u32>,
     },
 
+    /// Compose generations into a labeled side-by-side contact sheet
+    #[command(long_about = "Compose generations into a labeled grid image.\n\n\
+        Each cell shows the image scaled to fit, with ID, model, seed and cost underneath. \
+        Useful for evaluating matrix/batch runs side by side. Cells appear in the order given.\n\n\
+        Examples:\n  \
+        pixery compare 140 141 142 -o compare.png\n  \
+        pixery compare 140 141 142 143 144 145 --columns 3 --size 384")]
+    Compare {
+        /// Generation IDs, in grid order
+        #[arg(required = true)]
+        ids: Vec<i64>,
+
+        /// Output file (format from extension)
+        #[arg(short, long, default_value = "compare.png")]
+        output: PathBuf,
+
+        /// Grid columns (default: one row up to 4 images, then a square-ish grid)
+        #[arg(short, long)]
+        columns: Option<u32>,
+
+        /// Cell size in pixels (images are scaled to fit)
+        #[arg(long, default_value = "512")]
+        size: u32,
+    },
+
+    /// Re-run a generation with the same parameters and seed, and compare
+    #[command(long_about = "Re-issue a generation with its recorded parameters and seed, then report \
+        how close the new image is to the original.\n\n\
+        Similarity is a perceptual hash (dHash) comparison, 100% for visually identical images; \
+        byte-identical files are reported as such. Useful for checking whether a provider is \
+        deterministic and that the stored parameters are complete. The reproduction is kept as \
+        a child of the original.\n\n\
+        Only providers that take a seed (fal.ai, self-hosted, ComfyUI) can reproduce an image; \
+        Gemini and OpenAI don't report one. Imports and generations made before parameters \
+        were recorded can't be reproduced.\n\n\
+        Examples:\n  \
+        pixery reproduce 140\n  \
+        pixery reproduce 140 -t determinism-check")]
+    Reproduce {
+        /// Generation ID
+        id: i64,
+
+        /// Tags for the reproduction (comma-separated)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
+    },
+
     /// Add tags to a generation
     Tag {
         /// Generation ID
         id: i64,
 
         /// Tags (comma-separated)
+        #[arg(add = ArgValueCompleter::new(complete_tags))]
         tags: String,
     },
 
@@ -149,6 +449,7 @@ pub enum Commands {
         id: i64,
 
         /// Tag to remove
+        #[arg(add = ArgValueCompleter::new(complete_tags))]
         tag: String,
     },
 
@@ -164,6 +465,45 @@ pub enum Commands {
         id: i64,
     },
 
+    /// Lock a generation against edits, trash and delete
+    #[command(long_about = "Lock a finalized generation.\n\n\
+        Locked generations reject prompt/title/model edits, tag removal, trash and delete \
+        until unlocked. Starring, adding tags and collection membership still work.\n\n\
+        Examples:\n  \
+        pixery lock 140\n  \
+        pixery unlock 140")]
+    Lock {
+        /// Generation ID
+        id: i64,
+    },
+
+    /// Unlock a previously locked generation
+    Unlock {
+        /// Generation ID
+        id: i64,
+    },
+
+    /// Flag generations as NSFW and choose whether they're shown
+    #[command(long_about = "Flag NSFW generations so list, search, random and the gallery leave \
+        them out.\n\n\
+        New generations are flagged when their prompt has a rating tag (nsfw, explicit, \
+        questionable, rating:explicit, rating_explicit, ...; the negative prompt doesn't \
+        count). With the classifier on, each new image is also shown to the caption vision \
+        model (caption.json) and flagged if it answers yes. `scan` does the same for \
+        generations already in the archive. Settings are stored in nsfw.json in the archive \
+        root; with --show on, flagged generations are listed and the gallery blurs them.\n\n\
+        Examples:\n  \
+        pixery nsfw mark 140 141\n  \
+        pixery nsfw unmark 141\n  \
+        pixery nsfw scan                     # Flag by prompt across the archive\n  \
+        pixery nsfw scan --classify -n 200   # Also classify the 200 newest unflagged\n  \
+        pixery nsfw settings --classify on\n  \
+        pixery list --include-nsfw")]
+    Nsfw {
+        #[command(subcommand)]
+        action: NsfwAction,
+    },
+
     /// Update a generation's metadata
     Update {
         /// Generation ID
@@ -182,21 +522,28 @@ pub enum Commands {
         prompt_file: Option<PathBuf>,
 
         /// Update model
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCandidates::new(complete_models))]
         model: Option<String>,
 
-        /// Add reference image(s)
+        /// Add reference image(s): a path, ref:ID or gen:ID
         #[arg(short, long = "ref")]
         reference: Vec<PathBuf>,
 
         /// Add tags (comma-separated)
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
         tags: Option<String>,
     },
 
     /// List available models or show prompting guide
     #[command(long_about = "List available models or show prompting guide for a specific model.\n\n\
         Without arguments, lists all models with provider, cost, and reference support.\n\n\
+        Extra models and aliases can be defined in models.json in the archive root (see \
+        CLAUDE.md); they are merged into this list and accepted anywhere a model is. A \
+        \"quotas\" map there caps jobs per model per day ({\"gemini-pro\": 20}); the remaining quota is \
+        listed here, and generations past it are refused until midnight.\n\n\
+        With --refresh, fetches fal.ai's text-to-image models and prices (needs a fal key) and \
+        caches them in fal-catalog.json; new endpoints are then listed here and in the GUI \
+        picker, and usable by their endpoint ID.\n\n\
         With MODEL --guide, shows the prompting guide for that model including:\n\
         - Style (prose/tags/hybrid)\n\
         - Required prefix (if any)\n\
@@ -206,684 +553,4799 @@ pub enum Commands {
         - Concrete example\n\n\
         Examples:\n  \
         pixery models                    # List all models\n  \
+        pixery models --refresh          # Sync the fal.ai catalog, then list\n  \
         pixery models gemini-pro --guide # Gemini prompting guide\n  \
         pixery models animagine --guide  # Booru tag format guide\n  \
         pixery models pony --guide       # Pony score prefix guide")]
     Models {
         /// Model to get info about (optional)
+        #[arg(add = ArgValueCandidates::new(complete_models))]
         model: Option<String>,
 
         /// Show prompting guide for the model
         #[arg(short, long)]
         guide: bool,
-    },
 
-    /// List all tags with counts
-    Tags,
-
-    /// Show cost summary
-    Cost {
-        /// Time period (e.g., "7d", "30d", "all")
-        #[arg(long, default_value = "all")]
-        since: String,
+        /// Fetch fal.ai's model catalog first
+        #[arg(long)]
+        refresh: bool,
     },
 
-    /// Show recent failed generations
-    Failures {
-        /// Number of failures to show
-        #[arg(short = 'n', long, default_value = "10")]
-        limit: i64,
+    /// Print a shell completion script
+    #[command(long_about = "Print the completion script for a shell.\n\n\
+        Completion is dynamic: the script calls back into pixery, so --model offers the \
+        current model list and tag arguments offer tags from your archive.\n\n\
+        Setup:\n  \
+        bash:  echo 'source <(pixery completions bash)' >> ~/.bashrc\n  \
+        zsh:   echo 'source <(pixery completions zsh)' >> ~/.zshrc\n  \
+        fish:  pixery completions fish > ~/.config/fish/completions/pixery.fish")]
+    Completions {
+        /// Shell: bash, zsh, fish, elvish or powershell
+        shell: String,
     },
 
-    /// Import an existing image into the archive
-    Import {
-        /// Path to existing image file
-        #[arg(short, long)]
-        file: PathBuf,
-
-        /// Prompt text
+    /// Browse generations in a terminal UI
+    #[command(long_about = "Browse the archive in the terminal.\n\n\
+        Lists the most recent 500 generations with a preview of the selected image \
+        (kitty graphics protocol in kitty/WezTerm/Ghostty, colored blocks elsewhere).\n\n\
+        Keys:\n  \
+        j/k, arrows    move (PgUp/PgDn, g/G jump)\n  \
+        /              search prompts (empty clears)\n  \
+        f              toggle starred-only\n  \
+        s              star/unstar\n  \
+        t / T          add tags / remove a tag\n  \
+        d              move to trash (asks first)\n  \
+        r              reload\n  \
+        q, Esc         quit")]
+    Tui,
+
+    /// Rewrite a rough prompt in the target model's style with an LLM
+    #[command(long_about = "Rewrite a prompt for a target model using a text LLM.\n\n\
+        The LLM gets the target model's prompting guide (see 'pixery models MODEL --guide') \
+        and returns the prompt in that model's style: booru tags for Animagine, the score \
+        chain for Pony, prose for Gemini/FLUX. Prints the result; with --generate, generates \
+        from it right away. Defaults come from enhance.json in the archive root \
+        ({\"backend\": \"gemini\"|\"openai\", \"model\"}); the default is gemini-2.5-flash.\n\n\
+        Examples:\n  \
+        pixery enhance -p \"girl on rooftop\" -m animagine\n  \
+        pixery enhance -p \"girl on rooftop\" -m pony --generate -t rooftop\n  \
+        pixery enhance -p \"old lighthouse\" -m flux2-pro --backend openai")]
+    Enhance {
+        /// Rough prompt text
         #[arg(short, long)]
         prompt: Option<String>,
 
         /// Read prompt from file
-        #[arg(long = "prompt-file")]
-        prompt_file: Option<PathBuf>,
+        #[arg(short = 'f', long)]
+        file: Option<PathBuf>,
 
-        /// Model that generated this image
-        #[arg(short, long, default_value = "unknown")]
+        /// Target image model
+        #[arg(short, long, default_value = "gemini-flash", add = ArgValueCandidates::new(complete_models))]
         model: String,
 
-        /// Tags (comma-separated)
-        #[arg(short, long)]
-        tags: Option<String>,
-
-        /// Reference image(s) used for this generation
-        #[arg(short, long = "ref")]
-        reference: Vec<PathBuf>,
-
-        /// Override date (YYYY-MM-DD), otherwise extracted from filename or uses today
+        /// LLM backend: gemini or openai
         #[arg(long)]
-        date: Option<String>,
+        backend: Option<String>,
 
-        /// Override timestamp (HH:MM:SS), otherwise extracted from filename or uses now
+        /// LLM model (default: gemini-2.5-flash for gemini, gpt-4o-mini for openai)
         #[arg(long)]
-        time: Option<String>,
-    },
+        llm: Option<String>,
 
-    /// Regenerate all thumbnails at current size (400px)
-    RegenThumbs {
-        /// Only process thumbnails smaller than this size (default: regenerate all)
+        /// Generate from the enhanced prompt
         #[arg(long)]
-        if_smaller: Option<u32>,
+        generate: bool,
 
-        /// Dry run - show what would be regenerated without doing it
-        #[arg(long)]
-        dry_run: bool,
+        /// Tags for --generate (comma-separated)
+        #[arg(short, long, requires = "generate", add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
+
+        /// Aspect ratio for --generate (e.g., square, portrait, 16:9)
+        #[arg(long, requires = "generate")]
+        ratio: Option<String>,
     },
 
-    /// Generate multiple images from the same prompt
-    #[command(long_about = "Generate multiple images from the same prompt sequentially.\n\n\
-        Useful for exploring variations — same prompt/model produces different results each run. \
-        Reports per-image success/failure and a summary at the end.\n\n\
+    /// Check a prompt against the model's prompting guide
+    #[command(long_about = "Check a prompt against the model's prompting guide before spending money.\n\n\
+        Flags prose sent to a tags-only model (and tag lists sent to prose models), missing \
+        required prefix tags (Pony score chain, NoobAI quality tags), and words on the \
+        model's avoid list. Exits non-zero when issues are found. 'pixery generate' and \
+        'pixery batch' print the same warnings before generating.\n\n\
         Examples:\n  \
-        pixery batch -p \"fantasy landscape\" -n 6\n  \
-        pixery batch -p \"character portrait\" -m animagine -n 4 --ratio portrait\n  \
-        pixery batch -p \"concept art\" -m gemini-pro --ref mood.png -t exploration\n  \
-        pixery batch -p \"1girl, cafe\" -m animagine -n 4 --ref char.png --ip-scale 0.4")]
-    Batch {
+        pixery lint -m animagine -p \"a girl standing on a rooftop at night\"\n  \
+        pixery lint -m pony -f prompt.txt")]
+    Lint {
         /// Prompt text
         #[arg(short, long)]
-        prompt: String,
+        prompt: Option<String>,
 
-        /// Model to use
-        #[arg(short, long, default_value = "gemini-flash")]
+        /// Read prompt from file
+        #[arg(short = 'f', long)]
+        file: Option<PathBuf>,
+
+        /// Model to check against
+        #[arg(short, long, add = ArgValueCandidates::new(complete_models))]
         model: String,
+    },
 
-        /// Number of images to generate
-        #[arg(short = 'n', long, default_value = "4")]
-        count: u32,
+    /// List all tags with counts, or rename and merge tags
+    #[command(long_about = "List all tags with counts, or rename and merge them.\n\n\
+        Tags like character:kira or style:cel-shading belong to a namespace (the word \
+        before the colon). The listing groups them by namespace, and tag filters take \
+        'character:*' to match any tag in one.\n\n\
+        Examples:\n  \
+        pixery tags\n  \
+        pixery tags rename wip work-in-progress\n  \
+        pixery tags merge girl 1girl into female")]
+    Tags {
+        #[command(subcommand)]
+        action: Option<TagsAction>,
+    },
 
-        /// Tags (comma-separated)
+    /// Add and remove tags on every generation matching a filter
+    #[command(long_about = "Add and remove tags on every generation matching a filter, in one transaction.\n\n\
+        The filter takes the same terms as smart collections, joined by commas or AND: \
+        model=, tag=, tag!=, search=, since=, until=, min-width=, min-height=, \
+        orientation=, starred, nsfw, sfw, has-reference. Locked generations gain tags but keep the \
+        ones being removed.\n\n\
+        Examples:\n  \
+        pixery tag-bulk --filter \"model=noobai,tag=wip\" --add reviewed --remove wip\n  \
+        pixery tag-bulk --filter \"since=7d AND starred\" --add keeper --dry-run")]
+    TagBulk {
+        /// Filter expression, e.g. "model=noobai,tag=wip"
         #[arg(short, long)]
-        tags: Option<String>,
-
-        /// Reference image(s)
-        #[arg(short, long = "ref")]
-        reference: Vec<PathBuf>,
+        filter: String,
 
-        /// Negative prompt
-        #[arg(long)]
-        negative: Option<String>,
+        /// Tags to add (comma-separated)
+        #[arg(long, add = ArgValueCompleter::new(complete_tags))]
+        add: Option<String>,
 
-        /// Aspect ratio (e.g., square, portrait, 16:9, 2:3)
-        #[arg(long)]
-        ratio: Option<String>,
+        /// Tags to remove (comma-separated)
+        #[arg(long, add = ArgValueCompleter::new(complete_tags))]
+        remove: Option<String>,
 
-        /// IP-Adapter scale for self-hosted models (0.0-1.0, default 0.7)
+        /// Only report how many generations match
         #[arg(long)]
-        ip_scale: Option<f64>,
+        dry_run: bool,
     },
 
-    /// Export generations to a directory
-    #[command(long_about = "Copy generation images to an output directory.\n\n\
-        Select generations by ID, by tag, or both. With --with-metadata, writes a \
-        JSON sidecar file alongside each image containing prompt, model, tags, cost, etc.\n\n\
+    /// Show cost summary
+    #[command(long_about = "Show spend by model and day.\n\n\
+        Amounts are shown in the display currency (`pixery currency`), converted from the USD \
+        costs are recorded in; --usd shows them as recorded.\n\n\
+        With --export, also writes a report for spreadsheets. A .json path gets one file with \
+        per-generation rows and per-model/per-day summaries; any other path gets CSV: \
+        per-generation rows (id, date, model, provider, cost, prompt snippet) plus \
+        NAME.by-model.csv and NAME.by-day.csv alongside. Reports keep cost_usd and add a \
+        column in the display currency (e.g. cost_eur).\n\n\
         Examples:\n  \
-        pixery export --ids 100 101 102 -o ./export/\n  \
-        pixery export --tag character -o ./characters/ --with-metadata\n  \
-        pixery export --ids 50 --tag landscape -o ./portfolio/")]
-    Export {
-        /// Generation IDs to export
-        #[arg(short, long)]
-        ids: Vec<i64>,
-
-        /// Export all generations with this tag
-        #[arg(short, long)]
-        tag: Option<String>,
+        pixery cost --since 30d\n  \
+        pixery cost --since 30d --export costs.csv\n  \
+        pixery cost --since 2024-01-01 --export costs.json")]
+    Cost {
+        /// Time period (e.g., "7d", "30d", "all")
+        #[arg(long, default_value = "all")]
+        since: String,
 
-        /// Output directory
-        #[arg(short, long)]
-        output: PathBuf,
+        /// Write a CSV (or .json) cost report to this path
+        #[arg(long)]
+        export: Option<PathBuf>,
 
-        /// Write metadata.json sidecar files
+        /// Show amounts in USD, ignoring the display currency
         #[arg(long)]
-        with_metadata: bool,
+        usd: bool,
     },
 
-    /// Manage collections (project folders)
-    #[command(long_about = "Manage collections — lightweight project folders for organizing generations.\n\n\
-        Collections group generations by project or theme, independent of tags. \
-        A generation can belong to multiple collections.\n\n\
-        Subcommands:\n  \
-        create  Create a new collection\n  \
-        list    List all collections\n  \
-        add     Add generation(s) to a collection\n  \
-        remove  Remove generation(s) from a collection\n  \
-        delete  Delete a collection (does not delete generations)\n\n\
+    /// Show or set the currency costs are displayed in
+    #[command(long_about = "Show or set the currency costs are displayed in.\n\n\
+        Costs are always recorded in USD; `pixery cost`, its reports and the GUI convert them \
+        for display. The exchange rate is either set by hand with --rate or fetched from \
+        Frankfurter (ECB reference rates) and cached for a day in currency.json.\n\n\
         Examples:\n  \
-        pixery collection create \"rpg-portraits\" -d \"Character art for the RPG project\"\n  \
-        pixery collection add 100 101 102 -c rpg-portraits\n  \
-        pixery collection list")]
-    Collection {
+        pixery currency\n  \
+        pixery currency set EUR\n  \
+        pixery currency set EUR --rate 0.92\n  \
+        pixery currency refresh\n  \
+        pixery currency set USD")]
+    Currency {
         #[command(subcommand)]
-        action: CollectionAction,
+        action: Option<CurrencyAction>,
     },
 
-    /// Show recent prompt history
-    #[command(long_about = "Show recent prompts with generation IDs.\n\n\
-        Output columns: ID, DATE, PROMPT (truncated). Useful for re-using or iterating \
-        on previous prompts — copy the ID to 'pixery show' or 'pixery view' for details.\n\n\
+    /// Show or set whether the GUI opens read-only (guest mode)
+    #[command(long_about = "Show or set whether the GUI opens read-only.\n\n\
+        A read-only window can browse, search and open details, but every command that \
+        would change the archive (generating, tagging, deleting, settings) is refused, and \
+        background work like ingest, maintenance and schedules doesn't start. Handy for \
+        showing the archive to someone else. The setting is kept in readonly.json and only \
+        affects the GUI; `pixery --read-only` opens it read-only once.\n\n\
         Examples:\n  \
-        pixery history              # Last 20 prompts\n  \
-        pixery history -n 50        # Last 50 prompts")]
-    History {
-        /// Number of entries to show
-        #[arg(short = 'n', long, default_value = "20")]
-        limit: i64,
+        pixery read-only\n  \
+        pixery read-only on\n  \
+        pixery read-only off")]
+    ReadOnly {
+        /// on or off (default: show the current setting)
+        #[arg(value_parser = parse_on_off)]
+        state: Option<bool>,
     },
-}
 
-#[derive(Subcommand, Clone)]
-pub enum CollectionAction {
-    /// Create a new collection
-    Create {
-        /// Collection name
-        name: String,
+    /// List, add or switch between separate archives (e.g. personal and work)
+    #[command(long_about = "List, add or switch between separate archives.\n\n\
+        Each profile is its own archive root with its own database, images, references and \
+        settings (models.json, pricing, quotas, watch folders, ...). The default profile is \
+        ~/media/image-gen; others are listed in profiles.json there. Commands and the GUI use \
+        the active profile unless --archive NAME picks another for that run.\n\n\
+        Examples:\n  \
+        pixery profiles\n  \
+        pixery profiles add work ~/media/work-gen --description \"Client work\"\n  \
+        pixery profiles switch work\n  \
+        pixery --archive default list\n  \
+        pixery profiles remove work")]
+    Profiles {
+        #[command(subcommand)]
+        action: Option<ProfilesAction>,
+    },
 
-        /// Description
+    /// Show or change model prices (effective from a date)
+    #[command(long_about = "Show or change model prices without a new release.\n\n\
+        A price applies from its date until the model's next one; models without one use \
+        their built-in (or models.json) price. Prices are USD per image, or per second for \
+        video models. They're used wherever pixery estimates: generations whose provider \
+        reports no cost, video clips, retry budgets, --dry-run and the model list. Costs \
+        already recorded are kept unless --reprice rewrites them.\n\n\
+        Examples:\n  \
+        pixery pricing\n  \
+        pixery pricing update gemini-pro 0.134\n  \
+        pixery pricing update flux-pro 0.05 --from 2025-03-01 --reprice\n  \
+        pixery pricing remove flux-pro 2025-03-01")]
+    Pricing {
+        #[command(subcommand)]
+        action: Option<PricingAction>,
+    },
+
+    /// Show usage statistics
+    #[command(long_about = "Show usage statistics: generations per week, job success rate, \
+        per-model counts, cost and average generation time, and the most-used tags.\n\n\
+        `stats latency` shows median and 95th percentile generation times per model and \
+        per hour of the day, to pick the faster model or the quiet hours for a batch.\n\n\
+        Examples:\n  \
+        pixery stats                # All time\n  \
+        pixery stats --since 30d\n  \
+        pixery stats latency --since 7d\n  \
+        pixery stats latency -m noobai")]
+    Stats {
+        /// Time period (e.g., "7d", "30d", "2024-01-01", "all")
+        #[arg(long, default_value = "all", global = true)]
+        since: String,
+
+        #[command(subcommand)]
+        action: Option<StatsAction>,
+    },
+
+    /// Show recent failed generations
+    Failures {
+        /// Number of failures to show
+        #[arg(short = 'n', long, default_value = "10")]
+        limit: i64,
+    },
+
+    /// List and retry generation jobs
+    #[command(long_about = "Browse the job history and re-run failed jobs.\n\n\
+        Every generation runs as a job; `list` shows how long each took, where it was \
+        started (cli, gui, schedule) and the generation it produced or the error it hit. \
+        `retry` replays a failed or cancelled job with its original model, prompt, size, \
+        references and tags as a new job.\n\n\
+        Examples:\n  \
+        pixery jobs list\n  \
+        pixery jobs list --status failed --since 7d\n  \
+        pixery jobs retry 412")]
+    Jobs {
+        #[command(subcommand)]
+        action: JobsAction,
+    },
+
+    /// Show or change desktop notification and webhook settings
+    #[command(long_about = "Desktop notifications when generations finish or fail, per source: \
+        cli (generate, batch, fix, retry-failed), gui (only while the window is in the background) \
+        and schedule. Batches send one summary notification. Cancelled jobs never notify.\n\n\
+        A webhook URL is POSTed on every job completion or failure from any source, with the \
+        prompt, model, cost and image paths. Discord, Slack and ntfy URLs get a chat message; \
+        anything else gets the event as JSON. --webhook-format overrides the guess.\n\n\
+        Examples:\n  \
+        pixery notifications                 # Show settings\n  \
+        pixery notifications --cli off\n  \
+        pixery notifications --gui on --schedule on\n  \
+        pixery notifications --webhook https://ntfy.sh/my-pixery\n  \
+        pixery notifications --webhook off")]
+    Notifications {
+        /// Notify for CLI generations (on/off)
+        #[arg(long, value_parser = parse_on_off)]
+        cli: Option<bool>,
+
+        /// Notify for GUI generations while the window is in the background (on/off)
+        #[arg(long, value_parser = parse_on_off)]
+        gui: Option<bool>,
+
+        /// Notify for scheduled generations (on/off)
+        #[arg(long, value_parser = parse_on_off)]
+        schedule: Option<bool>,
+
+        /// Webhook URL to POST on completion/failure ("off" to remove)
+        #[arg(long)]
+        webhook: Option<String>,
+
+        /// Webhook payload: json, discord, slack, ntfy, or auto (guess from the URL)
+        #[arg(long)]
+        webhook_format: Option<String>,
+    },
+
+    /// Check that providers are configured and reachable
+    #[command(long_about = "Diagnose configuration problems before they fail a generation.\n\n\
+        'doctor providers' makes one cheap authenticated call per provider, all at once: the \
+        model list for Gemini, OpenAI and fal.ai (which verifies the API key), /health on the \
+        self-hosted server and /system_stats on ComfyUI. Each is reported as ok (with latency \
+        and any rate-limit or quota headers the API returned), missing (no API key), error, \
+        or skipped (self-hosted server not set, no ComfyUI templates). Exits non-zero if any \
+        provider is missing a key or failed its check. No images are generated and nothing \
+        is billed.\n\n\
+        Examples:\n  \
+        pixery doctor providers")]
+    Doctor {
+        #[command(subcommand)]
+        action: DoctorAction,
+    },
+
+    /// Store provider API keys in the OS keychain
+    #[command(long_about = "Store provider API keys in the OS keychain (macOS Keychain, Windows \
+        Credential Manager, or the Secret Service on Linux) instead of a plain-text ~/.env.\n\n\
+        Providers look for their key in the environment first (~/.env is loaded at startup): \
+        GEMINI_API_SECRET_KEY or GEMINI_API_KEY, OPENAI_API_SECRET_KEY or OPENAI_API_KEY, and \
+        FAL_KEY. Without one, the keychain entry is used. 'keys status' shows where each \
+        provider's key comes from.\n\n\
+        'keys set' reads the key from stdin when it isn't given, which keeps it out of shell \
+        history.\n\n\
+        Examples:\n  \
+        pixery keys set gemini            # Prompts for the key\n  \
+        pbpaste | pixery keys set fal\n  \
+        pixery keys status\n  \
+        pixery keys remove openai")]
+    Keys {
+        #[command(subcommand)]
+        action: KeysAction,
+    },
+
+    /// Run a Discord bot that generates images for /imagine
+    #[command(name = "discord-bot", long_about = "Connect a Discord bot and answer /imagine prompt:<text> \
+        slash commands. Requests are generated one at a time with the existing workflow, the \
+        images are uploaded to the channel, and each generation is archived with the discord \
+        tag and the requesting user (shown by pixery show).\n\n\
+        Needs DISCORD_BOT_TOKEN. Invite the bot with the applications.commands and bot scopes. \
+        Only --model is used unless --models offers a choice; anyone who can see the command \
+        can spend on those models.\n\n\
+        Examples:\n  \
+        pixery discord-bot\n  \
+        pixery discord-bot --guild 123456789012345678      # Register in one server (instant)\n  \
+        pixery discord-bot -m flux-schnell --models flux-schnell,animagine -t shared")]
+    DiscordBot {
+        /// Default model
+        #[arg(short, long, default_value = "gemini-flash", add = ArgValueCandidates::new(complete_models))]
+        model: String,
+
+        /// Models users may pick from (comma-separated, up to 25)
+        #[arg(long)]
+        models: Option<String>,
+
+        /// Register /imagine in this server only instead of globally
+        #[arg(long)]
+        guild: Option<String>,
+
+        /// Extra tags for every generation (comma-separated)
         #[arg(short, long)]
-        description: Option<String>,
+        tags: Option<String>,
     },
 
-    /// List all collections
-    List,
+    /// Run a Telegram bot that generates images from chat messages
+    #[command(name = "telegram-bot", long_about = "Long-poll a Telegram bot and answer /imagine <prompt> \
+        (in a private chat, any message is a prompt). Requests are generated one at a time with the \
+        existing workflow, the images are sent back as replies, and each generation is archived with \
+        the telegram tag and the requesting user (shown by pixery show).\n\n\
+        Reply to an image, or send one with the prompt as its caption, to use it as a reference; \
+        replying to an image the bot sent uses the archived original. Start a prompt with \
+        model:NAME to pick one of --models.\n\n\
+        Needs TELEGRAM_BOT_TOKEN (from @BotFather). Telegram bots are public, so without --allow \
+        anyone who finds the bot can spend on its models.\n\n\
+        Examples:\n  \
+        pixery telegram-bot --allow alice,bob\n  \
+        pixery telegram-bot -m flux-schnell --models flux-schnell,animagine -t shared")]
+    TelegramBot {
+        /// Default model
+        #[arg(short, long, default_value = "gemini-flash", add = ArgValueCandidates::new(complete_models))]
+        model: String,
 
-    /// Add generations to a collection
-    Add {
-        /// Generation IDs
-        ids: Vec<i64>,
+        /// Models users may pick with model:NAME (comma-separated)
+        #[arg(long)]
+        models: Option<String>,
 
-        /// Collection name
+        /// Telegram usernames or user IDs allowed to generate (comma-separated)
+        #[arg(long)]
+        allow: Option<String>,
+
+        /// Extra tags for every generation (comma-separated)
         #[arg(short, long)]
-        collection: String,
+        tags: Option<String>,
+
+        /// Bot API server, e.g. a local telegram-bot-api
+        #[arg(long, default_value = telegram::API_BASE)]
+        api_url: String,
     },
 
-    /// Remove generations from a collection
-    Remove {
-        /// Generation IDs
+    /// Re-run recent failed jobs, within a cost cap
+    #[command(name = "retry-failed", long_about = "Re-run every failed job from the last N hours with its \
+        original parameters -- handy after a provider outage kills half a batch.\n\n\
+        Jobs run oldest first. A job whose estimated cost would take the total past --max-cost is \
+        left alone, as is one the provider refused under its content policy (the same prompt \
+        would be refused again). Each original job is linked to the job that re-ran it, so running this again \
+        only picks up what is still failing.\n\n\
+        Examples:\n  \
+        pixery retry-failed\n  \
+        pixery retry-failed --hours 6 --max-cost 0.50")]
+    RetryFailed {
+        /// How far back to look for failures
+        #[arg(long, default_value = "24")]
+        hours: i64,
+
+        /// Maximum to spend on retries (USD)
+        #[arg(long, default_value_t = workflow::DEFAULT_RETRY_COST_CAP)]
+        max_cost: f64,
+    },
+
+    /// Open a generation in an image editor and import the edited result
+    #[command(long_about = "Copy a generation's image to the edits folder in the archive, open it \
+        in an editor, and watch it for saves. Each save offers to import the edited image as a \
+        child of the original (with its prompt, model and tags, plus `edited`); a save identical \
+        to an archived image isn't imported twice. Ctrl-C or q stops watching.\n\n\
+        Editors: photoshop, krita, gimp, default (the system's app for the file type), or one \
+        set up with `pixery editors add`. Without --with, the one picked with `pixery editors \
+        default` is used. Editing the same generation again continues with its working copy.\n\n\
+        Examples:\n  \
+        pixery edit 140\n  \
+        pixery edit 140 --with krita\n  \
+        pixery edit 140 --with photoshop --yes     # Import every save without asking")]
+    Edit {
+        /// Generation ID
+        id: i64,
+
+        /// Editor to open it in
+        #[arg(short, long, add = ArgValueCandidates::new(complete_editors))]
+        with: Option<String>,
+
+        /// Import every save without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// List editors for `pixery edit`, pick the default or add your own
+    #[command(long_about = "List the editors `pixery edit` can open images in, pick the one \
+        it uses by default, or add a command for an editor (stored in editors.json).\n\n\
+        A command is the program and its arguments; {file} stands for the image, which is \
+        appended when it's missing. A command for a built-in name (photoshop, krita, gimp, \
+        default) replaces how pixery starts it.\n\n\
+        Examples:\n  \
+        pixery editors\n  \
+        pixery editors default krita\n  \
+        pixery editors add krita -- ~/Apps/krita.AppImage {file}\n  \
+        pixery editors add pinta -- flatpak run com.github.PintaProject.Pinta\n  \
+        pixery editors remove pinta")]
+    Editors {
+        #[command(subcommand)]
+        action: Option<EditorsAction>,
+    },
+
+    /// Import an existing image, or a directory of them, into the archive
+    #[command(long_about = "Import an existing image, or a whole directory of them, into the archive.\n\n\
+        With --dir, every image in the directory (and its subdirectories with --recursive) \
+        is imported with the same model, tags and prompt (default: each file's name). \
+        Non-images are skipped.\n\n\
+        An image's date and time come from its EXIF/XMP capture time, else a -YYYYMMDD-HHMMSS \
+        suffix in its name, else now. Embedded camera, lens and software are kept as the \
+        generation's parameters (see `pixery show`) and matched by `pixery search`.\n\n\
+        A file byte-identical to an archived image (trash included) is not archived again: \
+        --on-duplicate skip (the default) leaves the existing generation alone, link adds \
+        this import's tags and references to it, and import archives a second copy anyway. \
+        Generations are matched by a SHA-256 of their image file. New generations from \
+        providers follow \"on_duplicate\" in generation.json in the archive root, which \
+        defaults to import so every paid call stays on record.\n\n\
+        Examples:\n  \
+        pixery import -f old.png -p \"a red fox\" -m flux-schnell\n  \
+        pixery import -f old.png -t favourite --on-duplicate link\n  \
+        pixery import --dir ~/old-outputs --recursive --tag legacy")]
+    Import {
+        /// Path to existing image file
+        #[arg(short, long, required_unless_present = "dir", conflicts_with = "dir")]
+        file: Option<PathBuf>,
+
+        /// Import every image in this directory instead of a single file
+        #[arg(long)]
+        dir: Option<PathBuf>,
+
+        /// With --dir, also import from subdirectories
+        #[arg(short = 'R', long, requires = "dir")]
+        recursive: bool,
+
+        /// Prompt text
+        #[arg(short, long)]
+        prompt: Option<String>,
+
+        /// Read prompt from file
+        #[arg(long = "prompt-file")]
+        prompt_file: Option<PathBuf>,
+
+        /// Model that generated this image
+        #[arg(short, long, default_value = "unknown", add = ArgValueCandidates::new(complete_models))]
+        model: String,
+
+        /// Tags (comma-separated)
+        #[arg(short, long, alias = "tag", add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
+
+        /// Reference image(s) used for this generation: a path, ref:ID or gen:ID
+        #[arg(short, long = "ref", conflicts_with = "dir")]
+        reference: Vec<PathBuf>,
+
+        /// Override date (YYYY-MM-DD), otherwise from EXIF/XMP or the filename, or today
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Override timestamp (HH:MM:SS), otherwise from EXIF/XMP or the filename, or now
+        #[arg(long)]
+        time: Option<String>,
+
+        /// When the image is already archived: skip, link or import
+        #[arg(long, default_value = "skip")]
+        on_duplicate: models::DuplicateAction,
+    },
+
+    /// Run archive maintenance (job cleanup, trash purge, preview pruning)
+    #[command(long_about = "Run archive maintenance once, or repeatedly with --daemon.\n\n\
+        Steps: mark stalled jobs failed, delete job records older than 24h, permanently \
+        delete generations trashed more than 30 days ago, prune /tmp/pixery-preview files \
+        older than a day, remove reference images no generation links to any more. The GUI \
+        runs the same pass hourly; the last run is recorded in maintenance.json in the \
+        archive root.\n\n\
+        Examples:\n  \
+        pixery maintenance            # Run once and print a report\n  \
+        pixery maintenance --status   # Show the last run without running\n  \
+        pixery maintenance --dry-run  # List unused references that would be removed\n  \
+        pixery maintenance --daemon   # Keep running hourly")]
+    Maintenance {
+        /// Keep running on the maintenance interval
+        #[arg(long)]
+        daemon: bool,
+
+        /// Only show the last recorded run
+        #[arg(long, conflicts_with = "daemon")]
+        status: bool,
+
+        /// List unused reference images the GC step would remove, without changing anything
+        #[arg(long, conflicts_with_all = ["daemon", "status"])]
+        dry_run: bool,
+    },
+
+    /// Auto-caption generations with a vision model (Ollama or OpenAI)
+    #[command(long_about = "Describe generations with a vision model and store the caption.\n\n\
+        Sends each thumbnail to a local Ollama vision model (default: llava) or OpenAI, and \
+        stores a one-line description plus keywords. Captions are matched by search, so \
+        imports whose prompt is just a filename become findable. Defaults come from \
+        caption.json in the archive root ({\"backend\", \"model\", \"url\"}).\n\n\
+        Examples:\n  \
+        pixery caption                        # Caption the 20 newest uncaptioned\n  \
+        pixery caption --limit 200            # Backfill more\n  \
+        pixery caption 140 141                # Re-caption specific generations\n  \
+        pixery caption --backend openai       # Use gpt-4o-mini instead of Ollama")]
+    Caption {
+        /// Generation IDs (default: uncaptioned generations, newest first)
         ids: Vec<i64>,
 
-        /// Collection name
+        /// Max uncaptioned generations to process when no IDs are given
+        #[arg(short, long, default_value = "20")]
+        limit: i64,
+
+        /// Vision backend: ollama or openai
+        #[arg(long)]
+        backend: Option<String>,
+
+        /// Vision model (default: llava for ollama, gpt-4o-mini for openai)
+        #[arg(long)]
+        model: Option<String>,
+    },
+
+    /// Regenerate all thumbnails at current sizes (400px grid, 800px preview)
+    RegenThumbs {
+        /// Only process thumbnails smaller than this size (default: regenerate all)
+        #[arg(long)]
+        if_smaller: Option<u32>,
+
+        /// Dry run - show what would be regenerated without doing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Store archive paths in the database relative to the archive root (one-time)
+    #[command(long_about = "Rewrite image, thumbnail and reference paths in the database to be \
+        relative to the archive root.\n\n\
+        New generations are stored that way, so the archive keeps working after it's moved or \
+        synced to a machine with another home directory. Rows written by older versions hold \
+        absolute paths; run this once to convert them. Paths outside the archive stay absolute.\n\n\
+        Examples:\n  \
+        pixery migrate-paths --dry-run\n  \
+        pixery migrate-paths")]
+    MigratePaths {
+        /// Count the paths that would change without changing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Generate multiple images from one prompt or a file of prompts
+    #[command(long_about = "Generate multiple images from the same prompt sequentially.\n\n\
+        Useful for exploring variations — same prompt/model produces different results each run. \
+        Reports per-image success/failure and a summary at the end. The run is recorded \
+        as an experiment (batch-YYYYMMDD-HHMMSS, see `pixery experiment`).\n\n\
+        --prompts-file runs every prompt in a file instead, -n images each (default 1), and \
+        ends with a per-prompt report. Each line is a plain prompt or a JSON object whose \
+        model, ratio, tags and negative replace the command line's for that prompt; blank \
+        lines and # comments are skipped:\n  \
+        a lighthouse at dusk\n  \
+        {\"prompt\": \"1girl, cafe\", \"model\": \"noobai\", \"ratio\": \"portrait\", \"tags\": [\"cafe\"]}\n\n\
+        --dry-run prints the number of images and API calls with the per-image and total \
+        estimated cost, then exits without calling any API. fal.ai models billed per megapixel \
+        are priced at the size --ratio renders; others use the model's listed price.\n\n\
+        --resume picks up a run that was cancelled or had failures, making only the API \
+        calls that didn't complete; settings come from the original run.\n\n\
+        Examples:\n  \
+        pixery batch -p \"fantasy landscape\" -n 6\n  \
+        pixery batch -p \"castle\" -m flux2-pro -n 40 --ratio wide --dry-run\n  \
+        pixery batch -p \"character portrait\" -m animagine -n 4 --ratio portrait\n  \
+        pixery batch -p \"concept art\" -m gemini-pro --ref mood.png -t exploration\n  \
+        pixery batch -p \"1girl, cafe\" -m animagine -n 4 --ref char.png --ip-scale 0.4\n  \
+        pixery batch -p \"forest shrine\" -m z-image -n 8 --count-per-call 4  # 2 API calls\n  \
+        pixery batch --prompts-file prompts.jsonl -m flux2-pro -t dataset --dry-run\n  \
+        pixery batch --resume batch-20250301-142233")]
+    Batch {
+        /// Prompt text
+        #[arg(short, long, required_unless_present_any = ["prompts_file", "resume"])]
+        prompt: Option<String>,
+
+        /// Run each prompt in this file (one per line, plain or JSON)
+        #[arg(long, value_name = "FILE", conflicts_with = "prompt")]
+        prompts_file: Option<PathBuf>,
+
+        /// Finish an earlier run's remaining API calls (experiment name, e.g. batch-20250301-142233)
+        #[arg(long, value_name = "RUN", conflicts_with_all = ["prompt", "prompts_file", "dry_run"])]
+        resume: Option<String>,
+
+        /// Model to use
+        #[arg(short, long, default_value = "gemini-flash", add = ArgValueCandidates::new(complete_models))]
+        model: String,
+
+        /// Number of images to generate (default 4; per prompt with --prompts-file, default 1)
+        #[arg(short = 'n', long, value_parser = clap::value_parser!(u32).range(1..))]
+        count: Option<u32>,
+
+        /// Tags (comma-separated)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
+
+        /// Reference image(s): a path, ref:ID (stored reference) or gen:ID (archived generation)
+        #[arg(short, long = "ref")]
+        reference: Vec<PathBuf>,
+
+        /// Negative prompt
+        #[arg(long)]
+        negative: Option<String>,
+
+        /// Aspect ratio (e.g., square, portrait, 16:9, 2:3)
+        #[arg(long)]
+        ratio: Option<String>,
+
+        /// IP-Adapter scale for self-hosted models (0.0-1.0, default 0.7)
+        #[arg(long)]
+        ip_scale: Option<f64>,
+
+        /// Use the model's prompting-guide negative template if --negative isn't given
+        #[arg(long)]
+        auto_negative: bool,
+
+        /// Title the result from the prompt (heuristic or LLM, see generation.json)
+        #[arg(long)]
+        auto_title: bool,
+
+        /// Images per API call (fal.ai up to 4, gpt-image-1 up to 10); -n is still the total
+        #[arg(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+        count_per_call: u32,
+
+        /// Preprocess references: shrink so the longest side is at most PX pixels
+        #[arg(long, value_name = "PX")]
+        ref_resize: Option<u32>,
+
+        /// Preprocess references: center-crop to square, portrait (2:3) or landscape (3:2)
+        #[arg(long)]
+        ref_crop: Option<refprep::RefCrop>,
+
+        /// Preprocess references: remove the background (self-hosted server /rembg)
+        #[arg(long)]
+        ref_rembg: bool,
+
+        /// Print the image count and estimated cost without generating anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Generate one prompt on several models side by side
+    #[command(long_about = "A/B model comparison: generate the same prompt on each listed model concurrently.\n\n\
+        The run is recorded as an experiment (ab-YYYYMMDD-HHMMSS, see `pixery experiment`) and \
+        every result is also tagged experiment:<name>. Prints a table of IDs, cost and \
+        generation time per model; --sheet also writes a labeled contact sheet (see `pixery compare`).\n\n\
+        Examples:\n  \
+        pixery ab -p \"lighthouse at dusk\" -m gemini-pro -m flux-pro -m noobai\n  \
+        pixery ab -p \"1girl, cafe\" -m animagine -m noobai --ratio portrait --sheet ab.png\n  \
+        pixery ab -p \"product shot\" -m gpt-image-1 -m flux2-pro --ref bottle.png -t packaging")]
+    Ab {
+        /// Prompt text
         #[arg(short, long)]
-        collection: String,
+        prompt: String,
+
+        /// Models to compare (repeat: -m gemini-pro -m flux-pro)
+        #[arg(short, long = "model", required = true, add = ArgValueCandidates::new(complete_models))]
+        models: Vec<String>,
+
+        /// Extra tags (comma-separated)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
+
+        /// Reference image(s): a path, ref:ID (stored reference) or gen:ID (archived generation)
+        #[arg(short, long = "ref")]
+        reference: Vec<PathBuf>,
+
+        /// Negative prompt
+        #[arg(long)]
+        negative: Option<String>,
+
+        /// Aspect ratio (e.g., square, portrait, 16:9, 2:3)
+        #[arg(long)]
+        ratio: Option<String>,
+
+        /// Use each model's prompting-guide negative template if --negative isn't given
+        #[arg(long)]
+        auto_negative: bool,
+
+        /// Also write a contact sheet of the results to this file
+        #[arg(long)]
+        sheet: Option<PathBuf>,
     },
 
-    /// Delete a collection
-    Delete {
-        /// Collection name
-        name: String,
+    /// Browse recorded ab and batch runs
+    #[command(long_about = "Browse experiments: the runs made by `pixery ab` and `pixery batch`.\n\n\
+        Each run is recorded with its prompt and parameters (models, count, ratio, references, \
+        tags, ...) and linked to the generations it produced. `show` prints the parameters, a \
+        per-model summary (images, cost, average time) and the generations in the order made.\n\n\
+        Subcommands:\n  \
+        list    Runs, newest first, with image count and cost\n  \
+        show    One run's parameters, per-model summary and generations\n\n\
+        Examples:\n  \
+        pixery experiment list\n  \
+        pixery experiment show ab-20250301-142233")]
+    Experiment {
+        #[command(subcommand)]
+        action: ExperimentAction,
     },
-}
 
-pub fn run(cmd: Commands) -> Result<()> {
-    // Ensure directories exist
-    archive::ensure_dirs()?;
+    /// Regenerate a masked area of a generation
+    #[command(long_about = "Inpaint: regenerate the masked area of an archived generation.\n\n\
+        The mask is an image the size of the source (it is scaled if not): white areas are \
+        regenerated from the prompt, black areas are kept. The result is saved as a child \
+        generation (parent_id = the source) and the mask is stored as its reference.\n\n\
+        Models with mask support: flux-fill (fal.ai FLUX.1 Fill [pro]), gpt-image-1 (OpenAI \
+        edits), and self-hosted SDXL models.\n\n\
+        Examples:\n  \
+        pixery inpaint 140 --mask sky-mask.png -p \"replace the sky with a thunderstorm\"\n  \
+        pixery inpaint 140 --mask hands.png -p \"hands holding a lantern\" -m gpt-image-1\n  \
+        pixery inpaint 88 --mask face.png -p \"1girl, smiling\" -m animagine")]
+    Inpaint {
+        /// Generation ID to edit
+        id: i64,
+
+        /// Mask image: white = regenerate, black = keep
+        #[arg(long)]
+        mask: PathBuf,
+
+        /// What to paint into the masked area
+        #[arg(short, long)]
+        prompt: String,
+
+        /// Model with mask support (flux-fill, gpt-image-1, self-hosted)
+        #[arg(short, long, default_value = "flux-fill", add = ArgValueCandidates::new(complete_models))]
+        model: String,
+
+        /// Tags (comma-separated)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
+
+        /// Negative prompt (self-hosted)
+        #[arg(long)]
+        negative: Option<String>,
+
+        /// Copy result to path
+        #[arg(long)]
+        copy_to: Option<PathBuf>,
+
+        /// Name the copy from a template, e.g. "{date}_{model}_{slug}_{id}"; --copy-to is then a directory
+        #[arg(long, requires = "copy_to")]
+        name_template: Option<NameTemplate>,
+    },
+
+    /// Extend a generation's canvas (outpainting)
+    #[command(long_about = "Outpaint: extend an archived generation's canvas and fill the new area.\n\n\
+        Pads the source on one side (or all sides) by --pixels, builds the mask automatically \
+        (new area plus a thin seam into the original) and sends both to a model with mask \
+        support. The result is a child generation (parent_id = the source); the mask is stored \
+        as its reference.\n\n\
+        Models: flux-fill (fal.ai, default), gpt-image-1, self-hosted SDXL models (which \
+        accept at most 1536px per side).\n\n\
+        Examples:\n  \
+        pixery extend 140 --direction left --pixels 512 -p \"continue the scene\"\n  \
+        pixery extend 140 --direction all --pixels 256 -p \"wider view of the valley\"\n  \
+        pixery extend 88 --direction down -p \"full body, standing on a stone path\" -m gpt-image-1")]
+    Extend {
+        /// Generation ID to extend
+        id: i64,
+
+        /// Side to extend: left, right, up, down or all
+        #[arg(short, long)]
+        direction: outpaint::Direction,
+
+        /// Pixels to add on that side
+        #[arg(long, default_value = "512")]
+        pixels: u32,
+
+        /// What the new area should show
+        #[arg(short, long)]
+        prompt: String,
+
+        /// Model with mask support (flux-fill, gpt-image-1, self-hosted)
+        #[arg(short, long, default_value = "flux-fill", add = ArgValueCandidates::new(complete_models))]
+        model: String,
+
+        /// Tags (comma-separated)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
+
+        /// Negative prompt (self-hosted)
+        #[arg(long)]
+        negative: Option<String>,
+
+        /// Copy result to path
+        #[arg(long)]
+        copy_to: Option<PathBuf>,
+
+        /// Name the copy from a template, e.g. "{date}_{model}_{slug}_{id}"; --copy-to is then a directory
+        #[arg(long, requires = "copy_to")]
+        name_template: Option<NameTemplate>,
+    },
+
+    /// Crop, rotate or flip a generation into a child generation
+    #[command(long_about = "Quick fixes without an editor: crop, rotate or flip a generation's \
+        image and archive the result as its child (parent_id = the source), with the same \
+        prompt, model, seed and tags plus `transform`. Nothing is sent to a provider and the \
+        result costs nothing; it's saved as PNG so no quality is lost.\n\n\
+        --crop takes ImageMagick geometry, WIDTHxHEIGHT+X+Y from the top left of the source. \
+        The steps run in a fixed order whatever the order given: crop, rotate (clockwise), \
+        flip. Running the same transform twice finds the earlier result instead of archiving \
+        a copy.\n\n\
+        Examples:\n  \
+        pixery transform 140 --crop 512x512+100+200\n  \
+        pixery transform 140 --rotate 90\n  \
+        pixery transform 140 --rotate -90 --flip h\n  \
+        pixery transform 140 --crop 1024x576+0+224 -t banner")]
+    #[command(group(clap::ArgGroup::new("steps").required(true).multiple(true).args(["crop", "rotate", "flip"])))]
+    Transform {
+        /// Generation ID
+        id: i64,
+
+        /// Region to keep: WIDTHxHEIGHT+X+Y, e.g. 512x512+100+200
+        #[arg(long)]
+        crop: Option<transform::Crop>,
+
+        /// Degrees clockwise: 90, 180, 270 (or -90)
+        #[arg(long, allow_hyphen_values = true)]
+        rotate: Option<transform::Rotation>,
+
+        /// Mirror: h (left-right) or v (top-bottom)
+        #[arg(long)]
+        flip: Option<transform::Flip>,
+
+        /// Extra tags for the result (comma-separated)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
+    },
+
+    /// Re-detail faces (or hands) in a generation
+    #[command(long_about = "Fix pass: detect regions in an archived generation, regenerate each one \
+        at higher resolution and composite it back (like ADetailer).\n\n\
+        Detection runs on the self-hosted server's /detect endpoint (YOLOv8 detectors; needs \
+        ultralytics installed there). Each region is cropped with some context, upscaled to \
+        1024px, repainted with img2img inpainting at --strength, scaled back and blended in. \
+        The result is a child generation (parent_id = the source).\n\n\
+        Prompt, model and negative prompt default to the source's. Only self-hosted models \
+        honour --strength; flux-fill and gpt-image-1 repaint the region from scratch.\n\n\
+        Examples:\n  \
+        pixery fix 140\n  \
+        pixery fix 140 --region hand --strength 0.5\n  \
+        pixery fix 88 -p \"1girl, detailed eyes, smiling\" --max-regions 1")]
+    Fix {
+        /// Generation ID to fix
+        id: i64,
+
+        /// Region to detect: face, hand or person
+        #[arg(short, long, default_value = "face")]
+        region: detail::Region,
+
+        /// Prompt for the regenerated regions (default: the source's prompt)
+        #[arg(short, long)]
+        prompt: Option<String>,
+
+        /// Model (default: the source's model)
+        #[arg(short, long, add = ArgValueCandidates::new(complete_models))]
+        model: Option<String>,
+
+        /// How much each region may change, 0-1 (self-hosted)
+        #[arg(long, default_value = "0.4", value_parser = parse_unit_interval)]
+        strength: f64,
+
+        /// Minimum detector confidence, 0-1
+        #[arg(long, default_value = "0.3", value_parser = parse_unit_interval)]
+        confidence: f64,
+
+        /// Fix at most this many regions (most confident first)
+        #[arg(long, default_value = "4")]
+        max_regions: usize,
+
+        /// Tags (comma-separated)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
+
+        /// Negative prompt (default: the source's)
+        #[arg(long)]
+        negative: Option<String>,
+
+        /// Copy result to path
+        #[arg(long)]
+        copy_to: Option<PathBuf>,
+
+        /// Name the copy from a template, e.g. "{date}_{model}_{slug}_{id}"; --copy-to is then a directory
+        #[arg(long, requires = "copy_to")]
+        name_template: Option<NameTemplate>,
+    },
+
+    /// Export generations to a directory
+    #[command(long_about = "Copy generation images to an output directory.\n\n\
+        Select generations by ID, by tag, by collection, or any combination. With \
+        --with-metadata, writes a JSON sidecar file alongside each image containing prompt, \
+        model, tags, cost, etc. With --html, also writes index.html: a static gallery \
+        (thumbnail grid, tag filter, lightbox with prompt/model/cost) that needs no app or \
+        network — share the whole output directory.\n\n\
+        Files attached with `pixery assets` are copied next to their image as \
+        <image name>-<asset file name>, unconverted.\n\n\
+        With --xmp, writes an XMP sidecar (<image name>.xmp) next to each image for Lightroom, \
+        digiKam, darktable and other photo managers: tags as keywords (dc:subject, with \
+        namespaced tags like character:kira also as the hierarchy character|kira), the prompt \
+        as the description, the title, starred as a 5-star rating, and model, provider, seed, \
+        cost and negative prompt under the pixery namespace. digiKam needs \"Read from sidecar \
+        files\" with commercial-program naming enabled to pick them up.\n\n\
+        With --max-size, --format or --strip-metadata, images are re-encoded rather than copied: \
+        scaled down to fit (never up), converted (--quality sets JPEG quality, default 90), and \
+        always without the metadata providers and cameras embed. Transparency becomes white in \
+        JPEGs. Videos and animations are copied as they are.\n\n\
+        --name-template names the files from tokens: {id}, {date}, {time}, {slug}, {title}, \
+        {model}, {provider}, {seed}, {collection}, {tag} (the one exported by, else the \
+        generation's first) and {counter} (001, 002, ... in export order). The same templates \
+        work with --copy-to on generate, where {counter} picks the next free number.\n\n\
+        Examples:\n  \
+        pixery export --ids 100 101 102 -o ./export/\n  \
+        pixery export --tag character -o ./characters/ --with-metadata\n  \
+        pixery export --tag portfolio -o ~/Pictures/pixery --xmp\n  \
+        pixery export --ids 50 --tag landscape -o ./portfolio/\n  \
+        pixery export --collection rpg-portraits -o ./rpg/ --html\n  \
+        pixery export --tag final -o ./client/ --max-size 2048 --format jpg --quality 90\n  \
+        pixery export --collection rpg -o ./rpg/ --name-template \"{collection}_{counter}_{seed}\"")]
+    Export {
+        /// Generation IDs to export
+        #[arg(short, long)]
+        ids: Vec<i64>,
+
+        /// Export all generations with this tag
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tag: Option<String>,
+
+        /// Export all generations in this collection
+        #[arg(short, long)]
+        collection: Option<String>,
+
+        /// Output directory
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Write metadata.json sidecar files
+        #[arg(long)]
+        with_metadata: bool,
+
+        /// Also write a static HTML gallery (index.html + thumbs/)
+        #[arg(long)]
+        html: bool,
+
+        /// Write XMP sidecar files (keywords, description, rating) for photo managers
+        #[arg(long)]
+        xmp: bool,
+
+        /// Scale images down so their longest side is at most this many pixels
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        max_size: Option<u32>,
+
+        /// Convert images to png, jpg or webp
+        #[arg(long)]
+        format: Option<ExportFormat>,
+
+        /// JPEG quality for converted images (1-100)
+        #[arg(long, default_value = "90", value_parser = clap::value_parser!(u8).range(1..=100))]
+        quality: u8,
+
+        /// Re-encode images so they carry no embedded metadata (provenance, EXIF, PNG text)
+        #[arg(long)]
+        strip_metadata: bool,
+
+        /// Name files from a template, e.g. "{date}_{model}_{slug}_{id}"
+        #[arg(long)]
+        name_template: Option<NameTemplate>,
+    },
+
+    /// Cycle through generations in a fullscreen window
+    #[command(long_about = "Open a window that cycles through generations, e.g. a moodboard \
+        on a second monitor while drawing.\n\n\
+        Shows the given IDs, or every image matching the filters, oldest first (collections in \
+        their own order), looping back to the start. The window is read-only and runs nothing \
+        in the background, so it can stay open next to the main one.\n\n\
+        Keys: space pauses, left/right step, f toggles fullscreen, Esc closes.\n\n\
+        Examples:\n  \
+        pixery slideshow --tag moodboard --interval 5\n  \
+        pixery slideshow --collection rpg-portraits --windowed\n  \
+        pixery slideshow --starred --shuffle --interval 10\n  \
+        pixery slideshow 140 141 142")]
+    Slideshow {
+        /// Generation IDs to show, in this order (default: everything the filters match)
+        ids: Vec<i64>,
+
+        /// Only generations with this tag ("character:*" matches any tag in that namespace)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tag: Option<String>,
+
+        /// Only generations in this collection
+        #[arg(short, long)]
+        collection: Option<String>,
+
+        /// Only generations from this model
+        #[arg(short, long, add = ArgValueCandidates::new(complete_models))]
+        model: Option<String>,
+
+        /// Only starred
+        #[arg(short, long)]
+        starred: bool,
+
+        /// Seconds per image
+        #[arg(short, long, default_value = "5", value_parser = parse_interval)]
+        interval: f64,
+
+        /// Random order
+        #[arg(long, conflicts_with = "ids")]
+        shuffle: bool,
+
+        /// Open in a normal window instead of fullscreen
+        #[arg(long)]
+        windowed: bool,
+
+        /// Include NSFW-flagged generations
+        #[arg(long)]
+        include_nsfw: bool,
+    },
+
+    /// Generate and print a pixery:// link to the result, for launchers
+    #[command(long_about = "Generate from a prompt and print nothing but a \
+        pixery://generation/ID link per image, for Raycast, Alfred and other launchers. \
+        Errors go to stderr with a non-zero exit.\n\n\
+        Opening a link (with `open`, `xdg-open`, or a launcher action) shows the generation in \
+        the GUI, starting it if needed -- once the scheme is registered: the macOS app bundle \
+        does it, elsewhere run `pixery open --register`. --open shows the result right away \
+        without the link round trip. Links into another archive profile carry ?archive=NAME.\n\n\
+        Examples:\n  \
+        pixery quick -p \"a lighthouse at dusk, oil painting\"\n  \
+        pixery quick -p \"a red fox in snow\" -m flux-schnell --open\n  \
+        open \"$(pixery quick -p 'isometric tiny cabin')\"")]
+    Quick {
+        /// Prompt text
+        #[arg(short, long)]
+        prompt: String,
+
+        /// Model to use
+        #[arg(short, long, default_value = "gemini-flash", add = ArgValueCandidates::new(complete_models))]
+        model: String,
+
+        /// Tags (comma-separated)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
+
+        /// Show the result in the GUI as well
+        #[arg(long)]
+        open: bool,
+    },
+
+    /// Show a generation in the GUI from a pixery:// link
+    #[command(long_about = "Show a generation in the GUI: bring the open window forward with it \
+        selected, or start the GUI on it. This is what the OS runs for pixery://generation/ID \
+        links; a plain ID works too.\n\n\
+        --register makes this pixery the handler for pixery:// links (a desktop entry set with \
+        xdg-mime on Linux, per-user registry keys on Windows). The macOS app bundle registers \
+        the scheme itself.\n\n\
+        Examples:\n  \
+        pixery open pixery://generation/140\n  \
+        pixery open 140\n  \
+        pixery open --register")]
+    Open {
+        /// pixery://generation/ID link or generation ID
+        #[arg(required_unless_present = "register")]
+        link: Option<String>,
+
+        /// Register pixery as the handler for pixery:// links
+        #[arg(long, conflicts_with = "link")]
+        register: bool,
+    },
+
+    /// Mirror the archive as folders of links by tag, model, collection or month
+    #[command(long_about = "Build a folder of links to the archive, grouped by tag, model, \
+        collection or month, for browsing in Finder, Explorer or any other tool.\n\n\
+        Links point at the archived files, so nothing is copied. Rerunning updates the folder \
+        in place: new generations and tags get links, links for trashed or retagged \
+        generations are removed, and files pixery didn't create are left alone (a \
+        .pixery-linkfarm.json in the folder lists its links). A generation with several tags \
+        appears under each; untagged ones go in untagged/. Nested collections become nested \
+        folders.\n\n\
+        Links are symlinks by default. --hardlink makes hard links instead, which tools that \
+        don't follow symlinks can read, but the folder has to be on the archive's filesystem. \
+        --name-template names links like `pixery export` does.\n\n\
+        Examples:\n  \
+        pixery linkfarm --by tag -o ~/media/by-tag\n  \
+        pixery linkfarm --by model -o ~/media/by-model\n  \
+        pixery linkfarm --by collection -o ~/media/by-collection --hardlink\n  \
+        pixery linkfarm --by date -o ~/media/by-month --name-template \"{date}_{slug}_{id}\"")]
+    Linkfarm {
+        /// Group by tag, model, collection or date (month)
+        #[arg(long, default_value = "tag")]
+        by: linkfarm::GroupBy,
+
+        /// Folder to build the links in
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Hard links instead of symlinks (must be on the archive's filesystem)
+        #[arg(long)]
+        hardlink: bool,
+
+        /// Name links from a template, e.g. "{date}_{model}_{slug}_{id}"
+        #[arg(long)]
+        name_template: Option<NameTemplate>,
+    },
+
+    /// Manage collections (project folders)
+    #[command(long_about = "Manage collections — lightweight project folders for organizing generations.\n\n\
+        Collections group generations by project or theme, independent of tags. \
+        A generation can belong to multiple collections.\n\n\
+        Subcommands:\n  \
+        create  Create a new collection\n  \
+        list    List all collections\n  \
+        show    List the generations in a collection, in order\n  \
+        add     Add generation(s) to a collection\n  \
+        remove  Remove generation(s) from a collection\n  \
+        order   Move generation(s) to the front of a collection's order\n  \
+        cover   Set the generation shown for a collection\n  \
+        export  Copy a collection's images to a directory, in order\n  \
+        delete  Delete a collection (does not delete generations)\n\n\
+        Collections nest: a name like rpg/chapter1 is a subcollection of rpg (missing \
+        parents are created), and commands take the full path. A collection's count, \
+        `show` and `export` include its subcollections; deleting it deletes them too.\n\n\
+        Collections keep a user-defined order: new members go to the end, and \
+        `order` moves the given IDs to the front. `export --numbered` prefixes the \
+        copied files with their zero-padded position (001-..., 002-...) so storyboards \
+        and decks stay in sequence, and copies the cover as cover.<ext>.\n\n\
+        Smart collections are saved searches: `create --smart --filter EXPR` stores a \
+        filter expression that is re-evaluated whenever the collection is shown, so new \
+        matching generations appear on their own. Terms are joined by AND: \
+        model=, tag=, tag!=, search=, since=, until=, min-width=, min-height=, \
+        orientation=, starred, nsfw, sfw, has-reference.\n\n\
+        Examples:\n  \
+        pixery collection create \"rpg-portraits\" -d \"Character art for the RPG project\"\n  \
+        pixery collection add 100 101 102 -c rpg-portraits\n  \
+        pixery collection create rpg/chapter1\n  \
+        pixery collection add 140 -c rpg/chapter1\n  \
+        pixery collection order rpg-portraits 102 100\n  \
+        pixery collection cover rpg-portraits 101\n  \
+        pixery collection export rpg-portraits -o ./handoff --numbered\n  \
+        pixery collection create best-noobai --smart --filter \"model=noobai AND tag=character AND starred\"\n  \
+        pixery collection show best-noobai\n  \
+        pixery collection list")]
+    Collection {
+        #[command(subcommand)]
+        action: CollectionAction,
+    },
+
+    /// Browse stored reference images
+    #[command(long_about = "Browse the reference image library.\n\n\
+        Reference images are stored once per content hash, however many generations use them. \
+        Reuse one anywhere --ref takes a path: ref:ID for a stored reference, gen:ID for an \
+        archived generation's image.\n\n\
+        Subcommands:\n  \
+        list  List stored references with usage counts\n  \
+        show  Show a reference and the generations that used it\n\n\
+        Examples:\n  \
+        pixery refs list\n  \
+        pixery refs show 42\n  \
+        pixery gen -p \"same character, winter outfit\" -m gemini-pro --ref ref:42\n  \
+        pixery gen -p \"variation\" -m z-image --ref gen:140")]
+    Refs {
+        #[command(subcommand)]
+        action: RefsAction,
+    },
+
+    /// Attach extra files to a generation: edited exports, upscales, crops
+    #[command(long_about = "Keep files made from a generation (a layered PSD export, an upscale, \
+        a crop) with it instead of archiving each as a generation of its own.\n\n\
+        Attached files are copied into assets/<generation ID>/ in the archive. `pixery show` \
+        lists them, `pixery export` writes them next to the image (named after it, never \
+        converted), archive bundles carry them, and deleting the generation deletes them.\n\n\
+        Subcommands:\n  \
+        add     Copy a file into the archive as an asset of a generation\n  \
+        list    List a generation's assets\n  \
+        remove  Delete an asset (file and record)\n\n\
+        Examples:\n  \
+        pixery assets add 140 ~/Desktop/fox-layers.psd --kind edit\n  \
+        pixery assets add 140 fox-4x.png --kind upscale --note \"4x-UltraSharp\"\n  \
+        pixery assets list 140\n  \
+        pixery assets remove 12")]
+    Assets {
+        #[command(subcommand)]
+        action: AssetsAction,
+    },
+
+    /// Extract dominant color palettes
+    #[command(long_about = "Show or re-extract the dominant colors of generations.\n\n\
+        A palette of up to 5 colors is extracted when a generation is saved (k-means over a \
+        small copy of the image; a video's first frame) and shown by `pixery show` and the \
+        gallery. `pixery list --color` searches them.\n\n\
+        Given IDs, their palettes are extracted again and printed. --missing extracts them \
+        for every generation without one, such as those archived before palettes existed.\n\n\
+        Examples:\n  \
+        pixery palette 140\n  \
+        pixery palette 140 141 142\n  \
+        pixery palette --missing")]
+    Palette {
+        /// Generation IDs
+        #[arg(required_unless_present = "missing", conflicts_with = "missing")]
+        ids: Vec<i64>,
+
+        /// Extract palettes for every generation that has none
+        #[arg(long)]
+        missing: bool,
+    },
+
+    /// Check, switch and warm up the self-hosted server
+    #[command(long_about = "Manage the self-hosted inference server's loaded model.\n\n\
+        Loading a checkpoint takes 20-30s and the first pass after it is slow as well; do it \
+        ahead of time so the first generation doesn't pay the cold start. The server keeps one \
+        model loaded at a time.\n\n\
+        Subcommands:\n  \
+        status  Server health, loaded model, GPU\n  \
+        use     Load (switch to) a model\n  \
+        warmup  Load a model and run one tiny pass\n\n\
+        Examples:\n  \
+        pixery selfhosted status\n  \
+        pixery selfhosted use pony\n  \
+        pixery selfhosted warmup\n  \
+        pixery selfhosted warmup -m noobai")]
+    Selfhosted {
+        #[command(subcommand)]
+        action: SelfhostedAction,
+    },
+
+    /// Move generations between machines as a single bundle file
+    #[command(long_about = "Export or import archive bundles (.tar.zst).\n\n\
+        A bundle holds images, reference images and the generations' database rows \
+        (metadata, tags, collections, parent links). Import assigns new IDs, remaps parent \
+        links, regenerates thumbnails and skips generations already present (same \
+        timestamp, model and prompt), so bundles from two machines can be merged in either \
+        direction. Trashed generations are not exported.\n\n\
+        Subcommands:\n  \
+        export  Write a bundle\n  \
+        import  Merge a bundle into this archive\n\n\
+        Examples:\n  \
+        pixery archive export --since 30d -o bundle.tar.zst\n  \
+        pixery archive import bundle.tar.zst")]
+    Archive {
+        #[command(subcommand)]
+        action: ArchiveAction,
+    },
+
+    /// Back up the archive to an S3 bucket or rclone remote
+    #[command(long_about = "Back up the archive to an S3-compatible bucket or any rclone remote.\n\n\
+        Push uploads images and reference images that are new or changed since the last push, \
+        then a snapshot of the database and a manifest of file hashes. Nothing is deleted from \
+        the remote, so it keeps everything ever pushed. Pull downloads files missing or \
+        different locally; the database is only replaced with the remote copy when the local \
+        archive is empty or with --db, and the local one is first kept as \
+        index.sqlite.bak-<timestamp>. Image paths are rewritten if the remote came from a \
+        different home directory.\n\n\
+        Transfers go through rclone (https://rclone.org), which must be installed. An s3:// \
+        remote needs no rclone config: credentials come from AWS_ACCESS_KEY_ID and \
+        AWS_SECRET_ACCESS_KEY (or ~/.aws), and --endpoint points it at MinIO, R2 and other \
+        S3-compatible services. Any other remote is an rclone remote path. The remote is \
+        stored in sync.json in the archive root.\n\n\
+        Subcommands:\n  \
+        remote  Show or set the remote\n  \
+        push    Upload new and changed files plus the database\n  \
+        pull    Download missing files (and the database when restoring)\n\n\
+        Examples:\n  \
+        pixery sync remote s3://my-bucket/pixery\n  \
+        pixery sync remote s3://pixery --endpoint https://<account>.r2.cloudflarestorage.com\n  \
+        pixery sync remote b2:backups/pixery\n  \
+        pixery sync push\n  \
+        pixery sync pull --dry-run")]
+    Sync {
+        #[command(subcommand)]
+        action: SyncAction,
+    },
+
+    /// Manage saved generation presets
+    #[command(long_about = "Save model + settings bundles under a name.\n\n\
+        A preset holds a model, aspect ratio, negative prompt, CFG scale and tags. \
+        'pixery gen --preset NAME' fills in whatever the command line leaves unset; \
+        preset tags are added to any given with -t. Saving an existing name replaces it. \
+        Presets also appear in the GUI's generate dialog.\n\n\
+        Subcommands:\n  \
+        add     Create or replace a preset\n  \
+        list    List presets\n  \
+        remove  Delete a preset\n\n\
+        Examples:\n  \
+        pixery preset add anime-portrait -m noobai --ratio portrait --guide-negative --cfg 5 -t anime,portrait\n  \
+        pixery gen --preset anime-portrait -p \"1girl, silver hair, cafe\"\n  \
+        pixery preset list")]
+    Preset {
+        #[command(subcommand)]
+        action: PresetAction,
+    },
+
+    /// Fire generations on a cron schedule
+    #[command(long_about = "Manage recurring generations.\n\n\
+        Schedules use standard 5-field cron expressions (min hour day month weekday) in \
+        local time. Every generation a schedule fires is tagged with the schedule name. \
+        Runs missed while no runner was active fire once, not once per missed slot.\n\n\
+        Subcommands:\n  \
+        add     Create a schedule\n  \
+        list    List schedules with their next run\n  \
+        remove  Delete a schedule (keeps its generations)\n  \
+        enable / disable  Pause or resume a schedule\n  \
+        run     Fire due schedules (a daemon; --once to check once and exit)\n  \
+        gui     Also fire schedules from the GUI while it is open (on/off)\n\n\
+        Examples:\n  \
+        pixery schedule add \"0 9 * * *\" -p \"daily sketch prompt\" -m flux-schnell\n  \
+        pixery schedule add \"0 */6 * * *\" -p \"weather study\" -m gemini-flash -n weather -t study\n  \
+        pixery schedule run\n  \
+        pixery schedule gui on")]
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleAction,
+    },
+
+    /// Auto-import new images from folders (e.g. a downloads folder)
+    #[command(long_about = "Import images that appear in watched folders.\n\n\
+        Each folder has a default model and tags recorded on its imports; the filename \
+        becomes the prompt, as with 'pixery import'. Folders are watched non-recursively. \
+        The GUI imports from watched folders while it is open; 'pixery watch run' does the \
+        same headless. Run one or the other, not both. Folders are stored in watch.json \
+        in the archive root.\n\n\
+        Subcommands:\n  \
+        add     Watch a folder\n  \
+        list    List watched folders\n  \
+        remove  Stop watching a folder\n  \
+        run     Import new images until interrupted\n\n\
+        Examples:\n  \
+        pixery watch add ~/Downloads/midjourney -m midjourney -t mj,inspiration\n  \
+        pixery watch run")]
+    Watch {
+        #[command(subcommand)]
+        action: WatchAction,
+    },
+
+    /// Show recent prompt history
+    #[command(long_about = "Show recent prompts with generation IDs.\n\n\
+        Output columns: ID, DATE, PROMPT (truncated). Useful for re-using or iterating \
+        on previous prompts — copy the ID to 'pixery show' or 'pixery view' for details.\n\n\
+        Examples:\n  \
+        pixery history              # Last 20 prompts\n  \
+        pixery history -n 50        # Last 50 prompts")]
+    History {
+        /// Number of entries to show
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: i64,
+    },
+
+    /// Show recent changes (generate, star, tag, trash, edits) and undo them
+    #[command(long_about = "Show recent changes to the archive, newest first.\n\n\
+        Every new generation, star, tag, trash/restore, prompt or title edit and permanent \
+        delete is logged, from the CLI, GUI and TUI alike. --undo reverses one entry by its \
+        EVENT number (the undo is logged too); permanent deletes can't be undone.\n\n\
+        Examples:\n  \
+        pixery activity              # Last 20 changes\n  \
+        pixery activity --id 42      # History of generation 42\n  \
+        pixery activity --undo 310   # Put back what event 310 changed")]
+    Activity {
+        /// Number of entries to show
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: i64,
+
+        /// Only this generation's history
+        #[arg(long)]
+        id: Option<i64>,
+
+        /// Undo this event
+        #[arg(long, value_name = "EVENT", conflicts_with = "id")]
+        undo: Option<i64>,
+    },
+
+    /// Undo the last tag removal, prompt/title edit, trash or collection change
+    #[command(long_about = "Undo the most recent destructive change: a tag removal, prompt or \
+        title edit, trash, or a collection add/remove, from the CLI, GUI or TUI.\n\n\
+        Run it again to step further back; changes already undone, and the undos themselves, \
+        are skipped. `pixery activity` lists the log, and `pixery activity --undo EVENT` \
+        reverses any other entry (stars, added tags, new generations).\n\n\
+        Examples:\n  \
+        pixery undo")]
+    Undo,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum PresetAction {
+    /// Create or replace a preset
+    Add {
+        /// Preset name
+        name: String,
+
+        /// Model to use
+        #[arg(short, long, add = ArgValueCandidates::new(complete_models))]
+        model: String,
+
+        /// Aspect ratio preset (see 'pixery generate --help')
+        #[arg(long)]
+        ratio: Option<String>,
+
+        /// Negative prompt
+        #[arg(long, conflicts_with = "guide_negative")]
+        negative: Option<String>,
+
+        /// Use the negative template from the model's prompting guide
+        #[arg(long)]
+        guide_negative: bool,
+
+        /// CFG scale (self-hosted models)
+        #[arg(long)]
+        cfg: Option<f64>,
+
+        /// Tags (comma-separated)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
+    },
+
+    /// List presets
+    List,
+
+    /// Delete a preset
+    Remove {
+        /// Preset name
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum WatchAction {
+    /// Watch a folder
+    Add {
+        /// Folder path
+        path: PathBuf,
+
+        /// Model recorded on imported images
+        #[arg(short, long, default_value = "unknown", add = ArgValueCandidates::new(complete_models))]
+        model: String,
+
+        /// Tags for imported images (comma-separated)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
+    },
+
+    /// List watched folders
+    List,
+
+    /// Stop watching a folder
+    Remove {
+        /// Folder path
+        path: PathBuf,
+    },
+
+    /// Import new images until interrupted
+    Run,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum ScheduleAction {
+    /// Create a schedule
+    Add {
+        /// Cron expression, e.g. "0 9 * * *" for 09:00 daily
+        cron: String,
+
+        /// Prompt text
+        #[arg(short, long)]
+        prompt: String,
+
+        /// Model to use
+        #[arg(short, long, default_value = "gemini-flash", add = ArgValueCandidates::new(complete_models))]
+        model: String,
+
+        /// Schedule name, also added as a tag (default: slug of the prompt)
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Extra tags (comma-separated)
+        #[arg(short, long, add = ArgValueCompleter::new(complete_tags))]
+        tags: Option<String>,
+
+        /// Negative prompt
+        #[arg(long)]
+        negative: Option<String>,
+
+        /// Aspect ratio preset (see 'pixery generate --help')
+        #[arg(long)]
+        ratio: Option<String>,
+    },
+
+    /// List schedules
+    List,
+
+    /// Delete a schedule
+    Remove {
+        /// Schedule name
+        name: String,
+    },
+
+    /// Resume a paused schedule
+    Enable {
+        /// Schedule name
+        name: String,
+    },
+
+    /// Pause a schedule
+    Disable {
+        /// Schedule name
+        name: String,
+    },
+
+    /// Fire due schedules until interrupted
+    Run {
+        /// Check once, fire anything due, and exit (for use from system cron)
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// Fire schedules from the GUI while it is open (takes effect on restart)
+    Gui {
+        /// on or off
+        state: String,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum ArchiveAction {
+    /// Write generations to a bundle file
+    Export {
+        /// Only generations since (e.g. 30d, 2w, 2026-01-01; default: all)
+        #[arg(short, long)]
+        since: Option<String>,
+
+        /// Output bundle path
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+
+    /// Merge a bundle into this archive
+    Import {
+        /// Bundle path
+        bundle: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum DoctorAction {
+    /// Verify API keys and servers with a cheap call to each provider
+    Providers,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum KeysAction {
+    /// Store a provider's API key in the keychain
+    Set {
+        /// gemini, openai or fal
+        #[arg(value_parser = ["gemini", "openai", "fal"])]
+        provider: String,
+
+        /// The key (read from stdin if omitted)
+        key: Option<String>,
+    },
+
+    /// Remove a provider's API key from the keychain
+    Remove {
+        /// gemini, openai or fal
+        #[arg(value_parser = ["gemini", "openai", "fal"])]
+        provider: String,
+    },
+
+    /// Show which providers have a key and where it comes from
+    Status,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum SyncAction {
+    /// Show or set the remote
+    Remote {
+        /// s3://bucket/prefix or an rclone remote path (remote:path)
+        remote: Option<String>,
+
+        /// Endpoint URL for a non-AWS S3-compatible service
+        #[arg(long)]
+        endpoint: Option<String>,
+    },
+
+    /// Upload new and changed files plus the database
+    Push {
+        /// Only report what would be uploaded
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Download files missing here
+    Pull {
+        /// Also replace the local database with the remote copy
+        #[arg(long)]
+        db: bool,
+
+        /// Only report what would be downloaded
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum ExperimentAction {
+    /// List experiments, newest first
+    List {
+        /// Maximum results
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// Show an experiment's parameters and generations
+    Show {
+        /// Experiment name (e.g. ab-20250301-142233)
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum RefsAction {
+    /// List stored references
+    List,
+
+    /// Show a reference and the generations that used it
+    Show {
+        /// Reference ID
+        id: i64,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum AssetsAction {
+    /// Copy a file into the archive as an asset of a generation
+    Add {
+        /// Generation ID
+        id: i64,
+
+        /// File to attach
+        file: PathBuf,
+
+        /// What the file is: edit, upscale, crop or other
+        #[arg(short, long, default_value = "other")]
+        kind: AssetKind,
+
+        /// A note shown with the asset, e.g. how it was made
+        #[arg(short, long)]
+        note: Option<String>,
+    },
+
+    /// List a generation's assets
+    List {
+        /// Generation ID
+        id: i64,
+    },
+
+    /// Delete an asset (file and record)
+    Remove {
+        /// Asset ID (from `pixery assets list`)
+        id: i64,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum JobsAction {
+    /// List jobs, newest first
+    List {
+        /// Only jobs with this status: pending, running, completed, failed, cancelled
+        #[arg(short, long)]
+        status: Option<models::JobStatus>,
+
+        /// Only jobs since (e.g. 7d, 2w, 2026-01-01)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Maximum number of jobs to show
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: i64,
+    },
+
+    /// Re-run a failed or cancelled job with its original parameters
+    Retry {
+        /// Job ID
+        job_id: i64,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum CurrencyAction {
+    /// Show the display currency and its rate (the default)
+    Show,
+
+    /// Display costs in another currency
+    Set {
+        /// ISO 4217 code, e.g. EUR (USD shows costs as recorded)
+        code: String,
+
+        /// Units of CODE per USD; fetched daily when not given
+        #[arg(long, value_parser = parse_price)]
+        rate: Option<f64>,
+    },
+
+    /// Fetch today's exchange rate now
+    Refresh,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum ProfilesAction {
+    /// Profiles and their roots (the default)
+    List,
+
+    /// Use a profile when --archive isn't given
+    Switch {
+        /// Profile name
+        #[arg(add = ArgValueCandidates::new(complete_profiles))]
+        name: String,
+    },
+
+    /// Add an archive profile (its directories are created on first use)
+    Add {
+        /// Profile name (letters, digits, '-' and '_')
+        name: String,
+
+        /// Root directory for the archive
+        root: PathBuf,
+
+        /// What the archive is for
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+
+    /// Forget a profile; its files are left alone
+    Remove {
+        /// Profile name
+        #[arg(add = ArgValueCandidates::new(complete_profiles))]
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum EditorsAction {
+    /// Editors and how each is started (the default)
+    List,
+
+    /// Use an editor when `pixery edit` isn't given --with
+    Default {
+        /// Editor name
+        #[arg(add = ArgValueCandidates::new(complete_editors))]
+        name: String,
+    },
+
+    /// Set the command for an editor
+    Add {
+        /// Editor name
+        name: String,
+
+        /// Program and arguments, after --; {file} is the image
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+
+    /// Remove an editor's command (a built-in one goes back to how pixery starts it)
+    Remove {
+        /// Editor name
+        #[arg(add = ArgValueCandidates::new(complete_editors))]
+        name: String,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum PricingAction {
+    /// Price history (the default)
+    List {
+        /// Only this model
+        #[arg(add = ArgValueCandidates::new(complete_models))]
+        model: Option<String>,
+    },
+
+    /// Set a model's price from a date on
+    Update {
+        #[arg(add = ArgValueCandidates::new(complete_models))]
+        model: String,
+
+        /// USD per image (per second for video models)
+        #[arg(value_parser = parse_price)]
+        cost: f64,
+
+        /// First day the price applies, YYYY-MM-DD (default: today)
+        #[arg(long, value_parser = parse_date)]
+        from: Option<String>,
+
+        /// Why it changed (e.g. a link to the provider's announcement)
+        #[arg(long)]
+        note: Option<String>,
+
+        /// Also rewrite the recorded cost of the model's generations from that
+        /// date until its next price, including costs providers reported
+        #[arg(long)]
+        reprice: bool,
+    },
+
+    /// Remove the price that starts on a date
+    Remove {
+        #[arg(add = ArgValueCandidates::new(complete_models))]
+        model: String,
+
+        /// Its first day, YYYY-MM-DD
+        #[arg(value_parser = parse_date)]
+        date: String,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum StatsAction {
+    /// Generation time percentiles per model and per hour of the day
+    Latency {
+        /// Only this model (the hourly table then shows when it's fastest)
+        #[arg(short, long, add = ArgValueCandidates::new(complete_models))]
+        model: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum NsfwAction {
+    /// Flag generations as NSFW
+    Mark {
+        /// Generation IDs
+        #[arg(required = true)]
+        ids: Vec<i64>,
+    },
+
+    /// Clear the NSFW flag
+    Unmark {
+        /// Generation IDs
+        #[arg(required = true)]
+        ids: Vec<i64>,
+    },
+
+    /// Flag existing generations by their prompts, and optionally the classifier
+    Scan {
+        /// Generation IDs (default: every unflagged generation)
+        ids: Vec<i64>,
+
+        /// Also ask the vision model about images whose prompt has no rating tag
+        #[arg(long)]
+        classify: bool,
+
+        /// Max images to classify when no IDs are given, newest first
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// Show or change NSFW settings
+    Settings {
+        /// List flagged generations and show them (blurred) in the gallery (on/off)
+        #[arg(long, value_parser = parse_on_off)]
+        show: Option<bool>,
+
+        /// Flag new generations with a rating tag in the prompt (on/off)
+        #[arg(long, value_parser = parse_on_off)]
+        from_prompt: Option<bool>,
+
+        /// Classify each new image with the caption vision model (on/off)
+        #[arg(long, value_parser = parse_on_off)]
+        classify: Option<bool>,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum SelfhostedAction {
+    /// Show server health and the loaded model
+    Status,
+
+    /// Load (switch to) a model without generating
+    Use {
+        /// Server model name (animagine, pony, noobai)
+        model: String,
+    },
+
+    /// Load a model and run one tiny pass
+    Warmup {
+        /// Model to warm up (default: the loaded one)
+        #[arg(short, long)]
+        model: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum TagsAction {
+    /// List all tags with counts (the default)
+    List,
+
+    /// Rename a tag on every generation
+    Rename {
+        /// Current name
+        #[arg(add = ArgValueCompleter::new(complete_tags))]
+        old: String,
+
+        /// New name (must not exist yet; merge into an existing tag instead)
+        new: String,
+    },
+
+    /// Fold one or more tags into another: `merge a b into c`
+    Merge {
+        /// Source tags, then `into`, then the target tag
+        #[arg(required = true, num_args = 3.., value_name = "TAG", add = ArgValueCompleter::new(complete_tags))]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum CollectionAction {
+    /// Create a new collection
+    Create {
+        /// Collection name, or a path like "rpg/chapter1" to nest it
+        name: String,
+
+        /// Description
+        #[arg(short, long)]
+        description: Option<String>,
+
+        /// Make a smart collection (saved search) instead of a folder
+        #[arg(long, requires = "filter")]
+        smart: bool,
+
+        /// Filter expression for --smart, e.g. "model=noobai AND tag=character AND starred"
+        #[arg(long, requires = "smart")]
+        filter: Option<String>,
+    },
+
+    /// List all collections
+    List {
+        /// List deleted collections (restorable until maintenance purges them)
+        #[arg(long)]
+        deleted: bool,
+    },
+
+    /// List the generations in a collection (smart collections are evaluated now)
+    Show {
+        /// Collection name
+        name: String,
+
+        /// Maximum results
+        #[arg(short, long, default_value = "20")]
+        limit: i64,
+    },
+
+    /// Add generations to a collection
+    Add {
+        /// Generation IDs
+        ids: Vec<i64>,
+
+        /// Collection name
+        #[arg(short, long)]
+        collection: String,
+    },
+
+    /// Remove generations from a collection
+    Remove {
+        /// Generation IDs
+        ids: Vec<i64>,
+
+        /// Collection name
+        #[arg(short, long)]
+        collection: String,
+    },
+
+    /// Move generations to the front of a collection's order, in the order given
+    Order {
+        /// Collection name
+        name: String,
+
+        /// Generation IDs
+        #[arg(required = true)]
+        ids: Vec<i64>,
+    },
+
+    /// Set the cover generation of a collection
+    Cover {
+        /// Collection name
+        name: String,
+
+        /// Generation ID (must be in the collection)
+        #[arg(required_unless_present = "clear")]
+        id: Option<i64>,
+
+        /// Go back to using the first generation in order
+        #[arg(long, conflicts_with = "id")]
+        clear: bool,
+    },
+
+    /// Copy a collection's images to a directory in collection order
+    Export {
+        /// Collection name
+        name: String,
+
+        /// Output directory
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Prefix files with their zero-padded position (001-, 002-, ...)
+        #[arg(long)]
+        numbered: bool,
+
+        /// Write metadata.json sidecar files
+        #[arg(long)]
+        with_metadata: bool,
+
+        /// Also write a static HTML gallery (index.html + thumbs/)
+        #[arg(long)]
+        html: bool,
+
+        /// Write XMP sidecar files (keywords, description, rating) for photo managers
+        #[arg(long)]
+        xmp: bool,
+
+        /// Scale images down so their longest side is at most this many pixels
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        max_size: Option<u32>,
+
+        /// Convert images to png, jpg or webp
+        #[arg(long)]
+        format: Option<ExportFormat>,
+
+        /// JPEG quality for converted images (1-100)
+        #[arg(long, default_value = "90", value_parser = clap::value_parser!(u8).range(1..=100))]
+        quality: u8,
+
+        /// Re-encode images so they carry no embedded metadata (provenance, EXIF, PNG text)
+        #[arg(long)]
+        strip_metadata: bool,
+
+        /// Name files from a template, e.g. "{date}_{model}_{slug}_{id}"
+        #[arg(long, conflicts_with = "numbered")]
+        name_template: Option<NameTemplate>,
+    },
+
+    /// Delete a collection (restorable with `collection restore`)
+    Delete {
+        /// Collection name
+        name: String,
+
+        /// Confirm deleting a collection with many memberships
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Bring back a deleted collection, with the subcollections deleted along with it
+    Restore {
+        /// Collection name
+        name: String,
+    },
+}
+
+pub fn run(cmd: Commands) -> Result<()> {
+    // Ensure directories exist
+    archive::ensure_dirs()?;
+
+    // Open database
+    let db = Database::open(&archive::db_path())?;
+
+    match cmd {
+        Commands::Generate {
+            prompt,
+            file,
+            prompt_clipboard,
+            model,
+            tags,
+            reference,
+            ref_clipboard,
+            copy_to,
+            name_template,
+            negative,
+            ratio,
+            ip_scale,
+            preset,
+            cfg,
+            auto_negative,
+            auto_title,
+            count_per_call,
+            ref_resize,
+            ref_crop,
+            ref_rembg,
+            transparent,
+        } => {
+            let prompt_text = if let Some(p) = prompt {
+                p
+            } else if let Some(f) = file {
+                std::fs::read_to_string(&f).context("Failed to read prompt file")?
+            } else if prompt_clipboard {
+                clipboard::read_text()?
+            } else {
+                anyhow::bail!("One of --prompt, --file or --prompt-clipboard is required");
+            };
+
+            let tag_list: Vec<String> = tags
+                .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default();
+
+            let mut ref_paths: Vec<String> = reference
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect();
+            if ref_clipboard {
+                ref_paths.push(clipboard::store_image_as_reference()?.to_string_lossy().to_string());
+            }
+
+            let (width, height) = resolve_ratio(ratio.as_deref())?;
+
+            let mut tag_list = tag_list;
+            let mut req = GenerationRequest {
+                model: model.unwrap_or_default(),
+                prompt: prompt_text,
+                reference_paths: ref_paths,
+                negative_prompt: negative,
+                width,
+                height,
+                ip_scale,
+                cfg_scale: cfg,
+                auto_negative,
+                num_images: count_per_call,
+                inpaint: None,
+                parent_id: None,
+                title: None,
+                auto_title,
+                seed: None,
+                transparent,
+            };
+            if let Some(name) = preset {
+                let preset = db
+                    .get_preset(&name)?
+                    .ok_or_else(|| anyhow::anyhow!("Preset '{}' not found", name))?;
+                preset.apply(&mut req, &mut tag_list);
+            }
+            if req.model.is_empty() {
+                req.model = "gemini-flash".to_string();
+            }
+
+            let prep = refprep::RefPrep { resize: ref_resize, crop: ref_crop, rembg: ref_rembg };
+
+            // Run async generation
+            let rt = generation_runtime()?;
+            rt.block_on(async {
+                req.reference_paths = preprocess_references(&db, &req.reference_paths, &prep).await?;
+                generate_image(&db, &req, &tag_list, copy_to.as_deref(), name_template.as_ref()).await
+            })?;
+        }
+
+        Commands::Video { prompt, file, model, image, duration, ratio, negative, seed, tags } => {
+            let prompt_text = if let Some(p) = prompt {
+                p
+            } else if let Some(f) = file {
+                std::fs::read_to_string(&f).context("Failed to read prompt file")?
+            } else {
+                anyhow::bail!("One of --prompt or --file is required");
+            };
+            let info = VideoModelInfo::find(&model).ok_or_else(|| {
+                let ids: Vec<&str> = VideoModelInfo::all().iter().map(|m| m.id).collect();
+                anyhow::anyhow!("Unknown video model: {} (available: {})", model, ids.join(", "))
+            })?;
+            providers::check_credentials(info.id)?;
+
+            let tag_list: Vec<String> = tags
+                .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default();
+            let parent_id = match image.as_deref().and_then(|spec| spec.strip_prefix("gen:")) {
+                Some(id) => Some(id.parse().with_context(|| format!("Invalid generation ID in 'gen:{}'", id))?),
+                None => None,
+            };
+            let req = VideoRequest {
+                model: info.id.to_string(),
+                prompt: prompt_text.trim().to_string(),
+                image_path: image.map(|spec| workflow::resolve_reference(&db, &spec)).transpose()?,
+                negative_prompt: negative,
+                duration,
+                aspect_ratio: ratio,
+                seed,
+                parent_id,
+            };
+
+            let seconds = duration.unwrap_or(info.durations[0]);
+            let estimate = match workflow::current_price(&db, info.id)? {
+                Some(rate) => rate * seconds as f64,
+                None => info.estimate_cost(Some(seconds)),
+            };
+            println!("Generating a {}s clip with {} (est. ${:.2})...", seconds, info.id, estimate);
+            let rt = generation_runtime()?;
+            let (id, gen) = rt.block_on(workflow::perform_video(&db, &req, &tag_list, JobSource::Cli, &print_queue_progress))?;
+            notify::send(JobSource::Cli, &notify::Message::generated(&gen));
+
+            println!("Generated: {} (ID: {})", gen.image_path, id);
+            match (gen.duration_seconds, gen.fps) {
+                (Some(d), Some(fps)) => println!("Length: {:.1}s at {:.0} fps", d, fps),
+                (Some(d), None) => println!("Length: {:.1}s", d),
+                _ => {}
+            }
+            if let Some(c) = gen.cost_estimate_usd {
+                println!("Cost: ${:.4}", c);
+            }
+        }
+
+        Commands::List {
+            limit,
+            tag,
+            model,
+            starred,
+            since,
+            until,
+            min_width,
+            min_height,
+            orientation,
+            has_reference,
+            media,
+            sort,
+            desc,
+            include_nsfw,
+            color,
+            tolerance,
+        } => {
+            if tolerance.is_some_and(|t| t < 0.0) {
+                anyhow::bail!("--tolerance can't be negative");
+            }
+            let parse_date = |s: Option<String>| -> Result<Option<String>> {
+                match s {
+                    Some(s) => models::parse_since(&s).map_err(|e| anyhow::anyhow!(e)),
+                    None => Ok(None),
+                }
+            };
+            let filter = ListFilter {
+                limit: Some(limit),
+                tags: tag.map(|t| vec![t]),
+                model,
+                starred_only: starred,
+                since: parse_date(since)?,
+                until: parse_date(until)?,
+                min_width,
+                min_height,
+                orientation,
+                has_reference,
+                media_type: media,
+                sort,
+                sort_desc: desc,
+                hide_nsfw: !(include_nsfw || nsfw::load_settings().show),
+                colors: (!color.is_empty()).then(|| color.iter().map(|c| c.to_string()).collect()),
+                color_tolerance: tolerance,
+                ..Default::default()
+            };
+
+            let generations = db.list_generations(&filter)?;
+            print_generations(&generations);
+        }
+
+        Commands::Random { count, tag, model, starred, paths, include_nsfw } => {
+            let filter = ListFilter {
+                limit: Some(count),
+                tags: tag.map(|t| vec![t]),
+                model,
+                starred_only: starred,
+                hide_nsfw: !(include_nsfw || nsfw::load_settings().show),
+                sort: Some(SortKey::Random),
+                ..Default::default()
+            };
+
+            let generations = db.list_generations(&filter)?;
+            if paths {
+                for gen in &generations {
+                    println!("{}", gen.image_path);
+                }
+            } else {
+                print_generations(&generations);
+            }
+        }
+
+        Commands::Search { query, limit, include_nsfw } => {
+            let generations = db.search_generations(&query, limit, include_nsfw || nsfw::load_settings().show)?;
+            print_generations(&generations);
+        }
+
+        Commands::Show { id } => {
+            let gen = db
+                .get_generation(id)?
+                .ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+
+            println!("ID: {}", gen.id);
+            println!("Slug: {}", gen.slug);
+            println!("Model: {} ({})", gen.model, gen.provider);
+            println!("Date: {}", gen.date);
+            println!("Time: {}", gen.timestamp);
+            println!("Path: {}", gen.image_path);
+            if let Some(t) = gen.generation_time_seconds {
+                println!("Generation time: {:.1}s", t);
+            }
+            if let Some(c) = gen.cost_estimate_usd {
+                println!("Cost: ${:.3}", c);
+            }
+            if let Some(s) = &gen.seed {
+                println!("Seed: {}", s);
+            }
+            if let (Some(w), Some(h)) = (gen.width, gen.height) {
+                println!("Dimensions: {}x{}", w, h);
+            }
+            if gen.media_type == MediaType::Video {
+                match (gen.duration_seconds, gen.fps) {
+                    (Some(d), Some(fps)) => println!("Video: {:.1}s at {:.2} fps", d, fps),
+                    (Some(d), None) => println!("Video: {:.1}s", d),
+                    _ => println!("Video: yes"),
+                }
+            }
+            if gen.animated {
+                println!("Animated: yes");
+            }
+            if gen.starred {
+                println!("Starred: yes");
+            }
+            if gen.locked {
+                println!("Locked: yes");
+            }
+            if gen.nsfw {
+                println!("NSFW: yes");
+            }
+            if !gen.tags.is_empty() {
+                println!("Tags: {}", gen.tags.join(", "));
+            }
+            if !gen.palette.is_empty() {
+                println!("Palette: {}", format_palette(&gen.palette));
+            }
+            if let Some(requested_by) = &gen.requested_by {
+                println!("Requested by: {}", requested_by);
+            }
+
+            // Show reference images
+            let refs = db.get_references_for_generation(id)?;
+            if !refs.is_empty() {
+                println!("References ({}):", refs.len());
+                for r in &refs {
+                    println!("  - {}", r.path);
+                }
+            }
+
+            let assets = db.get_assets_for_generation(id)?;
+            if !assets.is_empty() {
+                println!("Assets ({}):", assets.len());
+                for a in &assets {
+                    let note = a.note.as_deref().map(|n| format!(" ({})", n)).unwrap_or_default();
+                    println!("  {:>4} {:<8} {}{}", a.id, a.kind.to_string(), a.path, note);
+                }
+            }
+
+            println!("\nPrompt:\n{}", gen.prompt);
+
+            // Settings beyond the ones above; unset and default values are left out
+            if let Some(params) = gen.params.as_ref().and_then(|p| p.as_object()) {
+                let shown: Vec<_> = params
+                    .iter()
+                    .filter(|(key, _)| !matches!(key.as_str(), "prompt" | "model" | "reference_paths"))
+                    .filter(|(_, value)| match value {
+                        serde_json::Value::Null | serde_json::Value::Bool(false) => false,
+                        serde_json::Value::String(s) => !s.is_empty(),
+                        serde_json::Value::Array(a) => !a.is_empty(),
+                        _ => true,
+                    })
+                    .collect();
+                if !shown.is_empty() {
+                    println!("\nParameters:");
+                    for (key, value) in shown {
+                        match value.as_str() {
+                            Some(s) => println!("  {}: {}", key, s),
+                            None => println!("  {}: {}", key, value),
+                        }
+                    }
+                }
+            }
+
+            if let Some(caption) = &gen.caption {
+                println!("\nCaption:\n{}", caption);
+            }
+        }
+
+        Commands::View { ids, width, height } => {
+            view_images(&db, &ids, width, height)?;
+        }
+
+        Commands::Compare { ids, output, columns, size } => {
+            let mut generations = vec![];
+            for id in ids {
+                match db.get_generation(id)? {
+                    Some(gen) => generations.push(gen),
+                    None => eprintln!("Generation {} not found, skipping", id),
+                }
+            }
+
+            let n = generations.len() as u32;
+            let columns = columns.unwrap_or(if n <= 4 { n } else { (n as f64).sqrt().ceil() as u32 });
+            let sheet = compare::contact_sheet(&generations, columns, size)?;
+            sheet.save(&output).context("Failed to write contact sheet")?;
+            println!("Wrote {} ({}x{}, {} images)", output.display(), sheet.width(), sheet.height(), n);
+        }
+
+        Commands::Reproduce { id, tags } => {
+            let original = db
+                .get_generation(id)?
+                .ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+            let req = workflow::reproduction_request(&original)?;
+            match req.seed {
+                Some(seed) => println!("Reproducing {} with {} (seed {})...", id, req.model, seed),
+                None => println!(
+                    "Reproducing {} with {} (no seed recorded, so expect a different image)...",
+                    id, req.model
+                ),
+            }
+            providers::check_credentials(&req.model)?;
+            let tag_list: Vec<String> = tags
+                .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default();
+
+            let rt = generation_runtime()?;
+            let generations = rt.block_on(workflow::perform_generation(
+                &db,
+                &req,
+                &tag_list,
+                JobSource::Cli,
+                &print_queue_progress,
+            ))?;
+            let Generated { id: new_id, generation: reproduced, .. } = generations
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("No image returned"))?;
+
+            let similarity = compare::hash_similarity(
+                compare::perceptual_hash(Path::new(&original.image_path))?,
+                compare::perceptual_hash(Path::new(&reproduced.image_path))?,
+            );
+            let identical = archive::hash_file(Path::new(&original.image_path))?
+                == archive::hash_file(Path::new(&reproduced.image_path))?;
+            println!("Original:   {} (seed {})", id, original.seed.as_deref().unwrap_or("-"));
+            println!("Reproduced: {} (seed {})", new_id, reproduced.seed.as_deref().unwrap_or("-"));
+            if identical {
+                println!("Similarity: 100% (byte-identical)");
+            } else {
+                println!("Similarity: {:.1}% (perceptual hash)", similarity * 100.0);
+            }
+        }
+
+        Commands::Tag { id, tags } => {
+            let tag_list: Vec<String> = tags.split(',').map(|s| s.trim().to_string()).collect();
+            db.add_tags(id, &tag_list)?;
+            println!("Added tags to generation {}", id);
+        }
+
+        Commands::Untag { id, tag } => {
+            db.remove_tag(id, &tag)?;
+            println!("Removed tag '{}' from generation {}", tag, id);
+        }
+
+        Commands::Star { id } => {
+            let starred = db.toggle_starred(id)?;
+            if starred {
+                println!("Starred generation {}", id);
+            } else {
+                println!("Unstarred generation {}", id);
+            }
+        }
+
+        Commands::Delete { id } => {
+            if let Some(path) = db.permanently_delete_generation(id)? {
+                archive::delete_image(std::path::Path::new(&path))?;
+                archive::delete_assets(id)?;
+                println!("Deleted generation {}", id);
+            } else {
+                println!("Generation {} not found", id);
+            }
+        }
+
+        Commands::Lock { id } => {
+            if db.set_locked(id, true)? {
+                println!("Locked generation {}", id);
+            } else {
+                println!("Generation {} not found", id);
+            }
+        }
+
+        Commands::Unlock { id } => {
+            if db.set_locked(id, false)? {
+                println!("Unlocked generation {}", id);
+            } else {
+                println!("Generation {} not found", id);
+            }
+        }
+
+        Commands::Nsfw { action } => match action {
+            NsfwAction::Mark { ids } => set_nsfw_flags(&db, &ids, true)?,
+            NsfwAction::Unmark { ids } => set_nsfw_flags(&db, &ids, false)?,
+            NsfwAction::Scan { ids, classify, limit } => scan_nsfw(&db, &ids, classify, limit)?,
+            NsfwAction::Settings { show, from_prompt, classify } => {
+                let mut settings = nsfw::load_settings();
+                if show.is_some() || from_prompt.is_some() || classify.is_some() {
+                    settings.show = show.unwrap_or(settings.show);
+                    settings.from_prompt = from_prompt.unwrap_or(settings.from_prompt);
+                    settings.classify = classify.unwrap_or(settings.classify);
+                    nsfw::save_settings(&settings)?;
+                }
+                let state = |on: bool| if on { "on" } else { "off" };
+                println!("show:        {}", state(settings.show));
+                println!("from-prompt: {}", state(settings.from_prompt));
+                println!("classify:    {}", state(settings.classify));
+            }
+        },
+
+        Commands::Update {
+            id,
+            title,
+            prompt,
+            prompt_file,
+            model,
+            reference,
+            tags,
+        } => {
+            // Verify generation exists
+            db.get_generation(id)?
+                .ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+
+            let mut updates = vec![];
+
+            // Update title
+            if let Some(t) = title {
+                db.update_title(id, Some(&t))?;
+                updates.push("title");
+            }
+
+            // Update prompt
+            if let Some(p) = prompt {
+                db.update_prompt(id, &p)?;
+                updates.push("prompt");
+            } else if let Some(f) = prompt_file {
+                let p = std::fs::read_to_string(&f).context("Failed to read prompt file")?;
+                db.update_prompt(id, &p)?;
+                updates.push("prompt");
+            }
+
+            // Update model
+            if let Some(m) = model {
+                let model_info = ModelInfo::find(&m);
+                let provider = model_info
+                    .as_ref()
+                    .map(|mi| mi.provider.to_string())
+                    .unwrap_or_else(|| "unknown".to_string());
+                db.update_model(id, &ModelInfo::canonical_id(&m), &provider)?;
+                updates.push("model");
+            }
+
+            // Add tags
+            if let Some(t) = tags {
+                let tag_list: Vec<String> = t.split(',').map(|s| s.trim().to_string()).collect();
+                db.add_tags(id, &tag_list)?;
+                updates.push("tags");
+            }
+
+            // Add reference images
+            if !reference.is_empty() {
+                for ref_path in &reference {
+                    let ref_path = workflow::resolve_reference(&db, &ref_path.to_string_lossy())?;
+                    let (hash, stored_path) = archive::store_reference(Path::new(&ref_path))?;
+                    let ref_id = db.get_or_create_reference(&hash, stored_path.to_str().unwrap())?;
+                    db.link_reference(id, ref_id)?;
+                }
+                updates.push("references");
+            }
+
+            if updates.is_empty() {
+                println!("No updates specified for generation {}", id);
+            } else {
+                println!("Updated generation {}: {}", id, updates.join(", "));
+            }
+        }
+
+        Commands::Completions { shell } => {
+            let shells = clap_complete::env::Shells::builtins();
+            let Some(completer) = shells.completer(&shell) else {
+                anyhow::bail!(
+                    "Unsupported shell: {} (expected one of: {})",
+                    shell,
+                    shells.names().collect::<Vec<_>>().join(", ")
+                );
+            };
+            completer.write_registration("COMPLETE", "pixery", "pixery", "pixery", &mut std::io::stdout())?;
+        }
+
+        Commands::Tui => {
+            tui::run(&db)?;
+        }
+
+        Commands::Enhance { prompt, file, model, backend, llm, generate, tags, ratio } => {
+            let prompt_text = if let Some(p) = prompt {
+                p
+            } else if let Some(f) = file {
+                std::fs::read_to_string(&f).context("Failed to read prompt file")?
+            } else {
+                anyhow::bail!("Either --prompt or --file is required");
+            };
+
+            let mut settings = enhance::load_settings();
+            if let Some(b) = backend {
+                settings.backend = b.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+                settings.model = enhance::EnhanceSettings::default_model(settings.backend).to_string();
+            }
+            if let Some(m) = llm {
+                settings.model = m;
+            }
+
+            let rt = generation_runtime()?;
+            let enhanced = rt.block_on(enhance::enhance_prompt(&prompt_text, &model, &settings))?;
+            println!("{}", enhanced);
+
+            if generate {
+                let tag_list: Vec<String> = tags
+                    .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
+                    .unwrap_or_default();
+                let (width, height) = resolve_ratio(ratio.as_deref())?;
+                let req = GenerationRequest {
+                    model,
+                    prompt: enhanced,
+                    width,
+                    height,
+                    ..Default::default()
+                };
+                println!();
+                rt.block_on(generate_image(&db, &req, &tag_list, None, None))?;
+            }
+        }
+
+        Commands::Lint { prompt, file, model } => {
+            let prompt_text = if let Some(p) = prompt {
+                p
+            } else if let Some(f) = file {
+                std::fs::read_to_string(&f).context("Failed to read prompt file")?
+            } else {
+                anyhow::bail!("Either --prompt or --file is required");
+            };
+
+            if PromptingGuide::for_model(&model).is_none() {
+                println!("No prompting guide for {}; nothing to check", model);
+                return Ok(());
+            }
+            let issues = lint::lint_prompt(&model, &prompt_text);
+            if issues.is_empty() {
+                println!("No issues for {}", model);
+            } else {
+                for issue in &issues {
+                    println!("  - {}", issue);
+                }
+                anyhow::bail!("{} issue(s) found (see 'pixery models {} --guide')", issues.len(), model);
+            }
+        }
+
+        Commands::Models { model, guide, refresh } => {
+            if refresh {
+                let before = providers::fal::load_catalog().models.len();
+                let rt = tokio::runtime::Runtime::new()?;
+                let catalog = rt.block_on(providers::fal::refresh_catalog())?;
+                let priced = catalog.models.iter().filter(|m| m.unit_price.is_some()).count();
+                println!(
+                    "Cached {} fal.ai models ({} priced, {} before) in {}\n",
+                    catalog.models.len(),
+                    priced,
+                    before,
+                    providers::fal::catalog_path().display()
+                );
+            }
+            match (model, guide) {
+                // pixery models MODEL --guide
+                (Some(m), true) => {
+                    let m = ModelInfo::canonical_id(&m);
+                    if let Some(g) = PromptingGuide::for_model(&m) {
+                        println!("{}", g.format());
+                    } else {
+                        // No guide available, but model might exist
+                        if ModelInfo::find(&m).is_some() {
+                            println!("No prompting guide available for '{}'. This model uses standard prompting.", m);
+                        } else {
+                            eprintln!("Unknown model: {}", m);
+                            eprintln!("\nAvailable models:");
+                            for info in ModelInfo::all() {
+                                eprintln!("  {}", info.id);
+                            }
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                // pixery models MODEL (no --guide)
+                (Some(m), false) => {
+                    if let Some(info) = ModelInfo::find(&m) {
+                        if info.id != m {
+                            println!("Alias: {} (models.json)", m);
+                        }
+                        println!("Model: {}", info.id);
+                        println!("Display name: {}", info.display_name);
+                        println!("Provider: {}", info.provider);
+                        let cost = workflow::price_per_image(&db, &info.id)?.unwrap_or(info.cost_per_image);
+                        println!("Cost: ${:.3}/image", cost);
+                        println!("Max references: {}", if info.max_refs == 0 { "none (text-to-image only)".to_string() } else { info.max_refs.to_string() });
+                        if let Some(endpoint) = custom_models::load().endpoint_for(&info.id) {
+                            println!("Endpoint: {} (custom, models.json)", endpoint);
+                        }
+                        print_quota(&db, &info.id)?;
+
+                        if PromptingGuide::for_model(&m).is_some() {
+                            println!("\nTip: Use --guide for prompting instructions");
+                        }
+                    } else if let Some(info) = VideoModelInfo::find(&m) {
+                        let lengths: Vec<String> = info.durations.iter().map(|d| format!("{}s", d)).collect();
+                        println!("Model: {} (video)", info.id);
+                        println!("Display name: {}", info.display_name);
+                        println!("Provider: {}", info.provider);
+                        let cost = workflow::current_price(&db, info.id)?.unwrap_or(info.cost_per_second);
+                        println!("Cost: ${:.3}/second", cost);
+                        println!("Lengths: {}", lengths.join(", "));
+                        println!("Aspect ratios: {}", info.aspect_ratios.join(", "));
+                        println!("Start frame: {}", if info.image_to_video { "optional (--image)" } else { "no" });
+                        print_quota(&db, info.id)?;
+                    } else {
+                        eprintln!("Unknown model: {}", m);
+                        eprintln!("\nAvailable models:");
+                        for info in ModelInfo::all() {
+                            eprintln!("  {}", info.id);
+                        }
+                        std::process::exit(1);
+                    }
+                }
+                // pixery models --guide (no model specified)
+                (None, true) => {
+                    println!("Available prompting guides:");
+                    println!();
+                    for g in PromptingGuide::all() {
+                        println!("  {} ({})", g.model_pattern, g.style);
+                    }
+                    println!();
+                    println!("Usage: pixery models MODEL --guide");
+                }
+                // pixery models (list all)
+                (None, false) => {
+                    let models = workflow::priced_models(&db)?;
+                    println!("{:<30} {:<10} {:>8} {:>8}", "MODEL ID", "PROVIDER", "COST", "REFS");
+                    println!("{}", "-".repeat(60));
+                    for m in models {
+                        let refs_str = if m.max_refs == 0 {
+                            "-".to_string()
+                        } else {
+                            format!("{}", m.max_refs)
+                        };
+                        println!(
+                            "{:<30} {:<10} ${:>6.3} {:>8}",
+                            m.id, m.provider, m.cost_per_image, refs_str
+                        );
+                    }
+
+                    println!("\n{:<30} {:<10} {:>8} {:>8}", "VIDEO MODEL ID", "PROVIDER", "COST/S", "LENGTH");
+                    println!("{}", "-".repeat(60));
+                    for m in VideoModelInfo::all() {
+                        let lengths: Vec<String> = m.durations.iter().map(|d| format!("{}s", d)).collect();
+                        let cost = workflow::current_price(&db, m.id)?.unwrap_or(m.cost_per_second);
+                        println!(
+                            "{:<30} {:<10} ${:>6.3} {:>8}",
+                            m.id, m.provider, cost, lengths.join("/")
+                        );
+                    }
+
+                    let config = custom_models::load();
+                    if !config.aliases.is_empty() {
+                        println!("\nAliases ({}):", custom_models::config_path().display());
+                        for (alias, target) in &config.aliases {
+                            println!("  {:<20} -> {}", alias, target);
+                        }
+                    }
+
+                    let quotas = workflow::model_quotas(&db)?;
+                    if !quotas.is_empty() {
+                        println!("\nDaily quotas:");
+                        for quota in &quotas {
+                            println!(
+                                "  {:<28} {:>4}/{:<4} used today, {} left",
+                                quota.model, quota.used, quota.limit, quota.remaining
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        Commands::Tags { action } => match action.unwrap_or(TagsAction::List) {
+            TagsAction::List => {
+                let tags = db.list_tags()?;
+                if tags.is_empty() {
+                    println!("No tags yet");
+                } else {
+                    let (plain, namespaced): (Vec<_>, Vec<_>) = tags.into_iter().partition(|t| t.namespace.is_none());
+                    println!("{:<30} {:>8}", "TAG", "COUNT");
+                    println!("{}", "-".repeat(40));
+                    for t in &plain {
+                        println!("{:<30} {:>8}", t.name, t.count);
+                    }
+
+                    // Namespaced tags grouped under their namespace, most used first within it
+                    let mut groups: std::collections::BTreeMap<String, Vec<_>> = std::collections::BTreeMap::new();
+                    for t in namespaced {
+                        groups.entry(t.namespace.clone().unwrap_or_default()).or_default().push(t);
+                    }
+                    for (namespace, tags) in groups {
+                        println!();
+                        println!("{:<30} {:>8}", format!("{}:*", namespace), format!("{} tags", tags.len()));
+                        for t in tags {
+                            let leaf = t.name.split_once(':').map(|(_, leaf)| leaf).unwrap_or(&t.name);
+                            println!("  {:<28} {:>8}", leaf, t.count);
+                        }
+                    }
+                }
+            }
+            TagsAction::Rename { old, new } => {
+                let count = db.rename_tag(&old, &new)?;
+                println!("Renamed '{}' to '{}' ({} generation(s))", old, new, count);
+            }
+            TagsAction::Merge { args } => {
+                let (target, sources) = match args.split_last() {
+                    Some((target, rest)) if rest.last().map(String::as_str) == Some("into") => {
+                        (target, &rest[..rest.len() - 1])
+                    }
+                    _ => anyhow::bail!("Usage: pixery tags merge TAG [TAG ...] into TARGET"),
+                };
+                if sources.is_empty() {
+                    anyhow::bail!("Usage: pixery tags merge TAG [TAG ...] into TARGET");
+                }
+                let gained = db.merge_tags(sources, target)?;
+                println!("Merged {} into '{}' ({} generation(s) gained it)", sources.join(", "), target, gained);
+            }
+        },
+
+        Commands::TagBulk { filter, add, remove, dry_run } => {
+            let split = |tags: Option<String>| -> Vec<String> {
+                tags.map(|t| t.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                    .unwrap_or_default()
+            };
+            let (add, remove) = (split(add), split(remove));
+            let filter = ListFilter::parse_expr(&filter).map_err(|e| anyhow::anyhow!(e))?;
+            if dry_run {
+                println!("{} generation(s) match", db.count_generations(&filter)?);
+            } else {
+                if add.is_empty() && remove.is_empty() {
+                    anyhow::bail!("Nothing to do: pass --add and/or --remove");
+                }
+                let report = db.bulk_tag(&filter, &add, &remove)?;
+                println!(
+                    "{} generation(s) matched: {} tag(s) added, {} removed",
+                    report.matched, report.added, report.removed
+                );
+                if report.locked > 0 {
+                    println!("{} locked generation(s) kept their tags", report.locked);
+                }
+            }
+        }
+
+        Commands::Cost { since, export, usd } => {
+            let since_date = models::parse_since(&since).map_err(|e| anyhow::anyhow!(e))?;
+            let summary = db.get_cost_summary(since_date.as_deref())?;
+            let currency = if usd {
+                DisplayCurrency::default()
+            } else {
+                tokio::runtime::Runtime::new()?.block_on(currency::display())
+            };
+
+            if let Some(path) = export {
+                export_cost_report(&db, since_date.as_deref(), &path, &currency)?;
+            }
+
+            println!("Cost Summary");
+            println!("============");
+            if currency.is_usd() {
+                println!("Total: {}", currency.format(summary.total_usd, 2));
+            } else {
+                println!(
+                    "Total: {} (${:.2} at {:.4} {}/USD, {})",
+                    currency.format(summary.total_usd, 2),
+                    summary.total_usd,
+                    currency.rate,
+                    currency.code,
+                    currency.source
+                );
+            }
+            println!("Generations: {}", summary.count);
+            println!();
+
+            if !summary.by_model.is_empty() {
+                println!("By Model:");
+                for (model, cost) in &summary.by_model {
+                    println!("  {:<30} {}", model, currency.format(*cost, 2));
+                }
+                println!();
+            }
+
+            if !summary.by_collection.is_empty() {
+                println!("By Collection:");
+                for (collection, cost) in &summary.by_collection {
+                    println!("  {:<30} {}", collection, currency.format(*cost, 2));
+                }
+                println!();
+            }
+
+            if !summary.by_tag.is_empty() {
+                println!("By Tag (top 10):");
+                for (tag, cost) in summary.by_tag.iter().take(10) {
+                    println!("  {:<30} {}", tag, currency.format(*cost, 2));
+                }
+                println!();
+            }
+
+            if !summary.by_day.is_empty() {
+                println!("By Day (last 10):");
+                for (day, cost) in summary.by_day.iter().take(10) {
+                    println!("  {} {}", day, currency.format(*cost, 2));
+                }
+            }
+        }
+
+        Commands::ReadOnly { state } => {
+            let mut settings = readonly::load_settings();
+            if let Some(enabled) = state {
+                settings.enabled = enabled;
+                readonly::save_settings(&settings)?;
+            }
+            println!(
+                "GUI read-only mode {}{}",
+                if settings.enabled { "on" } else { "off" },
+                if state.is_some() { " (takes effect next time the GUI starts)" } else { "" }
+            );
+        }
+
+        Commands::Profiles { action } => match action.unwrap_or(ProfilesAction::List) {
+            ProfilesAction::List => {
+                let config = profiles::load_config();
+                for name in config.names() {
+                    let marker = if name == profiles::current_name() { "*" } else { " " };
+                    let root = config.root_of(&name).unwrap_or_default();
+                    let description = config.profiles.get(&name).and_then(|p| p.description.as_deref());
+                    print!("{} {:<12} {}", marker, name, root.display());
+                    if let Some(description) = description {
+                        print!("  ({})", description);
+                    }
+                    println!();
+                }
+                if profiles::current_name() != config.active_name() {
+                    println!("\nActive profile: {} (this run uses --archive {})", config.active_name(), profiles::current_name());
+                }
+            }
+            ProfilesAction::Switch { name } => {
+                let mut config = profiles::load_config();
+                config.switch(&name)?;
+                profiles::save_config(&config)?;
+                println!("Switched to {} ({})", name, config.root_of(&name).unwrap_or_default().display());
+            }
+            ProfilesAction::Add { name, root, description } => {
+                let name = profiles::parse_name(&name)?;
+                let mut config = profiles::load_config();
+                if config.root_of(&name).is_some() {
+                    anyhow::bail!("Profile '{}' already exists", name);
+                }
+                let root = std::path::absolute(&root).context("Invalid archive root")?;
+                let shown = root.display().to_string();
+                config.profiles.insert(name.clone(), profiles::Profile { root, description });
+                profiles::save_config(&config)?;
+                println!("Added profile {} ({})", name, shown);
+                println!("Use it with `pixery --archive {} ...` or `pixery profiles switch {}`", name, name);
+            }
+            ProfilesAction::Remove { name } => {
+                let mut config = profiles::load_config();
+                if name == profiles::DEFAULT_PROFILE {
+                    anyhow::bail!("The default profile can't be removed");
+                }
+                let profile = config
+                    .profiles
+                    .remove(&name)
+                    .ok_or_else(|| anyhow::anyhow!("No archive profile '{}'", name))?;
+                if config.active.as_deref() == Some(name.as_str()) {
+                    config.active = None;
+                    println!("Switched back to the default profile");
+                }
+                profiles::save_config(&config)?;
+                println!("Removed profile {} (files in {} are untouched)", name, profile.root.display());
+            }
+        },
+
+        Commands::Edit { id, with, yes } => {
+            let original = db.get_generation(id)?.ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+            if original.media_type == MediaType::Video {
+                anyhow::bail!("Generation {} is a video; only images can be edited", id);
+            }
+            let settings = editor::load_settings();
+            let name = with.unwrap_or_else(|| settings.default_editor().to_string());
+            let (file, reused) = editor::working_copy(&original)?;
+            editor::launch(&settings, &name, &file)?;
+            if reused {
+                println!("Editing {} in {} (working copy from an earlier edit)", file.display(), name);
+            } else {
+                println!("Editing {} in {}", file.display(), name);
+            }
+            println!("Watching for saves (Ctrl-C to stop)");
+
+            editor::watch(&file, || {
+                if !yes {
+                    print!("Saved. Import as a child of {}? [Y/n/q] ", id);
+                    std::io::stdout().flush()?;
+                    let mut answer = String::new();
+                    if std::io::stdin().read_line(&mut answer)? == 0 {
+                        println!();
+                        return Ok(false);
+                    }
+                    match answer.trim().to_lowercase().as_str() {
+                        "n" | "no" => return Ok(true),
+                        "q" | "quit" => return Ok(false),
+                        _ => {}
+                    }
+                }
+                match editor::import_edit(&db, &original, &file) {
+                    Ok(imported) if imported.duplicate => {
+                        println!("Unchanged: already archived as ID {}", imported.id);
+                    }
+                    Ok(imported) => {
+                        println!("Imported: {} (ID: {}, child of {})", imported.generation.image_path, imported.id, id);
+                    }
+                    Err(e) => eprintln!("Import failed: {:#}", e),
+                }
+                Ok(true)
+            })?;
+        }
+
+        Commands::Editors { action } => match action.unwrap_or(EditorsAction::List) {
+            EditorsAction::List => {
+                let settings = editor::load_settings();
+                for name in settings.names() {
+                    let marker = if name == settings.default_editor() { "*" } else { " " };
+                    let command = match settings.command_line(&name, Path::new("{file}")) {
+                        Ok(args) => args.join(" "),
+                        Err(_) => "(not available here; add a command)".to_string(),
+                    };
+                    let custom = if settings.commands.contains_key(&name) { "  (custom)" } else { "" };
+                    println!("{} {:<12} {}{}", marker, name, command, custom);
+                }
+            }
+            EditorsAction::Default { name } => {
+                let mut settings = editor::load_settings();
+                if !settings.names().contains(&name) {
+                    anyhow::bail!("Unknown editor '{}' (editors: {})", name, settings.names().join(", "));
+                }
+                settings.default = (name != "default").then(|| name.clone());
+                editor::save_settings(&settings)?;
+                println!("pixery edit now opens images in {}", name);
+            }
+            EditorsAction::Add { name, command } => {
+                let mut settings = editor::load_settings();
+                let shown = command.join(" ");
+                settings.commands.insert(name.clone(), command);
+                editor::save_settings(&settings)?;
+                println!("Editor {}: {}", name, shown);
+            }
+            EditorsAction::Remove { name } => {
+                let mut settings = editor::load_settings();
+                if settings.commands.remove(&name).is_none() {
+                    anyhow::bail!("No command set for editor '{}'", name);
+                }
+                if settings.default.as_deref() == Some(name.as_str()) && !editor::BUILTIN_EDITORS.contains(&name.as_str()) {
+                    settings.default = None;
+                    println!("pixery edit goes back to the system default editor");
+                }
+                editor::save_settings(&settings)?;
+                println!("Removed the command for {}", name);
+            }
+        },
+
+        Commands::Currency { action } => match action.unwrap_or(CurrencyAction::Show) {
+            CurrencyAction::Show => {
+                let settings = currency::load_settings();
+                let shown = tokio::runtime::Runtime::new()?.block_on(currency::display());
+                if settings.code == "USD" {
+                    println!("Costs are shown in USD, as recorded");
+                } else if shown.is_usd() {
+                    println!("Display currency: {} (no exchange rate yet, showing USD)", settings.code);
+                } else {
+                    println!("Display currency: {}", shown.code);
+                    println!("Rate: {:.4} {}/USD ({})", shown.rate, shown.code, shown.source);
+                }
+            }
+            CurrencyAction::Set { code, rate } => {
+                let mut settings = currency::load_settings();
+                settings.code = currency::parse_code(&code)?;
+                settings.manual_rate = rate;
+                currency::save_settings(&settings)?;
+                if settings.code == "USD" {
+                    println!("Costs are shown in USD, as recorded");
+                } else {
+                    let shown = tokio::runtime::Runtime::new()?.block_on(currency::display());
+                    if shown.is_usd() {
+                        println!("Display currency: {}, but no exchange rate yet (try --rate)", settings.code);
+                    } else {
+                        println!("Display currency: {} at {:.4} {}/USD ({})", shown.code, shown.rate, shown.code, shown.source);
+                    }
+                }
+            }
+            CurrencyAction::Refresh => {
+                let shown = tokio::runtime::Runtime::new()?.block_on(currency::refresh())?;
+                if shown.is_usd() {
+                    println!("Costs are shown in USD; nothing to fetch");
+                } else {
+                    println!("{}: {:.4} per USD", shown.code, shown.rate);
+                    if currency::load_settings().manual_rate.is_some() {
+                        println!("(a manual rate is set and used instead; `pixery currency set {}` clears it)", shown.code);
+                    }
+                }
+            }
+        },
+
+        Commands::Pricing { action } => match action.unwrap_or(PricingAction::List { model: None }) {
+            PricingAction::List { model } => {
+                let model = model.map(|m| ModelInfo::canonical_id(&m));
+                let prices = db.list_prices(model.as_deref())?;
+                if prices.is_empty() {
+                    println!("No prices set; models use their built-in price (pixery models)");
+                    return Ok(());
+                }
+                let today = Local::now().format("%Y-%m-%d").to_string();
+                println!("{:<30} {:<12} {:>10}  {:<10} NOTE", "MODEL", "FROM", "COST", "STATUS");
+                for (i, p) in prices.iter().enumerate() {
+                    let next = prices.get(i + 1).filter(|n| n.model == p.model);
+                    let status = if p.effective_from > today {
+                        "upcoming"
+                    } else if next.is_some_and(|n| n.effective_from <= today) {
+                        "replaced"
+                    } else {
+                        "current"
+                    };
+                    let unit = if VideoModelInfo::find(&p.model).is_some() { "/s" } else { "" };
+                    println!(
+                        "{:<30} {:<12} {:>10}  {:<10} {}",
+                        p.model,
+                        p.effective_from,
+                        format!("${:.4}{}", p.cost, unit),
+                        status,
+                        p.note.as_deref().unwrap_or("")
+                    );
+                }
+            }
+            PricingAction::Update { model, cost, from, note, reprice } => {
+                let model = ModelInfo::canonical_id(&model);
+                let unit = if ModelInfo::find(&model).is_some() {
+                    "image"
+                } else if VideoModelInfo::find(&model).is_some() {
+                    "second"
+                } else {
+                    anyhow::bail!("Unknown model: {} (see pixery models)", model);
+                };
+                let from = from.unwrap_or_else(|| Local::now().format("%Y-%m-%d").to_string());
+                db.set_price(&model, cost, &from, note.as_deref())?;
+                println!("{}: ${:.4}/{} from {}", model, cost, unit, from);
+
+                if reprice {
+                    let until = db
+                        .list_prices(Some(&model))?
+                        .into_iter()
+                        .map(|p| p.effective_from)
+                        .find(|date| *date > from);
+                    let n = db.reprice_generations(&model, cost, &from, until.as_deref())?;
+                    match until {
+                        Some(until) => println!("Repriced {} generation(s) dated {} to before {}", n, from, until),
+                        None => println!("Repriced {} generation(s) dated {} or later", n, from),
+                    }
+                }
+            }
+            PricingAction::Remove { model, date } => {
+                let model = ModelInfo::canonical_id(&model);
+                if !db.remove_price(&model, &date)? {
+                    anyhow::bail!("No price for {} starting {}", model, date);
+                }
+                println!("Removed the {} price from {}", model, date);
+            }
+        },
+
+        Commands::Stats { since, action: Some(StatsAction::Latency { model }) } => {
+            let since_date = models::parse_since(&since).map_err(|e| anyhow::anyhow!(e))?;
+            let model = model.map(|m| ModelInfo::canonical_id(&m));
+            let stats = db.get_latency_stats(since_date.as_deref(), model.as_deref())?;
+            if stats.by_model.is_empty() {
+                println!("No timed generations in this period");
+                return Ok(());
+            }
+
+            println!("{:<30} {:>6} {:>8} {:>8}", "MODEL", "COUNT", "P50", "P95");
+            for m in &stats.by_model {
+                println!("{:<30} {:>6} {:>7.1}s {:>7.1}s", m.group, m.count, m.p50_seconds, m.p95_seconds);
+            }
+            println!();
+
+            println!("{:<30} {:>6} {:>8} {:>8}", "HOUR", "COUNT", "P50", "P95");
+            for h in &stats.by_hour {
+                println!("{:<30} {:>6} {:>7.1}s {:>7.1}s", format!("{}:00", h.group), h.count, h.p50_seconds, h.p95_seconds);
+            }
+        }
+
+        Commands::Stats { since, action: None } => {
+            let since_date = models::parse_since(&since).map_err(|e| anyhow::anyhow!(e))?;
+            let stats = db.get_stats(since_date.as_deref())?;
+
+            println!("Statistics");
+            println!("==========");
+            println!("Generations: {}", stats.total_generations);
+            println!("Total cost: ${:.2}", stats.total_cost_usd);
+            match stats.success_rate {
+                Some(rate) => println!(
+                    "Job success: {:.1}% ({} completed, {} failed)",
+                    rate * 100.0,
+                    stats.jobs_completed,
+                    stats.jobs_failed
+                ),
+                None => println!("Job success: no finished jobs"),
+            }
+            println!();
+
+            if !stats.per_week.is_empty() {
+                println!("Per Week (last 8):");
+                let skip = stats.per_week.len().saturating_sub(8);
+                for week in stats.per_week.iter().skip(skip) {
+                    println!("  {}  {:>5}  ${:.2}", week.period, week.count, week.cost_usd);
+                }
+                println!();
+            }
+
+            if !stats.by_model.is_empty() {
+                println!("{:<30} {:>6} {:>9} {:>9}", "MODEL", "COUNT", "COST", "AVG TIME");
+                for m in &stats.by_model {
+                    let avg = m
+                        .avg_generation_seconds
+                        .map(|s| format!("{:.1}s", s))
+                        .unwrap_or_else(|| "-".to_string());
+                    println!("{:<30} {:>6} {:>9} {:>9}", m.model, m.count, format!("${:.2}", m.cost_usd), avg);
+                }
+                println!();
+            }
+
+            if !stats.top_tags.is_empty() {
+                println!("Top Tags:");
+                for t in stats.top_tags.iter().take(10) {
+                    println!("  {:<28} {:>6}", t.name, t.count);
+                }
+            }
+        }
+
+        Commands::Failures { limit } => {
+            let failures = db.list_recent_failed_jobs(limit)?;
+            if failures.is_empty() {
+                println!("No recent failures (last 24 hours)");
+            } else {
+                println!("Recent Failures");
+                println!("===============");
+                for job in failures {
+                    println!();
+                    println!("ID: {} | Model: {} | {}", job.id, job.model, job.completed_at.unwrap_or_default());
+                    println!("Prompt: \"{}\"", truncate_string(&job.prompt, 60));
+                    if let Some(error) = &job.error {
+                        match job.failure.as_deref() {
+                            Some(failure) if failure != "other" => println!("Error ({}): {}", failure, error),
+                            _ => println!("Error: {}", error),
+                        }
+                    }
+                    if job.retry_count > 0 {
+                        println!("Retries: {}", job.retry_count);
+                    }
+                    if let Some(retry) = job.retry_job_id {
+                        println!("Re-run as job {}", retry);
+                    }
+                }
+            }
+        }
+
+        Commands::Notifications { cli, gui, schedule, webhook, webhook_format } => {
+            let mut settings = notify::load_settings();
+            let webhook_format = match webhook_format.as_deref() {
+                None => None,
+                Some("auto") => Some(None),
+                Some(f) => Some(Some(f.parse::<notify::WebhookFormat>().map_err(|e| anyhow::anyhow!(e))?)),
+            };
+            if cli.is_some() || gui.is_some() || schedule.is_some() || webhook.is_some() || webhook_format.is_some() {
+                settings.cli = cli.unwrap_or(settings.cli);
+                settings.gui = gui.unwrap_or(settings.gui);
+                settings.schedule = schedule.unwrap_or(settings.schedule);
+                if let Some(url) = webhook {
+                    settings.webhook_url = (url != "off" && !url.is_empty()).then_some(url);
+                }
+                if let Some(format) = webhook_format {
+                    settings.webhook_format = format;
+                }
+                notify::save_settings(&settings)?;
+            }
+            let state = |on: bool| if on { "on" } else { "off" };
+            println!("cli:      {}", state(settings.cli));
+            println!("gui:      {}", state(settings.gui));
+            println!("schedule: {}", state(settings.schedule));
+            match &settings.webhook_url {
+                Some(url) => {
+                    let format = settings.webhook_format.map_or("auto".to_string(), |f| format!("{:?}", f).to_lowercase());
+                    println!("webhook:  {} ({})", url, format);
+                }
+                None => println!("webhook:  off"),
+            }
+        }
+
+        Commands::Doctor { action: DoctorAction::Providers } => {
+            let rt = tokio::runtime::Runtime::new()?;
+            let all = providers::all();
+            let results = rt.block_on(futures_util::future::join_all(all.iter().map(|p| p.check())));
+
+            println!("{:<12} {:<8} {:>8}  DETAIL", "PROVIDER", "STATUS", "LATENCY");
+            let mut problems = 0;
+            for (provider, result) in all.iter().zip(results) {
+                let name = provider.kind().to_string();
+                let key_source = keys::source(&name).ok().flatten();
+                match result {
+                    Ok(Some(check)) => {
+                        let detail = match key_source {
+                            Some(keys::KeySource::Env(var)) => format!("key from {}", var),
+                            Some(keys::KeySource::Keychain) => "key from keychain".to_string(),
+                            None => check.endpoint.clone(),
+                        };
+                        let latency = format!("{}ms", check.latency.as_millis());
+                        println!("{:<12} {:<8} {:>8}  {}", name, "ok", latency, detail);
+                        for (header, value) in &check.quota {
+                            println!("{:<31}{}: {}", "", header, value);
+                        }
+                    }
+                    Ok(None) => println!("{:<12} {:<8} {:>8}  not configured", name, "skipped", "-"),
+                    Err(e) => {
+                        problems += 1;
+                        let status = if keys::needs_key(&name) && key_source.is_none() { "missing" } else { "error" };
+                        let detail: String = format!("{:#}", e).replace('\n', " ").chars().take(200).collect();
+                        println!("{:<12} {:<8} {:>8}  {}", name, status, "-", detail);
+                    }
+                }
+            }
+            if problems > 0 {
+                anyhow::bail!("{} provider(s) need attention", problems);
+            }
+        }
+
+        Commands::Keys { action } => match action {
+            KeysAction::Set { provider, key } => {
+                let key = match key {
+                    Some(key) => key,
+                    None => {
+                        if std::io::stdin().is_terminal() {
+                            eprint!("{} API key: ", provider);
+                        }
+                        let mut line = String::new();
+                        std::io::stdin().read_line(&mut line).context("Failed to read key from stdin")?;
+                        line
+                    }
+                };
+                keys::set(&provider, &key)?;
+                println!("Stored {} key in the keychain", provider);
+                if let Some(keys::KeySource::Env(var)) = keys::source(&provider)? {
+                    println!("Note: {} is set and takes precedence", var);
+                }
+            }
+            KeysAction::Remove { provider } => {
+                if keys::remove(&provider)? {
+                    println!("Removed {} key from the keychain", provider);
+                } else {
+                    println!("No {} key in the keychain", provider);
+                }
+            }
+            KeysAction::Status => {
+                println!("{:<10} SOURCE", "PROVIDER");
+                for (provider, _) in keys::PROVIDERS {
+                    let source = match keys::source(provider) {
+                        Ok(Some(keys::KeySource::Env(var))) => format!("environment ({})", var),
+                        Ok(Some(keys::KeySource::Keychain)) => "keychain".to_string(),
+                        Ok(None) => "not configured".to_string(),
+                        Err(e) => format!("not in environment; {:#}", e),
+                    };
+                    println!("{:<10} {}", provider, source);
+                }
+            }
+        },
+
+        Commands::DiscordBot { model, models, guild, tags } => {
+            let split = |s: Option<String>| -> Vec<String> {
+                s.map(|s| s.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect())
+                    .unwrap_or_default()
+            };
+            let options = discord::BotOptions {
+                token: discord::get_token()?,
+                model,
+                models: split(models),
+                guild,
+                tags: split(tags),
+            };
+            let rt = generation_runtime()?;
+            rt.block_on(discord::run(&db, &options))?;
+        }
+
+        Commands::TelegramBot { model, models, allow, tags, api_url } => {
+            let split = |s: Option<String>| -> Vec<String> {
+                s.map(|s| s.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect())
+                    .unwrap_or_default()
+            };
+            let options = telegram::BotOptions {
+                token: telegram::get_token()?,
+                api_url: api_url.trim_end_matches('/').to_string(),
+                model,
+                models: split(models),
+                allow: split(allow),
+                tags: split(tags),
+            };
+            let rt = generation_runtime()?;
+            rt.block_on(telegram::run(&db, &options))?;
+        }
+
+        Commands::RetryFailed { hours, max_cost } => {
+            let rt = generation_runtime()?;
+            let report = rt.block_on(workflow::retry_failed(&db, hours, max_cost, JobSource::Cli, &print_queue_progress))?;
 
-    // Open database
-    let db = Database::open(&archive::db_path())?;
+            for (job_id, gen_id) in &report.retried {
+                println!("Job {} -> generation {}", job_id, gen_id);
+            }
+            for (job_id, error) in &report.failed {
+                println!("Job {} failed again: {}", job_id, error);
+            }
+            if !report.skipped.is_empty() {
+                println!("Skipped (no stored request): {}", join_ids(&report.skipped));
+            }
+            if !report.blocked.is_empty() {
+                println!("Skipped (refused under content policy): {}", join_ids(&report.blocked));
+            }
+            if !report.over_budget.is_empty() {
+                println!("Over the ${:.2} cap: {}", max_cost, join_ids(&report.over_budget));
+            }
+            println!(
+                "\nRetried {} of {} failed job(s), cost ${:.4}",
+                report.retried.len(),
+                report.retried.len()
+                    + report.failed.len()
+                    + report.skipped.len()
+                    + report.blocked.len()
+                    + report.over_budget.len(),
+                report.cost
+            );
+            notify::send(JobSource::Cli, &notify::Message::batch(report.retried.len(), report.failed.len()));
+        }
 
-    match cmd {
-        Commands::Generate {
-            prompt,
+        Commands::Jobs { action } => match action {
+            JobsAction::List { status, since, limit } => {
+                let since_date = match since {
+                    Some(s) => models::parse_since(&s).map_err(|e| anyhow::anyhow!(e))?,
+                    None => None,
+                };
+                let jobs = db.list_jobs(status, since_date.as_deref(), limit)?;
+                if jobs.is_empty() {
+                    println!("No jobs");
+                } else {
+                    println!("    ID STATUS     SOURCE    MODEL                    TIME  RESULT");
+                    println!("{}", "-".repeat(90));
+                    for job in &jobs {
+                        let result = match (job.generation_id, job.retry_job_id, &job.error) {
+                            (Some(gen_id), _, _) => format!("generation {}", gen_id),
+                            (None, Some(retry), _) => format!("retried as job {}", retry),
+                            (None, None, Some(error)) if job.failure.as_deref() == Some("content_policy") => {
+                                format!("blocked: {}", truncate_string(error, 31))
+                            }
+                            (None, None, Some(error)) => truncate_string(error, 40),
+                            (None, None, None) => truncate_string(&job.prompt, 40),
+                        };
+                        println!(
+                            "{:>6} {:<10} {:<9} {:<20} {:>8}  {}",
+                            job.id,
+                            job.status.to_string(),
+                            job.source.to_string(),
+                            truncate_string(&job.model, 20),
+                            job_duration(job),
+                            result
+                        );
+                    }
+                }
+            }
+            JobsAction::Retry { job_id } => {
+                let rt = generation_runtime()?;
+                println!("Retrying job {}...", job_id);
+                let generations =
+                    rt.block_on(workflow::retry_job(&db, job_id, JobSource::Cli, &print_queue_progress))?;
+                print_generated(&generations);
+            }
+        },
+
+        Commands::Import {
             file,
+            dir,
+            recursive,
+            prompt,
+            prompt_file,
             model,
             tags,
             reference,
-            copy_to,
-            negative,
-            ratio,
-            ip_scale,
+            date,
+            time,
+            on_duplicate,
         } => {
-            let prompt_text = if let Some(p) = prompt {
-                p
-            } else if let Some(f) = file {
-                std::fs::read_to_string(&f).context("Failed to read prompt file")?
-            } else {
-                anyhow::bail!("Either --prompt or --file is required");
+            let prompt_text = match (prompt, prompt_file) {
+                (Some(p), _) => Some(p),
+                (None, Some(f)) => Some(std::fs::read_to_string(&f).context("Failed to read prompt file")?),
+                (None, None) => None,
             };
 
             let tag_list: Vec<String> = tags
                 .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
                 .unwrap_or_default();
-
             let ref_paths: Vec<String> = reference
                 .iter()
                 .map(|p| p.to_string_lossy().to_string())
                 .collect();
 
-            let (width, height) = resolve_ratio(ratio.as_deref())?;
-
-            // Run async generation
-            let rt = tokio::runtime::Runtime::new()?;
-            rt.block_on(async {
-                generate_image(&db, &prompt_text, &model, &tag_list, &ref_paths, copy_to.as_ref(), negative.as_deref(), width, height, ip_scale)
-                    .await
-            })?;
-        }
-
-        Commands::List {
-            limit,
-            tag,
-            model,
-            starred,
-        } => {
-            let filter = ListFilter {
-                limit: Some(limit),
-                tags: tag.map(|t| vec![t]),
+            if dir.is_some() && (date.is_some() || time.is_some()) {
+                anyhow::bail!("--date and --time apply to a single --file import");
+            }
+            let options = workflow::ImportOptions {
+                prompt: prompt_text,
                 model,
-                starred_only: starred,
-                ..Default::default()
+                tags: tag_list,
+                reference_paths: ref_paths,
+                date,
+                time,
+                on_duplicate,
             };
 
-            let generations = db.list_generations(&filter)?;
-            print_generations(&generations);
-        }
+            if let Some(dir) = dir {
+                let report = workflow::import_directory(
+                    &db,
+                    &dir,
+                    recursive,
+                    &options,
+                    |done, total| {
+                        if done % 100 == 0 && total > 100 {
+                            eprintln!("  {}/{} files", done, total);
+                        }
+                    },
+                )?;
+                for (path, error) in &report.failed {
+                    eprintln!("Failed: {}: {}", path.display(), error);
+                }
+                println!(
+                    "Imported {} image(s) from {}: {} already archived, {} not images, {} failed",
+                    report.imported,
+                    dir.display(),
+                    report.duplicates,
+                    report.skipped,
+                    report.failed.len()
+                );
+                return Ok(());
+            }
 
-        Commands::Search { query, limit } => {
-            let generations = db.search_generations(&query, limit)?;
-            print_generations(&generations);
+            let file = file.context("--file or --dir is required")?;
+            import_image(&db, &file, &options)?;
         }
 
-        Commands::Show { id } => {
-            let gen = db
-                .get_generation(id)?
-                .ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
-
-            println!("ID: {}", gen.id);
-            println!("Slug: {}", gen.slug);
-            println!("Model: {} ({})", gen.model, gen.provider);
-            println!("Date: {}", gen.date);
-            println!("Time: {}", gen.timestamp);
-            println!("Path: {}", gen.image_path);
-            if let Some(t) = gen.generation_time_seconds {
-                println!("Generation time: {:.1}s", t);
-            }
-            if let Some(c) = gen.cost_estimate_usd {
-                println!("Cost: ${:.3}", c);
-            }
-            if let Some(s) = &gen.seed {
-                println!("Seed: {}", s);
-            }
-            if let (Some(w), Some(h)) = (gen.width, gen.height) {
-                println!("Dimensions: {}x{}", w, h);
+        Commands::Maintenance { daemon, status, dry_run } => {
+            if dry_run {
+                let unused = maintenance::collect_unused_references(&db, true)?;
+                if unused.is_empty() {
+                    println!("No unused reference images");
+                } else {
+                    println!("Would remove {} unused reference image(s):", unused.len());
+                    for r in &unused {
+                        println!("  {:>5} {}", r.id, r.path);
+                    }
+                }
+            } else if status {
+                print_maintenance_status(&maintenance::load_status());
+            } else if daemon {
+                println!("Running maintenance every {} minutes (Ctrl+C to stop)", maintenance::INTERVAL.as_secs() / 60);
+                loop {
+                    print_maintenance_status(&maintenance::run_and_record(&db));
+                    std::thread::sleep(maintenance::INTERVAL);
+                }
+            } else {
+                print_maintenance_status(&maintenance::run_and_record(&db));
             }
-            if gen.starred {
-                println!("Starred: yes");
+        }
+
+        Commands::Caption { ids, limit, backend, model } => {
+            let mut settings = caption::load_settings();
+            if let Some(b) = backend {
+                settings.backend = b.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+                settings.model = caption::CaptionSettings::default_model(settings.backend).to_string();
             }
-            if !gen.tags.is_empty() {
-                println!("Tags: {}", gen.tags.join(", "));
+            if let Some(m) = model {
+                settings.model = m;
             }
 
-            // Show reference images
-            let refs = db.get_references_for_generation(id)?;
-            if !refs.is_empty() {
-                println!("References ({}):", refs.len());
-                for r in &refs {
-                    println!("  - {}", r.path);
+            let targets: Vec<(i64, String)> = if ids.is_empty() {
+                db.list_uncaptioned(limit)?
+            } else {
+                let mut targets = vec![];
+                for id in ids {
+                    match db.get_generation(id)? {
+                        Some(gen) => targets.push((id, gen.image_path)),
+                        None => eprintln!("Generation {} not found, skipping", id),
+                    }
                 }
-            }
-
-            println!("\nPrompt:\n{}", gen.prompt);
-        }
-
-        Commands::View { ids, width, height } => {
-            view_images(&db, &ids, width, height)?;
-        }
+                targets
+            };
 
-        Commands::Tag { id, tags } => {
-            let tag_list: Vec<String> = tags.split(',').map(|s| s.trim().to_string()).collect();
-            db.add_tags(id, &tag_list)?;
-            println!("Added tags to generation {}", id);
+            if targets.is_empty() {
+                println!("Nothing to caption");
+            } else {
+                println!("Captioning {} generations with {}...", targets.len(), settings.model);
+                let rt = tokio::runtime::Runtime::new()?;
+                for (id, image_path) in targets {
+                    let path = archive::display_path(&image_path, archive::ThumbSize::Grid);
+                    match rt.block_on(caption::caption_image(Path::new(&path), &settings)) {
+                        Ok(text) => {
+                            db.set_caption(id, &text)?;
+                            println!("[{}] {}", id, text.replace('\n', " | "));
+                        }
+                        Err(e) => eprintln!("[{}] Error: {}", id, e),
+                    }
+                }
+            }
         }
 
-        Commands::Untag { id, tag } => {
-            db.remove_tag(id, &tag)?;
-            println!("Removed tag '{}' from generation {}", tag, id);
+        Commands::RegenThumbs { if_smaller, dry_run } => {
+            regenerate_thumbnails(&db, if_smaller, dry_run)?;
         }
 
-        Commands::Star { id } => {
-            let starred = db.toggle_starred(id)?;
-            if starred {
-                println!("Starred generation {}", id);
+        Commands::MigratePaths { dry_run } => {
+            let root = archive::archive_root();
+            let (changed, outside) = db.relativize_paths(&root.to_string_lossy(), dry_run)?;
+            if dry_run {
+                println!("Would make {} path(s) relative to {}", changed, root.display());
             } else {
-                println!("Unstarred generation {}", id);
+                println!("Made {} path(s) relative to {}", changed, root.display());
             }
-        }
-
-        Commands::Delete { id } => {
-            if let Some(path) = db.permanently_delete_generation(id)? {
-                archive::delete_image(std::path::Path::new(&path))?;
-                println!("Deleted generation {}", id);
-            } else {
-                println!("Generation {} not found", id);
+            if outside > 0 {
+                println!("{} path(s) outside the archive stay absolute", outside);
             }
         }
 
-        Commands::Update {
-            id,
-            title,
+        Commands::Batch {
             prompt,
-            prompt_file,
+            prompts_file,
+            resume,
             model,
-            reference,
+            count,
             tags,
+            reference,
+            negative,
+            ratio,
+            ip_scale,
+            auto_negative,
+            auto_title,
+            count_per_call,
+            ref_resize,
+            ref_crop,
+            ref_rembg,
+            dry_run,
         } => {
-            // Verify generation exists
-            db.get_generation(id)?
-                .ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
-
-            let mut updates = vec![];
-
-            // Update title
-            if let Some(t) = title {
-                db.update_title(id, Some(&t))?;
-                updates.push("title");
+            if let Some(name) = resume {
+                let experiment = db
+                    .get_experiment(&name)?
+                    .filter(|e| e.kind == "batch")
+                    .ok_or_else(|| anyhow::anyhow!("No batch run named {}", name))?;
+                if db.batch_call_count(experiment.id)? == 0 {
+                    anyhow::bail!("{} was started before batch runs could be resumed", name);
+                }
+                let calls = db.pending_batch_calls(experiment.id)?;
+                if calls.is_empty() {
+                    println!("{} is already complete", name);
+                    return Ok(());
+                }
+                let models: std::collections::BTreeSet<&str> = calls.iter().map(|c| c.request.model.as_str()).collect();
+                for model in models {
+                    providers::check_credentials(model)?;
+                }
+                let images: u32 = calls.iter().map(|c| c.request.num_images.unwrap_or(1)).sum();
+                println!("Resuming {}: {} image(s) in {} API call(s)...", name, images, calls.len());
+                run_batch(&db, experiment.id, &name, calls)?;
+                return Ok(());
             }
 
-            // Update prompt
-            if let Some(p) = prompt {
-                db.update_prompt(id, &p)?;
-                updates.push("prompt");
-            } else if let Some(f) = prompt_file {
-                let p = std::fs::read_to_string(&f).context("Failed to read prompt file")?;
-                db.update_prompt(id, &p)?;
-                updates.push("prompt");
-            }
+            let tag_list: Vec<String> = tags
+                .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default();
 
-            // Update model
-            if let Some(m) = model {
-                let model_info = ModelInfo::find(&m);
-                let provider = model_info
-                    .as_ref()
-                    .map(|mi| mi.provider.to_string())
-                    .unwrap_or_else(|| "unknown".to_string());
-                db.update_model(id, &m, &provider)?;
-                updates.push("model");
-            }
+            let ref_paths: Vec<String> = reference
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect();
 
-            // Add tags
-            if let Some(t) = tags {
-                let tag_list: Vec<String> = t.split(',').map(|s| s.trim().to_string()).collect();
-                db.add_tags(id, &tag_list)?;
-                updates.push("tags");
-            }
+            let (width, height) = resolve_ratio(ratio.as_deref())?;
 
-            // Add reference images
-            if !reference.is_empty() {
-                for ref_path in &reference {
-                    let (hash, stored_path) = archive::store_reference(ref_path)?;
-                    let ref_id = db.get_or_create_reference(&hash, stored_path.to_str().unwrap())?;
-                    db.link_reference(id, ref_id)?;
+            let base = GenerationRequest {
+                model,
+                prompt: prompt.unwrap_or_default(),
+                reference_paths: ref_paths,
+                negative_prompt: negative,
+                width,
+                height,
+                ip_scale,
+                cfg_scale: None,
+                auto_negative,
+                num_images: None,
+                inpaint: None,
+                parent_id: None,
+                title: None,
+                auto_title,
+                seed: None,
+                transparent: false,
+            };
+
+            // One request per prompt, with its tags and the file line it came from
+            let lines = match &prompts_file {
+                Some(path) => {
+                    let text = std::fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read {}", path.display()))?;
+                    BatchLine::parse_file(&text).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?
                 }
-                updates.push("references");
+                None => vec![BatchLine { prompt: base.prompt.clone(), ..Default::default() }],
+            };
+            let mut items = Vec::with_capacity(lines.len());
+            for line in lines {
+                let mut req = GenerationRequest { prompt: line.prompt, ..base.clone() };
+                if let Some(model) = line.model {
+                    req.model = model;
+                }
+                if let Some(ratio) = line.ratio.as_deref() {
+                    (req.width, req.height) = resolve_ratio(Some(ratio)).with_context(|| format!("Line {}", line.line))?;
+                }
+                if line.negative.is_some() {
+                    req.negative_prompt = line.negative;
+                }
+                let tags = line.tags.unwrap_or_else(|| tag_list.clone());
+                items.push((line.line, req, tags));
             }
+            let count = count.unwrap_or(if prompts_file.is_some() { 1 } else { 4 });
+            let total = count * items.len() as u32;
+            let batch_models: std::collections::BTreeSet<String> = items.iter().map(|(_, req, _)| req.model.clone()).collect();
 
-            if updates.is_empty() {
-                println!("No updates specified for generation {}", id);
-            } else {
-                println!("Updated generation {}: {}", id, updates.join(", "));
+            if prompts_file.is_none() {
+                print_lint_warnings(&base);
             }
-        }
-
-        Commands::Models { model, guide } => {
-            match (model, guide) {
-                // pixery models MODEL --guide
-                (Some(m), true) => {
-                    if let Some(g) = PromptingGuide::for_model(&m) {
-                        println!("{}", g.format());
-                    } else {
-                        // No guide available, but model might exist
-                        if ModelInfo::find(&m).is_some() {
-                            println!("No prompting guide available for '{}'. This model uses standard prompting.", m);
-                        } else {
-                            eprintln!("Unknown model: {}", m);
-                            eprintln!("\nAvailable models:");
-                            for info in ModelInfo::all() {
-                                eprintln!("  {}", info.id);
-                            }
-                            std::process::exit(1);
-                        }
-                    }
+            if dry_run {
+                let calls = count.div_ceil(count_per_call) * items.len() as u32;
+                match &prompts_file {
+                    Some(_) => println!("Dry run: {} image(s) from {} prompts in {} API call(s)", total, items.len(), calls),
+                    None => println!("Dry run: {} image(s) with {} in {} API call(s)", total, base.model, calls),
                 }
-                // pixery models MODEL (no --guide)
-                (Some(m), false) => {
-                    if let Some(info) = ModelInfo::find(&m) {
-                        println!("Model: {}", info.id);
-                        println!("Display name: {}", info.display_name);
-                        println!("Provider: {}", info.provider);
-                        println!("Cost: ${:.3}/image", info.cost_per_image);
-                        println!("Max references: {}", if info.max_refs == 0 { "none (text-to-image only)".to_string() } else { info.max_refs.to_string() });
-
-                        if PromptingGuide::for_model(&m).is_some() {
-                            println!("\nTip: Use --guide for prompting instructions");
-                        }
-                    } else {
-                        eprintln!("Unknown model: {}", m);
-                        eprintln!("\nAvailable models:");
-                        for info in ModelInfo::all() {
-                            eprintln!("  {}", info.id);
+                let mut cost = 0.0;
+                let mut unpriced = std::collections::BTreeSet::new();
+                for (_, req, _) in &items {
+                    let priced = workflow::current_price(&db, &ModelInfo::canonical_id(&req.model))?;
+                    match priced.or_else(|| providers::estimate_cost(req)) {
+                        Some(per_image) => cost += per_image * count as f64,
+                        None => {
+                            unpriced.insert(req.model.as_str());
                         }
-                        std::process::exit(1);
                     }
                 }
-                // pixery models --guide (no model specified)
-                (None, true) => {
-                    println!("Available prompting guides:");
-                    println!();
-                    for g in PromptingGuide::all() {
-                        println!("  {} ({})", g.model_pattern, g.style);
+                if unpriced.is_empty() {
+                    if prompts_file.is_none() {
+                        println!("  Per image: ${:.4}", cost / count as f64);
+                    }
+                    println!("  Total:     ${:.4}", cost);
+                } else {
+                    if cost > 0.0 {
+                        println!("  Total:     ${:.4} (priced models only)", cost);
+                    }
+                    for model in unpriced {
+                        println!("  No price known for {}", model);
                     }
-                    println!();
-                    println!("Usage: pixery models MODEL --guide");
                 }
-                // pixery models (list all)
-                (None, false) => {
-                    let models = ModelInfo::all();
-                    println!("{:<30} {:<10} {:>8} {:>8}", "MODEL ID", "PROVIDER", "COST", "REFS");
-                    println!("{}", "-".repeat(60));
-                    for m in models {
-                        let refs_str = if m.max_refs == 0 {
-                            "-".to_string()
-                        } else {
-                            format!("{}", m.max_refs)
-                        };
-                        println!(
-                            "{:<30} {:<10} ${:>6.3} {:>8}",
-                            m.id, m.provider, m.cost_per_image, refs_str
-                        );
+                for model in &batch_models {
+                    if let Err(e) = providers::check_credentials(model) {
+                        println!("  Warning: {}", e);
                     }
                 }
+                return Ok(());
             }
-        }
-
-        Commands::Tags => {
-            let tags = db.list_tags()?;
-            if tags.is_empty() {
-                println!("No tags yet");
-            } else {
-                println!("{:<30} {:>8}", "TAG", "COUNT");
-                println!("{}", "-".repeat(40));
-                for t in tags {
-                    println!("{:<30} {:>8}", t.name, t.count);
+            for model in &batch_models {
+                providers::check_credentials(model)?;
+            }
+            let params = serde_json::json!({
+                "model": base.model,
+                "count": count,
+                "count_per_call": count_per_call,
+                "tags": tag_list,
+                "references": base.reference_paths,
+                "negative_prompt": base.negative_prompt,
+                "ratio": ratio,
+                "width": width,
+                "height": height,
+                "ip_scale": ip_scale,
+                "auto_negative": auto_negative,
+                "auto_title": auto_title,
+                "prompts_file": prompts_file,
+                "prompts": items.len(),
+            });
+            let lines_count = items.len();
+            let experiment_prompt = match &prompts_file {
+                Some(path) => format!("{} prompts from {}", lines_count, path.display()),
+                None => base.prompt.clone(),
+            };
+            let rt = generation_runtime()?;
+            let prep = refprep::RefPrep { resize: ref_resize, crop: ref_crop, rembg: ref_rembg };
+            let ref_paths = rt.block_on(preprocess_references(&db, &base.reference_paths, &prep))?;
+            drop(rt);
+
+            // Each call asks for up to count_per_call images; the last one takes the remainder
+            let mut planned = vec![];
+            for (line, mut req, tags) in items {
+                req.reference_paths = ref_paths.clone();
+                let mut requested = 0u32;
+                while requested < count {
+                    let per_call = count_per_call.min(count - requested);
+                    req.num_images = (per_call > 1).then_some(per_call);
+                    requested += per_call;
+                    planned.push((prompts_file.is_some().then_some(line), req.clone(), tags.clone()));
                 }
             }
+
+            let (experiment_id, experiment) = db.create_experiment("batch", &experiment_prompt, &params)?;
+            db.add_batch_calls(experiment_id, &planned)?;
+            match &prompts_file {
+                Some(_) => println!("Generating {} images from {} prompts (experiment {})...", total, lines_count, experiment),
+                None => println!("Generating {} images with {} (experiment {})...", count, base.model, experiment),
+            }
+            run_batch(&db, experiment_id, &experiment, db.pending_batch_calls(experiment_id)?)?;
         }
 
-        Commands::Cost { since } => {
-            let since_date = models::parse_since(&since).map_err(|e| anyhow::anyhow!(e))?;
-            let summary = db.get_cost_summary(since_date.as_deref())?;
+        Commands::Ab { prompt, models, tags, reference, negative, ratio, auto_negative, sheet } => {
+            let mut seen = std::collections::HashSet::new();
+            if let Some(dup) = models.iter().find(|m| !seen.insert(m.as_str())) {
+                anyhow::bail!("Model {} is listed twice", dup);
+            }
+            if models.len() < 2 {
+                anyhow::bail!("Give at least two models to compare (-m A -m B)");
+            }
+            for model in &models {
+                providers::check_credentials(model)?;
+            }
 
-            println!("Cost Summary");
-            println!("============");
-            println!("Total: ${:.2}", summary.total_usd);
-            println!("Generations: {}", summary.count);
-            println!();
+            let mut tag_list: Vec<String> = tags
+                .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default();
+            let (width, height) = resolve_ratio(ratio.as_deref())?;
+            let ref_paths: Vec<String> = reference
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect();
 
-            if !summary.by_model.is_empty() {
-                println!("By Model:");
-                for (model, cost) in &summary.by_model {
-                    println!("  {:<30} ${:.2}", model, cost);
+            let params = serde_json::json!({
+                "models": models,
+                "tags": tag_list,
+                "references": ref_paths,
+                "negative_prompt": negative,
+                "ratio": ratio,
+                "width": width,
+                "height": height,
+                "auto_negative": auto_negative,
+            });
+            let (experiment_id, experiment) = db.create_experiment("ab", &prompt, &params)?;
+            tag_list.push(format!("experiment:{}", experiment));
+
+            let requests: Vec<GenerationRequest> = models
+                .iter()
+                .map(|model| GenerationRequest {
+                    model: model.clone(),
+                    prompt: prompt.clone(),
+                    reference_paths: ref_paths.clone(),
+                    negative_prompt: negative.clone(),
+                    width,
+                    height,
+                    auto_negative,
+                    ..Default::default()
+                })
+                .collect();
+            for req in &requests {
+                print_lint_warnings(req);
+            }
+
+            println!("Experiment {}: {} models...", experiment, requests.len());
+            let rt = generation_runtime()?;
+            let results = rt.block_on(futures_util::future::join_all(requests.iter().map(|req| {
+                workflow::perform_generation(&db, req, &tag_list, JobSource::Cli, &print_queue_progress)
+            })));
+
+            println!("\n{:<20} {:<10} {:<10} {:>9} {:>8}", "MODEL", "STATUS", "ID(S)", "COST", "TIME");
+            let mut ids = vec![];
+            let mut total_cost = 0.0;
+            let mut errors = vec![];
+            for (model, result) in models.iter().zip(results) {
+                match result {
+                    Ok(generations) => {
+                        let gen_ids: Vec<i64> = generations.iter().map(|saved| saved.id).collect();
+                        let cost: f64 = generations.iter().filter_map(|saved| saved.generation.cost_estimate_usd).sum();
+                        let time = generations
+                            .first()
+                            .and_then(|saved| saved.generation.generation_time_seconds)
+                            .map(|t| format!("{:.1}s", t))
+                            .unwrap_or_else(|| "-".to_string());
+                        println!("{:<20} {:<10} {:<10} {:>9} {:>8}", model, "ok", join_ids(&gen_ids), format!("${:.4}", cost), time);
+                        total_cost += cost;
+                        db.add_to_experiment(experiment_id, &gen_ids)?;
+                        ids.extend(gen_ids);
+                    }
+                    Err(e) => {
+                        let status = if cancel::is_cancelled(&e) { "cancelled" } else { "failed" };
+                        println!("{:<20} {:<10} {:<10} {:>9} {:>8}", model, status, "-", "-", "-");
+                        errors.push(format!("{}: {}", model, e));
+                    }
                 }
-                println!();
+            }
+            println!("Total: ${:.4}  (pixery experiment show {})", total_cost, experiment);
+            for error in &errors {
+                eprintln!("Error: {}", error);
             }
 
-            if !summary.by_day.is_empty() {
-                println!("By Day (last 10):");
-                for (day, cost) in summary.by_day.iter().take(10) {
-                    println!("  {} ${:.2}", day, cost);
+            if !ids.is_empty() {
+                let id_args: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+                match &sheet {
+                    Some(path) => {
+                        let mut generations = vec![];
+                        for id in &ids {
+                            generations.extend(db.get_generation(*id)?);
+                        }
+                        let n = generations.len() as u32;
+                        let columns = if n <= 4 { n } else { (n as f64).sqrt().ceil() as u32 };
+                        let image = compare::contact_sheet(&generations, columns, 512)?;
+                        image.save(path).context("Failed to write contact sheet")?;
+                        println!("Wrote {}", path.display());
+                    }
+                    None => println!("Compare: pixery compare {}", id_args.join(" ")),
                 }
             }
+            notify::send(JobSource::Cli, &notify::Message::batch(ids.len(), errors.len()));
         }
 
-        Commands::Failures { limit } => {
-            let failures = db.list_recent_failed_jobs(limit)?;
-            if failures.is_empty() {
-                println!("No recent failures (last 24 hours)");
-            } else {
-                println!("Recent Failures");
-                println!("===============");
-                for job in failures {
-                    println!();
-                    println!("ID: {} | Model: {} | {}", job.id, job.model, job.completed_at.unwrap_or_default());
-                    println!("Prompt: \"{}\"", truncate_string(&job.prompt, 60));
-                    if let Some(error) = &job.error {
-                        println!("Error: {}", error);
+        Commands::Experiment { action } => match action {
+            ExperimentAction::List { limit } => {
+                let experiments = db.list_experiments()?;
+                if experiments.is_empty() {
+                    println!("No experiments (runs of `pixery ab` and `pixery batch` are recorded here)");
+                } else {
+                    println!("{:<24} {:<6} {:>6} {:>9} {:<20} PROMPT", "NAME", "KIND", "IMAGES", "COST", "CREATED");
+                    println!("{}", "-".repeat(100));
+                    for e in experiments.iter().take(limit) {
+                        let prompt: String = e.prompt.chars().take(36).collect();
+                        println!(
+                            "{:<24} {:<6} {:>6} {:>9} {:<20} {}",
+                            e.name,
+                            e.kind,
+                            e.count,
+                            format!("${:.4}", e.total_cost),
+                            e.created_at,
+                            prompt
+                        );
                     }
                 }
             }
-        }
+            ExperimentAction::Show { name } => {
+                let experiment = db
+                    .get_experiment(&name)?
+                    .ok_or_else(|| anyhow::anyhow!("Experiment '{}' not found", name))?;
+                println!("Experiment: {} ({})", experiment.name, experiment.kind);
+                println!("Created:    {}", experiment.created_at);
+                println!("Prompt:     {}", experiment.prompt);
+                if let Some(params) = experiment.params.as_object() {
+                    println!("Parameters:");
+                    for (key, value) in params.iter().filter(|(_, v)| !v.is_null()) {
+                        match value.as_str() {
+                            Some(s) => println!("  {:<16} {}", key, s),
+                            None => println!("  {:<16} {}", key, value),
+                        }
+                    }
+                }
 
-        Commands::Import {
-            file,
-            prompt,
-            prompt_file,
-            model,
-            tags,
-            reference,
-            date,
-            time,
-        } => {
-            let prompt_text = if let Some(p) = prompt {
-                p
-            } else if let Some(f) = prompt_file {
-                std::fs::read_to_string(&f).context("Failed to read prompt file")?
-            } else {
-                // Use filename as prompt if none provided
-                file.file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("imported")
-                    .to_string()
+                let generations = db.list_generations(&ListFilter {
+                    experiment_id: Some(experiment.id),
+                    sort: Some(SortKey::Timestamp),
+                    sort_desc: false,
+                    ..Default::default()
+                })?;
+                if !generations.is_empty() {
+                    // Per-model summary, in the order the models first appear
+                    let mut models: Vec<(&str, usize, f64, Vec<f64>)> = vec![];
+                    for gen in &generations {
+                        let index = match models.iter().position(|(m, ..)| *m == gen.model) {
+                            Some(i) => i,
+                            None => {
+                                models.push((&gen.model, 0, 0.0, vec![]));
+                                models.len() - 1
+                            }
+                        };
+                        let entry = &mut models[index];
+                        entry.1 += 1;
+                        entry.2 += gen.cost_estimate_usd.unwrap_or(0.0);
+                        entry.3.extend(gen.generation_time_seconds);
+                    }
+                    println!("\n{:<25} {:>6} {:>9} {:>9}", "MODEL", "IMAGES", "COST", "AVG TIME");
+                    for (model, count, cost, times) in &models {
+                        let avg = if times.is_empty() {
+                            "-".to_string()
+                        } else {
+                            format!("{:.1}s", times.iter().sum::<f64>() / times.len() as f64)
+                        };
+                        println!("{:<25} {:>6} {:>9} {:>9}", model, count, format!("${:.4}", cost), avg);
+                    }
+                    println!("Total: {} images, ${:.4}\n", experiment.count, experiment.total_cost);
+                }
+                print_generations(&generations);
+            }
+        },
+
+        Commands::Inpaint { id, mask, prompt, model, tags, negative, copy_to, name_template } => {
+            let source = db
+                .get_generation(id)?
+                .ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+            if !mask.exists() {
+                anyhow::bail!("Mask not found: {}", mask.display());
+            }
+            let tag_list: Vec<String> = tags
+                .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default();
+
+            let req = GenerationRequest {
+                model,
+                prompt,
+                negative_prompt: negative,
+                width: source.width,
+                height: source.height,
+                inpaint: Some(models::Inpaint {
+                    image_path: source.image_path.clone(),
+                    mask_path: mask.to_string_lossy().to_string(),
+                    strength: None,
+                }),
+                parent_id: Some(id),
+                ..Default::default()
             };
 
+            let rt = generation_runtime()?;
+            rt.block_on(generate_image(&db, &req, &tag_list, copy_to.as_deref(), name_template.as_ref()))?;
+        }
+
+        Commands::Extend { id, direction, pixels, prompt, model, tags, negative, copy_to, name_template } => {
+            let source = db
+                .get_generation(id)?
+                .ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+            let (canvas_path, mask_path, width, height) =
+                outpaint::extend_canvas(Path::new(&source.image_path), direction, pixels)?;
+            println!("Canvas: {}x{}", width, height);
+
             let tag_list: Vec<String> = tags
                 .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
                 .unwrap_or_default();
 
-            let ref_paths: Vec<String> = reference
-                .iter()
-                .map(|p| p.to_string_lossy().to_string())
-                .collect();
+            let req = GenerationRequest {
+                model,
+                prompt,
+                negative_prompt: negative,
+                width: Some(width as i32),
+                height: Some(height as i32),
+                inpaint: Some(models::Inpaint {
+                    image_path: canvas_path.to_string_lossy().to_string(),
+                    mask_path: mask_path.to_string_lossy().to_string(),
+                    strength: None,
+                }),
+                parent_id: Some(id),
+                ..Default::default()
+            };
+
+            let rt = generation_runtime()?;
+            let result =
+                rt.block_on(generate_image(&db, &req, &tag_list, copy_to.as_deref(), name_template.as_ref()));
 
-            import_image(&db, &file, &prompt_text, &model, &tag_list, &ref_paths, date.as_deref(), time.as_deref())?;
+            // The mask was copied into references; the work files aren't needed either way
+            let _ = std::fs::remove_file(&canvas_path);
+            let _ = std::fs::remove_file(&mask_path);
+            result?;
         }
 
-        Commands::RegenThumbs { if_smaller, dry_run } => {
-            regenerate_thumbnails(&db, if_smaller, dry_run)?;
+        Commands::Transform { id, crop, rotate, flip, tags } => {
+            let original = db.get_generation(id)?.ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+            let tag_list: Vec<String> = tags
+                .map(|t| t.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                .unwrap_or_default();
+            let steps = transform::Transform { crop, rotate, flip };
+            let result = transform::transform_generation(&db, &original, &steps, &tag_list)?;
+            if result.duplicate {
+                println!("Unchanged: the result is already archived as ID {}", result.id);
+            } else {
+                println!("Transformed ({}): {}", steps, result.generation.image_path);
+                println!("ID: {} (child of {})", result.id, id);
+            }
         }
 
-        Commands::Batch {
+        Commands::Fix {
+            id,
+            region,
             prompt,
             model,
-            count,
+            strength,
+            confidence,
+            max_regions,
             tags,
-            reference,
             negative,
-            ratio,
-            ip_scale,
+            copy_to,
+            name_template,
         } => {
+            let source = db
+                .get_generation(id)?
+                .ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
             let tag_list: Vec<String> = tags
                 .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
                 .unwrap_or_default();
 
-            let ref_paths: Vec<String> = reference
-                .iter()
-                .map(|p| p.to_string_lossy().to_string())
-                .collect();
-
-            let (width, height) = resolve_ratio(ratio.as_deref())?;
-
-            println!("Generating {} images with {}...", count, model);
-
-            let rt = tokio::runtime::Runtime::new()?;
-            let mut successes = 0u32;
-            let mut failures = 0u32;
-
-            for i in 1..=count {
-                print!("[{}/{}] ", i, count);
-                match rt.block_on(async {
-                    workflow::perform_generation(
-                        &db,
-                        &prompt,
-                        &model,
-                        &tag_list,
-                        &ref_paths,
-                        JobSource::Cli,
-                        negative.as_deref(),
-                        width,
-                        height,
-                        ip_scale,
-                    )
-                    .await
-                }) {
-                    Ok((gen_id, generation)) => {
-                        println!("ID {} -> {}", gen_id, generation.image_path);
-                        successes += 1;
-                    }
-                    Err(e) => {
-                        println!("Error: {}", e);
-                        failures += 1;
+            let req = GenerationRequest {
+                model: model.unwrap_or_else(|| source.model.clone()),
+                prompt: prompt.unwrap_or_else(|| source.prompt.clone()),
+                negative_prompt: negative.or_else(|| source.negative_prompt.clone()),
+                parent_id: Some(id),
+                ..Default::default()
+            };
+            let opts = detail::FixOptions {
+                region,
+                strength,
+                confidence,
+                max_regions,
+                ..Default::default()
+            };
+            println!("Fixing {} regions with {}...", region.name(), req.model);
+
+            let rt = generation_runtime()?;
+            let result = rt.block_on(workflow::perform_fix(
+                &db,
+                &source,
+                &req,
+                &opts,
+                &tag_list,
+                JobSource::Cli,
+                &print_queue_progress,
+            ));
+            let saved = match result {
+                Ok(r) => r,
+                Err(e) => {
+                    if let Some(message) = notify::Message::failed(&req.model, &e) {
+                        notify::send(JobSource::Cli, &message);
                     }
+                    return Err(e);
                 }
-            }
+            };
+            notify::send(JobSource::Cli, &notify::Message::generated(&saved.generation));
 
-            println!("\nBatch complete: {} succeeded, {} failed", successes, failures);
+            if let Some(dest) = copy_to {
+                copy_result(&saved.generation, &dest, name_template.as_ref())?;
+            }
+            print_generated(std::slice::from_ref(&saved));
+            if let Some(c) = saved.generation.cost_estimate_usd {
+                println!("Cost: ${:.4}", c);
+            }
         }
 
         Commands::Export {
             ids,
             tag,
+            collection,
             output,
             with_metadata,
+            html,
+            xmp,
+            max_size,
+            format,
+            quality,
+            strip_metadata,
+            name_template,
         } => {
-            export_generations(&db, &ids, tag.as_deref(), &output, with_metadata)?;
+            let conversion = Conversion { max_size, format, quality, strip_metadata };
+            let options = ExportOptions { with_metadata, html, numbered: false, xmp, conversion, name_template };
+            export_generations(&db, &ids, tag.as_deref(), collection.as_deref(), &output, &options)?;
         }
 
+        Commands::Slideshow { ids, tag, collection, model, starred, interval, shuffle, windowed, include_nsfw } => {
+            let ids = if ids.is_empty() {
+                let collection_id = match collection {
+                    Some(name) => Some(
+                        db.list_collections()?
+                            .into_iter()
+                            .find(|c| c.name == name)
+                            .ok_or_else(|| anyhow::anyhow!("Collection '{}' not found", name))?
+                            .id,
+                    ),
+                    None => None,
+                };
+                let sort = match (shuffle, collection_id) {
+                    (true, _) => SortKey::Random,
+                    (false, Some(_)) => SortKey::Position,
+                    (false, None) => SortKey::Timestamp,
+                };
+                let filter = ListFilter {
+                    tags: tag.map(|t| vec![t]),
+                    model,
+                    starred_only: starred,
+                    collection_id,
+                    media_type: Some(MediaType::Image),
+                    hide_nsfw: !(include_nsfw || nsfw::load_settings().show),
+                    sort: Some(sort),
+                    ..Default::default()
+                };
+                db.list_generations(&filter)?.into_iter().map(|g| g.id).collect()
+            } else {
+                for &id in &ids {
+                    db.get_generation(id)?.ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+                }
+                ids
+            };
+            if ids.is_empty() {
+                anyhow::bail!("No generations to show");
+            }
+            println!("Showing {} generation(s), {}s each (Esc closes the window)", ids.len(), interval);
+            slideshow::start(slideshow::Slideshow { ids, interval_seconds: interval, fullscreen: !windowed });
+            crate::run();
+        }
+
+        Commands::Quick { prompt, model, tags, open } => {
+            let tag_list: Vec<String> = tags
+                .map(|t| t.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                .unwrap_or_default();
+            let req = GenerationRequest { model, prompt, ..Default::default() };
+            let rt = generation_runtime()?;
+            let generations = rt.block_on(perform_generation(&db, &req, &tag_list))?;
+            if let Some(saved) = generations.first() {
+                notify::send(JobSource::Cli, &notify::Message::generated(&saved.generation));
+            }
+            for saved in &generations {
+                println!("{}", deeplink::generation_url(saved.id));
+            }
+            if let (true, Some(saved)) = (open, generations.first()) {
+                // Start the GUI as its own process, so the launcher isn't left waiting on it
+                if ipc::forward_focus(saved.id).transpose()?.is_none() {
+                    std::process::Command::new(std::env::current_exe()?)
+                        .args(["--archive", profiles::current_name(), "open", &saved.id.to_string()])
+                        .stdout(std::process::Stdio::null())
+                        .spawn()
+                        .context("Failed to start the GUI")?;
+                }
+            }
+        }
+
+        Commands::Open { link, register } => {
+            if register {
+                println!("{}", deeplink::register()?);
+                return Ok(());
+            }
+            let link = link.expect("clap requires a link without --register");
+            let (id, archive) = deeplink::parse(&link)?;
+            // main switches to the link's profile unless --archive picked another
+            if let Some(archive) = archive.filter(|a| a != profiles::current_name()) {
+                anyhow::bail!("{} is in archive profile '{}', not '{}'", link, archive, profiles::current_name());
+            }
+            db.get_generation(id)?.ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+            if ipc::forward_focus(id).transpose()?.is_none() {
+                deeplink::open_at(id);
+                crate::run();
+            }
+        }
+
+        Commands::Linkfarm { by, output, hardlink, name_template } => {
+            let options = linkfarm::FarmOptions { by, hardlink, name_template };
+            let report = linkfarm::build(&db, &output, &options)?;
+            println!(
+                "Links by {} in {}: {} added, {} removed, {} unchanged",
+                by,
+                output.display(),
+                report.created,
+                report.removed,
+                report.unchanged
+            );
+            if report.missing > 0 {
+                eprintln!("{} generation(s) skipped, image file missing", report.missing);
+            }
+            for path in &report.conflicts {
+                eprintln!("Skipped {}: a file pixery didn't create is in the way", path.display());
+            }
+        }
+
+        Commands::Archive { action } => match action {
+            ArchiveAction::Export { since, output } => {
+                let since_date = match since {
+                    Some(s) => models::parse_since(&s).map_err(|e| anyhow::anyhow!(e))?,
+                    None => None,
+                };
+                let count = bundle::export(&db, since_date, &output)?;
+                println!("Exported {} generation(s) to {}", count, output.display());
+            }
+            ArchiveAction::Import { bundle: path } => {
+                let report = bundle::import(&db, &path)?;
+                println!(
+                    "Imported {} generation(s), skipped {} already present",
+                    report.imported, report.skipped
+                );
+            }
+        },
+
+        Commands::Sync { action } => match action {
+            SyncAction::Remote { remote, endpoint } => {
+                let mut settings = sync::load_settings();
+                if remote.is_some() || endpoint.is_some() {
+                    if remote.is_some() {
+                        settings.remote = remote;
+                    }
+                    if endpoint.is_some() {
+                        settings.s3_endpoint = endpoint.filter(|e| e != "off");
+                    }
+                    sync::save_settings(&settings)?;
+                }
+                match &settings.remote {
+                    Some(remote) => println!("Remote: {}", remote),
+                    None => println!("No remote set"),
+                }
+                if let Some(endpoint) = &settings.s3_endpoint {
+                    println!("S3 endpoint: {}", endpoint);
+                }
+            }
+            SyncAction::Push { dry_run } => {
+                let report = sync::push(&db, dry_run)?;
+                let verb = if dry_run { "Would upload" } else { "Uploaded" };
+                println!(
+                    "{} {} file(s) ({:.1} MB) and the database; {} unchanged",
+                    verb,
+                    report.transferred,
+                    report.bytes as f64 / 1_000_000.0,
+                    report.unchanged
+                );
+            }
+            SyncAction::Pull { db: restore_db, dry_run } => {
+                let report = sync::pull(&db, restore_db, dry_run)?;
+                let verb = if dry_run { "Would download" } else { "Downloaded" };
+                println!(
+                    "{} {} file(s) ({:.1} MB); {} unchanged",
+                    verb,
+                    report.transferred,
+                    report.bytes as f64 / 1_000_000.0,
+                    report.unchanged
+                );
+                match (report.database, dry_run) {
+                    (true, true) => println!("Would replace the local database with the remote copy"),
+                    (true, false) => println!("Restored the database from the remote (previous copy kept as a backup)"),
+                    (false, _) => println!("Kept the local database (--db replaces it with the remote copy)"),
+                }
+            }
+        },
+
+        Commands::Preset { action } => match action {
+            PresetAction::Add { name, model, ratio, negative, guide_negative, cfg, tags } => {
+                let (width, height) = resolve_ratio(ratio.as_deref())?;
+                let tag_list: Vec<String> = tags
+                    .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
+                    .unwrap_or_default();
+                let negative = if guide_negative {
+                    let template = PromptingGuide::for_model(&model).and_then(|g| g.negative_template);
+                    match template {
+                        Some(t) => Some(t.to_string()),
+                        None => anyhow::bail!("No negative template in the prompting guide for {}", model),
+                    }
+                } else {
+                    negative
+                };
+
+                db.save_preset(&NewPreset {
+                    name: name.clone(),
+                    model,
+                    negative_prompt: negative,
+                    width,
+                    height,
+                    cfg_scale: cfg,
+                    tags: tag_list,
+                })?;
+                println!("Saved preset '{}'", name);
+            }
+            PresetAction::List => {
+                let presets = db.list_presets()?;
+                if presets.is_empty() {
+                    println!("No presets");
+                } else {
+                    println!("{:<20} {:<16} {:<10} {:<5} TAGS", "NAME", "MODEL", "SIZE", "CFG");
+                    println!("{}", "-".repeat(70));
+                    for p in &presets {
+                        let size = match (p.width, p.height) {
+                            (Some(w), Some(h)) => format!("{}x{}", w, h),
+                            _ => "-".to_string(),
+                        };
+                        let cfg = p.cfg_scale.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string());
+                        println!(
+                            "{:<20} {:<16} {:<10} {:<5} {}",
+                            truncate_string(&p.name, 20),
+                            truncate_string(&p.model, 16),
+                            size,
+                            cfg,
+                            p.tags.join(", ")
+                        );
+                    }
+                }
+            }
+            PresetAction::Remove { name } => {
+                if db.delete_preset(&name)? {
+                    println!("Deleted preset '{}'", name);
+                } else {
+                    println!("Preset '{}' not found", name);
+                }
+            }
+        },
+
+        Commands::Watch { action } => match action {
+            WatchAction::Add { path, model, tags } => {
+                let path = path
+                    .canonicalize()
+                    .with_context(|| format!("Folder not found: {}", path.display()))?;
+                if !path.is_dir() {
+                    anyhow::bail!("Not a folder: {}", path.display());
+                }
+                let tag_list: Vec<String> = tags
+                    .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
+                    .unwrap_or_default();
+
+                let mut settings = watcher::load_settings();
+                settings.folders.retain(|f| f.path != path);
+                settings.folders.push(WatchFolder {
+                    path: path.clone(),
+                    model,
+                    tags: tag_list,
+                });
+                watcher::save_settings(&settings)?;
+                println!("Watching {}", path.display());
+            }
+            WatchAction::List => {
+                let settings = watcher::load_settings();
+                if settings.folders.is_empty() {
+                    println!("No watched folders");
+                } else {
+                    println!("{:<50} {:<16} TAGS", "FOLDER", "MODEL");
+                    println!("{}", "-".repeat(80));
+                    for f in &settings.folders {
+                        println!(
+                            "{:<50} {:<16} {}",
+                            f.path.display(),
+                            truncate_string(&f.model, 16),
+                            f.tags.join(", ")
+                        );
+                    }
+                }
+            }
+            WatchAction::Remove { path } => {
+                let path = path.canonicalize().unwrap_or(path);
+                let mut settings = watcher::load_settings();
+                let before = settings.folders.len();
+                settings.folders.retain(|f| f.path != path);
+                if settings.folders.len() < before {
+                    watcher::save_settings(&settings)?;
+                    println!("Stopped watching {}", path.display());
+                } else {
+                    println!("Folder '{}' is not watched", path.display());
+                }
+            }
+            WatchAction::Run => {
+                let folders = watcher::load_settings().folders;
+                if folders.is_empty() {
+                    anyhow::bail!("No watched folders (add one with 'pixery watch add <folder>')");
+                }
+                for f in &folders {
+                    println!("Watching {}", f.path.display());
+                }
+                println!("Importing new images (Ctrl-C to stop)...");
+                watcher::watch_folders(&db, &folders, |path, result| match result {
+                    Ok(imported) if imported.duplicate => {
+                        println!("Skipped {}: already archived as ID {}", path.display(), imported.id)
+                    }
+                    Ok(imported) => println!("Imported {} (ID: {})", path.display(), imported.id),
+                    Err(e) => eprintln!("Failed to import {}: {}", path.display(), e),
+                })?;
+            }
+        },
+
+        Commands::Schedule { action } => match action {
+            ScheduleAction::Add { cron, prompt, model, name, tags, negative, ratio } => {
+                let schedule = scheduler::parse_cron(&cron)?;
+                let name = name.unwrap_or_else(|| archive::slugify_prompt(&prompt));
+                let tag_list: Vec<String> = tags
+                    .map(|t| t.split(',').map(|s| s.trim().to_string()).collect())
+                    .unwrap_or_default();
+                let (width, height) = resolve_ratio(ratio.as_deref())?;
+
+                db.create_schedule(&NewSchedule {
+                    name: name.clone(),
+                    cron,
+                    prompt,
+                    model,
+                    tags: tag_list,
+                    negative_prompt: negative,
+                    width,
+                    height,
+                })?;
+                println!("Created schedule '{}'", name);
+                if let Some(next) = schedule.upcoming(Local).next() {
+                    println!("Next run: {}", next.format("%Y-%m-%d %H:%M"));
+                }
+                if !scheduler::load_settings().run_in_gui {
+                    println!("Start a runner with 'pixery schedule run' (or 'pixery schedule gui on')");
+                }
+            }
+            ScheduleAction::List => {
+                let schedules = db.list_schedules()?;
+                if schedules.is_empty() {
+                    println!("No schedules");
+                } else {
+                    println!("{:<20} {:<15} {:<16} {:<16} PROMPT", "NAME", "CRON", "MODEL", "NEXT RUN");
+                    println!("{}", "-".repeat(90));
+                    for s in &schedules {
+                        let next = if s.enabled {
+                            scheduler::next_run(s)
+                                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                                .unwrap_or_else(|| "-".to_string())
+                        } else {
+                            "disabled".to_string()
+                        };
+                        println!(
+                            "{:<20} {:<15} {:<16} {:<16} {}",
+                            truncate_string(&s.name, 20),
+                            s.cron,
+                            truncate_string(&s.model, 16),
+                            next,
+                            truncate_string(&s.prompt, 30)
+                        );
+                    }
+                }
+            }
+            ScheduleAction::Remove { name } => {
+                if db.delete_schedule(&name)? {
+                    println!("Deleted schedule '{}'", name);
+                } else {
+                    println!("Schedule '{}' not found", name);
+                }
+            }
+            ScheduleAction::Enable { name } => {
+                if db.set_schedule_enabled(&name, true)? {
+                    println!("Enabled schedule '{}'", name);
+                } else {
+                    println!("Schedule '{}' not found", name);
+                }
+            }
+            ScheduleAction::Disable { name } => {
+                if db.set_schedule_enabled(&name, false)? {
+                    println!("Disabled schedule '{}'", name);
+                } else {
+                    println!("Schedule '{}' not found", name);
+                }
+            }
+            ScheduleAction::Run { once } => {
+                let rt = generation_runtime()?;
+                if once {
+                    let count = rt.block_on(scheduler::run_due(&db))?;
+                    println!("Fired {} scheduled generation(s)", count);
+                } else {
+                    println!("Running schedules (Ctrl-C to stop)...");
+                    loop {
+                        match rt.block_on(scheduler::run_due(&db)) {
+                            Err(e) if cancel::is_cancelled(&e) => return Err(e),
+                            Err(e) => eprintln!("Scheduler error: {}", e),
+                            Ok(_) => {}
+                        }
+                        std::thread::sleep(scheduler::POLL_INTERVAL);
+                    }
+                }
+            }
+            ScheduleAction::Gui { state } => {
+                let run_in_gui = match state.to_lowercase().as_str() {
+                    "on" => true,
+                    "off" => false,
+                    _ => anyhow::bail!("Expected 'on' or 'off', got '{}'", state),
+                };
+                scheduler::save_settings(&scheduler::SchedulerSettings { run_in_gui })?;
+                println!(
+                    "GUI schedule runner {} (takes effect next time the GUI starts)",
+                    if run_in_gui { "enabled" } else { "disabled" }
+                );
+            }
+        },
+
         Commands::Collection { action } => {
             match action {
-                CollectionAction::Create { name, description } => {
+                CollectionAction::Create { name, description, smart: _, filter: Some(filter) } => {
+                    let id = db.create_smart_collection(&name, description.as_deref(), &filter)?;
+                    let count = db.count_generations(&ListFilter {
+                        smart_collection_id: Some(id),
+                        ..Default::default()
+                    })?;
+                    println!("Created smart collection '{}' (ID: {}, {} matching now)", name, id, count);
+                }
+                CollectionAction::Create { name, description, .. } => {
                     let id = db.create_collection(&name, description.as_deref())?;
                     println!("Created collection '{}' (ID: {})", name, id);
                 }
-                CollectionAction::List => {
+                CollectionAction::List { deleted: true } => {
+                    let deleted = db.list_deleted_collections()?;
+                    if deleted.is_empty() {
+                        println!("No deleted collections");
+                    } else {
+                        println!("{:<30} {:>7}  DELETED", "NAME", "MEMBERS");
+                        println!("{}", "-".repeat(60));
+                        for (name, deleted_at, members) in &deleted {
+                            println!("{:<30} {:>7}  {}", name, members, deleted_at);
+                        }
+                    }
+                }
+                CollectionAction::List { deleted: false } => {
                     let collections = db.list_collections()?;
                     if collections.is_empty() {
                         println!("No collections");
                     } else {
                         println!("{:<6} {:<20} {:>5} {:<12} {}", "ID", "NAME", "COUNT", "CREATED", "DESCRIPTION");
                         println!("{}", "-".repeat(70));
+                        // Subcollections indented under their parent; counts include them
                         for c in &collections {
                             let desc = c.description.as_deref().unwrap_or("");
-                            println!("{:<6} {:<20} {:>5} {:<12} {}", c.id, c.name, c.count, &c.created_at[..10], desc);
+                            let name = format!("{}{}", "  ".repeat(c.depth()), c.leaf_name());
+                            println!("{:<6} {:<20} {:>5} {:<12} {}", c.id, name, c.total_count, &c.created_at[..10], desc);
+                        }
+                    }
+
+                    let smart = db.list_smart_collections()?;
+                    if !smart.is_empty() {
+                        println!();
+                        println!("{:<6} {:<20} {:>5} {:<12} FILTER", "ID", "SMART", "COUNT", "CREATED");
+                        println!("{}", "-".repeat(70));
+                        for c in &smart {
+                            println!("{:<6} {:<20} {:>5} {:<12} {}", c.id, c.name, c.count, &c.created_at[..10], c.filter);
+                        }
+                    }
+                }
+                CollectionAction::Show { name, limit } => {
+                    let mut filter = ListFilter {
+                        limit: Some(limit),
+                        ..Default::default()
+                    };
+                    if let Some(smart) = db.get_smart_collection(&name)? {
+                        filter.smart_collection_id = Some(smart.id);
+                    } else {
+                        let coll = db
+                            .list_collections()?
+                            .into_iter()
+                            .find(|c| c.name == name)
+                            .ok_or_else(|| anyhow::anyhow!("Collection '{}' not found", name))?;
+                        filter.collection_id = Some(coll.id);
+                        filter.sort = Some(SortKey::Position);
+                    }
+                    print_generations(&db.list_generations(&filter)?);
+                }
+                CollectionAction::Order { name, ids } => {
+                    db.reorder_collection(&name, &ids)?;
+                    println!("Reordered '{}'", name);
+                }
+                CollectionAction::Cover { name, id, clear: _ } => {
+                    db.set_collection_cover(&name, id)?;
+                    match id {
+                        Some(id) => println!("Cover of '{}' set to {}", name, id),
+                        None => println!("Cover of '{}' cleared", name),
+                    }
+                }
+                CollectionAction::Export {
+                    name,
+                    output,
+                    numbered,
+                    with_metadata,
+                    html,
+                    xmp,
+                    max_size,
+                    format,
+                    quality,
+                    strip_metadata,
+                    name_template,
+                } => {
+                    let coll = db
+                        .list_collections()?
+                        .into_iter()
+                        .find(|c| c.name == name)
+                        .ok_or_else(|| anyhow::anyhow!("Collection '{}' not found", name))?;
+                    let generations = db.list_generations(&ListFilter {
+                        collection_id: Some(coll.id),
+                        sort: Some(SortKey::Position),
+                        ..Default::default()
+                    })?;
+                    let conversion = Conversion { max_size, format, quality, strip_metadata };
+                    let options = ExportOptions { with_metadata, html, numbered, xmp, conversion, name_template };
+                    let cover = match coll.cover_id {
+                        Some(id) => db.get_generation(id)?,
+                        None => None,
+                    };
+                    write_export(&db, &generations, Some(&name), None, &output, &options)?;
+                    let cover = cover.filter(|_| numbered && output.is_dir());
+                    if let Some(cover) = cover {
+                        let src = Path::new(&cover.image_path);
+                        if src.exists() {
+                            let ext = src.extension().and_then(|e| e.to_str()).unwrap_or("png");
+                            let dest = output.join(format!("cover.{}", ext));
+                            convert::export_file(src, &dest, &conversion)
+                                .with_context(|| format!("Failed to export cover {}", cover.id))?;
+                        } else {
+                            eprintln!("Image file missing for cover {}, skipping", cover.id);
                         }
                     }
                 }
@@ -899,13 +5361,173 @@ pub fn run(cmd: Commands) -> Result<()> {
                     }
                     println!("Removed {} generation(s) from '{}'", ids.len(), collection);
                 }
-                CollectionAction::Delete { name } => {
+                CollectionAction::Delete { name, yes } => {
+                    let members = db.collection_membership_count(&name)?;
+                    if members > db::COLLECTION_DELETE_CONFIRM && !yes {
+                        anyhow::bail!(
+                            "'{}' has {} memberships (subcollections included); pass --yes to delete it",
+                            name,
+                            members
+                        );
+                    }
                     if db.delete_collection(&name)? {
-                        println!("Deleted collection '{}'", name);
+                        println!("Deleted collection '{}' (undo with: pixery collection restore {})", name, name);
+                    } else if db.delete_smart_collection(&name)? {
+                        println!("Deleted smart collection '{}'", name);
                     } else {
                         println!("Collection '{}' not found", name);
                     }
                 }
+                CollectionAction::Restore { name } => {
+                    let restored = db.restore_collection(&name)?;
+                    if restored > 1 {
+                        println!("Restored '{}' and {} subcollection(s)", name, restored - 1);
+                    } else {
+                        println!("Restored '{}'", name);
+                    }
+                }
+            }
+        }
+
+        Commands::Refs { action } => match action {
+            RefsAction::List => {
+                let refs = db.list_references()?;
+                if refs.is_empty() {
+                    println!("No references");
+                } else {
+                    println!("ID      USES CREATED      PATH");
+                    println!("{}", "-".repeat(70));
+                    for r in &refs {
+                        let created = r.created_at.get(..10).unwrap_or(&r.created_at);
+                        println!("{:<6} {:>5} {:<12} {}", r.id, r.use_count, created, r.path);
+                    }
+                }
+            }
+            RefsAction::Show { id } => {
+                let reference = db
+                    .get_reference(id)?
+                    .ok_or_else(|| anyhow::anyhow!("Reference {} not found", id))?;
+                let generations = db.get_generations_for_reference(id)?;
+
+                println!("ID: {}", reference.id);
+                println!("Hash: {}", reference.hash);
+                println!("Path: {}", reference.path);
+                println!("Created: {}", reference.created_at);
+                println!("\nUsed by {} generation(s):", generations.len());
+                for g in &generations {
+                    println!("  {:>5} {:<12} {:<20} {}", g.id, g.date, g.model, truncate_string(&g.prompt, 40));
+                }
+                println!("\nReuse with: --ref ref:{}", reference.id);
+            }
+        },
+
+        Commands::Assets { action } => match action {
+            AssetsAction::Add { id, file, kind, note } => {
+                db.get_generation(id)?.ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+                if !file.is_file() {
+                    anyhow::bail!("{} is not a file", file.display());
+                }
+                let (path, size) = archive::store_asset(id, &file)?;
+                let asset_id = match db.insert_asset(id, kind, &path.to_string_lossy(), Some(size), note.as_deref()) {
+                    Ok(asset_id) => asset_id,
+                    Err(e) => {
+                        let _ = std::fs::remove_file(&path);
+                        return Err(e);
+                    }
+                };
+                println!("Attached {} to generation {} as {} (asset {})", path.display(), id, kind, asset_id);
+            }
+            AssetsAction::List { id } => {
+                db.get_generation(id)?.ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+                let assets = db.get_assets_for_generation(id)?;
+                if assets.is_empty() {
+                    println!("No assets for generation {}", id);
+                } else {
+                    println!("ID     KIND          SIZE CREATED      PATH");
+                    println!("{}", "-".repeat(70));
+                    for a in &assets {
+                        let size = match a.file_size {
+                            Some(s) if s >= 1_048_576 => format!("{:.1} MB", s as f64 / 1_048_576.0),
+                            Some(s) => format!("{} KB", (s + 1023) / 1024),
+                            None => "-".to_string(),
+                        };
+                        let created = a.created_at.get(..10).unwrap_or(&a.created_at);
+                        println!("{:<6} {:<8} {:>9} {:<12} {}", a.id, a.kind.to_string(), size, created, a.path);
+                        if let Some(note) = &a.note {
+                            println!("{:<6} {}", "", note);
+                        }
+                    }
+                }
+            }
+            AssetsAction::Remove { id } => {
+                let asset = db.get_asset(id)?.ok_or_else(|| anyhow::anyhow!("Asset {} not found", id))?;
+                db.delete_asset(id)?;
+                let path = Path::new(&asset.path);
+                if path.exists() {
+                    std::fs::remove_file(path).context("Failed to delete asset file")?;
+                }
+                println!("Removed asset {} ({}) from generation {}", id, asset.path, asset.generation_id);
+            }
+        },
+
+        Commands::Palette { ids, missing } => {
+            if missing {
+                let pending = db.generations_without_palette()?;
+                if pending.is_empty() {
+                    println!("Every generation has a palette");
+                    return Ok(());
+                }
+                let mut failed = 0;
+                for (i, (id, path)) in pending.iter().enumerate() {
+                    eprint!("\r{}/{}", i + 1, pending.len());
+                    if let Err(e) = palette::extract_file(Path::new(path)).and_then(|swatches| db.set_palette(*id, &swatches)) {
+                        eprintln!("\rGeneration {}: {:#}", id, e);
+                        failed += 1;
+                    }
+                }
+                eprintln!();
+                println!("Extracted {} palette(s), {} failed", pending.len() - failed, failed);
+            } else {
+                for id in ids {
+                    let gen = db.get_generation(id)?.ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+                    let swatches = palette::extract_file(Path::new(&gen.image_path))?;
+                    db.set_palette(id, &swatches)?;
+                    println!("{:<6} {}", id, format_palette(&swatches));
+                }
+            }
+        }
+
+        Commands::Selfhosted { action } => {
+            let url = providers::selfhosted::require_server_url()?;
+            let rt = tokio::runtime::Runtime::new()?;
+            match action {
+                SelfhostedAction::Status => {
+                    let health = rt.block_on(providers::selfhosted::check_health(&url))?;
+                    println!("Server: {}", url);
+                    println!("Status: {}", health.status);
+                    println!("Loaded model: {}", health.current_model.as_deref().unwrap_or("-"));
+                    println!("Available: {}", health.available_models.join(", "));
+                    if let Some(gpu) = &health.gpu_name {
+                        println!("GPU: {}", gpu);
+                    }
+                    if let Some(vram) = health.vram_allocated_gb {
+                        println!("VRAM allocated: {:.1} GB", vram);
+                    }
+                }
+                SelfhostedAction::Use { model } => {
+                    println!("Loading {}...", model);
+                    let start = std::time::Instant::now();
+                    rt.block_on(providers::selfhosted::switch_model(&url, &model))?;
+                    println!("{} loaded ({:.1}s)", model, start.elapsed().as_secs_f64());
+                }
+                SelfhostedAction::Warmup { model } => {
+                    println!("Warming up {}...", model.as_deref().unwrap_or("the loaded model"));
+                    let result = rt.block_on(providers::selfhosted::warmup(&url, model.as_deref()))?;
+                    println!(
+                        "{} ready (load {:.1}s, first pass {:.1}s)",
+                        result.model, result.load_seconds, result.warmup_seconds
+                    );
+                }
             }
         }
 
@@ -923,43 +5545,322 @@ pub fn run(cmd: Commands) -> Result<()> {
                 }
             }
         }
+
+        Commands::Undo => match db.undo_last()? {
+            Some(done) => println!("{}", done),
+            None => println!("Nothing to undo"),
+        },
+
+        Commands::Activity { limit, id, undo } => {
+            if let Some(event) = undo {
+                println!("{}", db.undo_event(event)?);
+                return Ok(());
+            }
+            let events = db.list_events(limit, id)?;
+            if events.is_empty() {
+                println!("No activity");
+            } else {
+                println!("{:>6} {:<19} {:>5} {:<17} DETAIL", "EVENT", "TIME", "ID", "ACTION");
+                println!("{}", "-".repeat(80));
+                for event in &events {
+                    let detail = describe_event(event);
+                    println!(
+                        "{:>6} {:<19} {:>5} {:<17} {}",
+                        event.id,
+                        event.created_at,
+                        event.generation_id,
+                        event.action,
+                        truncate_string(&detail, 40)
+                    );
+                }
+            }
+        }
     }
 
     Ok(())
 }
 
+/// Copy a result for --copy-to: to that path, or into it as a directory when
+/// --name-template names the file
+fn copy_result(generation: &Generation, dest: &Path, name_template: Option<&NameTemplate>) -> Result<()> {
+    let dest = match name_template {
+        Some(template) => archive::copy_named(generation, dest, template)?,
+        None => {
+            archive::copy_to(Path::new(&generation.image_path), dest)?;
+            dest.to_path_buf()
+        }
+    };
+    println!("Copied to: {}", dest.display());
+    Ok(())
+}
+
 async fn generate_image(
     db: &Database,
-    prompt: &str,
-    model: &str,
+    req: &GenerationRequest,
     tags: &[String],
-    reference_paths: &[String],
-    copy_to: Option<&PathBuf>,
-    negative_prompt: Option<&str>,
-    width: Option<i32>,
-    height: Option<i32>,
-    ip_scale: Option<f64>,
+    copy_to: Option<&Path>,
+    name_template: Option<&NameTemplate>,
 ) -> Result<()> {
-    println!("Generating with {}...", model);
-
-    let (gen_id, generation) =
-        workflow::perform_generation(db, prompt, model, tags, reference_paths, JobSource::Cli, negative_prompt, width, height, ip_scale)
-            .await?;
+    print_lint_warnings(req);
+    println!("Generating with {}...", req.model);
+
+    let generations = match perform_generation(db, req, tags).await {
+        Ok(generations) => generations,
+        Err(e) => {
+            if let Some(message) = notify::Message::failed(&req.model, &e) {
+                notify::send(JobSource::Cli, &message);
+            }
+            return Err(e);
+        }
+    };
+    if let Some(saved) = generations.first() {
+        notify::send(JobSource::Cli, &notify::Message::generated(&saved.generation));
+    }
 
-    // Copy to destination if requested
-    if let Some(dest) = copy_to {
-        archive::copy_to(std::path::Path::new(&generation.image_path), dest)?;
-        println!("Copied to: {}", dest.display());
+    // Copy to destination if requested (the first image, when a call returns several)
+    if let (Some(dest), Some(saved)) = (copy_to, generations.first()) {
+        copy_result(&saved.generation, dest, name_template)?;
     }
 
-    println!("Generated: {} (ID: {})", generation.image_path, gen_id);
-    if let Some(c) = generation.cost_estimate_usd {
+    print_generated(&generations);
+    let cost: Option<f64> = generations.iter().map(|saved| saved.generation.cost_estimate_usd).sum();
+    if let Some(c) = cost {
         println!("Cost: ${:.4}", c);
     }
 
     Ok(())
 }
 
+/// Where each image went, and which were already in the archive
+fn print_generated(generations: &[Generated]) {
+    for saved in generations {
+        if let Some(existing) = saved.duplicate_of {
+            println!("Image is identical to generation {}", existing);
+        }
+        println!("Generated: {} (ID: {})", saved.generation.image_path, saved.id);
+    }
+}
+
+/// Generate in the open GUI when there is one, so a single process writes the
+/// archive; otherwise here. References are resolved to absolute paths first,
+/// since the GUI doesn't share this working directory.
+async fn perform_generation(db: &Database, req: &GenerationRequest, tags: &[String]) -> Result<Vec<Generated>> {
+    let mut forwarded = req.clone();
+    forwarded.reference_paths = workflow::resolve_references(db, &req.reference_paths)?
+        .into_iter()
+        .map(|path| std::path::absolute(&path).map_or(path, |p| p.to_string_lossy().to_string()))
+        .collect();
+    match ipc::forward_generation(&forwarded, tags, &print_queue_progress) {
+        Some(result) => result,
+        None => workflow::perform_generation(db, req, tags, JobSource::Cli, &print_queue_progress).await,
+    }
+}
+
+/// Resolve ref:/gen: specs and run the preprocessing steps, if any were asked for
+async fn preprocess_references(db: &Database, specs: &[String], prep: &refprep::RefPrep) -> Result<Vec<String>> {
+    if prep.is_empty() || specs.is_empty() {
+        return Ok(specs.to_vec());
+    }
+    let paths = workflow::resolve_references(db, specs)?;
+    let processed = refprep::preprocess_all(&paths, prep).await?;
+    println!("Preprocessed {} reference(s)", processed.len());
+    Ok(processed)
+}
+
+/// Make a batch run's calls in order, recording each one that completes so
+/// `--resume` can skip it, then report and notify. Calls from a prompts file
+/// (those with a line) get a header per line and a per-line report.
+fn run_batch(db: &Database, experiment_id: i64, experiment: &str, calls: Vec<models::BatchCall>) -> Result<()> {
+    let rt = generation_runtime()?;
+    let per_line = calls.iter().any(|c| c.line.is_some());
+    let total: u32 = calls.iter().map(|c| c.request.num_images.unwrap_or(1)).sum();
+    let mut successes = 0u32;
+    let mut failures = 0u32;
+    let mut done = 0u32;
+    let mut cancelled = false;
+    // Per prompt: (line, model, generation IDs, failed images, cost)
+    let mut report: Vec<(usize, String, Vec<i64>, u32, f64)> = vec![];
+
+    for call in calls {
+        let req = &call.request;
+        let line = call.line.unwrap_or_default();
+        if report.last().is_none_or(|(l, model, ..)| *l != line || *model != req.model) {
+            if per_line {
+                let preview: String = req.prompt.chars().take(60).collect();
+                println!("Line {} ({}): {}", line, req.model, preview);
+                print_lint_warnings(req);
+            }
+            report.push((line, req.model.clone(), vec![], 0, 0.0));
+        }
+        let (_, _, ids, failed, cost) = report.last_mut().expect("pushed above");
+
+        let per_call = req.num_images.unwrap_or(1);
+        print!("[{}/{}] ", done + 1, total);
+        done += per_call;
+        match rt.block_on(perform_generation(db, req, &call.tags)) {
+            Ok(generations) => {
+                let id_strs: Vec<String> = generations.iter().map(|saved| saved.id.to_string()).collect();
+                let paths: Vec<&str> = generations.iter().map(|saved| saved.generation.image_path.as_str()).collect();
+                println!("ID {} -> {}", id_strs.join(", "), paths.join(", "));
+                for existing in generations.iter().filter_map(|saved| saved.duplicate_of) {
+                    println!("  Image is identical to generation {}", existing);
+                }
+                let gen_ids: Vec<i64> = generations.iter().map(|saved| saved.id).collect();
+                db.add_to_experiment(experiment_id, &gen_ids)?;
+                if let Some(&first) = gen_ids.first() {
+                    db.complete_batch_call(call.id, first)?;
+                }
+                *cost += generations.iter().filter_map(|saved| saved.generation.cost_estimate_usd).sum::<f64>();
+                successes += gen_ids.len() as u32;
+                ids.extend(gen_ids);
+            }
+            Err(e) if cancel::is_cancelled(&e) => {
+                println!("Cancelled");
+                cancelled = true;
+                break;
+            }
+            Err(e) => {
+                println!("Error: {}", e);
+                *failed += per_call;
+                failures += per_call;
+            }
+        }
+    }
+
+    if per_line {
+        println!("\n{:>5} {:<20} {:>4} {:>6} {:>9}  IDS", "LINE", "MODEL", "OK", "FAILED", "COST");
+        for (line, model, ids, failed, cost) in &report {
+            println!("{:>5} {:<20} {:>4} {:>6} {:>9}  {}", line, model, ids.len(), failed, format!("${:.4}", cost), join_ids(ids));
+        }
+        let cost: f64 = report.iter().map(|(_, _, _, _, cost)| cost).sum();
+        println!("Total: ${:.4}  (pixery experiment show {})", cost, experiment);
+    }
+    println!("\nBatch complete: {} succeeded, {} failed", successes, failures);
+    if cancelled || failures > 0 {
+        println!("Finish the rest with: pixery batch --resume {}", experiment);
+    }
+    notify::send(JobSource::Cli, &notify::Message::batch(successes as usize, failures as usize));
+    Ok(())
+}
+
+/// Warn (without stopping) when the prompt doesn't fit the model's guide
+fn print_lint_warnings(req: &GenerationRequest) {
+    for issue in lint::lint_prompt(&req.model, &req.prompt) {
+        eprintln!("Warning: {}", issue);
+    }
+}
+
+/// Runtime for commands that generate. Ctrl-C cancels the running job so it's
+/// recorded as cancelled instead of stalling as running; a second Ctrl-C, or one
+/// with nothing in flight, exits straight away.
+fn generation_runtime() -> Result<GenerationRuntime> {
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.spawn(async {
+        let mut interrupted = false;
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if interrupted || cancel::cancel_all() == 0 {
+                std::process::exit(130);
+            }
+            interrupted = true;
+            eprintln!("\nCancelling... (Ctrl-C again to quit)");
+        }
+    });
+    Ok(GenerationRuntime(rt))
+}
+
+/// Runtime that delivers pending webhooks before the command exits
+struct GenerationRuntime(tokio::runtime::Runtime);
+
+impl std::ops::Deref for GenerationRuntime {
+    type Target = tokio::runtime::Runtime;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Drop for GenerationRuntime {
+    fn drop(&mut self) {
+        self.0.block_on(notify::flush());
+    }
+}
+
+/// CLI progress: only the provider queue position is worth printing
+fn print_queue_progress(stage: ProgressStage) {
+    if let ProgressStage::InQueue { position: Some(pos) } = stage {
+        eprintln!("  queue position: {}", pos);
+    }
+}
+
+fn join_ids(ids: &[i64]) -> String {
+    ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// Wall time of a job: start to finish, or start to now while it runs
+fn job_duration(job: &models::Job) -> String {
+    let parse = |t: &str| chrono::NaiveDateTime::parse_from_str(t, "%Y-%m-%dT%H:%M:%S").ok();
+    let Some(started) = job.started_at.as_deref().and_then(parse) else {
+        return "-".to_string();
+    };
+    let end = match job.completed_at.as_deref() {
+        Some(t) => parse(t),
+        None => Some(Local::now().naive_local()),
+    };
+    match end {
+        Some(end) => {
+            let seconds = (end - started).num_seconds().max(0);
+            if seconds < 60 {
+                format!("{}s", seconds)
+            } else {
+                format!("{}m {}s", seconds / 60, seconds % 60)
+            }
+        }
+        None => "-".to_string(),
+    }
+}
+
+/// Clap parser for on/off switches
+fn parse_on_off(s: &str) -> std::result::Result<bool, String> {
+    match s.to_lowercase().as_str() {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(format!("expected 'on' or 'off', got '{}'", s)),
+    }
+}
+
+/// Clap parser for YYYY-MM-DD dates
+fn parse_date(s: &str) -> std::result::Result<String, String> {
+    chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(|date| date.to_string())
+        .map_err(|_| format!("'{}' is not a date (YYYY-MM-DD)", s))
+}
+
+/// Clap parser for prices in USD
+fn parse_price(s: &str) -> std::result::Result<f64, String> {
+    match s.trim_start_matches('$').parse::<f64>() {
+        Ok(value) if value.is_finite() && value >= 0.0 => Ok(value),
+        _ => Err(format!("'{}' is not a price in USD", s)),
+    }
+}
+
+/// Clap parser for --interval: seconds, at least a quarter of one
+fn parse_interval(s: &str) -> std::result::Result<f64, String> {
+    match s.trim_end_matches('s').parse::<f64>() {
+        Ok(value) if value.is_finite() && value >= 0.25 => Ok(value),
+        _ => Err(format!("'{}' is not an interval in seconds (at least 0.25)", s)),
+    }
+}
+
+/// Clap parser for 0-1 fractions (--strength, --confidence)
+fn parse_unit_interval(s: &str) -> std::result::Result<f64, String> {
+    let value: f64 = s.parse().map_err(|_| format!("{} is not a number", s))?;
+    if (0.0..=1.0).contains(&value) {
+        Ok(value)
+    } else {
+        Err(format!("{} is not between 0 and 1", value))
+    }
+}
+
 /// Resolve --ratio flag to (width, height), or (None, None) if not specified.
 fn resolve_ratio(ratio: Option<&str>) -> Result<(Option<i32>, Option<i32>)> {
     match ratio {
@@ -975,12 +5876,29 @@ fn resolve_ratio(ratio: Option<&str>) -> Result<(Option<i32>, Option<i32>)> {
     }
 }
 
+/// What `write_export` writes besides the images
+struct ExportOptions {
+    /// metadata.json sidecars
+    with_metadata: bool,
+    /// index.html gallery with thumbnails
+    html: bool,
+    /// Zero-padded position prefixes on the file names
+    numbered: bool,
+    /// XMP sidecars for photo managers
+    xmp: bool,
+    /// Resizing and format conversion of the images
+    conversion: Conversion,
+    /// File names from a template instead of the archive's
+    name_template: Option<NameTemplate>,
+}
+
 fn export_generations(
     db: &Database,
     ids: &[i64],
     tag: Option<&str>,
+    collection: Option<&str>,
     output: &Path,
-    with_metadata: bool,
+    options: &ExportOptions,
 ) -> Result<()> {
     // Collect generations to export
     let mut generations: Vec<Generation> = Vec::new();
@@ -1006,15 +5924,59 @@ fn export_generations(
         }
     }
 
+    if let Some(name) = collection {
+        let coll = db
+            .list_collections()?
+            .into_iter()
+            .find(|c| c.name == name)
+            .ok_or_else(|| anyhow::anyhow!("Collection '{}' not found", name))?;
+        let filter = ListFilter {
+            limit: None,
+            collection_id: Some(coll.id),
+            sort: Some(SortKey::Position),
+            ..Default::default()
+        };
+        for g in db.list_generations(&filter)? {
+            if !generations.iter().any(|existing| existing.id == g.id) {
+                generations.push(g);
+            }
+        }
+    }
+
+    write_export(db, &generations, collection, tag, output, options)
+}
+
+/// Copy generations' images (in the given order) into `output`, each with its
+/// assets named after it. The collection or tag they were selected by titles
+/// the gallery and fills name templates.
+fn write_export(
+    db: &Database,
+    generations: &[Generation],
+    collection: Option<&str>,
+    tag: Option<&str>,
+    output: &Path,
+    options: &ExportOptions,
+) -> Result<()> {
     if generations.is_empty() {
         println!("No generations to export");
         return Ok(());
     }
+    let ExportOptions { with_metadata, html, numbered, xmp, conversion, ref name_template } = *options;
+    let title = collection.or(tag).unwrap_or("Pixery export");
+    let digits = generations.len().to_string().len().max(3);
 
     std::fs::create_dir_all(output).context("Failed to create output directory")?;
 
+    let thumbs_dir = output.join("thumbs");
+    if html {
+        std::fs::create_dir_all(&thumbs_dir).context("Failed to create thumbs directory")?;
+    }
+
     let mut exported = 0;
-    for gen in &generations {
+    let mut assets_exported = 0;
+    let mut gallery_entries = vec![];
+    let mut used_stems = std::collections::HashSet::new();
+    for (index, gen) in generations.iter().enumerate() {
         let src = Path::new(&gen.image_path);
         if !src.exists() {
             eprintln!("Image file missing for ID {}, skipping", gen.id);
@@ -1023,10 +5985,35 @@ fn export_generations(
 
         let filename = src
             .file_name()
-            .ok_or_else(|| anyhow::anyhow!("Invalid image path for ID {}", gen.id))?;
-        let dest = output.join(filename);
-        std::fs::copy(src, &dest)
-            .with_context(|| format!("Failed to copy ID {} to {}", gen.id, dest.display()))?;
+            .ok_or_else(|| anyhow::anyhow!("Invalid image path for ID {}", gen.id))?
+            .to_string_lossy()
+            .to_string();
+        let filename = if let Some(template) = name_template {
+            let ctx = NameContext { generation: gen, collection, tag, counter: index + 1 };
+            let extension = src.extension().and_then(|e| e.to_str()).unwrap_or("png");
+            // Templates without {id} or {counter} can name two images alike
+            let base = template.render(&ctx);
+            let mut stem = base.clone();
+            let mut n = 1;
+            while !used_stems.insert(stem.clone()) {
+                n += 1;
+                stem = format!("{}-{}", base, n);
+            }
+            format!("{}.{}", stem, extension)
+        } else if numbered {
+            format!("{:0width$}-{}", index + 1, filename, width = digits)
+        } else {
+            filename
+        };
+        let (dest, converted) = convert::export_file(src, &output.join(&filename), &conversion)
+            .with_context(|| format!("Failed to export ID {}", gen.id))?;
+        if !converted && !conversion.is_copy() {
+            eprintln!("ID {} is a video or animation, copied unconverted", gen.id);
+        }
+        let filename = dest
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or(filename);
 
         if with_metadata {
             let meta_path = dest.with_extension("json");
@@ -1049,127 +6036,94 @@ fn export_generations(
                 .with_context(|| format!("Failed to write metadata for ID {}", gen.id))?;
         }
 
+        if xmp {
+            std::fs::write(dest.with_extension("xmp"), xmp::render(gen))
+                .with_context(|| format!("Failed to write XMP sidecar for ID {}", gen.id))?;
+        }
+
+        // Copied as they are, whatever the conversion: "<image name>-layers.psd"
+        let stem = dest.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        for asset in db.get_assets_for_generation(gen.id)? {
+            let src = Path::new(&asset.path);
+            let Some(name) = src.file_name().filter(|_| src.exists()) else {
+                eprintln!("Asset file {} missing for ID {}, skipping", asset.id, gen.id);
+                continue;
+            };
+            std::fs::copy(src, output.join(format!("{}-{}", stem, name.to_string_lossy())))
+                .with_context(|| format!("Failed to export asset {} of ID {}", asset.id, gen.id))?;
+            assets_exported += 1;
+        }
+
+        if html {
+            // Fall back to the full image when the thumbnail is missing
+            let thumb_src = gen
+                .thumb_path
+                .as_deref()
+                .map(Path::new)
+                .filter(|p| p.exists())
+                .unwrap_or(src);
+            let thumb_name = thumb_src
+                .file_name()
+                .ok_or_else(|| anyhow::anyhow!("Invalid thumbnail path for ID {}", gen.id))?;
+            std::fs::copy(thumb_src, thumbs_dir.join(thumb_name))
+                .with_context(|| format!("Failed to copy thumbnail for ID {}", gen.id))?;
+            gallery_entries.push(gallery::GalleryEntry {
+                generation: gen,
+                image: filename.clone(),
+                thumb: format!("thumbs/{}", thumb_name.to_string_lossy()),
+            });
+        }
+
         exported += 1;
     }
 
-    println!("Exported {} image(s) to {}", exported, output.display());
+    if assets_exported > 0 {
+        println!("Exported {} image(s) and {} asset(s) to {}", exported, assets_exported, output.display());
+    } else {
+        println!("Exported {} image(s) to {}", exported, output.display());
+    }
+
+    if html {
+        let page = gallery::render(title, &gallery_entries)?;
+        let index = output.join("index.html");
+        std::fs::write(&index, page).context("Failed to write index.html")?;
+        println!("Gallery: {}", index.display());
+    }
     Ok(())
 }
 
-fn import_image(
-    db: &Database,
-    source_path: &PathBuf,
-    prompt: &str,
-    model: &str,
-    tags: &[String],
-    reference_paths: &[String],
-    date_override: Option<&str>,
-    time_override: Option<&str>,
-) -> Result<()> {
-    // Read the source image
-    let data = std::fs::read(source_path).context("Failed to read source image")?;
-
-    // Try to extract date/time from filename pattern: name-YYYYMMDD-HHMMSS.ext
-    let filename = source_path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("");
-
-    let (extracted_date, extracted_time) = extract_datetime_from_filename(filename);
-
-    // Use override > extracted > current time
-    let now = Local::now();
-    let date = date_override
-        .map(|s| s.to_string())
-        .or(extracted_date)
-        .unwrap_or_else(|| now.format("%Y-%m-%d").to_string());
-
-    let time_str = time_override
-        .map(|s| s.replace(':', ""))
-        .or(extracted_time)
-        .unwrap_or_else(|| now.format("%H%M%S").to_string());
-
-    // Pad to 6 chars to prevent slice panics on short input
-    let time_str = format!("{:0<6}", time_str);
-
-    // Build full timestamp
-    let timestamp = format!(
-        "{}T{}:{}:{}",
-        date,
-        &time_str[0..2],
-        &time_str[2..4],
-        &time_str[4..6]
-    );
-
-    // Get model info for provider
-    let model_info = ModelInfo::find(model);
-    let provider = model_info
-        .as_ref()
-        .map(|m| m.provider.to_string())
-        .unwrap_or_else(|| "unknown".to_string());
-
-    // Save to archive (copies the file)
-    let slug = archive::slugify_prompt(prompt);
-    let (image_path, thumb_path, width, height, file_size) =
-        archive::save_image(&data, &date, &slug, &timestamp)?;
-
-    // Insert into database
-    let gen_id = db.insert_generation(
-        &slug,
-        prompt,
-        model,
-        &provider,
-        &timestamp,
-        &date,
-        image_path.to_str().unwrap(),
-        thumb_path.as_ref().and_then(|p| p.to_str()),
-        None, // generation_time_seconds - unknown for imports
-        None, // cost - unknown for imports
-        None, // seed
-        Some(width),
-        Some(height),
-        Some(file_size),
-        None, // parent_id
-        None, // negative_prompt
-    )?;
-
-    // Add tags
-    if !tags.is_empty() {
-        db.add_tags(gen_id, tags)?;
-    }
+fn import_image(db: &Database, source_path: &Path, options: &workflow::ImportOptions) -> Result<()> {
+    let imported = workflow::import_image(db, source_path, options)?;
+    let generation = &imported.generation;
 
-    // Store and link reference images
-    for ref_path in reference_paths {
-        let (hash, stored_path) = archive::store_reference(std::path::Path::new(ref_path))?;
-        let ref_id = db.get_or_create_reference(&hash, stored_path.to_str().unwrap())?;
-        db.link_reference(gen_id, ref_id)?;
+    if imported.duplicate {
+        let trashed = if generation.trashed_at.is_some() { " (in trash)" } else { "" };
+        let action = match options.on_duplicate {
+            models::DuplicateAction::Link => "added tags and references to it",
+            _ => "skipped",
+        };
+        println!("Already archived as ID {}{}; {}", imported.id, trashed, action);
+        println!("  Existing: {}", generation.image_path);
+        return Ok(());
     }
 
-    println!("Imported: {} (ID: {})", image_path.display(), gen_id);
+    println!("Imported: {} (ID: {})", generation.image_path, imported.id);
     println!("  Source: {}", source_path.display());
-    println!("  Date: {} Time: {}", date, time_str);
-    if !reference_paths.is_empty() {
-        println!("  References: {}", reference_paths.len());
+    println!("  Timestamp: {}", generation.timestamp);
+    if !options.reference_paths.is_empty() {
+        println!("  References: {}", options.reference_paths.len());
     }
 
     Ok(())
 }
 
-/// Extract date and time from filename patterns like:
-/// - name-YYYYMMDD-HHMMSS.ext
-/// - name-v1-YYYYMMDD-HHMMSS.ext
-fn extract_datetime_from_filename(filename: &str) -> (Option<String>, Option<String>) {
-    use std::sync::OnceLock;
-    static DATE_RE: OnceLock<regex::Regex> = OnceLock::new();
-    let re = DATE_RE.get_or_init(|| regex::Regex::new(r"(\d{4})(\d{2})(\d{2})-(\d{6})").unwrap());
-
-    if let Some(caps) = re.captures(filename) {
-        let date = format!("{}-{}-{}", &caps[1], &caps[2], &caps[3]);
-        let time = caps[4].to_string();
-        return (Some(date), Some(time));
-    }
-
-    (None, None)
+/// "#aabbcc 42%, #ddeeff 30%"
+fn format_palette(swatches: &[models::Swatch]) -> String {
+    swatches
+        .iter()
+        .map(|s| format!("{} {:.0}%", s.hex, s.share * 100.0))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 fn print_generations(generations: &[crate::models::Generation]) {
@@ -1201,6 +6155,243 @@ fn print_generations(generations: &[crate::models::Generation]) {
     }
 }
 
+/// `pixery models MODEL`: the model's daily quota, if models.json sets one
+fn print_quota(db: &Database, model_id: &str) -> Result<()> {
+    if let Some(quota) = workflow::model_quota(db, model_id)? {
+        println!("Daily quota: {} ({} used today, {} left)", quota.limit, quota.used, quota.remaining);
+    }
+    Ok(())
+}
+
+fn set_nsfw_flags(db: &Database, ids: &[i64], nsfw: bool) -> Result<()> {
+    for &id in ids {
+        if !db.set_nsfw(id, nsfw)? {
+            println!("Generation {} not found", id);
+        } else if nsfw {
+            println!("Flagged generation {} as NSFW", id);
+        } else {
+            println!("Cleared the NSFW flag on generation {}", id);
+        }
+    }
+    Ok(())
+}
+
+/// `pixery nsfw scan`: flag by prompt, then (with --classify) ask the vision
+/// model about the images whose prompt didn't give them away
+fn scan_nsfw(db: &Database, ids: &[i64], classify: bool, limit: usize) -> Result<()> {
+    let candidates = if ids.is_empty() {
+        db.list_unflagged(None)?
+    } else {
+        let mut candidates = vec![];
+        for &id in ids {
+            match db.get_generation(id)? {
+                Some(gen) if gen.nsfw => println!("[{}] already flagged", id),
+                Some(gen) => candidates.push((id, gen.prompt, gen.media_type, gen.image_path)),
+                None => eprintln!("Generation {} not found, skipping", id),
+            }
+        }
+        candidates
+    };
+
+    let mut flagged = 0;
+    let mut unclear = vec![];
+    for (id, prompt, media_type, image_path) in candidates {
+        if nsfw::prompt_is_nsfw(&prompt) {
+            db.set_nsfw(id, true)?;
+            println!("[{}] flagged (prompt)", id);
+            flagged += 1;
+        } else if media_type == MediaType::Image {
+            unclear.push((id, image_path));
+        }
+    }
+
+    if classify && !unclear.is_empty() {
+        if ids.is_empty() {
+            unclear.truncate(limit);
+        }
+        let settings = caption::load_settings();
+        println!("Classifying {} image(s) with {}...", unclear.len(), settings.model);
+        let rt = tokio::runtime::Runtime::new()?;
+        for (id, image_path) in unclear {
+            let path = archive::display_path(&image_path, archive::ThumbSize::Grid);
+            match rt.block_on(nsfw::classify(Path::new(&path), &settings)) {
+                Ok(true) => {
+                    db.set_nsfw(id, true)?;
+                    println!("[{}] flagged (classifier)", id);
+                    flagged += 1;
+                }
+                Ok(false) => {}
+                Err(e) => eprintln!("[{}] Error: {}", id, e),
+            }
+        }
+    }
+
+    println!("Flagged {} generation(s)", flagged);
+    Ok(())
+}
+
+fn print_maintenance_status(status: &MaintenanceStatus) {
+    match &status.last_run {
+        Some(t) => println!("Last run: {}", t),
+        None => {
+            println!("Maintenance has not run yet");
+            return;
+        }
+    }
+    if let Some(r) = &status.last_report {
+        println!("  Stalled jobs failed:  {}", r.stalled_jobs);
+        println!("  Old jobs removed:     {}", r.old_jobs);
+        println!("  Trash purged:         {}", r.trash_purged);
+        println!("  Collections purged:   {}", r.collections_purged);
+        println!("  Previews pruned:      {}", r.previews_pruned);
+        println!("  Unused refs removed:  {}", r.refs_removed);
+    }
+    if let Some(e) = &status.last_error {
+        println!("  Error: {}", e);
+    }
+}
+
+/// Spend grouped by one key (model or day)
+#[derive(serde::Serialize)]
+struct CostGroup {
+    key: String,
+    count: i64,
+    cost_usd: f64,
+}
+
+fn group_costs(rows: &[models::CostRow], key: impl Fn(&models::CostRow) -> &str) -> Vec<CostGroup> {
+    let mut groups: std::collections::BTreeMap<String, (i64, f64)> = std::collections::BTreeMap::new();
+    for row in rows {
+        let entry = groups.entry(key(row).to_string()).or_default();
+        entry.0 += 1;
+        entry.1 += row.cost_usd.unwrap_or(0.0);
+    }
+    groups
+        .into_iter()
+        .map(|(key, (count, cost_usd))| CostGroup { key, count, cost_usd })
+        .collect()
+}
+
+/// Write per-generation cost rows and per-model/per-day summaries as JSON
+/// (for a .json path) or as three CSV files. Outside USD, every amount gets
+/// a second column in the display currency (cost_eur, ...).
+fn export_cost_report(db: &Database, since: Option<&str>, path: &Path, currency: &DisplayCurrency) -> Result<()> {
+    let rows = db.list_costs(since)?;
+    let mut by_model = group_costs(&rows, |r| &r.model);
+    by_model.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd));
+    let by_day = group_costs(&rows, |r| &r.date);
+    let column = (!currency.is_usd()).then(|| format!("cost_{}", currency.code.to_lowercase()));
+    let with_converted = |mut value: serde_json::Value, usd: Option<f64>| {
+        if let (Some(column), Some(object)) = (&column, value.as_object_mut()) {
+            object.insert(column.clone(), serde_json::json!(usd.map(|c| currency.convert(c))));
+        }
+        value
+    };
+
+    // Spreadsheets choke on multi-line cells; a snippet is enough to recognize the image
+    let snippet = |prompt: &str| -> String {
+        let flat = prompt.split_whitespace().collect::<Vec<_>>().join(" ");
+        if flat.chars().count() > 80 {
+            format!("{}...", flat.chars().take(77).collect::<String>())
+        } else {
+            flat
+        }
+    };
+
+    let is_json = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    if is_json {
+        let groups = |groups: &[CostGroup]| -> Vec<serde_json::Value> {
+            groups.iter().map(|g| with_converted(serde_json::json!(g), Some(g.cost_usd))).collect()
+        };
+        let report = serde_json::json!({
+            "since": since,
+            "total_usd": rows.iter().filter_map(|r| r.cost_usd).fold(0.0, |a, b| a + b),
+            "currency": currency,
+            "generations": rows.iter().map(|r| with_converted(serde_json::json!({
+                "id": r.id,
+                "date": r.date,
+                "model": r.model,
+                "provider": r.provider,
+                "cost_usd": r.cost_usd,
+                "prompt": snippet(&r.prompt),
+            }), r.cost_usd)).collect::<Vec<_>>(),
+            "by_model": groups(&by_model),
+            "by_day": groups(&by_day),
+        });
+        std::fs::write(path, serde_json::to_string_pretty(&report)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("Wrote {} ({} generations)", path.display(), rows.len());
+        return Ok(());
+    }
+
+    let mut writer = csv::Writer::from_path(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut header = vec!["id", "date", "model", "provider", "cost_usd"];
+    header.extend(column.as_deref());
+    header.push("prompt");
+    writer.write_record(&header)?;
+    for r in &rows {
+        let mut record = vec![
+            r.id.to_string(),
+            r.date.clone(),
+            r.model.clone(),
+            r.provider.clone(),
+            r.cost_usd.map(|c| format!("{:.4}", c)).unwrap_or_default(),
+        ];
+        if column.is_some() {
+            record.push(r.cost_usd.map(|c| format!("{:.4}", currency.convert(c))).unwrap_or_default());
+        }
+        record.push(snippet(&r.prompt));
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+
+    // Summaries go next to the main file: costs.csv -> costs.by-model.csv, costs.by-day.csv
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("costs");
+    let mut written = vec![path.to_path_buf()];
+    for (suffix, header, groups) in [("by-model", "model", &by_model), ("by-day", "date", &by_day)] {
+        let summary_path = path.with_file_name(format!("{}.{}.csv", stem, suffix));
+        let mut writer = csv::Writer::from_path(&summary_path)
+            .with_context(|| format!("Failed to create {}", summary_path.display()))?;
+        let mut columns = vec![header, "count", "cost_usd"];
+        columns.extend(column.as_deref());
+        writer.write_record(&columns)?;
+        for g in groups.iter() {
+            let mut record = vec![g.key.clone(), g.count.to_string(), format!("{:.4}", g.cost_usd)];
+            if column.is_some() {
+                record.push(format!("{:.4}", currency.convert(g.cost_usd)));
+            }
+            writer.write_record(&record)?;
+        }
+        writer.flush()?;
+        written.push(summary_path);
+    }
+
+    for p in &written {
+        println!("Wrote {}", p.display());
+    }
+    println!();
+    Ok(())
+}
+
+/// One-line summary of an activity event's detail
+fn describe_event(event: &models::Event) -> String {
+    let detail = &event.detail;
+    let summary = if let Some(tags) = detail["tags"].as_array() {
+        let tags: Vec<&str> = tags.iter().filter_map(|t| t.as_str()).collect();
+        tags.join(", ")
+    } else if detail.get("after").is_some() {
+        let value = |key: &str| detail[key].as_str().unwrap_or("(none)").to_string();
+        format!("{} -> {}", truncate_string(&value("before"), 18), value("after"))
+    } else {
+        detail["collection"].as_str().or(detail["model"].as_str()).unwrap_or_default().to_string()
+    };
+    match (event.undo_of, event.undone) {
+        (Some(of), _) => format!("[undo of {}] {}", of, summary),
+        (None, true) => format!("[undone] {}", summary),
+        (None, false) => summary,
+    }
+}
+
 fn truncate_string(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
         s.to_string()
@@ -1209,6 +6400,58 @@ fn truncate_string(s: &str, max_len: usize) -> String {
     }
 }
 
+/// Completion candidates for --model
+fn complete_models() -> Vec<CompletionCandidate> {
+    let aliases = custom_models::load()
+        .aliases
+        .into_iter()
+        .map(|(alias, target)| CompletionCandidate::new(alias).help(Some(format!("alias for {}", target).into())));
+    ModelInfo::all()
+        .into_iter()
+        .map(|m| CompletionCandidate::new(m.id).help(Some(m.display_name.into())))
+        .chain(aliases)
+        .collect()
+}
+
+/// Completion candidates for archive profile names
+pub fn complete_editors() -> Vec<CompletionCandidate> {
+    editor::load_settings().names().into_iter().map(CompletionCandidate::new).collect()
+}
+
+pub fn complete_profiles() -> Vec<CompletionCandidate> {
+    let config = profiles::load_config();
+    config
+        .names()
+        .into_iter()
+        .map(|name| {
+            let root = config.root_of(&name).unwrap_or_default();
+            CompletionCandidate::new(name).help(Some(root.display().to_string().into()))
+        })
+        .collect()
+}
+
+/// Completion for tag arguments. Tags are comma-separated, so only the part
+/// after the last comma is completed.
+fn complete_tags(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return vec![];
+    };
+    let (done, partial) = match current.rfind(',') {
+        Some(i) => current.split_at(i + 1),
+        None => ("", current),
+    };
+    // Completion runs on every <Tab>; a missing archive just means no candidates
+    let Ok(db) = Database::open(&archive::db_path()) else {
+        return vec![];
+    };
+    db.list_tags()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|t| t.name.starts_with(partial))
+        .map(|t| CompletionCandidate::new(format!("{}{}", done, t.name)).help(Some(format!("{} images", t.count).into())))
+        .collect()
+}
+
 
 fn regenerate_thumbnails(db: &Database, if_smaller: Option<u32>, dry_run: bool) -> Result<()> {
     use image::GenericImageView;
@@ -1219,14 +6462,13 @@ fn regenerate_thumbnails(db: &Database, if_smaller: Option<u32>, dry_run: bool)
     };
     let generations = db.list_generations(&filter)?;
 
-    let target_size = archive::THUMBNAIL_SIZE;
     let mut regenerated = 0;
     let mut skipped = 0;
     let mut errors = 0;
 
     println!(
-        "Regenerating thumbnails at {}px{}",
-        target_size,
+        "Regenerating thumbnails at {}{}",
+        archive::ThumbSize::ALL.map(|s| format!("{}px", s.pixels())).join(" and "),
         if dry_run { " (dry run)" } else { "" }
     );
     println!();
@@ -1241,16 +6483,12 @@ fn regenerate_thumbnails(db: &Database, if_smaller: Option<u32>, dry_run: bool)
             continue;
         }
 
-        // Determine thumb path
-        let stem = image_path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("image");
-        let thumb_path = image_path.with_file_name(format!("{}.thumb.jpg", stem));
+        let thumb_path = archive::thumbnail_path(image_path, archive::ThumbSize::Grid);
 
-        // Check if we should regenerate based on --if-smaller
+        // Check if we should regenerate based on --if-smaller (a missing preview always is)
         if let Some(min_size) = if_smaller {
-            if thumb_path.exists() {
+            let preview_exists = archive::thumbnail_path(image_path, archive::ThumbSize::Preview).exists();
+            if preview_exists && thumb_path.exists() {
                 if let Ok(existing) = image::open(&thumb_path) {
                     let (w, h) = existing.dimensions();
                     if w >= min_size && h >= min_size {
@@ -1267,12 +6505,14 @@ fn regenerate_thumbnails(db: &Database, if_smaller: Option<u32>, dry_run: bool)
             continue;
         }
 
-        // Load source and generate new thumbnail
-        match image::open(image_path) {
+        // Load source (a video's first frame) and generate new thumbnails
+        match archive::thumbnail_source(image_path) {
             Ok(img) => {
-                let thumb = img.thumbnail(target_size, target_size);
-                match thumb.save(&thumb_path) {
-                    Ok(_) => {
+                let written = archive::ThumbSize::ALL
+                    .iter()
+                    .try_for_each(|&size| archive::write_thumbnail(image_path, &img, size).map(|_| ()));
+                match written {
+                    Ok(()) => {
                         println!("  [OK] ID {}: {}", gen.id, gen.slug);
                         regenerated += 1;
 
@@ -1307,7 +6547,7 @@ fn regenerate_thumbnails(db: &Database, if_smaller: Option<u32>, dry_run: bool)
 fn view_images(db: &Database, ids: &[i64], width: Option<u32>, height: Option<u32>) -> Result<()> {
     use image::GenericImageView;
 
-    let output_dir = PathBuf::from("/tmp/pixery-preview");
+    let output_dir = archive::preview_dir();
     std::fs::create_dir_all(&output_dir).context("Failed to create preview directory")?;
 
     for id in ids {
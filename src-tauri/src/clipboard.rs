@@ -0,0 +1,66 @@
+use anyhow::{Context, Result};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::archive;
+
+/// Clipboard kept open for the life of the process. On X11 and Wayland the
+/// copied image is served by its owner, so it vanishes if this is dropped.
+fn clipboard() -> Result<&'static Mutex<arboard::Clipboard>> {
+    static CLIPBOARD: OnceLock<Mutex<arboard::Clipboard>> = OnceLock::new();
+    if let Some(clipboard) = CLIPBOARD.get() {
+        return Ok(clipboard);
+    }
+    let clipboard = arboard::Clipboard::new().context("Failed to open the clipboard")?;
+    Ok(CLIPBOARD.get_or_init(|| Mutex::new(clipboard)))
+}
+
+/// Text on the clipboard, trimmed; an error if there is none
+pub fn read_text() -> Result<String> {
+    let text = clipboard()?
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_text()
+        .context("Clipboard doesn't contain text")?;
+    let text = text.trim();
+    if text.is_empty() {
+        anyhow::bail!("Clipboard text is empty");
+    }
+    Ok(text.to_string())
+}
+
+/// Store the clipboard image as a reference (PNG, deduplicated by hash) and
+/// return its path
+pub fn store_image_as_reference() -> Result<PathBuf> {
+    let data = clipboard()?
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_image()
+        .context("Clipboard doesn't contain an image")?;
+    let img = image::RgbaImage::from_raw(data.width as u32, data.height as u32, data.bytes.into_owned())
+        .context("Clipboard image has an unexpected size")?;
+
+    let mut png = Vec::new();
+    img.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .context("Failed to encode clipboard image")?;
+    let (_, path) = archive::store_reference_bytes(&png, "png")?;
+    Ok(path)
+}
+
+/// Put an image file on the clipboard
+pub fn copy_image(path: &Path) -> Result<()> {
+    let img = image::open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?
+        .to_rgba8();
+    let (width, height) = img.dimensions();
+    clipboard()?
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .set_image(arboard::ImageData {
+            width: width as usize,
+            height: height as usize,
+            bytes: img.into_raw().into(),
+        })
+        .context("Failed to copy image to the clipboard")
+}
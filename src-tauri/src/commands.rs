@@ -1,186 +1,374 @@
-use std::sync::Mutex;
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager, State};
 
 use crate::archive;
-use crate::db::Database;
-use crate::models::{self, CostSummary, Generation, GenerateParams, Job, JobSource, ListFilter, ModelInfo, Reference, TagCount};
+use crate::cancel;
+use crate::clipboard;
+use crate::currency;
+use crate::db::{self, Database, DbPool};
+use crate::deeplink;
+use crate::error::PixeryError;
+use crate::models::{self, CostSummary, DisplayCurrency, FilterFacets, Generation, GenerateParams, GenerationPage, GenerationProgress, GenerationRequest, Job, JobSource, ListFilter, MaintenanceStatus, ModelInfo, ModelQuota, Preset, ProgressStage, Reference, ReferenceInfo, SortKey, Stats, TagCount};
+use crate::notify;
+use crate::nsfw;
+use crate::slideshow::{self, Slideshow};
 use crate::workflow;
 
 pub struct AppState {
-    pub db: Mutex<Database>,
+    pub db: DbPool,
+    /// Guest mode: lib.rs refuses every command `readonly::allows` doesn't list
+    pub read_only: bool,
+}
+
+/// Run a database call off the async executor, with errors as strings for the frontend
+async fn with_db<T, F>(state: &State<'_, AppState>, f: F) -> Result<T, PixeryError>
+where
+    T: Send + 'static,
+    F: FnOnce(&Database) -> anyhow::Result<T> + Send + 'static,
+{
+    state.db.run(f).await.map_err(PixeryError::from)
 }
 
 #[tauri::command]
 pub async fn generate_image(
+    app: AppHandle,
     state: State<'_, AppState>,
     params: GenerateParams,
-) -> Result<Generation, String> {
-    // Phase 1: create job (lock, then drop before await)
-    let (job_id, estimated_cost, provider) = {
-        let db = state.db.lock().map_err(|e| e.to_string())?;
-        workflow::prepare_generation(
-            &db,
-            &params.model,
-            &params.prompt,
-            &params.tags,
+) -> Result<Generation, PixeryError> {
+    let mut req = GenerationRequest::from(&params);
+    let mut tags = params.tags.clone();
+    workflow::apply_auto_title(&mut req).await;
+
+    // Phase 1: apply preset and create job
+    let preset = params.preset.clone();
+    let (req, tags, (job_id, estimated_cost, provider)) = with_db(&state, move |db| {
+        if let Some(name) = &preset {
+            let preset = db
+                .get_preset(name)?
+                .ok_or_else(|| anyhow::anyhow!("Preset '{}' not found", name))?;
+            preset.apply(&mut req, &mut tags);
+        }
+        workflow::apply_auto_negative(&mut req);
+        req.reference_paths = workflow::resolve_references(db, &req.reference_paths)?;
+        workflow::validate_references(&req)?;
+        let job = workflow::prepare_generation(
+            db,
+            &req.model,
+            &req.prompt,
+            &tags,
             JobSource::Gui,
-            params.reference_paths.len(),
-        )
-        .map_err(|e| e.to_string())?
+            req.reference_paths.len(),
+            Some(&req),
+        )?;
+        Ok((req, tags, job))
+    })
+    .await?;
+
+    let notify_app = app.clone();
+    let progress = move |stage: ProgressStage| {
+        let _ = app.emit("generation-progress", GenerationProgress { job_id, stage });
     };
-
-    // Phase 2: async generation (no db lock held)
-    let result = match crate::providers::generate(
-        &params.model,
-        &params.prompt,
-        &params.reference_paths,
-        params.negative_prompt.as_deref(),
-        params.width,
-        params.height,
-        None, // ip_scale: GUI doesn't expose this yet
-    ).await {
+    progress(ProgressStage::Queued);
+
+    // Phase 2: async generation (no connection held)
+    let policy = crate::providers::RetryPolicy::from_env();
+    let call = crate::providers::with_retry(
+        &policy,
+        || crate::providers::generate(&req, &progress),
+        |retry, _| {
+            // Fire and forget; the count is informational
+            let pool = state.db.clone();
+            tokio::task::spawn_blocking(move || {
+                if let Ok(db) = pool.get() {
+                    let _ = db.update_job_retry_count(job_id, retry);
+                }
+            });
+        },
+    );
+    let result = match cancel::run(job_id, call).await {
         Ok(r) => r,
         Err(e) => {
-            let db = state.db.lock().map_err(|e| e.to_string())?;
-            let _ = db.update_job_failed(job_id, &e.to_string());
-            return Err(e.to_string());
+            let error = PixeryError::from(&e);
+            if let Some(message) = notify::Message::failed(&req.model, &e) {
+                notify_gui(&notify_app, &message);
+            }
+            let _ = with_db(&state, move |db| workflow::record_job_error(db, job_id, &e)).await;
+            return Err(error);
         }
     };
 
-    // Phase 3: save results (lock again)
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    let (_gen_id, generation) = workflow::complete_generation(
-        &db,
-        job_id,
-        &params.prompt,
-        &params.model,
-        &provider,
-        &params.tags,
-        &params.reference_paths,
-        &result,
-        estimated_cost,
-        params.negative_prompt.as_deref(),
-    )
-    .map_err(|e| e.to_string())?;
+    // Phase 3: save results (the GUI asks for one image per call)
+    let mut generation = with_db(&state, move |db| {
+        workflow::complete_generation(
+            db,
+            job_id,
+            &req,
+            &provider,
+            &tags,
+            &result,
+            estimated_cost,
+            &progress,
+        )
+    })
+    .await?
+    .into_iter()
+    .next()
+    .map(|saved| saved.generation)
+    .ok_or_else(|| PixeryError::Provider("Provider returned no images".to_string()))?;
+    if nsfw::classify_new(&generation, &nsfw::load_settings()).await {
+        let id = generation.id;
+        with_db(&state, move |db| db.set_nsfw(id, true)).await?;
+        generation.nsfw = true;
+    }
+    notify_gui(&notify_app, &notify::Message::generated(&generation));
 
     // Copy to destination if requested
     if let Some(ref dest) = params.copy_to {
         archive::copy_to(
             std::path::Path::new(&generation.image_path),
             std::path::Path::new(dest),
-        )
-        .map_err(|e| e.to_string())?;
+        )?;
     }
 
     Ok(generation)
 }
 
+/// Desktop notification for a GUI generation; skipped while the window has focus
+/// since the result is already on screen
+fn notify_gui(app: &AppHandle, message: &notify::Message) {
+    use tauri_plugin_notification::NotificationExt;
+
+    if !notify::load_settings().gui {
+        return;
+    }
+    if app.webview_windows().values().any(|w| w.is_focused().unwrap_or(false)) {
+        return;
+    }
+    let mut builder = app.notification().builder().title(&message.title).body(&message.body);
+    if let Some(image) = &message.image {
+        builder = builder.icon(image);
+    }
+    let _ = builder.show();
+}
+
+/// NSFW-flagged generations stay out of the gallery unless nsfw.json shows them
+fn gallery_filter(filter: ListFilter) -> ListFilter {
+    ListFilter {
+        hide_nsfw: filter.hide_nsfw || !nsfw::load_settings().show,
+        ..filter
+    }
+}
+
+#[tauri::command]
+pub async fn list_generations(
+    state: State<'_, AppState>,
+    filter: ListFilter,
+) -> Result<Vec<Generation>, PixeryError> {
+    let filter = gallery_filter(filter);
+    with_db(&state, move |db| db.list_generations(&filter)).await
+}
+
 #[tauri::command]
-pub fn list_generations(
+pub async fn list_generations_page(
     state: State<'_, AppState>,
     filter: ListFilter,
-) -> Result<Vec<Generation>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.list_generations(&filter).map_err(|e| e.to_string())
+    page_size: Option<i64>,
+) -> Result<GenerationPage, PixeryError> {
+    let page_size = page_size.unwrap_or(50).clamp(1, 500);
+    let filter = gallery_filter(filter);
+    with_db(&state, move |db| db.list_generations_page(&filter, page_size)).await
 }
 
+/// Random sample of generations matching a filter (its sort and paging are ignored)
 #[tauri::command]
-pub fn search_generations(
+pub async fn random_generations(
+    state: State<'_, AppState>,
+    filter: ListFilter,
+    count: i64,
+) -> Result<Vec<Generation>, PixeryError> {
+    let filter = ListFilter {
+        limit: Some(count.max(1)),
+        offset: None,
+        cursor: None,
+        sort: Some(SortKey::Random),
+        ..gallery_filter(filter)
+    };
+    with_db(&state, move |db| db.list_generations(&filter)).await
+}
+
+#[tauri::command]
+pub async fn count_generations(state: State<'_, AppState>, filter: ListFilter) -> Result<i64, PixeryError> {
+    let filter = gallery_filter(filter);
+    with_db(&state, move |db| db.count_generations(&filter)).await
+}
+
+#[tauri::command]
+pub async fn get_filter_facets(state: State<'_, AppState>, filter: ListFilter) -> Result<FilterFacets, PixeryError> {
+    let filter = gallery_filter(filter);
+    with_db(&state, move |db| db.filter_facets(&filter)).await
+}
+
+#[tauri::command]
+pub async fn search_generations(
     state: State<'_, AppState>,
     query: String,
     limit: i64,
-) -> Result<Vec<Generation>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.search_generations(&query, limit).map_err(|e| e.to_string())
+) -> Result<Vec<Generation>, PixeryError> {
+    let include_nsfw = nsfw::load_settings().show;
+    with_db(&state, move |db| db.search_generations(&query, limit, include_nsfw)).await
 }
 
 #[tauri::command]
-pub fn get_generation(state: State<'_, AppState>, id: i64) -> Result<Option<Generation>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_generation(id).map_err(|e| e.to_string())
+pub async fn get_generation(state: State<'_, AppState>, id: i64) -> Result<Option<Generation>, PixeryError> {
+    with_db(&state, move |db| db.get_generation(id)).await
 }
 
 #[tauri::command]
-pub fn toggle_starred(state: State<'_, AppState>, id: i64) -> Result<bool, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.toggle_starred(id).map_err(|e| e.to_string())
+pub async fn toggle_starred(state: State<'_, AppState>, id: i64) -> Result<bool, PixeryError> {
+    with_db(&state, move |db| db.toggle_starred(id)).await
 }
 
 #[tauri::command]
-pub fn trash_generation(state: State<'_, AppState>, id: i64) -> Result<bool, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.trash_generation(id).map_err(|e| e.to_string())
+pub async fn set_locked(state: State<'_, AppState>, id: i64, locked: bool) -> Result<bool, PixeryError> {
+    with_db(&state, move |db| db.set_locked(id, locked)).await
 }
 
 #[tauri::command]
-pub fn trash_generations(state: State<'_, AppState>, ids: Vec<i64>) -> Result<usize, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.trash_generations(&ids).map_err(|e| e.to_string())
+pub async fn set_nsfw(state: State<'_, AppState>, id: i64, nsfw: bool) -> Result<bool, PixeryError> {
+    with_db(&state, move |db| db.set_nsfw(id, nsfw)).await
 }
 
 #[tauri::command]
-pub fn restore_generation(state: State<'_, AppState>, id: i64) -> Result<bool, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.restore_generation(id).map_err(|e| e.to_string())
+pub async fn trash_generation(state: State<'_, AppState>, id: i64) -> Result<bool, PixeryError> {
+    with_db(&state, move |db| db.trash_generation(id)).await
 }
 
 #[tauri::command]
-pub fn permanently_delete_generation(state: State<'_, AppState>, id: i64) -> Result<bool, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    if let Some(path) = db.permanently_delete_generation(id).map_err(|e| e.to_string())? {
-        archive::delete_image(std::path::Path::new(&path)).map_err(|e| e.to_string())?;
-        Ok(true)
-    } else {
-        Ok(false)
-    }
+pub async fn trash_generations(state: State<'_, AppState>, ids: Vec<i64>) -> Result<usize, PixeryError> {
+    with_db(&state, move |db| db.trash_generations(&ids)).await
+}
+
+#[tauri::command]
+pub async fn restore_generation(state: State<'_, AppState>, id: i64) -> Result<bool, PixeryError> {
+    with_db(&state, move |db| db.restore_generation(id)).await
+}
+
+#[tauri::command]
+pub async fn permanently_delete_generation(state: State<'_, AppState>, id: i64) -> Result<bool, PixeryError> {
+    with_db(&state, move |db| {
+        if let Some(path) = db.permanently_delete_generation(id)? {
+            archive::delete_image(std::path::Path::new(&path))?;
+            archive::delete_assets(id)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn update_prompt(state: State<'_, AppState>, id: i64, prompt: String) -> Result<(), PixeryError> {
+    with_db(&state, move |db| db.update_prompt(id, &prompt)).await
+}
+
+#[tauri::command]
+pub async fn caption_generation(state: State<'_, AppState>, id: i64) -> Result<Generation, PixeryError> {
+    let gen = with_db(&state, move |db| db.get_generation(id))
+        .await?
+        .ok_or_else(|| PixeryError::NotFound(format!("Generation {} not found", id)))?;
+    let path = archive::display_path(&gen.image_path, archive::ThumbSize::Grid);
+
+    // No connection held while the vision model runs
+    let settings = crate::caption::load_settings();
+    let text = crate::caption::caption_image(std::path::Path::new(&path), &settings).await?;
+
+    with_db(&state, move |db| {
+        db.set_caption(id, &text)?;
+        db.get_generation(id)
+    })
+    .await?
+    .ok_or_else(|| PixeryError::NotFound(format!("Generation {} not found", id)))
+}
+
+#[tauri::command]
+pub async fn copy_image_to_clipboard(state: State<'_, AppState>, id: i64) -> Result<(), PixeryError> {
+    with_db(&state, move |db| {
+        let gen = db
+            .get_generation(id)?
+            .ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+        clipboard::copy_image(std::path::Path::new(&gen.image_path))
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn update_title(state: State<'_, AppState>, id: i64, title: Option<String>) -> Result<(), PixeryError> {
+    with_db(&state, move |db| db.update_title(id, title.as_deref())).await
 }
 
 #[tauri::command]
-pub fn update_prompt(state: State<'_, AppState>, id: i64, prompt: String) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.update_prompt(id, &prompt).map_err(|e| e.to_string())
+pub async fn add_tags(state: State<'_, AppState>, id: i64, tags: Vec<String>) -> Result<(), PixeryError> {
+    with_db(&state, move |db| db.add_tags(id, &tags)).await
 }
 
 #[tauri::command]
-pub fn update_title(state: State<'_, AppState>, id: i64, title: Option<String>) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.update_title(id, title.as_deref()).map_err(|e| e.to_string())
+pub async fn remove_tag(state: State<'_, AppState>, id: i64, tag: String) -> Result<(), PixeryError> {
+    with_db(&state, move |db| db.remove_tag(id, &tag)).await
 }
 
 #[tauri::command]
-pub fn add_tags(state: State<'_, AppState>, id: i64, tags: Vec<String>) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.add_tags(id, &tags).map_err(|e| e.to_string())
+pub async fn list_tags(state: State<'_, AppState>) -> Result<Vec<TagCount>, PixeryError> {
+    with_db(&state, move |db| db.list_tags()).await
 }
 
+/// Models with today's prices from the pricing table
 #[tauri::command]
-pub fn remove_tag(state: State<'_, AppState>, id: i64, tag: String) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.remove_tag(id, &tag).map_err(|e| e.to_string())
+pub async fn list_models(state: State<'_, AppState>) -> Result<Vec<ModelInfo>, PixeryError> {
+    with_db(&state, workflow::priced_models).await
 }
 
+/// Daily quotas from models.json with today's use
 #[tauri::command]
-pub fn list_tags(state: State<'_, AppState>) -> Result<Vec<TagCount>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.list_tags().map_err(|e| e.to_string())
+pub async fn get_model_quotas(state: State<'_, AppState>) -> Result<Vec<ModelQuota>, PixeryError> {
+    with_db(&state, workflow::model_quotas).await
 }
 
 #[tauri::command]
-pub fn list_models() -> Vec<ModelInfo> {
-    ModelInfo::all()
+pub fn get_model_catalog() -> crate::providers::fal::Catalog {
+    crate::providers::fal::load_catalog()
 }
 
 #[tauri::command]
-pub fn get_cost_summary(
+pub async fn refresh_model_catalog() -> Result<crate::providers::fal::Catalog, PixeryError> {
+    crate::providers::fal::refresh_catalog().await.map_err(PixeryError::from)
+}
+
+#[tauri::command]
+pub async fn list_presets(state: State<'_, AppState>) -> Result<Vec<Preset>, PixeryError> {
+    with_db(&state, move |db| db.list_presets()).await
+}
+
+#[tauri::command]
+pub async fn get_cost_summary(
     state: State<'_, AppState>,
     since: Option<String>,
-) -> Result<CostSummary, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+) -> Result<CostSummary, PixeryError> {
     let since_date = match since.as_deref() {
         Some(s) => models::parse_since(s)?,
         None => None,
     };
-    db.get_cost_summary(since_date.as_deref()).map_err(|e| e.to_string())
+    let mut summary = with_db(&state, move |db| db.get_cost_summary(since_date.as_deref())).await?;
+    summary.currency = currency::display().await;
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn get_stats(state: State<'_, AppState>, since: Option<String>) -> Result<Stats, PixeryError> {
+    let since_date = match since.as_deref() {
+        Some(s) => models::parse_since(s)?,
+        None => None,
+    };
+    with_db(&state, move |db| db.get_stats(since_date.as_deref())).await
 }
 
 #[tauri::command]
@@ -190,78 +378,296 @@ pub fn get_image_path(path: String) -> String {
     format!("asset://localhost/{}", path)
 }
 
+/// Store image bytes dropped onto the GUI as a reference; returns the stored
+/// path for `reference_paths`. Takes a raw body (`invoke('stage_reference', bytes)`).
+#[tauri::command]
+pub async fn stage_reference(request: tauri::ipc::Request<'_>) -> Result<String, PixeryError> {
+    let tauri::ipc::InvokeBody::Raw(data) = request.body() else {
+        return Err("Expected raw image bytes".into());
+    };
+    let data = data.clone();
+    tokio::task::spawn_blocking(move || archive::stage_reference(&data))
+        .await
+        .map_err(|e| e.to_string())?
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(PixeryError::from)
+}
+
+#[tauri::command]
+pub async fn get_references(state: State<'_, AppState>, id: i64) -> Result<Vec<Reference>, PixeryError> {
+    with_db(&state, move |db| db.get_references_for_generation(id)).await
+}
+
+#[tauri::command]
+pub async fn list_references(state: State<'_, AppState>) -> Result<Vec<ReferenceInfo>, PixeryError> {
+    with_db(&state, |db| db.list_references()).await
+}
+
+#[tauri::command]
+pub async fn get_reference_generations(state: State<'_, AppState>, id: i64) -> Result<Vec<Generation>, PixeryError> {
+    with_db(&state, move |db| db.get_generations_for_reference(id)).await
+}
+
 #[tauri::command]
-pub fn get_references(state: State<'_, AppState>, id: i64) -> Result<Vec<Reference>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_references_for_generation(id).map_err(|e| e.to_string())
+pub async fn list_jobs(state: State<'_, AppState>) -> Result<Vec<Job>, PixeryError> {
+    with_db(&state, move |db| db.list_active_jobs()).await
 }
 
 #[tauri::command]
-pub fn list_jobs(state: State<'_, AppState>) -> Result<Vec<Job>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.list_active_jobs().map_err(|e| e.to_string())
+pub async fn list_failed_jobs(state: State<'_, AppState>, limit: Option<i64>) -> Result<Vec<Job>, PixeryError> {
+    with_db(&state, move |db| db.list_recent_failed_jobs(limit.unwrap_or(10))).await
 }
 
+/// Re-run recent failed jobs within a cost cap. Runs on its own thread and
+/// connection like the scheduler, so the retries don't hold the GUI's pool.
 #[tauri::command]
-pub fn list_failed_jobs(state: State<'_, AppState>, limit: Option<i64>) -> Result<Vec<Job>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.list_recent_failed_jobs(limit.unwrap_or(10)).map_err(|e| e.to_string())
+pub async fn retry_failed_jobs(hours: Option<i64>, max_cost: Option<f64>) -> Result<models::RetryReport, PixeryError> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    std::thread::spawn(move || {
+        let result = (|| {
+            let db = Database::open(&archive::db_path())?;
+            let rt = tokio::runtime::Runtime::new()?;
+            rt.block_on(workflow::retry_failed(
+                &db,
+                hours.unwrap_or(24),
+                max_cost.unwrap_or(workflow::DEFAULT_RETRY_COST_CAP),
+                JobSource::Gui,
+                &|_| {},
+            ))
+        })();
+        let _ = tx.send(result.map_err(PixeryError::from));
+    });
+    rx.await.map_err(|e| e.to_string())?
+}
+
+/// Cancel an in-flight generation started by this app. Jobs from the CLI or another
+/// window run in a different process and have to be stopped there (Ctrl-C).
+#[tauri::command]
+pub fn cancel_job(job_id: i64) -> Result<(), PixeryError> {
+    if cancel::cancel(job_id) {
+        Ok(())
+    } else {
+        Err(format!("Job {} is not running in this window (CLI jobs can be stopped with Ctrl-C)", job_id).into())
+    }
 }
 
 // Collection commands
 
 #[tauri::command]
-pub fn list_collections(state: State<'_, AppState>) -> Result<Vec<models::Collection>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.list_collections().map_err(|e| e.to_string())
+pub async fn list_collections(state: State<'_, AppState>) -> Result<Vec<models::Collection>, PixeryError> {
+    with_db(&state, move |db| db.list_collections()).await
 }
 
 #[tauri::command]
-pub fn create_collection(
+pub async fn create_collection(
     state: State<'_, AppState>,
     name: String,
     description: Option<String>,
-) -> Result<i64, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.create_collection(&name, description.as_deref()).map_err(|e| e.to_string())
+) -> Result<i64, PixeryError> {
+    with_db(&state, move |db| db.create_collection(&name, description.as_deref())).await
 }
 
 #[tauri::command]
-pub fn add_to_collection(
+pub async fn add_to_collection(
     state: State<'_, AppState>,
     generation_id: i64,
     collection_name: String,
-) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.add_to_collection(generation_id, &collection_name).map_err(|e| e.to_string())
+) -> Result<(), PixeryError> {
+    with_db(&state, move |db| db.add_to_collection(generation_id, &collection_name)).await
 }
 
 #[tauri::command]
-pub fn remove_from_collection(
+pub async fn remove_from_collection(
     state: State<'_, AppState>,
     generation_id: i64,
     collection_name: String,
-) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.remove_from_collection(generation_id, &collection_name).map_err(|e| e.to_string())
+) -> Result<(), PixeryError> {
+    with_db(&state, move |db| db.remove_from_collection(generation_id, &collection_name)).await
+}
+
+#[tauri::command]
+pub async fn delete_collection(
+    state: State<'_, AppState>,
+    name: String,
+    force: Option<bool>,
+) -> Result<bool, PixeryError> {
+    with_db(&state, move |db| {
+        let members = db.collection_membership_count(&name)?;
+        if members > db::COLLECTION_DELETE_CONFIRM && !force.unwrap_or(false) {
+            anyhow::bail!("'{}' has {} memberships; confirm to delete it", name, members);
+        }
+        db.delete_collection(&name)
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn restore_collection(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<usize, PixeryError> {
+    with_db(&state, move |db| db.restore_collection(&name)).await
 }
 
+/// Move generations to the front of a collection's order, in the order given
 #[tauri::command]
-pub fn delete_collection(
+pub async fn reorder_collection(
     state: State<'_, AppState>,
     name: String,
-) -> Result<bool, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.delete_collection(&name).map_err(|e| e.to_string())
+    ids: Vec<i64>,
+) -> Result<(), PixeryError> {
+    with_db(&state, move |db| db.reorder_collection(&name, &ids)).await
+}
+
+/// Set the cover generation of a collection; None goes back to the first in order
+#[tauri::command]
+pub async fn set_collection_cover(
+    state: State<'_, AppState>,
+    name: String,
+    generation_id: Option<i64>,
+) -> Result<(), PixeryError> {
+    with_db(&state, move |db| db.set_collection_cover(&name, generation_id)).await
+}
+
+#[tauri::command]
+pub async fn list_smart_collections(state: State<'_, AppState>) -> Result<Vec<models::SmartCollection>, PixeryError> {
+    with_db(&state, move |db| db.list_smart_collections()).await
+}
+
+/// Save a filter expression (e.g. "model=noobai AND starred") as a smart collection
+#[tauri::command]
+pub async fn create_smart_collection(
+    state: State<'_, AppState>,
+    name: String,
+    filter: String,
+    description: Option<String>,
+) -> Result<i64, PixeryError> {
+    with_db(&state, move |db| db.create_smart_collection(&name, description.as_deref(), &filter)).await
+}
+
+#[tauri::command]
+pub async fn delete_smart_collection(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<bool, PixeryError> {
+    with_db(&state, move |db| db.delete_smart_collection(&name)).await
+}
+
+/// Runs recorded by `pixery ab` and `pixery batch`, newest first. Their
+/// generations are listed with `ListFilter::experiment_id`.
+#[tauri::command]
+pub async fn list_experiments(state: State<'_, AppState>) -> Result<Vec<models::Experiment>, PixeryError> {
+    with_db(&state, move |db| db.list_experiments()).await
+}
+
+#[tauri::command]
+pub async fn get_experiment(
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<Option<models::Experiment>, PixeryError> {
+    with_db(&state, move |db| db.get_experiment(&name)).await
+}
+
+/// Recent changes, newest first; one generation's when `generation_id` is given
+#[tauri::command]
+pub async fn activity_feed(
+    state: State<'_, AppState>,
+    limit: Option<i64>,
+    generation_id: Option<i64>,
+) -> Result<Vec<models::Event>, PixeryError> {
+    with_db(&state, move |db| db.list_events(limit.unwrap_or(50), generation_id)).await
+}
+
+/// Reverse an activity event; returns what was done
+#[tauri::command]
+pub async fn undo_event(state: State<'_, AppState>, id: i64) -> Result<String, PixeryError> {
+    with_db(&state, move |db| db.undo_event(id)).await
+}
+
+/// Undo the most recent destructive change; None if there's nothing to undo
+#[tauri::command]
+pub async fn undo_last(state: State<'_, AppState>) -> Result<Option<String>, PixeryError> {
+    with_db(&state, move |db| db.undo_last()).await
 }
 
 #[tauri::command]
-pub fn prompt_history(
+pub async fn prompt_history(
     state: State<'_, AppState>,
     limit: i64,
-) -> Result<Vec<(i64, String, String)>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.prompt_history(limit).map_err(|e| e.to_string())
+) -> Result<Vec<(i64, String, String)>, PixeryError> {
+    with_db(&state, move |db| db.prompt_history(limit)).await
+}
+
+#[tauri::command]
+pub fn get_maintenance_status() -> MaintenanceStatus {
+    crate::maintenance::load_status()
+}
+
+// Desktop notification settings
+
+#[tauri::command]
+pub fn get_notify_settings() -> notify::NotifySettings {
+    notify::load_settings()
+}
+
+#[tauri::command]
+pub fn set_notify_settings(settings: notify::NotifySettings) -> Result<(), PixeryError> {
+    notify::save_settings(&settings).map_err(PixeryError::from)
+}
+
+// NSFW settings
+
+#[tauri::command]
+pub fn get_nsfw_settings() -> nsfw::NsfwSettings {
+    nsfw::load_settings()
+}
+
+#[tauri::command]
+pub fn set_nsfw_settings(settings: nsfw::NsfwSettings) -> Result<(), PixeryError> {
+    nsfw::save_settings(&settings).map_err(PixeryError::from)
+}
+
+#[tauri::command]
+pub fn get_currency_settings() -> currency::CurrencySettings {
+    currency::load_settings()
+}
+
+/// Save the display currency; a new code without a manual rate fetches one
+#[tauri::command]
+pub async fn set_currency_settings(settings: currency::CurrencySettings) -> Result<DisplayCurrency, PixeryError> {
+    let mut settings = settings;
+    settings.code = currency::parse_code(&settings.code)?;
+    currency::save_settings(&settings)?;
+    Ok(currency::display().await)
+}
+
+/// How the GUI shows costs, fetching the exchange rate when it's a day old
+#[tauri::command]
+pub async fn get_display_currency() -> DisplayCurrency {
+    currency::display().await
+}
+
+#[tauri::command]
+pub async fn refresh_exchange_rate() -> Result<DisplayCurrency, PixeryError> {
+    Ok(currency::refresh().await?)
+}
+
+/// Whether this window is read-only, so the GUI can hide what it would refuse
+#[tauri::command]
+pub fn get_read_only(state: State<'_, AppState>) -> bool {
+    state.read_only
+}
+
+/// The slideshow this window was opened for (`pixery slideshow`); None for the gallery
+#[tauri::command]
+pub fn get_slideshow() -> Option<Slideshow> {
+    slideshow::current().cloned()
+}
+
+/// The generation a pixery:// link asked to show, once (see `deeplink`)
+#[tauri::command]
+pub fn take_focus_generation() -> Option<i64> {
+    deeplink::take_pending()
 }
 
 // Self-hosted server settings and health check commands
@@ -272,9 +678,20 @@ pub fn get_selfhosted_url() -> Option<String> {
 }
 
 #[tauri::command]
-pub fn set_selfhosted_url(url: Option<String>) -> Result<(), String> {
-    crate::providers::selfhosted::set_server_url(url.as_deref())
-        .map_err(|e| e.to_string())
+pub fn set_selfhosted_url(url: Option<String>) -> Result<(), PixeryError> {
+    crate::providers::selfhosted::set_server_url(url.as_deref()).map_err(PixeryError::from)
+}
+
+#[tauri::command]
+pub async fn switch_selfhosted_model(model: String) -> Result<(), PixeryError> {
+    let url = crate::providers::selfhosted::require_server_url()?;
+    crate::providers::selfhosted::switch_model(&url, &model).await.map_err(PixeryError::from)
+}
+
+#[tauri::command]
+pub async fn warmup_selfhosted(model: Option<String>) -> Result<crate::providers::selfhosted::WarmupResponse, PixeryError> {
+    let url = crate::providers::selfhosted::require_server_url()?;
+    crate::providers::selfhosted::warmup(&url, model.as_deref()).await.map_err(PixeryError::from)
 }
 
 /// Health check response for the frontend
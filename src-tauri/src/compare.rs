@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use embedded_graphics::mono_font::{ascii::FONT_10X20, MonoTextStyle};
+use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Baseline, Text};
+use image::{imageops::FilterType, Rgb, RgbImage};
+use std::path::Path;
+
+use crate::archive;
+use crate::models::Generation;
+
+const PADDING: u32 = 16;
+const LINE_HEIGHT: u32 = 22;
+const LABEL_LINES: u32 = 2;
+const CHAR_WIDTH: u32 = 10;
+const BACKGROUND: Rgb<u8> = Rgb([26, 25, 28]); // --bg-primary
+const TEXT: Rgb888 = Rgb888::new(232, 232, 235); // --text-primary
+const MUTED: Rgb888 = Rgb888::new(152, 152, 160); // --text-secondary
+
+/// Adapter so embedded-graphics can draw label text onto an image buffer
+struct Canvas<'a>(&'a mut RgbImage);
+
+impl OriginDimensions for Canvas<'_> {
+    fn size(&self) -> Size {
+        Size::new(self.0.width(), self.0.height())
+    }
+}
+
+impl DrawTarget for Canvas<'_> {
+    type Color = Rgb888;
+    type Error = std::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (w, h) = self.0.dimensions();
+        for Pixel(p, color) in pixels {
+            if p.x >= 0 && p.y >= 0 && (p.x as u32) < w && (p.y as u32) < h {
+                self.0.put_pixel(p.x as u32, p.y as u32, Rgb([color.r(), color.g(), color.b()]));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Label lines under a cell: "#id model" and "seed ... | $cost".
+/// The bitmap font is ASCII-only, so keep these plain.
+fn cell_label(gen: &Generation) -> [String; 2] {
+    let seed = gen.seed.as_deref().unwrap_or("-");
+    let cost = gen
+        .cost_estimate_usd
+        .map(|c| format!("${:.3}", c))
+        .unwrap_or_else(|| "-".to_string());
+    [
+        format!("#{} {}", gen.id, gen.model),
+        format!("seed {} | {}", seed, cost),
+    ]
+}
+
+/// Fit a label line into `max_chars`, marking truncation with "..."
+fn fit(text: &str, max_chars: usize) -> String {
+    let ascii: String = text.chars().map(|c| if c.is_ascii() { c } else { '?' }).collect();
+    if ascii.len() <= max_chars {
+        ascii
+    } else {
+        format!("{}...", &ascii[..max_chars.saturating_sub(3)])
+    }
+}
+
+/// Compose generations into a labeled grid, `columns` wide, each image scaled
+/// to fit a `cell_size` square. Cells are in the order given.
+pub fn contact_sheet(generations: &[Generation], columns: u32, cell_size: u32) -> Result<RgbImage> {
+    if generations.is_empty() {
+        anyhow::bail!("No generations to compare");
+    }
+
+    let columns = columns.clamp(1, generations.len() as u32);
+    let rows = (generations.len() as u32).div_ceil(columns);
+    let cell_w = cell_size + PADDING;
+    let cell_h = cell_size + PADDING + LABEL_LINES * LINE_HEIGHT;
+
+    let mut sheet = RgbImage::from_pixel(
+        columns * cell_w + PADDING,
+        rows * cell_h + PADDING,
+        BACKGROUND,
+    );
+    let max_chars = (cell_size / CHAR_WIDTH) as usize;
+
+    for (i, gen) in generations.iter().enumerate() {
+        let col = i as u32 % columns;
+        let row = i as u32 / columns;
+        let x0 = PADDING + col * cell_w;
+        let y0 = PADDING + row * cell_h;
+
+        // Videos are shown by their first frame
+        let img = archive::thumbnail_source(Path::new(&gen.image_path))
+            .with_context(|| format!("Failed to open image for generation {}", gen.id))?;
+        let scaled = img.resize(cell_size, cell_size, FilterType::Triangle).to_rgb8();
+
+        // Center within the square cell (non-square images get letterboxed)
+        let ox = x0 + (cell_size - scaled.width()) / 2;
+        let oy = y0 + (cell_size - scaled.height()) / 2;
+        image::imageops::replace(&mut sheet, &scaled, ox as i64, oy as i64);
+
+        let [title, detail] = cell_label(gen);
+        let mut canvas = Canvas(&mut sheet);
+        let label_y = (y0 + cell_size + 4) as i32;
+        let _ = Text::with_baseline(
+            &fit(&title, max_chars),
+            Point::new(x0 as i32, label_y),
+            MonoTextStyle::new(&FONT_10X20, TEXT),
+            Baseline::Top,
+        )
+        .draw(&mut canvas);
+        let _ = Text::with_baseline(
+            &fit(&detail, max_chars),
+            Point::new(x0 as i32, label_y + LINE_HEIGHT as i32),
+            MonoTextStyle::new(&FONT_10X20, MUTED),
+            Baseline::Top,
+        )
+        .draw(&mut canvas);
+    }
+
+    Ok(sheet)
+}
+
+/// 64-bit difference hash (dHash): the image shrunk to 9x8 grayscale, one bit
+/// per pixel for whether it is brighter than its right-hand neighbour. Robust
+/// to re-encoding and resizing, so near-identical images hash alike.
+pub fn perceptual_hash(path: &Path) -> Result<u64> {
+    let img = image::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let small = img.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    Ok(hash)
+}
+
+/// Similarity of two perceptual hashes: the fraction of matching bits, 1.0 when equal
+pub fn hash_similarity(a: u64, b: u64) -> f64 {
+    1.0 - (a ^ b).count_ones() as f64 / 64.0
+}
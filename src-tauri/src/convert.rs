@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, GenericImageView, ImageFormat, RgbImage};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::archive;
+
+/// File format an export is converted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Png,
+    Jpg,
+    Webp,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Jpg => "jpg",
+            ExportFormat::Webp => "webp",
+        }
+    }
+
+    fn from_image_format(format: ImageFormat) -> Option<Self> {
+        match format {
+            ImageFormat::Png => Some(ExportFormat::Png),
+            ImageFormat::Jpeg => Some(ExportFormat::Jpg),
+            ImageFormat::WebP => Some(ExportFormat::Webp),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.extension())
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "png" => Ok(ExportFormat::Png),
+            "jpg" | "jpeg" => Ok(ExportFormat::Jpg),
+            "webp" => Ok(ExportFormat::Webp),
+            _ => Err(format!("Unknown export format '{}' (png, jpg, webp)", s)),
+        }
+    }
+}
+
+/// How exported images are rewritten. With nothing set, files are copied
+/// byte for byte.
+#[derive(Debug, Clone, Copy)]
+pub struct Conversion {
+    /// Longest side in pixels; larger images are scaled down, never up
+    pub max_size: Option<u32>,
+    /// Keep the source's format when None
+    pub format: Option<ExportFormat>,
+    /// JPEG quality, 1-100 (WebP is written lossless)
+    pub quality: u8,
+    /// Re-encode even when nothing else changes, dropping embedded metadata
+    /// (provider provenance, EXIF, PNG text)
+    pub strip_metadata: bool,
+}
+
+impl Default for Conversion {
+    fn default() -> Self {
+        Conversion { max_size: None, format: None, quality: 90, strip_metadata: false }
+    }
+}
+
+impl Conversion {
+    /// Nothing asked for: plain copies
+    pub fn is_copy(&self) -> bool {
+        self.max_size.is_none() && self.format.is_none() && !self.strip_metadata
+    }
+}
+
+/// Write `src` to `dest`, converted as asked. Returns the path written (its
+/// extension follows the output format) and whether it was converted.
+/// Re-encoding keeps only pixels, so a converted file never carries the
+/// source's metadata. Videos and animations are copied unchanged.
+pub fn export_file(src: &Path, dest: &Path, conversion: &Conversion) -> Result<(PathBuf, bool)> {
+    let copy = |dest: &Path| -> Result<(PathBuf, bool)> {
+        fs::copy(src, dest).with_context(|| format!("Failed to copy to {}", dest.display()))?;
+        Ok((dest.to_path_buf(), false))
+    };
+    if conversion.is_copy() {
+        return copy(dest);
+    }
+
+    let data = fs::read(src).with_context(|| format!("Failed to read {}", src.display()))?;
+    let source_format = image::guess_format(&data).ok().and_then(ExportFormat::from_image_format);
+    let Some(source_format) = source_format.filter(|_| !archive::is_animated(&data)) else {
+        return copy(dest);
+    };
+
+    let mut img = image::load_from_memory(&data).with_context(|| format!("Failed to decode {}", src.display()))?;
+    if let Some(max) = conversion.max_size {
+        let (width, height) = img.dimensions();
+        if width.max(height) > max {
+            img = img.resize(max, max, image::imageops::FilterType::Lanczos3);
+        }
+    }
+
+    let format = conversion.format.unwrap_or(source_format);
+    let dest = dest.with_extension(format.extension());
+    match format {
+        ExportFormat::Jpg => {
+            let file = fs::File::create(&dest).with_context(|| format!("Failed to create {}", dest.display()))?;
+            let mut writer = std::io::BufWriter::new(file);
+            JpegEncoder::new_with_quality(&mut writer, conversion.quality)
+                .encode_image(&flatten(&img))
+                .with_context(|| format!("Failed to write {}", dest.display()))?;
+        }
+        ExportFormat::Png => img
+            .save_with_format(&dest, ImageFormat::Png)
+            .with_context(|| format!("Failed to write {}", dest.display()))?,
+        // The WebP encoder only takes 8-bit RGB(A)
+        ExportFormat::Webp => DynamicImage::ImageRgba8(img.to_rgba8())
+            .save_with_format(&dest, ImageFormat::WebP)
+            .with_context(|| format!("Failed to write {}", dest.display()))?,
+    }
+    Ok((dest, true))
+}
+
+/// JPEG has no alpha: transparent areas go white, which is what a client
+/// opening the file expects
+fn flatten(img: &DynamicImage) -> RgbImage {
+    if !img.color().has_alpha() {
+        return img.to_rgb8();
+    }
+    let rgba = img.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
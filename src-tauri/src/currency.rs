@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::archive;
+use crate::models::DisplayCurrency;
+use crate::providers;
+
+/// Frankfurter: ECB reference rates, no key needed
+const DEFAULT_RATES_URL: &str = "https://api.frankfurter.app/latest";
+
+/// A fetched rate older than this is fetched again
+const MAX_RATE_AGE_HOURS: i64 = 24;
+
+/// Don't hold up `pixery cost` or the dashboard for a slow rates API
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Display currency, stored in currency.json in the archive root. Costs are
+/// recorded and summed in USD; this only changes how they're shown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CurrencySettings {
+    /// ISO 4217 code; "USD" shows costs as recorded
+    pub code: String,
+    /// Units of `code` per USD, set by hand. When unset the rate is fetched
+    /// and cached for a day.
+    pub manual_rate: Option<f64>,
+    /// Rates API answering like Frankfurter's `latest` (default: Frankfurter)
+    pub rates_url: Option<String>,
+    /// The last fetched rate
+    pub fetched: Option<FetchedRate>,
+}
+
+impl Default for CurrencySettings {
+    fn default() -> Self {
+        CurrencySettings { code: "USD".to_string(), manual_rate: None, rates_url: None, fetched: None }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FetchedRate {
+    pub code: String,
+    pub rate: f64,
+    /// Local time
+    pub fetched_at: String,
+}
+
+#[derive(Deserialize)]
+struct RatesResponse {
+    rates: std::collections::HashMap<String, f64>,
+}
+
+fn settings_path() -> PathBuf {
+    archive::archive_root().join("currency.json")
+}
+
+pub fn load_settings() -> CurrencySettings {
+    std::fs::read_to_string(settings_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &CurrencySettings) -> Result<()> {
+    std::fs::write(settings_path(), serde_json::to_string_pretty(settings)?)
+        .context("Failed to write currency settings")
+}
+
+/// Check a currency code and normalize it to upper case
+pub fn parse_code(code: &str) -> Result<String> {
+    let code = code.trim().to_uppercase();
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        anyhow::bail!("'{}' is not a currency code (three letters, like EUR)", code);
+    }
+    Ok(code)
+}
+
+impl CurrencySettings {
+    /// The fetched rate, if it's for the configured currency
+    fn fetched_rate(&self) -> Option<&FetchedRate> {
+        self.fetched.as_ref().filter(|f| f.code == self.code)
+    }
+
+    fn needs_fetch(&self) -> bool {
+        if self.code == "USD" || self.manual_rate.is_some() {
+            return false;
+        }
+        let Some(fetched) = self.fetched_rate() else {
+            return true;
+        };
+        chrono::NaiveDateTime::parse_from_str(&fetched.fetched_at, TIMESTAMP_FORMAT)
+            .map(|at| chrono::Local::now().naive_local() - at > chrono::Duration::hours(MAX_RATE_AGE_HOURS))
+            .unwrap_or(true)
+    }
+
+    /// How costs are shown with these settings, without fetching. Falls back
+    /// to USD while there's no rate for the currency.
+    pub fn display(&self) -> DisplayCurrency {
+        if let Some(rate) = self.manual_rate.filter(|_| self.code != "USD") {
+            return DisplayCurrency { code: self.code.clone(), rate, source: "manual".to_string() };
+        }
+        match self.fetched_rate().filter(|_| self.code != "USD") {
+            Some(fetched) => fetched.display(),
+            None => DisplayCurrency::default(),
+        }
+    }
+}
+
+impl FetchedRate {
+    fn display(&self) -> DisplayCurrency {
+        DisplayCurrency {
+            code: self.code.clone(),
+            rate: self.rate,
+            source: format!("fetched {}", self.fetched_at.get(..10).unwrap_or(&self.fetched_at)),
+        }
+    }
+}
+
+/// Fetch today's USD rate for the configured currency and cache it in
+/// currency.json. Returns the fetched rate, even if a manual one is set.
+pub async fn refresh() -> Result<DisplayCurrency> {
+    let mut settings = load_settings();
+    if settings.code == "USD" {
+        return Ok(DisplayCurrency::default());
+    }
+    let url = settings.rates_url.as_deref().unwrap_or(DEFAULT_RATES_URL);
+    let response = providers::client()
+        .get(url)
+        .query(&[("from", "USD"), ("to", settings.code.as_str())])
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {}", url))?;
+    if !response.status().is_success() {
+        anyhow::bail!("Exchange rate request failed ({}) for {}", response.status(), settings.code);
+    }
+    let body: RatesResponse = response.json().await.context("Invalid exchange rate response")?;
+    let rate = *body
+        .rates
+        .get(&settings.code)
+        .ok_or_else(|| anyhow::anyhow!("No exchange rate for {}", settings.code))?;
+    let fetched = FetchedRate {
+        code: settings.code.clone(),
+        rate,
+        fetched_at: chrono::Local::now().format(TIMESTAMP_FORMAT).to_string(),
+    };
+    let shown = fetched.display();
+    settings.fetched = Some(fetched);
+    save_settings(&settings)?;
+    Ok(shown)
+}
+
+/// The display currency, fetching a new rate when the cached one is missing
+/// or a day old. A failed fetch keeps the old rate (or USD) with a warning.
+pub async fn display() -> DisplayCurrency {
+    let settings = load_settings();
+    if settings.needs_fetch() {
+        match refresh().await {
+            Ok(currency) => return currency,
+            Err(e) => eprintln!("Exchange rate for {} not updated: {:#}", settings.code, e),
+        }
+    }
+    settings.display()
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Once;
+
+use crate::archive;
+use crate::models::{ModelInfo, Provider};
+
+/// Alias chains longer than this are treated as a loop
+const MAX_ALIAS_DEPTH: usize = 8;
+
+/// User-defined models and aliases, stored in models.json in the archive root:
+///
+/// ```json
+/// {
+///   "models": [
+///     { "id": "seedream", "provider": "fal", "endpoint": "fal-ai/bytedance/seedream/v4/text-to-image",
+///       "display_name": "Seedream 4", "cost_per_image": 0.03, "max_refs": 0 }
+///   ],
+///   "aliases": { "fast": "fal-ai/flux/schnell", "best": "gemini-pro" },
+///   "quotas": { "gemini-pro": 20 }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelConfig {
+    pub models: Vec<CustomModel>,
+    /// alias -> model ID (built-in or custom)
+    pub aliases: BTreeMap<String, String>,
+    /// model ID or alias -> most jobs started per day
+    pub quotas: BTreeMap<String, u32>,
+}
+
+/// A model the provider supports but pixery doesn't ship
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomModel {
+    pub id: String,
+    pub provider: Provider,
+    /// Model string sent to the provider (fal endpoint path, OpenAI/Gemini model
+    /// name, self-hosted model key); defaults to the ID
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub cost_per_image: f64,
+    #[serde(default)]
+    pub max_refs: u32,
+    /// fal endpoint takes `background: "transparent"` (`pixery gen --transparent`)
+    #[serde(default)]
+    pub transparent: bool,
+}
+
+impl CustomModel {
+    pub fn endpoint(&self) -> &str {
+        self.endpoint.as_deref().unwrap_or(&self.id)
+    }
+
+    fn info(&self) -> ModelInfo {
+        ModelInfo {
+            id: self.id.clone(),
+            provider: self.provider,
+            display_name: self.display_name.clone().unwrap_or_else(|| format!("{} (custom)", self.id)),
+            cost_per_image: self.cost_per_image,
+            max_refs: self.max_refs,
+        }
+    }
+}
+
+pub fn config_path() -> PathBuf {
+    archive::archive_root().join("models.json")
+}
+
+/// Missing file means no custom models; a broken one is reported and ignored
+/// so a typo doesn't take the built-in models down with it
+pub fn load() -> ModelConfig {
+    let Ok(json) = std::fs::read_to_string(config_path()) else {
+        return ModelConfig::default();
+    };
+    serde_json::from_str(&json).unwrap_or_else(|e| {
+        // The registry is reloaded on every lookup; say it once per process
+        static WARNED: Once = Once::new();
+        WARNED.call_once(|| eprintln!("Warning: ignoring {}: {}", config_path().display(), e));
+        ModelConfig::default()
+    })
+}
+
+impl ModelConfig {
+    /// Merge custom models into the built-in list; a custom model with a built-in
+    /// ID replaces it (e.g. to correct a price)
+    pub fn merge_into(&self, models: &mut Vec<ModelInfo>) {
+        for custom in &self.models {
+            let info = custom.info();
+            match models.iter_mut().find(|m| m.id == custom.id) {
+                Some(existing) => *existing = info,
+                None => models.push(info),
+            }
+        }
+    }
+
+    /// Follow aliases to a model ID. Real model IDs are never shadowed by an alias.
+    pub fn resolve_alias(&self, model: &str, known: &[ModelInfo]) -> String {
+        let mut current = model.to_string();
+        for _ in 0..MAX_ALIAS_DEPTH {
+            if known.iter().any(|m| m.id == current) {
+                break;
+            }
+            match self.aliases.get(&current) {
+                Some(target) => current = target.clone(),
+                None => break,
+            }
+        }
+        current
+    }
+
+    /// Daily job quota for a model ID, whether keyed by the ID or an alias of it
+    pub fn quota_for(&self, model_id: &str, known: &[ModelInfo]) -> Option<u32> {
+        self.quotas
+            .iter()
+            .find(|(key, _)| self.resolve_alias(key, known) == model_id)
+            .map(|(_, limit)| *limit)
+    }
+
+    /// Provider model string for a custom model ID
+    pub fn endpoint_for(&self, model_id: &str) -> Option<&str> {
+        self.models.iter().find(|m| m.id == model_id).map(|m| m.endpoint())
+    }
+
+    /// Custom model calling this provider endpoint, if any
+    pub fn find_by_endpoint(&self, endpoint: &str) -> Option<&CustomModel> {
+        self.models.iter().find(|m| m.endpoint() == endpoint)
+    }
+}
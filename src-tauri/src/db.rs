@@ -1,9 +1,14 @@
 use anyhow::{Context, Result};
 use rusqlite::{params, Connection, OptionalExtension};
 use std::collections::HashMap;
-use std::path::Path;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-use crate::models::{Collection, CostSummary, Generation, Job, JobSource, JobStatus, ListFilter, Reference, TagCount};
+use crate::archive;
+use crate::error::PixeryError;
+use crate::palette::{self, Color};
+use crate::models::{self, Asset, AssetKind, BatchCall, BulkTagReport, Collection, CostRow, CostSummary, Event, EventAction, Experiment, FacetCount, FilterFacets, Generation, GenerationPage, GenerationRequest, Job, JobSource, JobStatus, LatencyStats, ListFilter, MediaType, ModelStat, NewPreset, NewSchedule, Orientation, PeriodStat, Preset, PriceEntry, Reference, ReferenceInfo, Schedule, SmartCollection, SortKey, Stats, Swatch, TagCount};
 
 const SCHEMA: &str = r#"
 -- Core generations table
@@ -101,8 +106,157 @@ CREATE TABLE IF NOT EXISTS generation_collections (
 );
 
 CREATE INDEX IF NOT EXISTS idx_gc_collection ON generation_collections(collection_id);
+
+-- Smart collections (saved searches as ListFilter expressions)
+CREATE TABLE IF NOT EXISTS smart_collections (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    filter TEXT NOT NULL,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Named generation settings (pixery preset)
+CREATE TABLE IF NOT EXISTS presets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    model TEXT NOT NULL,
+    negative_prompt TEXT,
+    width INTEGER,
+    height INTEGER,
+    cfg_scale REAL,
+    tags TEXT,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Recurring generations (pixery schedule)
+CREATE TABLE IF NOT EXISTS schedules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    cron TEXT NOT NULL,
+    prompt TEXT NOT NULL,
+    model TEXT NOT NULL,
+    tags TEXT,
+    negative_prompt TEXT,
+    width INTEGER,
+    height INTEGER,
+    enabled INTEGER DEFAULT 1,
+    last_run_at TEXT,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Systematic runs (pixery ab, pixery batch) with the parameters they were started with
+CREATE TABLE IF NOT EXISTS experiments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    kind TEXT NOT NULL,
+    prompt TEXT NOT NULL,
+    params TEXT NOT NULL,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS generation_experiments (
+    generation_id INTEGER REFERENCES generations(id) ON DELETE CASCADE,
+    experiment_id INTEGER REFERENCES experiments(id) ON DELETE CASCADE,
+    PRIMARY KEY (generation_id, experiment_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ge_experiment ON generation_experiments(experiment_id);
+
+-- The API calls a batch run planned, so `pixery batch --resume` can finish it.
+-- generation_id is the first generation the call produced; NULL until it has.
+-- No foreign key: the call stays done if that generation is deleted.
+CREATE TABLE IF NOT EXISTS batch_calls (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    experiment_id INTEGER NOT NULL REFERENCES experiments(id) ON DELETE CASCADE,
+    line INTEGER,
+    request TEXT NOT NULL,
+    tags TEXT NOT NULL,
+    generation_id INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_batch_calls_experiment ON batch_calls(experiment_id);
+
+-- Append-only activity log (pixery activity). No foreign key on generation_id,
+-- so a deleted generation's history stays.
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    generation_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    detail TEXT NOT NULL,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_events_generation ON events(generation_id);
+
+-- Effective-dated prices (pixery pricing): USD per image, per second for video
+-- models. The rate on a date is the latest row starting on or before it;
+-- models without one use their built-in price.
+CREATE TABLE IF NOT EXISTS pricing (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    model TEXT NOT NULL,
+    cost REAL NOT NULL,
+    effective_from TEXT NOT NULL,
+    note TEXT,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (model, effective_from)
+);
+
+-- Extra files a generation owns (pixery assets): edited exports, upscales, crops.
+-- The files live in assets/{generation id}/ and go with the generation.
+CREATE TABLE IF NOT EXISTS assets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    generation_id INTEGER NOT NULL REFERENCES generations(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    path TEXT NOT NULL,
+    file_size INTEGER,
+    note TEXT,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_assets_generation ON assets(generation_id);
+
+-- Dominant colors per generation (palette.rs), position 0 the largest. L*a*b*
+-- is stored next to the hex so color search can compare perceptually in SQL.
+CREATE TABLE IF NOT EXISTS generation_colors (
+    generation_id INTEGER NOT NULL REFERENCES generations(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    hex TEXT NOT NULL,
+    share REAL NOT NULL,
+    l REAL NOT NULL,
+    a REAL NOT NULL,
+    b REAL NOT NULL,
+    PRIMARY KEY (generation_id, position)
+);
 "#;
 
+/// Columns holding file paths. Paths inside the archive are stored relative
+/// to `archive::archive_root()` so the archive can move; paths outside it
+/// (and rows from before `pixery migrate-paths`) are absolute.
+const PATH_COLUMNS: [(&str, &str); 4] = [
+    ("generations", "image_path"),
+    ("generations", "thumb_path"),
+    ("refs", "path"),
+    ("assets", "path"),
+];
+
+/// A path as it's written to the database
+fn to_stored(path: &str) -> String {
+    match Path::new(path).strip_prefix(archive::archive_root()) {
+        Ok(relative) => relative.to_string_lossy().into_owned(),
+        Err(_) => path.to_string(),
+    }
+}
+
+/// A path from the database as an absolute path
+fn resolve(stored: String) -> String {
+    if Path::new(&stored).is_absolute() {
+        stored
+    } else {
+        archive::archive_root().join(stored).to_string_lossy().into_owned()
+    }
+}
+
 fn parse_job_row(row: &rusqlite::Row) -> rusqlite::Result<Job> {
     let status_str: String = row.get(1)?;
     let source_str: String = row.get(5)?;
@@ -121,23 +275,308 @@ fn parse_job_row(row: &rusqlite::Row) -> rusqlite::Result<Job> {
         completed_at: row.get(9)?,
         generation_id: row.get(10)?,
         error: row.get(11)?,
+        retry_count: row.get::<_, Option<i32>>(12)?.unwrap_or(0),
+        retry_job_id: row.get(13)?,
+        failure: row.get(14)?,
+    })
+}
+
+fn parse_preset_row(row: &rusqlite::Row) -> rusqlite::Result<Preset> {
+    let tags: Option<String> = row.get(7)?;
+    Ok(Preset {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        model: row.get(2)?,
+        negative_prompt: row.get(3)?,
+        width: row.get(4)?,
+        height: row.get(5)?,
+        cfg_scale: row.get(6)?,
+        tags: tags
+            .map(|t| t.split(',').map(|s| s.to_string()).collect())
+            .unwrap_or_default(),
+        created_at: row.get(8)?,
     })
 }
 
+/// WHERE conditions (ANDed) and their parameters
+type Conditions = (Vec<String>, Vec<Box<dyn rusqlite::ToSql>>);
+
+/// WHERE conditions for a ListFilter. Paging fields (limit, offset, cursor)
+/// are left to the caller, and a smart collection to `Database::conditions`.
+fn filter_conditions(filter: &ListFilter) -> Conditions {
+    let mut conditions = vec![];
+    let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![];
+
+    // Trashed filter: show trashed OR exclude trashed (default)
+    if filter.show_trashed {
+        conditions.push("g.trashed_at IS NOT NULL".to_string());
+    } else {
+        conditions.push("g.trashed_at IS NULL".to_string());
+    }
+
+    // Collection filter, including its subcollections (named "<path>/...")
+    if let Some(collection_id) = filter.collection_id {
+        conditions.push(
+            "g.id IN (
+                SELECT gc.generation_id FROM generation_collections gc
+                JOIN collections c ON c.id = gc.collection_id
+                JOIN collections root ON root.id = ?
+                WHERE c.deleted_at IS NULL
+                  AND (c.id = root.id OR substr(c.name, 1, length(root.name) + 1) = root.name || '/')
+            )"
+            .to_string(),
+        );
+        params_vec.push(Box::new(collection_id));
+    }
+
+    if let Some(experiment_id) = filter.experiment_id {
+        conditions.push("g.id IN (SELECT generation_id FROM generation_experiments WHERE experiment_id = ?)".to_string());
+        params_vec.push(Box::new(experiment_id));
+    }
+
+    if let Some(media_type) = filter.media_type {
+        conditions.push("g.media_type = ?".to_string());
+        params_vec.push(Box::new(media_type.to_string()));
+    }
+
+    // Uncategorized: not in any collection
+    if filter.uncategorized {
+        conditions.push(
+            "g.id NOT IN (
+                SELECT gc.generation_id FROM generation_collections gc
+                JOIN collections c ON c.id = gc.collection_id AND c.deleted_at IS NULL
+            )"
+            .to_string(),
+        );
+    }
+
+    // Multi-tag filter with AND logic: images must have ALL specified tags.
+    // A wildcard ("character:*") needs any tag in that namespace.
+    let (wildcards, tags): (Vec<&String>, Vec<&String>) = filter
+        .tags
+        .iter()
+        .flatten()
+        .partition(|t| models::tag_wildcard(t).is_some());
+    for wildcard in wildcards {
+        conditions.push(
+            "g.id IN (
+                SELECT gt.generation_id FROM generation_tags gt
+                JOIN tags t ON gt.tag_id = t.id
+                WHERE t.namespace = ?
+            )"
+            .to_string(),
+        );
+        params_vec.push(Box::new(models::tag_wildcard(wildcard).unwrap_or_default().to_string()));
+    }
+    if !tags.is_empty() {
+        let placeholders: Vec<&str> = tags.iter().map(|_| "?").collect();
+        let in_clause = placeholders.join(", ");
+        conditions.push(format!(
+            "g.id IN (
+                SELECT gt.generation_id FROM generation_tags gt
+                JOIN tags t ON gt.tag_id = t.id
+                WHERE t.name IN ({})
+                GROUP BY gt.generation_id
+                HAVING COUNT(DISTINCT t.name) = {}
+            )",
+            in_clause,
+            tags.len()
+        ));
+        for tag in tags {
+            params_vec.push(Box::new(tag.clone()));
+        }
+    }
+
+    // Exclude generations that have ANY of the excluded tags (or any tag in
+    // an excluded "namespace:*")
+    if let Some(ref exclude_tags) = filter.exclude_tags {
+        if !exclude_tags.is_empty() {
+            let placeholders: Vec<&str> = exclude_tags.iter().map(|_| "?").collect();
+            let in_clause = placeholders.join(", ");
+            conditions.push(format!(
+                "g.id NOT IN (
+                    SELECT gt.generation_id FROM generation_tags gt
+                    JOIN tags t ON gt.tag_id = t.id
+                    WHERE t.name IN ({0}) OR t.namespace IN ({0})
+                )",
+                in_clause
+            ));
+            for tag in exclude_tags {
+                params_vec.push(Box::new(tag.clone()));
+            }
+            for tag in exclude_tags {
+                params_vec.push(Box::new(models::tag_wildcard(tag).unwrap_or_default().to_string()));
+            }
+        }
+    }
+
+    if let Some(ref model) = filter.model {
+        conditions.push("g.model = ?".to_string());
+        params_vec.push(Box::new(model.clone()));
+    }
+
+    if filter.starred_only {
+        conditions.push("g.starred = 1".to_string());
+    }
+
+    if filter.nsfw_only {
+        conditions.push("g.nsfw = 1".to_string());
+    } else if filter.hide_nsfw {
+        conditions.push("g.nsfw = 0".to_string());
+    }
+
+    if let Some(ref search) = filter.search {
+        // Imports are also found by the camera/software they were made with
+        conditions.push(
+            "(g.prompt LIKE ? OR g.caption LIKE ? OR json_extract(g.params, '$.camera') LIKE ? \
+             OR json_extract(g.params, '$.lens') LIKE ? OR json_extract(g.params, '$.software') LIKE ?)"
+                .to_string(),
+        );
+        for _ in 0..5 {
+            params_vec.push(Box::new(format!("%{}%", search)));
+        }
+    }
+
+    if let Some(ref since) = filter.since {
+        conditions.push("g.date >= ?".to_string());
+        params_vec.push(Box::new(since.clone()));
+    }
+
+    if let Some(ref until) = filter.until {
+        conditions.push("g.date <= ?".to_string());
+        params_vec.push(Box::new(until.clone()));
+    }
+
+    if let Some(min_width) = filter.min_width {
+        conditions.push("g.width >= ?".to_string());
+        params_vec.push(Box::new(min_width));
+    }
+
+    if let Some(min_height) = filter.min_height {
+        conditions.push("g.height >= ?".to_string());
+        params_vec.push(Box::new(min_height));
+    }
+
+    // Unknown dimensions (old imports) never match an orientation
+    if let Some(orientation) = filter.orientation {
+        conditions.push(
+            match orientation {
+                Orientation::Portrait => "g.height > g.width",
+                Orientation::Landscape => "g.width > g.height",
+                Orientation::Square => "g.width = g.height",
+            }
+            .to_string(),
+        );
+    }
+
+    if filter.has_reference {
+        conditions.push("g.id IN (SELECT generation_id FROM generation_refs)".to_string());
+    }
+
+    // Each color needs a palette entry within the tolerance; an invalid
+    // color matches nothing
+    let tolerance = filter.color_tolerance.unwrap_or(palette::DEFAULT_TOLERANCE);
+    for color in filter.colors.iter().flatten() {
+        let Ok(color) = color.parse::<Color>() else {
+            conditions.push("0".to_string());
+            continue;
+        };
+        conditions.push(
+            "g.id IN (
+                SELECT generation_id FROM generation_colors
+                WHERE (l - ?) * (l - ?) + (a - ?) * (a - ?) + (b - ?) * (b - ?) <= ?
+            )"
+            .to_string(),
+        );
+        for channel in color.lab() {
+            params_vec.push(Box::new(channel));
+            params_vec.push(Box::new(channel));
+        }
+        params_vec.push(Box::new(tolerance * tolerance));
+    }
+
+    (conditions, params_vec)
+}
+
+/// ORDER BY for a filter. The default (newest first) is the keyset order used
+/// by cursors; id breaks ties so pages never skip or repeat rows.
+fn order_clause(filter: &ListFilter) -> String {
+    let dir = if filter.sort_desc { "DESC" } else { "ASC" };
+    // Missing values (imports, old rows) sort last in either direction
+    let nulls_last = |col: &str| format!("{col} IS NULL, {col} {dir}, g.id {dir}", col = col, dir = dir);
+    match filter.sort {
+        None => "g.timestamp DESC, g.id DESC".to_string(),
+        Some(SortKey::Timestamp) => format!("g.timestamp {0}, g.id {0}", dir),
+        Some(SortKey::Cost) => nulls_last("g.cost_estimate_usd"),
+        Some(SortKey::GenerationTime) => nulls_last("g.generation_time_seconds"),
+        Some(SortKey::FileSize) => nulls_last("g.file_size"),
+        Some(SortKey::Starred) => format!("g.starred {}, g.timestamp DESC, g.id DESC", dir),
+        Some(SortKey::Random) => "RANDOM()".to_string(),
+        // Only meaningful inside a collection; elsewhere it's the default order.
+        // Members of subcollections only (no position here) come last.
+        Some(SortKey::Position) => match filter.collection_id {
+            Some(collection_id) => nulls_last(&format!(
+                "(SELECT position FROM generation_collections WHERE collection_id = {} AND generation_id = g.id)",
+                collection_id
+            )),
+            None => "g.timestamp DESC, g.id DESC".to_string(),
+        },
+    }
+}
+
+/// Where the next page starts: after a (timestamp, id) row for the default
+/// order, or at a row offset ("+N") for other sorts
+enum PageCursor {
+    Keyset(String, i64),
+    Offset(i64),
+}
+
+fn parse_cursor(cursor: &str) -> Result<PageCursor> {
+    let parsed = match cursor.strip_prefix('+') {
+        Some(offset) => offset.parse().ok().map(PageCursor::Offset),
+        None => cursor
+            .rsplit_once('|')
+            .and_then(|(timestamp, id)| Some(PageCursor::Keyset(timestamp.to_string(), id.parse().ok()?))),
+    };
+    parsed.ok_or_else(|| anyhow::anyhow!("Invalid page cursor: {}", cursor))
+}
+
+/// How long a connection waits on another writer before failing with SQLITE_BUSY
+const BUSY_TIMEOUT_MS: u32 = 5000;
+
+/// Idle connections kept by DbPool; extra ones are closed when returned
+const POOL_MAX_IDLE: usize = 4;
+
+/// Deleting a collection with more memberships than this has to be confirmed
+pub const COLLECTION_DELETE_CONFIRM: i64 = 50;
+
 pub struct Database {
     conn: Connection,
 }
 
 impl Database {
     pub fn open(path: &Path) -> Result<Self> {
-        let conn = Connection::open(path).context("Failed to open database")?;
-        conn.execute_batch("PRAGMA foreign_keys = ON;")
-            .context("Failed to enable foreign keys")?;
-        let db = Database { conn };
+        let db = Self::connect(path)?;
         db.migrate()?;
         Ok(db)
     }
 
+    /// Open a connection without running migrations. WAL lets readers proceed
+    /// while another connection (GUI, CLI, scheduler) is writing; busy_timeout
+    /// makes concurrent writers wait instead of erroring.
+    fn connect(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path).context("Failed to open database")?;
+        conn.execute_batch(&format!(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;
+             PRAGMA busy_timeout = {};
+             PRAGMA foreign_keys = ON;",
+            BUSY_TIMEOUT_MS
+        ))
+        .context("Failed to configure database connection")?;
+        Ok(Database { conn })
+    }
+
     fn migrate(&self) -> Result<()> {
         self.conn
             .execute_batch(SCHEMA)
@@ -161,6 +600,143 @@ impl Database {
             [],
         );
 
+        // Add retry_count column to jobs if it doesn't exist
+        let _ = self.conn.execute(
+            "ALTER TABLE generation_jobs ADD COLUMN retry_count INTEGER DEFAULT 0",
+            [],
+        );
+
+        // Add locked column if it doesn't exist
+        let _ = self.conn.execute(
+            "ALTER TABLE generations ADD COLUMN locked INTEGER DEFAULT 0",
+            [],
+        );
+
+        // Add caption column (auto-generated by a vision model) if it doesn't exist
+        let _ = self.conn.execute(
+            "ALTER TABLE generations ADD COLUMN caption TEXT",
+            [],
+        );
+
+        // Add requested_by column (who asked for it through an integration) if it doesn't exist
+        let _ = self.conn.execute(
+            "ALTER TABLE generations ADD COLUMN requested_by TEXT",
+            [],
+        );
+
+        // Add request column (GenerationRequest JSON, for `jobs retry`) to jobs
+        let _ = self.conn.execute(
+            "ALTER TABLE generation_jobs ADD COLUMN request TEXT",
+            [],
+        );
+
+        // Add retry_job_id column (the job that re-ran this one) to jobs
+        let _ = self.conn.execute(
+            "ALTER TABLE generation_jobs ADD COLUMN retry_job_id INTEGER",
+            [],
+        );
+
+        // Add position column (user-defined order) to collection membership;
+        // existing members keep the order they were added in
+        let _ = self.conn.execute(
+            "ALTER TABLE generation_collections ADD COLUMN position INTEGER",
+            [],
+        );
+        self.conn.execute(
+            "UPDATE generation_collections SET position = rowid WHERE position IS NULL",
+            [],
+        )?;
+
+        // Add cover_id column (the generation shown for a collection) to collections
+        let _ = self.conn.execute(
+            "ALTER TABLE collections ADD COLUMN cover_id INTEGER REFERENCES generations(id) ON DELETE SET NULL",
+            [],
+        );
+
+        // Add parent_id column (nested collections, named by their "parent/child" path)
+        let _ = self.conn.execute(
+            "ALTER TABLE collections ADD COLUMN parent_id INTEGER REFERENCES collections(id) ON DELETE CASCADE",
+            [],
+        );
+
+        // Add namespace column ("character" for "character:kira") to tags and
+        // fill it in for tags created before it existed
+        if self.conn.execute("ALTER TABLE tags ADD COLUMN namespace TEXT", []).is_ok() {
+            let names: Vec<(i64, String)> = self
+                .conn
+                .prepare("SELECT id, name FROM tags WHERE name LIKE '%:%'")?
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<_, _>>()?;
+            for (id, name) in names {
+                if let Some(namespace) = models::tag_namespace(&name) {
+                    self.conn.execute("UPDATE tags SET namespace = ?1 WHERE id = ?2", params![namespace, id])?;
+                }
+            }
+        }
+        self.conn.execute("CREATE INDEX IF NOT EXISTS idx_tags_namespace ON tags(namespace)", [])?;
+
+        // Add content_hash column (SHA-256 of the image file) for duplicate
+        // detection; older rows are hashed on demand by workflow::find_duplicate
+        let _ = self.conn.execute("ALTER TABLE generations ADD COLUMN content_hash TEXT", []);
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_generations_content_hash ON generations(content_hash)",
+            [],
+        )?;
+
+        // Add params column (the resolved GenerationRequest as JSON) if it doesn't exist
+        let _ = self.conn.execute("ALTER TABLE generations ADD COLUMN params TEXT", []);
+
+        // Add media_type, duration_seconds and fps columns (video clips) if they don't exist
+        let _ = self.conn.execute("ALTER TABLE generations ADD COLUMN media_type TEXT NOT NULL DEFAULT 'image'", []);
+        let _ = self.conn.execute("ALTER TABLE generations ADD COLUMN duration_seconds REAL", []);
+        let _ = self.conn.execute("ALTER TABLE generations ADD COLUMN fps REAL", []);
+
+        // Add animated column (GIF/WebP/APNG kept as-is) if it doesn't exist
+        let _ = self.conn.execute("ALTER TABLE generations ADD COLUMN animated INTEGER NOT NULL DEFAULT 0", []);
+
+        // Add nsfw column (hidden from list/search unless asked for) if it doesn't exist
+        let _ = self.conn.execute("ALTER TABLE generations ADD COLUMN nsfw INTEGER NOT NULL DEFAULT 0", []);
+
+        // Add deleted_at column (soft-deleted collections, restorable) if it doesn't exist
+        let _ = self.conn.execute("ALTER TABLE collections ADD COLUMN deleted_at TEXT", []);
+
+        // Add failure column (error category of failed jobs) if it doesn't exist
+        let _ = self.conn.execute("ALTER TABLE generation_jobs ADD COLUMN failure TEXT", []);
+
+        // Add undo_of column (events made by an undo) if it doesn't exist
+        let _ = self.conn.execute("ALTER TABLE events ADD COLUMN undo_of INTEGER", []);
+
+        // batch_calls recorded the completing job in job_id, a foreign key that
+        // made deleting old jobs fail. SQLite can't drop a foreign key column,
+        // so rebuild the table with the job's generation instead.
+        let has_job_id: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('batch_calls') WHERE name = 'job_id'",
+            [],
+            |row| row.get(0),
+        )?;
+        if has_job_id > 0 {
+            self.conn
+                .execute_batch(
+                    "BEGIN;
+                     CREATE TABLE batch_calls_new (
+                         id INTEGER PRIMARY KEY AUTOINCREMENT,
+                         experiment_id INTEGER NOT NULL REFERENCES experiments(id) ON DELETE CASCADE,
+                         line INTEGER,
+                         request TEXT NOT NULL,
+                         tags TEXT NOT NULL,
+                         generation_id INTEGER
+                     );
+                     INSERT INTO batch_calls_new (id, experiment_id, line, request, tags, generation_id)
+                         SELECT b.id, b.experiment_id, b.line, b.request, b.tags, j.generation_id
+                         FROM batch_calls b LEFT JOIN generation_jobs j ON j.id = b.job_id;
+                     DROP TABLE batch_calls;
+                     ALTER TABLE batch_calls_new RENAME TO batch_calls;
+                     CREATE INDEX IF NOT EXISTS idx_batch_calls_experiment ON batch_calls(experiment_id);
+                     COMMIT;",
+                )
+                .context("Failed to migrate batch_calls")?;
+        }
+
         Ok(())
     }
 
@@ -186,16 +762,19 @@ impl Database {
         self.conn.execute(
             "INSERT INTO generations (slug, prompt, model, provider, timestamp, date, image_path, thumb_path, generation_time_seconds, cost_estimate_usd, seed, width, height, file_size, parent_id, negative_prompt)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
-            params![slug, prompt, model, provider, timestamp, date, image_path, thumb_path, generation_time, cost, seed, width, height, file_size, parent_id, negative_prompt],
+            params![slug, prompt, model, provider, timestamp, date, to_stored(image_path), thumb_path.map(to_stored), generation_time, cost, seed, width, height, file_size, parent_id, negative_prompt],
         ).context("Failed to insert generation")?;
-        Ok(self.conn.last_insert_rowid())
+        let id = self.conn.last_insert_rowid();
+        self.log_event(id, EventAction::Generate, serde_json::json!({ "model": model }))?;
+        Ok(id)
     }
 
     pub fn get_generation(&self, id: i64) -> Result<Option<Generation>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, slug, prompt, model, provider, timestamp, date, image_path, thumb_path,
                     generation_time_seconds, cost_estimate_usd, seed, width, height, file_size,
-                    parent_id, starred, created_at, trashed_at, title, negative_prompt
+                    parent_id, starred, created_at, trashed_at, title, negative_prompt, locked, caption, requested_by, params,
+                    media_type, duration_seconds, fps, animated, nsfw
              FROM generations WHERE id = ?1",
         )?;
 
@@ -209,8 +788,8 @@ impl Database {
                     provider: row.get(4)?,
                     timestamp: row.get(5)?,
                     date: row.get(6)?,
-                    image_path: row.get(7)?,
-                    thumb_path: row.get(8)?,
+                    image_path: resolve(row.get(7)?),
+                    thumb_path: row.get::<_, Option<String>>(8)?.map(resolve),
                     generation_time_seconds: row.get(9)?,
                     cost_estimate_usd: row.get(10)?,
                     seed: row.get(11)?,
@@ -223,9 +802,19 @@ impl Database {
                     trashed_at: row.get(18)?,
                     title: row.get(19)?,
                     negative_prompt: row.get(20)?,
+                    locked: row.get::<_, i32>(21)? != 0,
+                    caption: row.get(22)?,
+                    requested_by: row.get(23)?,
+                    params: row.get::<_, Option<String>>(24)?.and_then(|p| serde_json::from_str(&p).ok()),
+                    media_type: row.get::<_, String>(25)?.parse().unwrap_or(MediaType::Image),
+                    duration_seconds: row.get(26)?,
+                    fps: row.get(27)?,
+                    animated: row.get::<_, i32>(28)? != 0,
+                    nsfw: row.get::<_, i32>(29)? != 0,
                     tags: vec![],
                     references: vec![],
                     collection_names: vec![],
+                    palette: vec![],
                 })
             })
             .optional()?;
@@ -234,6 +823,7 @@ impl Database {
             g.tags = self.get_tags_for_generation(g.id)?;
             g.references = self.get_references_for_generation(g.id)?;
             g.collection_names = self.get_collections_for_generation(g.id)?;
+            g.palette = self.get_palette(g.id)?;
             Ok(Some(g))
         } else {
             Ok(None)
@@ -244,89 +834,26 @@ impl Database {
         let mut sql = String::from(
             "SELECT DISTINCT g.id, g.slug, g.prompt, g.model, g.provider, g.timestamp, g.date,
                     g.image_path, g.thumb_path, g.generation_time_seconds, g.cost_estimate_usd,
-                    g.seed, g.width, g.height, g.file_size, g.parent_id, g.starred, g.created_at, g.trashed_at, g.title, g.negative_prompt
+                    g.seed, g.width, g.height, g.file_size, g.parent_id, g.starred, g.created_at, g.trashed_at, g.title, g.negative_prompt, g.locked, g.caption, g.requested_by, g.params,
+                    g.media_type, g.duration_seconds, g.fps, g.animated, g.nsfw
              FROM generations g",
         );
 
-        let mut conditions = vec![];
-        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![];
-
-        // Trashed filter: show trashed OR exclude trashed (default)
-        if filter.show_trashed {
-            conditions.push("g.trashed_at IS NOT NULL".to_string());
-        } else {
-            conditions.push("g.trashed_at IS NULL".to_string());
-        }
-
-        // Collection filter
-        if let Some(collection_id) = filter.collection_id {
-            conditions.push("g.id IN (SELECT generation_id FROM generation_collections WHERE collection_id = ?)".to_string());
-            params_vec.push(Box::new(collection_id));
-        }
-
-        // Uncategorized: not in any collection
-        if filter.uncategorized {
-            conditions.push("g.id NOT IN (SELECT generation_id FROM generation_collections)".to_string());
-        }
-
-        // Multi-tag filter with AND logic: images must have ALL specified tags
-        if let Some(ref tags) = filter.tags {
-            if !tags.is_empty() {
-                let placeholders: Vec<&str> = tags.iter().map(|_| "?").collect();
-                let in_clause = placeholders.join(", ");
-                conditions.push(format!(
-                    "g.id IN (
-                        SELECT gt.generation_id FROM generation_tags gt
-                        JOIN tags t ON gt.tag_id = t.id
-                        WHERE t.name IN ({})
-                        GROUP BY gt.generation_id
-                        HAVING COUNT(DISTINCT t.name) = {}
-                    )",
-                    in_clause,
-                    tags.len()
-                ));
-                for tag in tags {
-                    params_vec.push(Box::new(tag.clone()));
-                }
-            }
-        }
+        let (mut conditions, mut params_vec) = self.conditions(filter)?;
 
-        // Exclude generations that have ANY of the excluded tags
-        if let Some(ref exclude_tags) = filter.exclude_tags {
-            if !exclude_tags.is_empty() {
-                let placeholders: Vec<&str> = exclude_tags.iter().map(|_| "?").collect();
-                let in_clause = placeholders.join(", ");
-                conditions.push(format!(
-                    "g.id NOT IN (
-                        SELECT gt.generation_id FROM generation_tags gt
-                        JOIN tags t ON gt.tag_id = t.id
-                        WHERE t.name IN ({})
-                    )",
-                    in_clause
-                ));
-                for tag in exclude_tags {
-                    params_vec.push(Box::new(tag.clone()));
+        let mut offset = filter.offset;
+        match filter.cursor.as_deref().map(parse_cursor).transpose()? {
+            // Keyset pagination: only rows after the cursor in (timestamp, id) order
+            Some(PageCursor::Keyset(timestamp, id)) => {
+                if filter.sort.is_some() {
+                    anyhow::bail!("Page cursor doesn't match the sort order; restart from the first page");
                 }
+                conditions.push("(g.timestamp, g.id) < (?, ?)".to_string());
+                params_vec.push(Box::new(timestamp));
+                params_vec.push(Box::new(id));
             }
-        }
-
-        if let Some(ref model) = filter.model {
-            conditions.push("g.model = ?".to_string());
-            params_vec.push(Box::new(model.clone()));
-        }
-
-        if filter.starred_only {
-            conditions.push("g.starred = 1".to_string());
-        }
-
-        if let Some(ref search) = filter.search {
-            conditions.push("g.prompt LIKE ?".to_string());
-            params_vec.push(Box::new(format!("%{}%", search)));
-        }
-
-        if let Some(ref since) = filter.since {
-            conditions.push("g.date >= ?".to_string());
-            params_vec.push(Box::new(since.clone()));
+            Some(PageCursor::Offset(n)) => offset = Some(n),
+            None => {}
         }
 
         if !conditions.is_empty() {
@@ -334,13 +861,14 @@ impl Database {
             sql.push_str(&conditions.join(" AND "));
         }
 
-        sql.push_str(" ORDER BY g.timestamp DESC");
+        sql.push_str(" ORDER BY ");
+        sql.push_str(&order_clause(filter));
 
         if let Some(limit) = filter.limit {
             sql.push_str(&format!(" LIMIT {}", limit));
         }
 
-        if let Some(offset) = filter.offset {
+        if let Some(offset) = offset {
             sql.push_str(&format!(" OFFSET {}", offset));
         }
 
@@ -356,8 +884,8 @@ impl Database {
                 provider: row.get(4)?,
                 timestamp: row.get(5)?,
                 date: row.get(6)?,
-                image_path: row.get(7)?,
-                thumb_path: row.get(8)?,
+                image_path: resolve(row.get(7)?),
+                thumb_path: row.get::<_, Option<String>>(8)?.map(resolve),
                 generation_time_seconds: row.get(9)?,
                 cost_estimate_usd: row.get(10)?,
                 seed: row.get(11)?,
@@ -370,9 +898,19 @@ impl Database {
                 trashed_at: row.get(18)?,
                 title: row.get(19)?,
                 negative_prompt: row.get(20)?,
+                locked: row.get::<_, i32>(21)? != 0,
+                caption: row.get(22)?,
+                requested_by: row.get(23)?,
+                params: row.get::<_, Option<String>>(24)?.and_then(|p| serde_json::from_str(&p).ok()),
+                media_type: row.get::<_, String>(25)?.parse().unwrap_or(MediaType::Image),
+                duration_seconds: row.get(26)?,
+                fps: row.get(27)?,
+                animated: row.get::<_, i32>(28)? != 0,
+                nsfw: row.get::<_, i32>(29)? != 0,
                 tags: vec![],
                 references: vec![],
                 collection_names: vec![],
+                palette: vec![],
             })
         })?;
 
@@ -383,6 +921,7 @@ impl Database {
             let tags_map = self.get_tags_for_generations(&ids)?;
             let refs_map = self.get_references_for_generations(&ids)?;
             let colls_map = self.get_collections_for_generations(&ids)?;
+            let mut palettes = self.get_palettes_for_generations(&ids)?;
 
             for g in &mut generations {
                 if let Some(tags) = tags_map.get(&g.id) {
@@ -394,16 +933,133 @@ impl Database {
                 if let Some(colls) = colls_map.get(&g.id) {
                     g.collection_names = colls.clone();
                 }
+                if let Some(palette) = palettes.remove(&g.id) {
+                    g.palette = palette;
+                }
             }
         }
 
         Ok(generations)
     }
 
-    pub fn search_generations(&self, query: &str, limit: i64) -> Result<Vec<Generation>> {
+    /// `filter_conditions`, plus those of the filter's smart collection if it has one
+    fn conditions(&self, filter: &ListFilter) -> Result<Conditions> {
+        let (mut conditions, mut params_vec) = filter_conditions(filter);
+        if let Some(id) = filter.smart_collection_id {
+            let expr: String = self
+                .conn
+                .query_row("SELECT filter FROM smart_collections WHERE id = ?1", params![id], |row| row.get(0))
+                .optional()?
+                .ok_or_else(|| anyhow::anyhow!("Smart collection {} not found", id))?;
+            let stored = ListFilter::parse_expr(&expr).map_err(|e| anyhow::anyhow!(e))?;
+            let (more, more_params) = filter_conditions(&stored);
+            conditions.extend(more);
+            params_vec.extend(more_params);
+        }
+        Ok((conditions, params_vec))
+    }
+
+    /// Number of generations matching a filter (limit, offset and cursor ignored)
+    pub fn count_generations(&self, filter: &ListFilter) -> Result<i64> {
+        let (conditions, params_vec) = self.conditions(filter)?;
+        let sql = format!("SELECT COUNT(*) FROM generations g WHERE {}", conditions.join(" AND "));
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+        Ok(self.conn.query_row(&sql, params_refs.as_slice(), |row| row.get(0))?)
+    }
+
+    /// Result counts per tag, model and collection under a filter, in one call
+    pub fn filter_facets(&self, filter: &ListFilter) -> Result<FilterFacets> {
+        let tags = self.facet_counts(
+            filter,
+            "SELECT t.name, COUNT(DISTINCT g.id) FROM generations g
+             JOIN generation_tags gt ON gt.generation_id = g.id
+             JOIN tags t ON t.id = gt.tag_id",
+            "t.name",
+        )?;
+
+        let mut model_filter = filter.clone();
+        model_filter.model = None;
+        let models = self.facet_counts(&model_filter, "SELECT g.model, COUNT(*) FROM generations g", "g.model")?;
+
+        let mut collection_filter = filter.clone();
+        collection_filter.collection_id = None;
+        collection_filter.uncategorized = false;
+        let collections = self.facet_counts(
+            &collection_filter,
+            "SELECT c.name, COUNT(DISTINCT g.id) FROM generations g
+             JOIN generation_collections gc ON gc.generation_id = g.id
+             JOIN collections c ON c.id = gc.collection_id AND c.deleted_at IS NULL",
+            "c.name",
+        )?;
+
+        Ok(FilterFacets {
+            total: self.count_generations(filter)?,
+            tags,
+            models,
+            collections,
+        })
+    }
+
+    /// Run a `SELECT name, count ... FROM generations g ...` grouped by `group_by`
+    /// under the filter's conditions, largest first
+    fn facet_counts(&self, filter: &ListFilter, select: &str, group_by: &str) -> Result<Vec<FacetCount>> {
+        let (conditions, params_vec) = self.conditions(filter)?;
+        let sql = format!(
+            "{} WHERE {} GROUP BY {} ORDER BY 2 DESC, 1 ASC",
+            select,
+            conditions.join(" AND "),
+            group_by
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+        let rows = stmt.query_map(params_refs.as_slice(), |row| {
+            Ok(FacetCount {
+                name: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// One page of generations continuing from `filter.cursor`. The default
+    /// newest-first order uses keyset paging, which stays fast deep into large
+    /// archives; other sorts page by offset, and random order is a single page.
+    pub fn list_generations_page(&self, filter: &ListFilter, page_size: i64) -> Result<GenerationPage> {
+        // Fetch one extra row to learn whether another page exists
+        let mut page_filter = filter.clone();
+        page_filter.limit = Some(page_size + 1);
+        page_filter.offset = None;
+        let mut items = self.list_generations(&page_filter)?;
+
+        let next_cursor = if items.len() as i64 > page_size {
+            items.truncate(page_size as usize);
+            match filter.sort {
+                None => items.last().map(|g| format!("{}|{}", g.timestamp, g.id)),
+                Some(SortKey::Random) => None,
+                Some(_) => {
+                    let start = match filter.cursor.as_deref().map(parse_cursor).transpose()? {
+                        Some(PageCursor::Offset(n)) => n,
+                        _ => 0,
+                    };
+                    Some(format!("+{}", start + page_size))
+                }
+            }
+        } else {
+            None
+        };
+
+        Ok(GenerationPage {
+            items,
+            next_cursor,
+            total: self.count_generations(filter)?,
+        })
+    }
+
+    pub fn search_generations(&self, query: &str, limit: i64, include_nsfw: bool) -> Result<Vec<Generation>> {
         self.list_generations(&ListFilter {
             limit: Some(limit),
             search: Some(query.to_string()),
+            hide_nsfw: !include_nsfw,
             ..Default::default()
         })
     }
@@ -420,35 +1076,90 @@ impl Database {
                 row.get(0)
             })?;
 
+        let action = if starred != 0 { EventAction::Star } else { EventAction::Unstar };
+        self.log_event(id, action, serde_json::json!({}))?;
         Ok(starred != 0)
     }
 
-    pub fn trash_generation(&self, id: i64) -> Result<bool> {
-        let now = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+    pub fn set_locked(&self, id: i64, locked: bool) -> Result<bool> {
         let rows = self.conn.execute(
-            "UPDATE generations SET trashed_at = ?1 WHERE id = ?2 AND trashed_at IS NULL",
-            params![now, id],
+            "UPDATE generations SET locked = ?1 WHERE id = ?2",
+            params![locked as i32, id],
         )?;
         Ok(rows > 0)
     }
 
-    pub fn trash_generations(&self, ids: &[i64]) -> Result<usize> {
-        if ids.is_empty() {
-            return Ok(0);
-        }
-        let now = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
-        let placeholders = ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-        let sql = format!(
-            "UPDATE generations SET trashed_at = ?1 WHERE id IN ({}) AND trashed_at IS NULL",
-            placeholders
-        );
-        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(now)];
-        for id in ids {
-            params_vec.push(Box::new(*id));
+    /// Flag or unflag a generation as NSFW. Not blocked by `locked`, like starring.
+    pub fn set_nsfw(&self, id: i64, nsfw: bool) -> Result<bool> {
+        let rows = self.conn.execute(
+            "UPDATE generations SET nsfw = ?1 WHERE id = ?2",
+            params![nsfw as i32, id],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Unflagged generations, newest first, for `pixery nsfw scan`.
+    /// Returns (id, prompt, media type, image path).
+    pub fn list_unflagged(&self, limit: Option<i64>) -> Result<Vec<(i64, String, MediaType, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, prompt, media_type, image_path FROM generations
+             WHERE nsfw = 0 AND trashed_at IS NULL
+             ORDER BY timestamp DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit.unwrap_or(-1)], |row| {
+            let media_type: String = row.get(2)?;
+            Ok((row.get(0)?, row.get(1)?, media_type.parse().unwrap_or(MediaType::Image), resolve(row.get(3)?)))
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Fail if the generation is locked. Missing generations pass through so
+    /// callers keep their existing not-found behavior.
+    fn ensure_unlocked(&self, id: i64) -> Result<()> {
+        let locked: Option<i32> = self
+            .conn
+            .query_row(
+                "SELECT locked FROM generations WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if locked.unwrap_or(0) != 0 {
+            return Err(PixeryError::Locked(id).into());
         }
-        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
-        let rows = self.conn.execute(&sql, params_refs.as_slice())?;
-        Ok(rows)
+        Ok(())
+    }
+
+    pub fn trash_generation(&self, id: i64) -> Result<bool> {
+        self.ensure_unlocked(id)?;
+        let now = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+        let rows = self.conn.execute(
+            "UPDATE generations SET trashed_at = ?1 WHERE id = ?2 AND trashed_at IS NULL",
+            params![now, id],
+        )?;
+        if rows > 0 {
+            self.log_event(id, EventAction::Trash, serde_json::json!({}))?;
+        }
+        Ok(rows > 0)
+    }
+
+    pub fn trash_generations(&self, ids: &[i64]) -> Result<usize> {
+        let now = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+        let tx = self.conn.unchecked_transaction()?;
+        let mut trashed = 0;
+        for &id in ids {
+            // Locked generations are skipped rather than failing the whole batch
+            let rows = tx.execute(
+                "UPDATE generations SET trashed_at = ?1 WHERE id = ?2 AND trashed_at IS NULL AND locked = 0",
+                params![now, id],
+            )?;
+            if rows > 0 {
+                self.log_event(id, EventAction::Trash, serde_json::json!({}))?;
+                trashed += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(trashed)
     }
 
     pub fn restore_generation(&self, id: i64) -> Result<bool> {
@@ -456,42 +1167,114 @@ impl Database {
             "UPDATE generations SET trashed_at = NULL WHERE id = ?1 AND trashed_at IS NOT NULL",
             params![id],
         )?;
+        if rows > 0 {
+            self.log_event(id, EventAction::Restore, serde_json::json!({}))?;
+        }
         Ok(rows > 0)
     }
 
     pub fn permanently_delete_generation(&self, id: i64) -> Result<Option<String>> {
+        self.ensure_unlocked(id)?;
         let path: Option<String> = self
             .conn
             .query_row(
                 "SELECT image_path FROM generations WHERE id = ?1",
                 params![id],
-                |row| row.get(0),
+                |row| row.get(0).map(resolve),
             )
             .optional()?;
 
-        self.conn
-            .execute("DELETE FROM generations WHERE id = ?1", params![id])?;
+        // Its children (variations, edits, transforms) and job records stay,
+        // no longer linked to it
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("UPDATE generations SET parent_id = NULL WHERE parent_id = ?1", params![id])?;
+        tx.execute("UPDATE generation_jobs SET generation_id = NULL WHERE generation_id = ?1", params![id])?;
+        tx.execute("DELETE FROM generations WHERE id = ?1", params![id])?;
+        tx.commit()?;
 
+        if let Some(path) = &path {
+            self.log_event(id, EventAction::Delete, serde_json::json!({ "image_path": path }))?;
+        }
         Ok(path)
     }
 
+    /// Trashed generations (id, image_path) due for purging. Locked ones stay
+    /// put (they can be locked after trashing) until unlocked, rather than
+    /// failing the purge.
+    pub fn list_trashed_before(&self, cutoff: &str) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, image_path FROM generations
+             WHERE trashed_at IS NOT NULL AND trashed_at < ?1 AND COALESCE(locked, 0) = 0",
+        )?;
+        let rows = stmt.query_map(params![cutoff], |row| Ok((row.get(0)?, resolve(row.get(1)?))))?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
     pub fn update_prompt(&self, id: i64, prompt: &str) -> Result<()> {
+        self.ensure_unlocked(id)?;
+        let before: Option<String> = self
+            .conn
+            .query_row("SELECT prompt FROM generations WHERE id = ?1", params![id], |row| row.get(0))
+            .optional()?;
         self.conn.execute(
             "UPDATE generations SET prompt = ?1 WHERE id = ?2",
             params![prompt, id],
         )?;
+        if let Some(before) = before.filter(|b| b != prompt) {
+            self.log_event(id, EventAction::EditPrompt, serde_json::json!({ "before": before, "after": prompt }))?;
+        }
+        Ok(())
+    }
+
+    pub fn set_content_hash(&self, id: i64, hash: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE generations SET content_hash = ?1 WHERE id = ?2",
+            params![hash, id],
+        )?;
         Ok(())
     }
 
+    /// (id, image_path) of generations whose content hash isn't known yet
+    pub fn unhashed_generations(&self) -> Result<Vec<(i64, String)>> {
+        let rows = self
+            .conn
+            .prepare("SELECT id, image_path FROM generations WHERE content_hash IS NULL ORDER BY id")?
+            .query_map([], |row| Ok((row.get(0)?, resolve(row.get(1)?))))?
+            .collect::<Result<_, _>>()?;
+        Ok(rows)
+    }
+
+    /// Oldest generation (trashed ones included) whose image has this content hash
+    pub fn find_by_content_hash(&self, hash: &str) -> Result<Option<i64>> {
+        let id = self
+            .conn
+            .query_row(
+                "SELECT id FROM generations WHERE content_hash = ?1 ORDER BY id LIMIT 1",
+                params![hash],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(id)
+    }
+
     pub fn update_title(&self, id: i64, title: Option<&str>) -> Result<()> {
+        self.ensure_unlocked(id)?;
+        let before: Option<Option<String>> = self
+            .conn
+            .query_row("SELECT title FROM generations WHERE id = ?1", params![id], |row| row.get(0))
+            .optional()?;
         self.conn.execute(
             "UPDATE generations SET title = ?1 WHERE id = ?2",
             params![title, id],
         )?;
+        if let Some(before) = before.filter(|b| b.as_deref() != title) {
+            self.log_event(id, EventAction::EditTitle, serde_json::json!({ "before": before, "after": title }))?;
+        }
         Ok(())
     }
 
     pub fn update_model(&self, id: i64, model: &str, provider: &str) -> Result<()> {
+        self.ensure_unlocked(id)?;
         self.conn.execute(
             "UPDATE generations SET model = ?1, provider = ?2 WHERE id = ?3",
             params![model, provider, id],
@@ -499,10 +1282,84 @@ impl Database {
         Ok(())
     }
 
+    /// Store an auto-generated caption. Not blocked by `locked`: captions are
+    /// derived metadata, not user edits.
+    pub fn set_caption(&self, id: i64, caption: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE generations SET caption = ?1 WHERE id = ?2",
+            params![caption, id],
+        )?;
+        Ok(())
+    }
+
+    /// Mark a generation as a video clip, with its length and frame rate when known
+    pub fn set_video_metadata(&self, id: i64, duration_seconds: Option<f64>, fps: Option<f64>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE generations SET media_type = ?1, duration_seconds = ?2, fps = ?3 WHERE id = ?4",
+            params![MediaType::Video.to_string(), duration_seconds, fps, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn set_generation_params(&self, id: i64, params: &serde_json::Value) -> Result<()> {
+        self.conn.execute(
+            "UPDATE generations SET params = ?1 WHERE id = ?2",
+            params![params.to_string(), id],
+        )?;
+        Ok(())
+    }
+
+    /// Flag a generation's image as animated, so the GUI plays it rather than
+    /// showing its (first-frame) thumbnail
+    pub fn set_animated(&self, id: i64) -> Result<()> {
+        self.conn.execute("UPDATE generations SET animated = 1 WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    /// Record who requested a generation (e.g. `discord:alice` from the Discord bot)
+    pub fn set_requested_by(&self, id: i64, requested_by: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE generations SET requested_by = ?1 WHERE id = ?2",
+            params![requested_by, id],
+        )?;
+        Ok(())
+    }
+
+    /// Generations without a caption, newest first. Returns (id, thumbnail or image path).
+    pub fn list_uncaptioned(&self, limit: i64) -> Result<Vec<(i64, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, image_path FROM generations
+             WHERE caption IS NULL AND trashed_at IS NULL
+             ORDER BY timestamp DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map(params![limit], |row| Ok((row.get(0)?, resolve(row.get(1)?))))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Look up a generation by its identity across archives (bundle import dedup)
+    pub fn find_generation_id(&self, timestamp: &str, model: &str, prompt: &str) -> Result<Option<i64>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT id FROM generations WHERE timestamp = ?1 AND model = ?2 AND prompt = ?3",
+                params![timestamp, model, prompt],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    pub fn set_parent_id(&self, id: i64, parent_id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE generations SET parent_id = ?1 WHERE id = ?2",
+            params![parent_id, id],
+        )?;
+        Ok(())
+    }
+
     pub fn update_thumb_path(&self, id: i64, thumb_path: &str) -> Result<()> {
         self.conn.execute(
             "UPDATE generations SET thumb_path = ?1 WHERE id = ?2",
-            params![thumb_path, id],
+            params![to_stored(thumb_path), id],
         )?;
         Ok(())
     }
@@ -521,27 +1378,40 @@ impl Database {
             return Ok(id);
         }
 
-        self.conn
-            .execute("INSERT INTO tags (name) VALUES (?1)", params![name])?;
+        self.conn.execute(
+            "INSERT INTO tags (name, namespace) VALUES (?1, ?2)",
+            params![name, models::tag_namespace(name)],
+        )?;
         Ok(self.conn.last_insert_rowid())
     }
 
     pub fn add_tags(&self, generation_id: i64, tags: &[String]) -> Result<()> {
+        let mut added = vec![];
         for tag in tags {
             let tag_id = self.get_or_create_tag(tag)?;
-            self.conn.execute(
+            let rows = self.conn.execute(
                 "INSERT OR IGNORE INTO generation_tags (generation_id, tag_id) VALUES (?1, ?2)",
                 params![generation_id, tag_id],
             )?;
+            if rows > 0 {
+                added.push(tag);
+            }
+        }
+        if !added.is_empty() {
+            self.log_event(generation_id, EventAction::Tag, serde_json::json!({ "tags": added }))?;
         }
         Ok(())
     }
 
     pub fn remove_tag(&self, generation_id: i64, tag: &str) -> Result<()> {
-        self.conn.execute(
+        self.ensure_unlocked(generation_id)?;
+        let rows = self.conn.execute(
             "DELETE FROM generation_tags WHERE generation_id = ?1 AND tag_id = (SELECT id FROM tags WHERE name = ?2)",
             params![generation_id, tag],
         )?;
+        if rows > 0 {
+            self.log_event(generation_id, EventAction::Untag, serde_json::json!({ "tags": [tag] }))?;
+        }
         Ok(())
     }
 
@@ -588,7 +1458,7 @@ impl Database {
                 Reference {
                     id: row.get(1)?,
                     hash: row.get(2)?,
-                    path: row.get(3)?,
+                    path: resolve(row.get(3)?),
                     created_at: row.get(4)?,
                 },
             ))
@@ -615,7 +1485,8 @@ impl Database {
 
     pub fn get_collections_for_generation(&self, generation_id: i64) -> Result<Vec<String>> {
         let mut stmt = self.conn.prepare(
-            "SELECT c.name FROM collections c JOIN generation_collections gc ON c.id = gc.collection_id WHERE gc.generation_id = ?1 ORDER BY c.name",
+            "SELECT c.name FROM collections c JOIN generation_collections gc ON c.id = gc.collection_id
+             WHERE gc.generation_id = ?1 AND c.deleted_at IS NULL ORDER BY c.name",
         )?;
         let rows = stmt.query_map(params![generation_id], |row| row.get(0))?;
         let mut names = vec![];
@@ -630,7 +1501,7 @@ impl Database {
         let sql = format!(
             "SELECT gc.generation_id, c.name FROM generation_collections gc
              JOIN collections c ON gc.collection_id = c.id
-             WHERE gc.generation_id IN ({})
+             WHERE gc.generation_id IN ({}) AND c.deleted_at IS NULL
              ORDER BY c.name",
             placeholders
         );
@@ -651,7 +1522,7 @@ impl Database {
 
     pub fn list_tags(&self) -> Result<Vec<TagCount>> {
         let mut stmt = self.conn.prepare(
-            "SELECT t.name, COUNT(gt.generation_id) as count
+            "SELECT t.name, COUNT(gt.generation_id) as count, t.namespace
              FROM tags t
              LEFT JOIN generation_tags gt ON t.id = gt.tag_id
              GROUP BY t.id
@@ -662,6 +1533,7 @@ impl Database {
             Ok(TagCount {
                 name: row.get(0)?,
                 count: row.get(1)?,
+                namespace: row.get(2)?,
             })
         })?;
 
@@ -672,6 +1544,111 @@ impl Database {
         Ok(tags)
     }
 
+    /// Add and remove tags on every generation matching `filter`, in one
+    /// transaction. Locked generations gain tags but never lose them.
+    pub fn bulk_tag(&self, filter: &ListFilter, add: &[String], remove: &[String]) -> Result<BulkTagReport> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut report = BulkTagReport::default();
+
+        // Each statement binds its own copy of the filter's parameters
+        let run = |sql: &str, leading: Option<i64>| -> Result<i64> {
+            let (conditions, filter_params) = self.conditions(filter)?;
+            let sql = sql.replace("{where}", &conditions.join(" AND "));
+            let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![];
+            if let Some(value) = leading {
+                params_vec.push(Box::new(value));
+            }
+            params_vec.extend(filter_params);
+            let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+            if sql.starts_with("SELECT") {
+                Ok(self.conn.query_row(&sql, params_refs.as_slice(), |row| row.get(0))?)
+            } else {
+                Ok(self.conn.execute(&sql, params_refs.as_slice())? as i64)
+            }
+        };
+
+        report.matched = run("SELECT COUNT(*) FROM generations g WHERE {where}", None)?;
+        if !remove.is_empty() {
+            report.locked = run("SELECT COUNT(*) FROM generations g WHERE {where} AND g.locked = 1", None)?;
+        }
+
+        for tag in add {
+            let tag_id = self.get_or_create_tag(tag)?;
+            report.added += run(
+                "INSERT OR IGNORE INTO generation_tags (generation_id, tag_id)
+                 SELECT g.id, ? FROM generations g WHERE {where}",
+                Some(tag_id),
+            )?;
+        }
+        for tag in remove {
+            let tag_id: Option<i64> = self
+                .conn
+                .query_row("SELECT id FROM tags WHERE name = ?1", params![tag], |row| row.get(0))
+                .optional()?;
+            if let Some(tag_id) = tag_id {
+                report.removed += run(
+                    "DELETE FROM generation_tags WHERE tag_id = ? AND generation_id IN (
+                         SELECT g.id FROM generations g WHERE {where} AND COALESCE(g.locked, 0) = 0
+                     )",
+                    Some(tag_id),
+                )?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(report)
+    }
+
+    /// Rename a tag everywhere; returns how many generations carry it. Fails
+    /// if `new` already exists (merge instead).
+    pub fn rename_tag(&self, old: &str, new: &str) -> Result<i64> {
+        let exists: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM tags WHERE name = ?1",
+            params![new],
+            |row| row.get(0),
+        )?;
+        if exists > 0 {
+            anyhow::bail!("Tag '{}' already exists (use `pixery tags merge {} into {}`)", new, old, new);
+        }
+        let rows = self.conn.execute(
+            "UPDATE tags SET name = ?1, namespace = ?2 WHERE name = ?3",
+            params![new, models::tag_namespace(new), old],
+        )?;
+        if rows == 0 {
+            anyhow::bail!("Tag '{}' not found", old);
+        }
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) FROM generation_tags gt JOIN tags t ON t.id = gt.tag_id WHERE t.name = ?1",
+            params![new],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Fold `sources` into `target` (created if needed): every generation with
+    /// a source tag gets the target, and the source tags are deleted. Returns
+    /// the number of generations that newly gained the target.
+    pub fn merge_tags(&self, sources: &[String], target: &str) -> Result<i64> {
+        let tx = self.conn.unchecked_transaction()?;
+        let target_id = self.get_or_create_tag(target)?;
+        let mut gained = 0;
+        for source in sources.iter().filter(|s| s.as_str() != target) {
+            let source_id: i64 = self
+                .conn
+                .query_row("SELECT id FROM tags WHERE name = ?1", params![source], |row| row.get(0))
+                .optional()?
+                .ok_or_else(|| anyhow::anyhow!("Tag '{}' not found", source))?;
+            gained += self.conn.execute(
+                "INSERT OR IGNORE INTO generation_tags (generation_id, tag_id)
+                 SELECT generation_id, ?1 FROM generation_tags WHERE tag_id = ?2",
+                params![target_id, source_id],
+            )? as i64;
+            self.conn.execute("DELETE FROM generation_tags WHERE tag_id = ?1", params![source_id])?;
+            self.conn.execute("DELETE FROM tags WHERE id = ?1", params![source_id])?;
+        }
+        tx.commit()?;
+        Ok(gained)
+    }
+
     // Reference operations
 
     pub fn get_or_create_reference(&self, hash: &str, path: &str) -> Result<i64> {
@@ -688,7 +1665,7 @@ impl Database {
 
         self.conn.execute(
             "INSERT INTO refs (hash, path) VALUES (?1, ?2)",
-            params![hash, path],
+            params![hash, to_stored(path)],
         )?;
         Ok(self.conn.last_insert_rowid())
     }
@@ -710,7 +1687,25 @@ impl Database {
                     Ok(Reference {
                         id: row.get(0)?,
                         hash: row.get(1)?,
-                        path: row.get(2)?,
+                        path: resolve(row.get(2)?),
+                        created_at: row.get(3)?,
+                    })
+                },
+            )
+            .optional()
+            .context("Failed to query reference")
+    }
+
+    pub fn get_reference(&self, id: i64) -> Result<Option<Reference>> {
+        self.conn
+            .query_row(
+                "SELECT id, hash, path, created_at FROM refs WHERE id = ?1",
+                params![id],
+                |row| {
+                    Ok(Reference {
+                        id: row.get(0)?,
+                        hash: row.get(1)?,
+                        path: resolve(row.get(2)?),
                         created_at: row.get(3)?,
                     })
                 },
@@ -719,6 +1714,75 @@ impl Database {
             .context("Failed to query reference")
     }
 
+    /// References no generation links to any more, oldest first. Ones created in the
+    /// last hour are left out: an in-flight generation may not have linked them yet.
+    pub fn list_unused_references(&self) -> Result<Vec<Reference>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, hash, path, created_at FROM refs
+             WHERE id NOT IN (SELECT ref_id FROM generation_refs)
+               AND created_at < datetime('now', '-1 hour')
+             ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(Reference {
+                id: row.get(0)?,
+                hash: row.get(1)?,
+                path: resolve(row.get(2)?),
+                created_at: row.get(3)?,
+            })
+        })?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    pub fn delete_reference(&self, id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM refs WHERE id = ?1", params![id])
+            .context("Failed to delete reference")?;
+        Ok(())
+    }
+
+    /// All stored references, newest first, with how many generations used each
+    pub fn list_references(&self) -> Result<Vec<ReferenceInfo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT r.id, r.hash, r.path, r.created_at, COUNT(gr.generation_id)
+             FROM refs r
+             LEFT JOIN generation_refs gr ON gr.ref_id = r.id
+             GROUP BY r.id
+             ORDER BY r.id DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ReferenceInfo {
+                id: row.get(0)?,
+                hash: row.get(1)?,
+                path: resolve(row.get(2)?),
+                created_at: row.get(3)?,
+                use_count: row.get(4)?,
+            })
+        })?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// Generations made with a reference, newest first
+    pub fn get_generations_for_reference(&self, ref_id: i64) -> Result<Vec<Generation>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT g.id FROM generations g
+             JOIN generation_refs gr ON gr.generation_id = g.id
+             WHERE gr.ref_id = ?1
+             ORDER BY g.timestamp DESC, g.id DESC",
+        )?;
+        let ids = stmt
+            .query_map(params![ref_id], |row| row.get::<_, i64>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut generations = vec![];
+        for id in ids {
+            if let Some(generation) = self.get_generation(id)? {
+                generations.push(generation);
+            }
+        }
+        Ok(generations)
+    }
+
     pub fn get_references_for_generation(&self, generation_id: i64) -> Result<Vec<Reference>> {
         let mut stmt = self.conn.prepare(
             "SELECT r.id, r.hash, r.path, r.created_at
@@ -731,7 +1795,7 @@ impl Database {
             Ok(Reference {
                 id: row.get(0)?,
                 hash: row.get(1)?,
-                path: row.get(2)?,
+                path: resolve(row.get(2)?),
                 created_at: row.get(3)?,
             })
         })?;
@@ -743,6 +1807,130 @@ impl Database {
         Ok(refs)
     }
 
+    // Palette operations
+
+    /// Replace a generation's palette
+    pub fn set_palette(&self, generation_id: i64, swatches: &[Swatch]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM generation_colors WHERE generation_id = ?1", params![generation_id])?;
+        for (position, swatch) in swatches.iter().enumerate() {
+            let color: Color = swatch.hex.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+            let [l, a, b] = color.lab();
+            tx.execute(
+                "INSERT INTO generation_colors (generation_id, position, hex, share, l, a, b)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![generation_id, position as i64, swatch.hex, swatch.share, l, a, b],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn get_palette(&self, generation_id: i64) -> Result<Vec<Swatch>> {
+        let swatches = self
+            .conn
+            .prepare("SELECT hex, share FROM generation_colors WHERE generation_id = ?1 ORDER BY position")?
+            .query_map(params![generation_id], |row| Ok(Swatch { hex: row.get(0)?, share: row.get(1)? }))?
+            .collect::<Result<_, _>>()?;
+        Ok(swatches)
+    }
+
+    fn get_palettes_for_generations(&self, ids: &[i64]) -> Result<HashMap<i64, Vec<Swatch>>> {
+        let placeholders = ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let sql = format!(
+            "SELECT generation_id, hex, share FROM generation_colors
+             WHERE generation_id IN ({}) ORDER BY generation_id, position",
+            placeholders
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let params: Vec<Box<dyn rusqlite::ToSql>> = ids.iter().map(|id| Box::new(*id) as Box<dyn rusqlite::ToSql>).collect();
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+
+        let mut map: HashMap<i64, Vec<Swatch>> = HashMap::new();
+        let rows = stmt.query_map(params_refs.as_slice(), |row| {
+            Ok((row.get::<_, i64>(0)?, Swatch { hex: row.get(1)?, share: row.get(2)? }))
+        })?;
+        for row in rows {
+            let (gen_id, swatch) = row?;
+            map.entry(gen_id).or_default().push(swatch);
+        }
+        Ok(map)
+    }
+
+    /// (id, image_path) of generations, trashed ones included, with no palette yet
+    pub fn generations_without_palette(&self) -> Result<Vec<(i64, String)>> {
+        let rows = self
+            .conn
+            .prepare(
+                "SELECT id, image_path FROM generations
+                 WHERE id NOT IN (SELECT generation_id FROM generation_colors) ORDER BY id",
+            )?
+            .query_map([], |row| Ok((row.get(0)?, resolve(row.get(1)?))))?
+            .collect::<Result<_, _>>()?;
+        Ok(rows)
+    }
+
+    // Asset operations
+
+    pub fn insert_asset(&self, generation_id: i64, kind: AssetKind, path: &str, file_size: Option<i64>, note: Option<&str>) -> Result<i64> {
+        self.conn
+            .execute(
+                "INSERT INTO assets (generation_id, kind, path, file_size, note) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![generation_id, kind.to_string(), to_stored(path), file_size, note],
+            )
+            .context("Failed to insert asset")?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    fn asset_from_row(row: &rusqlite::Row) -> rusqlite::Result<Asset> {
+        Ok(Asset {
+            id: row.get(0)?,
+            generation_id: row.get(1)?,
+            kind: row.get::<_, String>(2)?.parse().unwrap_or_default(),
+            path: resolve(row.get(3)?),
+            file_size: row.get(4)?,
+            note: row.get(5)?,
+            created_at: row.get(6)?,
+        })
+    }
+
+    pub fn get_asset(&self, id: i64) -> Result<Option<Asset>> {
+        self.conn
+            .query_row(
+                "SELECT id, generation_id, kind, path, file_size, note, created_at FROM assets WHERE id = ?1",
+                params![id],
+                Self::asset_from_row,
+            )
+            .optional()
+            .context("Failed to query asset")
+    }
+
+    /// A generation's assets, oldest first
+    pub fn get_assets_for_generation(&self, generation_id: i64) -> Result<Vec<Asset>> {
+        let assets = self
+            .conn
+            .prepare(
+                "SELECT id, generation_id, kind, path, file_size, note, created_at
+                 FROM assets WHERE generation_id = ?1 ORDER BY id",
+            )?
+            .query_map(params![generation_id], Self::asset_from_row)?
+            .collect::<Result<_, _>>()?;
+        Ok(assets)
+    }
+
+    /// Remove an asset's row; the caller deletes the file
+    pub fn delete_asset(&self, id: i64) -> Result<bool> {
+        let generation_id: Option<i64> = self
+            .conn
+            .query_row("SELECT generation_id FROM assets WHERE id = ?1", params![id], |row| row.get(0))
+            .optional()?;
+        if let Some(generation_id) = generation_id {
+            self.ensure_unlocked(generation_id)?;
+        }
+        let rows = self.conn.execute("DELETE FROM assets WHERE id = ?1", params![id])?;
+        Ok(rows > 0)
+    }
+
     // Cost tracking
 
     pub fn get_cost_summary(&self, since: Option<&str>) -> Result<CostSummary> {
@@ -813,14 +2001,229 @@ impl Database {
             }
         }
 
-        Ok(CostSummary {
-            total_usd: total,
-            by_model,
+        let by_collection = self.cost_breakdown(
+            "SELECT c.name, COALESCE(SUM(g.cost_estimate_usd), 0) FROM generations g
+             JOIN generation_collections gc ON gc.generation_id = g.id
+             JOIN collections c ON c.id = gc.collection_id AND c.deleted_at IS NULL
+             WHERE ?1 IS NULL OR g.date >= ?1
+             GROUP BY c.id ORDER BY SUM(g.cost_estimate_usd) DESC",
+            since,
+        )?;
+
+        let by_tag = self.cost_breakdown(
+            "SELECT t.name, COALESCE(SUM(g.cost_estimate_usd), 0) FROM generations g
+             JOIN generation_tags gt ON gt.generation_id = g.id
+             JOIN tags t ON t.id = gt.tag_id
+             WHERE ?1 IS NULL OR g.date >= ?1
+             GROUP BY t.id ORDER BY SUM(g.cost_estimate_usd) DESC LIMIT 30",
+            since,
+        )?;
+
+        Ok(CostSummary {
+            total_usd: total,
+            by_model,
             by_day,
+            by_collection,
+            by_tag,
             count,
+            currency: Default::default(),
+        })
+    }
+
+    /// Run a `name, cost` grouping query whose only parameter is the since date
+    fn cost_breakdown(&self, sql: &str, since: Option<&str>) -> Result<Vec<(String, f64)>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt.query_map(params![since], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// Per-generation spend since a date, oldest first. Includes trashed
+    /// generations so the rows add up to the cost summary.
+    pub fn list_costs(&self, since: Option<&str>) -> Result<Vec<CostRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, date, model, provider, cost_estimate_usd, prompt FROM generations
+             WHERE ?1 IS NULL OR date >= ?1
+             ORDER BY timestamp, id",
+        )?;
+        let rows = stmt.query_map(params![since], |row| {
+            Ok(CostRow {
+                id: row.get(0)?,
+                date: row.get(1)?,
+                model: row.get(2)?,
+                provider: row.get(3)?,
+                cost_usd: row.get(4)?,
+                prompt: row.get(5)?,
+            })
+        })?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// Set a model's price from `effective_from` (YYYY-MM-DD) on, replacing
+    /// one that starts the same day
+    pub fn set_price(&self, model: &str, cost: f64, effective_from: &str, note: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO pricing (model, cost, effective_from, note) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (model, effective_from) DO UPDATE SET cost = excluded.cost, note = excluded.note",
+            params![model, cost, effective_from, note],
+        )?;
+        Ok(())
+    }
+
+    /// Remove the price starting on `effective_from`; false if there was none
+    pub fn remove_price(&self, model: &str, effective_from: &str) -> Result<bool> {
+        let n = self.conn.execute(
+            "DELETE FROM pricing WHERE model = ?1 AND effective_from = ?2",
+            params![model, effective_from],
+        )?;
+        Ok(n > 0)
+    }
+
+    /// The price in force on `date`; None when the table has none yet for the model
+    pub fn price_on(&self, model: &str, date: &str) -> Result<Option<f64>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT cost FROM pricing WHERE model = ?1 AND effective_from <= ?2
+                 ORDER BY effective_from DESC LIMIT 1",
+                params![model, date],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Price history, by model then date
+    pub fn list_prices(&self, model: Option<&str>) -> Result<Vec<PriceEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, model, cost, effective_from, note, created_at FROM pricing
+             WHERE ?1 IS NULL OR model = ?1
+             ORDER BY model, effective_from",
+        )?;
+        let rows = stmt.query_map(params![model], |row| {
+            Ok(PriceEntry {
+                id: row.get(0)?,
+                model: row.get(1)?,
+                cost: row.get(2)?,
+                effective_from: row.get(3)?,
+                note: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// Rewrite the cost of a model's generations dated `from` up to `until`
+    /// (exclusive; open-ended when None) at `cost` per image, or per second
+    /// for videos. Overwrites what providers reported too. Returns the number
+    /// of generations changed.
+    pub fn reprice_generations(&self, model: &str, cost: f64, from: &str, until: Option<&str>) -> Result<usize> {
+        Ok(self.conn.execute(
+            "UPDATE generations
+             SET cost_estimate_usd = CASE WHEN media_type = 'video' THEN ?2 * duration_seconds ELSE ?2 END
+             WHERE model = ?1 AND date >= ?3 AND (?4 IS NULL OR date < ?4)",
+            params![model, cost, from, until],
+        )?)
+    }
+
+    /// Usage statistics since a date (all time if None). Trashed generations
+    /// are included, as they were still paid for.
+    pub fn get_stats(&self, since: Option<&str>) -> Result<Stats> {
+        let (total_generations, total_cost_usd): (i64, f64) = self.conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(cost_estimate_usd), 0) FROM generations
+             WHERE ?1 IS NULL OR date >= ?1",
+            params![since],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let period_stats = |period_expr: &str| -> Result<Vec<PeriodStat>> {
+            let sql = format!(
+                "SELECT {0}, COUNT(*), COALESCE(SUM(cost_estimate_usd), 0) FROM generations
+                 WHERE ?1 IS NULL OR date >= ?1
+                 GROUP BY {0} ORDER BY {0}",
+                period_expr
+            );
+            let mut stmt = self.conn.prepare(&sql)?;
+            let rows = stmt.query_map(params![since], |row| {
+                Ok(PeriodStat {
+                    period: row.get(0)?,
+                    count: row.get(1)?,
+                    cost_usd: row.get(2)?,
+                })
+            })?;
+            rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+        };
+        let per_day = period_stats("date")?;
+        let per_week = period_stats("strftime('%Y-W%W', date)")?;
+
+        // Job created_at is UTC; close enough for day-granularity filtering
+        let (jobs_completed, jobs_failed): (i64, i64) = self.conn.query_row(
+            "SELECT COALESCE(SUM(status = 'completed'), 0), COALESCE(SUM(status = 'failed'), 0)
+             FROM generation_jobs WHERE ?1 IS NULL OR date(created_at) >= ?1",
+            params![since],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let finished = jobs_completed + jobs_failed;
+        let success_rate = (finished > 0).then(|| jobs_completed as f64 / finished as f64);
+
+        let mut stmt = self.conn.prepare(
+            "SELECT model, COUNT(*), COALESCE(SUM(cost_estimate_usd), 0), AVG(generation_time_seconds)
+             FROM generations WHERE ?1 IS NULL OR date >= ?1
+             GROUP BY model ORDER BY COUNT(*) DESC",
+        )?;
+        let by_model = stmt
+            .query_map(params![since], |row| {
+                Ok(ModelStat {
+                    model: row.get(0)?,
+                    count: row.get(1)?,
+                    cost_usd: row.get(2)?,
+                    avg_generation_seconds: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = self.conn.prepare(
+            "SELECT t.name, COUNT(*), t.namespace FROM tags t
+             JOIN generation_tags gt ON gt.tag_id = t.id
+             JOIN generations g ON g.id = gt.generation_id
+             WHERE ?1 IS NULL OR g.date >= ?1
+             GROUP BY t.id ORDER BY COUNT(*) DESC, t.name ASC LIMIT 20",
+        )?;
+        let top_tags = stmt
+            .query_map(params![since], |row| {
+                Ok(TagCount {
+                    name: row.get(0)?,
+                    count: row.get(1)?,
+                    namespace: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Stats {
+            total_generations,
+            total_cost_usd,
+            per_day,
+            per_week,
+            jobs_completed,
+            jobs_failed,
+            success_rate,
+            by_model,
+            top_tags,
         })
     }
 
+    /// Generation times by model and hour of day, optionally for one model.
+    /// Imports and other generations without a recorded time are left out.
+    pub fn get_latency_stats(&self, since: Option<&str>, model: Option<&str>) -> Result<LatencyStats> {
+        let mut stmt = self.conn.prepare(
+            "SELECT model, substr(timestamp, 12, 2), generation_time_seconds FROM generations
+             WHERE generation_time_seconds IS NOT NULL
+               AND (?1 IS NULL OR date >= ?1) AND (?2 IS NULL OR model = ?2)",
+        )?;
+        let samples = stmt
+            .query_map(params![since, model], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(LatencyStats::from_samples(&samples))
+    }
+
     // Job operations
 
     pub fn create_job(
@@ -830,15 +2233,28 @@ impl Database {
         tags: Option<&[String]>,
         source: JobSource,
         ref_count: i32,
+        request: Option<&GenerationRequest>,
     ) -> Result<i64> {
         let tags_json = tags.map(|t| serde_json::to_string(t).unwrap_or_default());
+        let request_json = request.map(|r| serde_json::to_string(r).unwrap_or_default());
         self.conn.execute(
-            "INSERT INTO generation_jobs (model, prompt, tags, source, ref_count) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![model, prompt, tags_json, source.to_string(), ref_count],
+            "INSERT INTO generation_jobs (model, prompt, tags, source, ref_count, request) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![model, prompt, tags_json, source.to_string(), ref_count, request_json],
         ).context("Failed to create job")?;
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// Running or completed jobs for a model started on or after `since`
+    /// (local time, e.g. "2026-03-01"), for daily quotas
+    pub fn count_jobs_started(&self, model: &str, since: &str) -> Result<u32> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) FROM generation_jobs
+             WHERE model = ?1 AND started_at >= ?2 AND status IN ('running', 'completed')",
+            params![model, since],
+            |row| row.get(0),
+        )?)
+    }
+
     pub fn update_job_started(&self, id: i64) -> Result<()> {
         let now = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
         self.conn.execute(
@@ -857,18 +2273,36 @@ impl Database {
         Ok(())
     }
 
-    pub fn update_job_failed(&self, id: i64, error: &str) -> Result<()> {
+    pub fn update_job_retry_count(&self, id: i64, retry_count: u32) -> Result<()> {
+        self.conn.execute(
+            "UPDATE generation_jobs SET retry_count = ?1 WHERE id = ?2",
+            params![retry_count, id],
+        ).context("Failed to update job retry count")?;
+        Ok(())
+    }
+
+    /// `failure` is the error's category (`PixeryError::code`)
+    pub fn update_job_failed(&self, id: i64, error: &str, failure: &str) -> Result<()> {
         let now = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
         self.conn.execute(
-            "UPDATE generation_jobs SET status = 'failed', completed_at = ?1, error = ?2 WHERE id = ?3",
-            params![now, error, id],
+            "UPDATE generation_jobs SET status = 'failed', completed_at = ?1, error = ?2, failure = ?3 WHERE id = ?4",
+            params![now, error, failure, id],
         ).context("Failed to update job to failed")?;
         Ok(())
     }
 
+    pub fn update_job_cancelled(&self, id: i64) -> Result<()> {
+        let now = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+        self.conn.execute(
+            "UPDATE generation_jobs SET status = 'cancelled', completed_at = ?1, error = 'Cancelled' WHERE id = ?2",
+            params![now, id],
+        ).context("Failed to update job to cancelled")?;
+        Ok(())
+    }
+
     pub fn list_active_jobs(&self) -> Result<Vec<Job>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, status, model, prompt, tags, source, ref_count, created_at, started_at, completed_at, generation_id, error
+            "SELECT id, status, model, prompt, tags, source, ref_count, created_at, started_at, completed_at, generation_id, error, retry_count, retry_job_id, failure
              FROM generation_jobs
              WHERE status IN ('pending', 'running')
              ORDER BY created_at DESC",
@@ -888,7 +2322,7 @@ impl Database {
         let cutoff_str = cutoff.format("%Y-%m-%dT%H:%M:%S").to_string();
 
         let mut stmt = self.conn.prepare(
-            "SELECT id, status, model, prompt, tags, source, ref_count, created_at, started_at, completed_at, generation_id, error
+            "SELECT id, status, model, prompt, tags, source, ref_count, created_at, started_at, completed_at, generation_id, error, retry_count, retry_job_id, failure
              FROM generation_jobs
              WHERE status = 'failed' AND completed_at >= ?1
              ORDER BY completed_at DESC
@@ -903,12 +2337,81 @@ impl Database {
         Ok(jobs)
     }
 
+    pub fn get_job(&self, id: i64) -> Result<Option<Job>> {
+        self.conn
+            .query_row(
+                "SELECT id, status, model, prompt, tags, source, ref_count, created_at, started_at, completed_at, generation_id, error, retry_count, retry_job_id, failure
+                 FROM generation_jobs WHERE id = ?1",
+                params![id],
+                parse_job_row,
+            )
+            .optional()
+            .context("Failed to get job")
+    }
+
+    /// The request a job was started with; None for jobs that predate it or
+    /// can't be replayed as a plain generation (fix passes)
+    pub fn get_job_request(&self, id: i64) -> Result<Option<GenerationRequest>> {
+        let json: Option<String> = self
+            .conn
+            .query_row("SELECT request FROM generation_jobs WHERE id = ?1", params![id], |row| row.get(0))
+            .optional()?
+            .flatten();
+        json.map(|j| serde_json::from_str(&j).context("Failed to parse stored job request"))
+            .transpose()
+    }
+
+    /// Job history, newest first. `since` is a YYYY-MM-DD date.
+    pub fn list_jobs(&self, status: Option<JobStatus>, since: Option<&str>, limit: i64) -> Result<Vec<Job>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, status, model, prompt, tags, source, ref_count, created_at, started_at, completed_at, generation_id, error, retry_count, retry_job_id, failure
+             FROM generation_jobs
+             WHERE (?1 IS NULL OR status = ?1) AND (?2 IS NULL OR created_at >= ?2)
+             ORDER BY created_at DESC, id DESC
+             LIMIT ?3",
+        )?;
+
+        let status = status.map(|s| s.to_string());
+        let rows = stmt.query_map(params![status, since, limit], parse_job_row)?;
+        let mut jobs = vec![];
+        for row in rows {
+            jobs.push(row?);
+        }
+        Ok(jobs)
+    }
+
+    /// Link a job to the job that re-ran it
+    pub fn set_job_retried(&self, id: i64, retry_job_id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE generation_jobs SET retry_job_id = ?1 WHERE id = ?2",
+            params![retry_job_id, id],
+        ).context("Failed to link retried job")?;
+        Ok(())
+    }
+
+    /// Failed jobs finished since `cutoff` that haven't been re-run yet, oldest first
+    pub fn list_unretried_failed_jobs(&self, cutoff: &str) -> Result<Vec<Job>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, status, model, prompt, tags, source, ref_count, created_at, started_at, completed_at, generation_id, error, retry_count, retry_job_id, failure
+             FROM generation_jobs
+             WHERE status = 'failed' AND retry_job_id IS NULL AND completed_at >= ?1
+             ORDER BY completed_at ASC",
+        )?;
+
+        let rows = stmt.query_map(params![cutoff], parse_job_row)?;
+        let mut jobs = vec![];
+        for row in rows {
+            jobs.push(row?);
+        }
+        Ok(jobs)
+    }
+
     pub fn cleanup_old_jobs(&self, hours: i64) -> Result<usize> {
         let cutoff = chrono::Local::now() - chrono::Duration::hours(hours);
         let cutoff_str = cutoff.format("%Y-%m-%dT%H:%M:%S").to_string();
 
         let count = self.conn.execute(
-            "DELETE FROM generation_jobs WHERE status IN ('completed', 'failed') AND completed_at < ?1",
+            "DELETE FROM generation_jobs WHERE status IN ('completed', 'failed', 'cancelled') AND completed_at < ?1",
             params![cutoff_str],
         ).context("Failed to cleanup old jobs")?;
 
@@ -917,10 +2420,45 @@ impl Database {
 
     // Collection operations
 
+    /// Create a collection. A path like "rpg/chapter1" nests it under "rpg",
+    /// creating any missing parents along the way.
     pub fn create_collection(&self, name: &str, description: Option<&str>) -> Result<i64> {
+        if name.split('/').any(|segment| segment.trim().is_empty()) {
+            anyhow::bail!("Invalid collection path '{}'", name);
+        }
+        let smart: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM smart_collections WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )?;
+        if smart > 0 {
+            anyhow::bail!("A smart collection named '{}' already exists", name);
+        }
+        // A new collection replaces a deleted one of the same name for good
         self.conn.execute(
-            "INSERT INTO collections (name, description) VALUES (?1, ?2)",
-            params![name, description],
+            "DELETE FROM collections WHERE name = ?1 AND deleted_at IS NOT NULL",
+            params![name],
+        )?;
+        let parent_id = match name.rsplit_once('/') {
+            Some((parent, _)) => {
+                let existing = self
+                    .conn
+                    .query_row(
+                        "SELECT id FROM collections WHERE name = ?1 AND deleted_at IS NULL",
+                        params![parent],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                match existing {
+                    Some(id) => Some(id),
+                    None => Some(self.create_collection(parent, None)?),
+                }
+            }
+            None => None,
+        };
+        self.conn.execute(
+            "INSERT INTO collections (name, description, parent_id) VALUES (?1, ?2, ?3)",
+            params![name, description, parent_id],
         ).context("Failed to create collection")?;
         Ok(self.conn.last_insert_rowid())
     }
@@ -928,10 +2466,23 @@ impl Database {
     pub fn list_collections(&self) -> Result<Vec<Collection>> {
         let mut stmt = self.conn.prepare(
             "SELECT c.id, c.name, c.description, c.created_at,
-                    COUNT(gc.generation_id) as count
+                    COUNT(gc.generation_id) as count, c.cover_id,
+                    (SELECT COALESCE(cg.thumb_path, cg.image_path) FROM generations cg
+                     WHERE cg.id = COALESCE(c.cover_id, (
+                         SELECT first.generation_id FROM generation_collections first
+                         JOIN generations fg ON fg.id = first.generation_id AND fg.trashed_at IS NULL
+                         WHERE first.collection_id = c.id
+                         ORDER BY first.position LIMIT 1
+                     ))) as cover_path,
+                    c.parent_id,
+                    (SELECT COUNT(DISTINCT sgc.generation_id) FROM generation_collections sgc
+                     JOIN collections sc ON sc.id = sgc.collection_id AND sc.deleted_at IS NULL
+                     JOIN generations sg ON sg.id = sgc.generation_id AND sg.trashed_at IS NULL
+                     WHERE sc.id = c.id OR substr(sc.name, 1, length(c.name) + 1) = c.name || '/') as total_count
              FROM collections c
              LEFT JOIN generation_collections gc ON c.id = gc.collection_id
              LEFT JOIN generations g ON gc.generation_id = g.id AND g.trashed_at IS NULL
+             WHERE c.deleted_at IS NULL
              GROUP BY c.id
              ORDER BY c.name ASC",
         )?;
@@ -942,35 +2493,229 @@ impl Database {
                 description: row.get(2)?,
                 created_at: row.get(3)?,
                 count: row.get(4)?,
+                cover_id: row.get(5)?,
+                cover_path: row.get::<_, Option<String>>(6)?.map(resolve),
+                parent_id: row.get(7)?,
+                total_count: row.get(8)?,
             })
         })?;
+        let mut collections = rows.collect::<Result<Vec<_>, _>>()?;
+        // Tree order: each collection directly followed by its subcollections
+        collections.sort_by(|a, b| a.name.split('/').cmp(b.name.split('/')));
+        Ok(collections)
+    }
+
+    fn collection_id(&self, name: &str) -> Result<i64> {
+        self.conn
+            .query_row(
+                "SELECT id FROM collections WHERE name = ?1 AND deleted_at IS NULL",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| PixeryError::NotFound(format!("Collection '{}' not found", name)).into())
+    }
+
+    /// Member IDs of a collection in its user-defined order
+    pub fn collection_order(&self, name: &str) -> Result<Vec<i64>> {
+        let collection_id = self.collection_id(name)?;
+        let mut stmt = self.conn.prepare(
+            "SELECT generation_id FROM generation_collections WHERE collection_id = ?1 ORDER BY position, rowid",
+        )?;
+        let rows = stmt.query_map(params![collection_id], |row| row.get(0))?;
         rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
     }
 
-    pub fn add_to_collection(&self, generation_id: i64, collection_name: &str) -> Result<()> {
-        let collection_id: i64 = self.conn.query_row(
-            "SELECT id FROM collections WHERE name = ?1",
-            params![collection_name],
-            |row| row.get(0),
-        ).context("Collection not found")?;
+    /// Move `ids` to the front of a collection, in the order given; the other
+    /// members follow in their current order
+    pub fn reorder_collection(&self, name: &str, ids: &[i64]) -> Result<()> {
+        let collection_id = self.collection_id(name)?;
+        let current = self.collection_order(name)?;
+        if let Some(missing) = ids.iter().find(|id| !current.contains(id)) {
+            anyhow::bail!("Generation {} is not in collection '{}'", missing, name);
+        }
+        let rest = current.iter().filter(|id| !ids.contains(id));
+        let tx = self.conn.unchecked_transaction()?;
+        for (position, id) in ids.iter().chain(rest).enumerate() {
+            tx.execute(
+                "UPDATE generation_collections SET position = ?1 WHERE collection_id = ?2 AND generation_id = ?3",
+                params![position as i64 + 1, collection_id, id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Set (or with None, clear) the generation shown for a collection;
+    /// without one the first member in order is used
+    pub fn set_collection_cover(&self, name: &str, generation_id: Option<i64>) -> Result<()> {
+        let collection_id = self.collection_id(name)?;
+        if let Some(id) = generation_id {
+            if !self.collection_order(name)?.contains(&id) {
+                anyhow::bail!("Generation {} is not in collection '{}'", id, name);
+            }
+        }
         self.conn.execute(
-            "INSERT OR IGNORE INTO generation_collections (generation_id, collection_id) VALUES (?1, ?2)",
+            "UPDATE collections SET cover_id = ?1 WHERE id = ?2",
             params![generation_id, collection_id],
         )?;
         Ok(())
     }
 
+    pub fn add_to_collection(&self, generation_id: i64, collection_name: &str) -> Result<()> {
+        let collection_id = self.collection_id(collection_name)?;
+        // New members go to the end of the collection's order
+        let rows = self.conn.execute(
+            "INSERT OR IGNORE INTO generation_collections (generation_id, collection_id, position)
+             VALUES (?1, ?2, (SELECT COALESCE(MAX(position), 0) + 1 FROM generation_collections WHERE collection_id = ?2))",
+            params![generation_id, collection_id],
+        )?;
+        if rows > 0 {
+            self.log_event(generation_id, EventAction::CollectionAdd, serde_json::json!({ "collection": collection_name }))?;
+        }
+        Ok(())
+    }
+
     pub fn remove_from_collection(&self, generation_id: i64, collection_name: &str) -> Result<()> {
-        self.conn.execute(
-            "DELETE FROM generation_collections WHERE generation_id = ?1 AND collection_id = (SELECT id FROM collections WHERE name = ?2)",
+        let rows = self.conn.execute(
+            "DELETE FROM generation_collections WHERE generation_id = ?1
+             AND collection_id = (SELECT id FROM collections WHERE name = ?2 AND deleted_at IS NULL)",
             params![generation_id, collection_name],
         )?;
+        let was_cover = self.conn.execute(
+            "UPDATE collections SET cover_id = NULL WHERE cover_id = ?1 AND name = ?2 AND deleted_at IS NULL",
+            params![generation_id, collection_name],
+        )? > 0;
+        if rows > 0 {
+            self.log_event(
+                generation_id,
+                EventAction::CollectionRemove,
+                serde_json::json!({ "collection": collection_name, "cover": was_cover }),
+            )?;
+        }
         Ok(())
     }
 
+    /// Delete a collection and its subcollections (generations are kept).
+    /// They're only marked deleted: memberships stay until maintenance purges
+    /// them, and `restore_collection` brings them back.
     pub fn delete_collection(&self, name: &str) -> Result<bool> {
+        let now = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
         let rows = self.conn.execute(
-            "DELETE FROM collections WHERE name = ?1",
+            "UPDATE collections SET deleted_at = ?2
+             WHERE deleted_at IS NULL AND (name = ?1 OR substr(name, 1, length(?1) + 1) = ?1 || '/')",
+            params![name, now],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Memberships a delete of the collection would take away, subcollections included
+    pub fn collection_membership_count(&self, name: &str) -> Result<i64> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) FROM generation_collections gc
+             JOIN collections c ON c.id = gc.collection_id
+             WHERE c.deleted_at IS NULL AND (c.name = ?1 OR substr(c.name, 1, length(?1) + 1) = ?1 || '/')",
+            params![name],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Deleted collections (name, deleted_at, memberships), most recent first
+    pub fn list_deleted_collections(&self) -> Result<Vec<(String, String, i64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.name, c.deleted_at, COUNT(gc.generation_id) FROM collections c
+             LEFT JOIN generation_collections gc ON gc.collection_id = c.id
+             WHERE c.deleted_at IS NOT NULL
+             GROUP BY c.id ORDER BY c.deleted_at DESC, c.name",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    /// Undo `delete_collection`: the collection comes back with the
+    /// subcollections deleted along with it. Returns how many were restored.
+    pub fn restore_collection(&self, name: &str) -> Result<usize> {
+        let deleted_at: String = self
+            .conn
+            .query_row(
+                "SELECT deleted_at FROM collections WHERE name = ?1 AND deleted_at IS NOT NULL",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("No deleted collection named '{}'", name))?;
+        if let Some((parent, _)) = name.rsplit_once('/') {
+            if self.collection_id(parent).is_err() {
+                anyhow::bail!("Its parent '{}' is deleted too; restore that first", parent);
+            }
+        }
+        let rows = self.conn.execute(
+            "UPDATE collections SET deleted_at = NULL
+             WHERE deleted_at = ?2 AND (name = ?1 OR substr(name, 1, length(?1) + 1) = ?1 || '/')",
+            params![name, deleted_at],
+        )?;
+        Ok(rows)
+    }
+
+    /// Permanently remove collections deleted before the cutoff timestamp
+    pub fn purge_deleted_collections(&self, cutoff: &str) -> Result<usize> {
+        let rows = self.conn.execute(
+            "DELETE FROM collections WHERE deleted_at IS NOT NULL AND deleted_at < ?1",
+            params![cutoff],
+        )?;
+        Ok(rows)
+    }
+
+    // Smart collection operations
+
+    /// Save a filter expression as a smart collection; the expression is
+    /// validated here but evaluated afresh on every listing
+    pub fn create_smart_collection(&self, name: &str, description: Option<&str>, filter: &str) -> Result<i64> {
+        ListFilter::parse_expr(filter).map_err(|e| anyhow::anyhow!(e))?;
+        if self.list_collections()?.iter().any(|c| c.name == name) {
+            anyhow::bail!("A collection named '{}' already exists", name);
+        }
+        self.conn.execute(
+            "INSERT INTO smart_collections (name, description, filter) VALUES (?1, ?2, ?3)",
+            params![name, description, filter],
+        ).context("Failed to create smart collection")?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn list_smart_collections(&self) -> Result<Vec<SmartCollection>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, description, filter, created_at FROM smart_collections ORDER BY name ASC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(SmartCollection {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                description: row.get(2)?,
+                filter: row.get(3)?,
+                created_at: row.get(4)?,
+                count: 0,
+            })
+        })?;
+        let mut collections = rows.collect::<Result<Vec<_>, _>>()?;
+        for collection in &mut collections {
+            // An expression that no longer parses (e.g. a removed term) counts as empty
+            collection.count = self
+                .count_generations(&ListFilter {
+                    smart_collection_id: Some(collection.id),
+                    ..Default::default()
+                })
+                .unwrap_or(0);
+        }
+        Ok(collections)
+    }
+
+    pub fn get_smart_collection(&self, name: &str) -> Result<Option<SmartCollection>> {
+        Ok(self.list_smart_collections()?.into_iter().find(|c| c.name == name))
+    }
+
+    pub fn delete_smart_collection(&self, name: &str) -> Result<bool> {
+        let rows = self.conn.execute(
+            "DELETE FROM smart_collections WHERE name = ?1",
             params![name],
         )?;
         Ok(rows > 0)
@@ -1007,4 +2752,554 @@ impl Database {
 
         Ok(count)
     }
+
+    // Presets
+
+    /// Create a preset, replacing any existing preset with the same name
+    pub fn save_preset(&self, preset: &NewPreset) -> Result<i64> {
+        let tags_str = if preset.tags.is_empty() { None } else { Some(preset.tags.join(",")) };
+        self.conn.execute(
+            "INSERT INTO presets (name, model, negative_prompt, width, height, cfg_scale, tags)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(name) DO UPDATE SET
+                model = excluded.model, negative_prompt = excluded.negative_prompt,
+                width = excluded.width, height = excluded.height,
+                cfg_scale = excluded.cfg_scale, tags = excluded.tags",
+            params![
+                preset.name,
+                preset.model,
+                preset.negative_prompt,
+                preset.width,
+                preset.height,
+                preset.cfg_scale,
+                tags_str
+            ],
+        )?;
+        let id = self.conn.query_row(
+            "SELECT id FROM presets WHERE name = ?1",
+            params![preset.name],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    pub fn list_presets(&self) -> Result<Vec<Preset>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, model, negative_prompt, width, height, cfg_scale, tags, created_at
+             FROM presets ORDER BY name",
+        )?;
+        let rows = stmt.query_map([], parse_preset_row)?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    pub fn get_preset(&self, name: &str) -> Result<Option<Preset>> {
+        let preset = self
+            .conn
+            .query_row(
+                "SELECT id, name, model, negative_prompt, width, height, cfg_scale, tags, created_at
+                 FROM presets WHERE name = ?1",
+                params![name],
+                parse_preset_row,
+            )
+            .optional()?;
+        Ok(preset)
+    }
+
+    pub fn delete_preset(&self, name: &str) -> Result<bool> {
+        let rows = self.conn.execute("DELETE FROM presets WHERE name = ?1", params![name])?;
+        Ok(rows > 0)
+    }
+
+    // Schedules
+
+    pub fn create_schedule(&self, schedule: &NewSchedule) -> Result<i64> {
+        let tags_str = if schedule.tags.is_empty() { None } else { Some(schedule.tags.join(",")) };
+        self.conn.execute(
+            "INSERT INTO schedules (name, cron, prompt, model, tags, negative_prompt, width, height)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                schedule.name,
+                schedule.cron,
+                schedule.prompt,
+                schedule.model,
+                tags_str,
+                schedule.negative_prompt,
+                schedule.width,
+                schedule.height
+            ],
+        ).with_context(|| format!("Failed to create schedule '{}' (names must be unique)", schedule.name))?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn list_schedules(&self) -> Result<Vec<Schedule>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, cron, prompt, model, tags, negative_prompt, width, height,
+                    enabled, last_run_at, created_at
+             FROM schedules ORDER BY name",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let tags: Option<String> = row.get(5)?;
+            Ok(Schedule {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                cron: row.get(2)?,
+                prompt: row.get(3)?,
+                model: row.get(4)?,
+                tags: tags
+                    .map(|t| t.split(',').map(|s| s.to_string()).collect())
+                    .unwrap_or_default(),
+                negative_prompt: row.get(6)?,
+                width: row.get(7)?,
+                height: row.get(8)?,
+                enabled: row.get::<_, i32>(9)? != 0,
+                last_run_at: row.get(10)?,
+                created_at: row.get(11)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    pub fn delete_schedule(&self, name: &str) -> Result<bool> {
+        let rows = self.conn.execute("DELETE FROM schedules WHERE name = ?1", params![name])?;
+        Ok(rows > 0)
+    }
+
+    pub fn set_schedule_enabled(&self, name: &str, enabled: bool) -> Result<bool> {
+        let rows = self.conn.execute(
+            "UPDATE schedules SET enabled = ?1 WHERE name = ?2",
+            params![enabled as i32, name],
+        )?;
+        Ok(rows > 0)
+    }
+
+    /// Record a run, only if nobody else has since `previous` was read.
+    /// Returns false when another runner (CLI daemon vs GUI) already claimed it.
+    pub fn claim_schedule_run(&self, id: i64, previous: Option<&str>, run_at: &str) -> Result<bool> {
+        let rows = self.conn.execute(
+            "UPDATE schedules SET last_run_at = ?1 WHERE id = ?2 AND last_run_at IS ?3",
+            params![run_at, id, previous],
+        )?;
+        Ok(rows > 0)
+    }
+
+    // Experiments
+
+    /// Record a run, named `<kind>-YYYYMMDD-HHMMSS` (with a -2, -3, ... suffix
+    /// when another run started the same second). Returns its ID and name.
+    pub fn create_experiment(&self, kind: &str, prompt: &str, params: &serde_json::Value) -> Result<(i64, String)> {
+        let base = format!("{}-{}", kind, chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        let params_json = params.to_string();
+        for n in 1.. {
+            let name = if n == 1 { base.clone() } else { format!("{}-{}", base, n) };
+            let inserted = self.conn.execute(
+                "INSERT OR IGNORE INTO experiments (name, kind, prompt, params) VALUES (?1, ?2, ?3, ?4)",
+                params![name, kind, prompt, params_json],
+            )?;
+            if inserted > 0 {
+                return Ok((self.conn.last_insert_rowid(), name));
+            }
+        }
+        unreachable!()
+    }
+
+    pub fn add_to_experiment(&self, experiment_id: i64, generation_ids: &[i64]) -> Result<()> {
+        for id in generation_ids {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO generation_experiments (generation_id, experiment_id) VALUES (?1, ?2)",
+                params![id, experiment_id],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// All experiments, newest first, with the count and cost of their
+    /// (non-trashed) generations
+    pub fn list_experiments(&self) -> Result<Vec<Experiment>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.id, e.name, e.kind, e.prompt, e.params, e.created_at,
+                    COUNT(g.id), COALESCE(SUM(g.cost_estimate_usd), 0)
+             FROM experiments e
+             LEFT JOIN generation_experiments ge ON ge.experiment_id = e.id
+             LEFT JOIN generations g ON g.id = ge.generation_id AND g.trashed_at IS NULL
+             GROUP BY e.id
+             ORDER BY e.id DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let params: String = row.get(4)?;
+            Ok(Experiment {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                kind: row.get(2)?,
+                prompt: row.get(3)?,
+                params: serde_json::from_str(&params).unwrap_or_default(),
+                created_at: row.get(5)?,
+                count: row.get(6)?,
+                total_cost: row.get(7)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    pub fn get_experiment(&self, name: &str) -> Result<Option<Experiment>> {
+        Ok(self.list_experiments()?.into_iter().find(|e| e.name == name))
+    }
+
+    /// Append to the activity log
+    fn log_event(&self, generation_id: i64, action: EventAction, detail: serde_json::Value) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO events (generation_id, action, detail) VALUES (?1, ?2, ?3)",
+            params![generation_id, action.to_string(), detail.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Activity log, newest first, optionally for one generation
+    pub fn list_events(&self, limit: i64, generation_id: Option<i64>) -> Result<Vec<Event>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.id, e.generation_id, e.action, e.detail, e.created_at, e.undo_of,
+                    EXISTS (SELECT 1 FROM events u WHERE u.undo_of = e.id)
+             FROM events e
+             WHERE ?1 IS NULL OR e.generation_id = ?1
+             ORDER BY e.id DESC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![generation_id, limit], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get(4)?,
+                row.get(5)?,
+                row.get(6)?,
+            ))
+        })?;
+        let mut events = vec![];
+        for row in rows {
+            let (id, generation_id, action, detail, created_at, undo_of, undone) = row?;
+            events.push(Event {
+                id,
+                generation_id,
+                action: action.parse().map_err(|e: String| anyhow::anyhow!(e))?,
+                detail: serde_json::from_str(&detail).unwrap_or_default(),
+                created_at,
+                undo_of,
+                undone,
+            });
+        }
+        Ok(events)
+    }
+
+    /// Undo the most recent destructive change (`EventAction::is_destructive`)
+    /// that isn't itself an undo and hasn't been undone. None if there is none.
+    pub fn undo_last(&self) -> Result<Option<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.id, e.action FROM events e
+             WHERE e.undo_of IS NULL AND NOT EXISTS (SELECT 1 FROM events u WHERE u.undo_of = e.id)
+             ORDER BY e.id DESC",
+        )?;
+        let mut rows = stmt.query([])?;
+        let mut last = None;
+        while let Some(row) = rows.next()? {
+            let action: Option<EventAction> = row.get::<_, String>(1)?.parse().ok();
+            if action.is_some_and(EventAction::is_destructive) {
+                last = Some(row.get(0)?);
+                break;
+            }
+        }
+        drop(rows);
+        last.map(|id| self.undo_event(id)).transpose()
+    }
+
+    /// Reverse an event with the same calls a user would make, so the undo is
+    /// itself logged. Returns what was done, e.g. "Restored 12".
+    pub fn undo_event(&self, id: i64) -> Result<String> {
+        let event: Option<(i64, String, String, bool)> = self
+            .conn
+            .query_row(
+                "SELECT generation_id, action, detail, EXISTS (SELECT 1 FROM events WHERE undo_of = ?1)
+                 FROM events WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()?;
+        let (gen_id, action, detail, undone) = event.ok_or_else(|| anyhow::anyhow!("Event {} not found", id))?;
+        if undone {
+            anyhow::bail!("Event {} was already undone", id);
+        }
+
+        // The events the inverse calls log are marked as this undo's
+        let tx = self.conn.unchecked_transaction()?;
+        let last: i64 = tx.query_row("SELECT COALESCE(MAX(id), 0) FROM events", [], |row| row.get(0))?;
+        let done = self.apply_undo(gen_id, &action, &detail)?;
+        tx.execute("UPDATE events SET undo_of = ?1 WHERE id > ?2", params![id, last])?;
+        tx.commit()?;
+        Ok(done)
+    }
+
+    fn apply_undo(&self, gen_id: i64, action: &str, detail: &str) -> Result<String> {
+        let action: EventAction = action.parse().map_err(|e: String| anyhow::anyhow!(e))?;
+        let detail: serde_json::Value = serde_json::from_str(detail).unwrap_or_default();
+        let deleted = || anyhow::anyhow!("Generation {} was deleted permanently; that can't be undone", gen_id);
+        let gen = self.get_generation(gen_id)?.ok_or_else(deleted)?;
+        let tags: Vec<String> = serde_json::from_value(detail["tags"].clone()).unwrap_or_default();
+        let before = detail["before"].as_str();
+        let collection = detail["collection"].as_str().unwrap_or_default();
+
+        let done = match action {
+            EventAction::Generate | EventAction::Restore => {
+                self.trash_generation(gen_id)?;
+                "Trashed"
+            }
+            EventAction::Trash => {
+                self.restore_generation(gen_id)?;
+                "Restored"
+            }
+            EventAction::Star | EventAction::Unstar => {
+                if gen.starred == (action == EventAction::Star) {
+                    self.toggle_starred(gen_id)?;
+                }
+                if action == EventAction::Star { "Unstarred" } else { "Starred" }
+            }
+            EventAction::Tag => {
+                for tag in &tags {
+                    self.remove_tag(gen_id, tag)?;
+                }
+                "Untagged"
+            }
+            EventAction::Untag => {
+                self.add_tags(gen_id, &tags)?;
+                "Tagged"
+            }
+            EventAction::EditPrompt => {
+                self.update_prompt(gen_id, before.unwrap_or_default())?;
+                "Restored the prompt of"
+            }
+            EventAction::EditTitle => {
+                self.update_title(gen_id, before)?;
+                "Restored the title of"
+            }
+            EventAction::CollectionAdd => {
+                self.remove_from_collection(gen_id, collection)?;
+                return Ok(format!("Removed {} from {}", gen_id, collection));
+            }
+            EventAction::CollectionRemove => {
+                self.add_to_collection(gen_id, collection)?;
+                if detail["cover"].as_bool().unwrap_or(false) {
+                    self.set_collection_cover(collection, Some(gen_id))?;
+                }
+                return Ok(format!("Put {} back in {}", gen_id, collection));
+            }
+            EventAction::Delete => return Err(deleted()),
+        };
+        Ok(format!("{} {}", done, gen_id))
+    }
+
+    /// Record the calls a batch run will make, in order
+    pub fn add_batch_calls(&self, experiment_id: i64, calls: &[(Option<usize>, GenerationRequest, Vec<String>)]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for (line, request, tags) in calls {
+            tx.execute(
+                "INSERT INTO batch_calls (experiment_id, line, request, tags) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    experiment_id,
+                    line.map(|l| l as i64),
+                    serde_json::to_string(request)?,
+                    serde_json::to_string(tags)?
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// A batch run's calls that haven't produced a generation yet, in order
+    pub fn pending_batch_calls(&self, experiment_id: i64) -> Result<Vec<BatchCall>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, line, request, tags FROM batch_calls
+             WHERE experiment_id = ?1 AND generation_id IS NULL
+             ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![experiment_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+        })?;
+        let mut calls = vec![];
+        for row in rows {
+            let (id, line, request, tags) = row?;
+            calls.push(BatchCall {
+                id,
+                line: line.map(|l| l as usize),
+                request: serde_json::from_str(&request).context("Invalid stored batch request")?,
+                tags: serde_json::from_str(&tags).unwrap_or_default(),
+            });
+        }
+        Ok(calls)
+    }
+
+    /// Number of calls a batch run recorded; 0 for runs from before they were
+    pub fn batch_call_count(&self, experiment_id: i64) -> Result<i64> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) FROM batch_calls WHERE experiment_id = ?1",
+            params![experiment_id],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Mark a batch call done, having produced `generation_id` (its first
+    /// generation when it asked for several)
+    pub fn complete_batch_call(&self, id: i64, generation_id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE batch_calls SET generation_id = ?1 WHERE id = ?2",
+            params![generation_id, id],
+        )?;
+        Ok(())
+    }
+
+    // Sync operations
+
+    /// Write a consistent copy of the whole database to `dest` (which must not exist)
+    pub fn snapshot(&self, dest: &Path) -> Result<()> {
+        self.conn
+            .execute("VACUUM INTO ?1", params![dest.to_string_lossy()])
+            .with_context(|| format!("Failed to snapshot database to {}", dest.display()))?;
+        Ok(())
+    }
+
+    /// Overwrite the database at `path` with the SQLite file `source`, then
+    /// bring it up to this version's schema. Goes through SQLite's backup API,
+    /// so connections already open on `path` see the restored contents.
+    pub fn restore(path: &Path, source: &Path) -> Result<Database> {
+        let mut db = Self::connect(path)?;
+        db.conn
+            .restore(rusqlite::DatabaseName::Main, source, None::<fn(rusqlite::backup::Progress)>)
+            .with_context(|| format!("Failed to restore database from {}", source.display()))?;
+        db.migrate()?;
+        Ok(db)
+    }
+
+    /// Rewrite image, thumbnail and reference paths under `old_root` to sit
+    /// under `new_root` (a database restored from another machine).
+    /// Returns how many paths changed.
+    pub fn relocate_archive(&self, old_root: &str, new_root: &str) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut changed = 0;
+        for (table, column) in PATH_COLUMNS {
+            changed += tx.execute(
+                &format!(
+                    "UPDATE {0} SET {1} = ?2 || substr({1}, length(?1) + 1)
+                     WHERE substr({1}, 1, length(?1) + 1) = ?1 || '/'",
+                    table, column
+                ),
+                params![old_root, new_root],
+            )?;
+        }
+        tx.commit()?;
+        Ok(changed)
+    }
+
+    /// Rewrite absolute image, thumbnail and reference paths under `root` to
+    /// be relative to it (rows written before paths were stored relative).
+    /// Returns how many paths changed and how many absolute ones are left,
+    /// outside `root`. With `dry_run` the changes are counted, not kept.
+    pub fn relativize_paths(&self, root: &str, dry_run: bool) -> Result<(usize, usize)> {
+        let root = root.trim_end_matches('/');
+        let tx = self.conn.unchecked_transaction()?;
+        let mut changed = 0;
+        let mut outside = 0;
+        for (table, column) in PATH_COLUMNS {
+            changed += tx.execute(
+                &format!(
+                    "UPDATE {0} SET {1} = substr({1}, length(?1) + 2)
+                     WHERE substr({1}, 1, length(?1) + 1) = ?1 || '/'",
+                    table, column
+                ),
+                params![root],
+            )?;
+            let paths: Vec<String> = tx
+                .prepare(&format!("SELECT {0} FROM {1} WHERE {0} IS NOT NULL", column, table))?
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            outside += paths.iter().filter(|p| Path::new(p).is_absolute()).count();
+        }
+        if !dry_run {
+            tx.commit()?;
+        }
+        Ok((changed, outside))
+    }
+}
+
+/// Small connection pool for the GUI. Each command checks out its own
+/// connection, so a slow write no longer blocks list/search queries.
+/// Cloning is cheap and shares the pool.
+#[derive(Clone)]
+pub struct DbPool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    path: PathBuf,
+    idle: Mutex<Vec<Database>>,
+}
+
+impl DbPool {
+    /// Open the database (running migrations once) and seed the pool
+    pub fn open(path: &Path) -> Result<Self> {
+        let db = Database::open(path)?;
+        Ok(DbPool {
+            inner: Arc::new(PoolInner {
+                path: path.to_path_buf(),
+                idle: Mutex::new(vec![db]),
+            }),
+        })
+    }
+
+    /// Check out a connection, opening a new one if none are idle
+    pub fn get(&self) -> Result<PooledDb> {
+        // The idle list is only touched for a push/pop, so a poisoned lock
+        // can't hold a half-updated state; recover rather than failing
+        let idle = self.inner.idle.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        let db = match idle {
+            Some(db) => db,
+            None => Database::connect(&self.inner.path)?,
+        };
+        Ok(PooledDb { pool: self.clone(), db: Some(db) })
+    }
+
+    /// Run `f` on a pooled connection on tokio's blocking thread pool, so
+    /// SQLite work never stalls an async executor thread
+    pub async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Database) -> Result<T> + Send + 'static,
+    {
+        let pool = self.clone();
+        tokio::task::spawn_blocking(move || f(&*pool.get()?))
+            .await
+            .context("Database task panicked")?
+    }
+}
+
+/// A checked-out connection; returned to the pool on drop
+pub struct PooledDb {
+    pool: DbPool,
+    db: Option<Database>,
+}
+
+impl Deref for PooledDb {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        self.db.as_ref().expect("connection already returned")
+    }
+}
+
+impl Drop for PooledDb {
+    fn drop(&mut self) {
+        let Some(db) = self.db.take() else {
+            return;
+        };
+        let mut idle = self.pool.inner.idle.lock().unwrap_or_else(|e| e.into_inner());
+        if idle.len() < POOL_MAX_IDLE {
+            idle.push(db);
+        }
+    }
 }
@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::profiles;
+
+/// Links to one generation: `pixery://generation/42`, with `?archive=NAME`
+/// for generations outside the default archive profile
+pub const GENERATION_PREFIX: &str = "pixery://generation/";
+
+/// Tells the gallery there's a generation to show (`take_focus_generation`)
+pub const FOCUS_EVENT: &str = "focus-generation";
+
+/// Generation a link asked for that the gallery hasn't shown yet. Kept rather
+/// than sent with the event, so a link that launched the GUI waits for the
+/// gallery to load.
+static PENDING: Mutex<Option<i64>> = Mutex::new(None);
+
+/// Link to generation `id` in the current archive profile
+pub fn generation_url(id: i64) -> String {
+    match profiles::current_name() {
+        profiles::DEFAULT_PROFILE => format!("{}{}", GENERATION_PREFIX, id),
+        name => format!("{}{}?archive={}", GENERATION_PREFIX, id, name),
+    }
+}
+
+/// The generation ID and archive profile a link points at; a link without
+/// `?archive=` is in the default profile. A plain ID is accepted too, for
+/// typing `pixery open 42`, and has no profile: it's in the current one.
+pub fn parse(link: &str) -> Result<(i64, Option<String>)> {
+    let (rest, is_link) = match link.strip_prefix(GENERATION_PREFIX) {
+        Some(rest) => (rest, true),
+        None => (link, false),
+    };
+    let (id, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let id = id
+        .trim_end_matches('/')
+        .parse()
+        .ok()
+        .filter(|id| *id > 0)
+        .with_context(|| format!("'{}' is not a pixery://generation/ID link", link))?;
+    let archive = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("archive="))
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .or_else(|| is_link.then(|| profiles::DEFAULT_PROFILE.to_string()));
+    Ok((id, archive))
+}
+
+/// Have the GUI this process opens show generation `id` once it's loaded
+pub fn open_at(id: i64) {
+    *PENDING.lock().unwrap() = Some(id);
+}
+
+/// The generation a link asked for, once; None when there's none waiting
+pub fn take_pending() -> Option<i64> {
+    PENDING.lock().unwrap().take()
+}
+
+/// Bring the open window forward and show generation `id` in it
+pub fn focus(app: &AppHandle, id: i64) {
+    open_at(id);
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+    let _ = app.emit(FOCUS_EVENT, ());
+}
+
+/// Show the generation a link points at in this window. A link into another
+/// archive profile is refused: this window's IDs belong to its own archive.
+pub fn open_link(app: &AppHandle, link: &str) -> Result<()> {
+    let (id, archive) = parse(link)?;
+    let archive = archive.as_deref().unwrap_or(profiles::current_name());
+    if archive != profiles::current_name() {
+        anyhow::bail!("{} is in archive profile '{}', this window shows '{}'", link, archive, profiles::current_name());
+    }
+    focus(app, id);
+    Ok(())
+}
+
+/// Make this executable the handler for pixery:// links. macOS reads the
+/// scheme from the app bundle's Info.plist, so there's nothing to do there.
+pub fn register() -> Result<String> {
+    let exe = std::env::current_exe().context("Failed to find the pixery executable")?;
+    platform::register(&exe)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::*;
+    use std::path::Path;
+
+    const DESKTOP_FILE: &str = "pixery-url-handler.desktop";
+
+    /// A hidden desktop entry for the x-scheme-handler/pixery MIME type, made the default
+    pub fn register(exe: &Path) -> Result<String> {
+        let dir = dirs::data_dir().context("Could not find the applications directory")?.join("applications");
+        std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let path = dir.join(DESKTOP_FILE);
+        let entry = format!(
+            "[Desktop Entry]\nType=Application\nName=pixery\nExec=\"{}\" open %u\nNoDisplay=true\nMimeType=x-scheme-handler/pixery;\n",
+            exe.display()
+        );
+        std::fs::write(&path, entry).with_context(|| format!("Failed to write {}", path.display()))?;
+
+        let status = std::process::Command::new("xdg-mime")
+            .args(["default", DESKTOP_FILE, "x-scheme-handler/pixery"])
+            .status()
+            .context("Failed to run xdg-mime (install xdg-utils)")?;
+        if !status.success() {
+            anyhow::bail!("xdg-mime failed ({})", status);
+        }
+        Ok(format!("Registered {} for pixery:// links ({})", exe.display(), path.display()))
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::*;
+    use std::path::Path;
+
+    const KEY: &str = r"HKCU\Software\Classes\pixery";
+
+    fn reg_add(args: &[&str]) -> Result<()> {
+        let status = std::process::Command::new("reg")
+            .arg("add")
+            .args(args)
+            .arg("/f")
+            .status()
+            .context("Failed to run reg")?;
+        if !status.success() {
+            anyhow::bail!("reg add failed ({})", status);
+        }
+        Ok(())
+    }
+
+    /// The per-user URL protocol keys under HKCU\Software\Classes
+    pub fn register(exe: &Path) -> Result<String> {
+        let command = format!("\"{}\" open \"%1\"", exe.display());
+        reg_add(&[KEY, "/ve", "/d", "URL:pixery"])?;
+        reg_add(&[KEY, "/v", "URL Protocol", "/d", ""])?;
+        reg_add(&[&format!(r"{}\shell\open\command", KEY), "/ve", "/d", &command])?;
+        Ok(format!("Registered {} for pixery:// links", exe.display()))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    use super::*;
+    use std::path::Path;
+
+    pub fn register(_exe: &Path) -> Result<String> {
+        Ok("pixery:// links are registered by the app bundle; open pixery.app once".to_string())
+    }
+}
@@ -0,0 +1,259 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use image::{imageops::FilterType, GrayImage, Luma, RgbImage};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::models::{GenerationRequest, GenerationResult, Inpaint};
+use crate::providers;
+
+/// Crops are regenerated at this size so small regions get a full canvas of detail
+const DETAIL_SIZE: u32 = 1024;
+
+/// Width of the blend (in source pixels) around each detected box; the mask sent
+/// to the provider covers it too, so every blended pixel was repainted
+const FEATHER: u32 = 12;
+
+/// Kind of region to detect and re-detail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    Face,
+    Hand,
+    Person,
+}
+
+impl Region {
+    pub fn name(self) -> &'static str {
+        match self {
+            Region::Face => "face",
+            Region::Hand => "hand",
+            Region::Person => "person",
+        }
+    }
+}
+
+impl std::str::FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().trim_end_matches('s') {
+            "face" => Ok(Region::Face),
+            "hand" => Ok(Region::Hand),
+            "person" => Ok(Region::Person),
+            _ => Err(format!("Unknown region: {} (expected face, hand or person)", s)),
+        }
+    }
+}
+
+/// Settings for a fix pass
+#[derive(Debug, Clone)]
+pub struct FixOptions {
+    pub region: Region,
+    /// img2img strength for each crop: low keeps the composition, high redraws it
+    pub strength: f64,
+    /// Minimum detector confidence
+    pub confidence: f64,
+    /// Only the most confident detections are fixed
+    pub max_regions: usize,
+    /// Context kept around each box (source pixels); grows with the box
+    pub padding: u32,
+}
+
+impl Default for FixOptions {
+    fn default() -> Self {
+        FixOptions {
+            region: Region::Face,
+            strength: 0.4,
+            confidence: 0.3,
+            max_regions: 4,
+            padding: 32,
+        }
+    }
+}
+
+/// A detected region in source pixels
+#[derive(Debug, Clone, Deserialize)]
+pub struct Detection {
+    pub x0: f32,
+    pub y0: f32,
+    pub x1: f32,
+    pub y1: f32,
+    pub confidence: f32,
+}
+
+#[derive(Deserialize)]
+struct DetectResponse {
+    boxes: Vec<Detection>,
+}
+
+/// Ask the self-hosted server's /detect endpoint for regions, most confident first
+pub async fn detect(data: &[u8], region: Region, confidence: f64) -> Result<Vec<Detection>> {
+    let url = providers::selfhosted::get_server_url().ok_or_else(|| {
+        anyhow::anyhow!("Region detection needs the self-hosted server (set SELFHOSTED_API_URL or the GUI Server URL)")
+    })?;
+
+    let response = providers::client()
+        .post(format!("{}/detect", url.trim_end_matches('/')))
+        .json(&serde_json::json!({
+            "image": base64::engine::general_purpose::STANDARD.encode(data),
+            "region": region.name(),
+            "confidence": confidence,
+        }))
+        .timeout(Duration::from_secs(120))
+        .send()
+        .await
+        .context("Failed to send request to self-hosted /detect")?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        anyhow::bail!("Region detection failed {}: {}", status, text);
+    }
+
+    let data: DetectResponse = response.json().await.context("Failed to parse /detect response")?;
+    Ok(data.boxes)
+}
+
+/// Square crop around a box with context, shifted to stay inside the image.
+/// Returns (x, y, side).
+fn crop_box(det: &Detection, width: u32, height: u32, padding: u32) -> (u32, u32, u32) {
+    let box_w = (det.x1 - det.x0).max(1.0) as u32;
+    let box_h = (det.y1 - det.y0).max(1.0) as u32;
+    let longest = box_w.max(box_h);
+    let context = padding.max(longest / 4).max(FEATHER);
+    let side = (longest + 2 * context).min(width).min(height);
+
+    let center_x = ((det.x0 + det.x1) / 2.0) as i64;
+    let center_y = ((det.y0 + det.y1) / 2.0) as i64;
+    let x = (center_x - side as i64 / 2).clamp(0, (width - side) as i64) as u32;
+    let y = (center_y - side as i64 / 2).clamp(0, (height - side) as i64) as u32;
+    (x, y, side)
+}
+
+/// Blend weight for a pixel: 1 inside the box, fading to 0 over FEATHER pixels outside it
+fn blend_weight(det: &Detection, x: f32, y: f32) -> f32 {
+    let dx = (det.x0 - x).max(x - det.x1).max(0.0);
+    let dy = (det.y0 - y).max(y - det.y1).max(0.0);
+    1.0 - ((dx * dx + dy * dy).sqrt() / FEATHER as f32).min(1.0)
+}
+
+/// Where crops and masks are written while a fix runs
+fn work_dir() -> PathBuf {
+    std::env::temp_dir().join("pixery-fix")
+}
+
+/// Detect regions in `source`, regenerate each one at DETAIL_SIZE with img2img
+/// inpainting (using `req`'s model and prompt), and composite them back.
+/// Returns the finished image as a single-image result; cost covers every crop,
+/// at `estimate` each where the provider reports none.
+pub async fn fix_regions(
+    source: &Path,
+    req: &GenerationRequest,
+    opts: &FixOptions,
+    estimate: Option<f64>,
+    progress: providers::Progress<'_>,
+) -> Result<GenerationResult> {
+    let data = std::fs::read(source).with_context(|| format!("Failed to read {}", source.display()))?;
+    let mut detections = detect(&data, opts.region, opts.confidence).await?;
+    if detections.is_empty() {
+        anyhow::bail!("No {} detected in {}", opts.region.name(), source.display());
+    }
+    detections.truncate(opts.max_regions);
+
+    let mut canvas = image::load_from_memory(&data)
+        .context("Failed to decode source image")?
+        .to_rgb8();
+    let (width, height) = canvas.dimensions();
+
+    let dir = work_dir();
+    std::fs::create_dir_all(&dir).context("Failed to create fix work directory")?;
+    let stem = format!("{}-{}", std::process::id(), chrono::Local::now().format("%H%M%S%f"));
+    let crop_path = dir.join(format!("{}-crop.png", stem));
+    let mask_path = dir.join(format!("{}-mask.png", stem));
+
+    let mut cost: Option<f64> = None;
+    let start = Instant::now();
+
+    for det in &detections {
+        // Crop from the working canvas so overlapping regions build on each other
+        let (x, y, side) = crop_box(det, width, height, opts.padding);
+        let crop = image::imageops::crop_imm(&canvas, x, y, side, side).to_image();
+        let scale = DETAIL_SIZE as f32 / side as f32;
+        image::imageops::resize(&crop, DETAIL_SIZE, DETAIL_SIZE, FilterType::Lanczos3)
+            .save(&crop_path)
+            .context("Failed to write crop")?;
+
+        // White over the box plus the feather band, in crop coordinates
+        let feather = FEATHER as f32;
+        let mask = GrayImage::from_fn(DETAIL_SIZE, DETAIL_SIZE, |mx, my| {
+            let sx = x as f32 + mx as f32 / scale;
+            let sy = y as f32 + my as f32 / scale;
+            let inside = sx >= det.x0 - feather && sx <= det.x1 + feather && sy >= det.y0 - feather && sy <= det.y1 + feather;
+            Luma([if inside { 255 } else { 0 }])
+        });
+        mask.save(&mask_path).context("Failed to write mask")?;
+
+        let crop_req = GenerationRequest {
+            width: Some(DETAIL_SIZE as i32),
+            height: Some(DETAIL_SIZE as i32),
+            num_images: None,
+            inpaint: Some(Inpaint {
+                image_path: crop_path.to_string_lossy().to_string(),
+                mask_path: mask_path.to_string_lossy().to_string(),
+                strength: Some(opts.strength),
+            }),
+            ..req.clone()
+        };
+        let result = providers::generate(&crop_req, progress).await;
+        let result = match result {
+            Ok(r) => r,
+            Err(e) => {
+                let _ = std::fs::remove_file(&crop_path);
+                let _ = std::fs::remove_file(&mask_path);
+                return Err(e);
+            }
+        };
+        if let Some(c) = result.cost_usd.or(estimate) {
+            *cost.get_or_insert(0.0) += c;
+        }
+
+        let patch_data = result
+            .images
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("Provider returned no images"))?;
+        let patch: RgbImage = image::load_from_memory(patch_data)
+            .context("Failed to decode regenerated region")?
+            .to_rgb8();
+        let patch = image::imageops::resize(&patch, side, side, FilterType::Lanczos3);
+
+        for py in 0..side {
+            for px in 0..side {
+                let weight = blend_weight(det, (x + px) as f32, (y + py) as f32);
+                if weight <= 0.0 {
+                    continue;
+                }
+                let fixed = patch.get_pixel(px, py);
+                let pixel = canvas.get_pixel_mut(x + px, y + py);
+                for c in 0..3 {
+                    pixel[c] = (pixel[c] as f32 * (1.0 - weight) + fixed[c] as f32 * weight).round() as u8;
+                }
+            }
+        }
+    }
+
+    let _ = std::fs::remove_file(&crop_path);
+    let _ = std::fs::remove_file(&mask_path);
+
+    let mut png = Vec::new();
+    canvas
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .context("Failed to encode fixed image")?;
+
+    Ok(GenerationResult {
+        images: vec![png],
+        seed: None,
+        generation_time_seconds: start.elapsed().as_secs_f64(),
+        cost_usd: cost,
+    })
+}
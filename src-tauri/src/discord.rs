@@ -0,0 +1,440 @@
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+use crate::cancel;
+use crate::db::Database;
+use crate::models::{Generation, GenerationRequest, JobSource};
+use crate::providers;
+use crate::workflow;
+
+const API_BASE: &str = "https://discord.com/api/v10";
+const GATEWAY_URL: &str = "wss://gateway.discord.gg/?v=10&encoding=json";
+
+/// Tag added to every generation the bot makes
+pub const DISCORD_TAG: &str = "discord";
+
+/// Discord's attachment limit for bots without a boosted server
+const MAX_UPLOAD_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Gateway close codes that reconnecting won't fix (bad token, bad intents)
+const FATAL_CLOSE_CODES: &[u16] = &[4004, 4010, 4011, 4012, 4013, 4014];
+
+/// Wait before reconnecting after the gateway drops
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Interaction type for a slash command
+const APPLICATION_COMMAND: u8 = 2;
+
+/// Interaction response: "thinking..." now, message edited in later
+const DEFERRED_CHANNEL_MESSAGE: u8 = 5;
+
+/// How `pixery discord-bot` generates
+#[derive(Debug, Clone)]
+pub struct BotOptions {
+    pub token: String,
+    /// Model used when the command doesn't pick one
+    pub model: String,
+    /// Models offered as choices on /imagine; empty hides the option
+    pub models: Vec<String>,
+    /// Register /imagine in this server only (shows up instantly)
+    pub guild: Option<String>,
+    /// Added to every generation alongside the `discord` tag
+    pub tags: Vec<String>,
+}
+
+/// Bot token from DISCORD_BOT_TOKEN
+pub fn get_token() -> Result<String> {
+    std::env::var("DISCORD_BOT_TOKEN")
+        .ok()
+        .filter(|t| !t.is_empty())
+        .context("DISCORD_BOT_TOKEN environment variable not set")
+}
+
+#[derive(Debug, Deserialize)]
+struct Application {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Interaction {
+    id: String,
+    token: String,
+    #[serde(rename = "type")]
+    kind: u8,
+    data: Option<CommandData>,
+    /// Set in servers
+    member: Option<Member>,
+    /// Set in DMs
+    user: Option<User>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandData {
+    name: String,
+    #[serde(default)]
+    options: Vec<CommandOption>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandOption {
+    name: String,
+    value: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct Member {
+    user: User,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    username: String,
+}
+
+impl Interaction {
+    fn option(&self, name: &str) -> Option<&str> {
+        self.data
+            .as_ref()?
+            .options
+            .iter()
+            .find(|o| o.name == name)
+            .and_then(|o| o.value.as_str())
+    }
+
+    fn username(&self) -> &str {
+        self.member
+            .as_ref()
+            .map(|m| &m.user)
+            .or(self.user.as_ref())
+            .map_or("unknown", |u| u.username.as_str())
+    }
+}
+
+fn api(method: reqwest::Method, path: &str, token: &str) -> reqwest::RequestBuilder {
+    providers::client()
+        .request(method, format!("{}{}", API_BASE, path))
+        .header("Authorization", format!("Bot {}", token))
+}
+
+async fn check(response: reqwest::Response, what: &str) -> Result<reqwest::Response> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("Discord {} failed ({}): {}", what, status, body);
+    }
+    Ok(response)
+}
+
+/// /imagine definition; the model option only exists when there are models to pick from
+fn imagine_command(options: &BotOptions) -> Value {
+    let mut command_options = vec![json!({
+        "type": 3,
+        "name": "prompt",
+        "description": "What to generate",
+        "required": true,
+    })];
+    if !options.models.is_empty() {
+        let choices: Vec<Value> = options
+            .models
+            .iter()
+            .take(25)
+            .map(|m| json!({ "name": m, "value": m }))
+            .collect();
+        command_options.push(json!({
+            "type": 3,
+            "name": "model",
+            "description": format!("Model (default {})", options.model),
+            "choices": choices,
+        }));
+    }
+    json!([{
+        "name": "imagine",
+        "description": "Generate an image with pixery",
+        "options": command_options,
+    }])
+}
+
+async fn register_commands(app_id: &str, options: &BotOptions) -> Result<()> {
+    let path = match &options.guild {
+        Some(guild) => format!("/applications/{}/guilds/{}/commands", app_id, guild),
+        None => format!("/applications/{}/commands", app_id),
+    };
+    let response = api(reqwest::Method::PUT, &path, &options.token)
+        .json(&imagine_command(options))
+        .send()
+        .await
+        .context("Failed to register slash commands")?;
+    check(response, "command registration").await?;
+    Ok(())
+}
+
+/// Show "thinking..." so the interaction doesn't expire (3s) while we generate
+async fn defer(interaction_id: &str, interaction_token: &str) -> Result<()> {
+    let response = providers::client()
+        .post(format!("{}/interactions/{}/{}/callback", API_BASE, interaction_id, interaction_token))
+        .json(&json!({ "type": DEFERRED_CHANNEL_MESSAGE }))
+        .send()
+        .await?;
+    check(response, "interaction response").await?;
+    Ok(())
+}
+
+/// Replace the deferred "thinking..." message with the result
+async fn edit_reply(app_id: &str, interaction_token: &str, content: &str, images: &[&Generation]) -> Result<()> {
+    let mut attachments = vec![];
+    let mut form = reqwest::multipart::Form::new();
+    for (i, generation) in images.iter().enumerate() {
+        let path = Path::new(&generation.image_path);
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| format!("{}.png", generation.id));
+        let data = tokio::fs::read(path).await.context("Failed to read generated image")?;
+        form = form.part(
+            format!("files[{}]", i),
+            reqwest::multipart::Part::bytes(data).file_name(file_name.clone()),
+        );
+        attachments.push(json!({ "id": i, "filename": file_name }));
+    }
+    let payload = json!({ "content": content, "attachments": attachments });
+    let form = form.text("payload_json", payload.to_string());
+
+    let response = providers::client()
+        .patch(format!("{}/webhooks/{}/{}/messages/@original", API_BASE, app_id, interaction_token))
+        .multipart(form)
+        .send()
+        .await
+        .context("Failed to send reply to Discord")?;
+    check(response, "reply").await?;
+    Ok(())
+}
+
+/// Discord messages are capped at 2000 characters
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        format!("{}...", text.chars().take(max - 3).collect::<String>())
+    }
+}
+
+/// A slash command plus its in-flight "thinking..." response, which has to land
+/// before the reply can be edited
+type Pending = (Interaction, JoinHandle<Result<()>>);
+
+/// Keep a gateway connection open, deferring each /imagine as it arrives and
+/// passing it on. Reconnects when Discord drops the connection; returns only on
+/// a fatal close (bad token) or when the receiver has gone away.
+async fn gateway(token: String, interactions: mpsc::UnboundedSender<Pending>) -> Result<()> {
+    loop {
+        match gateway_session(&token, &interactions).await {
+            Ok(()) if interactions.is_closed() => return Ok(()),
+            Ok(()) => eprintln!("Discord gateway disconnected, reconnecting..."),
+            Err(e) if e.is::<FatalClose>() => return Err(e),
+            Err(e) => eprintln!("Discord gateway error: {:#}, reconnecting...", e),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+#[derive(Debug)]
+struct FatalClose(u16, String);
+
+impl std::fmt::Display for FatalClose {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Discord closed the gateway ({}): {}", self.0, self.1)
+    }
+}
+
+impl std::error::Error for FatalClose {}
+
+fn heartbeat_message(sequence: Option<u64>) -> WsMessage {
+    WsMessage::Text(json!({ "op": 1, "d": sequence }).to_string())
+}
+
+/// One gateway connection: hello, identify, then heartbeat and dispatch until it closes
+async fn gateway_session(token: &str, interactions: &mpsc::UnboundedSender<Pending>) -> Result<()> {
+    let (mut ws, _) = tokio_tungstenite::connect_async(GATEWAY_URL)
+        .await
+        .context("Failed to connect to the Discord gateway")?;
+
+    let hello: Value = match ws.next().await {
+        Some(Ok(WsMessage::Text(text))) => serde_json::from_str(&text)?,
+        other => anyhow::bail!("Expected gateway hello, got {:?}", other),
+    };
+    let interval = hello["d"]["heartbeat_interval"]
+        .as_u64()
+        .context("Gateway hello without heartbeat_interval")?;
+
+    let identify = json!({
+        "op": 2,
+        "d": {
+            "token": token,
+            // Slash commands arrive without any intents
+            "intents": 0,
+            "properties": { "os": std::env::consts::OS, "browser": "pixery", "device": "pixery" },
+        },
+    });
+    ws.send(WsMessage::Text(identify.to_string())).await?;
+
+    let mut heartbeat = tokio::time::interval(Duration::from_millis(interval));
+    heartbeat.tick().await;
+    let mut sequence: Option<u64> = None;
+    let mut acked = true;
+
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                // No ack since the last beat: the connection is a zombie
+                if !acked {
+                    return Ok(());
+                }
+                acked = false;
+                ws.send(heartbeat_message(sequence)).await?;
+            }
+            message = ws.next() => {
+                let payload: Value = match message {
+                    Some(Ok(WsMessage::Text(text))) => serde_json::from_str(&text)?,
+                    Some(Ok(WsMessage::Close(Some(frame)))) => {
+                        let code = u16::from(frame.code);
+                        if FATAL_CLOSE_CODES.contains(&code) {
+                            return Err(FatalClose(code, frame.reason.to_string()).into());
+                        }
+                        return Ok(());
+                    }
+                    Some(Ok(WsMessage::Close(None))) | None => return Ok(()),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                };
+                if let Some(s) = payload["s"].as_u64() {
+                    sequence = Some(s);
+                }
+                match payload["op"].as_u64() {
+                    // Dispatch
+                    Some(0) => match payload["t"].as_str() {
+                        Some("READY") => {
+                            let name = payload["d"]["user"]["username"].as_str().unwrap_or("bot");
+                            eprintln!("Connected to Discord as {}", name);
+                        }
+                        Some("INTERACTION_CREATE") => {
+                            let interaction: Interaction = serde_json::from_value(payload["d"].clone())?;
+                            if interaction.kind != APPLICATION_COMMAND {
+                                continue;
+                            }
+                            let (id, interaction_token) = (interaction.id.clone(), interaction.token.clone());
+                            let deferred = tokio::spawn(async move { defer(&id, &interaction_token).await });
+                            if interactions.send((interaction, deferred)).is_err() {
+                                return Ok(());
+                            }
+                        }
+                        _ => {}
+                    },
+                    // Heartbeat request
+                    Some(1) => ws.send(heartbeat_message(sequence)).await?,
+                    // Reconnect, invalid session
+                    Some(7) | Some(9) => return Ok(()),
+                    // Heartbeat ack
+                    Some(11) => acked = true,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Generate for one /imagine and reply with the images
+async fn handle(db: &Database, app_id: &str, options: &BotOptions, interaction: &Interaction) -> Result<()> {
+    let command = interaction.data.as_ref().map_or("", |d| d.name.as_str());
+    if command != "imagine" {
+        return edit_reply(app_id, &interaction.token, &format!("Unknown command /{}", command), &[]).await;
+    }
+    let Some(prompt) = interaction.option("prompt").filter(|p| !p.trim().is_empty()) else {
+        return edit_reply(app_id, &interaction.token, "A prompt is required", &[]).await;
+    };
+    let model = interaction.option("model").unwrap_or(&options.model);
+    if model != options.model && !options.models.iter().any(|m| m == model) {
+        return edit_reply(app_id, &interaction.token, &format!("Model {} isn't enabled", model), &[]).await;
+    }
+    let requester = interaction.username();
+    eprintln!("/imagine from {} with {}: {}", requester, model, prompt);
+
+    let req = GenerationRequest {
+        model: model.to_string(),
+        prompt: prompt.to_string(),
+        ..Default::default()
+    };
+    let mut tags = vec![DISCORD_TAG.to_string()];
+    tags.extend(options.tags.iter().cloned());
+
+    let generations = match workflow::perform_generation(db, &req, &tags, JobSource::Cli, &|_| {}).await {
+        Ok(generations) => generations,
+        Err(e) => {
+            let content = if cancel::is_cancelled(&e) {
+                "Generation cancelled".to_string()
+            } else {
+                truncate(&format!("Generation with {} failed: {}", model, e), 2000)
+            };
+            eprintln!("  {}", content);
+            return edit_reply(app_id, &interaction.token, &content, &[]).await;
+        }
+    };
+
+    let requested_by = format!("discord:{}", requester);
+    for saved in &generations {
+        db.set_requested_by(saved.id, &requested_by)?;
+    }
+
+    let ids: Vec<String> = generations.iter().map(|saved| format!("#{}", saved.id)).collect();
+    let mut content = format!("**{}** · {} · {}", truncate(prompt, 1500), model, ids.join(" "));
+    let mut uploads = vec![];
+    for generation in generations.iter().map(|saved| &saved.generation) {
+        match generation.file_size {
+            Some(size) if size as u64 > MAX_UPLOAD_BYTES => {
+                content.push_str(&format!("\n{} is too large to upload", Path::new(&generation.image_path).display()));
+            }
+            _ => uploads.push(generation),
+        }
+    }
+    eprintln!("  -> {}", ids.join(" "));
+    edit_reply(app_id, &interaction.token, &truncate(&content, 2000), &uploads).await
+}
+
+/// Run the bot until the gateway closes for good. Requests are generated one at
+/// a time, in order; each is acknowledged straight away so Discord shows
+/// "thinking..." while it waits its turn.
+pub async fn run(db: &Database, options: &BotOptions) -> Result<()> {
+    let response = api(reqwest::Method::GET, "/oauth2/applications/@me", &options.token)
+        .send()
+        .await
+        .context("Failed to reach Discord")?;
+    let app: Application = check(response, "login").await?.json().await?;
+
+    register_commands(&app.id, options).await?;
+    match &options.guild {
+        Some(guild) => eprintln!("Registered /imagine for {} in server {}", app.name, guild),
+        None => eprintln!("Registered /imagine for {} (global)", app.name),
+    }
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let gateway = tokio::spawn(gateway(options.token.clone(), tx));
+
+    while let Some((interaction, deferred)) = rx.recv().await {
+        // Without the deferred response there is no message to edit
+        if let Err(e) = deferred.await? {
+            eprintln!("{:#}", e);
+            continue;
+        }
+        if let Err(e) = handle(db, &app.id, options, &interaction).await {
+            eprintln!("  {:#}", e);
+        }
+    }
+    gateway.await?
+}
@@ -0,0 +1,218 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::archive;
+use crate::db::Database;
+use crate::models::{DuplicateAction, Generation};
+use crate::workflow::{self, ImportOptions, Imported};
+
+/// Editors pixery knows how to start; `default` is whatever the system opens the file type with
+pub const BUILTIN_EDITORS: &[&str] = &["default", "photoshop", "krita", "gimp"];
+
+/// Tag added to edited images imported back
+pub const EDITED_TAG: &str = "edited";
+
+/// Placeholder for the working copy in a custom editor command
+const FILE_PLACEHOLDER: &str = "{file}";
+
+/// How often the working copy is checked for saves
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A save counts once the file has stopped changing for this long
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+/// Editor choices for `pixery edit`, stored in editors.json in the archive root
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditorSettings {
+    /// Used when --with isn't given; None means the system default
+    pub default: Option<String>,
+    /// Commands for editors by name, e.g. "krita": ["~/Apps/krita.AppImage", "{file}"].
+    /// They take precedence over the built-in ones; without {file} the file is appended.
+    pub commands: BTreeMap<String, Vec<String>>,
+}
+
+fn settings_path() -> PathBuf {
+    archive::archive_root().join("editors.json")
+}
+
+pub fn load_settings() -> EditorSettings {
+    std::fs::read_to_string(settings_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &EditorSettings) -> Result<()> {
+    std::fs::write(settings_path(), serde_json::to_string_pretty(settings)?)
+        .context("Failed to write editor settings")
+}
+
+impl EditorSettings {
+    /// The editor used without --with
+    pub fn default_editor(&self) -> &str {
+        self.default.as_deref().unwrap_or("default")
+    }
+
+    /// Every editor that can be picked: built-in ones and configured commands
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = BUILTIN_EDITORS.iter().map(|s| s.to_string()).collect();
+        names.extend(self.commands.keys().filter(|k| !BUILTIN_EDITORS.contains(&k.as_str())).cloned());
+        names
+    }
+
+    /// The command line that opens `file` in editor `name`
+    pub fn command_line(&self, name: &str, file: &Path) -> Result<Vec<String>> {
+        let file = file.to_string_lossy().into_owned();
+        if let Some(command) = self.commands.get(name).filter(|c| !c.is_empty()) {
+            let mut args: Vec<String> = command.iter().map(|a| a.replace(FILE_PLACEHOLDER, &file)).collect();
+            if !command.iter().any(|a| a.contains(FILE_PLACEHOLDER)) {
+                args.push(file);
+            }
+            return Ok(args);
+        }
+        let Some(builtin) = builtin(name) else {
+            if BUILTIN_EDITORS.contains(&name) {
+                anyhow::bail!(
+                    "pixery doesn't know how to start {} here; set its command with `pixery editors add {} -- COMMAND`",
+                    name,
+                    name
+                );
+            }
+            anyhow::bail!("Unknown editor '{}' (editors: {})", name, self.names().join(", "));
+        };
+        let mut args: Vec<String> = builtin.iter().map(|a| a.to_string()).collect();
+        args.push(file);
+        Ok(args)
+    }
+}
+
+/// How a built-in editor starts on this platform; the file is appended.
+/// None when it isn't available here.
+#[cfg(target_os = "macos")]
+fn builtin(name: &str) -> Option<&'static [&'static str]> {
+    Some(match name {
+        "default" => &["open"],
+        "photoshop" => &["open", "-b", "com.adobe.Photoshop"],
+        "krita" => &["open", "-a", "Krita"],
+        "gimp" => &["open", "-a", "GIMP"],
+        _ => return None,
+    })
+}
+
+/// `start` finds installed editors through App Paths; the empty argument is the window title
+#[cfg(target_os = "windows")]
+fn builtin(name: &str) -> Option<&'static [&'static str]> {
+    Some(match name {
+        "default" => &["cmd", "/C", "start", ""],
+        "photoshop" => &["cmd", "/C", "start", "", "photoshop"],
+        "krita" => &["cmd", "/C", "start", "", "krita"],
+        "gimp" => &["cmd", "/C", "start", "", "gimp"],
+        _ => return None,
+    })
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn builtin(name: &str) -> Option<&'static [&'static str]> {
+    Some(match name {
+        "default" => &["xdg-open"],
+        "krita" => &["krita"],
+        "gimp" => &["gimp"],
+        _ => return None,
+    })
+}
+
+/// Directory of working copies being edited
+pub fn edits_dir() -> PathBuf {
+    archive::archive_root().join("edits")
+}
+
+/// Copy a generation's image to the edits directory. A copy left by an earlier
+/// `pixery edit` is kept, with whatever was done to it; returns whether it was.
+pub fn working_copy(generation: &Generation) -> Result<(PathBuf, bool)> {
+    let source = Path::new(&generation.image_path);
+    let file_name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| format!("{}.png", generation.id));
+    let dir = edits_dir();
+    std::fs::create_dir_all(&dir).context("Failed to create edits directory")?;
+    let path = dir.join(format!("{}-{}", generation.id, file_name));
+    if path.exists() {
+        return Ok((path, true));
+    }
+    std::fs::copy(source, &path).with_context(|| format!("Failed to copy {}", source.display()))?;
+    Ok((path, false))
+}
+
+/// Start editor `name` on `file` without waiting for it
+pub fn launch(settings: &EditorSettings, name: &str, file: &Path) -> Result<()> {
+    let args = settings.command_line(name, file)?;
+    std::process::Command::new(&args[0])
+        .args(&args[1..])
+        .stdin(std::process::Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to start {} ({})", name, args[0]))?;
+    Ok(())
+}
+
+/// Modification time and size, which change on every save
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// Call `on_save` each time `file` is saved, once the editor has finished
+/// writing it, until it returns false. Editors that save by writing a new file
+/// and renaming it over the old one are caught too.
+pub fn watch(file: &Path, mut on_save: impl FnMut() -> Result<bool>) -> Result<()> {
+    let mut last = stamp(file);
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let current = stamp(file);
+        // Gone for a moment while an editor swaps it in
+        if current.is_none() || current == last {
+            continue;
+        }
+        let mut settled = current;
+        loop {
+            std::thread::sleep(SETTLE_TIME);
+            let again = stamp(file);
+            if again == settled {
+                break;
+            }
+            settled = again;
+        }
+        last = settled;
+        if settled.is_some() && !on_save()? {
+            return Ok(());
+        }
+    }
+}
+
+/// Archive the edited working copy as a child of `original`, with its prompt,
+/// model and tags plus `edited`. A save identical to an archived image (say,
+/// the original) isn't imported again; `Imported::duplicate` says so.
+pub fn import_edit(db: &Database, original: &Generation, file: &Path) -> Result<Imported> {
+    let now = chrono::Local::now();
+    let mut tags = original.tags.clone();
+    tags.push(EDITED_TAG.to_string());
+    // Dated now: the working copy's name carries the original's timestamp
+    let options = ImportOptions {
+        prompt: Some(original.prompt.clone()),
+        model: original.model.clone(),
+        tags,
+        date: Some(now.format("%Y-%m-%d").to_string()),
+        time: Some(now.format("%H%M%S").to_string()),
+        on_duplicate: DuplicateAction::Skip,
+        ..Default::default()
+    };
+    let imported = workflow::import_image(db, file, &options)?;
+    if !imported.duplicate {
+        db.set_parent_id(imported.id, original.id)?;
+    }
+    Ok(imported)
+}
@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::models::PromptingGuide;
+use crate::providers;
+
+const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta/models";
+
+const INSTRUCTIONS: &str = "You rewrite image generation prompts for a specific model. \
+Keep the user's subject, composition and intent; add the detail, structure and style \
+conventions the model responds to. Follow the prompting guide below exactly (tag format, \
+required prefixes, things to avoid). Reply with the rewritten prompt only: no preamble, \
+no quotes, no explanation, no negative prompt.";
+
+/// Text LLM used for prompt enhancement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnhanceBackend {
+    OpenAI,
+    Gemini,
+}
+
+impl std::str::FromStr for EnhanceBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "openai" => Ok(EnhanceBackend::OpenAI),
+            "gemini" => Ok(EnhanceBackend::Gemini),
+            _ => Err(format!("Unknown enhance backend: {} (expected openai or gemini)", s)),
+        }
+    }
+}
+
+/// Enhancement settings, stored in enhance.json in the archive root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnhanceSettings {
+    pub backend: EnhanceBackend,
+    /// Text model name (backend-specific)
+    pub model: String,
+}
+
+impl Default for EnhanceSettings {
+    fn default() -> Self {
+        EnhanceSettings {
+            backend: EnhanceBackend::Gemini,
+            model: Self::default_model(EnhanceBackend::Gemini).to_string(),
+        }
+    }
+}
+
+impl EnhanceSettings {
+    /// Default model for a backend, used when switching backends from the CLI
+    pub fn default_model(backend: EnhanceBackend) -> &'static str {
+        match backend {
+            EnhanceBackend::OpenAI => "gpt-4o-mini",
+            EnhanceBackend::Gemini => "gemini-2.5-flash",
+        }
+    }
+}
+
+/// Load enhancement settings, falling back to defaults (Gemini 2.5 Flash)
+pub fn load_settings() -> EnhanceSettings {
+    let path = crate::archive::archive_root().join("enhance.json");
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GeminiResponse {
+    #[serde(default)]
+    candidates: Vec<GeminiCandidate>,
+}
+
+#[derive(Deserialize)]
+struct GeminiCandidate {
+    content: Option<GeminiContent>,
+}
+
+#[derive(Deserialize)]
+struct GeminiContent {
+    #[serde(default)]
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Deserialize)]
+struct GeminiPart {
+    text: Option<String>,
+}
+
+/// System prompt: instructions plus the target model's guide (if it has one)
+fn system_prompt(target_model: &str) -> String {
+    match PromptingGuide::for_model(target_model) {
+        Some(guide) => format!("{}\n\nTarget model: {}\n\n{}", INSTRUCTIONS, target_model, guide.format()),
+        None => format!(
+            "{}\n\nTarget model: {} (no guide available; write a clear, detailed descriptive prompt)",
+            INSTRUCTIONS, target_model
+        ),
+    }
+}
+
+/// Rewrite `prompt` in the style the target image model expects
+pub async fn enhance_prompt(prompt: &str, target_model: &str, settings: &EnhanceSettings) -> Result<String> {
+    let text = complete(&system_prompt(target_model), prompt, settings).await?;
+
+    // Models sometimes wrap the answer in quotes despite being told not to
+    let enhanced = text.trim().trim_matches('"').trim().to_string();
+    if enhanced.is_empty() {
+        anyhow::bail!("LLM returned an empty prompt");
+    }
+    Ok(enhanced)
+}
+
+/// One system + user exchange with the configured text LLM; returns its raw reply
+pub async fn complete(system: &str, prompt: &str, settings: &EnhanceSettings) -> Result<String> {
+    match settings.backend {
+        EnhanceBackend::OpenAI => enhance_openai(system, prompt, settings).await,
+        EnhanceBackend::Gemini => enhance_gemini(system, prompt, settings).await,
+    }
+}
+
+async fn enhance_openai(system: &str, prompt: &str, settings: &EnhanceSettings) -> Result<String> {
+    let api_key = providers::openai::get_api_key()?;
+    let response = providers::client()
+        .post("https://api.openai.com/v1/chat/completions")
+        .header("Authorization", format!("Bearer {}", api_key))
+        .json(&serde_json::json!({
+            "model": settings.model,
+            "messages": [
+                { "role": "system", "content": system },
+                { "role": "user", "content": prompt },
+            ],
+        }))
+        .timeout(Duration::from_secs(60))
+        .send()
+        .await
+        .context("Failed to send request to OpenAI API")?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        anyhow::bail!("OpenAI API error {}: {}", status, text);
+    }
+
+    let data: ChatResponse = response.json().await.context("Failed to parse OpenAI response")?;
+    data.choices
+        .into_iter()
+        .next()
+        .and_then(|c| c.message.content)
+        .ok_or_else(|| anyhow::anyhow!("No text in OpenAI response"))
+}
+
+async fn enhance_gemini(system: &str, prompt: &str, settings: &EnhanceSettings) -> Result<String> {
+    let api_key = providers::gemini::get_api_key()?;
+    let response = providers::client()
+        .post(format!("{}/{}:generateContent", GEMINI_API_BASE, settings.model))
+        .header("x-goog-api-key", &api_key)
+        .json(&serde_json::json!({
+            "systemInstruction": { "parts": [{ "text": system }] },
+            "contents": [{ "role": "user", "parts": [{ "text": prompt }] }],
+        }))
+        .timeout(Duration::from_secs(60))
+        .send()
+        .await
+        .context("Failed to send request to Gemini API")?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        anyhow::bail!("Gemini API error {}: {}", status, text);
+    }
+
+    let data: GeminiResponse = response.json().await.context("Failed to parse Gemini response")?;
+    let text: String = data
+        .candidates
+        .into_iter()
+        .next()
+        .and_then(|c| c.content)
+        .map(|c| c.parts.into_iter().filter_map(|p| p.text).collect())
+        .unwrap_or_default();
+    if text.is_empty() {
+        anyhow::bail!("No text in Gemini response");
+    }
+    Ok(text)
+}
@@ -0,0 +1,133 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::cancel::Cancelled;
+use crate::providers::ApiError;
+
+/// Errors the GUI tells apart. Raised as-is where the kind is known (a missing
+/// key, a locked generation) and carried through anyhow like any other error;
+/// `From<&anyhow::Error>` finds them again, and classifies provider and I/O
+/// errors from the chain. Serialized as `{ code, message }` for Tauri commands.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum PixeryError {
+    #[error("No {provider} API key: set {vars} (e.g. in ~/.env) or run `pixery keys set {provider}`")]
+    MissingApiKey { provider: String, vars: String },
+    /// The provider refused the key (401/403)
+    #[error("{0}")]
+    AuthFailed(String),
+    #[error("{0}")]
+    RateLimited(String),
+    /// The model's daily quota in models.json is used up
+    #[error("Daily quota for {model} used up ({limit} a day, set in models.json); it resets at midnight")]
+    QuotaExceeded { model: String, limit: u32 },
+    /// The provider refused the prompt or image under its content policy;
+    /// `reason` is what tripped, when the API says
+    #[error(
+        "{provider} refused this under its content policy{}",
+        .reason.as_ref().map(|r| format!(" ({})", r)).unwrap_or_default()
+    )]
+    ContentPolicy { provider: String, reason: Option<String> },
+    /// Any other error response from a provider
+    #[error("{0}")]
+    Provider(String),
+    /// Timeout or no connection
+    #[error("{0}")]
+    Network(String),
+    /// An image, reference or other file that should be on disk isn't
+    #[error("{0}")]
+    FileMissing(String),
+    /// No generation, job, reference, ... with that ID or name
+    #[error("{0}")]
+    NotFound(String),
+    #[error("Generation {0} is locked (unlock it first)")]
+    Locked(i64),
+    #[error("Generation cancelled")]
+    Cancelled,
+    /// A read-only (guest) window refused a command that would change something
+    #[error("Pixery is open read-only; {0} is disabled")]
+    ReadOnly(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl PixeryError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PixeryError::MissingApiKey { .. } => "missing_api_key",
+            PixeryError::AuthFailed(_) => "auth_failed",
+            PixeryError::RateLimited(_) => "rate_limited",
+            PixeryError::QuotaExceeded { .. } => "quota_exceeded",
+            PixeryError::ContentPolicy { .. } => "content_policy",
+            PixeryError::Provider(_) => "provider_error",
+            PixeryError::Network(_) => "network",
+            PixeryError::FileMissing(_) => "file_missing",
+            PixeryError::NotFound(_) => "not_found",
+            PixeryError::Locked(_) => "locked",
+            PixeryError::Cancelled => "cancelled",
+            PixeryError::ReadOnly(_) => "read_only",
+            PixeryError::Other(_) => "other",
+        }
+    }
+}
+
+impl From<&anyhow::Error> for PixeryError {
+    fn from(err: &anyhow::Error) -> Self {
+        // Every context with its cause, "Generation failed: Gemini API error 429: ..."
+        let message = format!("{:#}", err);
+        for cause in err.chain() {
+            if let Some(e) = cause.downcast_ref::<PixeryError>() {
+                return e.clone();
+            }
+            if cause.is::<Cancelled>() {
+                return PixeryError::Cancelled;
+            }
+            if let Some(api) = cause.downcast_ref::<ApiError>() {
+                return match api.status.as_u16() {
+                    401 | 403 => PixeryError::AuthFailed(message),
+                    429 => PixeryError::RateLimited(message),
+                    _ => PixeryError::Provider(message),
+                };
+            }
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                if e.is_timeout() || e.is_connect() {
+                    return PixeryError::Network(message);
+                }
+            }
+            if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    return PixeryError::FileMissing(message);
+                }
+            }
+            if let Some(rusqlite::Error::QueryReturnedNoRows) = cause.downcast_ref::<rusqlite::Error>() {
+                return PixeryError::NotFound(message);
+            }
+        }
+        PixeryError::Other(message)
+    }
+}
+
+impl From<anyhow::Error> for PixeryError {
+    fn from(err: anyhow::Error) -> Self {
+        PixeryError::from(&err)
+    }
+}
+
+impl From<String> for PixeryError {
+    fn from(message: String) -> Self {
+        PixeryError::Other(message)
+    }
+}
+
+impl From<&str> for PixeryError {
+    fn from(message: &str) -> Self {
+        PixeryError::Other(message.to_string())
+    }
+}
+
+impl Serialize for PixeryError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("PixeryError", 2)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.end()
+    }
+}
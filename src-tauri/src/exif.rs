@@ -0,0 +1,197 @@
+use serde::Serialize;
+use std::io::Cursor;
+
+use image::{ImageDecoder, ImageReader};
+
+use crate::xmp;
+
+/// Metadata a camera or editing program embedded in an image, read when it's
+/// imported. Stored in the generation's params, so imported photos and mood
+/// boards keep where they came from.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EmbeddedMetadata {
+    /// Make and model, "FUJIFILM X-T4"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub camera: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lens: Option<String>,
+    /// Program that wrote the file, "Adobe Photoshop 25.0"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub software: Option<String>,
+    /// When the picture was taken or made, "YYYY-MM-DDTHH:MM:SS"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+}
+
+impl EmbeddedMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+const TAG_MAKE: u16 = 0x010f;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_SOFTWARE: u16 = 0x0131;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_DATE_TIME_DIGITIZED: u16 = 0x9004;
+const TAG_LENS_MODEL: u16 = 0xa434;
+
+/// A TIFF-structured EXIF chunk, just enough of it to read ASCII tags
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(chunk: &'a [u8]) -> Option<Self> {
+        // JPEG APP1 segments carry an "Exif\0\0" prefix some decoders leave on
+        let data = chunk.strip_prefix(b"Exif\0\0").unwrap_or(chunk);
+        let little_endian = match data.get(..4)? {
+            [b'I', b'I', 42, 0] => true,
+            [b'M', b'M', 0, 42] => false,
+            _ => return None,
+        };
+        Some(Tiff { data, little_endian })
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) })
+    }
+
+    /// Entries of the IFD at `offset` as (tag, type, count, value offset)
+    fn entries(&self, offset: usize) -> Vec<(u16, u16, u32, usize)> {
+        let Some(count) = self.u16_at(offset) else {
+            return vec![];
+        };
+        (0..count as usize)
+            .map_while(|i| {
+                let entry = offset + 2 + i * 12;
+                Some((self.u16_at(entry)?, self.u16_at(entry + 2)?, self.u32_at(entry + 4)?, entry + 8))
+            })
+            .collect()
+    }
+
+    /// Offset of the first IFD
+    fn ifd0(&self) -> Option<usize> {
+        self.u32_at(4).map(|o| o as usize)
+    }
+
+    /// An ASCII (type 2) value; up to four bytes are stored inline
+    fn ascii(&self, kind: u16, count: u32, value_offset: usize) -> Option<String> {
+        if kind != 2 {
+            return None;
+        }
+        let len = count as usize;
+        let start = if len <= 4 { value_offset } else { self.u32_at(value_offset)? as usize };
+        let bytes = self.data.get(start..start.checked_add(len)?)?;
+        let text = String::from_utf8_lossy(bytes);
+        let text = text.trim_end_matches('\0').trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    /// ASCII tags of the IFD at `offset`, plus the offset of the EXIF sub-IFD it points to
+    fn ascii_tags(&self, offset: usize) -> (Vec<(u16, String)>, Option<usize>) {
+        let mut tags = vec![];
+        let mut exif_ifd = None;
+        for (tag, kind, count, value_offset) in self.entries(offset) {
+            if tag == TAG_EXIF_IFD {
+                exif_ifd = self.u32_at(value_offset).map(|o| o as usize);
+            } else if let Some(text) = self.ascii(kind, count, value_offset) {
+                tags.push((tag, text));
+            }
+        }
+        (tags, exif_ifd)
+    }
+}
+
+/// "2024:03:01 14:05:09" (EXIF) or an ISO 8601 date/time (XMP) as
+/// "YYYY-MM-DDTHH:MM:SS"; None for the all-zero dates some cameras write
+fn normalize_datetime(value: &str) -> Option<String> {
+    use chrono::{NaiveDate, NaiveDateTime};
+    let value = value.trim();
+    let formats = [("%Y:%m:%d %H:%M:%S", 19), ("%Y-%m-%dT%H:%M:%S", 19), ("%Y-%m-%dT%H:%M", 16)];
+    for (format, len) in formats {
+        if let Some(parsed) = value.get(..len).and_then(|v| NaiveDateTime::parse_from_str(v, format).ok()) {
+            return Some(parsed.format("%Y-%m-%dT%H:%M:%S").to_string());
+        }
+    }
+    let date = NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()?;
+    Some(format!("{}T00:00:00", date))
+}
+
+fn camera(make: Option<&str>, model: Option<&str>) -> Option<String> {
+    match (make, model) {
+        // Models usually repeat the make ("Canon" + "Canon EOS R5")
+        (Some(make), Some(model)) if model.to_lowercase().starts_with(&make.to_lowercase()) => Some(model.to_string()),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model).map(str::to_string),
+    }
+}
+
+fn from_exif(chunk: &[u8]) -> EmbeddedMetadata {
+    let Some(tiff) = Tiff::new(chunk) else {
+        return EmbeddedMetadata::default();
+    };
+    let (mut tags, exif_ifd) = tiff.ifd0().map(|o| tiff.ascii_tags(o)).unwrap_or_default();
+    if let Some(offset) = exif_ifd {
+        tags.extend(tiff.ascii_tags(offset).0);
+    }
+    let tag = |id: u16| tags.iter().find(|(t, _)| *t == id).map(|(_, v)| v.as_str());
+
+    EmbeddedMetadata {
+        camera: camera(tag(TAG_MAKE), tag(TAG_MODEL)),
+        lens: tag(TAG_LENS_MODEL).map(str::to_string),
+        software: tag(TAG_SOFTWARE).map(str::to_string),
+        // The original capture time; DateTime is when the file was last changed
+        created: [TAG_DATE_TIME_ORIGINAL, TAG_DATE_TIME_DIGITIZED, TAG_DATE_TIME]
+            .into_iter()
+            .find_map(|id| tag(id).and_then(normalize_datetime)),
+    }
+}
+
+fn from_xmp(packet: &str) -> EmbeddedMetadata {
+    let property = |name: &str| xmp::property(packet, name);
+    EmbeddedMetadata {
+        camera: camera(property("tiff:Make").as_deref(), property("tiff:Model").as_deref()),
+        lens: property("aux:Lens").or_else(|| property("exifEX:LensModel")),
+        software: property("xmp:CreatorTool"),
+        created: ["exif:DateTimeOriginal", "photoshop:DateCreated", "xmp:CreateDate"]
+            .into_iter()
+            .find_map(|name| property(name).as_deref().and_then(normalize_datetime)),
+    }
+}
+
+/// Read the EXIF and XMP metadata of an image file's bytes, EXIF taking
+/// precedence. Only headers are parsed, not pixels; anything unreadable
+/// just comes back empty.
+pub fn read(data: &[u8]) -> EmbeddedMetadata {
+    let Ok(mut decoder) = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(image::ImageError::from)
+        .and_then(|reader| reader.into_decoder())
+    else {
+        return EmbeddedMetadata::default();
+    };
+    let exif = decoder.exif_metadata().ok().flatten().map(|c| from_exif(&c)).unwrap_or_default();
+    let xmp = decoder
+        .xmp_metadata()
+        .ok()
+        .flatten()
+        .map(|p| from_xmp(&String::from_utf8_lossy(&p)))
+        .unwrap_or_default();
+
+    EmbeddedMetadata {
+        camera: exif.camera.or(xmp.camera),
+        lens: exif.lens.or(xmp.lens),
+        software: exif.software.or(xmp.software),
+        created: exif.created.or(xmp.created),
+    }
+}
@@ -0,0 +1,186 @@
+use anyhow::Result;
+
+use crate::models::Generation;
+
+/// One exported generation: the record plus image/thumbnail paths relative to index.html
+pub struct GalleryEntry<'a> {
+    pub generation: &'a Generation,
+    pub image: String,
+    pub thumb: String,
+}
+
+/// Escape text for HTML element content and attribute values
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Render a static gallery page (thumbnail grid, tag filter, lightbox with
+/// metadata). Everything is inline, so the page works from disk with no
+/// network access; the data is embedded as JSON and rendered by a small script.
+pub fn render(title: &str, entries: &[GalleryEntry]) -> Result<String> {
+    let items: Vec<serde_json::Value> = entries
+        .iter()
+        .map(|e| {
+            let g = e.generation;
+            serde_json::json!({
+                "id": g.id,
+                "title": g.title,
+                "prompt": g.prompt,
+                "negative_prompt": g.negative_prompt,
+                "model": g.model,
+                "cost": g.cost_estimate_usd,
+                "date": g.date,
+                "seed": g.seed,
+                "width": g.width,
+                "height": g.height,
+                "tags": g.tags,
+                "media_type": g.media_type,
+                "image": e.image,
+                "thumb": e.thumb,
+            })
+        })
+        .collect();
+
+    // "</" inside a <script> block would end it early
+    let data = serde_json::to_string(&items)?.replace("</", "<\\/");
+
+    Ok(TEMPLATE
+        .replace("{{TITLE}}", &escape_html(title))
+        .replace("{{DATA}}", &data))
+}
+
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{{TITLE}}</title>
+<style>
+  :root {
+    --bg-primary: #1a191c; --bg-secondary: #242327; --bg-hover: #33323a;
+    --text-primary: #e8e8eb; --text-secondary: #9898a0; --accent: #9b8afb;
+    --border: #3a3a40; --radius: 6px;
+  }
+  * { box-sizing: border-box; margin: 0; padding: 0; }
+  body { background: var(--bg-primary); color: var(--text-primary); font: 14px/1.5 system-ui, sans-serif; padding: 24px; }
+  header { display: flex; align-items: baseline; gap: 12px; margin-bottom: 16px; }
+  header span { color: var(--text-secondary); }
+  #tags { display: flex; flex-wrap: wrap; gap: 6px; margin-bottom: 20px; }
+  #tags button { background: var(--bg-secondary); color: var(--text-secondary); border: 1px solid var(--border); border-radius: 999px; padding: 2px 10px; cursor: pointer; font: inherit; font-size: 12px; }
+  #tags button.active { background: var(--accent); border-color: var(--accent); color: var(--bg-primary); }
+  #grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(200px, 1fr)); gap: 12px; }
+  #grid figure { background: var(--bg-secondary); border-radius: var(--radius); overflow: hidden; cursor: pointer; }
+  #grid figure:hover { background: var(--bg-hover); }
+  #grid img { width: 100%; aspect-ratio: 1; object-fit: cover; display: block; }
+  #grid figcaption { padding: 6px 8px; font-size: 12px; color: var(--text-secondary); white-space: nowrap; overflow: hidden; text-overflow: ellipsis; }
+  #lightbox { position: fixed; inset: 0; background: rgba(0, 0, 0, 0.9); display: none; }
+  #lightbox.open { display: flex; }
+  #lightbox .image { flex: 1; display: flex; align-items: center; justify-content: center; padding: 24px; min-width: 0; }
+  #lightbox img, #lightbox video { max-width: 100%; max-height: 100%; object-fit: contain; }
+  #lightbox aside { width: 360px; background: var(--bg-secondary); padding: 20px; overflow-y: auto; }
+  #lightbox h2 { font-size: 16px; margin-bottom: 12px; }
+  #lightbox dl { display: grid; grid-template-columns: auto 1fr; gap: 4px 12px; font-size: 12px; margin-bottom: 16px; }
+  #lightbox dt { color: var(--text-secondary); }
+  #lightbox .label { color: var(--text-secondary); font-size: 12px; margin-bottom: 4px; }
+  #lightbox .prompt { white-space: pre-wrap; background: var(--bg-primary); padding: 8px; border-radius: var(--radius); font-size: 13px; margin-bottom: 12px; }
+</style>
+</head>
+<body>
+<header><h1>{{TITLE}}</h1><span id="count"></span></header>
+<div id="tags"></div>
+<div id="grid"></div>
+<div id="lightbox">
+  <div class="image"></div>
+  <aside></aside>
+</div>
+<script type="application/json" id="data">{{DATA}}</script>
+<script>
+  const items = JSON.parse(document.getElementById('data').textContent);
+  const grid = document.getElementById('grid');
+  const tagBar = document.getElementById('tags');
+  const lightbox = document.getElementById('lightbox');
+  let activeTag = null;
+  let visible = items;
+  let current = -1;
+
+  const el = (tag, props = {}, children = []) => {
+    const node = Object.assign(document.createElement(tag), props);
+    node.append(...children);
+    return node;
+  };
+
+  function renderTags() {
+    const counts = {};
+    items.forEach(i => i.tags.forEach(t => { counts[t] = (counts[t] || 0) + 1; }));
+    const tags = Object.keys(counts).sort((a, b) => counts[b] - counts[a] || a.localeCompare(b));
+    tagBar.replaceChildren(...[null, ...tags].map(t => {
+      const btn = el('button', { textContent: t === null ? `All (${items.length})` : `${t} (${counts[t]})` });
+      btn.classList.toggle('active', t === activeTag);
+      btn.onclick = () => { activeTag = t; renderTags(); renderGrid(); };
+      return btn;
+    }));
+  }
+
+  function renderGrid() {
+    visible = activeTag === null ? items : items.filter(i => i.tags.includes(activeTag));
+    document.getElementById('count').textContent = `${visible.length} image${visible.length === 1 ? '' : 's'}`;
+    grid.replaceChildren(...visible.map((item, idx) => {
+      const fig = el('figure', {}, [
+        el('img', { src: item.thumb, alt: item.title || item.prompt, loading: 'lazy' }),
+        el('figcaption', { textContent: item.title || item.prompt }),
+      ]);
+      fig.onclick = () => open(idx);
+      return fig;
+    }));
+  }
+
+  function open(idx) {
+    current = idx;
+    const item = visible[idx];
+    lightbox.querySelector('.image').replaceChildren(item.media_type === 'video'
+      ? el('video', { src: item.image, controls: true, autoplay: true, loop: true })
+      : el('img', { src: item.image, alt: '' }));
+    const meta = [
+      ['ID', item.id], ['Model', item.model], ['Date', item.date],
+      ['Cost', item.cost != null ? `$${item.cost.toFixed(3)}` : null],
+      ['Size', item.width && item.height ? `${item.width} × ${item.height}` : null],
+      ['Seed', item.seed], ['Tags', item.tags.join(', ') || null],
+    ].filter(([, v]) => v != null);
+    const aside = lightbox.querySelector('aside');
+    aside.replaceChildren(
+      el('h2', { textContent: item.title || `#${item.id}` }),
+      el('dl', {}, meta.flatMap(([k, v]) => [el('dt', { textContent: k }), el('dd', { textContent: v })])),
+      el('div', { className: 'label', textContent: 'Prompt' }),
+      el('div', { className: 'prompt', textContent: item.prompt }),
+      ...(item.negative_prompt ? [
+        el('div', { className: 'label', textContent: 'Negative prompt' }),
+        el('div', { className: 'prompt', textContent: item.negative_prompt }),
+      ] : []),
+    );
+    lightbox.classList.add('open');
+  }
+
+  // Emptied rather than just hidden, so a video stops playing
+  function close() {
+    lightbox.classList.remove('open');
+    lightbox.querySelector('.image').replaceChildren();
+  }
+
+  lightbox.onclick = e => { if (e.target === lightbox || e.target.classList.contains('image')) close(); };
+  document.onkeydown = e => {
+    if (!lightbox.classList.contains('open')) return;
+    if (e.key === 'Escape') close();
+    if (e.key === 'ArrowRight' && current < visible.length - 1) open(current + 1);
+    if (e.key === 'ArrowLeft' && current > 0) open(current - 1);
+  };
+
+  renderTags();
+  renderGrid();
+</script>
+</body>
+</html>
+"#;
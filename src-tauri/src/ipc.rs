@@ -0,0 +1,192 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::archive;
+use crate::models::{Generated, GenerationRequest, ProgressStage};
+use crate::providers::Progress;
+
+/// Socket the GUI listens on while it's open; the CLI forwards generations
+/// to it so one process writes the archive
+pub fn socket_path() -> PathBuf {
+    archive::archive_root().join("pixery.sock")
+}
+
+/// A command from the CLI, sent as one JSON line per connection
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum Request {
+    Generate { request: Box<GenerationRequest>, tags: Vec<String> },
+    /// Bring the window forward showing this generation (`pixery open`)
+    Focus { id: i64 },
+}
+
+/// The GUI's answer, one JSON line each: progress, then how it ended
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "reply", rename_all = "snake_case")]
+enum Reply {
+    Progress { stage: ProgressStage },
+    Done { generations: Vec<Generated> },
+    Failed { error: String, cancelled: bool },
+    Focused,
+}
+
+/// Run a generation in the open GUI. None when no GUI is listening (or on
+/// platforms without unix sockets): generate directly then, which WAL and
+/// the archive's create-new file names keep safe alongside a GUI.
+pub fn forward_generation(
+    req: &GenerationRequest,
+    tags: &[String],
+    progress: Progress<'_>,
+) -> Option<Result<Vec<Generated>>> {
+    #[cfg(unix)]
+    {
+        let stream = std::os::unix::net::UnixStream::connect(socket_path()).ok()?;
+        let request = Request::Generate { request: Box::new(req.clone()), tags: tags.to_vec() };
+        Some(unix::exchange(&stream, &request, progress))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (req, tags, progress);
+        None
+    }
+}
+
+/// Show generation `id` in the open GUI. None when no GUI is listening.
+pub fn forward_focus(id: i64) -> Option<Result<()>> {
+    #[cfg(unix)]
+    {
+        let stream = std::os::unix::net::UnixStream::connect(socket_path()).ok()?;
+        Some(unix::focus(&stream, id))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = id;
+        None
+    }
+}
+
+/// Answer forwarded CLI commands until the GUI exits. A socket left behind by
+/// a GUI that crashed is replaced; if another GUI is already listening, it
+/// keeps the job.
+pub fn start_server(app: AppHandle) {
+    #[cfg(unix)]
+    unix::start_server(app);
+    #[cfg(not(unix))]
+    let _ = app;
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::Shutdown;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    use crate::cancel::{self, CancelToken, Cancelled};
+    use crate::db::Database;
+    use crate::deeplink;
+    use crate::models::JobSource;
+    use crate::workflow;
+
+    fn send(stream: &UnixStream, message: &impl Serialize) -> Result<()> {
+        let mut writer = stream;
+        writeln!(writer, "{}", serde_json::to_string(message)?)?;
+        Ok(())
+    }
+
+    pub(super) fn exchange(
+        stream: &UnixStream,
+        request: &Request,
+        progress: Progress<'_>,
+    ) -> Result<Vec<Generated>> {
+        send(stream, request)?;
+        for line in BufReader::new(stream).lines() {
+            match serde_json::from_str(&line?).context("Invalid reply from the GUI")? {
+                Reply::Progress { stage } => progress(stage),
+                Reply::Done { generations } => return Ok(generations),
+                Reply::Failed { cancelled: true, .. } => return Err(Cancelled.into()),
+                Reply::Failed { error, .. } => anyhow::bail!(error),
+                Reply::Focused => anyhow::bail!("Unexpected reply from the GUI"),
+            }
+        }
+        anyhow::bail!("The GUI closed before the generation finished")
+    }
+
+    pub(super) fn focus(stream: &UnixStream, id: i64) -> Result<()> {
+        send(stream, &Request::Focus { id })?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        match serde_json::from_str(&line).context("Invalid reply from the GUI")? {
+            Reply::Focused => Ok(()),
+            _ => anyhow::bail!("The GUI didn't show generation {}", id),
+        }
+    }
+
+    pub(super) fn start_server(app: AppHandle) {
+        let path = socket_path();
+        if UnixStream::connect(&path).is_ok() {
+            eprintln!("Another pixery window is answering CLI commands");
+            return;
+        }
+        let _ = std::fs::remove_file(&path);
+        let listener = match UnixListener::bind(&path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("CLI forwarding disabled: {}", e);
+                return;
+            }
+        };
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let app = app.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = serve(&app, &stream) {
+                        eprintln!("Forwarded CLI command failed: {}", e);
+                    }
+                    let _ = stream.shutdown(Shutdown::Both);
+                });
+            }
+        });
+    }
+
+    /// One connection: read the request; for a generation, run it with its
+    /// own database connection (like the scheduler) and stream the replies back
+    fn serve(app: &AppHandle, stream: &UnixStream) -> Result<()> {
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        let request: Request = serde_json::from_str(&line).context("Invalid request")?;
+        let (request, tags) = match request {
+            Request::Generate { request, tags } => (request, tags),
+            Request::Focus { id } => {
+                deeplink::focus(app, id);
+                return send(stream, &Reply::Focused);
+            }
+        };
+
+        // The CLI hanging up (Ctrl-C) cancels what it asked for
+        let token = CancelToken::default();
+        let hung_up = token.clone();
+        let mut watch = stream.try_clone()?;
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            while matches!(watch.read(&mut buf), Ok(n) if n > 0) {}
+            hung_up.cancel();
+        });
+
+        let db = Database::open(&archive::db_path())?;
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        let progress = |stage| {
+            let _ = send(stream, &Reply::Progress { stage });
+        };
+        let run = workflow::perform_generation(&db, &request, &tags, JobSource::Cli, &progress);
+        let reply = match rt.block_on(cancel::scope(token, run)) {
+            Ok(generations) => Reply::Done { generations },
+            Err(e) => Reply::Failed { error: e.to_string(), cancelled: cancel::is_cancelled(&e) },
+        };
+        // Nobody to tell if the CLI hung up
+        let _ = send(stream, &reply);
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+
+use crate::error::PixeryError;
+
+/// Keychain service the keys are stored under, one entry per provider
+const SERVICE: &str = "pixery";
+
+/// Providers that need an API key, with the environment variables checked
+/// (in order) before the keychain
+pub const PROVIDERS: &[(&str, &[&str])] = &[
+    ("gemini", &["GEMINI_API_SECRET_KEY", "GEMINI_API_KEY"]),
+    ("openai", &["OPENAI_API_SECRET_KEY", "OPENAI_API_KEY"]),
+    ("fal", &["FAL_KEY"]),
+];
+
+/// Where a provider's key comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeySource {
+    /// This environment variable (~/.env included)
+    Env(&'static str),
+    Keychain,
+}
+
+/// Whether the provider needs an API key (self-hosted servers don't)
+pub fn needs_key(provider: &str) -> bool {
+    PROVIDERS.iter().any(|(name, _)| *name == provider)
+}
+
+fn env_vars(provider: &str) -> Result<&'static [&'static str]> {
+    PROVIDERS
+        .iter()
+        .find(|(name, _)| *name == provider)
+        .map(|(_, vars)| *vars)
+        .with_context(|| format!("Unknown provider: {} (expected gemini, openai or fal)", provider))
+}
+
+fn entry(provider: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, provider).context("OS keychain unavailable")
+}
+
+/// The key and where it came from: environment first, then the OS keychain
+fn lookup(provider: &str) -> Result<Option<(String, KeySource)>> {
+    for var in env_vars(provider)? {
+        if let Ok(key) = std::env::var(var) {
+            if !key.is_empty() {
+                return Ok(Some((key, KeySource::Env(var))));
+            }
+        }
+    }
+    match entry(provider)?.get_password() {
+        Ok(key) => Ok(Some((key, KeySource::Keychain))),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e).context("Failed to read the OS keychain"),
+    }
+}
+
+/// A provider's API key, from the environment or else the OS keychain
+pub fn get(provider: &str) -> Result<String> {
+    let vars = env_vars(provider)?;
+    match lookup(provider) {
+        Ok(Some((key, _))) => Ok(key),
+        Ok(None) => Err(PixeryError::MissingApiKey { provider: provider.to_string(), vars: vars.join(" or ") }.into()),
+        Err(e) => Err(e.context(format!("{} not set and no {} key in the keychain", vars.join(" or "), provider))),
+    }
+}
+
+/// Where the provider's key would be read from, None if it has none
+pub fn source(provider: &str) -> Result<Option<KeySource>> {
+    Ok(lookup(provider)?.map(|(_, source)| source))
+}
+
+/// Store a key in the OS keychain, replacing any already there
+pub fn set(provider: &str, key: &str) -> Result<()> {
+    env_vars(provider)?;
+    let key = key.trim();
+    if key.is_empty() {
+        anyhow::bail!("API key is empty");
+    }
+    entry(provider)?
+        .set_password(key)
+        .context("Failed to write to the OS keychain")
+}
+
+/// Remove a key from the OS keychain. Returns false if there was none.
+pub fn remove(provider: &str) -> Result<bool> {
+    env_vars(provider)?;
+    match entry(provider)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(e).context("Failed to remove the key from the OS keychain"),
+    }
+}
@@ -1,16 +1,55 @@
-use std::sync::Mutex;
-
 pub mod archive;
+pub mod bundle;
+pub mod cancel;
+pub mod caption;
+pub mod clipboard;
+pub mod compare;
+pub mod convert;
+pub mod currency;
 mod commands;
+pub mod custom_models;
 pub mod db;
+pub mod deeplink;
+pub mod detail;
+pub mod editor;
+pub mod discord;
+pub mod enhance;
+pub mod error;
+pub mod exif;
+pub mod gallery;
+pub mod ipc;
+pub mod keys;
+pub mod linkfarm;
+pub mod lint;
+pub mod maintenance;
 pub mod models;
+pub mod notify;
+pub mod nsfw;
+pub mod outpaint;
+pub mod palette;
+pub mod profiles;
+pub mod protocol;
 pub mod providers;
-mod watcher;
+pub mod readonly;
+pub mod refprep;
+pub mod scheduler;
+pub mod slideshow;
+pub mod sync;
+pub mod telegram;
+pub mod template;
+pub mod title;
+pub mod transform;
+pub mod tui;
+pub mod video;
+pub mod watcher;
 pub mod workflow;
+pub mod xmp;
 
 pub mod cli;
 
 use commands::AppState;
+use error::PixeryError;
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -18,61 +57,148 @@ pub fn run() {
     archive::ensure_dirs().expect("Failed to create archive directories");
 
     // Open database
-    let db = db::Database::open(&archive::db_path()).expect("Failed to open database");
+    let db = db::DbPool::open(&archive::db_path()).expect("Failed to open database");
+    let read_only = readonly::is_enabled();
 
-    // Cleanup stale job records on startup
-    if let Ok(stalled) = db.cleanup_stalled_jobs() {
-        if stalled > 0 {
-            eprintln!("Cleaned up {} stalled jobs", stalled);
-        }
-    }
-    if let Ok(old) = db.cleanup_old_jobs(24) {
-        if old > 0 {
-            eprintln!("Cleaned up {} old completed/failed jobs", old);
-        }
-    }
+    let handler = tauri::generate_handler![
+        commands::generate_image,
+        commands::list_generations,
+        commands::list_generations_page,
+        commands::count_generations,
+        commands::random_generations,
+        commands::get_filter_facets,
+        commands::search_generations,
+        commands::get_generation,
+        commands::toggle_starred,
+        commands::set_locked,
+        commands::set_nsfw,
+        commands::trash_generation,
+        commands::trash_generations,
+        commands::restore_generation,
+        commands::permanently_delete_generation,
+        commands::update_prompt,
+        commands::update_title,
+        commands::caption_generation,
+        commands::copy_image_to_clipboard,
+        commands::add_tags,
+        commands::remove_tag,
+        commands::list_tags,
+        commands::list_models,
+        commands::get_model_quotas,
+        commands::get_model_catalog,
+        commands::refresh_model_catalog,
+        commands::list_presets,
+        commands::get_cost_summary,
+        commands::get_stats,
+        commands::get_image_path,
+        commands::stage_reference,
+        commands::get_references,
+        commands::list_references,
+        commands::get_reference_generations,
+        commands::list_jobs,
+        commands::list_failed_jobs,
+        commands::cancel_job,
+        commands::retry_failed_jobs,
+        commands::list_collections,
+        commands::create_collection,
+        commands::add_to_collection,
+        commands::remove_from_collection,
+        commands::delete_collection,
+        commands::restore_collection,
+        commands::reorder_collection,
+        commands::set_collection_cover,
+        commands::list_smart_collections,
+        commands::create_smart_collection,
+        commands::delete_smart_collection,
+        commands::list_experiments,
+        commands::get_experiment,
+        commands::activity_feed,
+        commands::undo_event,
+        commands::undo_last,
+        commands::prompt_history,
+        commands::get_selfhosted_url,
+        commands::set_selfhosted_url,
+        commands::check_selfhosted_health,
+        commands::switch_selfhosted_model,
+        commands::warmup_selfhosted,
+        commands::get_maintenance_status,
+        commands::get_notify_settings,
+        commands::set_notify_settings,
+        commands::get_nsfw_settings,
+        commands::set_nsfw_settings,
+        commands::get_currency_settings,
+        commands::set_currency_settings,
+        commands::get_display_currency,
+        commands::refresh_exchange_rate,
+        commands::get_read_only,
+        commands::get_slideshow,
+        commands::take_focus_generation,
+    ];
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_notification::init())
-        .manage(AppState { db: Mutex::new(db) })
-        .invoke_handler(tauri::generate_handler![
-            commands::generate_image,
-            commands::list_generations,
-            commands::search_generations,
-            commands::get_generation,
-            commands::toggle_starred,
-            commands::trash_generation,
-            commands::trash_generations,
-            commands::restore_generation,
-            commands::permanently_delete_generation,
-            commands::update_prompt,
-            commands::update_title,
-            commands::add_tags,
-            commands::remove_tag,
-            commands::list_tags,
-            commands::list_models,
-            commands::get_cost_summary,
-            commands::get_image_path,
-            commands::get_references,
-            commands::list_jobs,
-            commands::list_failed_jobs,
-            commands::list_collections,
-            commands::create_collection,
-            commands::add_to_collection,
-            commands::remove_from_collection,
-            commands::delete_collection,
-            commands::prompt_history,
-            commands::get_selfhosted_url,
-            commands::set_selfhosted_url,
-            commands::check_selfhosted_health,
-        ])
-        .setup(|app| {
+        .manage(AppState { db, read_only })
+        // Gallery images and thumbnails, served by generation ID with caching headers
+        .register_asynchronous_uri_scheme_protocol(protocol::SCHEME, |ctx, request, responder| {
+            let db = ctx.app_handle().state::<AppState>().db.clone();
+            tauri::async_runtime::spawn_blocking(move || responder.respond(protocol::respond(&db, &request)));
+        })
+        // Guest mode: refuse anything that isn't a read before it reaches the command
+        .invoke_handler(move |invoke| {
+            let command = invoke.message.command();
+            if read_only && !readonly::allows(command) {
+                let error = PixeryError::ReadOnly(command.to_string());
+                invoke.resolver.reject(error);
+                return true;
+            }
+            handler(invoke)
+        })
+        .setup(move |app| {
             // Start file watcher for auto-refresh
             let generations_dir = archive::generations_dir();
             watcher::start_watcher(app.handle().clone(), &generations_dir);
+            // Tell windows on different archive profiles apart
+            if profiles::current_name() != profiles::DEFAULT_PROFILE {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.set_title(&format!("pixery ({})", profiles::current_name()));
+                }
+            }
+            if let Some(show) = slideshow::current() {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.set_title("pixery slideshow");
+                    let _ = window.set_fullscreen(show.fullscreen);
+                }
+            }
+            if read_only {
+                // Nothing writes to the archive from a guest window
+                return Ok(());
+            }
+            // Auto-import from watch folders configured with 'pixery watch add'
+            watcher::start_ingest();
+            // Job cleanup, trash purge and preview pruning on a schedule
+            maintenance::start_background(app.handle().clone());
+            // Generations and `pixery open` links forwarded from the CLI while the GUI is open
+            ipc::start_server(app.handle().clone());
+            // Recurring generations, if enabled with 'pixery schedule gui on'
+            if scheduler::load_settings().run_in_gui {
+                scheduler::start_background();
+            }
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // macOS hands pixery:// links (Info.plist) to the app as events, running or not
+            #[cfg(target_os = "macos")]
+            if let tauri::RunEvent::Opened { urls } = event {
+                for url in urls {
+                    if let Err(e) = deeplink::open_link(app, url.as_str()) {
+                        eprintln!("{}", e);
+                    }
+                }
+            }
+            #[cfg(not(target_os = "macos"))]
+            let _ = (app, event);
+        });
 }
@@ -0,0 +1,251 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::db::Database;
+use crate::models::{Generation, ListFilter, SortKey};
+use crate::template::{NameContext, NameTemplate};
+
+/// Lists the links a farm holds, so a rerun only ever touches its own files
+const MANIFEST_NAME: &str = ".pixery-linkfarm.json";
+
+/// Folder for generations without a tag (`--by tag`)
+const UNTAGGED_DIR: &str = "untagged";
+
+/// What a link farm's folders are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    /// One folder per tag; a generation appears under each of its tags
+    Tag,
+    Model,
+    /// Nested collections ("parent/child") become nested folders
+    Collection,
+    /// One folder per month (YYYY-MM)
+    Date,
+}
+
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            GroupBy::Tag => "tag",
+            GroupBy::Model => "model",
+            GroupBy::Collection => "collection",
+            GroupBy::Date => "date",
+        })
+    }
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tag" | "tags" => Ok(GroupBy::Tag),
+            "model" => Ok(GroupBy::Model),
+            "collection" | "collections" => Ok(GroupBy::Collection),
+            "date" | "month" => Ok(GroupBy::Date),
+            _ => Err(format!("Unknown grouping '{}' (tag, model, collection, date)", s)),
+        }
+    }
+}
+
+/// How a farm is laid out
+#[derive(Debug, Clone)]
+pub struct FarmOptions {
+    pub by: GroupBy,
+    /// Hard links instead of symlinks (same filesystem only)
+    pub hardlink: bool,
+    /// Link names; by default the archive file name, with "-ID" added when
+    /// two generations in one folder share it
+    pub name_template: Option<NameTemplate>,
+}
+
+/// What a farm contained last time it was built
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    hardlink: bool,
+    /// Link path relative to the farm root -> the archive file it points at
+    links: BTreeMap<String, String>,
+}
+
+/// Outcome of building a farm
+#[derive(Debug, Default)]
+pub struct FarmReport {
+    pub created: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+    /// Generations whose file is missing from the archive
+    pub missing: usize,
+    /// Link paths taken by a file pixery didn't make, left alone
+    pub conflicts: Vec<PathBuf>,
+}
+
+/// A group name as a folder name: no path separators, never "." or ".."
+fn folder_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '\0') { '-' } else { c })
+        .collect();
+    match name.as_str() {
+        "" | "." | ".." => "_".to_string(),
+        _ => name,
+    }
+}
+
+/// Folders (relative to the farm root) a generation is linked into
+fn folders(gen: &Generation, by: GroupBy) -> Vec<PathBuf> {
+    match by {
+        GroupBy::Tag if gen.tags.is_empty() => vec![PathBuf::from(UNTAGGED_DIR)],
+        GroupBy::Tag => gen.tags.iter().map(|t| PathBuf::from(folder_name(t))).collect(),
+        GroupBy::Model => vec![PathBuf::from(folder_name(&gen.model))],
+        GroupBy::Collection => gen
+            .collection_names
+            .iter()
+            .map(|name| name.split('/').map(folder_name).collect())
+            .collect(),
+        GroupBy::Date => vec![PathBuf::from(folder_name(gen.date.get(..7).unwrap_or(&gen.date)))],
+    }
+}
+
+/// Every link the farm should hold: relative link path -> archive file
+fn plan(generations: &[Generation], options: &FarmOptions, report: &mut FarmReport) -> BTreeMap<PathBuf, PathBuf> {
+    let mut links = BTreeMap::new();
+    let mut counters: BTreeMap<PathBuf, usize> = BTreeMap::new();
+    for gen in generations {
+        let target = PathBuf::from(&gen.image_path);
+        if !target.exists() {
+            report.missing += 1;
+            continue;
+        }
+        let extension = target.extension().and_then(|e| e.to_str()).unwrap_or("png");
+        let stem = target.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| gen.id.to_string());
+        for folder in folders(gen, options.by) {
+            let counter = counters.entry(folder.clone()).or_default();
+            *counter += 1;
+            let name = match &options.name_template {
+                Some(template) => {
+                    let ctx = NameContext { generation: gen, collection: None, tag: None, counter: *counter };
+                    template.render(&ctx)
+                }
+                None => stem.clone(),
+            };
+            let mut link = folder.join(format!("{}.{}", name, extension));
+            if links.contains_key(&link) {
+                link = folder.join(format!("{}-{}.{}", name, gen.id, extension));
+            }
+            links.insert(link, target.clone());
+        }
+    }
+    links
+}
+
+fn manifest_path(root: &Path) -> PathBuf {
+    root.join(MANIFEST_NAME)
+}
+
+fn load_manifest(root: &Path) -> Manifest {
+    std::fs::read_to_string(manifest_path(root))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(target, link)
+}
+
+/// Whether `link` is still the link the farm made to `target`. A symlink
+/// must point there; a hard link can't be told from a copy, so any file counts.
+fn is_ours(link: &Path, target: &Path, hardlink: bool) -> bool {
+    if hardlink {
+        link.is_file()
+    } else {
+        std::fs::read_link(link).is_ok_and(|t| t == target)
+    }
+}
+
+/// Remove now-empty folders from `dir` up to (not including) `root`
+fn prune_empty(root: &Path, mut dir: &Path) {
+    while dir != root && dir.starts_with(root) {
+        if std::fs::remove_dir(dir).is_err() {
+            break;
+        }
+        match dir.parent() {
+            Some(parent) => dir = parent,
+            None => break,
+        }
+    }
+}
+
+/// Build or update the link farm in `root` from the archive's non-trashed
+/// generations. Links from an earlier run that are no longer wanted are
+/// removed; files pixery didn't create are never touched.
+pub fn build(db: &Database, root: &Path, options: &FarmOptions) -> Result<FarmReport> {
+    let generations = db.list_generations(&ListFilter {
+        sort: Some(SortKey::Timestamp),
+        sort_desc: false,
+        ..Default::default()
+    })?;
+    let mut report = FarmReport::default();
+    let wanted = plan(&generations, options, &mut report);
+
+    std::fs::create_dir_all(root).with_context(|| format!("Failed to create {}", root.display()))?;
+    let previous = load_manifest(root);
+    let same_kind = previous.hardlink == options.hardlink;
+
+    // Drop links that are gone or point elsewhere now
+    for (relative, target) in &previous.links {
+        let relative = PathBuf::from(relative);
+        let target = PathBuf::from(target);
+        if same_kind && wanted.get(&relative) == Some(&target) {
+            continue;
+        }
+        let link = root.join(&relative);
+        if is_ours(&link, &target, previous.hardlink) {
+            std::fs::remove_file(&link).with_context(|| format!("Failed to remove {}", link.display()))?;
+            report.removed += 1;
+            if let Some(parent) = link.parent() {
+                prune_empty(root, parent);
+            }
+        }
+    }
+
+    let mut manifest = Manifest { hardlink: options.hardlink, links: BTreeMap::new() };
+    for (relative, target) in &wanted {
+        let link = root.join(relative);
+        let key = relative.to_string_lossy().into_owned();
+        let listed = same_kind && previous.links.get(&key).map(PathBuf::from).as_ref() == Some(target);
+        if listed && is_ours(&link, target, options.hardlink) {
+            report.unchanged += 1;
+        } else if link.symlink_metadata().is_ok() {
+            report.conflicts.push(link);
+            continue;
+        } else {
+            if let Some(parent) = link.parent() {
+                std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+            }
+            let made = if options.hardlink { std::fs::hard_link(target, &link) } else { symlink(target, &link) };
+            made.with_context(|| {
+                let hint = if options.hardlink { " (hard links need the archive's filesystem)" } else { "" };
+                format!("Failed to link {}{}", link.display(), hint)
+            })?;
+            report.created += 1;
+        }
+        manifest.links.insert(key, target.to_string_lossy().into_owned());
+    }
+
+    std::fs::write(manifest_path(root), serde_json::to_string_pretty(&manifest)?)
+        .context("Failed to write link farm manifest")?;
+    Ok(report)
+}
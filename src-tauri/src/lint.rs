@@ -0,0 +1,86 @@
+use crate::models::PromptingGuide;
+
+/// Words that only show up in sentences, never in booru tags
+const PROSE_WORDS: &[&str] = &[
+    "a", "an", "the", "is", "are", "was", "with", "of", "and", "her", "his", "their", "while", "who", "which",
+];
+
+/// Comma-separated segments, trimmed and lowercased, empty ones dropped
+fn segments(prompt: &str) -> Vec<String> {
+    prompt
+        .split([',', '\n'])
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Long comma-free runs of words with articles/pronouns read as prose
+fn looks_like_prose(prompt: &str) -> bool {
+    let segs = segments(prompt);
+    let sentence_like = segs
+        .iter()
+        .filter(|s| {
+            let words: Vec<&str> = s.split_whitespace().collect();
+            words.len() >= 5 && words.iter().any(|w| PROSE_WORDS.contains(w))
+        })
+        .count();
+    sentence_like > 0 || prompt.contains(". ")
+}
+
+/// Many short segments and underscore tags read as booru tags
+fn looks_like_tags(prompt: &str) -> bool {
+    let segs = segments(prompt);
+    if segs.len() < 6 {
+        return false;
+    }
+    let words: usize = segs.iter().map(|s| s.split_whitespace().count()).sum();
+    let underscored = segs.iter().any(|s| !s.contains(' ') && s.contains('_'));
+    (words as f64 / segs.len() as f64) <= 2.5 || underscored
+}
+
+/// Check a prompt against the model's prompting guide. Returns one message per
+/// problem; empty when the prompt looks fine or the model has no guide.
+pub fn lint_prompt(model: &str, prompt: &str) -> Vec<String> {
+    let mut issues = vec![];
+    if prompt.trim().is_empty() {
+        issues.push("Prompt is empty".to_string());
+        return issues;
+    }
+    let Some(guide) = PromptingGuide::for_model(model) else {
+        return issues;
+    };
+
+    match guide.style {
+        "tags" if looks_like_prose(prompt) => issues.push(format!(
+            "{} expects comma-separated booru tags, but the prompt reads like prose",
+            guide.model_pattern
+        )),
+        "prose" if looks_like_tags(prompt) => issues.push(format!(
+            "{} works best with descriptive prose, but the prompt reads like a tag list",
+            guide.model_pattern
+        )),
+        _ => {}
+    }
+
+    let segs = segments(prompt);
+    if let Some(prefix) = guide.required_prefix {
+        let missing: Vec<&str> = prefix
+            .split(',')
+            .map(|t| t.trim())
+            .filter(|t| !segs.iter().any(|s| s == &t.to_lowercase()))
+            .collect();
+        if !missing.is_empty() {
+            issues.push(format!("Missing required prefix tags: {}", missing.join(", ")));
+        }
+    }
+
+    let lower = prompt.to_lowercase();
+    for token in guide.avoid_tokens {
+        let pattern = format!(r"\b{}\b", regex::escape(token));
+        if regex::Regex::new(&pattern).is_ok_and(|re| re.is_match(&lower)) {
+            issues.push(format!("'{}' is on the avoid list for {}", token, guide.model_pattern));
+        }
+    }
+
+    issues
+}
@@ -1,13 +1,17 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use clap_complete::engine::ArgValueCandidates;
 use pixery_lib::cli;
 
 #[derive(Parser)]
 #[command(name = "pixery")]
 #[command(about = "Unified image generation tool with CLI and GUI interfaces")]
 #[command(long_about = "Unified image generation tool with CLI and GUI interfaces.\n\n\
-    Subcommands provide CLI access; no args launches the GUI.\n\n\
+    Subcommands provide CLI access; no args launches the GUI. `pixery --read-only` opens it \
+    in guest mode, where browsing works and every change is refused. `--archive NAME` works on \
+    another archive profile (see `pixery profiles`).\n\n\
     Workflow: generate → list/search → show (metadata) → view (image)\n\
     Iteration: generate → view → refine prompt or use --ref → generate\n\n\
     Supports Gemini, fal.ai, OpenAI, and self-hosted providers. Images are archived to \
@@ -17,6 +21,14 @@ use pixery_lib::cli;
 struct Args {
     #[command(subcommand)]
     command: Option<cli::Commands>,
+
+    /// Open the GUI read-only (see also `pixery read-only`)
+    #[arg(long)]
+    read_only: bool,
+
+    /// Archive profile to use instead of the active one
+    #[arg(long, global = true, value_name = "NAME", add = ArgValueCandidates::new(cli::complete_profiles))]
+    archive: Option<String>,
 }
 
 fn main() {
@@ -28,18 +40,45 @@ fn main() {
         }
     }
 
+    // Answer shell completion requests (COMPLETE=<shell> set by the completion script)
+    clap_complete::CompleteEnv::with_factory(Args::command).complete();
+
     let args = Args::parse();
+    if args.read_only && args.command.is_some() {
+        Args::command()
+            .error(ErrorKind::ArgumentConflict, "--read-only only applies to the GUI (run without a subcommand)")
+            .exit();
+    }
+    // A pixery:// link is in the profile it names, or the default one
+    let archive = args.archive.clone().or_else(|| match &args.command {
+        Some(cli::Commands::Open { link: Some(link), .. }) => {
+            pixery_lib::deeplink::parse(link).ok().and_then(|(_, archive)| archive)
+        }
+        _ => None,
+    });
+    if let Some(name) = &archive {
+        if let Err(e) = pixery_lib::profiles::select(name) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
 
     match args.command {
         Some(cmd) => {
             // CLI mode
-            if let Err(e) = cli::run(cmd) {
+            let result = cli::run(cmd);
+            // Thumbnails are written in the background; finish them before exiting
+            pixery_lib::archive::wait_for_thumbnails();
+            if let Err(e) = result {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
         }
         None => {
             // GUI mode
+            if args.read_only {
+                pixery_lib::readonly::force();
+            }
             pixery_lib::run()
         }
     }
@@ -0,0 +1,161 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::archive;
+use crate::commands::AppState;
+use crate::db::Database;
+use crate::models::{MaintenanceReport, MaintenanceStatus, Reference};
+
+/// How often the background task runs
+pub const INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Completed/failed job records older than this are deleted
+const JOB_RETENTION_HOURS: i64 = 24;
+
+/// Trashed generations older than this are permanently deleted
+const TRASH_RETENTION_DAYS: i64 = 30;
+
+/// CLI preview files older than this are removed
+const PREVIEW_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+fn status_path() -> std::path::PathBuf {
+    archive::archive_root().join("maintenance.json")
+}
+
+/// Read the last persisted maintenance status (default if never run)
+pub fn load_status() -> MaintenanceStatus {
+    std::fs::read_to_string(status_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_status(status: &MaintenanceStatus) -> Result<()> {
+    std::fs::write(status_path(), serde_json::to_string_pretty(status)?)
+        .context("Failed to write maintenance status")
+}
+
+/// Run every maintenance step once. A step that fails doesn't stop the
+/// others, nor does one generation the trash purge can't delete; the errors
+/// are returned next to the counts of what did get done.
+pub fn run_once(db: &Database) -> (MaintenanceReport, Vec<String>) {
+    let mut errors = vec![];
+
+    let stalled_jobs = count(&mut errors, "Stalled jobs", db.cleanup_stalled_jobs());
+    let old_jobs = count(&mut errors, "Old jobs", db.cleanup_old_jobs(JOB_RETENTION_HOURS));
+
+    let cutoff = chrono::Local::now() - chrono::Duration::days(TRASH_RETENTION_DAYS);
+    let cutoff_str = cutoff.format("%Y-%m-%dT%H:%M:%S").to_string();
+    let purged = purge_trash(db, &cutoff_str, &mut errors);
+    let trash_purged = count(&mut errors, "Trash purge", purged);
+    let purged = db.purge_deleted_collections(&cutoff_str);
+    let collections_purged = count(&mut errors, "Collection purge", purged);
+
+    let previews_pruned = count(&mut errors, "Preview pruning", archive::prune_previews(PREVIEW_MAX_AGE));
+
+    // After the trash purge, so references only its generations used go now
+    let removed = collect_unused_references(db, false).map(|r| r.len());
+    let refs_removed = count(&mut errors, "Reference cleanup", removed);
+
+    let report = MaintenanceReport {
+        stalled_jobs,
+        old_jobs,
+        trash_purged,
+        collections_purged,
+        previews_pruned,
+        refs_removed,
+    };
+    (report, errors)
+}
+
+/// A step's count, or 0 with its error added to `errors`
+fn count(errors: &mut Vec<String>, step: &str, result: Result<usize>) -> usize {
+    result.unwrap_or_else(|e| {
+        errors.push(format!("{}: {:#}", step, e));
+        0
+    })
+}
+
+/// Permanently delete generations trashed before `cutoff`, returning how many
+/// were. One that can't be deleted is added to `errors` and the rest still go.
+fn purge_trash(db: &Database, cutoff: &str, errors: &mut Vec<String>) -> Result<usize> {
+    let mut purged = 0;
+    for (id, path) in db.list_trashed_before(cutoff)? {
+        let result = db
+            .permanently_delete_generation(id)
+            .and_then(|_| archive::delete_image(Path::new(&path)))
+            .and_then(|_| archive::delete_assets(id));
+        match result {
+            Ok(()) => purged += 1,
+            Err(e) => errors.push(format!("Trash purge of generation {}: {:#}", id, e)),
+        }
+    }
+    Ok(purged)
+}
+
+/// Delete reference images no generation links to (file and row). With
+/// `dry_run`, only reports them. Returns the references removed (or that would be).
+pub fn collect_unused_references(db: &Database, dry_run: bool) -> Result<Vec<Reference>> {
+    let unused = db.list_unused_references()?;
+    if dry_run {
+        return Ok(unused);
+    }
+    for reference in &unused {
+        let path = Path::new(&reference.path);
+        if path.exists() {
+            std::fs::remove_file(path)
+                .with_context(|| format!("Failed to delete reference {}", path.display()))?;
+        }
+        db.delete_reference(reference.id)?;
+    }
+    Ok(unused)
+}
+
+/// Run once and persist the outcome. Errors are recorded in the status rather
+/// than returned, so a failing step never kills the background loop.
+pub fn run_and_record(db: &Database) -> MaintenanceStatus {
+    let mut status = load_status();
+    status.last_run = Some(chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string());
+
+    let (report, errors) = run_once(db);
+    if report.stalled_jobs > 0 {
+        eprintln!("Cleaned up {} stalled jobs", report.stalled_jobs);
+    }
+    if report.old_jobs > 0 {
+        eprintln!("Cleaned up {} old completed/failed jobs", report.old_jobs);
+    }
+    if report.trash_purged > 0 {
+        eprintln!("Purged {} generations from trash", report.trash_purged);
+    }
+    if report.collections_purged > 0 {
+        eprintln!("Purged {} deleted collections", report.collections_purged);
+    }
+    if report.refs_removed > 0 {
+        eprintln!("Removed {} unused reference images", report.refs_removed);
+    }
+    for e in &errors {
+        eprintln!("Maintenance failed: {}", e);
+    }
+    status.last_report = Some(report);
+    status.last_error = (!errors.is_empty()).then(|| errors.join("; "));
+
+    if let Err(e) = save_status(&status) {
+        eprintln!("{}", e);
+    }
+    status
+}
+
+/// Starts the GUI maintenance loop: runs immediately, then every INTERVAL.
+pub fn start_background(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        {
+            let state = app.state::<AppState>();
+            if let Ok(db) = state.db.get() {
+                run_and_record(&db);
+            };
+        }
+        std::thread::sleep(INTERVAL);
+    });
+}
@@ -1,23 +1,26 @@
 use chrono::{Duration, Local, NaiveDate};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Supported image generation providers
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Provider {
     Gemini,
     Fal,
     OpenAI,
     SelfHosted,
+    ComfyUI,
 }
 
 impl std::fmt::Display for Provider {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Provider::Gemini => write!(f, "gemini"),
-            Provider::Fal => write!(f, "fal"),
-            Provider::OpenAI => write!(f, "openai"),
-            Provider::SelfHosted => write!(f, "selfhosted"),
+            Provider::Gemini => f.pad("gemini"),
+            Provider::Fal => f.pad("fal"),
+            Provider::OpenAI => f.pad("openai"),
+            Provider::SelfHosted => f.pad("selfhosted"),
+            Provider::ComfyUI => f.pad("comfyui"),
         }
     }
 }
@@ -31,6 +34,7 @@ impl std::str::FromStr for Provider {
             "fal" => Ok(Provider::Fal),
             "openai" => Ok(Provider::OpenAI),
             "selfhosted" => Ok(Provider::SelfHosted),
+            "comfyui" => Ok(Provider::ComfyUI),
             _ => Err(format!("Unknown provider: {}", s)),
         }
     }
@@ -56,6 +60,8 @@ pub struct PromptingGuide {
     pub structure: &'static str,
     pub tips: &'static str,
     pub avoid: Option<&'static str>,
+    /// Tags/words from `avoid` that `pixery lint` flags in prompts
+    pub avoid_tokens: &'static [&'static str],
     pub negative_template: Option<&'static str>,
     pub settings: Option<&'static str>,
     pub example: &'static str,
@@ -76,6 +82,7 @@ impl PromptingGuide {
 - Style cues: "faceted geometry", "flat cel shading", "Frutiger Aero palette"
 - Emotional tone: "optimistic melancholy", "gently futuristic""#,
                 avoid: Some("anime, screenshot, visual novel, cute"),
+                avoid_tokens: &["anime", "screenshot", "visual novel", "cute"],
                 negative_template: None,
                 settings: None,
                 example: r#"A young woman with short silver hair and distant eyes, as if hiding something beneath her cheerful exterior. She has the aesthetic of a Sega Dreamcast era 3D character - faceted geometry, flat cel shading, that pre-shader optimism. Her outfit suggests a near-future that never quite arrived: holographic accents on practical clothing. The background is a rain-slicked plaza at twilight, neon signs reflecting in puddles. Frutiger Aero color palette - teals, warm oranges, translucent whites."#,
@@ -120,6 +127,7 @@ LIMITATIONS:
   - Above 1536x1536 degrades quality (trained on SDXL resolution)
   - Style consistency requires explicit style tags"#,
                 avoid: Some("natural language, sentences, prose, text in images, resolutions above 1536x1536"),
+                avoid_tokens: &[],
                 negative_template: Some("lowres, bad anatomy, bad hands, text, error, missing finger, extra digits, fewer digits, cropped, worst quality, low quality, low score, bad score, average score, signature, watermark, username, blurry"),
                 settings: Some("CFG: 4-7 (5 recommended), Steps: 25-28, Sampler: Euler a"),
                 example: "1girl, solo, long silver hair, blue eyes, black hoodie, standing, smile, city background, night, year 2015, safe, masterpiece, high score, great score, absurdres",
@@ -140,6 +148,7 @@ SOURCE TAGS (style control):
   source_cartoon  - Western cartoon style
   source_furry    - furry art style
   source_pony     - MLP style
+  (the negative template excludes the non-anime sources)
 
 RATING TAGS:
   rating_safe, rating_questionable, rating_explicit
@@ -155,7 +164,8 @@ LIMITATIONS:
   - Sometimes generates pseudo-signatures hard to remove even with negatives
   - If signatures persist, try inpainting or V5.5"#,
                 avoid: Some("hd, masterpiece, best quality (score tags replace these)"),
-                negative_template: Some("source_cartoon, source_furry, source_pony (for anime style)"),
+                avoid_tokens: &["hd", "masterpiece", "best quality"],
+                negative_template: Some("source_cartoon, source_furry, source_pony"),
                 settings: Some("CFG: 7, Steps: 25, Sampler: Euler a, CLIP Skip: 2 (CRITICAL)"),
                 example: "score_9, score_8_up, score_7_up, score_6_up, score_5_up, score_4_up, source_anime, rating_safe, 1girl, silver hair, blue eyes, standing in rain, city night, neon lights reflecting on wet pavement",
             },
@@ -172,6 +182,7 @@ LIMITATIONS:
 - Default 40 inference steps; quality is high out of the box
 - Supports standard FLUX aspect ratios"#,
                 avoid: Some("including the trigger word manually (it's auto-prepended), overly technical HDR instructions (the model handles this)"),
+                avoid_tokens: &[],
                 negative_template: None,
                 settings: Some("Steps: 40 (default), LoRA Scale: 1.0 (default, range 0-2), Guidance: model default"),
                 example: "A weathered lighthouse on a rocky cliff at golden hour, waves crashing against the rocks below, dramatic clouds stretching across the sky",
@@ -193,6 +204,7 @@ LIMITATIONS:
 - All support seed for reproducibility
 - HEX color codes in prompt can guide palette (e.g. "background in #2D1B69")"#,
                 avoid: Some("booru tags (use prose instead), overly short prompts (more detail = better results)"),
+                avoid_tokens: &[],
                 negative_template: None,
                 settings: Some("Turbo: Steps 28, Guidance 2.5 (adjustable) | Pro/Max: no user-facing settings"),
                 example: "A cozy Japanese coffee shop interior at dusk, warm amber lighting from paper lanterns, a steaming cup of matcha latte on a worn wooden counter, rain visible through the window, soft bokeh lights from the street outside",
@@ -236,6 +248,7 @@ LIMITATIONS:
   - Requires Euler or DDIM only
   - Dynamic CFG 0.2 helps prevent oversaturation"#,
                 avoid: Some("Karras samplers, DPM++ Karras, resolutions far from 1M pixels"),
+                avoid_tokens: &[],
                 negative_template: Some("nsfw, worst quality, old, early, low quality, lowres, signature, username, logo, bad hands, mutated hands, mammal, anthro, furry, ambiguous form, feral, semi-anthro"),
                 settings: Some("CFG: 4-5, Steps: 28-35, Sampler: Euler (REQUIRED, no Karras), Dynamic CFG: 0.2 (optional)"),
                 example: "masterpiece, best quality, newest, absurdres, highres, safe, 1girl, silver hair, blue eyes, black jacket, standing on rooftop, city skyline, sunset, dramatic lighting, year 2023",
@@ -284,7 +297,7 @@ LIMITATIONS:
 
 impl ModelInfo {
     pub fn all() -> Vec<ModelInfo> {
-        vec![
+        let mut models = vec![
             // Gemini models - support multiple reference images
             ModelInfo {
                 id: "gemini-flash".into(),
@@ -300,7 +313,7 @@ impl ModelInfo {
                 cost_per_image: 0.134,
                 max_refs: 10,
             },
-            // fal.ai models - text-to-image only (no ref support)
+            // fal.ai models - text-to-image only except FLUX 2 Pro/Max (edit endpoints) and Z-Image
             ModelInfo {
                 id: "fal-ai/flux/schnell".into(),
                 provider: Provider::Fal,
@@ -341,14 +354,14 @@ impl ModelInfo {
                 provider: Provider::Fal,
                 display_name: "FLUX 2 Pro".into(),
                 cost_per_image: 0.03,
-                max_refs: 0,
+                max_refs: 4,
             },
             ModelInfo {
                 id: "flux2-max".into(),
                 provider: Provider::Fal,
                 display_name: "FLUX 2 Max".into(),
                 cost_per_image: 0.07,
-                max_refs: 0,
+                max_refs: 4,
             },
             ModelInfo {
                 id: "flux2-hdr".into(),
@@ -387,7 +400,15 @@ impl ModelInfo {
                 cost_per_image: 0.005,
                 max_refs: 1,
             },
-            // OpenAI models - text-to-image only
+            // FLUX.1 Fill [pro]: inpainting only (pixery inpaint), $0.05/MP
+            ModelInfo {
+                id: "flux-fill".into(),
+                provider: Provider::Fal,
+                display_name: "FLUX Fill (inpaint)".into(),
+                cost_per_image: 0.05,
+                max_refs: 0,
+            },
+            // OpenAI models - text-to-image; gpt-image-1 also inpaints via the edits endpoint
             ModelInfo {
                 id: "dall-e-3".into(),
                 provider: Provider::OpenAI,
@@ -403,33 +424,49 @@ impl ModelInfo {
                 max_refs: 0,
             },
             // Self-hosted models - requires SELFHOSTED_API_URL or GUI settings
-            // IP-Adapter supports 1 reference image
+            // IP-Adapter takes up to 4 reference images (embeddings are combined)
             ModelInfo {
                 id: "animagine".into(),
                 provider: Provider::SelfHosted,
                 display_name: "Animagine XL 4.0 (Local)".into(),
                 cost_per_image: 0.0,
-                max_refs: 1,
+                max_refs: 4,
             },
             ModelInfo {
                 id: "pony".into(),
                 provider: Provider::SelfHosted,
                 display_name: "Pony Diffusion V6 (Local)".into(),
                 cost_per_image: 0.0,
-                max_refs: 1,
+                max_refs: 4,
             },
             ModelInfo {
                 id: "noobai".into(),
                 provider: Provider::SelfHosted,
                 display_name: "NoobAI XL (Local)".into(),
                 cost_per_image: 0.0,
-                max_refs: 1,
+                max_refs: 4,
             },
-        ]
+        ];
+        // ComfyUI models come from workflow templates on disk
+        models.extend(crate::providers::comfyui::template_models());
+        // fal.ai endpoints cached by `pixery models --refresh`
+        let catalog = crate::providers::fal::catalog_models(&models);
+        models.extend(catalog);
+        // User-defined models from models.json
+        crate::custom_models::load().merge_into(&mut models);
+        models
     }
 
+    /// Look up a model by ID or alias
     pub fn find(model_id: &str) -> Option<ModelInfo> {
-        Self::all().into_iter().find(|m| m.id == model_id)
+        let models = Self::all();
+        let id = crate::custom_models::load().resolve_alias(model_id, &models);
+        models.into_iter().find(|m| m.id == id)
+    }
+
+    /// Model ID with any models.json alias resolved; unknown names pass through
+    pub fn canonical_id(model: &str) -> String {
+        crate::custom_models::load().resolve_alias(model, &Self::all())
     }
 
     pub fn provider_for_model(model_id: &str) -> Option<Provider> {
@@ -461,9 +498,42 @@ pub struct Generation {
     pub trashed_at: Option<String>,
     pub title: Option<String>,
     pub negative_prompt: Option<String>,
+    /// Finalized deliverables are locked against edits, trash and delete
+    pub locked: bool,
+    /// Auto-generated description + keywords from a vision model
+    pub caption: Option<String>,
+    /// Who asked for it when it came through an integration (`discord:<user>`)
+    pub requested_by: Option<String>,
+    /// The resolved GenerationRequest it was made from (model aliases
+    /// expanded, auto negative/title applied, references as stored); None for
+    /// imports and generations from before it was recorded
+    pub params: Option<serde_json::Value>,
+    /// Image, or an MP4 clip from `pixery video` (its thumbnails are of the first frame)
+    pub media_type: MediaType,
+    /// Clip length and frame rate; None for images
+    pub duration_seconds: Option<f64>,
+    pub fps: Option<f64>,
+    /// Animated GIF, WebP or APNG, archived byte-for-byte
+    pub animated: bool,
+    /// Flagged by hand, from a rating tag in the prompt, or by the classifier
+    /// (see `nsfw`); hidden from list, search and the gallery unless shown
+    #[serde(default)]
+    pub nsfw: bool,
     pub tags: Vec<String>,
     pub references: Vec<Reference>,
     pub collection_names: Vec<String>,
+    /// Dominant colors, largest share first; empty until extracted
+    #[serde(default)]
+    pub palette: Vec<Swatch>,
+}
+
+/// One color of a generation's palette (see `palette`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Swatch {
+    /// "#rrggbb"
+    pub hex: String,
+    /// Fraction of the image's pixels closest to this color
+    pub share: f64,
 }
 
 /// Parameters for generating a new image
@@ -477,6 +547,75 @@ pub struct GenerateParams {
     pub negative_prompt: Option<String>,
     pub width: Option<i32>,
     pub height: Option<i32>,
+    /// Preset whose settings fill in anything left unset
+    #[serde(default)]
+    pub preset: Option<String>,
+    #[serde(default)]
+    pub transparent: bool,
+}
+
+/// A model's price from a date on (`pixery pricing`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceEntry {
+    pub id: i64,
+    pub model: String,
+    /// USD per image, or per second for video models
+    pub cost: f64,
+    /// YYYY-MM-DD
+    pub effective_from: String,
+    pub note: Option<String>,
+    pub created_at: String,
+}
+
+/// Named bundle of generation settings (`pixery gen --preset`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub id: i64,
+    pub name: String,
+    pub model: String,
+    pub negative_prompt: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub cfg_scale: Option<f64>,
+    pub tags: Vec<String>,
+    pub created_at: String,
+}
+
+/// A preset's settings as given to `Database::save_preset`
+#[derive(Debug, Clone, Default)]
+pub struct NewPreset {
+    pub name: String,
+    pub model: String,
+    pub negative_prompt: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub cfg_scale: Option<f64>,
+    pub tags: Vec<String>,
+}
+
+impl Preset {
+    /// Fill request fields the caller left unset (empty model included) and
+    /// merge the preset's tags. Explicit values always win.
+    pub fn apply(&self, req: &mut GenerationRequest, tags: &mut Vec<String>) {
+        if req.model.is_empty() {
+            req.model = self.model.clone();
+        }
+        if req.negative_prompt.is_none() {
+            req.negative_prompt = self.negative_prompt.clone();
+        }
+        if req.width.is_none() && req.height.is_none() {
+            req.width = self.width;
+            req.height = self.height;
+        }
+        if req.cfg_scale.is_none() {
+            req.cfg_scale = self.cfg_scale;
+        }
+        for tag in &self.tags {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+    }
 }
 
 /// Reference image (deduplicated by hash)
@@ -488,20 +627,333 @@ pub struct Reference {
     pub created_at: String,
 }
 
+/// Stored reference with the number of generations that used it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceInfo {
+    pub id: i64,
+    pub hash: String,
+    pub path: String,
+    pub created_at: String,
+    pub use_count: i64,
+}
+
+/// What a generation's extra file is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetKind {
+    /// An edited version, e.g. a layered PSD export
+    Edit,
+    Upscale,
+    Crop,
+    #[default]
+    Other,
+}
+
+impl std::fmt::Display for AssetKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetKind::Edit => write!(f, "edit"),
+            AssetKind::Upscale => write!(f, "upscale"),
+            AssetKind::Crop => write!(f, "crop"),
+            AssetKind::Other => write!(f, "other"),
+        }
+    }
+}
+
+impl std::str::FromStr for AssetKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "edit" => Ok(AssetKind::Edit),
+            "upscale" => Ok(AssetKind::Upscale),
+            "crop" => Ok(AssetKind::Crop),
+            "other" => Ok(AssetKind::Other),
+            _ => Err(format!("Unknown asset kind: {} (expected edit, upscale, crop or other)", s)),
+        }
+    }
+}
+
+/// A file a generation owns besides its image (`pixery assets`), kept in the
+/// archive's assets/{generation id}/ directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Asset {
+    pub id: i64,
+    pub generation_id: i64,
+    pub kind: AssetKind,
+    pub path: String,
+    pub file_size: Option<i64>,
+    pub note: Option<String>,
+    pub created_at: String,
+}
+
 /// Tag with usage count
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TagCount {
     pub name: String,
     pub count: i64,
+    /// "character" for "character:kira"
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+/// Namespace of a tag like "character:kira" or "style:cel-shading": a plain
+/// word before the first colon. Prompt fragments such as "(detailed:1.2)"
+/// don't count.
+pub fn tag_namespace(tag: &str) -> Option<&str> {
+    let (namespace, rest) = tag.split_once(':')?;
+    let plain = namespace
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    (plain && !namespace.is_empty() && !rest.is_empty()).then_some(namespace)
+}
+
+/// Namespace queried by a wildcard tag filter ("character:*")
+pub fn tag_wildcard(tag: &str) -> Option<&str> {
+    tag.strip_suffix(":*").filter(|ns| !ns.is_empty())
+}
+
+/// Outcome of a bulk tag change over a filter
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BulkTagReport {
+    /// Generations matching the filter
+    pub matched: i64,
+    /// Tag links created (a generation that already had the tag doesn't count)
+    pub added: i64,
+    /// Tag links removed
+    pub removed: i64,
+    /// Matching generations left untouched by removals because they're locked
+    pub locked: i64,
+}
+
+/// What to do with an incoming image that's byte-identical to an archived one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateAction {
+    /// Keep the archived generation as it is
+    Skip,
+    /// Keep the archived generation, adding the incoming tags and references to it
+    Link,
+    /// Archive it again as a separate generation
+    #[default]
+    Import,
+}
+
+impl std::str::FromStr for DuplicateAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(DuplicateAction::Skip),
+            "link" => Ok(DuplicateAction::Link),
+            "import" => Ok(DuplicateAction::Import),
+            _ => Err(format!("Unknown duplicate action: {} (expected skip, link or import)", s)),
+        }
+    }
+}
+
+/// A filter value and how many generations it would match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FacetCount {
+    pub name: String,
+    pub count: i64,
 }
 
-/// Cost summary
+/// Per-value result counts under the current filter, for filter chips.
+/// Tag counts are for adding that tag; model and collection counts are for
+/// switching to that model/collection (their own selection is ignored).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterFacets {
+    pub total: i64,
+    pub tags: Vec<FacetCount>,
+    pub models: Vec<FacetCount>,
+    pub collections: Vec<FacetCount>,
+}
+
+/// One page of list results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationPage {
+    pub items: Vec<Generation>,
+    /// Cursor for the next page; None on the last page
+    pub next_cursor: Option<String>,
+    /// Total matches for the filter across all pages
+    pub total: i64,
+}
+
+/// The currency costs are shown in (currency.json): USD amounts times `rate`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisplayCurrency {
+    /// ISO 4217 code
+    pub code: String,
+    /// Units of `code` per USD
+    pub rate: f64,
+    /// "manual", "fetched YYYY-MM-DD", or "" for USD
+    pub source: String,
+}
+
+impl Default for DisplayCurrency {
+    fn default() -> Self {
+        DisplayCurrency { code: "USD".to_string(), rate: 1.0, source: String::new() }
+    }
+}
+
+impl DisplayCurrency {
+    pub fn is_usd(&self) -> bool {
+        self.code == "USD"
+    }
+
+    pub fn convert(&self, usd: f64) -> f64 {
+        usd * self.rate
+    }
+
+    /// "$1.23", "€1.23", or "1.23 CHF" for currencies without a common symbol
+    pub fn format(&self, usd: f64, decimals: usize) -> String {
+        let amount = self.convert(usd);
+        let symbol = match self.code.as_str() {
+            "USD" => "$",
+            "EUR" => "€",
+            "GBP" => "£",
+            "JPY" => "¥",
+            _ => return format!("{:.*} {}", decimals, amount, self.code),
+        };
+        format!("{}{:.*}", symbol, decimals, amount)
+    }
+}
+
+/// Cost summary. Amounts are USD; `currency` is how to show them.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CostSummary {
     pub total_usd: f64,
     pub by_model: Vec<(String, f64)>,
     pub by_day: Vec<(String, f64)>,
+    /// Spend per collection; a generation in several collections counts toward each
+    pub by_collection: Vec<(String, f64)>,
+    /// Spend per tag (top 30); a generation counts toward each of its tags
+    pub by_tag: Vec<(String, f64)>,
     pub count: i64,
+    #[serde(default)]
+    pub currency: DisplayCurrency,
+}
+
+/// One generation's spend, for cost reports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostRow {
+    pub id: i64,
+    pub date: String,
+    pub model: String,
+    pub provider: String,
+    pub cost_usd: Option<f64>,
+    pub prompt: String,
+}
+
+/// Generation count and spend for one day or week
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodStat {
+    /// YYYY-MM-DD for days, YYYY-Www for weeks
+    pub period: String,
+    pub count: i64,
+    pub cost_usd: f64,
+}
+
+/// Per-model usage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelStat {
+    pub model: String,
+    pub count: i64,
+    pub cost_usd: f64,
+    pub avg_generation_seconds: Option<f64>,
+}
+
+/// Generation time percentiles for one model or one hour of the day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyStat {
+    /// Model ID, or the hour as "00".."23" (local time)
+    pub group: String,
+    pub count: usize,
+    pub p50_seconds: f64,
+    pub p95_seconds: f64,
+}
+
+/// `pixery stats latency`: how long generations take, by model and by the
+/// hour they ran
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyStats {
+    /// Fastest median first
+    pub by_model: Vec<LatencyStat>,
+    /// Hours with generations, in order
+    pub by_hour: Vec<LatencyStat>,
+}
+
+impl LatencyStat {
+    /// Nearest-rank percentiles of `seconds`, which must be sorted and non-empty
+    fn from_sorted(group: String, seconds: &[f64]) -> Self {
+        let rank = |p: f64| seconds[((p * seconds.len() as f64).ceil() as usize).max(1) - 1];
+        LatencyStat { group, count: seconds.len(), p50_seconds: rank(0.5), p95_seconds: rank(0.95) }
+    }
+}
+
+impl LatencyStats {
+    /// From (model, hour, seconds) samples
+    pub fn from_samples(samples: &[(String, String, f64)]) -> Self {
+        let mut by_model = latency_groups(samples.iter().map(|(model, _, s)| (model, *s)));
+        by_model.sort_by(|a, b| a.p50_seconds.total_cmp(&b.p50_seconds));
+        let by_hour = latency_groups(samples.iter().map(|(_, hour, s)| (hour, *s)));
+        LatencyStats { by_model, by_hour }
+    }
+}
+
+/// Percentiles per group, in group order
+fn latency_groups<'a>(samples: impl Iterator<Item = (&'a String, f64)>) -> Vec<LatencyStat> {
+    let mut groups: BTreeMap<&String, Vec<f64>> = BTreeMap::new();
+    for (group, seconds) in samples {
+        groups.entry(group).or_default().push(seconds);
+    }
+    groups
+        .into_iter()
+        .map(|(group, mut seconds)| {
+            seconds.sort_by(f64::total_cmp);
+            LatencyStat::from_sorted(group.clone(), &seconds)
+        })
+        .collect()
+}
+
+/// Aggregates for the statistics dashboard and `pixery stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stats {
+    pub total_generations: i64,
+    pub total_cost_usd: f64,
+    /// Oldest first, for charting
+    pub per_day: Vec<PeriodStat>,
+    pub per_week: Vec<PeriodStat>,
+    pub jobs_completed: i64,
+    pub jobs_failed: i64,
+    /// completed / (completed + failed); None when no jobs finished
+    pub success_rate: Option<f64>,
+    pub by_model: Vec<ModelStat>,
+    pub top_tags: Vec<TagCount>,
+}
+
+/// Counts from a single maintenance pass
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    pub stalled_jobs: usize,
+    pub old_jobs: usize,
+    pub trash_purged: usize,
+    /// Deleted collections dropped for good (missing from older status files)
+    #[serde(default)]
+    pub collections_purged: usize,
+    pub previews_pruned: usize,
+    /// Unused reference images removed (missing from status files written before GC existed)
+    #[serde(default)]
+    pub refs_removed: usize,
+}
+
+/// Last maintenance run, persisted so CLI and GUI see the same status
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaintenanceStatus {
+    pub last_run: Option<String>,
+    pub last_report: Option<MaintenanceReport>,
+    pub last_error: Option<String>,
 }
 
 /// Query filters for listing generations
@@ -509,27 +961,415 @@ pub struct CostSummary {
 pub struct ListFilter {
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    /// Keyset cursor from GenerationPage::next_cursor; continues after that row
+    pub cursor: Option<String>,
     pub tags: Option<Vec<String>>,
     pub exclude_tags: Option<Vec<String>>,
     pub model: Option<String>,
     pub starred_only: bool,
+    /// Leave out NSFW-flagged generations (list, search and the gallery set
+    /// this unless NSFW is shown)
+    #[serde(default)]
+    pub hide_nsfw: bool,
+    /// Only NSFW-flagged generations
+    #[serde(default)]
+    pub nsfw_only: bool,
     pub search: Option<String>,
     pub since: Option<String>,
+    /// Last date included (YYYY-MM-DD)
+    pub until: Option<String>,
+    pub min_width: Option<i32>,
+    pub min_height: Option<i32>,
+    pub orientation: Option<Orientation>,
+    /// Only generations made with reference images
+    #[serde(default)]
+    pub has_reference: bool,
+    /// Sort order; None is newest first
+    pub sort: Option<SortKey>,
+    /// Descending order for `sort` (ignored when sort is None or random)
+    #[serde(default)]
+    pub sort_desc: bool,
     pub collection_id: Option<i64>,
+    /// Also apply this smart collection's stored filter
+    #[serde(default)]
+    pub smart_collection_id: Option<i64>,
+    /// Only generations from this experiment (`pixery ab`, `pixery batch`)
+    #[serde(default)]
+    pub experiment_id: Option<i64>,
+    /// Only images or only videos
+    #[serde(default)]
+    pub media_type: Option<MediaType>,
+    /// Only generations whose palette has a color near each of these ("#ff6600")
+    #[serde(default)]
+    pub colors: Option<Vec<String>>,
+    /// How near, as a CIE76 color difference; None is `palette::DEFAULT_TOLERANCE`
+    #[serde(default)]
+    pub color_tolerance: Option<f64>,
     pub show_trashed: bool,
     pub uncategorized: bool,
 }
 
+impl ListFilter {
+    /// Parse a filter expression: terms joined by AND or commas, e.g.
+    /// `model=noobai AND tag=character AND starred`. Relative dates (since=7d)
+    /// resolve when parsed, which is why smart collections store the expression.
+    pub fn parse_expr(expr: &str) -> Result<ListFilter, String> {
+        let mut filter = ListFilter::default();
+        let terms: Vec<&str> = expr
+            .split(" AND ")
+            .flat_map(|t| t.split(" and "))
+            .flat_map(|t| t.split(','))
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .collect();
+        if terms.is_empty() {
+            return Err("Empty filter expression".to_string());
+        }
+
+        for term in terms {
+            let (key, value) = match term.split_once("!=") {
+                Some((key, value)) => (format!("-{}", key.trim()), value.trim()),
+                None => match term.split_once('=') {
+                    Some((key, value)) => (key.trim().to_string(), value.trim()),
+                    None => (term.to_string(), ""),
+                },
+            };
+            let number = |v: &str| v.parse::<i32>().map_err(|_| format!("Expected a number in '{}'", term));
+            match (key.to_lowercase().as_str(), value.is_empty()) {
+                ("starred", true) => filter.starred_only = true,
+                ("nsfw", true) => filter.nsfw_only = true,
+                ("sfw", true) => filter.hide_nsfw = true,
+                ("has-reference" | "has_reference", true) => filter.has_reference = true,
+                ("model", false) => filter.model = Some(value.to_string()),
+                ("tag", false) => filter.tags.get_or_insert_with(Vec::new).push(value.to_string()),
+                ("-tag", false) => filter.exclude_tags.get_or_insert_with(Vec::new).push(value.to_string()),
+                ("search", false) => filter.search = Some(value.to_string()),
+                ("since", false) => filter.since = parse_since(value)?,
+                ("until", false) => filter.until = parse_since(value)?,
+                ("min-width" | "min_width", false) => filter.min_width = Some(number(value)?),
+                ("min-height" | "min_height", false) => filter.min_height = Some(number(value)?),
+                ("orientation", false) => filter.orientation = Some(value.parse()?),
+                ("media", false) => filter.media_type = Some(value.parse()?),
+                ("color", false) => {
+                    let color: crate::palette::Color = value.parse()?;
+                    filter.colors.get_or_insert_with(Vec::new).push(color.to_string());
+                }
+                ("color-tolerance" | "color_tolerance", false) => {
+                    let tolerance = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|t| *t >= 0.0)
+                        .ok_or_else(|| format!("Expected a non-negative number in '{}'", term))?;
+                    filter.color_tolerance = Some(tolerance);
+                }
+                _ => {
+                    return Err(format!(
+                        "Unknown filter term: {} (expected model=, tag=, tag!=, search=, since=, until=, \
+                         min-width=, min-height=, orientation=, media=, color=, color-tolerance=, starred, \
+                         nsfw, sfw or has-reference)",
+                        term
+                    ))
+                }
+            }
+        }
+        Ok(filter)
+    }
+}
+
+/// List sort keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    Timestamp,
+    Cost,
+    GenerationTime,
+    FileSize,
+    /// Starred first (or last), newest first within each group
+    #[serde(alias = "rating")]
+    Starred,
+    Random,
+    /// A collection's user-defined order (requires collection_id)
+    Position,
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "timestamp" | "date" => Ok(SortKey::Timestamp),
+            "cost" => Ok(SortKey::Cost),
+            "generation_time" | "time" => Ok(SortKey::GenerationTime),
+            "file_size" | "size" => Ok(SortKey::FileSize),
+            "starred" | "rating" => Ok(SortKey::Starred),
+            "random" => Ok(SortKey::Random),
+            "position" | "collection" => Ok(SortKey::Position),
+            _ => Err(format!(
+                "Unknown sort key: {} (expected timestamp, cost, generation-time, file-size, starred, random, position)",
+                s
+            )),
+        }
+    }
+}
+
+/// Image shape, for filtering by dimensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+    Square,
+}
+
+impl std::str::FromStr for Orientation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "portrait" => Ok(Orientation::Portrait),
+            "landscape" => Ok(Orientation::Landscape),
+            "square" => Ok(Orientation::Square),
+            _ => Err(format!("Unknown orientation: {} (expected portrait, landscape or square)", s)),
+        }
+    }
+}
+
+/// What a generation's file holds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    #[default]
+    Image,
+    Video,
+}
+
+impl std::fmt::Display for MediaType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MediaType::Image => write!(f, "image"),
+            MediaType::Video => write!(f, "video"),
+        }
+    }
+}
+
+impl std::str::FromStr for MediaType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "image" => Ok(MediaType::Image),
+            "video" => Ok(MediaType::Video),
+            _ => Err(format!("Unknown media type: {} (expected image or video)", s)),
+        }
+    }
+}
+
+/// Everything a provider receives for one generation.
+/// Providers ignore fields their API doesn't support.
+/// Stored as JSON on the job so `pixery jobs retry` can replay it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationRequest {
+    pub model: String,
+    pub prompt: String,
+    pub reference_paths: Vec<String>,
+    pub negative_prompt: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    /// IP-Adapter scale (self-hosted only)
+    pub ip_scale: Option<f64>,
+    /// CFG / guidance scale (self-hosted only)
+    pub cfg_scale: Option<f64>,
+    /// Use the model's prompting-guide negative template if no negative prompt is set
+    pub auto_negative: bool,
+    /// Images per API call (fal.ai up to 4, gpt-image-1 up to 10); None means one.
+    /// Other providers always return one image.
+    pub num_images: Option<u32>,
+    /// Source image and mask for inpainting; providers without mask support reject it
+    pub inpaint: Option<Inpaint>,
+    /// Generation this one derives from (inpaint, extend, fix), stored as parent_id
+    pub parent_id: Option<i64>,
+    /// Title given to the result(s)
+    pub title: Option<String>,
+    /// Derive a title from the prompt when none is set (see `workflow::apply_auto_title`)
+    pub auto_title: bool,
+    /// Fixed seed for providers that take one (fal.ai, self-hosted, ComfyUI);
+    /// None lets the provider pick
+    pub seed: Option<u64>,
+    /// Transparent background, for compositing: gpt-image-1 (and fal endpoints
+    /// that support it) make one themselves, other models' results go through
+    /// the self-hosted /rembg. Tagged `transparent` when the result has alpha.
+    pub transparent: bool,
+}
+
+/// Inpainting input. Mask convention: white = regenerate, black = keep
+/// (providers that expect something else convert it).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Inpaint {
+    pub image_path: String,
+    pub mask_path: String,
+    /// How far the masked area may drift from the source, 0-1 (self-hosted only;
+    /// None repaints it almost entirely)
+    pub strength: Option<f64>,
+}
+
+impl From<&GenerateParams> for GenerationRequest {
+    fn from(params: &GenerateParams) -> Self {
+        GenerationRequest {
+            model: params.model.clone(),
+            prompt: params.prompt.clone(),
+            reference_paths: params.reference_paths.clone(),
+            negative_prompt: params.negative_prompt.clone(),
+            width: params.width,
+            height: params.height,
+            ip_scale: None, // GUI doesn't expose this yet
+            cfg_scale: None,
+            auto_negative: false, // generation.json default still applies
+            num_images: None,
+            inpaint: None,
+            parent_id: None,
+            title: None,
+            auto_title: false, // generation.json default still applies
+            seed: None,
+            transparent: params.transparent,
+        }
+    }
+}
+
+/// One image a generation saved (`workflow::complete_generation`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Generated {
+    pub id: i64,
+    pub generation: Generation,
+    /// The image was already archived as this generation: `id` is that one,
+    /// unless `on_duplicate` is import and a copy was saved anyway
+    pub duplicate_of: Option<i64>,
+}
+
 /// Result of image generation from a provider
 #[derive(Debug)]
 pub struct GenerationResult {
-    pub image_data: Vec<u8>,
+    /// One entry per returned image; always at least one
+    pub images: Vec<Vec<u8>>,
+    pub seed: Option<String>,
+    pub generation_time_seconds: f64,
+    /// Actual cost from API (token-based) for the whole call, if available.
+    /// Takes precedence over estimate; split evenly across the images.
+    pub cost_usd: Option<f64>,
+}
+
+/// A text-to-video model (`pixery video`). Kept apart from ModelInfo: a
+/// video model can't stand in for an image one, and clips are billed per second.
+#[derive(Debug, Clone, Serialize)]
+pub struct VideoModelInfo {
+    pub id: &'static str,
+    pub provider: Provider,
+    pub display_name: &'static str,
+    pub cost_per_second: f64,
+    /// Clip lengths the API accepts, in seconds; the first is the default
+    pub durations: &'static [u32],
+    /// Aspect ratios the API accepts; the first is the default
+    pub aspect_ratios: &'static [&'static str],
+    /// Accepts a start frame (image-to-video)
+    pub image_to_video: bool,
+}
+
+impl VideoModelInfo {
+    /// Pricing (as of Jan 2026): Kling 2.1 Master $1.40 per 5s clip on fal.ai,
+    /// Veo 3 $0.40/s and Veo 3 Fast $0.15/s (with audio) on the Gemini API
+    pub fn all() -> Vec<VideoModelInfo> {
+        vec![
+            VideoModelInfo {
+                id: "kling",
+                provider: Provider::Fal,
+                display_name: "Kling 2.1 Master",
+                cost_per_second: 0.28,
+                durations: &[5, 10],
+                aspect_ratios: &["16:9", "9:16", "1:1"],
+                image_to_video: true,
+            },
+            VideoModelInfo {
+                id: "veo3",
+                provider: Provider::Gemini,
+                display_name: "Veo 3",
+                cost_per_second: 0.40,
+                durations: &[8],
+                aspect_ratios: &["16:9", "9:16"],
+                image_to_video: true,
+            },
+            VideoModelInfo {
+                id: "veo3-fast",
+                provider: Provider::Gemini,
+                display_name: "Veo 3 Fast",
+                cost_per_second: 0.15,
+                durations: &[8],
+                aspect_ratios: &["16:9", "9:16"],
+                image_to_video: true,
+            },
+        ]
+    }
+
+    pub fn find(id: &str) -> Option<VideoModelInfo> {
+        Self::all().into_iter().find(|m| m.id == id)
+    }
+
+    /// Cost of a clip of `duration` seconds (the default length when None)
+    pub fn estimate_cost(&self, duration: Option<u32>) -> f64 {
+        self.cost_per_second * duration.unwrap_or(self.durations[0]) as f64
+    }
+}
+
+/// Everything a provider receives for one video clip
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoRequest {
+    pub model: String,
+    pub prompt: String,
+    /// Start frame for image-to-video
+    pub image_path: Option<String>,
+    pub negative_prompt: Option<String>,
+    /// Seconds, one of the model's `durations`; None for its default
+    pub duration: Option<u32>,
+    /// One of the model's `aspect_ratios` ("16:9"); None for its default
+    pub aspect_ratio: Option<String>,
+    pub seed: Option<u64>,
+    /// Generation the start frame came from, stored as parent_id
+    pub parent_id: Option<i64>,
+}
+
+/// Result of video generation from a provider
+#[derive(Debug)]
+pub struct VideoResult {
+    /// MP4 bytes
+    pub video: Vec<u8>,
     pub seed: Option<String>,
     pub generation_time_seconds: f64,
-    /// Actual cost from API (token-based), if available. Takes precedence over estimate.
     pub cost_usd: Option<f64>,
 }
 
+/// Stage of an in-flight generation, streamed to the GUI as it happens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum ProgressStage {
+    Queued,
+    RequestSent,
+    /// Waiting in the provider's queue (fal.ai reports position)
+    InQueue { position: Option<u32> },
+    /// Denoising step reported by the provider (self-hosted job queue)
+    Step { step: u32, total: u32 },
+    Downloading,
+    Saving,
+    ThumbnailDone,
+}
+
+/// Payload of the `generation-progress` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationProgress {
+    pub job_id: i64,
+    #[serde(flatten)]
+    pub stage: ProgressStage,
+}
+
 /// Job status for generation tracking
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -538,6 +1378,7 @@ pub enum JobStatus {
     Running,
     Completed,
     Failed,
+    Cancelled,
 }
 
 impl std::fmt::Display for JobStatus {
@@ -547,6 +1388,7 @@ impl std::fmt::Display for JobStatus {
             JobStatus::Running => write!(f, "running"),
             JobStatus::Completed => write!(f, "completed"),
             JobStatus::Failed => write!(f, "failed"),
+            JobStatus::Cancelled => write!(f, "cancelled"),
         }
     }
 }
@@ -560,6 +1402,7 @@ impl std::str::FromStr for JobStatus {
             "running" => Ok(JobStatus::Running),
             "completed" => Ok(JobStatus::Completed),
             "failed" => Ok(JobStatus::Failed),
+            "cancelled" => Ok(JobStatus::Cancelled),
             _ => Err(format!("Unknown job status: {}", s)),
         }
     }
@@ -571,6 +1414,7 @@ impl std::str::FromStr for JobStatus {
 pub enum JobSource {
     Cli,
     Gui,
+    Schedule,
 }
 
 impl std::fmt::Display for JobSource {
@@ -578,6 +1422,7 @@ impl std::fmt::Display for JobSource {
         match self {
             JobSource::Cli => write!(f, "cli"),
             JobSource::Gui => write!(f, "gui"),
+            JobSource::Schedule => write!(f, "schedule"),
         }
     }
 }
@@ -589,11 +1434,154 @@ impl std::str::FromStr for JobSource {
         match s.to_lowercase().as_str() {
             "cli" => Ok(JobSource::Cli),
             "gui" => Ok(JobSource::Gui),
+            "schedule" => Ok(JobSource::Schedule),
             _ => Err(format!("Unknown job source: {}", s)),
         }
     }
 }
 
+/// A recurring generation fired on a cron expression
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: i64,
+    /// Unique name, also added as a tag on every generation it fires
+    pub name: String,
+    /// Cron expression (5-field `min hour dom mon dow`, or 6-field with seconds)
+    pub cron: String,
+    pub prompt: String,
+    pub model: String,
+    pub tags: Vec<String>,
+    pub negative_prompt: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub enabled: bool,
+    pub last_run_at: Option<String>,
+    pub created_at: String,
+}
+
+/// A schedule's settings as given to `Database::create_schedule`
+#[derive(Debug, Clone, Default)]
+pub struct NewSchedule {
+    pub name: String,
+    pub cron: String,
+    pub prompt: String,
+    pub model: String,
+    pub tags: Vec<String>,
+    pub negative_prompt: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+}
+
+/// A systematic run (`pixery ab`, `pixery batch`) and the generations it produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Experiment {
+    pub id: i64,
+    /// `<kind>-YYYYMMDD-HHMMSS`, as printed when the run starts
+    pub name: String,
+    /// Command that ran it: "ab" or "batch"
+    pub kind: String,
+    pub prompt: String,
+    /// Run parameters as given (models, count, ratio, references, ...)
+    pub params: serde_json::Value,
+    pub created_at: String,
+    /// Non-trashed generations from the run
+    pub count: i64,
+    pub total_cost: f64,
+}
+
+/// A change recorded in the activity log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventAction {
+    Generate,
+    Star,
+    Unstar,
+    Tag,
+    Untag,
+    Trash,
+    Restore,
+    Delete,
+    EditPrompt,
+    EditTitle,
+    CollectionAdd,
+    CollectionRemove,
+}
+
+impl EventAction {
+    /// Changes `pixery undo` takes back: the ones that lose something
+    /// (a tag, text, a place in a collection) or hide an image
+    pub fn is_destructive(self) -> bool {
+        matches!(
+            self,
+            EventAction::Untag
+                | EventAction::Trash
+                | EventAction::EditPrompt
+                | EventAction::EditTitle
+                | EventAction::CollectionAdd
+                | EventAction::CollectionRemove
+        )
+    }
+}
+
+impl std::fmt::Display for EventAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            EventAction::Generate => "generate",
+            EventAction::Star => "star",
+            EventAction::Unstar => "unstar",
+            EventAction::Tag => "tag",
+            EventAction::Untag => "untag",
+            EventAction::Trash => "trash",
+            EventAction::Restore => "restore",
+            EventAction::Delete => "delete",
+            EventAction::EditPrompt => "edit_prompt",
+            EventAction::EditTitle => "edit_title",
+            EventAction::CollectionAdd => "collection_add",
+            EventAction::CollectionRemove => "collection_remove",
+        };
+        f.pad(name)
+    }
+}
+
+impl std::str::FromStr for EventAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "generate" => Ok(EventAction::Generate),
+            "star" => Ok(EventAction::Star),
+            "unstar" => Ok(EventAction::Unstar),
+            "tag" => Ok(EventAction::Tag),
+            "untag" => Ok(EventAction::Untag),
+            "trash" => Ok(EventAction::Trash),
+            "restore" => Ok(EventAction::Restore),
+            "delete" => Ok(EventAction::Delete),
+            "edit_prompt" => Ok(EventAction::EditPrompt),
+            "edit_title" => Ok(EventAction::EditTitle),
+            "collection_add" => Ok(EventAction::CollectionAdd),
+            "collection_remove" => Ok(EventAction::CollectionRemove),
+            _ => Err(format!("Unknown event action: {}", s)),
+        }
+    }
+}
+
+/// One entry of the activity log (`pixery activity`, the GUI's recent changes)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub id: i64,
+    /// Kept after the generation is deleted, so its history still reads
+    pub generation_id: i64,
+    pub action: EventAction,
+    /// What undo needs: the tags added or removed, a field's old and new
+    /// value, the collection
+    pub detail: serde_json::Value,
+    pub created_at: String,
+    /// Set on the events an undo made: the event it reversed
+    pub undo_of: Option<i64>,
+    /// Reversed by a later undo
+    pub undone: bool,
+}
+
 /// A generation job record for tracking in-flight generations
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Job {
@@ -609,6 +1597,97 @@ pub struct Job {
     pub completed_at: Option<String>,
     pub generation_id: Option<i64>,
     pub error: Option<String>,
+    /// Transient provider failures retried before success/failure
+    pub retry_count: i32,
+    /// Job that re-ran this one (`jobs retry`, `retry-failed`)
+    pub retry_job_id: Option<i64>,
+    /// Why a failed job failed, as a `PixeryError` code ("content_policy",
+    /// "rate_limited", ...); None for jobs failed before it was recorded
+    pub failure: Option<String>,
+}
+
+/// A model's daily job quota (models.json "quotas") and how much of it is used today
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelQuota {
+    pub model: String,
+    pub limit: u32,
+    /// Jobs started today that are running or completed (failed and cancelled
+    /// ones don't count)
+    pub used: u32,
+    pub remaining: u32,
+}
+
+/// Outcome of re-running failed jobs (`pixery retry-failed`)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RetryReport {
+    /// (original job ID, new generation ID)
+    pub retried: Vec<(i64, i64)>,
+    /// (original job ID, error) for jobs that failed again
+    pub failed: Vec<(i64, String)>,
+    /// Jobs with no stored request to replay
+    pub skipped: Vec<i64>,
+    /// Jobs the provider refused under its content policy; retrying the same
+    /// prompt would be refused again
+    pub blocked: Vec<i64>,
+    /// Jobs left alone because they would have passed the cost cap
+    pub over_budget: Vec<i64>,
+    /// Cost of the successful retries
+    pub cost: f64,
+}
+
+/// One prompt of a `pixery batch --prompts-file`: a plain line, or a JSON
+/// object whose fields replace the command line's for that prompt
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchLine {
+    /// 1-based line in the file, for the report
+    #[serde(skip)]
+    pub line: usize,
+    pub prompt: String,
+    pub model: Option<String>,
+    pub ratio: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub negative: Option<String>,
+}
+
+/// One API call of a batch run, as recorded for `pixery batch --resume`
+#[derive(Debug, Clone)]
+pub struct BatchCall {
+    pub id: i64,
+    /// Prompts-file line; None for a single-prompt run
+    pub line: Option<usize>,
+    /// With num_images set for this call
+    pub request: GenerationRequest,
+    pub tags: Vec<String>,
+}
+
+impl BatchLine {
+    /// Parse a prompts file. Blank lines and `#` comments are skipped; a
+    /// line starting with `{` is JSON (`{"prompt": ..., "model": ...}`),
+    /// anything else is the prompt itself.
+    pub fn parse_file(text: &str) -> Result<Vec<BatchLine>, String> {
+        let mut lines = vec![];
+        for (i, raw) in text.lines().enumerate() {
+            let trimmed = raw.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let mut line = if trimmed.starts_with('{') {
+                serde_json::from_str::<BatchLine>(trimmed).map_err(|e| format!("Line {}: {}", i + 1, e))?
+            } else {
+                BatchLine { prompt: trimmed.to_string(), ..Default::default() }
+            };
+            if line.prompt.trim().is_empty() {
+                return Err(format!("Line {}: empty prompt", i + 1));
+            }
+            line.line = i + 1;
+            lines.push(line);
+        }
+        if lines.is_empty() {
+            return Err("No prompts in file".to_string());
+        }
+        Ok(lines)
+    }
 }
 
 /// Resolve a user-friendly aspect ratio name to pixel dimensions (SDXL native ~1M pixels)
@@ -633,6 +1712,38 @@ pub struct Collection {
     pub description: Option<String>,
     pub created_at: String,
     pub count: i64,
+    /// Explicit cover generation
+    pub cover_id: Option<i64>,
+    /// Thumbnail of the cover, or of the first member when none is set
+    pub cover_path: Option<String>,
+    /// Enclosing collection; `name` is the full "parent/child" path
+    pub parent_id: Option<i64>,
+    /// Generations here or in any subcollection
+    pub total_count: i64,
+}
+
+impl Collection {
+    /// Last segment of the path ("chapter1" for "rpg/chapter1")
+    pub fn leaf_name(&self) -> &str {
+        self.name.rsplit('/').next().unwrap_or(&self.name)
+    }
+
+    /// Nesting level; 0 for a top-level collection
+    pub fn depth(&self) -> usize {
+        self.name.matches('/').count()
+    }
+}
+
+/// A saved search: generations matching `filter` (a `ListFilter::parse_expr`
+/// expression), evaluated whenever it's listed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmartCollection {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub filter: String,
+    pub created_at: String,
+    pub count: i64,
 }
 
 /// Parse a "since" string (e.g., "7d", "30d", "today", "all") into a date string.
@@ -0,0 +1,274 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::archive::{self, ThumbSize};
+use crate::cancel;
+use crate::models::{Generated, Generation, JobSource};
+
+/// Give up on a webhook after this long; it must never hold up a generation
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Which job sources raise desktop notifications, plus an optional webhook,
+/// stored in notifications.json in the archive root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifySettings {
+    pub cli: bool,
+    /// Only while the window is in the background
+    pub gui: bool,
+    pub schedule: bool,
+    /// POSTed on every job completion or failure, whatever the source
+    pub webhook_url: Option<String>,
+    /// Payload shape; guessed from the URL when unset
+    pub webhook_format: Option<WebhookFormat>,
+}
+
+impl Default for NotifySettings {
+    fn default() -> Self {
+        NotifySettings {
+            cli: true,
+            gui: true,
+            schedule: true,
+            webhook_url: None,
+            webhook_format: None,
+        }
+    }
+}
+
+/// Body a webhook receiver expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// `WebhookEvent` as JSON
+    Json,
+    /// `{"content": text}`
+    Discord,
+    /// `{"text": text}`
+    Slack,
+    /// Plain text body with a Title header
+    Ntfy,
+}
+
+impl WebhookFormat {
+    fn detect(url: &str) -> Self {
+        if url.contains("discord.com/api/webhooks") || url.contains("discordapp.com/api/webhooks") {
+            WebhookFormat::Discord
+        } else if url.contains("hooks.slack.com") {
+            WebhookFormat::Slack
+        } else if url.contains("ntfy") {
+            WebhookFormat::Ntfy
+        } else {
+            WebhookFormat::Json
+        }
+    }
+}
+
+impl std::str::FromStr for WebhookFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(WebhookFormat::Json),
+            "discord" => Ok(WebhookFormat::Discord),
+            "slack" => Ok(WebhookFormat::Slack),
+            "ntfy" => Ok(WebhookFormat::Ntfy),
+            _ => Err(format!("Unknown webhook format: {} (expected json, discord, slack or ntfy)", s)),
+        }
+    }
+}
+
+impl NotifySettings {
+    pub fn enabled(&self, source: JobSource) -> bool {
+        match source {
+            JobSource::Cli => self.cli,
+            JobSource::Gui => self.gui,
+            JobSource::Schedule => self.schedule,
+        }
+    }
+}
+
+fn settings_path() -> PathBuf {
+    archive::archive_root().join("notifications.json")
+}
+
+pub fn load_settings() -> NotifySettings {
+    std::fs::read_to_string(settings_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &NotifySettings) -> Result<()> {
+    if let Some(url) = &settings.webhook_url {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            anyhow::bail!("Webhook must be an http(s) URL: {}", url);
+        }
+    }
+    std::fs::write(settings_path(), serde_json::to_string_pretty(settings)?)
+        .context("Failed to write notification settings")
+}
+
+/// Notification text plus an optional image (the generation's thumbnail)
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub title: String,
+    pub body: String,
+    pub image: Option<String>,
+}
+
+impl Message {
+    pub fn generated(generation: &Generation) -> Self {
+        Message {
+            title: format!("Generated with {}", generation.model),
+            body: generation.prompt.chars().take(120).collect(),
+            image: Some(archive::display_path(&generation.image_path, ThumbSize::Grid)),
+        }
+    }
+
+    /// None for a cancelled job: the user stopped it and doesn't need telling
+    pub fn failed(model: &str, err: &anyhow::Error) -> Option<Self> {
+        if cancel::is_cancelled(err) {
+            return None;
+        }
+        Some(Message {
+            title: format!("Generation with {} failed", model),
+            body: err.to_string().chars().take(200).collect(),
+            image: None,
+        })
+    }
+
+    pub fn batch(succeeded: usize, failed: usize) -> Self {
+        Message {
+            title: "Batch complete".to_string(),
+            body: format!("{} succeeded, {} failed", succeeded, failed),
+            image: None,
+        }
+    }
+}
+
+/// Show a notification if `source` has them enabled. Best effort: a desktop
+/// without a notification service just doesn't get one.
+pub fn send(source: JobSource, message: &Message) {
+    if !load_settings().enabled(source) {
+        return;
+    }
+    let mut notification = notify_rust::Notification::new();
+    notification.appname("pixery").summary(&message.title).body(&message.body);
+    #[cfg(any(all(unix, not(target_os = "macos")), target_os = "windows"))]
+    if let Some(image) = &message.image {
+        notification.image_path(image);
+    }
+    let _ = notification.show();
+}
+
+/// What a webhook receives when a job finishes
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookEvent {
+    /// "completed" or "failed"
+    pub event: &'static str,
+    pub job_id: i64,
+    pub model: String,
+    pub prompt: String,
+    pub generation_ids: Vec<i64>,
+    pub image_paths: Vec<String>,
+    pub cost_usd: Option<f64>,
+    pub error: Option<String>,
+}
+
+impl WebhookEvent {
+    pub fn completed(job_id: i64, generations: &[Generated]) -> Option<Self> {
+        let first = &generations.first()?.generation;
+        Some(WebhookEvent {
+            event: "completed",
+            job_id,
+            model: first.model.clone(),
+            prompt: first.prompt.clone(),
+            generation_ids: generations.iter().map(|saved| saved.id).collect(),
+            image_paths: generations.iter().map(|saved| saved.generation.image_path.clone()).collect(),
+            cost_usd: generations.iter().map(|saved| saved.generation.cost_estimate_usd).sum(),
+            error: None,
+        })
+    }
+
+    pub fn failed(job_id: i64, model: &str, prompt: &str, error: &str) -> Self {
+        WebhookEvent {
+            event: "failed",
+            job_id,
+            model: model.to_string(),
+            prompt: prompt.to_string(),
+            generation_ids: vec![],
+            image_paths: vec![],
+            cost_usd: None,
+            error: Some(error.to_string()),
+        }
+    }
+
+    fn title(&self) -> String {
+        match self.error {
+            None => format!("Generated with {}", self.model),
+            Some(_) => format!("Generation with {} failed", self.model),
+        }
+    }
+
+    /// Chat-friendly rendering for Discord, Slack and ntfy
+    fn text(&self) -> String {
+        let mut lines = vec![];
+        match (&self.error, self.cost_usd) {
+            (Some(error), _) => lines.push(format!("{}: {}", self.title(), error)),
+            (None, Some(cost)) => lines.push(format!("{} (${:.4})", self.title(), cost)),
+            (None, None) => lines.push(self.title()),
+        }
+        lines.push(format!("> {}", self.prompt.chars().take(300).collect::<String>()));
+        lines.extend(self.image_paths.iter().cloned());
+        lines.join("\n")
+    }
+}
+
+/// Webhook posts still in flight
+fn pending() -> &'static Mutex<Vec<JoinHandle<()>>> {
+    static PENDING: OnceLock<Mutex<Vec<JoinHandle<()>>>> = OnceLock::new();
+    PENDING.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// POST `event` to the configured webhook in the background. Failures are
+/// printed, never returned: a dead webhook mustn't fail the generation.
+pub fn post_webhook(event: WebhookEvent) {
+    let settings = load_settings();
+    let Some(url) = settings.webhook_url.filter(|u| !u.trim().is_empty()) else {
+        return;
+    };
+    let Ok(handle) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    let format = settings.webhook_format.unwrap_or_else(|| WebhookFormat::detect(&url));
+
+    let task = handle.spawn(async move {
+        let request = crate::providers::client().post(&url).timeout(WEBHOOK_TIMEOUT);
+        let request = match format {
+            WebhookFormat::Json => request.json(&event),
+            WebhookFormat::Discord => request.json(&serde_json::json!({ "content": event.text() })),
+            WebhookFormat::Slack => request.json(&serde_json::json!({ "text": event.text() })),
+            WebhookFormat::Ntfy => request.header("Title", event.title()).body(event.text()),
+        };
+        match request.send().await {
+            Ok(response) if !response.status().is_success() => {
+                eprintln!("Webhook returned {}", response.status());
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Webhook failed: {}", e),
+        }
+    });
+    pending().lock().unwrap_or_else(|e| e.into_inner()).push(task);
+}
+
+/// Wait for webhook posts still in flight; the CLI calls this before exiting
+pub async fn flush() {
+    let tasks: Vec<_> = std::mem::take(&mut *pending().lock().unwrap_or_else(|e| e.into_inner()));
+    for task in tasks {
+        let _ = task.await;
+    }
+}
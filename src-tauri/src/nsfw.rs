@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::archive;
+use crate::caption;
+use crate::models::{Generation, MediaType};
+
+/// Prompt tags that mark an image as NSFW: plain "nsfw" and the Danbooru
+/// rating tags SDXL anime checkpoints are prompted with (NoobAI and
+/// Animagine write "rating:explicit" or just "explicit", Pony "rating_explicit")
+const RATING_TAGS: &[&str] = &[
+    "nsfw",
+    "explicit",
+    "questionable",
+    "rating:explicit",
+    "rating:questionable",
+    "rating:e",
+    "rating:q",
+    "rating_explicit",
+    "rating_questionable",
+];
+
+const CLASSIFY_PROMPT: &str = "Does this image contain nudity or sexual content? \
+Answer with one word: yes or no.";
+
+/// NSFW settings, stored in nsfw.json in the archive root
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NsfwSettings {
+    /// Show flagged generations in list, search and the gallery (blurred there)
+    pub show: bool,
+    /// Flag new generations whose prompt has a rating tag
+    pub from_prompt: bool,
+    /// Also ask the caption vision model about each new image
+    pub classify: bool,
+}
+
+impl Default for NsfwSettings {
+    fn default() -> Self {
+        NsfwSettings { show: false, from_prompt: true, classify: false }
+    }
+}
+
+fn settings_path() -> PathBuf {
+    archive::archive_root().join("nsfw.json")
+}
+
+pub fn load_settings() -> NsfwSettings {
+    std::fs::read_to_string(settings_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &NsfwSettings) -> Result<()> {
+    std::fs::write(settings_path(), serde_json::to_string_pretty(settings)?).context("Failed to write NSFW settings")
+}
+
+/// Whether a prompt has a rating tag. Tags are matched whole, ignoring case,
+/// attention weights ("(nsfw:1.2)") and spaces after the colon
+/// ("rating: explicit"). Only pass the positive prompt: negative prompts list
+/// "nsfw" to keep images clean.
+pub fn prompt_is_nsfw(prompt: &str) -> bool {
+    prompt.split([',', '\n']).any(|tag| {
+        let tag = tag.trim().trim_matches(['(', ')', '[', ']', '{', '}']).to_lowercase();
+        let tag = match tag.rsplit_once(':') {
+            Some((name, weight)) if weight.trim().parse::<f32>().is_ok() => name.trim().to_string(),
+            _ => tag,
+        };
+        let tag = tag.replace(": ", ":");
+        RATING_TAGS.contains(&tag.as_str())
+    })
+}
+
+/// Ask the caption vision model (caption.json) whether an image is NSFW
+pub async fn classify(path: &Path, settings: &caption::CaptionSettings) -> Result<bool> {
+    let answer = caption::ask(path, CLASSIFY_PROMPT, settings).await?;
+    let answer = answer.trim().trim_start_matches(['"', '\'', '*']).to_lowercase();
+    if answer.starts_with("yes") {
+        Ok(true)
+    } else if answer.starts_with("no") {
+        Ok(false)
+    } else {
+        anyhow::bail!("Vision model gave no yes/no answer: {}", answer)
+    }
+}
+
+/// For a new generation with `classify` on: whether the vision model flags
+/// it. Images only (not already flagged); a classifier error is reported and
+/// leaves the generation unflagged rather than failing it.
+pub async fn classify_new(generation: &Generation, settings: &NsfwSettings) -> bool {
+    if !settings.classify || generation.nsfw || generation.media_type != MediaType::Image {
+        return false;
+    }
+    let path = archive::display_path(&generation.image_path, archive::ThumbSize::Grid);
+    match classify(Path::new(&path), &caption::load_settings()).await {
+        Ok(flagged) => flagged,
+        Err(e) => {
+            eprintln!("NSFW classifier failed for generation {}: {}", generation.id, e);
+            false
+        }
+    }
+}
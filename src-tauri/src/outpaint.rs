@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use image::{GenericImageView, GrayImage, Luma, RgbImage};
+use std::path::{Path, PathBuf};
+
+/// The mask reaches this far into the original image so the seam is repainted
+/// too; without it the provider leaves a hard edge where the canvas was padded.
+const SEAM_OVERLAP: u32 = 32;
+
+/// Side of the canvas to extend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+    /// Every side by the same amount
+    All,
+}
+
+impl std::str::FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            "up" | "top" => Ok(Direction::Up),
+            "down" | "bottom" => Ok(Direction::Down),
+            "all" => Ok(Direction::All),
+            _ => Err(format!("Unknown direction: {} (expected left, right, up, down or all)", s)),
+        }
+    }
+}
+
+/// Padding added on each side: (left, right, top, bottom)
+fn padding(direction: Direction, pixels: u32) -> (u32, u32, u32, u32) {
+    match direction {
+        Direction::Left => (pixels, 0, 0, 0),
+        Direction::Right => (0, pixels, 0, 0),
+        Direction::Up => (0, 0, pixels, 0),
+        Direction::Down => (0, 0, 0, pixels),
+        Direction::All => (pixels, pixels, pixels, pixels),
+    }
+}
+
+/// Where the padded image and mask are written; they only live until the
+/// generation is saved (the mask is then kept as a reference)
+fn work_dir() -> PathBuf {
+    std::env::temp_dir().join("pixery-extend")
+}
+
+/// Pad `source` and build the matching inpainting mask (white = new canvas plus a
+/// thin seam, black = keep). The new area repeats the nearest edge pixels, which
+/// gives providers a better starting point than a flat fill.
+/// Returns (padded image path, mask path, width, height).
+pub fn extend_canvas(source: &Path, direction: Direction, pixels: u32) -> Result<(PathBuf, PathBuf, u32, u32)> {
+    if pixels == 0 {
+        anyhow::bail!("Nothing to extend: --pixels must be greater than 0");
+    }
+    let img = image::open(source).with_context(|| format!("Failed to open {}", source.display()))?;
+    let (w, h) = img.dimensions();
+    let rgb = img.to_rgb8();
+
+    let (left, right, top, bottom) = padding(direction, pixels);
+    let (new_w, new_h) = (w + left + right, h + top + bottom);
+
+    let padded = RgbImage::from_fn(new_w, new_h, |x, y| {
+        let sx = x.saturating_sub(left).min(w - 1);
+        let sy = y.saturating_sub(top).min(h - 1);
+        *rgb.get_pixel(sx, sy)
+    });
+
+    // Keep box: the original minus the seam on every padded side
+    let keep_x0 = if left > 0 { left + SEAM_OVERLAP.min(w / 2) } else { 0 };
+    let keep_y0 = if top > 0 { top + SEAM_OVERLAP.min(h / 2) } else { 0 };
+    let keep_x1 = if right > 0 { left + w - SEAM_OVERLAP.min(w / 2) } else { new_w };
+    let keep_y1 = if bottom > 0 { top + h - SEAM_OVERLAP.min(h / 2) } else { new_h };
+    let mask = GrayImage::from_fn(new_w, new_h, |x, y| {
+        let keep = (keep_x0..keep_x1).contains(&x) && (keep_y0..keep_y1).contains(&y);
+        Luma([if keep { 0 } else { 255 }])
+    });
+
+    let dir = work_dir();
+    std::fs::create_dir_all(&dir).context("Failed to create extend work directory")?;
+    let stem = format!("{}-{}", std::process::id(), chrono::Local::now().format("%H%M%S%f"));
+    let image_path = dir.join(format!("{}-canvas.png", stem));
+    let mask_path = dir.join(format!("{}-mask.png", stem));
+    padded.save(&image_path).context("Failed to write padded image")?;
+    mask.save(&mask_path).context("Failed to write mask")?;
+
+    Ok((image_path, mask_path, new_w, new_h))
+}
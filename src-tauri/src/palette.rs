@@ -0,0 +1,190 @@
+use anyhow::Result;
+use image::DynamicImage;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::archive;
+use crate::db::Database;
+use crate::models::Swatch;
+
+/// Colors clustered per image; near-identical ones are merged afterwards
+const PALETTE_SIZE: usize = 5;
+
+/// Longest side of the downscaled copy that's clustered: a few thousand
+/// pixels give the same colors as the full image, fast enough to run on save
+const SAMPLE_SIDE: u32 = 64;
+
+/// Lloyd iterations at most; palettes settle well before this
+const ITERATIONS: usize = 12;
+
+/// Clusters closer than this (CIE76) are one color split in two
+const MERGE_DISTANCE: f64 = 8.0;
+
+/// Colors covering less of the image than this aren't part of its palette
+const MIN_SHARE: f64 = 0.03;
+
+/// `pixery list --color` tolerance without --tolerance: a CIE76 difference,
+/// where 2 is barely visible and 20 is still recognizably the same color
+pub const DEFAULT_TOLERANCE: f64 = 20.0;
+
+/// An sRGB color, written "#rrggbb"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color(pub [u8; 3]);
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    /// "#ff6600", "ff6600" or "#f60"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid color: {} (expected a hex color like #ff6600)", s);
+        let hex = s.trim().trim_start_matches('#');
+        let hex = match hex.len() {
+            3 => hex.chars().flat_map(|c| [c, c]).collect(),
+            6 => hex.to_string(),
+            _ => return Err(invalid()),
+        };
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2).ok_or_else(invalid)?, 16).map_err(|_| invalid());
+        Ok(Color([channel(0)?, channel(2)?, channel(4)?]))
+    }
+}
+
+/// CIE L*a*b* under D65, where straight-line distance follows perceived difference
+pub type Lab = [f64; 3];
+
+const WHITE: [f64; 3] = [0.95047, 1.0, 1.08883];
+
+impl Color {
+    pub fn lab(self) -> Lab {
+        let linear = |c: u8| {
+            let c = c as f64 / 255.0;
+            if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+        };
+        let [r, g, b] = self.0.map(linear);
+        let xyz = [
+            0.4124 * r + 0.3576 * g + 0.1805 * b,
+            0.2126 * r + 0.7152 * g + 0.0722 * b,
+            0.0193 * r + 0.1192 * g + 0.9505 * b,
+        ];
+        let f = |t: f64| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+        let [fx, fy, fz] = [f(xyz[0] / WHITE[0]), f(xyz[1] / WHITE[1]), f(xyz[2] / WHITE[2])];
+        [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+    }
+
+    /// The nearest sRGB color to a Lab one
+    pub fn from_lab([l, a, b]: Lab) -> Color {
+        let fy = (l + 16.0) / 116.0;
+        let inverse = |t: f64| if t.powi(3) > 0.008856 { t.powi(3) } else { (t - 16.0 / 116.0) / 7.787 };
+        let [x, y, z] = [
+            WHITE[0] * inverse(fy + a / 500.0),
+            WHITE[1] * inverse(fy),
+            WHITE[2] * inverse(fy - b / 200.0),
+        ];
+        let gamma = |c: f64| {
+            let c = if c <= 0.0031308 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+            (c * 255.0).round().clamp(0.0, 255.0) as u8
+        };
+        Color([
+            gamma(3.2406 * x - 1.5372 * y - 0.4986 * z),
+            gamma(-0.9689 * x + 1.8758 * y + 0.0415 * z),
+            gamma(0.0557 * x - 0.2040 * y + 1.0570 * z),
+        ])
+    }
+}
+
+/// CIE76 color difference
+pub fn distance(a: Lab, b: Lab) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+fn nearest(centers: &[Lab], point: Lab) -> usize {
+    (0..centers.len())
+        .min_by(|&i, &j| distance(centers[i], point).total_cmp(&distance(centers[j], point)))
+        .unwrap_or(0)
+}
+
+/// Dominant colors of an image, largest share first: k-means in Lab over a
+/// downscaled copy. Deterministic, so re-extracting gives the same palette.
+/// Mostly transparent pixels are left out.
+pub fn extract(img: &DynamicImage) -> Vec<Swatch> {
+    let sample = img.thumbnail(SAMPLE_SIDE, SAMPLE_SIDE).to_rgba8();
+    let mut points: Vec<Lab> = sample
+        .pixels()
+        .filter(|p| p[3] >= 128)
+        .map(|p| Color([p[0], p[1], p[2]]).lab())
+        .collect();
+    if points.is_empty() {
+        return vec![];
+    }
+
+    // Start from evenly spaced lightness quantiles rather than random picks
+    points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+    let k = PALETTE_SIZE.min(points.len());
+    let mut centers: Vec<Lab> = (0..k).map(|i| points[(2 * i + 1) * points.len() / (2 * k)]).collect();
+    let mut assignment: Vec<usize> = points.iter().map(|&p| nearest(&centers, p)).collect();
+    for _ in 0..ITERATIONS {
+        let mut sums = vec![[0.0; 3]; k];
+        let mut counts = vec![0usize; k];
+        for (point, &cluster) in points.iter().zip(&assignment) {
+            for channel in 0..3 {
+                sums[cluster][channel] += point[channel];
+            }
+            counts[cluster] += 1;
+        }
+        for cluster in 0..k {
+            if counts[cluster] > 0 {
+                centers[cluster] = sums[cluster].map(|sum| sum / counts[cluster] as f64);
+            }
+        }
+        let next: Vec<usize> = points.iter().map(|&p| nearest(&centers, p)).collect();
+        if next == assignment {
+            break;
+        }
+        assignment = next;
+    }
+
+    let mut clusters: Vec<(Lab, usize)> = centers
+        .into_iter()
+        .enumerate()
+        .map(|(cluster, center)| (center, assignment.iter().filter(|&&a| a == cluster).count()))
+        .filter(|&(_, count)| count > 0)
+        .collect();
+    clusters.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+    // A near-duplicate adds its pixels to the larger color it's split from
+    let mut merged: Vec<(Lab, usize)> = vec![];
+    for (center, count) in clusters {
+        match merged.iter_mut().find(|(kept, _)| distance(*kept, center) < MERGE_DISTANCE) {
+            Some((_, kept_count)) => *kept_count += count,
+            None => merged.push((center, count)),
+        }
+    }
+    merged.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+    let total = points.len() as f64;
+    merged
+        .into_iter()
+        .map(|(center, count)| Swatch { hex: Color::from_lab(center).to_string(), share: count as f64 / total })
+        .filter(|swatch| swatch.share >= MIN_SHARE)
+        .collect()
+}
+
+/// Palette of an image file, or of a video's first frame
+pub fn extract_file(path: &Path) -> Result<Vec<Swatch>> {
+    Ok(extract(&archive::thumbnail_source(path)?))
+}
+
+/// Extract and store a newly saved generation's palette. Saving doesn't fail
+/// over it: errors are logged, and `pixery palette --missing` fills the gap.
+pub fn record_new(db: &Database, id: i64, path: &Path) {
+    if let Err(e) = extract_file(path).and_then(|palette| db.set_palette(id, &palette)) {
+        eprintln!("Palette for generation {} failed: {:#}", id, e);
+    }
+}
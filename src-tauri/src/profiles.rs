@@ -0,0 +1,134 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The archive in ~/media/image-gen, always there
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// A separate archive with its own root directory: database, images,
+/// references and settings files all live inside it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    /// May start with ~/
+    pub root: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Archives besides the default one, stored in profiles.json in the default
+/// archive root. `active` is used when a command doesn't pass --archive.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfilesConfig {
+    pub active: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// The profile this process works on: picked by --archive, or on first use
+static CURRENT: OnceLock<(String, PathBuf)> = OnceLock::new();
+
+fn default_root() -> PathBuf {
+    dirs::home_dir()
+        .expect("Could not find home directory")
+        .join("media")
+        .join("image-gen")
+}
+
+fn config_path() -> PathBuf {
+    default_root().join("profiles.json")
+}
+
+pub fn load_config() -> ProfilesConfig {
+    std::fs::read_to_string(config_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_config(config: &ProfilesConfig) -> Result<()> {
+    std::fs::create_dir_all(default_root()).context("Failed to create archive directory")?;
+    std::fs::write(config_path(), serde_json::to_string_pretty(config)?)
+        .context("Failed to write profiles")
+}
+
+/// A profile name, which also ends up in temp directory names
+pub fn parse_name(name: &str) -> Result<String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        anyhow::bail!("'{}' is not a valid profile name (use letters, digits, '-' and '_')", name);
+    }
+    Ok(name.to_string())
+}
+
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+impl ProfilesConfig {
+    /// A profile's root directory; None if there's no such profile
+    pub fn root_of(&self, name: &str) -> Option<PathBuf> {
+        if name == DEFAULT_PROFILE {
+            return Some(default_root());
+        }
+        self.profiles.get(name).map(|p| expand_home(&p.root))
+    }
+
+    /// The profile used without --archive
+    pub fn active_name(&self) -> &str {
+        self.active.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
+
+    /// Every profile name, the default first
+    pub fn names(&self) -> Vec<String> {
+        std::iter::once(DEFAULT_PROFILE.to_string()).chain(self.profiles.keys().cloned()).collect()
+    }
+
+    fn unknown(&self, name: &str) -> anyhow::Error {
+        anyhow::anyhow!("No archive profile '{}' (profiles: {})", name, self.names().join(", "))
+    }
+
+    /// Make `name` the profile used without --archive
+    pub fn switch(&mut self, name: &str) -> Result<()> {
+        if self.root_of(name).is_none() {
+            return Err(self.unknown(name));
+        }
+        self.active = (name != DEFAULT_PROFILE).then(|| name.to_string());
+        Ok(())
+    }
+}
+
+/// Work on profile `name` for the rest of this process (`--archive NAME`).
+/// Call it before anything asks for an archive path.
+pub fn select(name: &str) -> Result<()> {
+    let config = load_config();
+    let root = config.root_of(name).ok_or_else(|| config.unknown(name))?;
+    CURRENT
+        .set((name.to_string(), root))
+        .map_err(|_| anyhow::anyhow!("The archive profile was already chosen"))
+}
+
+fn current() -> &'static (String, PathBuf) {
+    CURRENT.get_or_init(|| {
+        let config = load_config();
+        let name = config.active_name();
+        match config.root_of(name) {
+            Some(root) => (name.to_string(), root),
+            // The active profile was deleted from profiles.json by hand
+            None => (DEFAULT_PROFILE.to_string(), default_root()),
+        }
+    })
+}
+
+/// Name of the profile this process works on
+pub fn current_name() -> &'static str {
+    &current().0
+}
+
+/// Root directory of the profile this process works on
+pub fn current_root() -> PathBuf {
+    current().1.clone()
+}
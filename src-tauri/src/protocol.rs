@@ -0,0 +1,145 @@
+use std::fs::{File, Metadata};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use tauri::http::{header, Request, Response, StatusCode};
+
+use crate::archive::{self, ThumbSize};
+use crate::db::DbPool;
+use crate::providers;
+
+/// URI scheme the webview loads images through: `pixery://localhost/<id>/<size>`
+pub const SCHEME: &str = "pixery";
+
+/// Images at a URL only change if thumbnails are regenerated; the ETag
+/// covers that once the day is up
+const MAX_AGE: u32 = 86400;
+
+/// Most bytes sent for one Range request. `<video>` asks for `bytes=0-` and
+/// then whatever it needs next, so a long clip is never read in one go.
+const MAX_RANGE: u64 = 4 * 1024 * 1024;
+
+/// A generation's image at one of the sizes the GUI shows it
+#[derive(Debug, Clone, Copy)]
+enum Variant {
+    Thumb(ThumbSize),
+    Full,
+}
+
+/// `<id>/<size>` from the request path. convertFileSrc percent-encodes the
+/// whole path, so the separator may arrive as %2F.
+fn parse_path(path: &str) -> Option<(i64, Variant)> {
+    let path = path.trim_start_matches('/').replace("%2F", "/").replace("%2f", "/");
+    let (id, size) = path.split_once('/')?;
+    let variant = match size {
+        "grid" => Variant::Thumb(ThumbSize::Grid),
+        "preview" => Variant::Thumb(ThumbSize::Preview),
+        "full" => Variant::Full,
+        _ => return None,
+    };
+    Some((id.parse().ok()?, variant))
+}
+
+/// Weak validator from the file's size and mtime, which change whenever
+/// the file is rewritten
+fn etag(meta: &Metadata) -> Option<String> {
+    let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(format!("W/\"{:x}-{:x}\"", meta.len(), modified.as_millis()))
+}
+
+/// First range of a `bytes=start-end` Range header, as an inclusive span
+/// clamped to the file and to `MAX_RANGE`; `<video>` seeks with these
+fn byte_range(header: &str, len: u64) -> Option<(u64, u64)> {
+    let spec = header.strip_prefix("bytes=")?.split(',').next()?.trim();
+    let (start, end) = spec.split_once('-')?;
+    let last = len.checked_sub(1)?;
+    let (start, end) = match (start.parse::<u64>().ok(), end.parse::<u64>().ok()) {
+        (Some(start), Some(end)) => (start, end.min(last)),
+        (Some(start), None) => (start, last),
+        // bytes=-N is the last N bytes
+        (None, Some(suffix)) => (len.saturating_sub(suffix), last),
+        (None, None) => return None,
+    };
+    (start <= end).then_some((start, end.min(start + MAX_RANGE - 1)))
+}
+
+/// Bytes `start..=end` of a file, without reading the rest of it
+fn read_range(path: &Path, start: u64, end: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = vec![0; (end - start + 1) as usize];
+    file.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn status(code: StatusCode, message: &str) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(code)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(message.as_bytes().to_vec())
+        .unwrap()
+}
+
+/// Serve a `pixery://` request. Thumbnails still being written in the
+/// background are created on the spot; a thumbnail that can't be made falls
+/// back to the original. Range requests are answered for video playback.
+/// Blocks on disk and database I/O, so call it off the main thread.
+pub fn respond(db: &DbPool, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let Some((id, variant)) = parse_path(request.uri().path()) else {
+        return status(StatusCode::BAD_REQUEST, "Expected /<id>/<grid|preview|full>");
+    };
+    let gen = match db.get().and_then(|db| db.get_generation(id)) {
+        Ok(Some(gen)) => gen,
+        Ok(None) => return status(StatusCode::NOT_FOUND, "No such generation"),
+        Err(e) => return status(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+    };
+
+    let image_path = Path::new(&gen.image_path);
+    let path = match variant {
+        Variant::Thumb(size) => {
+            archive::ensure_thumbnail(image_path, size).unwrap_or_else(|_| image_path.to_path_buf())
+        }
+        Variant::Full => image_path.to_path_buf(),
+    };
+    let meta = std::fs::metadata(&path).ok();
+    let Some((len, etag)) = meta.and_then(|meta| Some((meta.len(), etag(&meta)?))) else {
+        return status(StatusCode::NOT_FOUND, "Image file is missing");
+    };
+
+    let builder = Response::builder()
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CACHE_CONTROL, format!("max-age={}", MAX_AGE))
+        .header(header::ETAG, &etag)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+    let unchanged = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|tag| tag.trim() == etag));
+    if unchanged {
+        return builder.status(StatusCode::NOT_MODIFIED).body(vec![]).unwrap();
+    }
+
+    let builder = builder.header(header::CONTENT_TYPE, providers::mime_type(&path));
+    let range = request.headers().get(header::RANGE).and_then(|v| v.to_str().ok());
+    match range.map(|r| byte_range(r, len)) {
+        None => match std::fs::read(&path) {
+            Ok(bytes) => builder.body(bytes).unwrap(),
+            Err(e) => status(StatusCode::NOT_FOUND, &e.to_string()),
+        },
+        Some(Some((start, end))) => match read_range(&path, start, end) {
+            Ok(bytes) => builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len))
+                .body(bytes)
+                .unwrap(),
+            Err(e) => status(StatusCode::NOT_FOUND, &e.to_string()),
+        },
+        Some(None) => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+            .body(vec![])
+            .unwrap(),
+    }
+}
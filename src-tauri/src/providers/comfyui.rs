@@ -0,0 +1,383 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::models::{GenerationRequest, GenerationResult, ModelInfo, ProgressStage, Provider};
+
+const DEFAULT_URL: &str = "http://127.0.0.1:8188";
+const MODEL_PREFIX: &str = "comfyui:";
+const POLL_INTERVAL_MS: u64 = 1000;
+const MAX_POLL_ATTEMPTS: u32 = 600; // 10 minutes - first run loads checkpoints from disk
+
+#[derive(Deserialize)]
+struct PromptResponse {
+    prompt_id: String,
+}
+
+#[derive(Deserialize)]
+struct UploadResponse {
+    name: String,
+    #[serde(default)]
+    subfolder: String,
+}
+
+#[derive(Deserialize)]
+struct HistoryEntry {
+    #[serde(default)]
+    outputs: HashMap<String, NodeOutput>,
+    status: Option<HistoryStatus>,
+}
+
+#[derive(Deserialize)]
+struct NodeOutput {
+    #[serde(default)]
+    images: Vec<OutputImage>,
+}
+
+#[derive(Deserialize)]
+struct OutputImage {
+    filename: String,
+    #[serde(default)]
+    subfolder: String,
+    #[serde(rename = "type", default)]
+    kind: String,
+}
+
+#[derive(Deserialize)]
+struct HistoryStatus {
+    status_str: Option<String>,
+    #[serde(default)]
+    messages: Vec<Value>,
+}
+
+#[derive(Deserialize)]
+struct QueueResponse {
+    #[serde(default)]
+    queue_pending: Vec<Vec<Value>>,
+}
+
+/// Get the ComfyUI server URL from settings file, env, or the ComfyUI default
+pub fn get_server_url() -> String {
+    let settings_path = crate::archive::archive_root().join("comfyui.json");
+    if let Ok(contents) = std::fs::read_to_string(&settings_path) {
+        if let Ok(settings) = serde_json::from_str::<Value>(&contents) {
+            if let Some(url) = settings.get("url").and_then(|v| v.as_str()) {
+                if !url.is_empty() {
+                    return url.to_string();
+                }
+            }
+        }
+    }
+    std::env::var("COMFYUI_URL").unwrap_or_else(|_| DEFAULT_URL.to_string())
+}
+
+/// Directory holding workflow templates, one `<name>.json` per model
+pub fn templates_dir() -> PathBuf {
+    crate::archive::archive_root().join("comfyui")
+}
+
+fn template_path(model: &str) -> Option<PathBuf> {
+    let name = model.strip_prefix(MODEL_PREFIX)?;
+    // Template names are file stems; reject anything that could escape the directory
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return None;
+    }
+    Some(templates_dir().join(format!("{}.json", name)))
+}
+
+/// One model per workflow template found in `templates_dir()`.
+/// Templates containing `{{reference}}` accept a single reference image.
+pub fn template_models() -> Vec<ModelInfo> {
+    let Ok(entries) = std::fs::read_dir(templates_dir()) else {
+        return vec![];
+    };
+
+    let mut models: Vec<ModelInfo> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("json"))
+        .filter_map(|p| {
+            let name = p.file_stem()?.to_str()?.to_string();
+            let uses_reference = std::fs::read_to_string(&p)
+                .map(|t| t.contains("{{reference}}"))
+                .unwrap_or(false);
+            Some(ModelInfo {
+                id: format!("{}{}", MODEL_PREFIX, name),
+                provider: Provider::ComfyUI,
+                display_name: format!("{} (ComfyUI)", name),
+                cost_per_image: 0.0,
+                max_refs: if uses_reference { 1 } else { 0 },
+            })
+        })
+        .collect();
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    models
+}
+
+/// Fill `{{placeholder}}` strings in a workflow. A string that is exactly a
+/// numeric placeholder (`"{{seed}}"`) becomes a JSON number, since ComfyUI
+/// rejects numeric inputs passed as strings.
+fn fill_template(value: &mut Value, vars: &[(&str, Value)]) {
+    match value {
+        Value::String(s) => {
+            for (key, replacement) in vars {
+                let placeholder = format!("{{{{{}}}}}", key);
+                if *s == placeholder {
+                    *value = replacement.clone();
+                    return;
+                }
+                if s.contains(&placeholder) {
+                    let text = match replacement {
+                        Value::String(r) => r.clone(),
+                        other => other.to_string(),
+                    };
+                    *s = s.replace(&placeholder, &text);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| fill_template(v, vars)),
+        Value::Object(map) => map.values_mut().for_each(|v| fill_template(v, vars)),
+        _ => {}
+    }
+}
+
+fn random_seed() -> u64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    // ComfyUI's KSampler caps seeds at 2^53 (JS number precision in the UI)
+    nanos % (1u64 << 53)
+}
+
+/// Upload a reference image to ComfyUI's input folder, returning the name to
+/// use in a LoadImage node
+async fn upload_reference(base_url: &str, path: &Path) -> Result<String> {
+    let data = std::fs::read(path).context("Failed to read reference image")?;
+    let filename = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("reference.png")
+        .to_string();
+    let part = reqwest::multipart::Part::bytes(data)
+        .file_name(filename)
+        .mime_str(super::mime_type(path))?;
+    let form = reqwest::multipart::Form::new()
+        .part("image", part)
+        .text("overwrite", "true");
+
+    let response = super::client()
+        .post(format!("{}/upload/image", base_url))
+        .multipart(form)
+        .timeout(Duration::from_secs(60))
+        .send()
+        .await
+        .context("Failed to upload reference image to ComfyUI")?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        anyhow::bail!("ComfyUI upload error {}: {}", status, text);
+    }
+
+    let uploaded: UploadResponse = response.json().await.context("Failed to parse ComfyUI upload response")?;
+    Ok(if uploaded.subfolder.is_empty() {
+        uploaded.name
+    } else {
+        format!("{}/{}", uploaded.subfolder, uploaded.name)
+    })
+}
+
+/// 1-based position in ComfyUI's pending queue, if the prompt is still waiting
+async fn queue_position(base_url: &str, prompt_id: &str) -> Option<u32> {
+    let queue: QueueResponse = super::client()
+        .get(format!("{}/queue", base_url))
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    // Pending entries are [number, prompt_id, prompt, extra, outputs]
+    queue
+        .queue_pending
+        .iter()
+        .position(|item| item.get(1).and_then(|v| v.as_str()) == Some(prompt_id))
+        .map(|i| i as u32 + 1)
+}
+
+/// Local ComfyUI instance driven by per-model workflow templates
+pub struct ComfyUI;
+
+#[async_trait::async_trait]
+impl super::ImageProvider for ComfyUI {
+    fn kind(&self) -> Provider {
+        Provider::ComfyUI
+    }
+
+    async fn generate(
+        &self,
+        req: &GenerationRequest,
+        progress: super::Progress<'_>,
+    ) -> Result<GenerationResult> {
+        generate(req, progress).await
+    }
+
+    /// Only checked once there are workflow templates to run
+    async fn check(&self) -> Result<Option<super::HealthCheck>> {
+        if template_models().is_empty() {
+            return Ok(None);
+        }
+        let endpoint = format!("{}/system_stats", get_server_url().trim_end_matches('/'));
+        let request = super::client().get(&endpoint);
+        super::timed_check("ComfyUI", &endpoint, request).await.map(Some)
+    }
+}
+
+/// Submit the model's workflow template to ComfyUI, wait for it in the
+/// history API, and download the first output image
+async fn generate(req: &GenerationRequest, progress: super::Progress<'_>) -> Result<GenerationResult> {
+    if req.inpaint.is_some() {
+        anyhow::bail!("ComfyUI templates don't support inpainting yet");
+    }
+    let path = template_path(&req.model)
+        .ok_or_else(|| anyhow::anyhow!("Not a ComfyUI model: {}", req.model))?;
+    let template = std::fs::read_to_string(&path)
+        .with_context(|| format!("No ComfyUI workflow template at {}", path.display()))?;
+    let mut workflow: Value =
+        serde_json::from_str(&template).context("Invalid ComfyUI workflow template JSON")?;
+
+    let base_url = get_server_url().trim_end_matches('/').to_string();
+    let client = super::client();
+
+    let reference = match req.reference_paths.first() {
+        Some(ref_path) => upload_reference(&base_url, Path::new(ref_path)).await?,
+        None => String::new(),
+    };
+
+    let seed = req.seed.unwrap_or_else(random_seed);
+    fill_template(
+        &mut workflow,
+        &[
+            ("prompt", Value::from(req.prompt.as_str())),
+            ("negative_prompt", Value::from(req.negative_prompt.as_deref().unwrap_or(""))),
+            ("width", Value::from(req.width.unwrap_or(1024))),
+            ("height", Value::from(req.height.unwrap_or(1024))),
+            ("seed", Value::from(seed)),
+            ("reference", Value::from(reference)),
+        ],
+    );
+
+    let start = Instant::now();
+    progress(ProgressStage::RequestSent);
+    let response = client
+        .post(format!("{}/prompt", base_url))
+        .json(&serde_json::json!({ "prompt": workflow }))
+        .timeout(Duration::from_secs(30))
+        .send()
+        .await
+        .context("Failed to send workflow to ComfyUI")?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(super::ApiError {
+            provider: "ComfyUI",
+            status,
+            body: text,
+        }
+        .into());
+    }
+
+    let submitted: PromptResponse = response.json().await.context("Failed to parse ComfyUI prompt response")?;
+    let prompt_id = submitted.prompt_id;
+
+    // The prompt shows up in /history only once it has finished (or failed)
+    let mut entry: Option<HistoryEntry> = None;
+    for _ in 0..MAX_POLL_ATTEMPTS {
+        progress(ProgressStage::InQueue {
+            position: queue_position(&base_url, &prompt_id).await,
+        });
+        tokio::time::sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
+
+        let poll = client
+            .get(format!("{}/history/{}", base_url, prompt_id))
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .context("Failed to poll ComfyUI history")?;
+
+        if !poll.status().is_success() {
+            let status = poll.status();
+            let text = poll.text().await.unwrap_or_default();
+            anyhow::bail!("ComfyUI history error {}: {}", status, text);
+        }
+
+        let mut history: HashMap<String, HistoryEntry> =
+            poll.json().await.context("Failed to parse ComfyUI history")?;
+        if let Some(found) = history.remove(&prompt_id) {
+            entry = Some(found);
+            break;
+        }
+    }
+
+    let entry = entry.ok_or_else(|| anyhow::anyhow!("Timeout waiting for ComfyUI generation"))?;
+
+    if let Some(status) = &entry.status {
+        if status.status_str.as_deref() == Some("error") {
+            let detail = status
+                .messages
+                .iter()
+                .filter(|m| m.get(0).and_then(|v| v.as_str()) == Some("execution_error"))
+                .filter_map(|m| m.get(1)?.get("exception_message")?.as_str())
+                .collect::<Vec<_>>()
+                .join("; ");
+            anyhow::bail!("ComfyUI execution error: {}", detail);
+        }
+    }
+
+    // Prefer saved outputs over PreviewImage temp files
+    let images: Vec<&OutputImage> = entry.outputs.values().flat_map(|o| &o.images).collect();
+    let image = images
+        .iter()
+        .find(|i| i.kind == "output")
+        .or_else(|| images.first())
+        .ok_or_else(|| anyhow::anyhow!("No images in ComfyUI output"))?;
+
+    progress(ProgressStage::Downloading);
+    let image_response = client
+        .get(format!("{}/view", base_url))
+        .query(&[
+            ("filename", image.filename.as_str()),
+            ("subfolder", image.subfolder.as_str()),
+            ("type", image.kind.as_str()),
+        ])
+        .timeout(Duration::from_secs(60))
+        .send()
+        .await
+        .context("Failed to fetch image from ComfyUI")?;
+
+    let elapsed = start.elapsed().as_secs_f64();
+
+    if !image_response.status().is_success() {
+        anyhow::bail!("Failed to fetch image: {}", image_response.status());
+    }
+
+    let image_data = image_response
+        .bytes()
+        .await
+        .context("Failed to read image bytes")?
+        .to_vec();
+
+    let uses_seed = template.contains("{{seed}}");
+    Ok(GenerationResult {
+        images: vec![image_data],
+        seed: uses_seed.then(|| seed.to_string()),
+        generation_time_seconds: elapsed,
+        cost_usd: None, // Local, no API cost
+    })
+}
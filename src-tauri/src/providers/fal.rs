@@ -4,11 +4,15 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::time::{Duration, Instant};
 
-use crate::models::GenerationResult;
+use crate::keys;
+use crate::models::{GenerationRequest, GenerationResult, ModelInfo, ProgressStage, Provider, VideoModelInfo, VideoRequest, VideoResult};
 
 const API_BASE: &str = "https://queue.fal.run";
 const POLL_INTERVAL_MS: u64 = 1000; // 1 second between polls
 const MAX_POLL_ATTEMPTS: u32 = 300; // 5 minutes max (Ultra models queue longer)
+const MAX_IMAGES_PER_CALL: u32 = 4;
+/// FLUX.1 Fill [pro]: source image_url + mask_url
+const FILL_ENDPOINT: &str = "fal-ai/flux-pro/v1/fill";
 
 /// Model ID mapping for fal.ai models
 ///
@@ -27,8 +31,21 @@ fn resolve_model(model: &str, has_reference: bool) -> &str {
         "flux-ultra" | "fal-ai/flux-pro/v1.1-ultra" => "fal-ai/flux-pro/v1.1-ultra",
         "recraft" | "fal-ai/recraft-v3" => "fal-ai/recraft-v3",
         "flux2-turbo" | "fal-ai/flux-2/turbo" => "fal-ai/flux-2/turbo",
-        "flux2-pro" | "fal-ai/flux-2-pro" => "fal-ai/flux-2-pro",
-        "flux2-max" | "fal-ai/flux-2-max" => "fal-ai/flux-2-max",
+        // FLUX 2 Pro/Max: route to the multi-reference edit endpoints when refs provided
+        "flux2-pro" | "fal-ai/flux-2-pro" | "fal-ai/flux-2-pro/edit" => {
+            if has_reference {
+                "fal-ai/flux-2-pro/edit"
+            } else {
+                "fal-ai/flux-2-pro"
+            }
+        }
+        "flux2-max" | "fal-ai/flux-2-max" | "fal-ai/flux-2-max/edit" => {
+            if has_reference {
+                "fal-ai/flux-2-max/edit"
+            } else {
+                "fal-ai/flux-2-max"
+            }
+        }
         "flux2-hdr" | "fal-ai/flux-2-lora-gallery/hdr-style" => "fal-ai/flux-2-lora-gallery/hdr-style",
         "imagen4" | "fal-ai/imagen4/preview" => "fal-ai/imagen4/preview",
         "imagen4-fast" | "fal-ai/imagen4/preview/fast" => "fal-ai/imagen4/preview/fast",
@@ -41,15 +58,53 @@ fn resolve_model(model: &str, has_reference: bool) -> &str {
                 "fal-ai/z-image/turbo"
             }
         }
+        "flux-fill" | "fal-ai/flux-pro/v1/fill" => FILL_ENDPOINT,
         _ => model,
     }
 }
 
+/// Encode an image file as a data URI
+fn data_uri(path: &Path) -> Result<String> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read image {}", path.display()))?;
+    let b64 = base64::engine::general_purpose::STANDARD.encode(&data);
+    Ok(format!("data:{};base64,{}", super::mime_type(path), b64))
+}
+
+/// Reference images an endpoint accepts, and whether it takes them as a list
+/// (`image_urls`) rather than a single `image_url`
+fn reference_support(model_id: &str) -> (usize, bool) {
+    match model_id {
+        "fal-ai/z-image/turbo/image-to-image" => (1, false),
+        "fal-ai/flux-2-pro/edit" | "fal-ai/flux-2-max/edit" => (4, true),
+        // Custom models (models.json): one reference goes in image_url, more in image_urls
+        _ => crate::custom_models::load()
+            .find_by_endpoint(model_id)
+            .map(|m| (m.max_refs as usize, m.max_refs > 1))
+            .unwrap_or((0, false)),
+    }
+}
+
+/// Whether an endpoint draws transparent backgrounds itself when sent
+/// `background: "transparent"`; results from others need /rembg
+pub fn native_transparency(model: &str) -> bool {
+    let model_id = resolve_model(model, false);
+    model_id.starts_with("fal-ai/gpt-image-1/")
+        || crate::custom_models::load()
+            .find_by_endpoint(model_id)
+            .is_some_and(|m| m.transparent)
+}
+
 #[derive(Serialize)]
 struct FalRequest {
     prompt: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     image_url: Option<String>,
+    /// Multi-reference endpoints (FLUX 2 edit) take a list instead of image_url
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_urls: Option<Vec<String>>,
+    /// Inpainting mask (fill endpoint): white = regenerate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mask_url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     image_size: Option<String>,
     /// Aspect ratio for models that use ratio strings (e.g. Imagen 4: "1:1", "16:9")
@@ -59,6 +114,17 @@ struct FalRequest {
     /// Higher = more influence from prompt, lower = more from reference
     #[serde(skip_serializing_if = "Option::is_none")]
     strength: Option<f64>,
+    /// Images per call (1-4); omitted for a single image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_images: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    /// "transparent" for `--transparent` on endpoints that support it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    background: Option<String>,
+    /// "png" alongside a transparent background, so the alpha survives
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_format: Option<String>,
 }
 
 /// Response from fal.ai - can be either a queue status or the final result
@@ -67,8 +133,12 @@ struct FalResponse {
     // Queue status fields
     status: Option<String>,
     response_url: Option<String>,
+    cancel_url: Option<String>,
+    queue_position: Option<u32>,
     // Result fields
     images: Option<Vec<FalImage>>,
+    /// Video endpoints return one clip instead of images
+    video: Option<FalVideo>,
     seed: Option<u64>,
     error: Option<String>,
 }
@@ -76,10 +146,88 @@ struct FalResponse {
 #[derive(Deserialize, Debug)]
 struct FalImage {
     url: String,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+/// Cancels a queued fal.ai request if polling stops before it finishes
+/// (job cancelled, poll error, timeout), so it isn't run and billed anyway
+struct QueueGuard {
+    cancel_url: Option<String>,
+    api_key: String,
+    finished: bool,
+}
+
+impl Drop for QueueGuard {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let Some(url) = self.cancel_url.take() else {
+            return;
+        };
+        let api_key = std::mem::take(&mut self.api_key);
+        crate::cancel::spawn_cleanup(async move {
+            let _ = super::client()
+                .put(url)
+                .header("Authorization", format!("Key {}", api_key))
+                .timeout(Duration::from_secs(5))
+                .send()
+                .await;
+        });
+    }
+}
+
+/// Megapixel-billed endpoints: (rate for the first MP, rate per additional MP).
+/// fal counts a megapixel as 1024x1024, so square_hd is exactly 1 MP.
+/// Pricing (as of Jan 2026):
+/// - z-image/turbo (+ image-to-image): $0.005/MP; with LoRA: $0.0085/MP
+/// - flux/schnell: $0.003/MP
+/// - flux-pro/v1.1: $0.04/MP
+/// - flux-pro/v1/fill: $0.05/MP
+/// - flux-2/turbo: $0.008/MP
+/// - flux-2-pro: $0.03 first MP, $0.015 each additional
+/// - flux-2-max: $0.07 first MP, $0.03 each additional
+///
+/// Flat per-image models (Ultra, Recraft, Imagen 4) return None and keep the estimate.
+fn megapixel_rates(model_id: &str) -> Option<(f64, f64)> {
+    match model_id {
+        "fal-ai/z-image/turbo" | "fal-ai/z-image/turbo/image-to-image" => Some((0.005, 0.005)),
+        "fal-ai/z-image/turbo/lora" => Some((0.0085, 0.0085)),
+        "fal-ai/flux/schnell" => Some((0.003, 0.003)),
+        "fal-ai/flux-pro/v1.1" => Some((0.04, 0.04)),
+        FILL_ENDPOINT => Some((0.05, 0.05)),
+        "fal-ai/flux-2/turbo" => Some((0.008, 0.008)),
+        "fal-ai/flux-2-pro" => Some((0.03, 0.015)),
+        "fal-ai/flux-2-max" => Some((0.07, 0.03)),
+        _ => None,
+    }
+}
+
+/// Calculate cost from the returned image dimensions
+fn calculate_cost(model_id: &str, width: u32, height: u32) -> Option<f64> {
+    let (first_rate, extra_rate) = megapixel_rates(model_id)?;
+    let megapixels = (width as f64 * height as f64) / (1024.0 * 1024.0);
+    let extra = (megapixels - 1.0).max(0.0);
+    Some(first_rate * megapixels.min(1.0) + extra_rate * extra)
+}
+
+/// Per-image cost of a request before it runs, for megapixel-billed endpoints,
+/// at the size its `image_size` preset renders
+pub fn estimate_cost(req: &GenerationRequest) -> Option<f64> {
+    let model_id = resolve_model(&req.model, !req.reference_paths.is_empty());
+    let (width, height) = match resolve_image_size(req.width, req.height).as_str() {
+        "landscape_4_3" => (1024, 768),
+        "portrait_4_3" => (768, 1024),
+        "landscape_16_9" => (1024, 576),
+        "portrait_16_9" => (576, 1024),
+        _ => (1024, 1024),
+    };
+    calculate_cost(model_id, width, height)
 }
 
 fn get_api_key() -> Result<String> {
-    std::env::var("FAL_KEY").context("FAL_KEY environment variable not set")
+    keys::get("fal")
 }
 
 /// Map pixel dimensions to fal.ai image_size string names
@@ -114,27 +262,85 @@ fn resolve_aspect_ratio(width: Option<i32>, height: Option<i32>) -> String {
     }
 }
 
-pub async fn generate(
-    model: &str,
-    prompt: &str,
-    reference_paths: &[String],
-    _negative_prompt: Option<&str>,
-    width: Option<i32>,
-    height: Option<i32>,
-) -> Result<GenerationResult> {
+/// fal.ai hosted models (FLUX, Recraft, Z-Image)
+pub struct Fal;
+
+#[async_trait::async_trait]
+impl super::ImageProvider for Fal {
+    fn kind(&self) -> Provider {
+        Provider::Fal
+    }
+
+    async fn generate(
+        &self,
+        req: &GenerationRequest,
+        progress: super::Progress<'_>,
+    ) -> Result<GenerationResult> {
+        generate(req, progress).await
+    }
+
+    async fn generate_video(
+        &self,
+        info: &VideoModelInfo,
+        req: &VideoRequest,
+        progress: super::Progress<'_>,
+    ) -> Result<VideoResult> {
+        generate_video(info, req, progress).await
+    }
+
+    async fn check(&self) -> Result<Option<super::HealthCheck>> {
+        let endpoint = format!("{}/models", PLATFORM_API);
+        let request = super::client()
+            .get(&endpoint)
+            .header("Authorization", format!("Key {}", get_api_key()?))
+            .query(&[("limit", "1")]);
+        super::timed_check("fal.ai", &endpoint, request).await.map(Some)
+    }
+}
+
+async fn generate(req: &GenerationRequest, progress: super::Progress<'_>) -> Result<GenerationResult> {
+    let model = req.model.as_str();
+    let prompt = req.prompt.as_str();
+    let reference_paths = &req.reference_paths;
+    let (width, height) = (req.width, req.height);
     let api_key = get_api_key()?;
     let has_reference = !reference_paths.is_empty();
     let model_id = resolve_model(model, has_reference);
 
-    // Build image_url from reference if provided (max 1 for Z-Image)
-    let image_url = if let Some(ref_path) = reference_paths.first() {
-        let path = Path::new(ref_path);
-        let data = std::fs::read(path).context("Failed to read reference image")?;
-        let b64 = base64::engine::general_purpose::STANDARD.encode(&data);
-        let mime = super::mime_type(path);
-        Some(format!("data:{};base64,{}", mime, b64))
+    // Reject extra references instead of silently dropping them
+    let (max_refs, takes_list) = reference_support(model_id);
+    if reference_paths.len() > max_refs {
+        if max_refs == 0 {
+            anyhow::bail!("{} does not accept reference images", model);
+        }
+        anyhow::bail!(
+            "{} accepts at most {} reference image(s), got {}",
+            model,
+            max_refs,
+            reference_paths.len()
+        );
+    }
+
+    // Masks only go to the fill endpoint, and it does nothing else
+    let is_fill = model_id == FILL_ENDPOINT;
+    match (&req.inpaint, is_fill) {
+        (Some(_), false) => anyhow::bail!("{} can't inpaint; use -m flux-fill", model),
+        (None, true) => anyhow::bail!("flux-fill only inpaints (see 'pixery inpaint')"),
+        _ => {}
+    }
+
+    let data_uris = reference_paths
+        .iter()
+        .map(|ref_path| data_uri(Path::new(ref_path)))
+        .collect::<Result<Vec<_>>>()?;
+    let (image_url, image_urls) = if let Some(inpaint) = &req.inpaint {
+        (Some(data_uri(Path::new(&inpaint.image_path))?), None)
+    } else if data_uris.is_empty() {
+        (None, None)
+    } else if takes_list {
+        (None, Some(data_uris))
     } else {
-        None
+        (data_uris.into_iter().next(), None)
     };
 
     // Set strength for image-to-image models (0.6 default balances prompt vs reference)
@@ -144,24 +350,101 @@ pub async fn generate(
         None
     };
 
+    let mask_url = match &req.inpaint {
+        Some(inpaint) => Some(data_uri(Path::new(&inpaint.mask_path))?),
+        None => None,
+    };
+
+    // Fill keeps the source image's size
     let uses_aspect_ratio = model_id.starts_with("fal-ai/imagen4/");
+    let transparent = req.transparent && native_transparency(model_id);
     let request = FalRequest {
         prompt: prompt.to_string(),
         image_url,
-        image_size: if uses_aspect_ratio { None } else { Some(resolve_image_size(width, height)) },
+        image_urls,
+        mask_url,
+        image_size: if uses_aspect_ratio || is_fill { None } else { Some(resolve_image_size(width, height)) },
         aspect_ratio: if uses_aspect_ratio { Some(resolve_aspect_ratio(width, height)) } else { None },
         strength,
+        num_images: req.num_images.filter(|&n| n > 1).map(|n| n.min(MAX_IMAGES_PER_CALL)),
+        seed: req.seed,
+        background: transparent.then(|| "transparent".to_string()),
+        output_format: transparent.then(|| "png".to_string()),
     };
 
+    let start = Instant::now();
+    let data = submit(model_id, &request, &api_key, MAX_POLL_ATTEMPTS, progress).await?;
+
+    // Get image URLs from response
+    let image_infos = data.images.unwrap_or_default();
+    if image_infos.is_empty() {
+        anyhow::bail!("No images in fal.ai response");
+    }
+
+    // Fetch the actual images
+    progress(ProgressStage::Downloading);
+    let client = super::client();
+    let mut images = Vec::with_capacity(image_infos.len());
+    let mut costs = Vec::with_capacity(image_infos.len());
+    for image_info in image_infos {
+        let image_response = client
+            .get(&image_info.url)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .context("Failed to fetch image from fal.ai")?;
+
+        if !image_response.status().is_success() {
+            anyhow::bail!("Failed to fetch image: {}", image_response.status());
+        }
+
+        let image_data = image_response
+            .bytes()
+            .await
+            .context("Failed to read image bytes")?
+            .to_vec();
+
+        // Bill from the dimensions fal reports, or the decoded image if it doesn't
+        let dimensions = match (image_info.width, image_info.height) {
+            (Some(w), Some(h)) => Some((w, h)),
+            _ => image::ImageReader::new(std::io::Cursor::new(&image_data))
+                .with_guessed_format()
+                .ok()
+                .and_then(|reader| reader.into_dimensions().ok()),
+        };
+        costs.push(dimensions.and_then(|(w, h)| calculate_cost(model_id, w, h)));
+        images.push(image_data);
+    }
+
+    let elapsed = start.elapsed().as_secs_f64();
+    let cost_usd = costs.into_iter().sum::<Option<f64>>();
+
+    Ok(GenerationResult {
+        images,
+        seed: data.seed.map(|s| s.to_string()),
+        generation_time_seconds: elapsed,
+        cost_usd,
+    })
+}
+
+/// POST a request to an endpoint and, if fal queues it, poll until it's done
+/// (up to `max_polls` seconds). A queued request is cancelled if polling stops early.
+async fn submit<T: Serialize>(
+    model_id: &str,
+    request: &T,
+    api_key: &str,
+    max_polls: u32,
+    progress: super::Progress<'_>,
+) -> Result<FalResponse> {
     let url = format!("{}/{}", API_BASE, model_id);
     let client = super::client();
 
-    let start = Instant::now();
+    progress(ProgressStage::RequestSent);
     let response = client
         .post(&url)
         .header("Authorization", format!("Key {}", api_key))
         .header("Content-Type", "application/json")
-        .json(&request)
+        .json(request)
         .timeout(std::time::Duration::from_secs(300)) // 5 minutes - Ultra models can be slow
         .send()
         .await
@@ -170,7 +453,12 @@ pub async fn generate(
     if !response.status().is_success() {
         let status = response.status();
         let text = response.text().await.unwrap_or_default();
-        anyhow::bail!("fal.ai API error {}: {}", status, text);
+        return Err(super::ApiError {
+            provider: "fal.ai",
+            status,
+            body: text,
+        }
+        .into());
     }
 
     let mut data: FalResponse = response.json().await.context("Failed to parse fal.ai response")?;
@@ -181,11 +469,18 @@ pub async fn generate(
 
     // Handle queue-based response - poll until complete
     if data.status.as_deref() == Some("IN_QUEUE") || data.status.as_deref() == Some("IN_PROGRESS") {
+        progress(ProgressStage::InQueue { position: data.queue_position });
         let response_url = data
             .response_url
+            .take()
             .ok_or_else(|| anyhow::anyhow!("Queue response missing response_url"))?;
+        let mut guard = QueueGuard {
+            cancel_url: data.cancel_url.take(),
+            api_key: api_key.to_string(),
+            finished: false,
+        };
 
-        for attempt in 0..MAX_POLL_ATTEMPTS {
+        for attempt in 0..max_polls {
             tokio::time::sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
 
             let poll_response = client
@@ -220,8 +515,8 @@ pub async fn generate(
                 anyhow::bail!("fal.ai API error: {}", error);
             }
 
-            // Check if we have images now
-            if data.images.is_some() {
+            // Check if we have the result now
+            if data.images.is_some() || data.video.is_some() {
                 break;
             }
 
@@ -229,46 +524,273 @@ pub async fn generate(
             if data.status.as_deref() == Some("IN_QUEUE")
                 || data.status.as_deref() == Some("IN_PROGRESS")
             {
+                progress(ProgressStage::InQueue { position: data.queue_position });
                 continue;
             }
 
             // Unknown status with no images
-            if attempt == MAX_POLL_ATTEMPTS - 1 {
+            if attempt == max_polls - 1 {
                 anyhow::bail!("Timeout waiting for fal.ai generation");
             }
         }
+        guard.finished = true;
     }
+    Ok(data)
+}
 
-    // Get image URL from response
-    let image_info = data
-        .images
-        .and_then(|images| images.into_iter().next())
-        .ok_or_else(|| anyhow::anyhow!("No images in fal.ai response"))?;
-
-    // Fetch the actual image
-    let image_response = client
-        .get(&image_info.url)
-        .timeout(Duration::from_secs(30))
-        .send()
-        .await
-        .context("Failed to fetch image from fal.ai")?;
+// -- Video ----------------------------------------------------------------------
 
-    let elapsed = start.elapsed().as_secs_f64();
+/// Polls before giving up on a clip (15 minutes; Kling Master often takes 5+)
+const VIDEO_MAX_POLL_ATTEMPTS: u32 = 900;
 
-    if !image_response.status().is_success() {
-        anyhow::bail!("Failed to fetch image: {}", image_response.status());
+/// Endpoint for a video model, text- or image-to-video
+fn resolve_video_model(model: &str, has_image: bool) -> Result<&'static str> {
+    match (model, has_image) {
+        ("kling", false) => Ok("fal-ai/kling-video/v2.1/master/text-to-video"),
+        ("kling", true) => Ok("fal-ai/kling-video/v2.1/master/image-to-video"),
+        _ => anyhow::bail!("{} is not a fal.ai video model", model),
     }
+}
 
-    let image_data = image_response
-        .bytes()
+#[derive(Serialize)]
+struct FalVideoRequest {
+    prompt: String,
+    /// Start frame (image-to-video endpoints)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_url: Option<String>,
+    /// Seconds, as a string ("5")
+    duration: String,
+    /// Text-to-video only; image-to-video follows the start frame
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aspect_ratio: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    negative_prompt: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FalVideo {
+    url: String,
+}
+
+/// Generate a clip with a fal.ai video model (`pixery video`)
+async fn generate_video(
+    info: &VideoModelInfo,
+    req: &VideoRequest,
+    progress: super::Progress<'_>,
+) -> Result<VideoResult> {
+    let api_key = get_api_key()?;
+    let model_id = resolve_video_model(info.id, req.image_path.is_some())?;
+    let duration = req.duration.unwrap_or(info.durations[0]);
+    let request = FalVideoRequest {
+        prompt: req.prompt.clone(),
+        image_url: req.image_path.as_deref().map(|p| data_uri(Path::new(p))).transpose()?,
+        duration: duration.to_string(),
+        aspect_ratio: match &req.image_path {
+            Some(_) => None,
+            None => Some(req.aspect_ratio.clone().unwrap_or_else(|| info.aspect_ratios[0].to_string())),
+        },
+        negative_prompt: req.negative_prompt.clone(),
+    };
+
+    let start = Instant::now();
+    let data = submit(model_id, &request, &api_key, VIDEO_MAX_POLL_ATTEMPTS, progress).await?;
+    let video_info = data.video.context("No video in fal.ai response")?;
+
+    progress(ProgressStage::Downloading);
+    let response = super::client()
+        .get(&video_info.url)
+        .timeout(Duration::from_secs(120))
+        .send()
         .await
-        .context("Failed to read image bytes")?
-        .to_vec();
+        .context("Failed to fetch video from fal.ai")?;
+    if !response.status().is_success() {
+        anyhow::bail!("Failed to fetch video: {}", response.status());
+    }
+    let video = response.bytes().await.context("Failed to read video bytes")?.to_vec();
 
-    Ok(GenerationResult {
-        image_data,
+    Ok(VideoResult {
+        video,
         seed: data.seed.map(|s| s.to_string()),
-        generation_time_seconds: elapsed,
-        cost_usd: None, // fal.ai doesn't return token-based billing
+        generation_time_seconds: start.elapsed().as_secs_f64(),
+        cost_usd: Some(info.estimate_cost(Some(duration))),
     })
 }
+
+// -- Model catalog ------------------------------------------------------------
+//
+// `pixery models --refresh` caches fal's text-to-image endpoints (with pricing)
+// in fal-catalog.json so new models show up without a release. Image-to-image and
+// edit endpoints take references in endpoint-specific ways; add those through
+// models.json with an explicit max_refs instead.
+
+const PLATFORM_API: &str = "https://api.fal.ai/v1";
+const CATALOG_CATEGORY: &str = "text-to-image";
+/// Endpoint IDs per pricing request
+const PRICING_BATCH: usize = 50;
+
+/// Cached catalog, written by `refresh_catalog`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Catalog {
+    pub fetched_at: String,
+    pub models: Vec<CatalogModel>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogModel {
+    pub endpoint_id: String,
+    pub display_name: String,
+    /// USD per `unit`; None when fal didn't return a price
+    pub unit_price: Option<f64>,
+    pub unit: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ModelsPage {
+    #[serde(default)]
+    models: Vec<ModelsEntry>,
+    next_cursor: Option<String>,
+    #[serde(default)]
+    has_more: bool,
+}
+
+#[derive(Deserialize)]
+struct ModelsEntry {
+    endpoint_id: String,
+    #[serde(default)]
+    metadata: Option<ModelsMetadata>,
+}
+
+#[derive(Deserialize)]
+struct ModelsMetadata {
+    display_name: Option<String>,
+    status: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PricingResponse {
+    #[serde(default)]
+    prices: Vec<PriceEntry>,
+}
+
+#[derive(Deserialize)]
+struct PriceEntry {
+    endpoint_id: String,
+    unit_price: f64,
+    unit: Option<String>,
+}
+
+pub fn catalog_path() -> std::path::PathBuf {
+    crate::archive::archive_root().join("fal-catalog.json")
+}
+
+/// Cached catalog, or an empty one if it was never refreshed (or can't be read)
+pub fn load_catalog() -> Catalog {
+    std::fs::read_to_string(catalog_path())
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Catalog entries as models, skipping endpoints `existing` already covers
+/// (built-ins are often listed under a short name like `imagen4`)
+pub fn catalog_models(existing: &[ModelInfo]) -> Vec<ModelInfo> {
+    let known: std::collections::HashSet<String> = existing
+        .iter()
+        .filter(|m| m.provider == Provider::Fal)
+        .map(|m| resolve_model(&m.id, false).to_string())
+        .collect();
+    load_catalog()
+        .models
+        .into_iter()
+        .filter(|m| !known.contains(&m.endpoint_id))
+        .map(|m| ModelInfo {
+            display_name: format!("{} (fal catalog)", m.display_name),
+            // Per-megapixel prices are close enough to per-image at the default 1024x1024
+            cost_per_image: m.unit_price.unwrap_or(0.0),
+            id: m.endpoint_id,
+            provider: Provider::Fal,
+            max_refs: 0,
+        })
+        .collect()
+}
+
+/// Fetch fal's active text-to-image endpoints and their prices, and cache them.
+/// Returns the new catalog.
+pub async fn refresh_catalog() -> Result<Catalog> {
+    let api_key = get_api_key()?;
+    let client = super::client();
+
+    let mut models = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut query = vec![("category", CATALOG_CATEGORY.to_string()), ("limit", "100".to_string())];
+        if let Some(c) = &cursor {
+            query.push(("cursor", c.clone()));
+        }
+        let response = client
+            .get(format!("{}/models", PLATFORM_API))
+            .header("Authorization", format!("Key {}", api_key))
+            .query(&query)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .context("Failed to fetch fal.ai model list")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            anyhow::bail!("fal.ai model list error {}: {}", status, text);
+        }
+        let page: ModelsPage = response.json().await.context("Failed to parse fal.ai model list")?;
+
+        for entry in page.models {
+            let metadata = entry.metadata.unwrap_or(ModelsMetadata { display_name: None, status: None });
+            if metadata.status.as_deref().is_some_and(|s| s != "active") {
+                continue;
+            }
+            models.push(CatalogModel {
+                display_name: metadata.display_name.unwrap_or_else(|| entry.endpoint_id.clone()),
+                endpoint_id: entry.endpoint_id,
+                unit_price: None,
+                unit: None,
+            });
+        }
+
+        match page.next_cursor {
+            Some(next) if page.has_more => cursor = Some(next),
+            _ => break,
+        }
+    }
+
+    // Pricing is best-effort: a model without a price is still usable
+    let ids: Vec<String> = models.iter().map(|m| m.endpoint_id.clone()).collect();
+    for batch in ids.chunks(PRICING_BATCH) {
+        let query: Vec<(&str, &str)> = batch.iter().map(|id| ("endpoint_id", id.as_str())).collect();
+        let response = client
+            .get(format!("{}/models/pricing", PLATFORM_API))
+            .header("Authorization", format!("Key {}", api_key))
+            .query(&query)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await;
+        let Ok(response) = response else { continue };
+        if !response.status().is_success() {
+            continue;
+        }
+        let Ok(pricing) = response.json::<PricingResponse>().await else { continue };
+        for price in pricing.prices {
+            if let Some(model) = models.iter_mut().find(|m| m.endpoint_id == price.endpoint_id) {
+                model.unit_price = Some(price.unit_price);
+                model.unit = price.unit;
+            }
+        }
+    }
+
+    models.sort_by(|a, b| a.endpoint_id.cmp(&b.endpoint_id));
+    let catalog = Catalog {
+        fetched_at: chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        models,
+    };
+    let json = serde_json::to_string_pretty(&catalog)?;
+    std::fs::write(catalog_path(), json).context("Failed to write fal.ai catalog cache")?;
+    Ok(catalog)
+}
@@ -2,9 +2,11 @@ use anyhow::{Context, Result};
 use base64::Engine;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use crate::models::GenerationResult;
+use crate::error::PixeryError;
+use crate::keys;
+use crate::models::{GenerationRequest, GenerationResult, ProgressStage, Provider, VideoModelInfo, VideoRequest, VideoResult};
 
 const API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta/models";
 
@@ -53,6 +55,9 @@ struct GenerationConfig {
 struct GeminiResponse {
     candidates: Option<Vec<Candidate>>,
     error: Option<GeminiError>,
+    /// Set when the prompt itself was blocked (no candidates then)
+    #[serde(rename = "promptFeedback")]
+    prompt_feedback: Option<PromptFeedback>,
     #[serde(rename = "usageMetadata")]
     usage_metadata: Option<UsageMetadata>,
 }
@@ -71,8 +76,68 @@ struct GeminiError {
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    block_reason: Option<String>,
+    #[serde(default)]
+    safety_ratings: Vec<SafetyRating>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct Candidate {
     content: Option<CandidateContent>,
+    finish_reason: Option<String>,
+    /// Explanation that sometimes comes with a non-STOP finish reason
+    finish_message: Option<String>,
+    #[serde(default)]
+    safety_ratings: Vec<SafetyRating>,
+}
+
+#[derive(Deserialize)]
+struct SafetyRating {
+    category: String,
+    #[serde(default)]
+    blocked: bool,
+}
+
+/// Finish reasons meaning a safety filter stopped the image
+const BLOCKED_FINISH_REASONS: &[&str] = &[
+    "SAFETY",
+    "IMAGE_SAFETY",
+    "PROHIBITED_CONTENT",
+    "IMAGE_PROHIBITED_CONTENT",
+    "BLOCKLIST",
+    "SPII",
+];
+
+/// "HARM_CATEGORY_SEXUALLY_EXPLICIT" -> "sexually explicit"
+fn humanize(code: &str) -> String {
+    code.trim_start_matches("HARM_CATEGORY_").replace('_', " ").to_lowercase()
+}
+
+/// The refusal in a successful response, if any: the prompt blocked outright
+/// (promptFeedback) or the image stopped by a filter (finishReason). The
+/// reason names the filter and the categories Gemini marked as blocked.
+fn content_policy_error(data: &GeminiResponse) -> Option<PixeryError> {
+    let (reason, ratings, message) = match &data.prompt_feedback {
+        Some(PromptFeedback { block_reason: Some(reason), safety_ratings }) => (reason, safety_ratings, None),
+        _ => {
+            let candidate = data.candidates.as_ref()?.first()?;
+            let reason = candidate.finish_reason.as_ref()?;
+            if !BLOCKED_FINISH_REASONS.contains(&reason.as_str()) {
+                return None;
+            }
+            (reason, &candidate.safety_ratings, candidate.finish_message.as_deref())
+        }
+    };
+    let mut parts = vec![humanize(reason)];
+    parts.extend(ratings.iter().filter(|r| r.blocked).map(|r| humanize(&r.category)));
+    let mut reason = parts.join(", ");
+    if let Some(message) = message {
+        reason = format!("{}: {}", reason, message);
+    }
+    Some(PixeryError::ContentPolicy { provider: "Gemini".to_string(), reason: Some(reason) })
 }
 
 #[derive(Deserialize)]
@@ -91,10 +156,8 @@ struct ResponseInlineData {
     data: String,
 }
 
-fn get_api_key() -> Result<String> {
-    std::env::var("GEMINI_API_SECRET_KEY")
-        .or_else(|_| std::env::var("GEMINI_API_KEY"))
-        .context("GEMINI_API_SECRET_KEY or GEMINI_API_KEY environment variable not set")
+pub fn get_api_key() -> Result<String> {
+    keys::get("gemini")
 }
 
 /// Calculate cost based on token usage
@@ -133,14 +196,48 @@ fn calculate_cost(model: &str, usage: &UsageMetadata) -> Option<f64> {
     Some(input_cost + output_cost)
 }
 
-pub async fn generate(
-    model: &str,
-    prompt: &str,
-    reference_paths: &[String],
-    _negative_prompt: Option<&str>,
-    _width: Option<i32>,
-    _height: Option<i32>,
-) -> Result<GenerationResult> {
+/// Google Gemini image models
+pub struct Gemini;
+
+#[async_trait::async_trait]
+impl super::ImageProvider for Gemini {
+    fn kind(&self) -> Provider {
+        Provider::Gemini
+    }
+
+    async fn generate(
+        &self,
+        req: &GenerationRequest,
+        progress: super::Progress<'_>,
+    ) -> Result<GenerationResult> {
+        generate(req, progress).await
+    }
+
+    async fn generate_video(
+        &self,
+        info: &VideoModelInfo,
+        req: &VideoRequest,
+        progress: super::Progress<'_>,
+    ) -> Result<VideoResult> {
+        generate_video(info, req, progress).await
+    }
+
+    async fn check(&self) -> Result<Option<super::HealthCheck>> {
+        let request = super::client()
+            .get(API_BASE)
+            .header("x-goog-api-key", get_api_key()?)
+            .query(&[("pageSize", "1")]);
+        super::timed_check("Gemini", API_BASE, request).await.map(Some)
+    }
+}
+
+async fn generate(req: &GenerationRequest, progress: super::Progress<'_>) -> Result<GenerationResult> {
+    if req.inpaint.is_some() {
+        anyhow::bail!("Gemini models don't support mask inpainting");
+    }
+    let model = req.model.as_str();
+    let prompt = req.prompt.as_str();
+    let reference_paths = &req.reference_paths;
     let api_key = get_api_key()?;
     let model_id = resolve_model(model);
 
@@ -178,6 +275,7 @@ pub async fn generate(
     let client = super::client();
 
     let start = Instant::now();
+    progress(ProgressStage::RequestSent);
     let response = client
         .post(&url)
         .header("x-goog-api-key", &api_key)
@@ -193,7 +291,12 @@ pub async fn generate(
     if !response.status().is_success() {
         let status = response.status();
         let text = response.text().await.unwrap_or_default();
-        anyhow::bail!("Gemini API error {}: {}", status, text);
+        return Err(super::ApiError {
+            provider: "Gemini",
+            status,
+            body: text,
+        }
+        .into());
     }
 
     let data: GeminiResponse = response.json().await.context("Failed to parse Gemini response")?;
@@ -201,6 +304,9 @@ pub async fn generate(
     if let Some(error) = data.error {
         anyhow::bail!("Gemini API error: {}", error.message);
     }
+    if let Some(refusal) = content_policy_error(&data) {
+        return Err(refusal.into());
+    }
 
     // Calculate actual cost from token usage
     let cost_usd = data
@@ -226,9 +332,200 @@ pub async fn generate(
         .context("Failed to decode base64 image data")?;
 
     Ok(GenerationResult {
-        image_data: image_bytes,
+        images: vec![image_bytes],
         seed: None,
         generation_time_seconds: elapsed,
         cost_usd,
     })
 }
+
+// -- Video (Veo) ------------------------------------------------------------------
+
+/// Long-running operations are polled under this base ("v1beta/{name}")
+const OPERATIONS_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
+/// Veo clips take one to several minutes
+const VIDEO_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const VIDEO_MAX_POLL_ATTEMPTS: u32 = 120;
+
+fn resolve_video_model(model: &str) -> Result<&'static str> {
+    match model {
+        "veo3" => Ok("veo-3.0-generate-001"),
+        "veo3-fast" => Ok("veo-3.0-fast-generate-001"),
+        _ => anyhow::bail!("{} is not a Gemini video model", model),
+    }
+}
+
+#[derive(Serialize)]
+struct VeoRequest {
+    instances: Vec<VeoInstance>,
+    parameters: VeoParameters,
+}
+
+#[derive(Serialize)]
+struct VeoInstance {
+    prompt: String,
+    /// Start frame (image-to-video)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<VeoImage>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VeoImage {
+    bytes_base64_encoded: String,
+    mime_type: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VeoParameters {
+    aspect_ratio: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    negative_prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+}
+
+/// A long-running operation: `name` to poll, then `response` once `done`
+#[derive(Deserialize)]
+struct Operation {
+    name: Option<String>,
+    #[serde(default)]
+    done: bool,
+    error: Option<GeminiError>,
+    response: Option<OperationResponse>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OperationResponse {
+    generate_video_response: Option<GenerateVideoResponse>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateVideoResponse {
+    #[serde(default)]
+    generated_samples: Vec<GeneratedSample>,
+    /// Why the responsible-AI filters dropped the clip
+    #[serde(default)]
+    rai_media_filtered_reasons: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct GeneratedSample {
+    video: Option<VideoFile>,
+}
+
+#[derive(Deserialize)]
+struct VideoFile {
+    uri: String,
+}
+
+/// Generate a clip with Veo (`pixery video`): start a long-running operation,
+/// poll it, then download the file it produced
+async fn generate_video(
+    info: &VideoModelInfo,
+    req: &VideoRequest,
+    progress: super::Progress<'_>,
+) -> Result<VideoResult> {
+    let api_key = get_api_key()?;
+    let model_id = resolve_video_model(info.id)?;
+    let image = match &req.image_path {
+        Some(path) => {
+            let path = Path::new(path);
+            Some(VeoImage {
+                bytes_base64_encoded: super::image_to_base64(path).context("Failed to read start frame")?,
+                mime_type: super::mime_type(path).to_string(),
+            })
+        }
+        None => None,
+    };
+    let request = VeoRequest {
+        instances: vec![VeoInstance { prompt: req.prompt.clone(), image }],
+        parameters: VeoParameters {
+            aspect_ratio: req.aspect_ratio.clone().unwrap_or_else(|| info.aspect_ratios[0].to_string()),
+            negative_prompt: req.negative_prompt.clone(),
+            seed: req.seed,
+        },
+    };
+
+    let client = super::client();
+    let start = Instant::now();
+    progress(ProgressStage::RequestSent);
+    let response = client
+        .post(format!("{}/{}:predictLongRunning", API_BASE, model_id))
+        .header("x-goog-api-key", &api_key)
+        .json(&request)
+        .timeout(Duration::from_secs(60))
+        .send()
+        .await
+        .context("Failed to send request to Gemini API")?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(super::ApiError { provider: "Gemini", status, body }.into());
+    }
+    let mut operation: Operation = response.json().await.context("Failed to parse Veo response")?;
+    let name = operation.name.clone().context("Veo response has no operation name")?;
+
+    let mut polls = 0;
+    while !operation.done {
+        if polls == VIDEO_MAX_POLL_ATTEMPTS {
+            anyhow::bail!("Timeout waiting for Veo generation");
+        }
+        polls += 1;
+        progress(ProgressStage::InQueue { position: None });
+        tokio::time::sleep(VIDEO_POLL_INTERVAL).await;
+        let response = client
+            .get(format!("{}/{}", OPERATIONS_BASE, name))
+            .header("x-goog-api-key", &api_key)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .context("Failed to poll Veo operation")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(super::ApiError { provider: "Gemini", status, body }.into());
+        }
+        operation = response.json().await.context("Failed to parse Veo operation")?;
+    }
+    if let Some(error) = operation.error {
+        anyhow::bail!("Veo error: {}", error.message);
+    }
+
+    let video_response = operation.response.and_then(|r| r.generate_video_response);
+    if let Some(filtered) = video_response.as_ref().filter(|r| !r.rai_media_filtered_reasons.is_empty()) {
+        return Err(PixeryError::ContentPolicy {
+            provider: "Gemini".to_string(),
+            reason: Some(filtered.rai_media_filtered_reasons.join("; ")),
+        }
+        .into());
+    }
+    let uri = video_response
+        .and_then(|r| r.generated_samples.into_iter().next())
+        .and_then(|s| s.video)
+        .map(|v| v.uri)
+        .context("No video in Veo response (it may have been blocked by safety filters)")?;
+
+    progress(ProgressStage::Downloading);
+    let response = client
+        .get(&uri)
+        .header("x-goog-api-key", &api_key)
+        .timeout(Duration::from_secs(120))
+        .send()
+        .await
+        .context("Failed to fetch video from Gemini")?;
+    if !response.status().is_success() {
+        anyhow::bail!("Failed to fetch video: {}", response.status());
+    }
+    let video = response.bytes().await.context("Failed to read video bytes")?.to_vec();
+
+    Ok(VideoResult {
+        video,
+        seed: req.seed.map(|s| s.to_string()),
+        generation_time_seconds: start.elapsed().as_secs_f64(),
+        cost_usd: Some(info.estimate_cost(req.duration)),
+    })
+}
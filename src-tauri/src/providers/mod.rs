@@ -1,8 +1,12 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::future::Future;
 use std::path::Path;
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, Instant};
 
-use crate::models::{GenerationResult, ModelInfo, Provider};
+use crate::keys;
+use crate::models::{GenerationRequest, GenerationResult, ModelInfo, ProgressStage, Provider, VideoModelInfo, VideoRequest, VideoResult};
 
 static HTTP_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
 
@@ -11,22 +15,165 @@ pub fn client() -> &'static reqwest::Client {
     HTTP_CLIENT.get_or_init(reqwest::Client::new)
 }
 
+/// Progress callback threaded through providers. Sync so generation futures stay Send.
+pub type Progress<'a> = &'a (dyn Fn(ProgressStage) + Send + Sync);
+
+pub mod comfyui;
 pub mod fal;
 pub mod gemini;
 pub mod openai;
 pub mod selfhosted;
 
+/// A generation backend. Each provider module implements this on a unit struct
+/// and is registered in `registry()`, so adding a provider never touches the
+/// dispatch in `generate`.
+#[async_trait::async_trait]
+pub trait ImageProvider: Send + Sync {
+    /// Which `Provider` this backend serves (matched against `ModelInfo.provider`)
+    fn kind(&self) -> Provider;
+
+    /// Generate one image. Fields the API doesn't support are ignored.
+    async fn generate(
+        &self,
+        req: &GenerationRequest,
+        progress: Progress<'_>,
+    ) -> Result<GenerationResult>;
+
+    /// Generate one video clip with one of this provider's `VideoModelInfo`
+    /// models. Providers without video models keep the default.
+    async fn generate_video(
+        &self,
+        info: &VideoModelInfo,
+        req: &VideoRequest,
+        progress: Progress<'_>,
+    ) -> Result<VideoResult> {
+        let _ = (req, progress);
+        anyhow::bail!("{} has no video models ({})", self.kind(), info.id)
+    }
+
+    /// Verify the key or server with a cheap authenticated call (a model list
+    /// or health endpoint). None when there is nothing configured to check.
+    async fn check(&self) -> Result<Option<HealthCheck>> {
+        Ok(None)
+    }
+}
+
+/// How long a health check waits for its response
+const CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A successful provider health check
+#[derive(Debug, Clone)]
+pub struct HealthCheck {
+    /// URL that was called
+    pub endpoint: String,
+    pub latency: Duration,
+    /// Rate-limit and quota response headers, for APIs that send them
+    pub quota: Vec<(String, String)>,
+}
+
+/// Send a health check request and time it; a non-success status is an `ApiError`
+pub async fn timed_check(provider: &'static str, endpoint: &str, request: reqwest::RequestBuilder) -> Result<HealthCheck> {
+    let start = Instant::now();
+    let response = request
+        .timeout(CHECK_TIMEOUT)
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {}", endpoint))?;
+    let latency = start.elapsed();
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(ApiError { provider, status, body }.into());
+    }
+
+    let quota = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| name.as_str().contains("ratelimit") || name.as_str().contains("quota"))
+        .map(|(name, value)| (name.to_string(), value.to_str().unwrap_or_default().to_string()))
+        .collect();
+    Ok(HealthCheck {
+        endpoint: endpoint.to_string(),
+        latency,
+        quota,
+    })
+}
+
+/// Per-image cost estimate for a request, without calling any API: size-based
+/// pricing where the provider bills by megapixel, else the model's flat
+/// `cost_per_image`. None for models with no known price.
+pub fn estimate_cost(req: &GenerationRequest) -> Option<f64> {
+    let model = ModelInfo::canonical_id(&req.model);
+    let info = ModelInfo::find(&model)?;
+    if info.provider == Provider::Fal {
+        let mut req = req.clone();
+        req.model = crate::custom_models::load().endpoint_for(&model).unwrap_or(&model).to_string();
+        if let Some(cost) = fal::estimate_cost(&req) {
+            return Some(cost);
+        }
+    }
+    Some(info.cost_per_image)
+}
+
+/// Fail before any work is queued if the model's provider needs an API key
+/// that isn't configured
+pub fn check_credentials(model: &str) -> Result<()> {
+    let provider = ModelInfo::provider_for_model(&ModelInfo::canonical_id(model))
+        .or_else(|| VideoModelInfo::find(model).map(|info| info.provider))
+        .map(|p| p.to_string());
+    match provider.filter(|p| keys::needs_key(p)) {
+        Some(provider) => keys::get(&provider).map(|_| ()),
+        None => Ok(()),
+    }
+}
+
+static REGISTRY: OnceLock<RwLock<HashMap<Provider, Arc<dyn ImageProvider>>>> = OnceLock::new();
+
+/// Provider registry, seeded with the built-in providers on first use
+fn registry() -> &'static RwLock<HashMap<Provider, Arc<dyn ImageProvider>>> {
+    REGISTRY.get_or_init(|| {
+        let builtins: Vec<Arc<dyn ImageProvider>> = vec![
+            Arc::new(gemini::Gemini),
+            Arc::new(fal::Fal),
+            Arc::new(openai::OpenAI),
+            Arc::new(selfhosted::SelfHosted),
+            Arc::new(comfyui::ComfyUI),
+        ];
+        RwLock::new(builtins.into_iter().map(|p| (p.kind(), p)).collect())
+    })
+}
+
+/// Register a provider, replacing any existing one of the same kind
+pub fn register(provider: Arc<dyn ImageProvider>) {
+    let mut map = registry().write().unwrap_or_else(|e| e.into_inner());
+    map.insert(provider.kind(), provider);
+}
+
+/// Every registered provider, by name
+pub fn all() -> Vec<Arc<dyn ImageProvider>> {
+    let map = registry().read().unwrap_or_else(|e| e.into_inner());
+    let mut providers: Vec<_> = map.values().cloned().collect();
+    providers.sort_by_key(|p| p.kind().to_string());
+    providers
+}
+
+/// Look up the registered provider for a kind
+pub fn get(kind: Provider) -> Option<Arc<dyn ImageProvider>> {
+    let map = registry().read().unwrap_or_else(|e| e.into_inner());
+    map.get(&kind).cloned()
+}
+
 /// Generate an image using the appropriate provider for the model
-pub async fn generate(
-    model: &str,
-    prompt: &str,
-    reference_paths: &[String],
-    negative_prompt: Option<&str>,
-    width: Option<i32>,
-    height: Option<i32>,
-    ip_scale: Option<f64>,
-) -> Result<GenerationResult> {
-    let provider = ModelInfo::provider_for_model(model)
+pub async fn generate(req: &GenerationRequest, progress: Progress<'_>) -> Result<GenerationResult> {
+    // Aliases resolve to a model ID; custom models name their provider model string
+    let config = crate::custom_models::load();
+    let model = ModelInfo::canonical_id(&req.model);
+    let mut req = req.clone();
+    req.model = config.endpoint_for(&model).unwrap_or(&model).to_string();
+    let req = &req;
+
+    let kind = ModelInfo::provider_for_model(&model)
         .or_else(|| {
             // Fallback: route unknown models to self-hosted server if configured
             if selfhosted::get_server_url().is_some() {
@@ -35,13 +182,155 @@ pub async fn generate(
                 None
             }
         })
-        .ok_or_else(|| anyhow::anyhow!("Unknown model: {}", model))?;
+        .ok_or_else(|| anyhow::anyhow!("Unknown model: {}", req.model))?;
+
+    // Clone the Arc out so the registry lock isn't held across the request
+    let provider = get(kind)
+        .ok_or_else(|| anyhow::anyhow!("No provider registered for {}", kind))?;
+    let mut result = provider.generate(req, progress).await?;
+
+    // Models with native transparency make the background themselves; other
+    // models' results have theirs removed by the self-hosted server. An image
+    // that can't be cut out is still worth keeping, just not as transparent.
+    if req.transparent {
+        for image in &mut result.images {
+            if !has_alpha(image) {
+                match selfhosted::remove_background(image).await {
+                    Ok(cut_out) => *image = cut_out,
+                    Err(e) => eprintln!("Background not removed, saving the opaque image: {:#}", e),
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Whether a model draws transparent backgrounds itself (gpt-image-1, and fal
+/// endpoints that take `background`), rather than needing /rembg afterwards
+pub fn native_transparency(model: &str) -> bool {
+    let config = crate::custom_models::load();
+    let model = ModelInfo::canonical_id(model);
+    let endpoint = config.endpoint_for(&model).unwrap_or(&model);
+    match ModelInfo::find(&model).map(|m| m.provider) {
+        Some(Provider::OpenAI) => endpoint == "gpt-image-1",
+        Some(Provider::Fal) => fal::native_transparency(endpoint),
+        _ => false,
+    }
+}
+
+/// Whether image bytes have an alpha channel, from their header
+pub fn has_alpha(data: &[u8]) -> bool {
+    use image::ImageDecoder;
+    image::ImageReader::new(std::io::Cursor::new(data))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_decoder().ok())
+        .is_some_and(|decoder| decoder.color_type().has_alpha())
+}
+
+/// Generate a video clip using the provider of the video model
+pub async fn generate_video(req: &VideoRequest, progress: Progress<'_>) -> Result<VideoResult> {
+    let info = VideoModelInfo::find(&req.model).ok_or_else(|| anyhow::anyhow!("Unknown video model: {}", req.model))?;
+    let provider = get(info.provider)
+        .ok_or_else(|| anyhow::anyhow!("No provider registered for {}", info.provider))?;
+    provider.generate_video(&info, req, progress).await
+}
+
+/// Non-success HTTP response from a provider API.
+/// Kept as a typed error so the retry layer can inspect the status code.
+#[derive(Debug, thiserror::Error)]
+#[error("{provider} API error {status}: {body}")]
+pub struct ApiError {
+    pub provider: &'static str,
+    pub status: reqwest::StatusCode,
+    pub body: String,
+}
+
+/// Retry policy for transient provider failures
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts including the first (1 = no retries)
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Default policy, with attempts overridable via PIXERY_RETRY_ATTEMPTS
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        if let Some(attempts) = std::env::var("PIXERY_RETRY_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+        {
+            policy.max_attempts = attempts.max(1);
+        }
+        policy
+    }
 
-    match provider {
-        Provider::Gemini => gemini::generate(model, prompt, reference_paths, negative_prompt, width, height).await,
-        Provider::Fal => fal::generate(model, prompt, reference_paths, negative_prompt, width, height).await,
-        Provider::OpenAI => openai::generate(model, prompt, reference_paths, negative_prompt, width, height).await,
-        Provider::SelfHosted => selfhosted::generate(model, prompt, reference_paths, negative_prompt, width, height, ip_scale).await,
+    /// Exponential backoff for the given retry (1-based), with up to 50% jitter
+    /// so parallel batch jobs don't hammer a rate-limited API in lockstep.
+    fn delay_for(&self, retry: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() as u64)
+            .unwrap_or(0);
+        let jitter_ms = nanos % (exp.as_millis() as u64 / 2 + 1);
+        exp / 2 + Duration::from_millis(jitter_ms)
+    }
+}
+
+/// Whether an error is worth retrying: rate limits, server errors, timeouts
+/// and connection failures. Client errors (bad prompt, bad key) are not.
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    for cause in err.chain() {
+        if let Some(api) = cause.downcast_ref::<ApiError>() {
+            return api.status.as_u16() == 429 || api.status.is_server_error();
+        }
+        if let Some(req) = cause.downcast_ref::<reqwest::Error>() {
+            return req.is_timeout() || req.is_connect();
+        }
+    }
+    false
+}
+
+/// Run `attempt` until it succeeds, fails with a non-transient error, or the
+/// policy's attempts are exhausted. `on_retry` is called with the retry number
+/// and the error before each backoff sleep (used to record retries on the job).
+pub async fn with_retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    mut attempt: F,
+    mut on_retry: impl FnMut(u32, &anyhow::Error),
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut retries = 0;
+    loop {
+        match attempt().await {
+            Ok(v) => return Ok(v),
+            Err(e) if retries + 1 < policy.max_attempts && is_transient(&e) => {
+                retries += 1;
+                on_retry(retries, &e);
+                tokio::time::sleep(policy.delay_for(retries)).await;
+            }
+            Err(e) => return Err(e),
+        }
     }
 }
 
@@ -60,6 +349,9 @@ pub fn mime_type(path: &Path) -> &'static str {
         Some("jpg") | Some("jpeg") => "image/jpeg",
         Some("webp") => "image/webp",
         Some("gif") => "image/gif",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("mov") => "video/quicktime",
         _ => "image/png",
     }
 }
@@ -1,11 +1,17 @@
 use anyhow::{Context, Result};
 use base64::Engine;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 use std::time::Instant;
 
-use crate::models::GenerationResult;
+use crate::error::PixeryError;
+use crate::keys;
+use crate::models::{GenerationRequest, GenerationResult, Inpaint, ProgressStage, Provider};
 
 const API_URL: &str = "https://api.openai.com/v1/images/generations";
+const EDITS_URL: &str = "https://api.openai.com/v1/images/edits";
+const MODELS_URL: &str = "https://api.openai.com/v1/models";
+const MAX_IMAGES_PER_CALL: u32 = 10;
 
 /// Model ID mapping
 fn resolve_model(model: &str) -> &str {
@@ -28,17 +34,62 @@ struct OpenAIRequest {
     quality: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     style: Option<String>,
+    /// "transparent" (gpt-image-1 only, with PNG or WebP output)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    background: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_format: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct OpenAIResponse {
     data: Option<Vec<OpenAIImage>>,
     error: Option<OpenAIError>,
+    /// Token usage (gpt-image-1 only; DALL-E omits it)
+    usage: Option<ImageUsage>,
+}
+
+#[derive(Deserialize)]
+struct ImageUsage {
+    input_tokens: Option<u32>,
+    output_tokens: Option<u32>,
+    input_tokens_details: Option<InputTokensDetails>,
+}
+
+#[derive(Deserialize)]
+struct InputTokensDetails {
+    text_tokens: Option<u32>,
+    image_tokens: Option<u32>,
 }
 
 #[derive(Deserialize)]
 struct OpenAIError {
     message: String,
+    code: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIErrorBody {
+    error: OpenAIError,
+}
+
+/// Error codes for a prompt or input image refused by the safety system
+const POLICY_CODES: &[&str] = &["moderation_blocked", "content_policy_violation"];
+
+/// The refusal in an error response body, if that's what it is. The reason is
+/// the categories OpenAI lists ("... safety_violations=[sexual].") when it does.
+fn content_policy_error(body: &str) -> Option<PixeryError> {
+    let error = serde_json::from_str::<OpenAIErrorBody>(body).ok()?.error;
+    if !POLICY_CODES.contains(&error.code.as_deref()?) {
+        return None;
+    }
+    let reason = error
+        .message
+        .split_once("safety_violations=[")
+        .and_then(|(_, rest)| rest.split_once(']'))
+        .map(|(list, _)| list.split(',').map(str::trim).collect::<Vec<_>>().join(", "))
+        .filter(|list| !list.is_empty());
+    Some(PixeryError::ContentPolicy { provider: "OpenAI".to_string(), reason })
 }
 
 #[derive(Deserialize)]
@@ -46,20 +97,106 @@ struct OpenAIImage {
     b64_json: Option<String>,
 }
 
-fn get_api_key() -> Result<String> {
-    std::env::var("OPENAI_API_SECRET_KEY")
-        .or_else(|_| std::env::var("OPENAI_API_KEY"))
-        .context("OPENAI_API_SECRET_KEY or OPENAI_API_KEY environment variable not set")
+/// Calculate cost based on token usage
+/// Pricing (as of Jan 2026):
+/// - gpt-image-1:
+///   - Input text: $5/1M tokens
+///   - Input image: $10/1M tokens
+///   - Output image: $40/1M tokens. The quality tier sets the output token count
+///     (1024x1024: low 272, medium 1056, high 4160), so usage already reflects it.
+fn calculate_cost(model: &str, usage: &ImageUsage) -> Option<f64> {
+    let (text_rate, image_rate, output_rate) = match model {
+        "gpt-image-1" => (5.0, 10.0, 40.0),
+        _ => return None,
+    };
+
+    let (text_tokens, image_tokens) = match &usage.input_tokens_details {
+        Some(details) => (details.text_tokens.unwrap_or(0), details.image_tokens.unwrap_or(0)),
+        // No breakdown: treat all input as text
+        None => (usage.input_tokens.unwrap_or(0), 0),
+    };
+    let output_tokens = usage.output_tokens.unwrap_or(0) as f64;
+
+    let input_cost = (text_tokens as f64 * text_rate + image_tokens as f64 * image_rate) / 1_000_000.0;
+    let output_cost = output_tokens * output_rate / 1_000_000.0;
+
+    Some(input_cost + output_cost)
+}
+
+/// Multipart body for /images/edits: the request's fields plus image and mask files
+fn edit_form(request: &OpenAIRequest, inpaint: &Inpaint) -> Result<reqwest::multipart::Form> {
+    let image_path = Path::new(&inpaint.image_path);
+    let image_data = std::fs::read(image_path).context("Failed to read source image")?;
+    let image = reqwest::multipart::Part::bytes(image_data)
+        .file_name("image")
+        .mime_str(super::mime_type(image_path))?;
+    let mask = reqwest::multipart::Part::bytes(transparent_mask(image_path, Path::new(&inpaint.mask_path))?)
+        .file_name("mask.png")
+        .mime_str("image/png")?;
+
+    let mut form = reqwest::multipart::Form::new()
+        .text("model", request.model.clone())
+        .text("prompt", request.prompt.clone())
+        .text("n", request.n.to_string())
+        .part("image", image)
+        .part("mask", mask);
+    // gpt-image-1 always returns base64 and rejects response_format
+    if request.model == "dall-e-2" {
+        form = form.text("response_format", request.response_format.clone());
+    }
+    if let (Some(background), Some(format)) = (&request.background, &request.output_format) {
+        form = form.text("background", background.clone()).text("output_format", format.clone());
+    }
+    Ok(form)
+}
+
+/// OpenAI masks mark the area to edit as transparent; ours mark it white.
+/// The mask is scaled to the source size, which the API requires.
+fn transparent_mask(image_path: &Path, mask_path: &Path) -> Result<Vec<u8>> {
+    let (width, height) = image::image_dimensions(image_path).context("Failed to read source image size")?;
+    let mask = image::open(mask_path)
+        .context("Failed to read mask")?
+        .resize_exact(width, height, image::imageops::FilterType::Nearest)
+        .to_luma8();
+    let rgba = image::RgbaImage::from_fn(width, height, |x, y| {
+        let alpha = if mask.get_pixel(x, y)[0] > 127 { 0 } else { 255 };
+        image::Rgba([0, 0, 0, alpha])
+    });
+    let mut png = std::io::Cursor::new(Vec::new());
+    rgba.write_to(&mut png, image::ImageFormat::Png)?;
+    Ok(png.into_inner())
+}
+
+pub fn get_api_key() -> Result<String> {
+    keys::get("openai")
+}
+
+/// OpenAI image models (DALL-E, gpt-image-1)
+pub struct OpenAI;
+
+#[async_trait::async_trait]
+impl super::ImageProvider for OpenAI {
+    fn kind(&self) -> Provider {
+        Provider::OpenAI
+    }
+
+    async fn generate(
+        &self,
+        req: &GenerationRequest,
+        progress: super::Progress<'_>,
+    ) -> Result<GenerationResult> {
+        generate(req, progress).await
+    }
+
+    async fn check(&self) -> Result<Option<super::HealthCheck>> {
+        let request = super::client().get(MODELS_URL).bearer_auth(get_api_key()?);
+        super::timed_check("OpenAI", MODELS_URL, request).await.map(Some)
+    }
 }
 
-pub async fn generate(
-    model: &str,
-    prompt: &str,
-    _reference_paths: &[String],
-    _negative_prompt: Option<&str>,
-    _width: Option<i32>,
-    _height: Option<i32>,
-) -> Result<GenerationResult> {
+async fn generate(req: &GenerationRequest, progress: super::Progress<'_>) -> Result<GenerationResult> {
+    let model = req.model.as_str();
+    let prompt = req.prompt.as_str();
     let api_key = get_api_key()?;
     let model_id = resolve_model(model);
 
@@ -69,7 +206,12 @@ pub async fn generate(
     let request = OpenAIRequest {
         model: model_id.to_string(),
         prompt: prompt.to_string(),
-        n: 1,
+        // DALL-E 3 only accepts n=1
+        n: if model_id == "dall-e-3" {
+            1
+        } else {
+            req.num_images.unwrap_or(1).clamp(1, MAX_IMAGES_PER_CALL)
+        },
         size: "1024x1024".to_string(),
         response_format: "b64_json".to_string(),
         quality: if model_id == "dall-e-3" {
@@ -82,16 +224,31 @@ pub async fn generate(
         } else {
             None
         },
+        // DALL-E can't; its images get the background removed afterwards
+        background: (req.transparent && model_id == "gpt-image-1").then(|| "transparent".to_string()),
+        output_format: (req.transparent && model_id == "gpt-image-1").then(|| "png".to_string()),
     };
 
     let client = super::client();
 
+    // Inpainting goes to the edits endpoint as multipart; everything else is JSON
+    let builder = match &req.inpaint {
+        Some(inpaint) => {
+            if !matches!(model_id, "gpt-image-1" | "dall-e-2") {
+                anyhow::bail!("{} can't inpaint; use gpt-image-1", model);
+            }
+            client.post(EDITS_URL).multipart(edit_form(&request, inpaint)?)
+        }
+        None => client
+            .post(API_URL)
+            .header("Content-Type", "application/json")
+            .json(&request),
+    };
+
     let start = Instant::now();
-    let response = client
-        .post(API_URL)
+    progress(ProgressStage::RequestSent);
+    let response = builder
         .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&request)
         .timeout(std::time::Duration::from_secs(120))
         .send()
         .await
@@ -102,7 +259,15 @@ pub async fn generate(
     if !response.status().is_success() {
         let status = response.status();
         let text = response.text().await.unwrap_or_default();
-        anyhow::bail!("OpenAI API error {}: {}", status, text);
+        if let Some(refusal) = content_policy_error(&text) {
+            return Err(refusal.into());
+        }
+        return Err(super::ApiError {
+            provider: "OpenAI",
+            status,
+            body: text,
+        }
+        .into());
     }
 
     let data: OpenAIResponse = response.json().await.context("Failed to parse OpenAI response")?;
@@ -111,21 +276,32 @@ pub async fn generate(
         anyhow::bail!("OpenAI API error: {}", error.message);
     }
 
+    // Calculate actual cost from token usage
+    let cost_usd = data
+        .usage
+        .as_ref()
+        .and_then(|usage| calculate_cost(model_id, usage));
+
     // Extract image data
-    let b64_data = data
+    let images = data
         .data
-        .and_then(|images| images.into_iter().next())
-        .and_then(|img| img.b64_json)
-        .ok_or_else(|| anyhow::anyhow!("No image data in OpenAI response"))?;
-
-    let image_data = base64::engine::general_purpose::STANDARD
-        .decode(&b64_data)
-        .context("Failed to decode base64 image data")?;
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|img| img.b64_json)
+        .map(|b64| {
+            base64::engine::general_purpose::STANDARD
+                .decode(&b64)
+                .context("Failed to decode base64 image data")
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if images.is_empty() {
+        anyhow::bail!("No image data in OpenAI response");
+    }
 
     Ok(GenerationResult {
-        image_data,
+        images,
         seed: None,
         generation_time_seconds: elapsed,
-        cost_usd: None, // OpenAI doesn't return token-based billing
+        cost_usd,
     })
 }
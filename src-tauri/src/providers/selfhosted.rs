@@ -4,9 +4,11 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::time::Instant;
 
-use crate::models::GenerationResult;
+use crate::models::{GenerationRequest, GenerationResult, ProgressStage, Provider};
 
 const REQUEST_TIMEOUT_SECS: u64 = 300; // 5 minutes - model loading can be slow
+const POLL_INTERVAL_MS: u64 = 500;
+const MAX_REFERENCES: usize = 4; // IP-Adapter image embeddings, combined server-side
 
 #[derive(Serialize)]
 struct SelfHostedRequest {
@@ -20,12 +22,26 @@ struct SelfHostedRequest {
     height: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     reference_image: Option<String>,
+    /// All references when there is more than one (servers before multi-ref read only reference_image)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reference_images: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     ip_adapter_scale: Option<f64>,
+    /// Inpainting source and mask (white = regenerate), base64
+    #[serde(skip_serializing_if = "Option::is_none")]
+    init_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mask_image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strength: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cfg_scale: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     lora_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     lora_scale: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -104,26 +120,301 @@ pub async fn check_health(url: &str) -> Result<HealthResponse> {
         .context("Failed to parse health response")
 }
 
+/// Turn a non-success response into an error, using the server's `detail` when present
+async fn response_error(response: reqwest::Response) -> anyhow::Error {
+    let status = response.status();
+    let text = response.text().await.unwrap_or_default();
+    if let Ok(error) = serde_json::from_str::<SelfHostedError>(&text) {
+        return anyhow::anyhow!("Self-hosted server error: {}", error.detail);
+    }
+    super::ApiError {
+        provider: "Self-hosted server",
+        status,
+        body: text,
+    }
+    .into()
+}
+
+/// POST /generate and wait for the image (servers without /jobs)
+async fn generate_blocking(base_url: &str, request: &SelfHostedRequest) -> Result<SelfHostedResponse> {
+    let response = super::client()
+        .post(format!("{}/generate", base_url))
+        .json(request)
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .send()
+        .await
+        .context("Failed to send request to self-hosted server")?;
+
+    if !response.status().is_success() {
+        return Err(response_error(response).await);
+    }
+    response
+        .json()
+        .await
+        .context("Failed to parse self-hosted server response")
+}
+
+#[derive(Deserialize, Debug)]
+struct JobSubmitted {
+    job_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct JobStatus {
+    status: String,
+    position: Option<u32>,
+    #[serde(default)]
+    step: u32,
+    total_steps: Option<u32>,
+    error: Option<String>,
+    result: Option<SelfHostedResponse>,
+}
+
+/// Queue a generation with POST /jobs. Returns None if the server predates the queue.
+async fn submit_job(base_url: &str, request: &SelfHostedRequest) -> Result<Option<String>> {
+    let response = super::client()
+        .post(format!("{}/jobs", base_url))
+        .json(request)
+        .timeout(std::time::Duration::from_secs(30))
+        .send()
+        .await
+        .context("Failed to send request to self-hosted server")?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND || response.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(response_error(response).await);
+    }
+    let submitted: JobSubmitted = response.json().await.context("Failed to parse /jobs response")?;
+    Ok(Some(submitted.job_id))
+}
+
+/// Cancels the server-side job when polling stops early: the generation future
+/// was dropped (aborted) or failed, so nobody will collect the result
+struct JobGuard {
+    url: String,
+    finished: bool,
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        let url = std::mem::take(&mut self.url);
+        crate::cancel::spawn_cleanup(async move {
+            let _ = super::client()
+                .delete(url)
+                .timeout(std::time::Duration::from_secs(5))
+                .send()
+                .await;
+        });
+    }
+}
+
+/// Poll GET /jobs/{id} until the job finishes, reporting queue position and steps
+async fn poll_job(base_url: &str, job_id: &str, progress: super::Progress<'_>) -> Result<SelfHostedResponse> {
+    let url = format!("{}/jobs/{}", base_url, job_id);
+    let mut guard = JobGuard { url: url.clone(), finished: false };
+    let mut running_since: Option<Instant> = None;
+    let mut last_step = None;
+
+    loop {
+        let response = super::client()
+            .get(&url)
+            .timeout(std::time::Duration::from_secs(30))
+            .send()
+            .await
+            .context("Failed to poll self-hosted job")?;
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+        let job: JobStatus = response.json().await.context("Failed to parse job status")?;
+
+        match job.status.as_str() {
+            "queued" => progress(ProgressStage::InQueue { position: job.position }),
+            "running" => {
+                let since = *running_since.get_or_insert_with(Instant::now);
+                if since.elapsed().as_secs() > REQUEST_TIMEOUT_SECS {
+                    anyhow::bail!("Timeout waiting for self-hosted generation");
+                }
+                if let Some(total) = job.total_steps {
+                    if last_step != Some(job.step) {
+                        last_step = Some(job.step);
+                        progress(ProgressStage::Step { step: job.step, total });
+                    }
+                }
+            }
+            "done" => {
+                guard.finished = true;
+                return job.result.ok_or_else(|| anyhow::anyhow!("Self-hosted job finished without an image"));
+            }
+            "cancelled" => {
+                guard.finished = true;
+                anyhow::bail!("Self-hosted job was cancelled");
+            }
+            _ => {
+                guard.finished = true;
+                anyhow::bail!(
+                    "Self-hosted server error: {}",
+                    job.error.unwrap_or_else(|| format!("job {}", job.status))
+                );
+            }
+        }
+
+        tokio::time::sleep(std::time::Duration::from_millis(POLL_INTERVAL_MS)).await;
+    }
+}
+
+/// Server URL, or an error telling the user how to set one
+pub fn require_server_url() -> Result<String> {
+    get_server_url().ok_or_else(|| {
+        anyhow::anyhow!("Self-hosted server not configured (set SELFHOSTED_API_URL or the GUI Server URL)")
+    })
+}
+
+/// POST to a server endpoint that loads a model; these take as long as a generation
+async fn post_model_command<T: serde::de::DeserializeOwned>(url: &str, path: &str, body: serde_json::Value) -> Result<T> {
+    let response = super::client()
+        .post(format!("{}{}", url.trim_end_matches('/'), path))
+        .json(&body)
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .send()
+        .await
+        .with_context(|| format!("Failed to send request to self-hosted {}", path))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        let detail = serde_json::from_str::<SelfHostedError>(&text).map(|e| e.detail).unwrap_or(text);
+        anyhow::bail!("Self-hosted {} failed ({}): {}", path, status, detail);
+    }
+
+    response
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse {} response", path))
+}
+
+/// Load (or switch to) a model without generating
+pub async fn switch_model(url: &str, model: &str) -> Result<()> {
+    let _: serde_json::Value = post_model_command(url, "/switch-model", serde_json::json!({ "model": model })).await?;
+    Ok(())
+}
+
+/// Result of a server warm-up
+#[derive(Deserialize, Debug, Clone, Serialize)]
+pub struct WarmupResponse {
+    pub model: String,
+    pub load_seconds: f64,
+    pub warmup_seconds: f64,
+}
+
+/// Load a model (default: the server's current one) and run a tiny pass so the
+/// next real generation doesn't pay the cold start
+pub async fn warmup(url: &str, model: Option<&str>) -> Result<WarmupResponse> {
+    post_model_command(url, "/warmup", serde_json::json!({ "model": model })).await
+}
+
+#[derive(Deserialize)]
+struct RembgResponse {
+    image: String,
+}
+
+/// Send an image to the server's /rembg endpoint; returns PNG bytes with alpha.
+/// Used for `--ref-rembg` and for transparent results from models that can't
+/// make them.
+pub async fn remove_background(data: &[u8]) -> Result<Vec<u8>> {
+    let url = get_server_url().ok_or_else(|| {
+        anyhow::anyhow!("Background removal needs the self-hosted server (set SELFHOSTED_API_URL or the GUI Server URL)")
+    })?;
+
+    let response = super::client()
+        .post(format!("{}/rembg", url.trim_end_matches('/')))
+        .json(&serde_json::json!({
+            "image": base64::engine::general_purpose::STANDARD.encode(data),
+        }))
+        .timeout(std::time::Duration::from_secs(120))
+        .send()
+        .await
+        .context("Failed to send request to self-hosted /rembg")?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        anyhow::bail!("Background removal failed {}: {}", status, text);
+    }
+
+    let data: RembgResponse = response.json().await.context("Failed to parse /rembg response")?;
+    base64::engine::general_purpose::STANDARD
+        .decode(&data.image)
+        .context("Failed to decode background-removed image")
+}
+
+/// User-run inference server, URL from selfhosted.json
+pub struct SelfHosted;
+
+#[async_trait::async_trait]
+impl super::ImageProvider for SelfHosted {
+    fn kind(&self) -> Provider {
+        Provider::SelfHosted
+    }
+
+    async fn generate(
+        &self,
+        req: &GenerationRequest,
+        progress: super::Progress<'_>,
+    ) -> Result<GenerationResult> {
+        generate(req, progress).await
+    }
+
+    async fn check(&self) -> Result<Option<super::HealthCheck>> {
+        let Some(url) = get_server_url() else {
+            return Ok(None);
+        };
+        let endpoint = format!("{}/health", url.trim_end_matches('/'));
+        let request = super::client().get(&endpoint);
+        super::timed_check("Self-hosted server", &endpoint, request).await.map(Some)
+    }
+}
+
 /// Generate an image using the self-hosted inference server
-pub async fn generate(
-    model: &str,
-    prompt: &str,
-    reference_paths: &[String],
-    negative_prompt: Option<&str>,
-    width: Option<i32>,
-    height: Option<i32>,
-    ip_scale: Option<f64>,
-) -> Result<GenerationResult> {
+async fn generate(req: &GenerationRequest, progress: super::Progress<'_>) -> Result<GenerationResult> {
+    let model = req.model.as_str();
+    let prompt = req.prompt.as_str();
+    let reference_paths = &req.reference_paths;
+    let negative_prompt = req.negative_prompt.as_deref();
+    let (width, height, ip_scale) = (req.width, req.height, req.ip_scale);
     let base_url = get_server_url()
         .ok_or_else(|| anyhow::anyhow!("Self-hosted server URL not configured"))?;
 
-    // Build reference image as base64 (server expects bare base64, not data URI)
-    let reference_image = if let Some(ref_path) = reference_paths.first() {
-        let path = Path::new(ref_path);
-        let data = std::fs::read(path).context("Failed to read reference image")?;
-        Some(base64::engine::general_purpose::STANDARD.encode(&data))
-    } else {
-        None
+    if reference_paths.len() > MAX_REFERENCES {
+        anyhow::bail!(
+            "Self-hosted models accept at most {} reference images, got {}",
+            MAX_REFERENCES,
+            reference_paths.len()
+        );
+    }
+
+    // Build reference images as base64 (server expects bare base64, not data URI)
+    let encoded = reference_paths
+        .iter()
+        .map(|ref_path| {
+            let data = std::fs::read(Path::new(ref_path)).context("Failed to read reference image")?;
+            Ok(base64::engine::general_purpose::STANDARD.encode(&data))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let reference_image = encoded.first().cloned();
+    let reference_images = (encoded.len() > 1).then_some(encoded);
+
+    let encode_file = |path: &str| -> Result<String> {
+        let data = std::fs::read(Path::new(path)).with_context(|| format!("Failed to read {}", path))?;
+        Ok(base64::engine::general_purpose::STANDARD.encode(&data))
+    };
+    let (init_image, mask_image) = match &req.inpaint {
+        Some(inpaint) => (Some(encode_file(&inpaint.image_path)?), Some(encode_file(&inpaint.mask_path)?)),
+        None => (None, None),
     };
 
     // Set IP adapter scale only when reference is provided
@@ -140,38 +431,25 @@ pub async fn generate(
         width: Some(width.unwrap_or(1024)),
         height: Some(height.unwrap_or(1024)),
         reference_image,
+        reference_images,
         ip_adapter_scale,
+        init_image,
+        mask_image,
+        strength: req.inpaint.as_ref().and_then(|i| i.strength),
+        cfg_scale: req.cfg_scale,
         lora_name: None,
         lora_scale: None,
+        seed: req.seed,
     };
 
-    let url = format!("{}/generate", base_url.trim_end_matches('/'));
-    let client = super::client();
-
+    let base_url = base_url.trim_end_matches('/');
     let start = Instant::now();
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .json(&request)
-        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT_SECS))
-        .send()
-        .await
-        .context("Failed to send request to self-hosted server")?;
-
-    let status = response.status();
-    if !status.is_success() {
-        let text = response.text().await.unwrap_or_default();
-        // Try to parse as JSON error
-        if let Ok(error) = serde_json::from_str::<SelfHostedError>(&text) {
-            anyhow::bail!("Self-hosted server error: {}", error.detail);
-        }
-        anyhow::bail!("Self-hosted server error ({}): {}", status, text);
-    }
-
-    let data: SelfHostedResponse = response
-        .json()
-        .await
-        .context("Failed to parse self-hosted server response")?;
+    progress(ProgressStage::RequestSent);
+    let data = match submit_job(base_url, &request).await? {
+        Some(job_id) => poll_job(base_url, &job_id, progress).await?,
+        // Servers without the job queue: one blocking request
+        None => generate_blocking(base_url, &request).await?,
+    };
 
     let elapsed = start.elapsed().as_secs_f64();
 
@@ -181,7 +459,7 @@ pub async fn generate(
         .context("Failed to decode base64 image from server")?;
 
     Ok(GenerationResult {
-        image_data,
+        images: vec![image_data],
         seed: data.seed.map(|s| s.to_string()),
         generation_time_seconds: elapsed,
         cost_usd: None, // Self-hosted has no direct API cost
@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::archive;
+
+/// Tauri commands a read-only window still answers: browsing, search,
+/// details and settings reads. Anything not listed is refused, so a new
+/// command stays locked in guest mode until it's added here.
+const ALLOWED_COMMANDS: &[&str] = &[
+    "list_generations",
+    "list_generations_page",
+    "count_generations",
+    "random_generations",
+    "get_filter_facets",
+    "search_generations",
+    "get_generation",
+    "copy_image_to_clipboard",
+    "list_tags",
+    "list_models",
+    "get_model_quotas",
+    "get_model_catalog",
+    "list_presets",
+    "get_cost_summary",
+    "get_stats",
+    "get_image_path",
+    "get_references",
+    "list_references",
+    "get_reference_generations",
+    "list_jobs",
+    "list_failed_jobs",
+    "list_collections",
+    "list_smart_collections",
+    "list_experiments",
+    "get_experiment",
+    "activity_feed",
+    "prompt_history",
+    "get_selfhosted_url",
+    "check_selfhosted_health",
+    "get_maintenance_status",
+    "get_notify_settings",
+    "get_nsfw_settings",
+    "get_currency_settings",
+    "get_display_currency",
+    "get_read_only",
+    "get_slideshow",
+    "take_focus_generation",
+];
+
+/// Set by `pixery --read-only` for this launch
+static FORCED: AtomicBool = AtomicBool::new(false);
+
+/// Guest mode for every GUI launch (`pixery read-only on`), stored in
+/// readonly.json in the archive root. The CLI is never affected.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReadOnlySettings {
+    pub enabled: bool,
+}
+
+fn settings_path() -> PathBuf {
+    archive::archive_root().join("readonly.json")
+}
+
+pub fn load_settings() -> ReadOnlySettings {
+    std::fs::read_to_string(settings_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &ReadOnlySettings) -> Result<()> {
+    std::fs::write(settings_path(), serde_json::to_string_pretty(settings)?)
+        .context("Failed to write read-only settings")
+}
+
+/// Open the GUI read-only this time, whatever readonly.json says
+pub fn force() {
+    FORCED.store(true, Ordering::Relaxed);
+}
+
+/// Whether the GUI runs read-only: forced for this launch or on in readonly.json
+pub fn is_enabled() -> bool {
+    FORCED.load(Ordering::Relaxed) || load_settings().enabled
+}
+
+/// Whether a read-only window may run the Tauri command `name`
+pub fn allows(name: &str) -> bool {
+    ALLOWED_COMMANDS.contains(&name)
+}
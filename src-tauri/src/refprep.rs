@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::archive;
+use crate::providers;
+
+/// Aspect ratio to center-crop a reference to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefCrop {
+    Square,
+    /// 2:3
+    Portrait,
+    /// 3:2
+    Landscape,
+}
+
+impl RefCrop {
+    fn ratio(self) -> (u32, u32) {
+        match self {
+            RefCrop::Square => (1, 1),
+            RefCrop::Portrait => (2, 3),
+            RefCrop::Landscape => (3, 2),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            RefCrop::Square => "square",
+            RefCrop::Portrait => "portrait",
+            RefCrop::Landscape => "landscape",
+        }
+    }
+}
+
+impl std::str::FromStr for RefCrop {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "square" | "1:1" => Ok(RefCrop::Square),
+            "portrait" | "2:3" => Ok(RefCrop::Portrait),
+            "landscape" | "3:2" => Ok(RefCrop::Landscape),
+            _ => Err(format!("Unknown crop: {} (expected square, portrait or landscape)", s)),
+        }
+    }
+}
+
+/// Steps applied to each reference before it is sent, in order:
+/// background removal, crop, resize
+#[derive(Debug, Clone, Default)]
+pub struct RefPrep {
+    /// Longest side in pixels (never upscales)
+    pub resize: Option<u32>,
+    pub crop: Option<RefCrop>,
+    /// Remove the background via the self-hosted server's /rembg endpoint
+    pub rembg: bool,
+}
+
+impl RefPrep {
+    pub fn is_empty(&self) -> bool {
+        self.resize.is_none() && self.crop.is_none() && !self.rembg
+    }
+
+    /// Cache file suffix naming the steps, e.g. `rembg-square-1024`
+    fn cache_key(&self) -> String {
+        let mut parts = vec![];
+        if self.rembg {
+            parts.push("rembg".to_string());
+        }
+        if let Some(crop) = self.crop {
+            parts.push(crop.name().to_string());
+        }
+        if let Some(size) = self.resize {
+            parts.push(size.to_string());
+        }
+        parts.join("-")
+    }
+}
+
+/// Processed references, keyed by source hash and steps so reruns are free
+pub fn cache_dir() -> PathBuf {
+    archive::archive_root().join("ref-cache")
+}
+
+/// Preprocess one reference image. Returns the processed file's path, or the
+/// original path when there is nothing to do.
+pub async fn preprocess(source: &Path, prep: &RefPrep) -> Result<PathBuf> {
+    if prep.is_empty() {
+        return Ok(source.to_path_buf());
+    }
+
+    let data = std::fs::read(source)
+        .with_context(|| format!("Failed to read reference image {}", source.display()))?;
+    let hash = archive::hash_bytes(&data)?;
+    let cached = cache_dir().join(format!("{}-{}.png", hash, prep.cache_key()));
+    if cached.exists() {
+        return Ok(cached);
+    }
+
+    let data = if prep.rembg { providers::selfhosted::remove_background(&data).await? } else { data };
+    let mut img = image::load_from_memory(&data).context("Failed to decode reference image")?;
+
+    if let Some(crop) = prep.crop {
+        let (rw, rh) = crop.ratio();
+        let (w, h) = (img.width(), img.height());
+        // Largest centered box with the target ratio
+        let (cw, ch) = if w as u64 * rh as u64 > h as u64 * rw as u64 {
+            (h * rw / rh, h)
+        } else {
+            (w, w * rh / rw)
+        };
+        img = img.crop_imm((w - cw) / 2, (h - ch) / 2, cw, ch);
+    }
+
+    if let Some(size) = prep.resize {
+        if img.width().max(img.height()) > size {
+            img = img.resize(size, size, image::imageops::FilterType::Lanczos3);
+        }
+    }
+
+    std::fs::create_dir_all(cache_dir()).context("Failed to create reference cache directory")?;
+    img.save_with_format(&cached, image::ImageFormat::Png)
+        .context("Failed to write processed reference")?;
+    Ok(cached)
+}
+
+/// Preprocess every reference path in order
+pub async fn preprocess_all(paths: &[String], prep: &RefPrep) -> Result<Vec<String>> {
+    let mut processed = Vec::with_capacity(paths.len());
+    for path in paths {
+        let out = preprocess(Path::new(path), prep).await?;
+        processed.push(out.to_string_lossy().to_string());
+    }
+    Ok(processed)
+}
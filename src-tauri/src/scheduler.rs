@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::archive;
+use crate::cancel;
+use crate::db::Database;
+use crate::models::{GenerationRequest, JobSource, Schedule};
+use crate::notify;
+use crate::workflow;
+
+/// How often runners check for due schedules
+pub const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Scheduler settings, stored in scheduler.json in the archive root
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerSettings {
+    /// Fire schedules from the GUI while it is open (read at startup)
+    pub run_in_gui: bool,
+}
+
+fn settings_path() -> std::path::PathBuf {
+    archive::archive_root().join("scheduler.json")
+}
+
+pub fn load_settings() -> SchedulerSettings {
+    std::fs::read_to_string(settings_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &SchedulerSettings) -> Result<()> {
+    std::fs::write(settings_path(), serde_json::to_string_pretty(settings)?)
+        .context("Failed to write scheduler settings")
+}
+
+/// Parse a cron expression. Standard 5-field expressions (`min hour dom mon dow`)
+/// get a leading seconds field; 6/7-field expressions are passed through.
+pub fn parse_cron(expr: &str) -> Result<cron::Schedule> {
+    let expr = expr.trim();
+    let full = if expr.split_whitespace().count() == 5 {
+        format!("0 {}", expr)
+    } else {
+        expr.to_string()
+    };
+    cron::Schedule::from_str(&full).map_err(|e| anyhow::anyhow!("Invalid cron expression '{}': {}", expr, e))
+}
+
+/// When the schedule last fired, or when it was created if it never has
+fn anchor(schedule: &Schedule) -> Option<DateTime<Local>> {
+    if let Some(last) = &schedule.last_run_at {
+        let naive = NaiveDateTime::parse_from_str(last, TIMESTAMP_FORMAT).ok()?;
+        return Local.from_local_datetime(&naive).earliest();
+    }
+    // created_at is SQLite CURRENT_TIMESTAMP (UTC)
+    let naive = NaiveDateTime::parse_from_str(&schedule.created_at, "%Y-%m-%d %H:%M:%S").ok()?;
+    Some(chrono::Utc.from_utc_datetime(&naive).with_timezone(&Local))
+}
+
+/// Next fire time after the last run (may be in the past if a run is due)
+pub fn next_run(schedule: &Schedule) -> Option<DateTime<Local>> {
+    let cron = parse_cron(&schedule.cron).ok()?;
+    let after = anchor(schedule)?;
+    cron.after(&after).next()
+}
+
+/// Whether a schedule should fire now. Runs missed while nothing was running
+/// collapse into a single run rather than firing once per missed slot.
+fn is_due(schedule: &Schedule, now: DateTime<Local>) -> bool {
+    schedule.enabled && next_run(schedule).is_some_and(|next| next <= now)
+}
+
+/// Fire every due schedule once. Each run is claimed in the database before
+/// generating, so a CLI daemon and the GUI runner never both fire the same slot.
+/// Returns the number of generations completed.
+pub async fn run_due(db: &Database) -> Result<usize> {
+    let now = Local::now();
+    let mut completed = 0;
+
+    for schedule in db.list_schedules()? {
+        if !is_due(&schedule, now) {
+            continue;
+        }
+        let run_at = now.format(TIMESTAMP_FORMAT).to_string();
+        if !db.claim_schedule_run(schedule.id, schedule.last_run_at.as_deref(), &run_at)? {
+            continue;
+        }
+
+        let req = GenerationRequest {
+            model: schedule.model.clone(),
+            prompt: schedule.prompt.clone(),
+            negative_prompt: schedule.negative_prompt.clone(),
+            width: schedule.width,
+            height: schedule.height,
+            ..Default::default()
+        };
+        let mut tags = schedule.tags.clone();
+        if !tags.contains(&schedule.name) {
+            tags.push(schedule.name.clone());
+        }
+
+        eprintln!("Schedule '{}': generating with {}...", schedule.name, schedule.model);
+        match workflow::perform_generation(db, &req, &tags, JobSource::Schedule, &|_| {}).await {
+            Ok(generations) => {
+                for saved in &generations {
+                    eprintln!("Schedule '{}': generated ID {}", schedule.name, saved.id);
+                }
+                if let Some(saved) = generations.first() {
+                    notify::send(JobSource::Schedule, &notify::Message::generated(&saved.generation));
+                }
+                completed += generations.len();
+            }
+            // Stop the run; the remaining schedules fire on the next poll
+            Err(e) if cancel::is_cancelled(&e) => return Err(e),
+            // The failure is recorded on the job; the next slot tries again
+            Err(e) => {
+                eprintln!("Schedule '{}' failed: {}", schedule.name, e);
+                if let Some(message) = notify::Message::failed(&schedule.model, &e) {
+                    notify::send(JobSource::Schedule, &message);
+                }
+            }
+        }
+    }
+
+    Ok(completed)
+}
+
+/// Starts the GUI schedule runner. It uses its own database connection so
+/// generations never hold the GUI's lock; new images reach the UI through
+/// the file watcher.
+pub fn start_background() {
+    std::thread::spawn(|| {
+        let db = match Database::open(&archive::db_path()) {
+            Ok(db) => db,
+            Err(e) => {
+                eprintln!("Scheduler disabled: {}", e);
+                return;
+            }
+        };
+        let rt = match tokio::runtime::Runtime::new() {
+            Ok(rt) => rt,
+            Err(e) => {
+                eprintln!("Scheduler disabled: {}", e);
+                return;
+            }
+        };
+        loop {
+            if let Err(e) = rt.block_on(run_due(&db)) {
+                eprintln!("Scheduler error: {}", e);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
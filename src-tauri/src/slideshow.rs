@@ -0,0 +1,25 @@
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// What `pixery slideshow` asked the window to cycle through
+#[derive(Debug, Clone, Serialize)]
+pub struct Slideshow {
+    /// Generation IDs in showing order; the show loops back to the first
+    pub ids: Vec<i64>,
+    pub interval_seconds: f64,
+    pub fullscreen: bool,
+}
+
+static SLIDESHOW: OnceLock<Slideshow> = OnceLock::new();
+
+/// Open the GUI as this slideshow instead of the gallery. Call it before
+/// `run()`; the window is read-only and starts no background work.
+pub fn start(show: Slideshow) {
+    let _ = SLIDESHOW.set(show);
+    crate::readonly::force();
+}
+
+/// The slideshow this window shows, if it was opened as one
+pub fn current() -> Option<&'static Slideshow> {
+    SLIDESHOW.get()
+}
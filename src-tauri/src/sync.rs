@@ -0,0 +1,331 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+
+use crate::archive;
+use crate::db::Database;
+use crate::models::ListFilter;
+
+/// File hashes, kept on the remote and cached in the archive root
+const MANIFEST_NAME: &str = "sync-manifest.json";
+/// Name of the database snapshot on the remote
+const DB_NAME: &str = "index.sqlite";
+
+/// Where `pixery sync` backs up to, stored in sync.json in the archive root
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyncSettings {
+    /// `s3://bucket/prefix`, or any rclone remote path (`b2:pixery`, `gdrive:backup/pixery`)
+    pub remote: Option<String>,
+    /// Endpoint for S3-compatible services other than AWS (MinIO, R2, ...)
+    pub s3_endpoint: Option<String>,
+}
+
+impl SyncSettings {
+    /// The remote as an rclone path. An s3:// URL becomes an on-the-fly S3
+    /// remote taking credentials from the usual AWS environment variables.
+    fn target(&self) -> Result<String> {
+        let remote = self
+            .remote
+            .as_deref()
+            .map(|r| r.trim().trim_end_matches('/'))
+            .filter(|r| !r.is_empty())
+            .context("No sync remote set; use `pixery sync remote s3://bucket/prefix` or an rclone remote")?;
+        let Some(path) = remote.strip_prefix("s3://") else {
+            return Ok(remote.to_string());
+        };
+        match &self.s3_endpoint {
+            Some(endpoint) => Ok(format!(":s3,provider=Other,env_auth=true,endpoint='{}':{}", endpoint, path)),
+            None => Ok(format!(":s3,env_auth=true:{}", path)),
+        }
+    }
+}
+
+fn settings_path() -> PathBuf {
+    archive::archive_root().join("sync.json")
+}
+
+pub fn load_settings() -> SyncSettings {
+    std::fs::read_to_string(settings_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &SyncSettings) -> Result<()> {
+    settings.target()?;
+    std::fs::write(settings_path(), serde_json::to_string_pretty(settings)?)
+        .context("Failed to write sync settings")
+}
+
+/// A synced file. Size and mtime are what the hash was computed at, so an
+/// unchanged file isn't hashed again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileEntry {
+    hash: String,
+    size: u64,
+    modified: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    /// Archive root of the machine that wrote it; paths in its database start with it
+    root: String,
+    updated_at: String,
+    /// Archive-relative, '/'-separated path -> entry
+    files: BTreeMap<String, FileEntry>,
+}
+
+impl Manifest {
+    /// Paths whose content here differs from (or is missing in) `other`
+    fn differing_from(&self, other: &Manifest) -> Vec<String> {
+        self.files
+            .iter()
+            .filter(|(path, entry)| other.files.get(*path).map(|e| &e.hash) != Some(&entry.hash))
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    fn bytes(&self, paths: &[String]) -> u64 {
+        paths.iter().filter_map(|p| self.files.get(p)).map(|e| e.size).sum()
+    }
+}
+
+/// Outcome of a push or pull
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// Files uploaded (push) or downloaded (pull)
+    pub transferred: usize,
+    pub bytes: u64,
+    /// Files already the same on both sides
+    pub unchanged: usize,
+    /// The database was uploaded (push) or restored (pull)
+    pub database: bool,
+}
+
+/// Run rclone, returning its stdout
+fn rclone<S: AsRef<OsStr>>(args: &[S]) -> Result<String> {
+    let output = Command::new("rclone").args(args).output().map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            anyhow::anyhow!("rclone not found; install it from https://rclone.org to use sync")
+        } else {
+            anyhow::anyhow!("Failed to run rclone: {}", e)
+        }
+    })?;
+    if !output.status.success() {
+        anyhow::bail!("rclone failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn remote_path(target: &str, name: &str) -> String {
+    format!("{}/{}", target, name)
+}
+
+/// The remote's manifest; empty if nothing has been pushed there yet
+fn fetch_manifest(target: &str) -> Result<Manifest> {
+    match rclone(&["cat", &remote_path(target, MANIFEST_NAME)]) {
+        Ok(text) if text.trim().is_empty() => Ok(Manifest::default()),
+        Ok(text) => serde_json::from_str(&text).context("Remote sync manifest is corrupt"),
+        Err(e) if e.to_string().contains("not found") => Ok(Manifest::default()),
+        Err(e) => Err(e),
+    }
+}
+
+fn upload_manifest(target: &str, manifest: &Manifest) -> Result<()> {
+    let staged = std::env::temp_dir().join(format!("pixery-{}-{}", std::process::id(), MANIFEST_NAME));
+    std::fs::write(&staged, serde_json::to_string_pretty(manifest)?)?;
+    let result = rclone(&[OsStr::new("copyto"), staged.as_os_str(), OsStr::new(&remote_path(target, MANIFEST_NAME))]);
+    let _ = std::fs::remove_file(&staged);
+    result.map(|_| ())
+}
+
+/// Copy archive-relative `paths` from `src` to `dest` (one of them the remote)
+fn transfer(src: &str, dest: &str, paths: &[String]) -> Result<()> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let list = std::env::temp_dir().join(format!("pixery-sync-{}.txt", std::process::id()));
+    std::fs::write(&list, paths.join("\n")).context("Failed to write sync file list")?;
+    let result = rclone(&[
+        OsStr::new("copy"),
+        OsStr::new(src),
+        OsStr::new(dest),
+        OsStr::new("--files-from-raw"),
+        list.as_os_str(),
+        OsStr::new("--no-traverse"),
+    ]);
+    let _ = std::fs::remove_file(&list);
+    result.map(|_| ())
+}
+
+fn local_cache_path() -> PathBuf {
+    archive::archive_root().join(MANIFEST_NAME)
+}
+
+/// Files under `dir`, recursively
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Hash the archive's images and references, reusing cached hashes of
+/// files whose size and mtime haven't changed
+fn scan_local() -> Result<Manifest> {
+    let root = archive::archive_root();
+    let cache: Manifest = std::fs::read_to_string(local_cache_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+
+    let mut paths = vec![];
+    walk(&archive::generations_dir(), &mut paths)?;
+    walk(&archive::references_dir(), &mut paths)?;
+
+    let mut files = BTreeMap::new();
+    for path in paths {
+        let Ok(relative) = path.strip_prefix(&root) else {
+            continue;
+        };
+        let key = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let meta = std::fs::metadata(&path)?;
+        let size = meta.len();
+        let modified = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let hash = match cache.files.get(&key) {
+            Some(e) if e.size == size && e.modified == modified => e.hash.clone(),
+            _ => archive::hash_file(&path)?,
+        };
+        files.insert(key, FileEntry { hash, size, modified });
+    }
+
+    Ok(Manifest {
+        root: root.to_string_lossy().into_owned(),
+        updated_at: chrono::Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        files,
+    })
+}
+
+fn save_local_cache(manifest: &Manifest) -> Result<()> {
+    std::fs::write(local_cache_path(), serde_json::to_string(manifest)?).context("Failed to write sync cache")
+}
+
+/// Upload images and references that are new or changed since the last
+/// push, then a snapshot of the database and the updated manifest. Nothing
+/// is ever deleted from the remote.
+pub fn push(db: &Database, dry_run: bool) -> Result<SyncReport> {
+    let target = load_settings().target()?;
+    let local = scan_local()?;
+    let remote = fetch_manifest(&target)?;
+
+    let changed = local.differing_from(&remote);
+    let report = SyncReport {
+        transferred: changed.len(),
+        bytes: local.bytes(&changed),
+        unchanged: local.files.len() - changed.len(),
+        database: true,
+    };
+    if dry_run {
+        return Ok(report);
+    }
+
+    // Files first: a manifest or database must never name files the remote lacks
+    transfer(&local.root, &target, &changed)?;
+
+    let snapshot = std::env::temp_dir().join(format!("pixery-sync-{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&snapshot);
+    db.snapshot(&snapshot)?;
+    let uploaded = rclone(&[OsStr::new("copyto"), snapshot.as_os_str(), OsStr::new(&remote_path(&target, DB_NAME))]);
+    let _ = std::fs::remove_file(&snapshot);
+    uploaded?;
+
+    let mut files = remote.files;
+    files.extend(local.files.clone());
+    upload_manifest(
+        &target,
+        &Manifest {
+            root: local.root.clone(),
+            updated_at: local.updated_at.clone(),
+            files,
+        },
+    )?;
+    save_local_cache(&local)?;
+    Ok(report)
+}
+
+/// Download images and references missing or different here. The database
+/// is replaced with the remote copy when `restore_db` is set or this archive
+/// is empty; the local one is kept as index.sqlite.bak-<timestamp> first,
+/// and paths are rewritten if the remote came from a different archive root.
+pub fn pull(db: &Database, restore_db: bool, dry_run: bool) -> Result<SyncReport> {
+    let target = load_settings().target()?;
+    let remote = fetch_manifest(&target)?;
+    if remote.root.is_empty() {
+        anyhow::bail!("Nothing has been pushed to {} yet", target);
+    }
+    let local = scan_local()?;
+
+    let missing = remote.differing_from(&local);
+    // Trashed generations are counted separately
+    let mut empty = true;
+    for show_trashed in [false, true] {
+        empty &= db.count_generations(&ListFilter {
+            show_trashed,
+            ..Default::default()
+        })? == 0;
+    }
+    let report = SyncReport {
+        transferred: missing.len(),
+        bytes: remote.bytes(&missing),
+        unchanged: remote.files.len() - missing.len(),
+        database: restore_db || empty,
+    };
+    if dry_run {
+        return Ok(report);
+    }
+
+    transfer(&target, &local.root, &missing)?;
+
+    if report.database {
+        let pulled = std::env::temp_dir().join(format!("pixery-sync-{}.sqlite", std::process::id()));
+        rclone(&[OsStr::new("copyto"), OsStr::new(&remote_path(&target, DB_NAME)), pulled.as_os_str()])?;
+
+        let backup = archive::archive_root().join(format!(
+            "index.sqlite.bak-{}",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        db.snapshot(&backup)?;
+        let restored = Database::restore(&archive::db_path(), &pulled);
+        let _ = std::fs::remove_file(&pulled);
+        let restored = restored?;
+        if remote.root != local.root {
+            restored.relocate_archive(&remote.root, &local.root)?;
+        }
+    }
+
+    save_local_cache(&scan_local()?)?;
+    Ok(report)
+}
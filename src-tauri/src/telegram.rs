@@ -0,0 +1,501 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::cancel;
+use crate::db::Database;
+use crate::models::{Generated, Generation, GenerationRequest, JobSource};
+use crate::providers;
+use crate::workflow;
+
+pub const API_BASE: &str = "https://api.telegram.org";
+
+/// Tag added to every generation the bot makes
+pub const TELEGRAM_TAG: &str = "telegram";
+
+/// sendPhoto's limit; larger images go out as documents
+const MAX_PHOTO_BYTES: u64 = 10 * 1024 * 1024;
+
+/// sendDocument's limit on the public Bot API server
+const MAX_DOCUMENT_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Telegram caps photo captions at 1024 characters, messages at 4096
+const MAX_CAPTION: usize = 1024;
+const MAX_MESSAGE: usize = 4096;
+
+/// How long one getUpdates call waits for a message
+const POLL_TIMEOUT_SECS: u64 = 50;
+
+/// Wait before polling again after a network error or a Telegram hiccup
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// "sending photo..." lasts five seconds, so it's repeated while generating
+const CHAT_ACTION_INTERVAL: Duration = Duration::from_secs(4);
+
+/// How `pixery telegram-bot` generates
+#[derive(Debug, Clone)]
+pub struct BotOptions {
+    pub token: String,
+    /// Bot API server, without a trailing slash
+    pub api_url: String,
+    /// Model used when the message doesn't pick one
+    pub model: String,
+    /// Models users may pick with `model:NAME`; empty allows only `model`
+    pub models: Vec<String>,
+    /// Usernames (without @) or numeric user IDs allowed to generate; empty allows anyone
+    pub allow: Vec<String>,
+    /// Added to every generation alongside the `telegram` tag
+    pub tags: Vec<String>,
+}
+
+/// Bot token from TELEGRAM_BOT_TOKEN
+pub fn get_token() -> Result<String> {
+    std::env::var("TELEGRAM_BOT_TOKEN")
+        .ok()
+        .filter(|t| !t.is_empty())
+        .context("TELEGRAM_BOT_TOKEN environment variable not set")
+}
+
+#[derive(Debug, Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Message {
+    message_id: i64,
+    chat: Chat,
+    /// Missing for channel posts
+    from: Option<User>,
+    text: Option<String>,
+    /// Text sent along with a photo or document
+    caption: Option<String>,
+    /// One entry per size Telegram made, smallest first
+    #[serde(default)]
+    photo: Vec<PhotoSize>,
+    document: Option<Document>,
+    reply_to_message: Option<Box<Message>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Chat {
+    id: i64,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    id: i64,
+    username: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhotoSize {
+    file_id: String,
+    file_unique_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Document {
+    file_id: String,
+    file_unique_id: String,
+    mime_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct File {
+    file_path: Option<String>,
+}
+
+impl Message {
+    fn is_private(&self) -> bool {
+        self.chat.kind == "private"
+    }
+
+    /// The username, or the numeric ID for users without one
+    fn requester(&self) -> String {
+        match &self.from {
+            Some(User { username: Some(name), .. }) => name.clone(),
+            Some(user) => user.id.to_string(),
+            None => "unknown".to_string(),
+        }
+    }
+
+    /// The image this message carries: the largest size of a photo, or an
+    /// image sent as a file (uncompressed)
+    fn image(&self) -> Option<(&str, &str)> {
+        if let Some(photo) = self.photo.last() {
+            return Some((&photo.file_id, &photo.file_unique_id));
+        }
+        self.document
+            .as_ref()
+            .filter(|d| d.mime_type.as_deref().is_some_and(|m| m.starts_with("image/")))
+            .map(|d| (d.file_id.as_str(), d.file_unique_id.as_str()))
+    }
+}
+
+/// A Bot API call that Telegram answered with `ok: false`
+#[derive(Debug)]
+struct ApiFailure {
+    method: &'static str,
+    code: u16,
+    description: String,
+}
+
+impl std::fmt::Display for ApiFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Telegram {} failed ({}): {}", self.method, self.code, self.description)
+    }
+}
+
+impl std::error::Error for ApiFailure {}
+
+/// Whether polling again could help: network trouble, flood limits and server
+/// errors pass, a bad token (401) or a second poller on the same bot (409) don't
+fn is_retryable(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<ApiFailure>()
+        .is_none_or(|f| f.code == 429 || f.code >= 500)
+}
+
+fn request(options: &BotOptions, method: &str) -> reqwest::RequestBuilder {
+    providers::client().post(format!("{}/bot{}/{}", options.api_url, options.token, method))
+}
+
+/// Send a Bot API request and unwrap its `result`. reqwest errors name the
+/// URL, which holds the token, so it's dropped from them.
+async fn send<T: DeserializeOwned>(request: reqwest::RequestBuilder, method: &'static str) -> Result<T> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| e.without_url())
+        .with_context(|| format!("Failed to reach Telegram ({})", method))?;
+    let status = response.status();
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| e.without_url())
+        .with_context(|| format!("Telegram {} returned {} without a JSON body", method, status))?;
+    if body["ok"].as_bool() != Some(true) {
+        return Err(ApiFailure {
+            method,
+            code: body["error_code"].as_u64().map_or(status.as_u16(), |c| c as u16),
+            description: body["description"].as_str().unwrap_or("no description").to_string(),
+        }
+        .into());
+    }
+    serde_json::from_value(body["result"].clone()).with_context(|| format!("Unexpected {} response", method))
+}
+
+async fn call<T: DeserializeOwned>(options: &BotOptions, method: &'static str, payload: &Value) -> Result<T> {
+    send(request(options, method).json(payload), method).await
+}
+
+/// Answer `to` in its chat with plain text
+async fn reply(options: &BotOptions, to: &Message, text: &str) -> Result<()> {
+    let payload = json!({
+        "chat_id": to.chat.id,
+        "text": truncate(text, MAX_MESSAGE),
+        "reply_parameters": { "message_id": to.message_id, "allow_sending_without_reply": true },
+    });
+    call::<Value>(options, "sendMessage", &payload).await?;
+    Ok(())
+}
+
+/// Upload one generation in answer to `to`, as a photo when Telegram takes it
+/// as one and as a document otherwise. Returns the sent message's ID.
+async fn send_image(options: &BotOptions, to: &Message, generation: &Generation, caption: &str) -> Result<i64> {
+    let path = Path::new(&generation.image_path);
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| format!("{}.png", generation.id));
+    let data = tokio::fs::read(path).await.context("Failed to read generated image")?;
+    let as_photo = data.len() as u64 <= MAX_PHOTO_BYTES;
+
+    let form = |method_field: &str| {
+        let reply_to = json!({ "message_id": to.message_id, "allow_sending_without_reply": true });
+        reqwest::multipart::Form::new()
+            .text("chat_id", to.chat.id.to_string())
+            .text("caption", truncate(caption, MAX_CAPTION))
+            .text("reply_parameters", reply_to.to_string())
+            .part(
+                method_field.to_string(),
+                reqwest::multipart::Part::bytes(data.clone()).file_name(file_name.clone()),
+            )
+    };
+
+    if as_photo {
+        let sent: Result<Message> = send(request(options, "sendPhoto").multipart(form("photo")), "sendPhoto").await;
+        match sent {
+            Ok(message) => return Ok(message.message_id),
+            // Photos have dimension limits (e.g. a very long strip); documents don't
+            Err(e) if e.downcast_ref::<ApiFailure>().is_some_and(|f| f.code == 400) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let sent: Message = send(request(options, "sendDocument").multipart(form("document")), "sendDocument").await?;
+    Ok(sent.message_id)
+}
+
+/// Download a file someone sent to a temp file, for use as a reference
+async fn download(options: &BotOptions, file_id: &str, unique_id: &str) -> Result<PathBuf> {
+    let file: File = call(options, "getFile", &json!({ "file_id": file_id }))
+        .await
+        .context("Failed to look up the reference image (bots can only fetch files up to 20 MB)")?;
+    let remote = file.file_path.context("Telegram gave no path for the reference image")?;
+    let response = providers::client()
+        .get(format!("{}/file/bot{}/{}", options.api_url, options.token, remote))
+        .send()
+        .await
+        .map_err(|e| e.without_url())
+        .context("Failed to download the reference image")?;
+    let status = response.status();
+    if !status.is_success() {
+        anyhow::bail!("Telegram file download failed ({})", status);
+    }
+    let data = response.bytes().await.map_err(|e| e.without_url())?;
+
+    let extension = Path::new(&remote).extension().and_then(|e| e.to_str()).unwrap_or("jpg");
+    let path = std::env::temp_dir().join(format!("pixery-telegram-{}.{}", unique_id, extension));
+    tokio::fs::write(&path, &data).await.context("Failed to save the reference image")?;
+    Ok(path)
+}
+
+/// Telegram captions and messages have hard length limits
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        format!("{}...", text.chars().take(max - 3).collect::<String>())
+    }
+}
+
+/// What a message asks the bot for
+enum Command {
+    Help,
+    Models,
+    Imagine(String),
+    Unknown(String),
+}
+
+/// Read a command from a message. `/imagine@bot` addressed to another bot is
+/// ignored, and so is plain text outside a private chat, where it's just
+/// people talking; in a private chat plain text is a prompt.
+fn parse_command(message: &Message, bot_name: &str) -> Option<Command> {
+    let text = message.text.as_deref().or(message.caption.as_deref())?.trim();
+    let Some(rest) = text.strip_prefix('/') else {
+        return message.is_private().then(|| Command::Imagine(text.to_string()));
+    };
+    let (word, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let (name, addressee) = match word.split_once('@') {
+        Some((name, addressee)) => (name, Some(addressee)),
+        None => (word, None),
+    };
+    if addressee.is_some_and(|a| !a.eq_ignore_ascii_case(bot_name)) {
+        return None;
+    }
+    Some(match name {
+        "start" | "help" => Command::Help,
+        "models" => Command::Models,
+        "imagine" => Command::Imagine(args.trim().to_string()),
+        // In groups, commands meant for other bots arrive too
+        _ if addressee.is_none() && !message.is_private() => return None,
+        _ => Command::Unknown(name.to_string()),
+    })
+}
+
+fn help_text(options: &BotOptions) -> String {
+    let mut text = String::from(
+        "Send /imagine followed by a prompt (in a private chat, just the prompt).\n\n\
+         Reply to an image, or send one with the prompt as its caption, to use it as a reference. \
+         Replying to an image the bot sent uses the full-size original.",
+    );
+    if !options.models.is_empty() {
+        text.push_str(&format!(
+            "\n\nStart the prompt with model:NAME to pick a model (default {}); /models lists them.",
+            options.model
+        ));
+    }
+    text
+}
+
+/// Whether the sender may generate
+fn is_allowed(options: &BotOptions, message: &Message) -> bool {
+    if options.allow.is_empty() {
+        return true;
+    }
+    let Some(user) = &message.from else {
+        return false;
+    };
+    let id = user.id.to_string();
+    options.allow.iter().any(|a| {
+        *a == id || user.username.as_deref().is_some_and(|u| a.trim_start_matches('@').eq_ignore_ascii_case(u))
+    })
+}
+
+/// Images the bot sent, by (chat, message), so a reply to one can use the archived original
+type Sent = HashMap<(i64, i64), i64>;
+
+/// Reference images for a request: the image sent with it and the one it replies to
+async fn references(options: &BotOptions, message: &Message, sent: &Sent, downloads: &mut Vec<PathBuf>) -> Result<Vec<String>> {
+    let mut references = vec![];
+    for source in std::iter::once(message).chain(message.reply_to_message.as_deref()) {
+        if let Some(id) = sent.get(&(source.chat.id, source.message_id)) {
+            references.push(format!("gen:{}", id));
+        } else if let Some((file_id, unique_id)) = source.image() {
+            let path = download(options, file_id, unique_id).await?;
+            references.push(path.to_string_lossy().into_owned());
+            downloads.push(path);
+        }
+    }
+    Ok(references)
+}
+
+/// Generate for one /imagine and reply with the images
+async fn imagine(db: &Database, options: &BotOptions, sent: &mut Sent, message: &Message, text: &str) -> Result<()> {
+    let (model, prompt) = match text.strip_prefix("model:") {
+        Some(rest) => {
+            let (model, prompt) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            (model, prompt.trim())
+        }
+        None => (options.model.as_str(), text),
+    };
+    if prompt.is_empty() {
+        return reply(options, message, "A prompt is required, e.g. /imagine a lighthouse at dusk").await;
+    }
+    if model != options.model && !options.models.iter().any(|m| m == model) {
+        return reply(options, message, &format!("Model {} isn't enabled", model)).await;
+    }
+    let requester = message.requester();
+    eprintln!("/imagine from {} with {}: {}", requester, model, prompt);
+
+    let mut downloads = vec![];
+    let reference_paths = match references(options, message, sent, &mut downloads).await {
+        Ok(paths) => paths,
+        Err(e) => return reply(options, message, &format!("{:#}", e)).await,
+    };
+    let req = GenerationRequest {
+        model: model.to_string(),
+        prompt: prompt.to_string(),
+        reference_paths,
+        ..Default::default()
+    };
+    let mut tags = vec![TELEGRAM_TAG.to_string()];
+    tags.extend(options.tags.iter().cloned());
+
+    let action = request(options, "sendChatAction").json(&json!({ "chat_id": message.chat.id, "action": "upload_photo" }));
+    let uploading = tokio::spawn(async move {
+        while let Some(action) = action.try_clone() {
+            let _ = action.send().await;
+            tokio::time::sleep(CHAT_ACTION_INTERVAL).await;
+        }
+    });
+    let result = workflow::perform_generation(db, &req, &tags, JobSource::Cli, &|_| {}).await;
+    uploading.abort();
+    // The archive keeps its own copy of each reference
+    for path in &downloads {
+        let _ = std::fs::remove_file(path);
+    }
+
+    let generations = match result {
+        Ok(generations) => generations,
+        Err(e) => {
+            let text = if cancel::is_cancelled(&e) {
+                "Generation cancelled".to_string()
+            } else {
+                format!("Generation with {} failed: {:#}", model, e)
+            };
+            eprintln!("  {}", text);
+            return reply(options, message, &text).await;
+        }
+    };
+
+    let requested_by = format!("telegram:{}", requester);
+    for saved in &generations {
+        db.set_requested_by(saved.id, &requested_by)?;
+    }
+
+    let caption_prompt = truncate(prompt, MAX_CAPTION - 100);
+    for Generated { id, generation, .. } in &generations {
+        let caption = format!("{} · {} · #{}", caption_prompt, model, id);
+        let size = generation.file_size.map_or(0, |s| s as u64);
+        if size > MAX_DOCUMENT_BYTES {
+            reply(options, message, &format!("{}\n#{} is too large to upload", caption, id)).await?;
+            continue;
+        }
+        let message_id = send_image(options, message, generation, &caption).await?;
+        sent.insert((message.chat.id, message_id), *id);
+    }
+    let ids: Vec<String> = generations.iter().map(|saved| format!("#{}", saved.id)).collect();
+    eprintln!("  -> {}", ids.join(" "));
+    Ok(())
+}
+
+async fn handle(db: &Database, options: &BotOptions, bot_name: &str, sent: &mut Sent, message: &Message) -> Result<()> {
+    let Some(command) = parse_command(message, bot_name) else {
+        return Ok(());
+    };
+    if !is_allowed(options, message) {
+        eprintln!("Ignored a request from {} (not in --allow)", message.requester());
+        return reply(options, message, "You're not allowed to generate with this bot").await;
+    }
+    match command {
+        Command::Help => reply(options, message, &help_text(options)).await,
+        Command::Models => {
+            let mut models = vec![format!("{} (default)", options.model)];
+            models.extend(options.models.iter().filter(|m| **m != options.model).cloned());
+            reply(options, message, &models.join("\n")).await
+        }
+        Command::Unknown(name) => reply(options, message, &format!("Unknown command /{}; try /help", name)).await,
+        Command::Imagine(text) => imagine(db, options, sent, message, &text).await,
+    }
+}
+
+/// Run the bot until Telegram refuses it (bad token, another instance polling).
+/// Messages are long-polled and handled one at a time, in order; network
+/// errors are retried.
+pub async fn run(db: &Database, options: &BotOptions) -> Result<()> {
+    let me: User = call(options, "getMe", &json!({})).await.context("Failed to log in to Telegram")?;
+    let bot_name = me.username.unwrap_or_default();
+    eprintln!("Connected to Telegram as @{}", bot_name);
+    if options.allow.is_empty() {
+        eprintln!("Anyone who finds @{} can generate; use --allow to limit it", bot_name);
+    }
+
+    let mut offset = 0;
+    let mut sent = Sent::new();
+    loop {
+        let payload = json!({
+            "offset": offset,
+            "timeout": POLL_TIMEOUT_SECS,
+            "allowed_updates": ["message"],
+        });
+        let poll = request(options, "getUpdates")
+            .json(&payload)
+            .timeout(Duration::from_secs(POLL_TIMEOUT_SECS + 15));
+        let updates: Vec<Update> = match send(poll, "getUpdates").await {
+            Ok(updates) => updates,
+            Err(e) if is_retryable(&e) => {
+                eprintln!("Telegram polling error: {:#}, retrying...", e);
+                tokio::time::sleep(RETRY_DELAY).await;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        for update in updates {
+            offset = update.update_id + 1;
+            let Some(message) = update.message else {
+                continue;
+            };
+            if let Err(e) = handle(db, options, &bot_name, &mut sent, &message).await {
+                eprintln!("  {:#}", e);
+            }
+        }
+    }
+}
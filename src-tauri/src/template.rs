@@ -0,0 +1,146 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::models::Generation;
+
+/// Tokens a name template can use
+const TOKENS: &[&str] = &[
+    "id", "date", "time", "slug", "title", "model", "provider", "seed", "collection", "tag", "counter",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Token(&'static str),
+}
+
+/// A file name pattern like "{date}_{model}_{slug}_{id}", for exports and
+/// --copy-to. Token values are made file-name safe; the caller adds the
+/// extension.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTemplate {
+    source: String,
+    parts: Vec<Part>,
+}
+
+/// What a template is rendered for
+#[derive(Debug, Clone, Copy)]
+pub struct NameContext<'a> {
+    pub generation: &'a Generation,
+    /// The collection or tag an export was selected by; otherwise {collection}
+    /// and {tag} are the generation's first
+    pub collection: Option<&'a str>,
+    pub tag: Option<&'a str>,
+    /// {counter}: position in an export, or the first free number for --copy-to
+    pub counter: usize,
+}
+
+impl NameTemplate {
+    pub fn uses_counter(&self) -> bool {
+        self.parts.contains(&Part::Token("counter"))
+    }
+
+    /// The file name without extension. Falls back to the generation ID when
+    /// every token came out empty (e.g. "{seed}" for an image without one).
+    pub fn render(&self, ctx: &NameContext) -> String {
+        let gen = ctx.generation;
+        let mut name = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => name.push_str(text),
+                Part::Token(token) => {
+                    let value = match *token {
+                        "id" => gen.id.to_string(),
+                        "date" => gen.date.clone(),
+                        "time" => gen.timestamp.clone(),
+                        "slug" => gen.slug.clone(),
+                        "title" => gen.title.clone().unwrap_or_default(),
+                        "model" => gen.model.clone(),
+                        "provider" => gen.provider.clone(),
+                        "seed" => gen.seed.clone().unwrap_or_default(),
+                        "collection" => ctx
+                            .collection
+                            .or(gen.collection_names.first().map(String::as_str))
+                            .unwrap_or_default()
+                            .to_string(),
+                        "tag" => ctx.tag.or(gen.tags.first().map(String::as_str)).unwrap_or_default().to_string(),
+                        "counter" => format!("{:03}", ctx.counter),
+                        _ => unreachable!("tokens are checked when parsing"),
+                    };
+                    name.push_str(&sanitize(&value));
+                }
+            }
+        }
+        if name.chars().all(|c| !c.is_alphanumeric()) {
+            return gen.id.to_string();
+        }
+        name
+    }
+
+    /// Where to put a file in `dir`. With {counter}, the first number whose
+    /// file doesn't exist yet, so repeated copies into a folder count up.
+    pub fn path_in(&self, dir: &Path, ctx: &NameContext, extension: &str) -> PathBuf {
+        let path = |ctx: &NameContext| dir.join(format!("{}.{}", self.render(ctx), extension));
+        if !self.uses_counter() {
+            return path(ctx);
+        }
+        let mut ctx = *ctx;
+        ctx.counter = 1;
+        while path(&ctx).exists() {
+            ctx.counter += 1;
+        }
+        path(&ctx)
+    }
+}
+
+/// Keep letters, digits, '-', '_' and '.'; anything else (path separators in
+/// model IDs like fal-ai/flux, spaces in titles) becomes '-'
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '-' })
+        .collect()
+}
+
+impl fmt::Display for NameTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.source)
+    }
+}
+
+impl FromStr for NameTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(['/', '\\']) {
+            return Err("Name templates can't contain path separators".to_string());
+        }
+        let mut parts = vec![];
+        let mut rest = s;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("Unclosed '{{' in name template '{}'", s))?;
+            let name = &rest[start + 1..start + end];
+            let token = TOKENS.iter().find(|t| **t == name).ok_or_else(|| {
+                format!("Unknown token {{{}}} in name template (available: {{{}}})", name, TOKENS.join("}, {"))
+            })?;
+            parts.push(Part::Token(token));
+            rest = &rest[start + end + 1..];
+        }
+        if rest.contains('}') {
+            return Err(format!("Unmatched '}}' in name template '{}'", s));
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+        if !parts.iter().any(|p| matches!(p, Part::Token(_))) {
+            return Err("A name template needs at least one token, like {id}".to_string());
+        }
+        Ok(NameTemplate { source: s.to_string(), parts })
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::enhance::{self, EnhanceSettings};
+
+const INSTRUCTIONS: &str = "You name images. Given the prompt an image was generated from, \
+reply with a short human title for it: 2-6 words, Title Case, describing the subject. \
+Ignore quality tags, weights and technical settings. Reply with the title only: no quotes, \
+no punctuation at the end, no explanation.";
+
+/// Longest title kept, in characters
+const MAX_TITLE_CHARS: usize = 60;
+
+/// Prompt boilerplate that says nothing about the subject
+const BOILERPLATE: &[&str] = &[
+    "masterpiece", "best quality", "high quality", "highest quality", "amazing quality",
+    "very aesthetic", "absurdres", "highres", "ultra detailed", "highly detailed",
+    "extremely detailed", "detailed", "8k", "4k", "uhd", "hdr", "photorealistic",
+    "newest", "safe", "sfw",
+];
+
+/// How `--auto-title` derives a title
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TitleSource {
+    /// First descriptive clause of the prompt, cleaned up (free, instant)
+    #[default]
+    Heuristic,
+    /// The prompt-enhancement LLM (enhance.json), falling back to the heuristic
+    Llm,
+}
+
+/// Strip weights and emphasis: `(red hair:1.2)` -> `red hair`, `[[blue]]` -> `blue`
+fn clean_clause(clause: &str) -> String {
+    let without_weight = match clause.rfind(':') {
+        Some(i) if clause[i + 1..].trim().trim_end_matches([')', ']']).parse::<f64>().is_ok() => &clause[..i],
+        _ => clause,
+    };
+    let text: String = without_weight
+        .chars()
+        .filter(|c| !matches!(c, '(' | ')' | '[' | ']' | '{' | '}' | '<' | '>' | '"'))
+        .collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn is_boilerplate(clause: &str) -> bool {
+    let lower = clause.to_lowercase();
+    BOILERPLATE.contains(&lower.as_str()) || lower.starts_with("score_") || lower.starts_with("rating")
+}
+
+/// Cap the length at a word boundary and capitalize the first letter
+fn finish(title: &str) -> String {
+    let mut title = title.trim_end_matches(['.', ',', ';', ':']).to_string();
+    if title.chars().count() > MAX_TITLE_CHARS {
+        let cut: String = title.chars().take(MAX_TITLE_CHARS).collect();
+        title = match cut.rfind(' ') {
+            Some(i) => cut[..i].to_string(),
+            None => cut,
+        };
+    }
+    let mut chars = title.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => title,
+    }
+}
+
+/// Title from the prompt's first descriptive clause. Tag-style prompts, where
+/// no clause reads like a phrase, get their first three subject tags instead.
+pub fn heuristic(prompt: &str) -> Option<String> {
+    let clauses: Vec<String> = prompt
+        .split([',', '.', ';', '\n', '|'])
+        .map(clean_clause)
+        .filter(|c| !c.is_empty() && !is_boilerplate(c))
+        .collect();
+
+    let phrase = clauses.iter().find(|c| c.split_whitespace().count() >= 3).map(|c| {
+        let words: Vec<&str> = c.split_whitespace().collect();
+        // "a mountain lake at sunset" -> "mountain lake at sunset"
+        match words[0].to_lowercase().as_str() {
+            "a" | "an" | "the" => words[1..].join(" "),
+            _ => c.clone(),
+        }
+    });
+    let title = phrase.unwrap_or_else(|| clauses.iter().take(3).cloned().collect::<Vec<_>>().join(", "));
+    if title.is_empty() {
+        None
+    } else {
+        Some(finish(&title))
+    }
+}
+
+/// Ask the enhancement LLM for a title
+pub async fn llm(prompt: &str, settings: &EnhanceSettings) -> Result<String> {
+    let text = enhance::complete(INSTRUCTIONS, prompt, settings).await?;
+    let title = text.lines().next().unwrap_or_default().trim().trim_matches(['"', '\'', '*']).trim();
+    if title.is_empty() {
+        anyhow::bail!("LLM returned an empty title");
+    }
+    Ok(finish(title))
+}
+
+/// Title for a prompt from `source`. An LLM failure falls back to the heuristic
+/// rather than failing the generation.
+pub async fn generate(prompt: &str, source: TitleSource) -> Option<String> {
+    match source {
+        TitleSource::Heuristic => heuristic(prompt),
+        TitleSource::Llm => match llm(prompt, &enhance::load_settings()).await {
+            Ok(title) => Some(title),
+            Err(e) => {
+                eprintln!("Title LLM failed, using the prompt: {:#}", e);
+                heuristic(prompt)
+            }
+        },
+    }
+}
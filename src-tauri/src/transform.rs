@@ -0,0 +1,226 @@
+use anyhow::{Context, Result};
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::archive;
+use crate::db::Database;
+use crate::models::{Generation, MediaType};
+use crate::palette;
+use crate::workflow::{self, Imported};
+
+/// Tag added to generations made by `pixery transform`
+pub const TRANSFORM_TAG: &str = "transform";
+
+/// Region to keep, in ImageMagick geometry: WIDTHxHEIGHT+X+Y from the top left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crop {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl fmt::Display for Crop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}+{}+{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+impl FromStr for Crop {
+    type Err = String;
+
+    /// "512x512+100+200", or "512x512" for the top left corner
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid crop '{}' (expected WIDTHxHEIGHT+X+Y, e.g. 512x512+100+200)", s);
+        let (size, offset) = s.split_once('+').unwrap_or((s, "0+0"));
+        let (width, height) = size.split_once(['x', 'X']).ok_or_else(invalid)?;
+        let (x, y) = offset.split_once('+').ok_or_else(invalid)?;
+        let parse = |v: &str| v.trim().parse::<u32>().map_err(|_| invalid());
+        let crop = Crop { width: parse(width)?, height: parse(height)?, x: parse(x)?, y: parse(y)? };
+        if crop.width == 0 || crop.height == 0 {
+            return Err(invalid());
+        }
+        Ok(crop)
+    }
+}
+
+/// Clockwise rotation in quarter turns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rotation::Deg90 => write!(f, "90"),
+            Rotation::Deg180 => write!(f, "180"),
+            Rotation::Deg270 => write!(f, "270"),
+        }
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    /// Degrees clockwise; negative ones turn the other way (-90 is 270)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid rotation: {} (expected 90, 180, 270 or -90)", s);
+        match s.trim().parse::<i32>().map_err(|_| invalid())?.rem_euclid(360) {
+            90 => Ok(Rotation::Deg90),
+            180 => Ok(Rotation::Deg180),
+            270 => Ok(Rotation::Deg270),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Mirror direction: h swaps left and right, v top and bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flip {
+    Horizontal,
+    Vertical,
+}
+
+impl fmt::Display for Flip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Flip::Horizontal => write!(f, "h"),
+            Flip::Vertical => write!(f, "v"),
+        }
+    }
+}
+
+impl FromStr for Flip {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "h" | "horizontal" => Ok(Flip::Horizontal),
+            "v" | "vertical" => Ok(Flip::Vertical),
+            _ => Err(format!("Unknown flip: {} (expected h or v)", s)),
+        }
+    }
+}
+
+/// What `pixery transform` does to an image. Applied in a fixed order, so the
+/// crop is in the original's coordinates: crop, then rotate, then flip.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Transform {
+    pub crop: Option<Crop>,
+    pub rotate: Option<Rotation>,
+    pub flip: Option<Flip>,
+}
+
+/// "crop 512x512+100+200, rotate 90, flip h", kept in the result's parameters
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut steps = vec![];
+        if let Some(crop) = self.crop {
+            steps.push(format!("crop {}", crop));
+        }
+        if let Some(rotate) = self.rotate {
+            steps.push(format!("rotate {}", rotate));
+        }
+        if let Some(flip) = self.flip {
+            steps.push(format!("flip {}", flip));
+        }
+        write!(f, "{}", steps.join(", "))
+    }
+}
+
+impl Transform {
+    pub fn apply(&self, mut img: DynamicImage) -> Result<DynamicImage> {
+        if let Some(crop) = self.crop {
+            let (width, height) = img.dimensions();
+            let fits = crop.x.checked_add(crop.width).is_some_and(|right| right <= width)
+                && crop.y.checked_add(crop.height).is_some_and(|bottom| bottom <= height);
+            if !fits {
+                anyhow::bail!("Crop {} doesn't fit in the {}x{} image", crop, width, height);
+            }
+            img = img.crop_imm(crop.x, crop.y, crop.width, crop.height);
+        }
+        img = match self.rotate {
+            Some(Rotation::Deg90) => img.rotate90(),
+            Some(Rotation::Deg180) => img.rotate180(),
+            Some(Rotation::Deg270) => img.rotate270(),
+            None => img,
+        };
+        img = match self.flip {
+            Some(Flip::Horizontal) => img.fliph(),
+            Some(Flip::Vertical) => img.flipv(),
+            None => img,
+        };
+        Ok(img)
+    }
+}
+
+/// Apply `transform` to a generation's image and archive the result as its
+/// child, dated now: same prompt, model and seed, its tags plus `transform`
+/// and `tags`, no cost. The result is a PNG, so nothing is lost to
+/// re-encoding. One identical to an archived image (the same transform run
+/// twice) isn't saved again; `Imported::duplicate` says so.
+pub fn transform_generation(db: &Database, original: &Generation, transform: &Transform, tags: &[String]) -> Result<Imported> {
+    if original.media_type == MediaType::Video || original.animated {
+        anyhow::bail!("Generation {} is a video or animation; only still images can be transformed", original.id);
+    }
+    let img = image::open(&original.image_path).with_context(|| format!("Failed to open {}", original.image_path))?;
+    let mut data = vec![];
+    transform
+        .apply(img)?
+        .write_to(&mut std::io::Cursor::new(&mut data), ImageFormat::Png)
+        .context("Failed to encode transformed image")?;
+
+    let (content_hash, existing) = workflow::find_duplicate(db, &data)?;
+    if let Some(id) = existing {
+        let generation = db
+            .get_generation(id)?
+            .ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+        return Ok(Imported { id, generation, duplicate: true });
+    }
+
+    let now = chrono::Local::now();
+    let date = now.format("%Y-%m-%d").to_string();
+    let timestamp = now.format("%Y-%m-%dT%H:%M:%S").to_string();
+    let (image_path, thumb_path, width, height, file_size) =
+        archive::save_image(&data, &date, &original.slug, &timestamp)?;
+
+    let id = db.insert_generation(
+        &original.slug,
+        &original.prompt,
+        &original.model,
+        &original.provider,
+        &timestamp,
+        &date,
+        image_path.to_str().unwrap(),
+        thumb_path.as_ref().and_then(|p| p.to_str()),
+        None, // generation_time_seconds - nothing was generated
+        None, // cost - free
+        original.seed.as_deref(),
+        Some(width),
+        Some(height),
+        Some(file_size),
+        Some(original.id),
+        original.negative_prompt.as_deref(),
+    )?;
+    db.set_content_hash(id, &content_hash)?;
+    db.set_generation_params(id, &serde_json::json!({ "transform": transform.to_string() }))?;
+    palette::record_new(db, id, &image_path);
+    archive::queue_thumbnails(image_path);
+
+    let mut all_tags = original.tags.clone();
+    all_tags.push(TRANSFORM_TAG.to_string());
+    all_tags.extend(tags.iter().cloned());
+    db.add_tags(id, &all_tags)?;
+    if original.nsfw {
+        db.set_nsfw(id, true)?;
+    }
+
+    let generation = db
+        .get_generation(id)?
+        .ok_or_else(|| anyhow::anyhow!("Failed to retrieve generation after transform"))?;
+    Ok(Imported { id, generation, duplicate: false })
+}
@@ -0,0 +1,441 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::crossterm::{cursor, execute};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::io::Write;
+use std::path::Path;
+
+use crate::archive::{self, ThumbSize};
+use crate::db::Database;
+use crate::models::{Generation, ListFilter};
+use crate::nsfw;
+
+/// Rows loaded per refresh; the TUI is for browsing recent work, not the whole archive
+const LOAD_LIMIT: i64 = 500;
+
+const HELP: &str = "j/k move  / search  f starred  s star  t tag  T untag  d trash  r reload  q quit";
+
+/// How the selected image is shown
+#[derive(Clone, Copy, PartialEq)]
+enum PreviewMode {
+    /// Kitty graphics protocol (kitty, WezTerm, Ghostty)
+    Kitty,
+    /// Colored half-block characters; works in any truecolor terminal
+    Blocks,
+}
+
+impl PreviewMode {
+    fn detect() -> Self {
+        let term = std::env::var("TERM").unwrap_or_default();
+        let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        if std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || program == "WezTerm"
+            || program == "ghostty"
+        {
+            PreviewMode::Kitty
+        } else {
+            PreviewMode::Blocks
+        }
+    }
+}
+
+/// Line-editing prompts shown in the footer
+enum Input {
+    None,
+    Search(String),
+    AddTags(String),
+    RemoveTag(String),
+    ConfirmTrash,
+}
+
+struct App<'a> {
+    db: &'a Database,
+    filter: ListFilter,
+    generations: Vec<Generation>,
+    list: ListState,
+    input: Input,
+    status: String,
+    preview_mode: PreviewMode,
+    /// Rendered half-block preview, keyed by (generation id, width, height)
+    block_cache: Option<((i64, u16, u16), Vec<Line<'static>>)>,
+    /// Last image placed with the kitty protocol, keyed the same way
+    kitty_shown: Option<(i64, u16, u16)>,
+}
+
+impl<'a> App<'a> {
+    fn new(db: &'a Database, preview_mode: PreviewMode) -> Self {
+        App {
+            db,
+            filter: ListFilter {
+                limit: Some(LOAD_LIMIT),
+                hide_nsfw: !nsfw::load_settings().show,
+                ..Default::default()
+            },
+            generations: vec![],
+            list: ListState::default(),
+            input: Input::None,
+            status: String::new(),
+            preview_mode,
+            block_cache: None,
+            kitty_shown: None,
+        }
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        let selected_id = self.selected().map(|g| g.id);
+        self.generations = self.db.list_generations(&self.filter)?;
+        let index = selected_id
+            .and_then(|id| self.generations.iter().position(|g| g.id == id))
+            .or(if self.generations.is_empty() { None } else { Some(0) });
+        self.list.select(index);
+        Ok(())
+    }
+
+    fn selected(&self) -> Option<&Generation> {
+        self.list.selected().and_then(|i| self.generations.get(i))
+    }
+
+    /// Re-read one generation after an edit, keeping its place in the list
+    fn refresh_selected(&mut self) -> Result<()> {
+        if let Some(i) = self.list.selected() {
+            let id = self.generations[i].id;
+            if let Some(gen) = self.db.get_generation(id)? {
+                self.generations[i] = gen;
+            }
+        }
+        Ok(())
+    }
+
+    fn move_by(&mut self, delta: i64) {
+        if self.generations.is_empty() {
+            return;
+        }
+        let last = self.generations.len() as i64 - 1;
+        let current = self.list.selected().unwrap_or(0) as i64;
+        self.list.select(Some((current + delta).clamp(0, last) as usize));
+    }
+
+    /// Run an edit on the selected generation and report the outcome in the footer
+    fn edit<F>(&mut self, f: F)
+    where
+        F: FnOnce(&Database, i64) -> Result<String>,
+    {
+        let Some(id) = self.selected().map(|g| g.id) else {
+            return;
+        };
+        self.status = match f(self.db, id).and_then(|msg| self.refresh_selected().map(|_| msg)) {
+            Ok(msg) => msg,
+            Err(e) => format!("Error: {}", e),
+        };
+    }
+
+    /// Handle a key; returns false to quit
+    fn on_key(&mut self, code: KeyCode) -> Result<bool> {
+        if !matches!(self.input, Input::None) {
+            self.on_input_key(code)?;
+            return Ok(true);
+        }
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Char('j') | KeyCode::Down => self.move_by(1),
+            KeyCode::Char('k') | KeyCode::Up => self.move_by(-1),
+            KeyCode::PageDown => self.move_by(20),
+            KeyCode::PageUp => self.move_by(-20),
+            KeyCode::Char('g') | KeyCode::Home => self.move_by(-(LOAD_LIMIT)),
+            KeyCode::Char('G') | KeyCode::End => self.move_by(LOAD_LIMIT),
+            KeyCode::Char('/') => {
+                self.input = Input::Search(self.filter.search.clone().unwrap_or_default());
+            }
+            KeyCode::Char('f') => {
+                self.filter.starred_only = !self.filter.starred_only;
+                self.reload()?;
+                self.status = if self.filter.starred_only { "Starred only" } else { "All generations" }.to_string();
+            }
+            KeyCode::Char('s') => self.edit(|db, id| {
+                let starred = db.toggle_starred(id)?;
+                Ok(format!("{} #{}", if starred { "Starred" } else { "Unstarred" }, id))
+            }),
+            KeyCode::Char('t') if self.selected().is_some() => self.input = Input::AddTags(String::new()),
+            KeyCode::Char('T') if self.selected().is_some() => self.input = Input::RemoveTag(String::new()),
+            KeyCode::Char('d') if self.selected().is_some() => self.input = Input::ConfirmTrash,
+            KeyCode::Char('r') => {
+                self.reload()?;
+                self.status = format!("Loaded {} generation(s)", self.generations.len());
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Keys while a footer prompt is open
+    fn on_input_key(&mut self, code: KeyCode) -> Result<()> {
+        if matches!(self.input, Input::ConfirmTrash) {
+            self.input = Input::None;
+            if code == KeyCode::Char('y') {
+                self.edit(|db, id| {
+                    db.trash_generation(id)?;
+                    Ok(format!("Trashed #{}", id))
+                });
+                self.reload()?;
+            } else {
+                self.status = "Cancelled".to_string();
+            }
+            return Ok(());
+        }
+
+        let text = match &mut self.input {
+            Input::Search(t) | Input::AddTags(t) | Input::RemoveTag(t) => t,
+            _ => return Ok(()),
+        };
+        match code {
+            KeyCode::Char(c) => text.push(c),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Esc => self.input = Input::None,
+            KeyCode::Enter => match std::mem::replace(&mut self.input, Input::None) {
+                Input::Search(text) => {
+                    self.filter.search = Some(text.trim().to_string()).filter(|s| !s.is_empty());
+                    self.reload()?;
+                    self.status = match &self.filter.search {
+                        Some(q) => format!("{} result(s) for '{}'", self.generations.len(), q),
+                        None => "Search cleared".to_string(),
+                    };
+                }
+                Input::AddTags(text) => {
+                    let tags: Vec<String> = text
+                        .split(',')
+                        .map(|t| t.trim().to_string())
+                        .filter(|t| !t.is_empty())
+                        .collect();
+                    if !tags.is_empty() {
+                        self.edit(|db, id| {
+                            db.add_tags(id, &tags)?;
+                            Ok(format!("Tagged #{}: {}", id, tags.join(", ")))
+                        });
+                    }
+                }
+                Input::RemoveTag(text) => {
+                    let tag = text.trim().to_string();
+                    if !tag.is_empty() {
+                        self.edit(|db, id| {
+                            db.remove_tag(id, &tag)?;
+                            Ok(format!("Removed '{}' from #{}", tag, id))
+                        });
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) -> Rect {
+    let [main, footer] = Layout::vertical([Constraint::Min(3), Constraint::Length(2)]).areas(frame.area());
+    let [left, right] = Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)]).areas(main);
+
+    // Generation list
+    let items: Vec<ListItem> = app
+        .generations
+        .iter()
+        .map(|g| {
+            let star = if g.starred { "*" } else { " " };
+            let label = g.title.as_deref().unwrap_or(&g.prompt).replace('\n', " ");
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:>5}{} ", g.id, star), Style::default().fg(Color::Yellow)),
+                Span::styled(format!("{} ", &g.date), Style::default().fg(Color::DarkGray)),
+                Span::raw(label),
+            ]))
+        })
+        .collect();
+    let mut title = format!(" Generations ({}) ", app.generations.len());
+    if let Some(q) = &app.filter.search {
+        title = format!(" Generations ({}) - '{}' ", app.generations.len(), q);
+    }
+    if app.filter.starred_only {
+        title.push_str("[starred] ");
+    }
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, left, &mut app.list);
+
+    // Details + preview
+    let [meta_area, preview_block] = Layout::vertical([Constraint::Length(8), Constraint::Min(4)]).areas(right);
+    let preview_area = Block::default().borders(Borders::ALL).title(" Preview ").inner(preview_block);
+    frame.render_widget(Block::default().borders(Borders::ALL).title(" Preview "), preview_block);
+
+    if let Some(gen) = app.selected().cloned() {
+        let mut lines = vec![
+            Line::from(vec![Span::styled("Model  ", Style::default().fg(Color::DarkGray)), Span::raw(gen.model.clone())]),
+            Line::from(vec![
+                Span::styled("Cost   ", Style::default().fg(Color::DarkGray)),
+                Span::raw(gen.cost_estimate_usd.map(|c| format!("${:.3}", c)).unwrap_or_else(|| "-".to_string())),
+                Span::styled("   Size ", Style::default().fg(Color::DarkGray)),
+                Span::raw(match (gen.width, gen.height) {
+                    (Some(w), Some(h)) => format!("{}x{}", w, h),
+                    _ => "-".to_string(),
+                }),
+            ]),
+            Line::from(vec![Span::styled("Tags   ", Style::default().fg(Color::DarkGray)), Span::raw(gen.tags.join(", "))]),
+        ];
+        lines.push(Line::from(Span::raw(gen.prompt.clone())));
+        let meta = Paragraph::new(lines)
+            .wrap(Wrap { trim: true })
+            .block(Block::default().borders(Borders::ALL).title(format!(" #{} ", gen.id)));
+        frame.render_widget(meta, meta_area);
+
+        if app.preview_mode == PreviewMode::Blocks {
+            let key = (gen.id, preview_area.width, preview_area.height);
+            if app.block_cache.as_ref().map(|(k, _)| *k) != Some(key) {
+                let path = archive::display_path(&gen.image_path, ThumbSize::Preview);
+                let lines = half_block_lines(Path::new(&path), preview_area.width, preview_area.height)
+                    .unwrap_or_else(|e| vec![Line::from(format!("No preview: {}", e))]);
+                app.block_cache = Some((key, lines));
+            }
+            if let Some((_, lines)) = &app.block_cache {
+                frame.render_widget(Paragraph::new(lines.clone()), preview_area);
+            }
+        }
+    } else {
+        frame.render_widget(Block::default().borders(Borders::ALL), meta_area);
+    }
+
+    // Footer: prompt or status, then key help
+    let prompt_line = match &app.input {
+        Input::None => Line::from(app.status.clone()),
+        Input::Search(t) => Line::from(format!("Search: {}_", t)),
+        Input::AddTags(t) => Line::from(format!("Add tags (comma-separated): {}_", t)),
+        Input::RemoveTag(t) => Line::from(format!("Remove tag: {}_", t)),
+        Input::ConfirmTrash => Line::from("Move to trash? (y/N)"),
+    };
+    frame.render_widget(
+        Paragraph::new(vec![prompt_line, Line::styled(HELP, Style::default().fg(Color::DarkGray))]),
+        footer,
+    );
+
+    preview_area
+}
+
+/// Render an image as rows of "▀" cells: foreground is the upper pixel,
+/// background the lower, so each cell shows two roughly square pixels.
+fn half_block_lines(path: &Path, width: u16, height: u16) -> Result<Vec<Line<'static>>> {
+    let img = image::open(path).context("unreadable image")?;
+    let img = img.resize(width as u32, height as u32 * 2, image::imageops::FilterType::Triangle).to_rgb8();
+    let mut lines = vec![];
+    for y in (0..img.height()).step_by(2) {
+        let spans: Vec<Span> = (0..img.width())
+            .map(|x| {
+                let top = img.get_pixel(x, y);
+                let bottom = if y + 1 < img.height() { img.get_pixel(x, y + 1) } else { top };
+                Span::styled(
+                    "\u{2580}",
+                    Style::default()
+                        .fg(Color::Rgb(top[0], top[1], top[2]))
+                        .bg(Color::Rgb(bottom[0], bottom[1], bottom[2])),
+                )
+            })
+            .collect();
+        lines.push(Line::from(spans));
+    }
+    Ok(lines)
+}
+
+/// Place an image over `area` with the kitty graphics protocol (PNG payload,
+/// sent in 4KB chunks). Replaces any image placed before.
+fn kitty_show(path: &Path, area: Rect) -> Result<()> {
+    let img = image::open(path).context("unreadable image")?;
+    let mut png = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut png, image::ImageFormat::Png)?;
+    let data = base64::engine::general_purpose::STANDARD.encode(png.into_inner());
+
+    let mut out = std::io::stdout().lock();
+    kitty_clear(&mut out)?;
+    execute!(out, cursor::MoveTo(area.x, area.y))?;
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+        let chunk = std::str::from_utf8(chunk)?;
+        if i == 0 {
+            // a=T transmit+display, f=100 PNG, c/r fit to cells, C=1 keep cursor, q=2 quiet
+            write!(out, "\x1b_Ga=T,f=100,c={},r={},C=1,q=2,m={};{}\x1b\\", area.width, area.height, more, chunk)?;
+        } else {
+            write!(out, "\x1b_Gm={};{}\x1b\\", more, chunk)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn kitty_clear(out: &mut impl Write) -> Result<()> {
+    write!(out, "\x1b_Ga=d,q=2\x1b\\")?;
+    Ok(())
+}
+
+fn run_app(terminal: &mut DefaultTerminal, app: &mut App) -> Result<()> {
+    loop {
+        let mut preview_area = Rect::default();
+        terminal.draw(|frame| preview_area = draw(frame, app))?;
+
+        if app.preview_mode == PreviewMode::Kitty {
+            let current = app.selected().map(|g| {
+                (
+                    (g.id, preview_area.width, preview_area.height),
+                    archive::display_path(&g.image_path, ThumbSize::Preview),
+                )
+            });
+            if current.as_ref().map(|(k, _)| *k) != app.kitty_shown {
+                match &current {
+                    Some((_, path)) => {
+                        if let Err(e) = kitty_show(Path::new(path), preview_area) {
+                            app.status = format!("No preview: {}", e);
+                        }
+                    }
+                    None => kitty_clear(&mut std::io::stdout())?,
+                }
+                app.kitty_shown = current.map(|(k, _)| k);
+            }
+        }
+
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && !app.on_key(key.code)? {
+                return Ok(());
+            }
+        } else {
+            // Resize etc.: force the kitty image to be re-placed
+            app.kitty_shown = None;
+        }
+    }
+}
+
+/// Open the terminal browser. Edits go straight to the database, so the GUI
+/// (if open) picks them up on its next refresh.
+pub fn run(db: &Database) -> Result<()> {
+    let mut app = App::new(db, PreviewMode::detect());
+    app.reload()?;
+    app.status = format!("Loaded {} generation(s)", app.generations.len());
+
+    enable_raw_mode()?;
+    execute!(std::io::stdout(), EnterAlternateScreen)?;
+    let mut terminal = ratatui::Terminal::new(ratatui::backend::CrosstermBackend::new(std::io::stdout()))?;
+
+    let result = run_app(&mut terminal, &mut app);
+
+    // Always restore the terminal, even when the app errored
+    if app.preview_mode == PreviewMode::Kitty {
+        let _ = kitty_clear(&mut std::io::stdout());
+    }
+    let _ = disable_raw_mode();
+    let _ = execute!(std::io::stdout(), LeaveAlternateScreen, cursor::Show);
+    result
+}
@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::Command;
+
+/// Extensions archived as video clips
+const EXTENSIONS: &[&str] = &["mp4", "webm", "mov"];
+
+/// Video file, by extension
+pub fn is_video(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// File extension for clip bytes, from their container signature
+pub fn extension_for(data: &[u8]) -> Option<&'static str> {
+    match data.get(..8)? {
+        [0x1a, 0x45, 0xdf, 0xa3, ..] => Some("webm"),
+        [_, _, _, _, b'f', b't', b'y', b'p'] if data.get(8..10) == Some(b"qt") => Some("mov"),
+        [_, _, _, _, b'f', b't', b'y', b'p'] => Some("mp4"),
+        _ => None,
+    }
+}
+
+/// Run an ffmpeg tool, returning its stdout
+fn ffmpeg_tool<S: AsRef<OsStr>>(program: &str, args: &[S]) -> Result<Vec<u8>> {
+    let output = Command::new(program).args(args).output().map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            anyhow::anyhow!("{} not found; install ffmpeg for video thumbnails and metadata", program)
+        } else {
+            anyhow::anyhow!("Failed to run {}: {}", program, e)
+        }
+    })?;
+    if !output.status.success() {
+        anyhow::bail!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(output.stdout)
+}
+
+/// What ffprobe reports about a clip
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VideoInfo {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub duration_seconds: Option<f64>,
+    pub fps: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    width: Option<i32>,
+    height: Option<i32>,
+    /// "24/1", "30000/1001"
+    avg_frame_rate: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    /// Seconds, as a decimal string
+    duration: Option<String>,
+}
+
+/// ffprobe's "num/den" rate; None for "0/0" (unknown)
+fn parse_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let (num, den): (f64, f64) = (num.parse().ok()?, den.parse().ok()?);
+    (num > 0.0 && den > 0.0).then(|| num / den)
+}
+
+/// Size, length and frame rate of a clip's first video stream
+pub fn probe(path: &Path) -> Result<VideoInfo> {
+    let stdout = ffmpeg_tool(
+        "ffprobe",
+        &[
+            OsStr::new("-v"),
+            OsStr::new("error"),
+            OsStr::new("-select_streams"),
+            OsStr::new("v:0"),
+            OsStr::new("-show_entries"),
+            OsStr::new("stream=width,height,avg_frame_rate:format=duration"),
+            OsStr::new("-of"),
+            OsStr::new("json"),
+            path.as_os_str(),
+        ],
+    )?;
+    let output: ProbeOutput = serde_json::from_slice(&stdout).context("Failed to parse ffprobe output")?;
+    let stream = output.streams.into_iter().next();
+    Ok(VideoInfo {
+        width: stream.as_ref().and_then(|s| s.width),
+        height: stream.as_ref().and_then(|s| s.height),
+        duration_seconds: output.format.and_then(|f| f.duration).and_then(|d| d.parse().ok()),
+        fps: stream.and_then(|s| s.avg_frame_rate).as_deref().and_then(parse_rate),
+    })
+}
+
+/// A clip's first frame, which its thumbnails are made from
+pub fn poster_frame(path: &Path) -> Result<image::DynamicImage> {
+    let png = ffmpeg_tool(
+        "ffmpeg",
+        &[
+            OsStr::new("-v"),
+            OsStr::new("error"),
+            OsStr::new("-i"),
+            path.as_os_str(),
+            OsStr::new("-frames:v"),
+            OsStr::new("1"),
+            OsStr::new("-f"),
+            OsStr::new("image2pipe"),
+            OsStr::new("-c:v"),
+            OsStr::new("png"),
+            OsStr::new("-"),
+        ],
+    )?;
+    image::load_from_memory(&png).with_context(|| format!("Failed to decode the first frame of {}", path.display()))
+}
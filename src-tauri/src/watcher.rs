@@ -1,9 +1,55 @@
+use anyhow::{Context, Result};
 use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebouncedEventKind};
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::time::Duration;
 use tauri::{AppHandle, Emitter};
 
+use crate::archive;
+use crate::db::Database;
+use crate::models::DuplicateAction;
+use crate::workflow::{self, ImportOptions, Imported};
+
+/// A directory whose new images are imported into the archive automatically
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchFolder {
+    pub path: PathBuf,
+    /// Model recorded on imported generations
+    #[serde(default = "default_model")]
+    pub model: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_model() -> String {
+    "unknown".to_string()
+}
+
+/// Watch folder settings, stored in watch.json in the archive root
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IngestSettings {
+    pub folders: Vec<WatchFolder>,
+}
+
+fn settings_path() -> PathBuf {
+    archive::archive_root().join("watch.json")
+}
+
+pub fn load_settings() -> IngestSettings {
+    std::fs::read_to_string(settings_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &IngestSettings) -> Result<()> {
+    std::fs::write(settings_path(), serde_json::to_string_pretty(settings)?)
+        .context("Failed to write watch folder settings")
+}
+
 /// Starts watching the generations directory for new images.
 /// Emits "generation-added" event when new .png files are detected.
 pub fn start_watcher(app: AppHandle, generations_dir: &Path) {
@@ -34,26 +80,9 @@ pub fn start_watcher(app: AppHandle, generations_dir: &Path) {
             match rx.recv() {
                 Ok(Ok(events)) => {
                     // Check if any event is a new .png file (not a thumbnail)
-                    let has_new_image = events.iter().any(|event| {
-                        if event.kind != DebouncedEventKind::Any {
-                            return false;
-                        }
-                        let path = &event.path;
-                        let is_image = path
-                            .extension()
-                            .and_then(|ext| ext.to_str())
-                            .map(|ext| {
-                                let ext = ext.to_ascii_lowercase();
-                                ext == "png" || ext == "jpg" || ext == "jpeg" || ext == "webp"
-                            })
-                            .unwrap_or(false);
-                        let is_thumb = path
-                            .file_name()
-                            .and_then(|n| n.to_str())
-                            .map(|n| n.contains(".thumb."))
-                            .unwrap_or(false);
-                        is_image && !is_thumb
-                    });
+                    let has_new_image = events
+                        .iter()
+                        .any(|event| event.kind == DebouncedEventKind::Any && archive::is_image(&event.path));
 
                     if has_new_image {
                         if let Err(e) = app.emit("generation-added", ()) {
@@ -72,3 +101,106 @@ pub fn start_watcher(app: AppHandle, generations_dir: &Path) {
         }
     });
 }
+
+/// Wait until a file stops growing, so half-written downloads aren't imported
+fn wait_until_written(path: &Path) -> bool {
+    let mut last = None;
+    for _ in 0..20 {
+        let size = std::fs::metadata(path).map(|m| m.len()).ok();
+        if size.is_some_and(|s| s > 0) && size == last {
+            return true;
+        }
+        last = size;
+        std::thread::sleep(Duration::from_millis(500));
+    }
+    false
+}
+
+/// Import one file from a watch folder. The filename stem becomes the prompt,
+/// as with `pixery import`.
+pub fn ingest_file(db: &Database, path: &Path, folder: &WatchFolder) -> Result<Imported> {
+    let options = ImportOptions {
+        model: folder.model.clone(),
+        tags: folder.tags.clone(),
+        on_duplicate: DuplicateAction::Skip,
+        ..Default::default()
+    };
+    workflow::import_image(db, path, &options)
+}
+
+/// Watch the given folders (non-recursively) and import each new image once.
+/// Blocks until the watcher fails; `on_result` reports every import attempt.
+pub fn watch_folders<F>(db: &Database, folders: &[WatchFolder], mut on_result: F) -> Result<()>
+where
+    F: FnMut(&Path, Result<Imported>),
+{
+    let (tx, rx) = mpsc::channel();
+    // Longer debounce than the generations watcher: browsers write downloads in bursts
+    let mut debouncer = new_debouncer(Duration::from_secs(2), tx).context("Failed to create file watcher")?;
+    for folder in folders {
+        debouncer
+            .watcher()
+            .watch(&folder.path, RecursiveMode::NonRecursive)
+            .with_context(|| format!("Failed to watch {}", folder.path.display()))?;
+    }
+
+    // Files seen this session; a file rewritten in place is not imported twice
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+
+    loop {
+        let events = match rx.recv() {
+            Ok(Ok(events)) => events,
+            Ok(Err(e)) => {
+                eprintln!("Watch error: {:?}", e);
+                continue;
+            }
+            Err(e) => anyhow::bail!("Watch channel closed: {:?}", e),
+        };
+
+        for event in events {
+            let path = event.path;
+            if !archive::is_image(&path) || !path.is_file() || seen.contains(&path) {
+                continue;
+            }
+            let Some(folder) = folders.iter().find(|f| path.parent() == Some(f.path.as_path())) else {
+                continue;
+            };
+            if !wait_until_written(&path) {
+                continue;
+            }
+            seen.insert(path.clone());
+            on_result(&path, ingest_file(db, &path, folder));
+        }
+    }
+}
+
+/// Starts importing from configured watch folders while the GUI is open.
+/// Uses its own database connection; imported images reach the UI through
+/// the generations watcher.
+pub fn start_ingest() {
+    let folders = load_settings().folders;
+    if folders.is_empty() {
+        return;
+    }
+
+    std::thread::spawn(move || {
+        let db = match Database::open(&archive::db_path()) {
+            Ok(db) => db,
+            Err(e) => {
+                eprintln!("Watch folders disabled: {}", e);
+                return;
+            }
+        };
+        println!("Watching {} folder(s) for images to import", folders.len());
+        let result = watch_folders(&db, &folders, |path, result| match result {
+            Ok(imported) if imported.duplicate => {
+                println!("Skipped {}: already archived as ID {}", path.display(), imported.id)
+            }
+            Ok(imported) => println!("Imported {} (ID: {})", path.display(), imported.id),
+            Err(e) => eprintln!("Failed to import {}: {}", path.display(), e),
+        });
+        if let Err(e) = result {
+            eprintln!("Watch folders stopped: {}", e);
+        }
+    });
+}
@@ -1,12 +1,142 @@
-use anyhow::Result;
-use std::path::Path;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 use crate::archive;
+use crate::cancel;
+use crate::custom_models;
 use crate::db::Database;
-use crate::models::{Generation, GenerationResult, JobSource, ModelInfo};
+use crate::detail::{self, FixOptions};
+use crate::error::PixeryError;
+use crate::exif;
+use crate::models::{DuplicateAction, Generated, Generation, GenerationRequest, GenerationResult, JobSource, JobStatus, ModelInfo, ModelQuota, ProgressStage, PromptingGuide, RetryReport, VideoModelInfo, VideoRequest};
+use crate::notify;
+use crate::nsfw;
+use crate::palette;
 use crate::providers;
+use crate::title::{self, TitleSource};
+use crate::video;
+
+/// Generation defaults, stored in generation.json in the archive root
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GenerationSettings {
+    /// Apply the model's negative template whenever no negative prompt is given
+    pub auto_negative: bool,
+    /// Title every generation from its prompt
+    pub auto_title: bool,
+    /// How auto-titles are made
+    pub title_source: TitleSource,
+    /// What to do when a provider returns an image identical to an archived
+    /// one; "import" by default so the call's cost stays on record
+    pub on_duplicate: DuplicateAction,
+}
+
+pub fn load_settings() -> GenerationSettings {
+    std::fs::read_to_string(archive::archive_root().join("generation.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Fill in the prompting guide's negative template when the request has no
+/// negative prompt and auto-negative is on (per request or in generation.json).
+/// Models whose guide has no template are left alone.
+pub fn apply_auto_negative(req: &mut GenerationRequest) {
+    if req.negative_prompt.is_some() || !(req.auto_negative || load_settings().auto_negative) {
+        return;
+    }
+    req.negative_prompt = PromptingGuide::for_model(&ModelInfo::canonical_id(&req.model))
+        .and_then(|g| g.negative_template)
+        .map(|t| t.to_string());
+}
+
+/// Fill in a title derived from the prompt when the request has none and
+/// auto-title is on (per request or in generation.json)
+pub async fn apply_auto_title(req: &mut GenerationRequest) {
+    if req.title.is_some() {
+        return;
+    }
+    let settings = load_settings();
+    if !(req.auto_title || settings.auto_title) {
+        return;
+    }
+    req.title = title::generate(&req.prompt, settings.title_source).await;
+}
+
+/// Resolve a reference spec to a file path: `ref:42` is a stored reference by ID,
+/// `gen:140` an archived generation's image, anything else is a path as given.
+pub fn resolve_reference(db: &Database, spec: &str) -> Result<String> {
+    if let Some(id) = spec.strip_prefix("ref:") {
+        let id: i64 = id.parse().with_context(|| format!("Invalid reference ID in '{}'", spec))?;
+        let reference = db
+            .get_reference(id)?
+            .ok_or_else(|| PixeryError::NotFound(format!("Reference {} not found", id)))?;
+        return Ok(reference.path);
+    }
+    if let Some(id) = spec.strip_prefix("gen:") {
+        let id: i64 = id.parse().with_context(|| format!("Invalid generation ID in '{}'", spec))?;
+        let generation = db
+            .get_generation(id)?
+            .ok_or_else(|| PixeryError::NotFound(format!("Generation {} not found", id)))?;
+        return Ok(generation.image_path);
+    }
+    Ok(spec.to_string())
+}
+
+pub fn resolve_references(db: &Database, specs: &[String]) -> Result<Vec<String>> {
+    specs.iter().map(|spec| resolve_reference(db, spec)).collect()
+}
+
+/// Reject more reference images than the model accepts, rather than letting the
+/// provider silently drop the extras. Unknown models pass (the provider decides).
+pub fn validate_references(req: &GenerationRequest) -> Result<()> {
+    let Some(info) = ModelInfo::find(&req.model) else {
+        return Ok(());
+    };
+    let count = req.reference_paths.len();
+    if count > info.max_refs as usize {
+        if info.max_refs == 0 {
+            anyhow::bail!("{} does not accept reference images (got {})", info.display_name, count);
+        }
+        anyhow::bail!(
+            "{} accepts at most {} reference image(s), got {}",
+            info.display_name,
+            info.max_refs,
+            count
+        );
+    }
+    Ok(())
+}
+
+/// The request that made `gen` again, from its stored params, with its seed
+/// pinned so providers that take one can return the same image. The result
+/// is one image, a child of `gen`.
+pub fn reproduction_request(gen: &Generation) -> Result<GenerationRequest> {
+    // Imports only carry their embedded camera metadata
+    let params = gen.params.clone().filter(|p| p.get("prompt").is_some()).with_context(|| {
+        format!(
+            "Generation {} has no recorded parameters (imported, or made before they were stored)",
+            gen.id
+        )
+    })?;
+    let mut req: GenerationRequest =
+        serde_json::from_value(params).context("Stored generation parameters are invalid")?;
+    if let Some(missing) = req.reference_paths.iter().find(|p| !Path::new(p).exists()) {
+        return Err(PixeryError::FileMissing(format!("Reference image {} is missing from the archive", missing)).into());
+    }
+    req.seed = req.seed.or_else(|| gen.seed.as_deref().and_then(|s| s.parse().ok()));
+    req.num_images = None;
+    req.parent_id = Some(gen.id);
+    // Negative and title are already resolved
+    req.auto_negative = false;
+    req.auto_title = false;
+    Ok(req)
+}
 
 /// Pre-generation: create job, resolve model info. Returns (job_id, estimated_cost, provider).
+/// `request` is stored on the job for `pixery jobs retry`; pass None when replaying it
+/// as a plain generation would be wrong (fix passes).
 pub fn prepare_generation(
     db: &Database,
     model: &str,
@@ -14,117 +144,833 @@ pub fn prepare_generation(
     tags: &[String],
     source: JobSource,
     ref_count: usize,
+    request: Option<&GenerationRequest>,
 ) -> Result<(i64, Option<f64>, String)> {
-    let model_info = ModelInfo::find(model);
-    let estimated_cost = model_info.as_ref().map(|m| m.cost_per_image);
-    let provider = model_info
-        .as_ref()
+    let provider = ModelInfo::find(model)
         .map(|m| m.provider.to_string())
         .unwrap_or_else(|| "unknown".to_string());
 
+    // Jobs and generations record the model ID, not the alias it was requested by
+    let model = ModelInfo::canonical_id(model);
+    let estimated_cost = price_per_image(db, &model)?;
+    check_quota(db, &model)?;
+    if request.is_some_and(|r| r.transparent) {
+        check_transparency(&model)?;
+    }
     let tags_opt = if tags.is_empty() { None } else { Some(tags) };
-    let job_id = db.create_job(model, prompt, tags_opt, source, ref_count as i32)?;
+    let job_id = db.create_job(&model, prompt, tags_opt, source, ref_count as i32, request)?;
     db.update_job_started(job_id)?;
 
     Ok((job_id, estimated_cost, provider))
 }
 
-/// Post-generation: save image, insert into DB, add tags, link refs, complete job.
-/// Returns (generation_id, Generation).
+/// The price `pixery pricing` set for a model as of today. None when it has
+/// none, and the built-in (or models.json) price applies.
+pub fn current_price(db: &Database, model_id: &str) -> Result<Option<f64>> {
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    db.price_on(model_id, &today)
+}
+
+/// Per-image estimate for an image model: today's price from the pricing
+/// table, else its `cost_per_image`. None for unknown models.
+pub fn price_per_image(db: &Database, model: &str) -> Result<Option<f64>> {
+    let model = ModelInfo::canonical_id(model);
+    Ok(current_price(db, &model)?.or_else(|| ModelInfo::find(&model).map(|m| m.cost_per_image)))
+}
+
+/// `ModelInfo::all()` with today's prices from the pricing table
+pub fn priced_models(db: &Database) -> Result<Vec<ModelInfo>> {
+    let mut models = ModelInfo::all();
+    for info in &mut models {
+        if let Some(cost) = current_price(db, &info.id)? {
+            info.cost_per_image = cost;
+        }
+    }
+    Ok(models)
+}
+
+/// Today's use of a model's daily quota; None when models.json sets none
+pub fn model_quota(db: &Database, model_id: &str) -> Result<Option<ModelQuota>> {
+    let Some(limit) = custom_models::load().quota_for(model_id, &ModelInfo::all()) else {
+        return Ok(None);
+    };
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let used = db.count_jobs_started(model_id, &today)?;
+    Ok(Some(ModelQuota {
+        model: model_id.to_string(),
+        limit,
+        used,
+        remaining: limit.saturating_sub(used),
+    }))
+}
+
+/// Every quota in models.json, by model ID
+pub fn model_quotas(db: &Database) -> Result<Vec<ModelQuota>> {
+    let config = custom_models::load();
+    let known = ModelInfo::all();
+    let mut quotas = vec![];
+    for key in config.quotas.keys() {
+        let model_id = config.resolve_alias(key, &known);
+        if let Some(quota) = model_quota(db, &model_id)? {
+            quotas.push(quota);
+        }
+    }
+    Ok(quotas)
+}
+
+/// Refuse `--transparent` when nothing would make the background transparent:
+/// the model can't itself, and there's no self-hosted server for /rembg
+fn check_transparency(model_id: &str) -> Result<()> {
+    if providers::native_transparency(model_id) || providers::selfhosted::get_server_url().is_some() {
+        return Ok(());
+    }
+    anyhow::bail!(
+        "{} can't make transparent backgrounds; use gpt-image-1, or set SELFHOSTED_API_URL for background removal",
+        model_id
+    )
+}
+
+/// Refuse to start a job once the model's daily quota is used up
+fn check_quota(db: &Database, model_id: &str) -> Result<()> {
+    match model_quota(db, model_id)? {
+        Some(quota) if quota.remaining == 0 => Err(PixeryError::QuotaExceeded {
+            model: quota.model,
+            limit: quota.limit,
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Post-generation: save images, insert into DB, add tags, link refs, complete job.
+/// Each returned image gets its own generation row sharing the job's metadata; the
+/// job points at the first. Returns one `Generated` per image, noting duplicates.
 pub fn complete_generation(
     db: &Database,
     job_id: i64,
-    prompt: &str,
-    model: &str,
+    req: &GenerationRequest,
     provider: &str,
     tags: &[String],
-    reference_paths: &[String],
     result: &GenerationResult,
     estimated_cost: Option<f64>,
-    negative_prompt: Option<&str>,
-) -> Result<(i64, Generation)> {
+    progress: providers::Progress<'_>,
+) -> Result<Vec<Generated>> {
     let now = chrono::Local::now();
     let date = now.format("%Y-%m-%d").to_string();
     let timestamp = now.format("%Y-%m-%dT%H:%M:%S").to_string();
-    let slug = archive::slugify_prompt(prompt);
+    let slug = archive::slugify_prompt(&req.prompt);
+    let model = ModelInfo::canonical_id(&req.model);
 
-    let (image_path, thumb_path, width, height, file_size) =
-        archive::save_image(&result.image_data, &date, &slug, &timestamp)?;
+    // Store references once; every image from the call links to them.
+    // An inpainting mask is kept as a reference too (the source is the parent).
+    let mask_path = req.inpaint.as_ref().map(|i| &i.mask_path);
+    let mut ref_ids = Vec::with_capacity(req.reference_paths.len() + 1);
+    let mut stored_paths = Vec::with_capacity(req.reference_paths.len());
+    for ref_path in req.reference_paths.iter().chain(mask_path) {
+        let (hash, stored_path) = archive::store_reference(Path::new(ref_path))?;
+        ref_ids.push(db.get_or_create_reference(&hash, stored_path.to_str().unwrap())?);
+        stored_paths.push(stored_path.to_string_lossy().into_owned());
+    }
+
+    // The request as it was sent, pointing at the archived references
+    let mut resolved = req.clone();
+    resolved.model = model.clone();
+    stored_paths.truncate(req.reference_paths.len());
+    resolved.reference_paths = stored_paths;
+    resolved.seed = result.seed.as_deref().and_then(|s| s.parse().ok()).or(req.seed);
+    let params = serde_json::to_value(&resolved)?;
+
+    // API cost covers the whole call; the estimate is already per image
+    let image_count = result.images.len().max(1) as f64;
+    let cost = result.cost_usd.map(|c| c / image_count).or(estimated_cost);
+
+    let on_duplicate = load_settings().on_duplicate;
+    let mut generations = Vec::with_capacity(result.images.len());
+    for image_data in &result.images {
+        progress(ProgressStage::Saving);
+        let (content_hash, existing) = find_duplicate(db, image_data)?;
+        if let Some(existing_id) = existing {
+            if on_duplicate != DuplicateAction::Import {
+                if on_duplicate == DuplicateAction::Link {
+                    link_duplicate(db, existing_id, tags, &ref_ids)?;
+                }
+                let generation = db
+                    .get_generation(existing_id)?
+                    .ok_or_else(|| anyhow::anyhow!("Generation {} not found", existing_id))?;
+                generations.push(Generated { id: existing_id, generation, duplicate_of: Some(existing_id) });
+                continue;
+            }
+        }
+
+        let (image_path, thumb_path, width, height, file_size) =
+            archive::save_image(image_data, &date, &slug, &timestamp)?;
+        progress(ProgressStage::ThumbnailDone);
+
+        let gen_id = db.insert_generation(
+            &slug,
+            &req.prompt,
+            &model,
+            provider,
+            &timestamp,
+            &date,
+            image_path.to_str().unwrap(),
+            thumb_path.as_ref().and_then(|p| p.to_str()),
+            Some(result.generation_time_seconds),
+            cost,
+            result.seed.as_deref(),
+            Some(width),
+            Some(height),
+            Some(file_size),
+            req.parent_id,
+            req.negative_prompt.as_deref(),
+        )?;
+        db.set_content_hash(gen_id, &content_hash)?;
+        db.set_generation_params(gen_id, &params)?;
+        if archive::is_animated(image_data) {
+            db.set_animated(gen_id)?;
+        }
+        palette::record_new(db, gen_id, &image_path);
+        archive::queue_thumbnails(image_path.clone());
+
+        if !tags.is_empty() {
+            db.add_tags(gen_id, tags)?;
+        }
+        // So assets meant for compositing can be filtered; an image whose
+        // background couldn't be removed isn't one
+        if req.transparent && providers::has_alpha(image_data) {
+            db.add_tags(gen_id, &["transparent".to_string()])?;
+        }
+        flag_nsfw_prompt(db, gen_id, &req.prompt)?;
+
+        if let Some(title) = &req.title {
+            db.update_title(gen_id, Some(title))?;
+        }
+
+        for &ref_id in &ref_ids {
+            db.link_reference(gen_id, ref_id)?;
+        }
+
+        let generation = db
+            .get_generation(gen_id)?
+            .ok_or_else(|| anyhow::anyhow!("Failed to retrieve generation after insert"))?;
+        generations.push(Generated { id: gen_id, generation, duplicate_of: existing });
+    }
+
+    let first_id = generations
+        .first()
+        .map(|saved| saved.id)
+        .ok_or_else(|| anyhow::anyhow!("Provider returned no images"))?;
+    db.update_job_completed(job_id, first_id)?;
+
+    if let Some(event) = notify::WebhookEvent::completed(job_id, &generations) {
+        notify::post_webhook(event);
+    }
+
+    Ok(generations)
+}
+
+/// Flag a new generation as NSFW when its prompt has a rating tag (unless
+/// that's turned off in nsfw.json)
+fn flag_nsfw_prompt(db: &Database, gen_id: i64, prompt: &str) -> Result<()> {
+    if nsfw::load_settings().from_prompt && nsfw::prompt_is_nsfw(prompt) {
+        db.set_nsfw(gen_id, true)?;
+    }
+    Ok(())
+}
+
+/// Run the NSFW classifier, when it's on, over freshly saved generations
+pub async fn classify_nsfw(db: &Database, generations: &mut [Generated]) -> Result<()> {
+    let settings = nsfw::load_settings();
+    for saved in generations.iter_mut() {
+        if nsfw::classify_new(&saved.generation, &settings).await {
+            db.set_nsfw(saved.id, true)?;
+            saved.generation.nsfw = true;
+        }
+    }
+    Ok(())
+}
+
+/// Mark a job cancelled or failed depending on why its provider call stopped
+pub fn record_job_error(db: &Database, job_id: i64, err: &anyhow::Error) -> Result<()> {
+    if cancel::is_cancelled(err) {
+        return db.update_job_cancelled(job_id);
+    }
+    db.update_job_failed(job_id, &err.to_string(), PixeryError::from(err).code())?;
+    if let Some(job) = db.get_job(job_id)? {
+        notify::post_webhook(notify::WebhookEvent::failed(job_id, &job.model, &job.prompt, &err.to_string()));
+    }
+    Ok(())
+}
+
+/// Full generation workflow (CLI convenience -- no Send requirement).
+pub async fn perform_generation(
+    db: &Database,
+    req: &GenerationRequest,
+    tags: &[String],
+    source: JobSource,
+    progress: providers::Progress<'_>,
+) -> Result<Vec<Generated>> {
+    run_generation(db, req, tags, source, None, progress).await
+}
+
+/// `perform_generation`, optionally as a re-run of job `retry_of` (linked to it
+/// as soon as the new job exists, so a retry that fails again isn't retried twice)
+async fn run_generation(
+    db: &Database,
+    req: &GenerationRequest,
+    tags: &[String],
+    source: JobSource,
+    retry_of: Option<i64>,
+    progress: providers::Progress<'_>,
+) -> Result<Vec<Generated>> {
+    let mut req = req.clone();
+    apply_auto_negative(&mut req);
+    req.reference_paths = resolve_references(db, &req.reference_paths)?;
+    validate_references(&req)?;
+    apply_auto_title(&mut req).await;
+    let req = &req;
+
+    let (job_id, estimated_cost, provider) = prepare_generation(
+        db,
+        &req.model,
+        &req.prompt,
+        tags,
+        source,
+        req.reference_paths.len(),
+        Some(req),
+    )?;
+    if let Some(original) = retry_of {
+        db.set_job_retried(original, job_id)?;
+    }
+    progress(ProgressStage::Queued);
+
+    let policy = providers::RetryPolicy::from_env();
+    let call = providers::with_retry(
+        &policy,
+        || providers::generate(req, progress),
+        |retry, e| {
+            eprintln!("Transient error, retrying ({}): {}", retry, e);
+            let _ = db.update_job_retry_count(job_id, retry);
+        },
+    );
+    let result = match cancel::run(job_id, call).await {
+        Ok(r) => r,
+        Err(e) => {
+            record_job_error(db, job_id, &e)?;
+            return Err(e);
+        }
+    };
+
+    let mut generations = complete_generation(
+        db,
+        job_id,
+        req,
+        &provider,
+        tags,
+        &result,
+        estimated_cost,
+        progress,
+    )?;
+    classify_nsfw(db, &mut generations).await?;
+    Ok(generations)
+}
+
+/// Re-run a failed or cancelled job with the request it was started with, as a new job
+pub async fn retry_job(
+    db: &Database,
+    job_id: i64,
+    source: JobSource,
+    progress: providers::Progress<'_>,
+) -> Result<Vec<Generated>> {
+    let job = db
+        .get_job(job_id)?
+        .ok_or_else(|| PixeryError::NotFound(format!("Job {} not found", job_id)))?;
+    if !matches!(job.status, JobStatus::Failed | JobStatus::Cancelled) {
+        anyhow::bail!("Job {} is {}; only failed or cancelled jobs can be retried", job_id, job.status);
+    }
+    let req = db.get_job_request(job_id)?.ok_or_else(|| {
+        anyhow::anyhow!("Job {} has no stored request (fix pass, or created before jobs recorded one)", job_id)
+    })?;
+    let tags = job.tags.unwrap_or_default();
+    run_generation(db, &req, &tags, source, Some(job_id), progress).await
+}
+
+/// Spend limit for `retry-failed` when none is given (USD)
+pub const DEFAULT_RETRY_COST_CAP: f64 = 1.0;
+
+/// Re-run every failed job from the last `hours` that hasn't been retried yet,
+/// oldest first, skipping any whose estimated cost would take the total past `max_cost`
+pub async fn retry_failed(
+    db: &Database,
+    hours: i64,
+    max_cost: f64,
+    source: JobSource,
+    progress: providers::Progress<'_>,
+) -> Result<RetryReport> {
+    let cutoff = chrono::Local::now() - chrono::Duration::hours(hours);
+    let jobs = db.list_unretried_failed_jobs(&cutoff.format("%Y-%m-%dT%H:%M:%S").to_string())?;
+
+    let mut report = RetryReport::default();
+    for job in jobs {
+        // The same prompt would be refused again
+        if job.failure.as_deref() == Some("content_policy") {
+            report.blocked.push(job.id);
+            continue;
+        }
+        let Some(req) = db.get_job_request(job.id)? else {
+            report.skipped.push(job.id);
+            continue;
+        };
+        let estimate = price_per_image(db, &req.model)?.unwrap_or(0.0) * req.num_images.unwrap_or(1) as f64;
+        if report.cost + estimate > max_cost {
+            report.over_budget.push(job.id);
+            continue;
+        }
+
+        let tags = job.tags.clone().unwrap_or_default();
+        match run_generation(db, &req, &tags, source, Some(job.id), progress).await {
+            Ok(generations) => {
+                report.cost += generations
+                    .iter()
+                    .map(|saved| saved.generation.cost_estimate_usd.unwrap_or(0.0))
+                    .sum::<f64>();
+                if let Some(saved) = generations.first() {
+                    report.retried.push((job.id, saved.id));
+                }
+            }
+            Err(e) if cancel::is_cancelled(&e) => return Err(e),
+            Err(e) => report.failed.push((job.id, e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+/// Region fix workflow: detect regions in the source, re-detail them, and save the
+/// composite as a child generation. `req` carries the model, prompt and parent_id.
+pub async fn perform_fix(
+    db: &Database,
+    source: &Generation,
+    req: &GenerationRequest,
+    opts: &FixOptions,
+    tags: &[String],
+    source_kind: JobSource,
+    progress: providers::Progress<'_>,
+) -> Result<Generated> {
+    let mut req = req.clone();
+    apply_auto_negative(&mut req);
+    let req = &req;
+
+    let (job_id, estimated_cost, provider) =
+        prepare_generation(db, &req.model, &req.prompt, tags, source_kind, 0, None)?;
+    progress(ProgressStage::Queued);
+
+    let call = detail::fix_regions(Path::new(&source.image_path), req, opts, estimated_cost, progress);
+    let result = match cancel::run(job_id, call).await {
+        Ok(r) => r,
+        Err(e) => {
+            record_job_error(db, job_id, &e)?;
+            return Err(e);
+        }
+    };
+
+    let mut generations = complete_generation(db, job_id, req, &provider, tags, &result, estimated_cost, progress)?;
+    classify_nsfw(db, &mut generations).await?;
+    Ok(generations.remove(0))
+}
+
+/// Check a video request against what its model accepts
+fn validate_video_request(info: &VideoModelInfo, req: &VideoRequest) -> Result<()> {
+    if let Some(duration) = req.duration.filter(|d| !info.durations.contains(d)) {
+        let allowed: Vec<String> = info.durations.iter().map(|d| format!("{}s", d)).collect();
+        anyhow::bail!("{} makes {} clips, not {}s", info.id, allowed.join(" or "), duration);
+    }
+    if let Some(ratio) = req.aspect_ratio.as_deref().filter(|r| !info.aspect_ratios.contains(r)) {
+        anyhow::bail!("{} supports aspect ratios {}, not {}", info.id, info.aspect_ratios.join(", "), ratio);
+    }
+    if let Some(image_path) = &req.image_path {
+        if !info.image_to_video {
+            anyhow::bail!("{} doesn't take a start frame", info.id);
+        }
+        if !Path::new(image_path).exists() {
+            return Err(PixeryError::FileMissing(format!("Start frame not found: {}", image_path)).into());
+        }
+    }
+    Ok(())
+}
+
+/// Video workflow (`pixery video`): a job like any generation, then the clip
+/// archived as a video generation. Length and frame rate come from ffprobe
+/// when it's installed, else the length is the one requested; thumbnails are
+/// of the first frame. The start frame is kept as a reference.
+pub async fn perform_video(
+    db: &Database,
+    req: &VideoRequest,
+    tags: &[String],
+    source: JobSource,
+    progress: providers::Progress<'_>,
+) -> Result<(i64, Generation)> {
+    let info = VideoModelInfo::find(&req.model)
+        .ok_or_else(|| anyhow::anyhow!("Unknown video model: {}", req.model))?;
+    validate_video_request(&info, req)?;
+
+    // Jobs only store GenerationRequests, so video jobs can't be retried
+    let tags_opt = if tags.is_empty() { None } else { Some(tags) };
+    let ref_count = req.image_path.is_some() as i32;
+    check_quota(db, info.id)?;
+    let job_id = db.create_job(info.id, &req.prompt, tags_opt, source, ref_count, None)?;
+    db.update_job_started(job_id)?;
+    progress(ProgressStage::Queued);
+
+    let result = match cancel::run(job_id, providers::generate_video(req, progress)).await {
+        Ok(r) => r,
+        Err(e) => {
+            record_job_error(db, job_id, &e)?;
+            return Err(e);
+        }
+    };
 
-    let cost = result.cost_usd.or(estimated_cost);
+    progress(ProgressStage::Saving);
+    let now = chrono::Local::now();
+    let date = now.format("%Y-%m-%d").to_string();
+    let timestamp = now.format("%Y-%m-%dT%H:%M:%S").to_string();
+    let slug = archive::slugify_prompt(&req.prompt);
+    let (video_path, thumb_path, file_size) = archive::save_video(&result.video, &date, &slug, &timestamp)?;
+    let probed = video::probe(&video_path).unwrap_or_else(|e| {
+        eprintln!("No video metadata: {}", e);
+        video::VideoInfo::default()
+    });
+    let duration = probed
+        .duration_seconds
+        .or(Some(req.duration.unwrap_or(info.durations[0]) as f64));
+    // Video providers report a per-second estimate too; a pricing table rate replaces it
+    let cost = match current_price(db, info.id)? {
+        Some(rate) => Some(rate * req.duration.unwrap_or(info.durations[0]) as f64),
+        None => result.cost_usd.or(Some(info.estimate_cost(req.duration))),
+    };
+
+    let mut resolved = req.clone();
+    let mut ref_id = None;
+    if let Some(image_path) = &req.image_path {
+        let (hash, stored_path) = archive::store_reference(Path::new(image_path))?;
+        ref_id = Some(db.get_or_create_reference(&hash, stored_path.to_str().unwrap())?);
+        resolved.image_path = Some(stored_path.to_string_lossy().into_owned());
+    }
+    resolved.seed = result.seed.as_deref().and_then(|s| s.parse().ok()).or(req.seed);
 
     let gen_id = db.insert_generation(
         &slug,
-        prompt,
-        model,
-        provider,
+        &req.prompt,
+        info.id,
+        &info.provider.to_string(),
         &timestamp,
         &date,
-        image_path.to_str().unwrap(),
-        thumb_path.as_ref().and_then(|p| p.to_str()),
+        video_path.to_str().unwrap(),
+        thumb_path.to_str(),
         Some(result.generation_time_seconds),
         cost,
         result.seed.as_deref(),
-        Some(width),
-        Some(height),
+        probed.width,
+        probed.height,
         Some(file_size),
-        None, // parent_id
-        negative_prompt,
+        req.parent_id,
+        req.negative_prompt.as_deref(),
     )?;
+    db.set_content_hash(gen_id, &archive::hash_bytes(&result.video)?)?;
+    db.set_video_metadata(gen_id, duration, probed.fps)?;
+    db.set_generation_params(gen_id, &serde_json::to_value(&resolved)?)?;
+    palette::record_new(db, gen_id, &video_path);
+    archive::queue_thumbnails(video_path);
+    progress(ProgressStage::ThumbnailDone);
 
     if !tags.is_empty() {
         db.add_tags(gen_id, tags)?;
     }
-
-    for ref_path in reference_paths {
-        let (hash, stored_path) = archive::store_reference(Path::new(ref_path))?;
-        let ref_id = db.get_or_create_reference(&hash, stored_path.to_str().unwrap())?;
+    flag_nsfw_prompt(db, gen_id, &req.prompt)?;
+    if let Some(ref_id) = ref_id {
         db.link_reference(gen_id, ref_id)?;
     }
-
     db.update_job_completed(job_id, gen_id)?;
 
     let generation = db
         .get_generation(gen_id)?
         .ok_or_else(|| anyhow::anyhow!("Failed to retrieve generation after insert"))?;
-
+    let generations = [Generated { id: gen_id, generation, duplicate_of: None }];
+    if let Some(event) = notify::WebhookEvent::completed(job_id, &generations) {
+        notify::post_webhook(event);
+    }
+    let [Generated { generation, .. }] = generations;
     Ok((gen_id, generation))
 }
 
-/// Full generation workflow (CLI convenience -- no Send requirement).
-pub async fn perform_generation(
-    db: &Database,
-    prompt: &str,
-    model: &str,
-    tags: &[String],
-    reference_paths: &[String],
-    source: JobSource,
-    negative_prompt: Option<&str>,
-    width: Option<i32>,
-    height: Option<i32>,
-    ip_scale: Option<f64>,
-) -> Result<(i64, Generation)> {
-    let (job_id, estimated_cost, provider) =
-        prepare_generation(db, model, prompt, tags, source, reference_paths.len())?;
+/// Content hash of `data` and the archived generation with identical content,
+/// if any. Generations archived before hashes were stored are hashed first.
+pub fn find_duplicate(db: &Database, data: &[u8]) -> Result<(String, Option<i64>)> {
+    for (id, image_path) in db.unhashed_generations()? {
+        // A generation whose file is gone can't be matched; it stays unhashed
+        if let Ok(existing) = std::fs::read(&image_path) {
+            db.set_content_hash(id, &archive::hash_bytes(&existing)?)?;
+        }
+    }
+    let hash = archive::hash_bytes(data)?;
+    let existing = db.find_by_content_hash(&hash)?;
+    Ok((hash, existing))
+}
 
-    let result = match providers::generate(model, prompt, reference_paths, negative_prompt, width, height, ip_scale).await {
-        Ok(r) => r,
-        Err(e) => {
-            db.update_job_failed(job_id, &e.to_string())?;
-            return Err(e);
+/// Give an archived generation the tags and references of an identical
+/// incoming image (`DuplicateAction::Link`)
+fn link_duplicate(db: &Database, id: i64, tags: &[String], ref_ids: &[i64]) -> Result<()> {
+    if !tags.is_empty() {
+        db.add_tags(id, tags)?;
+    }
+    for &ref_id in ref_ids {
+        db.link_reference(id, ref_id)?;
+    }
+    Ok(())
+}
+
+/// Result of `import_image`
+#[derive(Debug)]
+pub struct Imported {
+    pub id: i64,
+    pub generation: Generation,
+    /// The file was already archived as this generation, so nothing new was saved
+    pub duplicate: bool,
+}
+
+/// What `import_image` and `import_directory` record on imported files
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// None uses the file's name (without extension)
+    pub prompt: Option<String>,
+    pub model: String,
+    pub tags: Vec<String>,
+    /// Reference image paths or IDs, as for `pixery gen --ref`
+    pub reference_paths: Vec<String>,
+    /// YYYY-MM-DD, over the file's own capture date
+    pub date: Option<String>,
+    /// HHMMSS or HH:MM:SS, over the file's own capture time
+    pub time: Option<String>,
+    pub on_duplicate: DuplicateAction,
+}
+
+/// Import an existing image file into the archive (CLI `import` and watch folders).
+/// Date/time come from the options, else EXIF/XMP, else a `-YYYYMMDD-HHMMSS` filename
+/// suffix, else now. A file identical to an archived image is handled per `on_duplicate`.
+pub fn import_image(db: &Database, source_path: &Path, options: &ImportOptions) -> Result<Imported> {
+    // Read the source image
+    let data = std::fs::read(source_path).context("Failed to read source image")?;
+
+    // Store references up front; a linked duplicate gets them too
+    let mut ref_ids = vec![];
+    for ref_path in &resolve_references(db, &options.reference_paths)? {
+        let (hash, stored_path) = archive::store_reference(Path::new(ref_path))?;
+        ref_ids.push(db.get_or_create_reference(&hash, stored_path.to_str().unwrap())?);
+    }
+
+    let (content_hash, existing) = find_duplicate(db, &data)?;
+    if let Some(id) = existing.filter(|_| options.on_duplicate != DuplicateAction::Import) {
+        if options.on_duplicate == DuplicateAction::Link {
+            link_duplicate(db, id, &options.tags, &ref_ids)?;
         }
+        let generation = db
+            .get_generation(id)?
+            .ok_or_else(|| anyhow::anyhow!("Generation {} not found", id))?;
+        return Ok(Imported { id, generation, duplicate: true });
+    }
+
+    // Camera/software metadata, whose capture time beats the filename's
+    let embedded = exif::read(&data);
+
+    // Otherwise try the filename pattern: name-YYYYMMDD-HHMMSS.ext
+    let filename = source_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("");
+
+    let (extracted_date, extracted_time) = match &embedded.created {
+        Some(created) => (Some(created[..10].to_string()), Some(created[11..].replace(':', ""))),
+        None => extract_datetime_from_filename(filename),
     };
 
-    complete_generation(
-        db,
-        job_id,
+    // Use override > extracted > current time
+    let now = chrono::Local::now();
+    let date = options
+        .date
+        .clone()
+        .or(extracted_date)
+        .unwrap_or_else(|| now.format("%Y-%m-%d").to_string());
+
+    let time_str = options
+        .time
+        .as_ref()
+        .map(|s| s.replace(':', ""))
+        .or(extracted_time)
+        .unwrap_or_else(|| now.format("%H%M%S").to_string());
+
+    // Pad to 6 chars to prevent slice panics on short input
+    let time_str = format!("{:0<6}", time_str);
+
+    // Build full timestamp
+    let timestamp = format!(
+        "{}T{}:{}:{}",
+        date,
+        &time_str[0..2],
+        &time_str[2..4],
+        &time_str[4..6]
+    );
+
+    // Get model info for provider
+    let model_info = ModelInfo::find(&options.model);
+    let provider = model_info
+        .as_ref()
+        .map(|m| m.provider.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let prompt = options
+        .prompt
+        .as_deref()
+        .unwrap_or_else(|| source_path.file_stem().and_then(|s| s.to_str()).unwrap_or("imported"));
+
+    // Save to archive (copies the file)
+    let slug = archive::slugify_prompt(prompt);
+    let (image_path, thumb_path, width, height, file_size) =
+        archive::save_image(&data, &date, &slug, &timestamp)?;
+
+    // Insert into database
+    let gen_id = db.insert_generation(
+        &slug,
         prompt,
-        model,
+        &options.model,
         &provider,
-        tags,
-        reference_paths,
-        &result,
-        estimated_cost,
-        negative_prompt,
-    )
+        &timestamp,
+        &date,
+        image_path.to_str().unwrap(),
+        thumb_path.as_ref().and_then(|p| p.to_str()),
+        None, // generation_time_seconds - unknown for imports
+        None, // cost - unknown for imports
+        None, // seed
+        Some(width),
+        Some(height),
+        Some(file_size),
+        None, // parent_id
+        None, // negative_prompt
+    )?;
+    db.set_content_hash(gen_id, &content_hash)?;
+    if !embedded.is_empty() {
+        db.set_generation_params(gen_id, &serde_json::to_value(&embedded)?)?;
+    }
+    if archive::is_animated(&data) {
+        db.set_animated(gen_id)?;
+    }
+    palette::record_new(db, gen_id, &image_path);
+    archive::queue_thumbnails(image_path.clone());
+
+    // Add tags
+    if !options.tags.is_empty() {
+        db.add_tags(gen_id, &options.tags)?;
+    }
+    flag_nsfw_prompt(db, gen_id, prompt)?;
+
+    // Link reference images
+    for &ref_id in &ref_ids {
+        db.link_reference(gen_id, ref_id)?;
+    }
+
+    let generation = db
+        .get_generation(gen_id)?
+        .ok_or_else(|| anyhow::anyhow!("Failed to retrieve generation after import"))?;
+
+    Ok(Imported { id: gen_id, generation, duplicate: false })
+}
+
+/// Outcome of `import_directory`
+#[derive(Debug, Default)]
+pub struct DirImportReport {
+    pub imported: usize,
+    /// Same content as an archived image, or as one earlier in this import;
+    /// skipped or linked rather than imported
+    pub duplicates: usize,
+    /// Not an image, by extension or content
+    pub skipped: usize,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// Import every image in `dir` (and below it with `recursive`), oldest path
+/// first, each with `options`. Files whose content is already archived are
+/// handled per `on_duplicate`; each file gets its date/time from its metadata
+/// or name like a single import, so `options` shouldn't set them. `progress`
+/// is called with (files done, total files).
+pub fn import_directory(
+    db: &Database,
+    dir: &Path,
+    recursive: bool,
+    options: &ImportOptions,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<DirImportReport> {
+    let mut files = vec![];
+    collect_files(dir, recursive, &mut files)?;
+    files.sort();
+
+    let mut report = DirImportReport::default();
+    for (done, path) in files.iter().enumerate() {
+        progress(done, files.len());
+        if !archive::is_image(path) {
+            report.skipped += 1;
+            continue;
+        }
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) => {
+                report.failed.push((path.clone(), e.to_string()));
+                continue;
+            }
+        };
+        if image::guess_format(&data).is_err() {
+            report.skipped += 1;
+            continue;
+        }
+
+        match import_image(db, path, options) {
+            Ok(imported) if imported.duplicate => report.duplicates += 1,
+            Ok(_) => report.imported += 1,
+            Err(e) => report.failed.push((path.clone(), format!("{:#}", e))),
+        }
+    }
+    progress(files.len(), files.len());
+    Ok(report)
+}
+
+/// Regular files in `dir`, descending into subdirectories (but not through
+/// symlinks, which could loop) when `recursive`
+fn collect_files(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).with_context(|| format!("Failed to read directory {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if recursive {
+                collect_files(&entry.path(), recursive, files)?;
+            }
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+/// Extract date and time from filename patterns like:
+/// - name-YYYYMMDD-HHMMSS.ext
+/// - name-v1-YYYYMMDD-HHMMSS.ext
+fn extract_datetime_from_filename(filename: &str) -> (Option<String>, Option<String>) {
+    use std::sync::OnceLock;
+    static DATE_RE: OnceLock<regex::Regex> = OnceLock::new();
+    let re = DATE_RE.get_or_init(|| regex::Regex::new(r"(\d{4})(\d{2})(\d{2})-(\d{6})").unwrap());
+
+    if let Some(caps) = re.captures(filename) {
+        let date = format!("{}-{}-{}", &caps[1], &caps[2], &caps[3]);
+        let time = caps[4].to_string();
+        return (Some(date), Some(time));
+    }
+
+    (None, None)
 }
@@ -0,0 +1,141 @@
+use crate::models::{self, Generation};
+
+/// Namespace for the fields photo tools have no standard property for
+pub const PIXERY_NS: &str = "https://github.com/rvdemonk/pixery/ns/1.0/";
+
+/// Escape text for XML element content and attribute values
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Undo `escape_xml`, plus numeric character references
+fn unescape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find(';').map(|e| start + e) else {
+            break;
+        };
+        let entity = &rest[start + 1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => out.push(c),
+            None => out.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// A simple property ("xmp:CreatorTool") of an XMP packet, written either as
+/// an attribute of rdf:Description or as an element. Good enough for the
+/// flat properties imports read; not a general RDF parser.
+pub fn property(packet: &str, name: &str) -> Option<String> {
+    let name = regex::escape(name);
+    let attribute = regex::Regex::new(&format!(r#"\s{}\s*=\s*(?:"([^"]*)"|'([^']*)')"#, name)).ok()?;
+    let element = regex::Regex::new(&format!(r"<{0}(?:\s[^>]*)?>([^<]*)</{0}>", name)).ok()?;
+    let value = match attribute.captures(packet) {
+        Some(caps) => caps.get(1).or_else(|| caps.get(2))?.as_str(),
+        None => element.captures(packet)?.get(1)?.as_str(),
+    };
+    let value = unescape_xml(value.trim());
+    (!value.is_empty()).then_some(value)
+}
+
+/// A language-alternative property (dc:title, dc:description)
+fn alt(name: &str, value: &str) -> String {
+    format!(
+        "   <{0}>\n    <rdf:Alt>\n     <rdf:li xml:lang=\"x-default\">{1}</rdf:li>\n    </rdf:Alt>\n   </{0}>\n",
+        name,
+        escape_xml(value)
+    )
+}
+
+/// An unordered list property (dc:subject, lr:hierarchicalSubject)
+fn bag(name: &str, values: &[String]) -> String {
+    let items: String = values
+        .iter()
+        .map(|v| format!("     <rdf:li>{}</rdf:li>\n", escape_xml(v)))
+        .collect();
+    format!("   <{0}>\n    <rdf:Bag>\n{1}    </rdf:Bag>\n   </{0}>\n", name, items)
+}
+
+/// Render an XMP sidecar for a generation: tags as dc:subject (namespaced
+/// tags also as Lightroom keyword hierarchies, "character|kira"), the prompt
+/// as dc:description, the title as dc:title, starred as a 5-star rating, and
+/// everything else under the pixery namespace.
+pub fn render(gen: &Generation) -> String {
+    let mut attrs = vec![
+        ("xmp:CreatorTool", "pixery".to_string()),
+        ("xmp:CreateDate", gen.timestamp.clone()),
+        ("pixery:id", gen.id.to_string()),
+        ("pixery:model", gen.model.clone()),
+        ("pixery:provider", gen.provider.clone()),
+    ];
+    if gen.starred {
+        attrs.push(("xmp:Rating", "5".to_string()));
+    }
+    if let Some(seed) = &gen.seed {
+        attrs.push(("pixery:seed", seed.clone()));
+    }
+    if let Some(cost) = gen.cost_estimate_usd {
+        attrs.push(("pixery:costUsd", format!("{:.4}", cost)));
+    }
+    if let Some(parent_id) = gen.parent_id {
+        attrs.push(("pixery:parentId", parent_id.to_string()));
+    }
+
+    let mut xml = String::from("<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n");
+    xml.push_str("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n");
+    xml.push_str(" <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n");
+    xml.push_str("  <rdf:Description rdf:about=\"\"\n");
+    xml.push_str("    xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n");
+    xml.push_str("    xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\"\n");
+    xml.push_str("    xmlns:lr=\"http://ns.adobe.com/lightroom/1.0/\"\n");
+    xml.push_str(&format!("    xmlns:pixery=\"{}\"", PIXERY_NS));
+    for (name, value) in &attrs {
+        xml.push_str(&format!("\n    {}=\"{}\"", name, escape_xml(value)));
+    }
+    xml.push_str(">\n");
+
+    if let Some(title) = &gen.title {
+        xml.push_str(&alt("dc:title", title));
+    }
+    xml.push_str(&alt("dc:description", &gen.prompt));
+    if !gen.tags.is_empty() {
+        xml.push_str(&bag("dc:subject", &gen.tags));
+        let hierarchical: Vec<String> = gen
+            .tags
+            .iter()
+            .filter(|t| models::tag_namespace(t).is_some())
+            .map(|t| t.replacen(':', "|", 1))
+            .collect();
+        if !hierarchical.is_empty() {
+            xml.push_str(&bag("lr:hierarchicalSubject", &hierarchical));
+        }
+    }
+    if let Some(negative) = &gen.negative_prompt {
+        xml.push_str(&format!("   <pixery:negativePrompt>{}</pixery:negativePrompt>\n", escape_xml(negative)));
+    }
+
+    xml.push_str("  </rdf:Description>\n </rdf:RDF>\n</x:xmpmeta>\n<?xpacket end=\"w\"?>\n");
+    xml
+}
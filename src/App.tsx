@@ -1,7 +1,8 @@
 import { useState, useEffect, useCallback, useMemo } from 'react';
 import { listen } from '@tauri-apps/api/event';
-import type { Generation, ModelInfo, ListFilter, SelfHostedStatus, Collection, TodayCost } from './lib/types';
+import type { Generation, ModelInfo, ListFilter, SelfHostedStatus, Collection, SmartCollection, SortKey, TodayCost, DisplayCurrency } from './lib/types';
 import * as api from './lib/api';
+import { setDisplayCurrency } from './lib/currency';
 import { useGenerations } from './hooks/useGenerations';
 import { useTags } from './hooks/useTags';
 import { useKeyboard } from './hooks/useKeyboard';
@@ -27,18 +28,34 @@ import type { Reference } from './lib/types';
 
 type View = 'gallery' | 'compare' | 'dashboard';
 
+// Gallery sort dropdown: option value -> ListFilter sort fields
+const SORT_OPTIONS: Record<string, { label: string; sort?: SortKey; sort_desc?: boolean }> = {
+  newest: { label: 'Newest' },
+  oldest: { label: 'Oldest', sort: 'timestamp' },
+  'cost-desc': { label: 'Cost ↓', sort: 'cost', sort_desc: true },
+  cost: { label: 'Cost ↑', sort: 'cost' },
+  'time-desc': { label: 'Slowest', sort: 'generation_time', sort_desc: true },
+  'size-desc': { label: 'Largest file', sort: 'file_size', sort_desc: true },
+  starred: { label: 'Starred first', sort: 'starred', sort_desc: true },
+  random: { label: 'Random', sort: 'random' },
+  position: { label: 'Collection order', sort: 'position' },
+};
+
 export default function App() {
   // Filter state
   const [filter, setFilter] = useState<ListFilter>({ limit: 100, starred_only: false, show_trashed: false, uncategorized: false });
   const [filterTags, setFilterTags] = useState<string[]>([]);
   const [filterModel, setFilterModel] = useState<string | null>(null);
   const [starredOnly, setStarredOnly] = useState(false);
+  const [sortOption, setSortOption] = useState('newest');
 
   // Sidebar navigation state
   const [activeCollection, setActiveCollection] = useState<number | null>(null);
   const [showTrashed, setShowTrashed] = useState(false);
   const [showUncategorized, setShowUncategorized] = useState(false);
   const [collections, setCollections] = useState<Collection[]>([]);
+  const [activeSmartCollection, setActiveSmartCollection] = useState<number | null>(null);
+  const [smartCollections, setSmartCollections] = useState<SmartCollection[]>([]);
 
   // Selection state
   const [selectedId, setSelectedId] = useState<number | null>(null);
@@ -72,6 +89,12 @@ export default function App() {
 
   // Today's cost
   const [todayCost, setTodayCost] = useState<TodayCost>({ total: 0, byModel: [] });
+  // formatCost reads the module-level currency; this state re-renders when it changes
+  const [, setCurrency] = useState<DisplayCurrency | null>(null);
+  // Guest mode (`pixery --read-only`): browsing only, no generate or edit shortcuts
+  const [readOnly, setReadOnly] = useState(false);
+  // Opened from a pixery:// link; kept in case it's outside the loaded page
+  const [focusedGeneration, setFocusedGeneration] = useState<Generation | null>(null);
 
   // Combined model list: self-hosted first (if connected), then cloud
   const models = useMemo(() => {
@@ -100,7 +123,7 @@ export default function App() {
   const { hiddenTags, toggleHiddenTag, thumbnailSize, setThumbnailSize } = useSettings();
   const { tags: allTags, addTags, removeTag, refresh: refreshTags } = useTags();
   const { generating, progress: generateProgress, error: generateError, generate } = useGenerate();
-  const { jobs, activeCount, failedJobs, failedCount, dismissFailedJob } = useJobs();
+  const { jobs, activeCount, failedJobs, failedCount, progress: jobProgress, dismissFailedJob, cancelJob, retryFailedJobs } = useJobs();
 
   // Build filter with exclude_tags for server-side hidden tag filtering
   const generationsFilter = useMemo(() => ({
@@ -116,22 +139,32 @@ export default function App() {
     [allTags, hiddenTags]
   );
 
+  const applyCurrency = useCallback((currency: DisplayCurrency) => {
+    setDisplayCurrency(currency);
+    setCurrency(currency);
+  }, []);
+
   const refreshTodayCost = useCallback(() => {
     api.getCostSummary('today').then((s) => {
+      applyCurrency(s.currency);
       setTodayCost({ total: s.total_usd, byModel: s.by_model });
     }).catch(() => {});
-  }, []);
+  }, [applyCurrency]);
 
   // Load models, collections, and today's cost on mount
   useEffect(() => {
     api.listModels().then(setCloudModels);
     api.checkSelfhostedHealth().then(setSelfHostedStatus);
     api.listCollections().then(setCollections).catch(() => {});
+    api.listSmartCollections().then(setSmartCollections).catch(() => {});
+    api.getReadOnly().then(setReadOnly).catch(() => {});
     refreshTodayCost();
   }, [refreshTodayCost]);
 
   const refreshCollections = useCallback(() => {
     api.listCollections().then(setCollections).catch(() => {});
+    // Smart collection counts move with every new, tagged or starred image
+    api.listSmartCollections().then(setSmartCollections).catch(() => {});
   }, []);
 
   // Refresh self-hosted status (called when settings change)
@@ -160,10 +193,13 @@ export default function App() {
       model: filterModel || undefined,
       starred_only: starredOnly,
       collection_id: activeCollection || undefined,
+      smart_collection_id: activeSmartCollection || undefined,
       show_trashed: showTrashed,
       uncategorized: showUncategorized,
+      sort: SORT_OPTIONS[sortOption].sort,
+      sort_desc: SORT_OPTIONS[sortOption].sort_desc,
     }));
-  }, [filterTags, filterModel, starredOnly, activeCollection, showTrashed, showUncategorized]);
+  }, [filterTags, filterModel, starredOnly, activeCollection, activeSmartCollection, showTrashed, showUncategorized, sortOption]);
 
   // Sidebar navigation handlers (mutually exclusive)
   const handleShowAll = useCallback(() => {
@@ -171,14 +207,38 @@ export default function App() {
     setShowTrashed(false);
     setShowUncategorized(false);
     setActiveCollection(null);
+    setActiveSmartCollection(null);
     setFilterTags([]);
   }, []);
 
+  // pixery://generation/ID links (`pixery open`, `pixery quick`): show all, select it, open details
+  useEffect(() => {
+    const focusLinked = () => {
+      api.takeFocusGeneration().then(async (id) => {
+        if (id === null) return;
+        const generation = await api.getGeneration(id);
+        if (!generation) return;
+        handleShowAll();
+        setView('gallery');
+        setFocusedGeneration(generation);
+        setMarkedIds(new Set());
+        setSelectedId(id);
+        setDetailsOpen(true);
+      }).catch(() => {});
+    };
+    focusLinked();
+    const unlisten = listen('focus-generation', focusLinked);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [handleShowAll]);
+
   const handleShowStarred = useCallback(() => {
     setStarredOnly(true);
     setShowTrashed(false);
     setShowUncategorized(false);
     setActiveCollection(null);
+    setActiveSmartCollection(null);
   }, []);
 
   const handleShowTrashed = useCallback(() => {
@@ -186,6 +246,7 @@ export default function App() {
     setStarredOnly(false);
     setShowUncategorized(false);
     setActiveCollection(null);
+    setActiveSmartCollection(null);
   }, []);
 
   const handleShowUncategorized = useCallback(() => {
@@ -193,15 +254,38 @@ export default function App() {
     setStarredOnly(false);
     setShowTrashed(false);
     setActiveCollection(null);
+    setActiveSmartCollection(null);
   }, []);
 
   const handleSelectCollection = useCallback((id: number) => {
     setActiveCollection(id);
+    setActiveSmartCollection(null);
+    setStarredOnly(false);
+    setShowTrashed(false);
+    setShowUncategorized(false);
+  }, []);
+
+  const handleSelectSmartCollection = useCallback((id: number) => {
+    setActiveSmartCollection(id);
+    setActiveCollection(null);
     setStarredOnly(false);
     setShowTrashed(false);
     setShowUncategorized(false);
   }, []);
 
+  const handleCreateSmartCollection = useCallback(async (name: string, filter: string) => {
+    await api.createSmartCollection(name, filter);
+    refreshCollections();
+  }, [refreshCollections]);
+
+  const handleDeleteSmartCollection = useCallback(async (name: string) => {
+    await api.deleteSmartCollection(name);
+    if (smartCollections.find((c) => c.name === name)?.id === activeSmartCollection) {
+      setActiveSmartCollection(null);
+    }
+    refreshCollections();
+  }, [smartCollections, activeSmartCollection, refreshCollections]);
+
   const handleCreateCollection = useCallback(async (name: string) => {
     await api.createCollection(name);
     refreshCollections();
@@ -222,8 +306,9 @@ export default function App() {
 
   // Selected generation
   const selectedGeneration = useMemo(
-    () => generations.find((g) => g.id === selectedId) || null,
-    [generations, selectedId]
+    () => generations.find((g) => g.id === selectedId)
+      || (focusedGeneration?.id === selectedId ? focusedGeneration : null),
+    [generations, selectedId, focusedGeneration]
   );
 
   // Compare generations
@@ -269,6 +354,24 @@ export default function App() {
     refresh();
   }, [selectedId, refresh]);
 
+  const handleToggleLock = useCallback(async () => {
+    if (!selectedGeneration) return;
+    await api.setLocked(selectedGeneration.id, !selectedGeneration.locked);
+    refresh();
+  }, [selectedGeneration, refresh]);
+
+  const handleToggleNsfw = useCallback(async () => {
+    if (!selectedGeneration) return;
+    await api.setNsfw(selectedGeneration.id, !selectedGeneration.nsfw);
+    refresh();
+  }, [selectedGeneration, refresh]);
+
+  const handleCaption = useCallback(async () => {
+    if (!selectedId) return;
+    await api.captionGeneration(selectedId);
+    refresh();
+  }, [selectedId, refresh]);
+
   const handleUpdateTitle = useCallback(async (title: string | null) => {
     if (!selectedId) return;
     await api.updateTitle(selectedId, title);
@@ -384,7 +487,7 @@ export default function App() {
     refresh();
   }, [selectedId, refresh]);
 
-  const handleGenerate = useCallback(async (prompt: string, model: string, genTags: string[], referencePaths: string[], negativePrompt: string | null = null, numRuns: number = 1) => {
+  const handleGenerate = useCallback(async (prompt: string, model: string, genTags: string[], referencePaths: string[], negativePrompt: string | null = null, numRuns: number = 1, preset: string | null = null, transparent: boolean = false) => {
     setGenerateOpen(false);
     const results = await generate({
       prompt,
@@ -395,6 +498,8 @@ export default function App() {
       negative_prompt: negativePrompt,
       width: null,
       height: null,
+      preset,
+      transparent,
     }, numRuns);
     if (results.length > 0) {
       refresh();
@@ -526,7 +631,7 @@ export default function App() {
   useKeyboard({
     onNext: selectNext,
     onPrevious: selectPrevious,
-    onToggleStar: handleToggleStar,
+    onToggleStar: readOnly ? undefined : handleToggleStar,
     onOpenDetails: () => selectedId && setDetailsOpen(true),
     onCompare: () => {
       if (markedIds.size === 2) {
@@ -535,8 +640,8 @@ export default function App() {
         setView('compare');
       }
     },
-    onRegenerate: handleOpenRemix,
-    onFocusGenerate: () => {
+    onRegenerate: readOnly ? undefined : handleOpenRemix,
+    onFocusGenerate: readOnly ? undefined : () => {
       setGenerateInitialState(undefined);
       setGenerateOpen(true);
     },
@@ -569,14 +674,14 @@ export default function App() {
         setSelectedId(null);
       }
     },
-    onDelete: handleTrash,
+    onDelete: readOnly ? undefined : handleTrash,
     // Batch selection handlers
     onMark: handleMark,
     onClearSelection: handleClearSelection,
-    onBatchTag: () => setBatchTagOpen(true),
-    onBatchRefs: handleUseAsRefs,
-    onBatchRegen: handleBatchRegen,
-    onBatchDelete: handleBatchDelete,
+    onBatchTag: readOnly ? undefined : () => setBatchTagOpen(true),
+    onBatchRefs: readOnly ? undefined : handleUseAsRefs,
+    onBatchRegen: readOnly ? undefined : handleBatchRegen,
+    onBatchDelete: readOnly ? undefined : handleBatchDelete,
     hasSelection: markedIds.size > 0,
   }, (view === 'gallery' || showHelp) && !lightboxOpen);
 
@@ -585,6 +690,8 @@ export default function App() {
       <Sidebar
         collections={collections}
         activeCollection={activeCollection}
+        smartCollections={smartCollections}
+        activeSmartCollection={activeSmartCollection}
         starredOnly={starredOnly}
         showTrashed={showTrashed}
         showUncategorized={showUncategorized}
@@ -594,6 +701,9 @@ export default function App() {
         onShowUncategorized={handleShowUncategorized}
         onSelectCollection={handleSelectCollection}
         onCreateCollection={handleCreateCollection}
+        onSelectSmartCollection={handleSelectSmartCollection}
+        onCreateSmartCollection={handleCreateSmartCollection}
+        onDeleteSmartCollection={handleDeleteSmartCollection}
         onOpenDashboard={() => setView('dashboard')}
         onOpenSettings={() => setSettingsOpen(true)}
         pinned={sidebarPinned}
@@ -613,6 +723,16 @@ export default function App() {
             filterModel={filterModel}
             onSetModel={setFilterModel}
           />
+          <select
+            className="size-select"
+            value={sortOption}
+            onChange={(e) => setSortOption(e.target.value)}
+            title="Sort order"
+          >
+            {Object.entries(SORT_OPTIONS).map(([value, { label }]) => (
+              <option key={value} value={value}>{label}</option>
+            ))}
+          </select>
           <select
             className="size-select"
             value={thumbnailSize}
@@ -635,17 +755,26 @@ export default function App() {
             activeCount={activeCount}
             failedJobs={failedJobs}
             failedCount={failedCount}
+            progress={jobProgress}
             onDismissFailedJob={dismissFailedJob}
+            onCancelJob={cancelJob}
+            onRetryFailed={retryFailedJobs}
           />
-          <button
-            className="btn btn-primary"
-            onClick={() => {
-              setGenerateInitialState(undefined);
-              setGenerateOpen(true);
-            }}
-          >
-            Generate
-          </button>
+          {readOnly ? (
+            <span className="read-only-badge" title="Opened read-only: browsing works, changes are disabled">
+              Read-only
+            </span>
+          ) : (
+            <button
+              className="btn btn-primary"
+              onClick={() => {
+                setGenerateInitialState(undefined);
+                setGenerateOpen(true);
+              }}
+            >
+              Generate
+            </button>
+          )}
         </header>
 
         <Gallery
@@ -675,7 +804,10 @@ export default function App() {
           collections={collections}
           onClose={() => setDetailsOpen(false)}
           onToggleStar={handleToggleStar}
+          onToggleLock={handleToggleLock}
+          onToggleNsfw={handleToggleNsfw}
           onUpdateTitle={handleUpdateTitle}
+          onCaption={handleCaption}
           onAddTag={handleAddTag}
           onRemoveTag={handleRemoveTag}
           onAddToCollection={async (collectionName) => {
@@ -690,6 +822,16 @@ export default function App() {
             refresh();
             refreshCollections();
           }}
+          onSetCover={async (collectionName) => {
+            if (!selectedId) return;
+            await api.setCollectionCover(collectionName, selectedId);
+            refreshCollections();
+          }}
+          onMoveToFront={async (collectionName) => {
+            if (!selectedId) return;
+            await api.reorderCollection(collectionName, [selectedId]);
+            refresh();
+          }}
           onFilterByTag={addFilterTag}
           onRemix={handleOpenRemix}
           onReference={handleOpenReference}
@@ -720,6 +862,9 @@ export default function App() {
           onToggleHiddenTag={toggleHiddenTag}
           onClose={() => setSettingsOpen(false)}
           onSelfHostedChange={refreshSelfHostedStatus}
+          onModelsChange={() => api.listModels().then(setCloudModels)}
+          onNsfwChange={refresh}
+          onCurrencyChange={applyCurrency}
         />
       )}
 
@@ -812,6 +957,14 @@ export default function App() {
           font-size: 13px;
           font-weight: 600;
         }
+        .read-only-badge {
+          padding: var(--spacing-xs) var(--spacing-md);
+          border: 1px solid var(--border);
+          border-radius: var(--radius-md);
+          color: var(--text-secondary);
+          font-size: 13px;
+          font-weight: 600;
+        }
         .size-select {
           height: 36px;
           padding: 0 var(--spacing-md);
@@ -1,5 +1,5 @@
 import type { Generation } from '../lib/types';
-import { getImageUrl } from '../lib/api';
+import { getGenerationImageUrl } from '../lib/api';
 
 interface CompareProps {
   left: Generation;
@@ -19,7 +19,7 @@ export function Compare({ left, right, onClose }: CompareProps) {
         <div className="compare-content">
           <div className="compare-side">
             <div className="compare-image">
-              <img src={getImageUrl(left.image_path)} alt={left.slug} />
+              <img src={getGenerationImageUrl(left.id, 'full')} alt={left.slug} />
             </div>
             <div className="compare-info">
               <span className="compare-model">{left.model}</span>
@@ -32,7 +32,7 @@ export function Compare({ left, right, onClose }: CompareProps) {
 
           <div className="compare-side">
             <div className="compare-image">
-              <img src={getImageUrl(right.image_path)} alt={right.slug} />
+              <img src={getGenerationImageUrl(right.id, 'full')} alt={right.slug} />
             </div>
             <div className="compare-info">
               <span className="compare-model">{right.model}</span>
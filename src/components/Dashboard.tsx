@@ -1,6 +1,7 @@
 import { useState, useEffect } from 'react';
 import type { CostSummary } from '../lib/types';
 import * as api from '../lib/api';
+import { formatCost, setDisplayCurrency } from '../lib/currency';
 
 interface DashboardProps {
   onClose: () => void;
@@ -18,6 +19,7 @@ export function Dashboard({ onClose }: DashboardProps) {
       try {
         const since = period === 'all' ? undefined : period;
         const data = await api.getCostSummary(since);
+        setDisplayCurrency(data.currency);
         setSummary(data);
       } catch (e) {
         console.error('Failed to load cost summary:', e);
@@ -54,7 +56,14 @@ export function Dashboard({ onClose }: DashboardProps) {
           <div className="dashboard-content">
             <div className="dashboard-stats">
               <div className="stat-card">
-                <span className="stat-value">${summary.total_usd.toFixed(2)}</span>
+                <span
+                  className="stat-value"
+                  title={summary.currency.code !== 'USD'
+                    ? `$${summary.total_usd.toFixed(2)} at ${summary.currency.rate} ${summary.currency.code}/USD (${summary.currency.source})`
+                    : undefined}
+                >
+                  {formatCost(summary.total_usd, 2)}
+                </span>
                 <span className="stat-label">Total Spent</span>
               </div>
               <div className="stat-card">
@@ -63,7 +72,7 @@ export function Dashboard({ onClose }: DashboardProps) {
               </div>
               <div className="stat-card">
                 <span className="stat-value">
-                  ${summary.count > 0 ? (summary.total_usd / summary.count).toFixed(3) : '0.000'}
+                  {formatCost(summary.count > 0 ? summary.total_usd / summary.count : 0, 3)}
                 </span>
                 <span className="stat-label">Avg Cost</span>
               </div>
@@ -75,12 +84,40 @@ export function Dashboard({ onClose }: DashboardProps) {
                 {summary.by_model.map(([model, cost]) => (
                   <div key={model} className="model-row">
                     <span className="model-name truncate">{model}</span>
-                    <span className="model-cost">${cost.toFixed(2)}</span>
+                    <span className="model-cost">{formatCost(cost, 2)}</span>
                   </div>
                 ))}
               </div>
             </div>
 
+            {summary.by_collection.length > 0 && (
+              <div className="dashboard-section">
+                <h3>By Collection</h3>
+                <div className="model-list">
+                  {summary.by_collection.map(([collection, cost]) => (
+                    <div key={collection} className="model-row">
+                      <span className="model-name truncate">{collection}</span>
+                      <span className="model-cost">{formatCost(cost, 2)}</span>
+                    </div>
+                  ))}
+                </div>
+              </div>
+            )}
+
+            {summary.by_tag.length > 0 && (
+              <div className="dashboard-section">
+                <h3>By Tag</h3>
+                <div className="model-list">
+                  {summary.by_tag.slice(0, 10).map(([tag, cost]) => (
+                    <div key={tag} className="model-row">
+                      <span className="model-name truncate">{tag}</span>
+                      <span className="model-cost">{formatCost(cost, 2)}</span>
+                    </div>
+                  ))}
+                </div>
+              </div>
+            )}
+
             <div className="dashboard-section">
               <h3>By Day</h3>
               <div className="day-chart">
@@ -92,7 +129,7 @@ export function Dashboard({ onClose }: DashboardProps) {
                     onMouseLeave={() => setHoveredDay(null)}
                   >
                     {hoveredDay === day && (
-                      <div className="day-tooltip">${cost.toFixed(2)}</div>
+                      <div className="day-tooltip">{formatCost(cost, 2)}</div>
                     )}
                     <div
                       className="day-bar"
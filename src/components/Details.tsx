@@ -1,7 +1,8 @@
-import { useState, useEffect, memo } from 'react';
+import { useState, useEffect, memo, Fragment } from 'react';
 import Markdown from 'react-markdown';
 import type { Generation, ModelInfo, Collection } from '../lib/types';
-import { getImageUrl } from '../lib/api';
+import { getImageUrl, getGenerationImageUrl, copyImageToClipboard, errorMessage } from '../lib/api';
+import { formatCost } from '../lib/currency';
 import { TagChips } from './TagChips';
 
 interface DetailsProps {
@@ -10,11 +11,16 @@ interface DetailsProps {
   collections: Collection[];
   onClose: () => void;
   onToggleStar: () => void;
+  onToggleLock: () => void;
+  onToggleNsfw: () => void;
   onUpdateTitle: (title: string | null) => void;
+  onCaption: () => Promise<void>;
   onAddTag: (tag: string) => void;
   onRemoveTag: (tag: string) => void;
   onAddToCollection: (collectionName: string) => void;
   onRemoveFromCollection: (collectionName: string) => void;
+  onSetCover: (collectionName: string) => void;
+  onMoveToFront: (collectionName: string) => void;
   onFilterByTag: (tag: string) => void;
   onRemix: () => void;
   onReference: () => void;
@@ -28,11 +34,16 @@ export const Details = memo(function Details({
   collections,
   onClose,
   onToggleStar,
+  onToggleLock,
+  onToggleNsfw,
   onUpdateTitle,
+  onCaption,
   onAddTag,
   onRemoveTag,
   onAddToCollection,
   onRemoveFromCollection,
+  onSetCover,
+  onMoveToFront,
   onFilterByTag,
   onRemix,
   onReference,
@@ -44,6 +55,9 @@ export const Details = memo(function Details({
   const [showTrashConfirm, setShowTrashConfirm] = useState(false);
   const [promptExpanded, setPromptExpanded] = useState(true);
   const [metadataExpanded, setMetadataExpanded] = useState(false);
+  const [captioning, setCaptioning] = useState(false);
+  const [captionError, setCaptionError] = useState<string | null>(null);
+  const [copyState, setCopyState] = useState<'idle' | 'copied' | 'failed'>('idle');
 
   // Sync local state when selected generation changes
   useEffect(() => {
@@ -52,8 +66,32 @@ export const Details = memo(function Details({
     setShowTrashConfirm(false);
     setPromptExpanded(true);
     setMetadataExpanded(false);
+    setCaptionError(null);
+    setCopyState('idle');
   }, [generation.id]);
 
+  const handleCopy = async () => {
+    try {
+      await copyImageToClipboard(generation.id);
+      setCopyState('copied');
+    } catch {
+      setCopyState('failed');
+    }
+    setTimeout(() => setCopyState('idle'), 1500);
+  };
+
+  const handleCaption = async () => {
+    setCaptioning(true);
+    setCaptionError(null);
+    try {
+      await onCaption();
+    } catch (e) {
+      setCaptionError(errorMessage(e));
+    } finally {
+      setCaptioning(false);
+    }
+  };
+
   const handleTitleSave = () => {
     const newTitle = titleValue.trim() || null;
     if (newTitle !== generation.title) {
@@ -78,6 +116,13 @@ export const Details = memo(function Details({
     return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
   };
 
+  // Request settings not shown elsewhere; unset and default values are left out
+  const paramEntries = Object.entries(generation.params ?? {}).filter(([key, value]) =>
+    !['prompt', 'model', 'reference_paths', 'title'].includes(key) &&
+    value !== null && value !== false && value !== '' &&
+    !(Array.isArray(value) && value.length === 0)
+  );
+
   const modelInfo = models.find(m => m.id === generation.model);
 
   return (
@@ -88,11 +133,22 @@ export const Details = memo(function Details({
       </div>
 
       <div className="details-image">
-        <img
-          src={getImageUrl(generation.image_path)}
-          alt={generation.slug}
-          onClick={onOpenFullViewer}
-        />
+        {generation.media_type === 'video' ? (
+          <video
+            key={generation.id}
+            src={getGenerationImageUrl(generation.id, 'full')}
+            poster={getGenerationImageUrl(generation.id, 'preview')}
+            controls
+            loop
+            preload="metadata"
+          />
+        ) : (
+          <img
+            src={getGenerationImageUrl(generation.id, generation.animated ? 'full' : 'preview')}
+            alt={generation.slug}
+            onClick={onOpenFullViewer}
+          />
+        )}
       </div>
 
       <div className="details-content">
@@ -123,13 +179,29 @@ export const Details = memo(function Details({
         {/* Model + Star row */}
         <div className="model-star-row">
           <span className="model-badge">{modelInfo?.display_name || generation.model}</span>
-          <button
-            className={`star-btn ${generation.starred ? 'starred' : ''}`}
-            onClick={onToggleStar}
-            title={generation.starred ? 'Remove star' : 'Add star'}
-          >
-            {generation.starred ? '★' : '☆'}
-          </button>
+          <div className="model-star-actions">
+            <button
+              className={`lock-btn ${generation.locked ? 'locked' : ''}`}
+              onClick={onToggleLock}
+              title={generation.locked ? 'Unlock (allow edits and trash)' : 'Lock (prevent edits and trash)'}
+            >
+              {generation.locked ? '🔒' : '🔓'}
+            </button>
+            <button
+              className={`nsfw-btn ${generation.nsfw ? 'flagged' : ''}`}
+              onClick={onToggleNsfw}
+              title={generation.nsfw ? 'Clear the NSFW flag' : 'Flag as NSFW (hidden unless shown in settings)'}
+            >
+              18+
+            </button>
+            <button
+              className={`star-btn ${generation.starred ? 'starred' : ''}`}
+              onClick={onToggleStar}
+              title={generation.starred ? 'Remove star' : 'Add star'}
+            >
+              {generation.starred ? '★' : '☆'}
+            </button>
+          </div>
         </div>
 
         {/* References */}
@@ -146,6 +218,23 @@ export const Details = memo(function Details({
           </div>
         )}
 
+        {/* Palette */}
+        {generation.palette.length > 0 && (
+          <div className="details-section">
+            <label className="details-label">Palette</label>
+            <div className="palette-row">
+              {generation.palette.map((swatch) => (
+                <div
+                  key={swatch.hex}
+                  className="palette-swatch"
+                  style={{ background: swatch.hex, flexGrow: swatch.share }}
+                  title={`${swatch.hex} (${Math.round(swatch.share * 100)}%)`}
+                />
+              ))}
+            </div>
+          </div>
+        )}
+
         {/* Tags */}
         <div className="details-section">
           <label className="details-label">Tags</label>
@@ -166,6 +255,13 @@ export const Details = memo(function Details({
             <button className="btn btn-secondary details-action-btn" onClick={onReference}>
               Reference
             </button>
+            <button
+              className="btn btn-secondary details-action-btn"
+              onClick={handleCopy}
+              title="Copy image to clipboard"
+            >
+              {copyState === 'copied' ? 'Copied' : copyState === 'failed' ? 'Failed' : 'Copy'}
+            </button>
           </div>
         </div>
 
@@ -192,6 +288,24 @@ export const Details = memo(function Details({
                 <option key={c.id} value={c.name}>{c.name}</option>
               ))}
             </select>
+            {generation.collection_names[0] && (
+              <div className="collection-actions">
+                <button
+                  className="btn btn-secondary"
+                  onClick={() => onSetCover(generation.collection_names[0])}
+                  disabled={collections.find((c) => c.name === generation.collection_names[0])?.cover_id === generation.id}
+                >
+                  Use as cover
+                </button>
+                <button
+                  className="btn btn-secondary"
+                  onClick={() => onMoveToFront(generation.collection_names[0])}
+                  title="Move to the start of the collection order"
+                >
+                  Move to front
+                </button>
+              </div>
+            )}
           </div>
         )}
 
@@ -213,6 +327,23 @@ export const Details = memo(function Details({
           )}
         </div>
 
+        {/* Caption (auto-generated by a vision model) */}
+        <div className="details-section">
+          <div className="caption-header">
+            <span className="details-label" style={{ marginBottom: 0 }}>Caption</span>
+            <button
+              className="btn btn-ghost btn-sm"
+              onClick={handleCaption}
+              disabled={captioning}
+              title="Describe this image with the configured vision model"
+            >
+              {captioning ? 'Captioning...' : generation.caption ? 'Redo' : 'Generate'}
+            </button>
+          </div>
+          {generation.caption && <div className="caption-text">{generation.caption}</div>}
+          {captionError && <div className="caption-error">{captionError}</div>}
+        </div>
+
         {/* Metadata (collapsible) */}
         <div className="details-section">
           <button
@@ -238,6 +369,16 @@ export const Details = memo(function Details({
                   </>
                 )}
 
+                {generation.duration_seconds && (
+                  <>
+                    <span className="meta-label">Length</span>
+                    <span>
+                      {generation.duration_seconds.toFixed(1)}s
+                      {generation.fps && ` @ ${Math.round(generation.fps)} fps`}
+                    </span>
+                  </>
+                )}
+
                 {generation.generation_time_seconds && (
                   <>
                     <span className="meta-label">Time</span>
@@ -248,7 +389,7 @@ export const Details = memo(function Details({
                 {generation.cost_estimate_usd && (
                   <>
                     <span className="meta-label">Cost</span>
-                    <span>${generation.cost_estimate_usd.toFixed(3)}</span>
+                    <span>{formatCost(generation.cost_estimate_usd, 3)}</span>
                   </>
                 )}
 
@@ -261,6 +402,20 @@ export const Details = memo(function Details({
                     <span className="text-mono">{generation.seed}</span>
                   </>
                 )}
+
+                {generation.requested_by && (
+                  <>
+                    <span className="meta-label">Requested by</span>
+                    <span>{generation.requested_by}</span>
+                  </>
+                )}
+
+                {paramEntries.map(([key, value]) => (
+                  <Fragment key={key}>
+                    <span className="meta-label">{key}</span>
+                    <span className="text-mono">{typeof value === 'object' ? JSON.stringify(value) : String(value)}</span>
+                  </Fragment>
+                ))}
               </div>
             </div>
           )}
@@ -317,6 +472,11 @@ export const Details = memo(function Details({
         .details-image img:hover {
           opacity: 0.9;
         }
+        .details-image video {
+          display: block;
+          width: 100%;
+          border-radius: var(--radius-sm);
+        }
         .details-content {
           padding: var(--spacing-md);
           overflow-y: auto;
@@ -400,6 +560,45 @@ export const Details = memo(function Details({
         .star-btn.starred {
           color: var(--warning);
         }
+        .model-star-actions {
+          display: flex;
+          align-items: center;
+        }
+        .lock-btn {
+          background: none;
+          border: none;
+          font-size: 14px;
+          cursor: pointer;
+          opacity: 0.4;
+          padding: var(--spacing-xs);
+          min-width: 32px;
+          min-height: 32px;
+          transition: opacity var(--transition-fast);
+        }
+        .lock-btn:hover,
+        .lock-btn.locked {
+          opacity: 1;
+        }
+        .nsfw-btn {
+          background: none;
+          border: none;
+          color: var(--text-secondary);
+          font-size: 11px;
+          font-weight: 600;
+          cursor: pointer;
+          opacity: 0.4;
+          padding: var(--spacing-xs);
+          min-width: 32px;
+          min-height: 32px;
+          transition: opacity var(--transition-fast);
+        }
+        .nsfw-btn:hover {
+          opacity: 1;
+        }
+        .nsfw-btn.flagged {
+          color: var(--error);
+          opacity: 1;
+        }
 
         /* Sections */
         .details-section {
@@ -432,6 +631,15 @@ export const Details = memo(function Details({
           outline: none;
           border-color: var(--accent);
         }
+        .collection-actions {
+          display: flex;
+          gap: var(--spacing-xs);
+          margin-top: var(--spacing-xs);
+        }
+        .collection-actions .btn {
+          flex: 1;
+          font-size: 12px;
+        }
 
         /* Collapsible sections */
         .collapse-header {
@@ -502,7 +710,36 @@ export const Details = memo(function Details({
           object-fit: cover;
         }
 
+        /* Palette */
+        .palette-row {
+          display: flex;
+          height: 24px;
+          border-radius: var(--radius-sm);
+          overflow: hidden;
+          border: 1px solid var(--border);
+        }
+        .palette-swatch {
+          flex-basis: 0;
+          min-width: 12px;
+        }
+
         /* Prompt */
+        .caption-header {
+          display: flex;
+          align-items: center;
+          justify-content: space-between;
+          margin-bottom: var(--spacing-xs);
+        }
+        .caption-text {
+          font-size: 12px;
+          line-height: 1.5;
+          color: var(--text-secondary);
+          white-space: pre-wrap;
+        }
+        .caption-error {
+          font-size: 12px;
+          color: var(--error);
+        }
         .prompt-text {
           background: var(--bg-primary);
           padding: var(--spacing-sm);
@@ -1,6 +1,6 @@
 import { useState, useEffect, useMemo } from 'react';
 import type { Generation } from '../lib/types';
-import { getImageUrl } from '../lib/api';
+import { getGenerationImageUrl } from '../lib/api';
 import * as api from '../lib/api';
 
 interface GalleryPickerModalProps {
@@ -78,7 +78,7 @@ export function GalleryPickerModal({
                   title={gen.prompt.slice(0, 100)}
                 >
                   <img
-                    src={getImageUrl(gen.thumb_path || gen.image_path)}
+                    src={getGenerationImageUrl(gen.id, 'grid')}
                     alt={gen.slug}
                   />
                   {isSelected && <span className="picker-check">✓</span>}
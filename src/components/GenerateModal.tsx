@@ -1,9 +1,11 @@
 import { useState, useEffect, useMemo, useRef, useCallback } from 'react';
-import type { Generation, ModelInfo } from '../lib/types';
-import { getImageUrl, promptHistory } from '../lib/api';
+import type { Generation, ModelInfo, ModelQuota, Preset } from '../lib/types';
+import { getImageUrl, getGenerationImageUrl, promptHistory, errorMessage } from '../lib/api';
 import * as api from '../lib/api';
+import { formatCost } from '../lib/currency';
 
 interface SelectedRef {
+  /** Generation ID; negative for images dropped from outside the archive */
   id: number;
   path: string;
   thumbPath: string | null;
@@ -22,7 +24,7 @@ interface GenerateModalProps {
   models: ModelInfo[];
   initialState?: GenerateModalInitialState;
   onClose: () => void;
-  onGenerate: (prompt: string, model: string, tags: string[], referencePaths: string[], negativePrompt: string | null, numRuns?: number) => void;
+  onGenerate: (prompt: string, model: string, tags: string[], referencePaths: string[], negativePrompt: string | null, numRuns?: number, preset?: string | null, transparent?: boolean) => void;
 }
 
 export function GenerateModal({
@@ -39,6 +41,10 @@ export function GenerateModal({
   const [tagsInput, setTagsInput] = useState(initialState?.tags?.join(', ') || '');
   const [selectedRefs, setSelectedRefs] = useState<SelectedRef[]>(initialState?.references || []);
   const [numRuns, setNumRuns] = useState(1);
+  const [transparent, setTransparent] = useState(false);
+  const [presets, setPresets] = useState<Preset[]>([]);
+  const [selectedPreset, setSelectedPreset] = useState('');
+  const [quotas, setQuotas] = useState<ModelQuota[]>([]);
 
   // Prompt autocomplete
   const [recentPrompts, setRecentPrompts] = useState<string[]>([]);
@@ -65,6 +71,34 @@ export function GenerateModal({
     }
   }, [selectedRefs.length, models, selectedModel]);
 
+  // Fetch presets on mount
+  useEffect(() => {
+    api.listPresets().then(setPresets).catch(() => {});
+  }, []);
+
+  // Daily quotas from models.json, shown next to the cost
+  useEffect(() => {
+    api.getModelQuotas().then(setQuotas).catch(() => {});
+  }, []);
+  const quota = quotas.find((q) => q.model === selectedModel);
+
+  // Picking a preset fills the visible fields; size and CFG are applied by the backend
+  const handleSelectPreset = (name: string) => {
+    setSelectedPreset(name);
+    const preset = presets.find((p) => p.name === name);
+    if (!preset) return;
+    setSelectedModel(preset.model);
+    if (preset.tags.length > 0) {
+      const current = tagsInput.split(',').map((t) => t.trim()).filter(Boolean);
+      const merged = [...current, ...preset.tags.filter((t) => !current.includes(t))];
+      setTagsInput(merged.join(', '));
+    }
+    if (preset.negative_prompt) {
+      setNegativePrompt(preset.negative_prompt);
+      setAdvancedOpen(true);
+    }
+  };
+
   // Fetch recent prompts on mount
   useEffect(() => {
     promptHistory(50).then((rows) => {
@@ -174,6 +208,26 @@ export function GenerateModal({
     }
   };
 
+  const [dragOver, setDragOver] = useState(false);
+  const [dropError, setDropError] = useState<string | null>(null);
+  const nextDroppedId = useRef(-1);
+
+  const handleDrop = async (e: React.DragEvent) => {
+    e.preventDefault();
+    setDragOver(false);
+    setDropError(null);
+    const files = Array.from(e.dataTransfer.files).filter((f) => f.type.startsWith('image/'));
+    for (const file of files) {
+      try {
+        const path = await api.stageReference(file);
+        const id = nextDroppedId.current--;
+        setSelectedRefs((prev) => (prev.some((r) => r.path === path) ? prev : [...prev, { id, path, thumbPath: null }]));
+      } catch (err) {
+        setDropError(`${file.name}: ${errorMessage(err)}`);
+      }
+    }
+  };
+
   const handleAddLineageRef = (ref: SelectedRef) => {
     setSelectedRefs((prev) => {
       if (prev.some((r) => r.id === ref.id)) {
@@ -189,7 +243,7 @@ export function GenerateModal({
       .split(',')
       .map((t) => t.trim())
       .filter(Boolean);
-    onGenerate(prompt, selectedModel, tags, referencePaths, negativePrompt.trim() || null, numRuns);
+    onGenerate(prompt, selectedModel, tags, referencePaths, negativePrompt.trim() || null, numRuns, selectedPreset || null, transparent);
   };
 
   const lineageRefs = initialState?.lineage || [];
@@ -236,7 +290,7 @@ export function GenerateModal({
                       title={gen.prompt.slice(0, 100)}
                     >
                       <img
-                        src={getImageUrl(gen.thumb_path || gen.image_path)}
+                        src={getGenerationImageUrl(gen.id, 'grid')}
                         alt={gen.slug}
                       />
                       {isSelected && <span className="genmodal-check">✓</span>}
@@ -250,6 +304,26 @@ export function GenerateModal({
 
           {/* Right: Editor */}
           <div className="genmodal-editor">
+            {/* Preset */}
+            {presets.length > 0 && (
+              <div className="genmodal-section">
+                <label className="genmodal-label">Preset</label>
+                <select
+                  value={selectedPreset}
+                  onChange={(e) => handleSelectPreset(e.target.value)}
+                  className="genmodal-select"
+                >
+                  <option value="">None</option>
+                  {presets.map((p) => (
+                    <option key={p.id} value={p.name}>
+                      {p.name}
+                      {p.width && p.height ? ` (${p.width}×${p.height})` : ''}
+                    </option>
+                  ))}
+                </select>
+              </div>
+            )}
+
             {/* Model */}
             <div className="genmodal-section">
               <label className="genmodal-label">Model</label>
@@ -263,7 +337,7 @@ export function GenerateModal({
                   const maxRefs = m.max_refs ?? 0;
                   return (
                     <option key={m.id} value={m.id} disabled={!compatible}>
-                      {m.display_name} (${m.cost_per_image.toFixed(3)})
+                      {m.display_name} ({formatCost(m.cost_per_image, 3)})
                       {!compatible && ` - max ${maxRefs} ref${maxRefs !== 1 ? 's' : ''}`}
                     </option>
                   );
@@ -363,6 +437,17 @@ export function GenerateModal({
                     onChange={(e) => setNegativePrompt(e.target.value)}
                     placeholder="Things to avoid..."
                   />
+                  <label
+                    className="genmodal-checkbox"
+                    title="PNG with alpha, tagged transparent. gpt-image-1 makes it natively; other models need the self-hosted server's background removal."
+                  >
+                    <input
+                      type="checkbox"
+                      checked={transparent}
+                      onChange={(e) => setTransparent(e.target.checked)}
+                    />
+                    Transparent background
+                  </label>
                 </div>
               )}
             </div>
@@ -390,8 +475,16 @@ export function GenerateModal({
                 </div>
               </div>
               <span className="genmodal-cost">
-                ~${((models.find((m) => m.id === selectedModel)?.cost_per_image ?? 0) * numRuns).toFixed(3)}
+                ~{formatCost((models.find((m) => m.id === selectedModel)?.cost_per_image ?? 0) * numRuns, 3)}
               </span>
+              {quota && (
+                <span
+                  className={`genmodal-quota ${quota.remaining < numRuns ? 'over' : ''}`}
+                  title={`Daily quota: ${quota.used}/${quota.limit} used today`}
+                >
+                  {quota.remaining} left today
+                </span>
+              )}
               <button
                 className="btn btn-primary genmodal-generate"
                 onClick={handleGenerate}
@@ -404,16 +497,25 @@ export function GenerateModal({
         </div>
 
         {/* Bottom: Selected references strip */}
-        <div className="genmodal-selected">
+        <div
+          className={`genmodal-selected ${dragOver ? 'genmodal-selected-dragover' : ''}`}
+          onDragOver={(e) => {
+            e.preventDefault();
+            setDragOver(true);
+          }}
+          onDragLeave={() => setDragOver(false)}
+          onDrop={handleDrop}
+        >
           <div className="genmodal-selected-header">
             <span className="genmodal-label">
               References ({selectedRefs.length})
             </span>
+            {dropError && <span className="genmodal-drop-error">{dropError}</span>}
           </div>
           <div className="genmodal-selected-strip">
             {selectedRefs.length === 0 ? (
               <div className="genmodal-selected-empty">
-                Click images in the gallery to add as references
+                Click images in the gallery, or drop image files here, to add as references
               </div>
             ) : (
               <>
@@ -423,8 +525,8 @@ export function GenerateModal({
                     className={`genmodal-selected-thumb ${previewRef?.id === ref.id ? 'genmodal-selected-active' : ''}`}
                     onClick={() => setPreviewRef(previewRef?.id === ref.id ? null : ref)}
                   >
-                    <img src={getImageUrl(ref.thumbPath || ref.path)} alt={`#${ref.id}`} />
-                    <span className="genmodal-id">#{ref.id}</span>
+                    <img src={getImageUrl(ref.thumbPath || ref.path)} alt={ref.id > 0 ? `#${ref.id}` : 'Dropped image'} />
+                    <span className="genmodal-id">{ref.id > 0 ? `#${ref.id}` : 'file'}</span>
                     <button
                       className="genmodal-selected-remove"
                       onClick={(e) => {
@@ -739,6 +841,15 @@ export function GenerateModal({
           font-size: 13px;
         }
 
+        .genmodal-quota {
+          color: var(--text-muted);
+          font-size: 12px;
+        }
+
+        .genmodal-quota.over {
+          color: var(--error);
+        }
+
         .genmodal-generate {
           min-width: 120px;
           min-height: var(--input-height-lg);
@@ -770,6 +881,17 @@ export function GenerateModal({
           min-height: 100px;
         }
 
+        .genmodal-selected-dragover {
+          background: var(--bg-hover);
+          outline: 2px dashed var(--accent);
+          outline-offset: -4px;
+        }
+
+        .genmodal-drop-error {
+          color: var(--error);
+          font-size: 12px;
+        }
+
         .genmodal-selected-empty {
           color: var(--text-muted);
           font-size: 13px;
@@ -919,6 +1041,15 @@ export function GenerateModal({
           margin-top: var(--spacing-xs);
         }
 
+        .genmodal-checkbox {
+          display: flex;
+          align-items: center;
+          gap: var(--spacing-xs);
+          font-size: 13px;
+          color: var(--text-secondary);
+          cursor: pointer;
+        }
+
         .genmodal-negative-prompt {
           min-height: 60px;
           resize: vertical;
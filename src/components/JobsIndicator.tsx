@@ -1,12 +1,16 @@
 import { useState } from 'react';
-import type { Job } from '../lib/types';
+import type { Job, GenerationProgress, RetryReport } from '../lib/types';
+import { errorMessage } from '../lib/api';
 
 interface JobsIndicatorProps {
   jobs: Job[];
   activeCount: number;
   failedJobs: Job[];
   failedCount: number;
+  progress: Record<number, GenerationProgress>;
   onDismissFailedJob: (id: number) => void;
+  onCancelJob: (id: number) => void;
+  onRetryFailed: () => Promise<RetryReport>;
 }
 
 function formatElapsed(createdAt: string, startedAt: string | null): string {
@@ -32,6 +36,18 @@ function formatTimeAgo(timestamp: string): string {
   return `${Math.floor(seconds / 86400)}d ago`;
 }
 
+function formatStage(p: GenerationProgress): string {
+  switch (p.stage) {
+    case 'queued': return 'Queued';
+    case 'request_sent': return 'Generating';
+    case 'in_queue': return p.position != null ? `In queue (#${p.position})` : 'In queue';
+    case 'step': return `Step ${p.step}/${p.total}`;
+    case 'downloading': return 'Downloading';
+    case 'saving': return 'Saving';
+    case 'thumbnail_done': return 'Finishing';
+  }
+}
+
 function truncatePrompt(prompt: string, maxLength: number = 50): string {
   if (prompt.length <= maxLength) return prompt;
   return prompt.slice(0, maxLength) + '...';
@@ -42,9 +58,29 @@ function truncateError(error: string, maxLength: number = 100): string {
   return error.slice(0, maxLength) + '...';
 }
 
-export function JobsIndicator({ jobs, activeCount, failedJobs, failedCount, onDismissFailedJob }: JobsIndicatorProps) {
+export function JobsIndicator({ jobs, activeCount, failedJobs, failedCount, progress, onDismissFailedJob, onCancelJob, onRetryFailed }: JobsIndicatorProps) {
   const [showTooltip, setShowTooltip] = useState(false);
   const [showFailedTooltip, setShowFailedTooltip] = useState(false);
+  const [retrying, setRetrying] = useState(false);
+  const [retryMessage, setRetryMessage] = useState<string | null>(null);
+
+  const handleRetryFailed = async () => {
+    setRetrying(true);
+    setRetryMessage(null);
+    try {
+      const report = await onRetryFailed();
+      const parts = [`Retried ${report.retried.length}`];
+      if (report.failed.length > 0) parts.push(`${report.failed.length} failed again`);
+      if (report.over_budget.length > 0) parts.push(`${report.over_budget.length} over cost cap`);
+      if (report.skipped.length > 0) parts.push(`${report.skipped.length} not retryable`);
+      if (report.blocked.length > 0) parts.push(`${report.blocked.length} blocked by content policy`);
+      setRetryMessage(parts.join(', '));
+    } catch (e) {
+      setRetryMessage(errorMessage(e));
+    } finally {
+      setRetrying(false);
+    }
+  };
 
   const activeJobs = jobs.filter(j => j.status === 'pending' || j.status === 'running');
 
@@ -64,7 +100,21 @@ export function JobsIndicator({ jobs, activeCount, failedJobs, failedCount, onDi
 
           {showFailedTooltip && (
             <div className="jobs-tooltip jobs-tooltip-failed">
-              <div className="jobs-tooltip-title">Recent Failures</div>
+              <div className="jobs-tooltip-title">
+                Recent Failures
+                <button
+                  className="jobs-retry-btn"
+                  onClick={(e) => {
+                    e.stopPropagation();
+                    handleRetryFailed();
+                  }}
+                  disabled={retrying}
+                  title="Re-run these jobs (up to the retry cost cap)"
+                >
+                  {retrying ? 'Retrying...' : 'Retry all'}
+                </button>
+              </div>
+              {retryMessage && <div className="jobs-tooltip-note">{retryMessage}</div>}
               {failedJobs.map((job) => (
                 <div key={job.id} className="jobs-tooltip-item jobs-tooltip-item-failed">
                   <div className="jobs-tooltip-header">
@@ -88,6 +138,9 @@ export function JobsIndicator({ jobs, activeCount, failedJobs, failedCount, onDi
                   </div>
                   {job.error && (
                     <div className="jobs-tooltip-error">
+                      {job.failure === 'content_policy' && (
+                        <div className="jobs-tooltip-blocked">Blocked by content policy</div>
+                      )}
                       {truncateError(job.error)}
                     </div>
                   )}
@@ -119,10 +172,26 @@ export function JobsIndicator({ jobs, activeCount, failedJobs, failedCount, onDi
                     <span className="jobs-tooltip-time">
                       {formatElapsed(job.created_at, job.started_at)}
                     </span>
+                    {/* CLI jobs run in another process; they're stopped with Ctrl-C there */}
+                    {job.source !== 'cli' && (
+                      <button
+                        className="jobs-dismiss-btn"
+                        onClick={(e) => {
+                          e.stopPropagation();
+                          onCancelJob(job.id);
+                        }}
+                        title="Cancel"
+                      >
+                        ×
+                      </button>
+                    )}
                   </div>
                   <div className="jobs-tooltip-prompt">
                     "{truncatePrompt(job.prompt)}"
                   </div>
+                  {progress[job.id] && (
+                    <div className="jobs-tooltip-stage">{formatStage(progress[job.id])}</div>
+                  )}
                   {job.tags && job.tags.length > 0 && (
                     <div className="jobs-tooltip-tags">
                       {job.tags.map((tag) => (
@@ -218,6 +287,33 @@ export function JobsIndicator({ jobs, activeCount, failedJobs, failedCount, onDi
           border-bottom: 1px solid rgba(255,255,255,0.04);
         }
 
+        .jobs-retry-btn {
+          float: right;
+          background: none;
+          border: 1px solid var(--border);
+          border-radius: var(--radius-sm);
+          color: var(--text-secondary);
+          font-size: 11px;
+          padding: 0 var(--spacing-xs);
+          cursor: pointer;
+        }
+
+        .jobs-retry-btn:hover:not(:disabled) {
+          color: var(--text-primary);
+          background: var(--bg-hover);
+        }
+
+        .jobs-retry-btn:disabled {
+          cursor: default;
+          opacity: 0.6;
+        }
+
+        .jobs-tooltip-note {
+          padding: var(--spacing-xs) var(--spacing-sm);
+          font-size: 11px;
+          color: var(--text-muted);
+        }
+
         .jobs-tooltip-item {
           padding: var(--spacing-sm);
           background: var(--bg-primary);
@@ -250,6 +346,11 @@ export function JobsIndicator({ jobs, activeCount, failedJobs, failedCount, onDi
           font-family: var(--font-mono);
         }
 
+        .jobs-tooltip-stage {
+          font-size: 11px;
+          color: var(--accent);
+          margin-top: 2px;
+        }
         .jobs-tooltip-prompt {
           font-size: 13px;
           color: var(--text-secondary);
@@ -280,6 +381,12 @@ export function JobsIndicator({ jobs, activeCount, failedJobs, failedCount, onDi
           word-break: break-word;
         }
 
+        .jobs-tooltip-blocked {
+          font-family: var(--font-sans);
+          font-weight: 600;
+          margin-bottom: 2px;
+        }
+
         .jobs-dismiss-btn {
           background: none;
           border: none;
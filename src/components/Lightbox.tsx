@@ -1,6 +1,6 @@
 import { useEffect, useCallback } from 'react';
 import type { Generation } from '../lib/types';
-import { getImageUrl } from '../lib/api';
+import { getGenerationImageUrl } from '../lib/api';
 
 interface LightboxProps {
   generation: Generation;
@@ -19,7 +19,7 @@ export function Lightbox({
   hasNext,
   hasPrevious
 }: LightboxProps) {
-  const imageSrc = getImageUrl(generation.image_path);
+  const imageSrc = getGenerationImageUrl(generation.id, 'full');
 
   const handleKeyDown = useCallback((e: KeyboardEvent) => {
     if (e.key === 'Escape') {
@@ -39,7 +39,11 @@ export function Lightbox({
   return (
     <div className="lightbox-backdrop" onClick={onClose}>
       <div className="lightbox-content" onClick={(e) => e.stopPropagation()}>
-        <img src={imageSrc} alt={generation.slug} />
+        {generation.media_type === 'video' ? (
+          <video key={generation.id} src={imageSrc} controls autoPlay loop />
+        ) : (
+          <img src={imageSrc} alt={generation.slug} />
+        )}
       </div>
 
       {hasPrevious && (
@@ -82,7 +86,8 @@ export function Lightbox({
           max-height: 95vh;
           cursor: default;
         }
-        .lightbox-content img {
+        .lightbox-content img,
+        .lightbox-content video {
           max-width: 95vw;
           max-height: 95vh;
           object-fit: contain;
@@ -1,6 +1,7 @@
 import { useState, useEffect } from 'react';
 import type { Generation, ModelInfo, Reference } from '../lib/types';
 import { getImageUrl } from '../lib/api';
+import { formatCost } from '../lib/currency';
 
 interface RemixModalProps {
   generation: Generation;
@@ -97,7 +98,7 @@ export function RemixModal({
               >
                 {models.map((m) => (
                   <option key={m.id} value={m.id}>
-                    {m.display_name} (${m.cost_per_image.toFixed(3)})
+                    {m.display_name} ({formatCost(m.cost_per_image, 3)})
                   </option>
                 ))}
               </select>
@@ -153,7 +154,7 @@ export function RemixModal({
                 </div>
               </div>
               <span className="remix-cost">
-                ~${((models.find((m) => m.id === selectedModel)?.cost_per_image ?? 0) * numRuns).toFixed(3)}
+                ~{formatCost((models.find((m) => m.id === selectedModel)?.cost_per_image ?? 0) * numRuns, 3)}
               </span>
               <button
                 className="btn btn-primary remix-generate"
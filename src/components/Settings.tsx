@@ -1,5 +1,5 @@
 import { useState, useEffect } from 'react';
-import type { TagCount, SelfHostedStatus } from '../lib/types';
+import type { TagCount, SelfHostedStatus, ModelCatalog, NotifySettings, NsfwSettings, JobSource, CurrencySettings, DisplayCurrency } from '../lib/types';
 import * as api from '../lib/api';
 
 interface SettingsProps {
@@ -8,11 +8,26 @@ interface SettingsProps {
   onToggleHiddenTag: (tag: string) => void;
   onClose: () => void;
   onSelfHostedChange?: () => void;
+  onModelsChange?: () => void;
+  onNsfwChange?: () => void;
+  onCurrencyChange?: (currency: DisplayCurrency) => void;
 }
 
-type SettingsSection = 'hidden-tags' | 'selfhosted' | null;
+type SettingsSection = 'hidden-tags' | 'selfhosted' | 'catalog' | 'notifications' | 'nsfw' | 'currency' | null;
 
-export function Settings({ tags, hiddenTags, onToggleHiddenTag, onClose, onSelfHostedChange }: SettingsProps) {
+const NSFW_OPTIONS: { key: keyof NsfwSettings; label: string; description: string }[] = [
+  { key: 'show', label: 'Show NSFW', description: 'Include flagged images, blurred until hovered' },
+  { key: 'from_prompt', label: 'Flag from prompt', description: 'Rating tags like nsfw, explicit, rating_explicit' },
+  { key: 'classify', label: 'Classify new images', description: 'Ask the caption vision model about each one' },
+];
+
+const NOTIFY_SOURCES: { source: JobSource; label: string; description: string }[] = [
+  { source: 'gui', label: 'App', description: 'Only while the window is in the background' },
+  { source: 'cli', label: 'Command line', description: 'generate, batch, fix and retry-failed' },
+  { source: 'schedule', label: 'Schedules', description: 'Each scheduled generation' },
+];
+
+export function Settings({ tags, hiddenTags, onToggleHiddenTag, onClose, onSelfHostedChange, onModelsChange, onNsfwChange, onCurrencyChange }: SettingsProps) {
   const [activeSection, setActiveSection] = useState<SettingsSection>(null);
 
   // Self-hosted server state
@@ -20,9 +35,46 @@ export function Settings({ tags, hiddenTags, onToggleHiddenTag, onClose, onSelfH
   const [serverStatus, setServerStatus] = useState<SelfHostedStatus | null>(null);
   const [testing, setTesting] = useState(false);
   const [saving, setSaving] = useState(false);
+  const [serverBusy, setServerBusy] = useState<string | null>(null);
+  const [serverMessage, setServerMessage] = useState<string | null>(null);
+
+  // fal.ai model catalog state
+  const [catalog, setCatalog] = useState<ModelCatalog | null>(null);
+  const [refreshing, setRefreshing] = useState(false);
+  const [catalogError, setCatalogError] = useState<string | null>(null);
+
+  // Desktop notification state
+  const [notifySettings, setNotifySettings] = useState<NotifySettings | null>(null);
+  const [webhookUrl, setWebhookUrl] = useState('');
+
+  // NSFW filtering state
+  const [nsfwSettings, setNsfwSettings] = useState<NsfwSettings | null>(null);
+
+  // Display currency state
+  const [currencySettings, setCurrencySettings] = useState<CurrencySettings | null>(null);
+  const [currencyCode, setCurrencyCode] = useState('');
+  const [manualRate, setManualRate] = useState('');
+  const [shownCurrency, setShownCurrency] = useState<DisplayCurrency | null>(null);
+  const [currencyError, setCurrencyError] = useState<string | null>(null);
 
   // Load current server URL on mount
   useEffect(() => {
+    api.getModelCatalog().then(setCatalog).catch(() => {});
+    api.getNotifySettings()
+      .then((settings) => {
+        setNotifySettings(settings);
+        setWebhookUrl(settings.webhook_url ?? '');
+      })
+      .catch(() => {});
+    api.getNsfwSettings().then(setNsfwSettings).catch(() => {});
+    api.getCurrencySettings()
+      .then((settings) => {
+        setCurrencySettings(settings);
+        setCurrencyCode(settings.code);
+        setManualRate(settings.manual_rate?.toString() ?? '');
+      })
+      .catch(() => {});
+    api.getDisplayCurrency().then(setShownCurrency).catch(() => {});
     api.getSelfhostedUrl().then((url) => {
       if (url) setServerUrl(url);
     });
@@ -49,6 +101,26 @@ export function Settings({ tags, hiddenTags, onToggleHiddenTag, onClose, onSelfH
     onSelfHostedChange?.();
   };
 
+  // Load a model (or warm up the current one) ahead of the first generation
+  const handleLoadModel = async (model: string | null) => {
+    setServerBusy(model ?? 'warmup');
+    setServerMessage(null);
+    try {
+      if (model) {
+        await api.switchSelfhostedModel(model);
+        setServerMessage(`${model} loaded`);
+      } else {
+        const result = await api.warmupSelfhosted();
+        setServerMessage(`${result.model} ready (load ${result.load_seconds}s, first pass ${result.warmup_seconds}s)`);
+      }
+    } catch (e) {
+      setServerMessage(api.errorMessage(e));
+    }
+    setServerStatus(await api.checkSelfhostedHealth());
+    setServerBusy(null);
+    onSelfHostedChange?.();
+  };
+
   const handleClearUrl = async () => {
     setSaving(true);
     setServerUrl('');
@@ -58,6 +130,72 @@ export function Settings({ tags, hiddenTags, onToggleHiddenTag, onClose, onSelfH
     onSelfHostedChange?.();
   };
 
+  const handleRefreshCatalog = async () => {
+    setRefreshing(true);
+    setCatalogError(null);
+    try {
+      setCatalog(await api.refreshModelCatalog());
+      onModelsChange?.();
+    } catch (e) {
+      setCatalogError(api.errorMessage(e));
+    }
+    setRefreshing(false);
+  };
+
+  const handleToggleNotify = async (source: JobSource) => {
+    if (!notifySettings) return;
+    const next = { ...notifySettings, [source]: !notifySettings[source] };
+    setNotifySettings(next);
+    await api.setNotifySettings(next);
+  };
+
+  const handleSaveWebhook = async () => {
+    if (!notifySettings) return;
+    const next = { ...notifySettings, webhook_url: webhookUrl.trim() || null };
+    setNotifySettings(next);
+    await api.setNotifySettings(next);
+  };
+
+  const handleToggleNsfw = async (key: keyof NsfwSettings) => {
+    if (!nsfwSettings) return;
+    const next = { ...nsfwSettings, [key]: !nsfwSettings[key] };
+    setNsfwSettings(next);
+    await api.setNsfwSettings(next);
+    if (key === 'show') onNsfwChange?.();
+  };
+
+  const applyCurrency = (currency: DisplayCurrency) => {
+    setShownCurrency(currency);
+    onCurrencyChange?.(currency);
+  };
+
+  const handleSaveCurrency = async () => {
+    if (!currencySettings) return;
+    const rate = parseFloat(manualRate);
+    const next = {
+      ...currencySettings,
+      code: currencyCode.trim().toUpperCase(),
+      manual_rate: manualRate.trim() && rate > 0 ? rate : null,
+    };
+    setCurrencyError(null);
+    try {
+      applyCurrency(await api.setCurrencySettings(next));
+      setCurrencySettings(next);
+    } catch (e) {
+      setCurrencyError(api.errorMessage(e));
+    }
+  };
+
+  const handleRefreshRate = async () => {
+    setCurrencyError(null);
+    try {
+      await api.refreshExchangeRate();
+      applyCurrency(await api.getDisplayCurrency());
+    } catch (e) {
+      setCurrencyError(api.errorMessage(e));
+    }
+  };
+
   const visibleTags = tags.filter((t) => !hiddenTags.includes(t.name));
   const hiddenTagsList = tags.filter((t) => hiddenTags.includes(t.name));
 
@@ -75,6 +213,10 @@ export function Settings({ tags, hiddenTags, onToggleHiddenTag, onClose, onSelfH
               <h2>
                 {activeSection === 'hidden-tags' && 'Hidden Tags'}
                 {activeSection === 'selfhosted' && 'Self-Hosted Server'}
+                {activeSection === 'catalog' && 'fal.ai Model Catalog'}
+                {activeSection === 'notifications' && 'Notifications'}
+                {activeSection === 'nsfw' && 'NSFW'}
+                {activeSection === 'currency' && 'Currency'}
               </h2>
             </>
           ) : (
@@ -106,6 +248,64 @@ export function Settings({ tags, hiddenTags, onToggleHiddenTag, onClose, onSelfH
                   <path fillRule="evenodd" d="M7.293 14.707a1 1 0 010-1.414L10.586 10 7.293 6.707a1 1 0 011.414-1.414l4 4a1 1 0 010 1.414l-4 4a1 1 0 01-1.414 0z" clipRule="evenodd" />
                 </svg>
               </button>
+              <button
+                className="settings-menu-item"
+                onClick={() => setActiveSection('catalog')}
+              >
+                <div className="settings-menu-item-content">
+                  <span className="settings-menu-item-label">fal.ai Model Catalog</span>
+                  <span className="settings-menu-item-value">
+                    {catalog?.fetched_at ? `${catalog.models.length} models` : 'Not fetched'}
+                  </span>
+                </div>
+                <svg width="16" height="16" viewBox="0 0 20 20" fill="currentColor">
+                  <path fillRule="evenodd" d="M7.293 14.707a1 1 0 010-1.414L10.586 10 7.293 6.707a1 1 0 011.414-1.414l4 4a1 1 0 010 1.414l-4 4a1 1 0 01-1.414 0z" clipRule="evenodd" />
+                </svg>
+              </button>
+              <button
+                className="settings-menu-item"
+                onClick={() => setActiveSection('notifications')}
+              >
+                <div className="settings-menu-item-content">
+                  <span className="settings-menu-item-label">Notifications</span>
+                  <span className="settings-menu-item-value">
+                    {notifySettings
+                      ? `${NOTIFY_SOURCES.filter(({ source }) => notifySettings[source]).length} of ${NOTIFY_SOURCES.length} on`
+                      : 'Loading...'}
+                  </span>
+                </div>
+                <svg width="16" height="16" viewBox="0 0 20 20" fill="currentColor">
+                  <path fillRule="evenodd" d="M7.293 14.707a1 1 0 010-1.414L10.586 10 7.293 6.707a1 1 0 011.414-1.414l4 4a1 1 0 010 1.414l-4 4a1 1 0 01-1.414 0z" clipRule="evenodd" />
+                </svg>
+              </button>
+              <button
+                className="settings-menu-item"
+                onClick={() => setActiveSection('nsfw')}
+              >
+                <div className="settings-menu-item-content">
+                  <span className="settings-menu-item-label">NSFW</span>
+                  <span className="settings-menu-item-value">
+                    {nsfwSettings ? (nsfwSettings.show ? 'Shown, blurred' : 'Hidden') : 'Loading...'}
+                  </span>
+                </div>
+                <svg width="16" height="16" viewBox="0 0 20 20" fill="currentColor">
+                  <path fillRule="evenodd" d="M7.293 14.707a1 1 0 010-1.414L10.586 10 7.293 6.707a1 1 0 011.414-1.414l4 4a1 1 0 010 1.414l-4 4a1 1 0 01-1.414 0z" clipRule="evenodd" />
+                </svg>
+              </button>
+              <button
+                className="settings-menu-item"
+                onClick={() => setActiveSection('currency')}
+              >
+                <div className="settings-menu-item-content">
+                  <span className="settings-menu-item-label">Currency</span>
+                  <span className="settings-menu-item-value">
+                    {shownCurrency ? shownCurrency.code : 'Loading...'}
+                  </span>
+                </div>
+                <svg width="16" height="16" viewBox="0 0 20 20" fill="currentColor">
+                  <path fillRule="evenodd" d="M7.293 14.707a1 1 0 010-1.414L10.586 10 7.293 6.707a1 1 0 011.414-1.414l4 4a1 1 0 010 1.414l-4 4a1 1 0 01-1.414 0z" clipRule="evenodd" />
+                </svg>
+              </button>
               <button
                 className="settings-menu-item"
                 onClick={() => setActiveSection('hidden-tags')}
@@ -197,6 +397,33 @@ export function Settings({ tags, hiddenTags, onToggleHiddenTag, onClose, onSelfH
                       )}
                     </div>
                   )}
+                  {/* Reachable (even with no model loaded yet): offer to load one */}
+                  {serverStatus.available_models.length > 0 && (
+                    <div className="settings-model-actions">
+                      {serverStatus.available_models.map((model) => (
+                        <button
+                          key={model}
+                          className="btn btn-ghost"
+                          onClick={() => handleLoadModel(model)}
+                          disabled={serverBusy !== null || model === serverStatus.current_model}
+                          title={`Load ${model} now so the first generation doesn't wait`}
+                        >
+                          {serverBusy === model ? 'Loading...' : `Load ${model}`}
+                        </button>
+                      ))}
+                      <button
+                        className="btn btn-secondary"
+                        onClick={() => handleLoadModel(null)}
+                        disabled={serverBusy !== null}
+                        title="Load the current model and run one tiny pass"
+                      >
+                        {serverBusy === 'warmup' ? 'Warming up...' : 'Warm up'}
+                      </button>
+                    </div>
+                  )}
+                  {serverMessage && (
+                    <div className="status-details">{serverMessage}</div>
+                  )}
                   {serverStatus.error && (
                     <div className="status-error-message">{serverStatus.error}</div>
                   )}
@@ -205,6 +432,193 @@ export function Settings({ tags, hiddenTags, onToggleHiddenTag, onClose, onSelfH
             </div>
           )}
 
+          {activeSection === 'catalog' && (
+            <div className="settings-section">
+              <p className="settings-description">
+                Fetch fal.ai's text-to-image models and prices so new ones appear in the model
+                picker without an app update. Same as <code>pixery models --refresh</code>.
+              </p>
+
+              <div className="settings-actions">
+                <button
+                  className="btn btn-primary"
+                  onClick={handleRefreshCatalog}
+                  disabled={refreshing}
+                >
+                  {refreshing ? 'Refreshing...' : 'Refresh'}
+                </button>
+              </div>
+
+              {(catalog?.fetched_at || catalogError) && (
+                <div className={`settings-status ${catalogError ? 'status-error' : 'status-ok'}`}>
+                  {catalog?.fetched_at && (
+                    <div className="status-details">
+                      <div className="status-row">
+                        <span className="status-label">Models</span>
+                        <span className="status-value">{catalog.models.length}</span>
+                      </div>
+                      <div className="status-row">
+                        <span className="status-label">Last refreshed</span>
+                        <span className="status-value">{catalog.fetched_at.replace('T', ' ')}</span>
+                      </div>
+                    </div>
+                  )}
+                  {catalogError && (
+                    <div className="status-error-message">{catalogError}</div>
+                  )}
+                </div>
+              )}
+            </div>
+          )}
+
+          {activeSection === 'nsfw' && (
+            <div className="settings-section">
+              <p className="settings-description">
+                Flagged generations are left out of the gallery, search and the command line.
+                Flag one by hand from its details. Same as <code>pixery nsfw settings</code>.
+              </p>
+
+              {nsfwSettings && (
+                <div className="settings-tag-list">
+                  {NSFW_OPTIONS.map(({ key, label, description }) => (
+                    <label key={key} className="settings-toggle">
+                      <input
+                        type="checkbox"
+                        checked={nsfwSettings[key]}
+                        onChange={() => handleToggleNsfw(key)}
+                      />
+                      <span className="settings-toggle-label">{label}</span>
+                      <span className="settings-toggle-description">{description}</span>
+                    </label>
+                  ))}
+                </div>
+              )}
+            </div>
+          )}
+
+          {activeSection === 'currency' && (
+            <div className="settings-section">
+              <p className="settings-description">
+                Costs are recorded in USD and shown in this currency. Leave the rate empty to
+                fetch it daily (ECB reference rates). Same as <code>pixery currency</code>.
+              </p>
+
+              {currencySettings && (
+                <>
+                  <div className="settings-field">
+                    <label htmlFor="currency-code">Currency</label>
+                    <input
+                      id="currency-code"
+                      type="text"
+                      value={currencyCode}
+                      onChange={(e) => setCurrencyCode(e.target.value)}
+                      placeholder="EUR"
+                      maxLength={3}
+                      className="settings-input"
+                    />
+                  </div>
+                  <div className="settings-field">
+                    <label htmlFor="currency-rate">Rate per USD</label>
+                    <input
+                      id="currency-rate"
+                      type="text"
+                      value={manualRate}
+                      onChange={(e) => setManualRate(e.target.value)}
+                      placeholder="Fetched"
+                      className="settings-input"
+                    />
+                  </div>
+                  <div className="settings-actions">
+                    <button className="btn btn-primary" onClick={handleSaveCurrency}>
+                      Save
+                    </button>
+                    <button
+                      className="btn btn-secondary"
+                      onClick={handleRefreshRate}
+                      disabled={currencySettings.code === 'USD'}
+                    >
+                      Fetch rate
+                    </button>
+                  </div>
+                </>
+              )}
+
+              {(shownCurrency || currencyError) && (
+                <div className={`settings-status ${currencyError ? 'status-error' : 'status-ok'}`}>
+                  {shownCurrency && shownCurrency.code !== 'USD' && (
+                    <div className="status-details">
+                      <div className="status-row">
+                        <span className="status-label">Rate</span>
+                        <span className="status-value">{shownCurrency.rate} {shownCurrency.code}/USD</span>
+                      </div>
+                      <div className="status-row">
+                        <span className="status-label">Source</span>
+                        <span className="status-value">{shownCurrency.source}</span>
+                      </div>
+                    </div>
+                  )}
+                  {currencyError && (
+                    <div className="status-error-message">{currencyError}</div>
+                  )}
+                </div>
+              )}
+            </div>
+          )}
+
+          {activeSection === 'notifications' && (
+            <div className="settings-section">
+              <p className="settings-description">
+                Desktop notifications when generations finish or fail. Batches send one summary;
+                cancelled jobs never notify. Same as <code>pixery notifications</code>.
+              </p>
+
+              {notifySettings && (
+                <div className="settings-tag-list">
+                  {NOTIFY_SOURCES.map(({ source, label, description }) => (
+                    <label key={source} className="settings-toggle">
+                      <input
+                        type="checkbox"
+                        checked={notifySettings[source]}
+                        onChange={() => handleToggleNotify(source)}
+                      />
+                      <span className="settings-toggle-label">{label}</span>
+                      <span className="settings-toggle-description">{description}</span>
+                    </label>
+                  ))}
+                </div>
+              )}
+
+              {notifySettings && (
+                <>
+                  <div className="settings-field">
+                    <label htmlFor="webhook-url">Webhook URL</label>
+                    <input
+                      id="webhook-url"
+                      type="text"
+                      value={webhookUrl}
+                      onChange={(e) => setWebhookUrl(e.target.value)}
+                      placeholder="https://ntfy.sh/my-pixery"
+                      className="settings-input"
+                    />
+                  </div>
+                  <p className="settings-description">
+                    POSTed on every completion or failure, from any source. Discord, Slack and ntfy
+                    URLs get a chat message; anything else gets the event as JSON.
+                  </p>
+                  <div className="settings-actions">
+                    <button
+                      className="btn btn-primary"
+                      onClick={handleSaveWebhook}
+                      disabled={(webhookUrl.trim() || null) === notifySettings.webhook_url}
+                    >
+                      Save
+                    </button>
+                  </div>
+                </>
+              )}
+            </div>
+          )}
+
           {activeSection === 'hidden-tags' && (
             <div className="settings-section">
               <p className="settings-description">
@@ -351,6 +765,27 @@ export function Settings({ tags, hiddenTags, onToggleHiddenTag, onClose, onSelfH
           font-size: 13px;
           margin-bottom: var(--spacing-lg);
         }
+        .settings-toggle {
+          display: flex;
+          align-items: center;
+          gap: var(--spacing-sm);
+          padding: var(--spacing-sm) var(--spacing-md);
+          border-radius: var(--radius-sm);
+          color: var(--text-secondary);
+          cursor: pointer;
+        }
+        .settings-toggle:hover {
+          background: var(--bg-hover);
+          color: var(--text-primary);
+        }
+        .settings-toggle-label {
+          font-weight: 500;
+        }
+        .settings-toggle-description {
+          margin-left: auto;
+          font-size: 12px;
+          color: var(--text-muted);
+        }
         .settings-tag-group {
           margin-bottom: var(--spacing-lg);
         }
@@ -489,6 +924,12 @@ export function Settings({ tags, hiddenTags, onToggleHiddenTag, onClose, onSelfH
         .status-value {
           color: var(--text-secondary);
         }
+        .settings-model-actions {
+          display: flex;
+          flex-wrap: wrap;
+          gap: var(--spacing-xs);
+          margin-top: var(--spacing-sm);
+        }
         .status-error-message {
           margin-top: var(--spacing-sm);
           font-size: 12px;
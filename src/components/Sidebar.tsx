@@ -1,9 +1,13 @@
 import { useState, useEffect, useRef } from 'react';
-import type { Collection, TodayCost } from '../lib/types';
+import { getImageUrl, errorMessage } from '../lib/api';
+import { formatCost } from '../lib/currency';
+import type { Collection, SmartCollection, TodayCost } from '../lib/types';
 
 interface SidebarProps {
   collections: Collection[];
   activeCollection: number | null;
+  smartCollections: SmartCollection[];
+  activeSmartCollection: number | null;
   starredOnly: boolean;
   showTrashed: boolean;
   showUncategorized: boolean;
@@ -13,6 +17,10 @@ interface SidebarProps {
   onShowUncategorized: () => void;
   onSelectCollection: (id: number) => void;
   onCreateCollection: (name: string) => void;
+  onSelectSmartCollection: (id: number) => void;
+  /** Rejects with the backend's message for a bad filter expression */
+  onCreateSmartCollection: (name: string, filter: string) => Promise<void>;
+  onDeleteSmartCollection: (name: string) => void;
   onOpenDashboard: () => void;
   onOpenSettings: () => void;
   pinned: boolean;
@@ -23,6 +31,8 @@ interface SidebarProps {
 export function Sidebar({
   collections,
   activeCollection,
+  smartCollections,
+  activeSmartCollection,
   starredOnly,
   showTrashed,
   showUncategorized,
@@ -32,6 +42,9 @@ export function Sidebar({
   onShowUncategorized,
   onSelectCollection,
   onCreateCollection,
+  onSelectSmartCollection,
+  onCreateSmartCollection,
+  onDeleteSmartCollection,
   onOpenDashboard,
   onOpenSettings,
   pinned,
@@ -40,10 +53,14 @@ export function Sidebar({
 }: SidebarProps) {
   const [creating, setCreating] = useState(false);
   const [newName, setNewName] = useState('');
+  const [creatingSmart, setCreatingSmart] = useState(false);
+  const [smartName, setSmartName] = useState('');
+  const [smartFilter, setSmartFilter] = useState('');
+  const [smartError, setSmartError] = useState<string | null>(null);
   const [open, setOpen] = useState(false);
   const sidebarRef = useRef<HTMLElement>(null);
 
-  const isAllActive = !starredOnly && !showTrashed && !showUncategorized && activeCollection === null;
+  const isAllActive = !starredOnly && !showTrashed && !showUncategorized && activeCollection === null && activeSmartCollection === null;
 
   // Close on click outside (only when open but not pinned)
   useEffect(() => {
@@ -82,6 +99,25 @@ export function Sidebar({
     setCreating(false);
   };
 
+  const cancelSmart = () => {
+    setCreatingSmart(false);
+    setSmartName('');
+    setSmartFilter('');
+    setSmartError(null);
+  };
+
+  const handleSmartSubmit = async () => {
+    const name = smartName.trim();
+    const filter = smartFilter.trim();
+    if (!name || !filter) return;
+    try {
+      await onCreateSmartCollection(name, filter);
+      cancelSmart();
+    } catch (e) {
+      setSmartError(errorMessage(e));
+    }
+  };
+
   const sidebarClass = `sidebar ${pinned ? 'sidebar-pinned' : ''} ${open && !pinned ? 'sidebar-open' : ''}`;
 
   return (
@@ -151,7 +187,7 @@ export function Sidebar({
                   if (e.key === 'Enter') handleCreateSubmit();
                   if (e.key === 'Escape') { setCreating(false); setNewName(''); }
                 }}
-                placeholder="Collection name..."
+                placeholder="Name, or parent/name..."
                 autoFocus
                 onBlur={handleCreateSubmit}
               />
@@ -163,9 +199,12 @@ export function Sidebar({
                 key={col.id}
                 className={`sidebar-item ${activeCollection === col.id ? 'sidebar-item-active' : ''}`}
                 onClick={() => onSelectCollection(col.id)}
+                style={{ paddingLeft: `calc(var(--spacing-md) + ${col.name.split('/').length - 1} * 14px)` }}
+                title={col.name}
               >
-                <span className="truncate">{col.name}</span>
-                <span className="sidebar-count">{col.count}</span>
+                {col.cover_path && <img className="sidebar-cover" src={getImageUrl(col.cover_path)} alt="" />}
+                <span className="truncate">{col.name.split('/').pop()}</span>
+                <span className="sidebar-count">{col.total_count}</span>
               </button>
             ))}
             {collections.length === 0 && !creating && (
@@ -174,18 +213,76 @@ export function Sidebar({
           </div>
         </div>
 
+        <div className="sidebar-section">
+          <div className="sidebar-section-header">
+            <h3 className="sidebar-section-title">Smart Collections</h3>
+            <button
+              className="sidebar-add-btn"
+              onClick={() => (creatingSmart ? cancelSmart() : setCreatingSmart(true))}
+              title="Create smart collection (saved search)"
+            >
+              +
+            </button>
+          </div>
+          {creatingSmart && (
+            <div className="sidebar-create-input">
+              <input
+                type="text"
+                value={smartName}
+                onChange={(e) => setSmartName(e.target.value)}
+                onKeyDown={(e) => { if (e.key === 'Escape') cancelSmart(); }}
+                placeholder="Name..."
+                autoFocus
+              />
+              <input
+                type="text"
+                value={smartFilter}
+                onChange={(e) => { setSmartFilter(e.target.value); setSmartError(null); }}
+                onKeyDown={(e) => {
+                  if (e.key === 'Enter') handleSmartSubmit();
+                  if (e.key === 'Escape') cancelSmart();
+                }}
+                placeholder="model=noobai AND starred"
+              />
+              {smartError && <p className="sidebar-error">{smartError}</p>}
+            </div>
+          )}
+          <div className="sidebar-collections">
+            {smartCollections.map((col) => (
+              <button
+                key={col.id}
+                className={`sidebar-item sidebar-item-smart ${activeSmartCollection === col.id ? 'sidebar-item-active' : ''}`}
+                onClick={() => onSelectSmartCollection(col.id)}
+                onContextMenu={(e) => {
+                  e.preventDefault();
+                  if (confirm(`Delete smart collection "${col.name}"? Generations are not affected.`)) {
+                    onDeleteSmartCollection(col.name);
+                  }
+                }}
+                title={`${col.filter}\nRight-click to delete`}
+              >
+                <span className="truncate">{col.name}</span>
+                <span className="sidebar-count">{col.count}</span>
+              </button>
+            ))}
+            {smartCollections.length === 0 && !creatingSmart && (
+              <p className="sidebar-empty">No saved searches</p>
+            )}
+          </div>
+        </div>
+
         {todayCost.total > 0 && (
           <div className="sidebar-today-cost">
             <div className="sidebar-cost-header">Today</div>
             {todayCost.byModel.map(([model, cost]) => (
               <div key={model} className="sidebar-cost-row">
                 <span className="sidebar-cost-model truncate">{model}</span>
-                <span className="sidebar-cost-amount">${cost.toFixed(3)}</span>
+                <span className="sidebar-cost-amount">{formatCost(cost, 3)}</span>
               </div>
             ))}
             <div className="sidebar-cost-row sidebar-cost-total">
               <span>Total</span>
-              <span>${todayCost.total.toFixed(2)}</span>
+              <span>{formatCost(todayCost.total, 2)}</span>
             </div>
           </div>
         )}
@@ -363,6 +460,24 @@ export function Sidebar({
           max-height: 300px;
           overflow-y: auto;
         }
+        .sidebar-cover {
+          width: 20px;
+          height: 20px;
+          object-fit: cover;
+          border-radius: 3px;
+          flex-shrink: 0;
+        }
+        .sidebar-cover + .truncate {
+          flex: 1;
+        }
+        .sidebar-item-smart .truncate {
+          font-style: italic;
+        }
+        .sidebar-error {
+          padding: 2px var(--spacing-md);
+          color: var(--error);
+          font-size: 12px;
+        }
         .sidebar-today-cost {
           margin-top: auto;
           padding: var(--spacing-sm) 0;
@@ -0,0 +1,96 @@
+import { useState, useEffect, useCallback } from 'react';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+import type { Slideshow as SlideshowConfig } from '../lib/types';
+import { getGenerationImageUrl } from '../lib/api';
+
+interface SlideshowProps {
+  show: SlideshowConfig;
+}
+
+// The whole window when opened by `pixery slideshow`
+export function Slideshow({ show }: SlideshowProps) {
+  const [index, setIndex] = useState(0);
+  const [paused, setPaused] = useState(false);
+  const count = show.ids.length;
+
+  const step = useCallback((delta: number) => {
+    setIndex((i) => (i + delta + count) % count);
+  }, [count]);
+
+  // Restarted on every change, so an image stepped to by hand gets a full interval
+  useEffect(() => {
+    if (paused) return;
+    const timer = window.setTimeout(() => step(1), show.interval_seconds * 1000);
+    return () => window.clearTimeout(timer);
+  }, [index, paused, step, show.interval_seconds]);
+
+  // Load the next image ahead so it doesn't pop in
+  useEffect(() => {
+    const next = new Image();
+    next.src = getGenerationImageUrl(show.ids[(index + 1) % count], 'full');
+  }, [index, count, show.ids]);
+
+  const handleKeyDown = useCallback((e: KeyboardEvent) => {
+    const win = getCurrentWindow();
+    if (e.key === 'Escape') {
+      win.close();
+    } else if (e.key === ' ') {
+      e.preventDefault();
+      setPaused((p) => !p);
+    } else if (e.key === 'ArrowRight' || e.key === 'l') {
+      step(1);
+    } else if (e.key === 'ArrowLeft' || e.key === 'h') {
+      step(-1);
+    } else if (e.key === 'f') {
+      win.isFullscreen().then((fullscreen) => win.setFullscreen(!fullscreen));
+    }
+  }, [step]);
+
+  useEffect(() => {
+    document.addEventListener('keydown', handleKeyDown);
+    return () => document.removeEventListener('keydown', handleKeyDown);
+  }, [handleKeyDown]);
+
+  const id = show.ids[index];
+
+  return (
+    <div className="slideshow" onClick={() => step(1)}>
+      <img key={id} src={getGenerationImageUrl(id, 'full')} alt="" />
+      {paused && (
+        <div className="slideshow-status">
+          Paused · {index + 1}/{count}
+        </div>
+      )}
+
+      <style>{`
+        .slideshow {
+          position: fixed;
+          inset: 0;
+          background: #000;
+          display: flex;
+          align-items: center;
+          justify-content: center;
+          cursor: none;
+        }
+        .slideshow img {
+          max-width: 100vw;
+          max-height: 100vh;
+          object-fit: contain;
+          animation: slideshow-fade 0.4s ease-out;
+        }
+        .slideshow-status {
+          position: fixed;
+          bottom: var(--spacing-lg);
+          right: var(--spacing-lg);
+          color: var(--text-muted);
+          font-family: var(--font-mono);
+          font-size: 13px;
+        }
+        @keyframes slideshow-fade {
+          from { opacity: 0; }
+          to { opacity: 1; }
+        }
+      `}</style>
+    </div>
+  );
+}
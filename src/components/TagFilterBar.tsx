@@ -40,6 +40,15 @@ export function TagFilterBar({
     const query = inputValue.toLowerCase();
     const items: SuggestionItem[] = [];
 
+    // Namespace wildcards ("character:*" matches any character tag)
+    const namespaces = new Set(availableTags.flatMap((tag) => (tag.namespace ? [tag.namespace] : [])));
+    for (const namespace of [...namespaces].sort()) {
+      const wildcard = `${namespace}:*`;
+      if (!filterTags.includes(wildcard) && wildcard.includes(query)) {
+        items.push({ type: 'tag', name: wildcard, displayName: `${namespace}: any` });
+      }
+    }
+
     // Add matching tags
     for (const tag of availableTags) {
       if (!filterTags.includes(tag.name) && tag.name.toLowerCase().includes(query)) {
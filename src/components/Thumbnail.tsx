@@ -1,6 +1,6 @@
-import { memo } from 'react';
+import { memo, useState } from 'react';
 import type { Generation } from '../lib/types';
-import { getImageUrl } from '../lib/api';
+import { getGenerationImageUrl } from '../lib/api';
 
 interface ThumbnailProps {
   generation: Generation;
@@ -12,19 +12,21 @@ interface ThumbnailProps {
 }
 
 export const Thumbnail = memo(function Thumbnail({ generation, selected, marked, onClick, onDoubleClick, onContextMenu }: ThumbnailProps) {
-  const imageSrc = generation.thumb_path
-    ? getImageUrl(generation.thumb_path)
-    : getImageUrl(generation.image_path);
+  // Animated images play on hover; the grid thumbnail is their first frame
+  const [hovered, setHovered] = useState(false);
+  const imageSrc = getGenerationImageUrl(generation.id, generation.animated && hovered ? 'full' : 'grid');
 
   // Extract short model name (last segment of path)
   const shortModel = generation.model.split('/').pop();
 
   return (
     <div
-      className={`thumbnail ${selected ? 'thumbnail-selected' : ''}`}
+      className={`thumbnail ${selected ? 'thumbnail-selected' : ''} ${generation.nsfw ? 'thumbnail-nsfw' : ''}`}
       onClick={onClick}
       onDoubleClick={onDoubleClick}
       onContextMenu={onContextMenu}
+      onMouseEnter={generation.animated ? () => setHovered(true) : undefined}
+      onMouseLeave={generation.animated ? () => setHovered(false) : undefined}
     >
       <img src={imageSrc} alt={generation.slug} loading="lazy" />
       {marked && (
@@ -35,6 +37,13 @@ export const Thumbnail = memo(function Thumbnail({ generation, selected, marked,
         </span>
       )}
       {generation.starred && <span className="thumbnail-star">★</span>}
+      {generation.media_type === 'video' && (
+        <span className="thumbnail-video">
+          ▶{generation.duration_seconds ? ` ${Math.round(generation.duration_seconds)}s` : ''}
+        </span>
+      )}
+      {generation.animated && <span className="thumbnail-video">ANIM</span>}
+      {generation.nsfw && <span className="thumbnail-nsfw-badge">NSFW</span>}
       <span className="thumbnail-id">#{generation.id}</span>
       <div className="thumbnail-overlay">
         <span className="thumbnail-info">#{generation.id}</span>
@@ -63,6 +72,34 @@ export const Thumbnail = memo(function Thumbnail({ generation, selected, marked,
           width: 100%;
           height: auto;
         }
+        /* Flagged images stay blurred until hovered */
+        .thumbnail-nsfw img {
+          filter: blur(18px);
+          transform: scale(1.1);
+          transition: filter var(--transition-fast);
+        }
+        .thumbnail-nsfw:hover img {
+          filter: none;
+          transform: none;
+        }
+        .thumbnail-nsfw-badge {
+          position: absolute;
+          top: 50%;
+          left: 50%;
+          transform: translate(-50%, -50%);
+          padding: 2px 8px;
+          font-size: 11px;
+          font-weight: 600;
+          color: white;
+          background: rgba(0,0,0,0.6);
+          border-radius: var(--radius-sm);
+          z-index: 1;
+          pointer-events: none;
+          transition: opacity var(--transition-fast);
+        }
+        .thumbnail-nsfw:hover .thumbnail-nsfw-badge {
+          opacity: 0;
+        }
         .thumbnail-check {
           position: absolute;
           top: var(--spacing-xs);
@@ -87,6 +124,22 @@ export const Thumbnail = memo(function Thumbnail({ generation, selected, marked,
           text-shadow: 0 1px 2px rgba(0,0,0,0.5);
           z-index: 2;
         }
+        .thumbnail-video {
+          position: absolute;
+          bottom: var(--spacing-xs);
+          right: var(--spacing-xs);
+          padding: 1px 6px;
+          font-size: 11px;
+          font-weight: 600;
+          color: white;
+          background: rgba(0,0,0,0.6);
+          border-radius: var(--radius-sm);
+          z-index: 1;
+          transition: opacity var(--transition-fast);
+        }
+        .thumbnail:hover .thumbnail-video {
+          opacity: 0;
+        }
         .thumbnail-id {
           position: absolute;
           bottom: var(--spacing-xs);
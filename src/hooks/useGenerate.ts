@@ -41,8 +41,8 @@ export function useGenerate(): UseGenerateResult {
       if (results.length > 0) {
         setLastGeneration(results[results.length - 1]);
       }
-      const message = e instanceof Error ? e.message : 'Generation failed';
-      setError(message);
+      // A cancel is the user's doing, not a failure to show
+      setError(api.isPixeryError(e) && e.code === 'cancelled' ? null : api.errorMessage(e));
       return results;
     } finally {
       setGenerating(false);
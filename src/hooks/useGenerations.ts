@@ -15,6 +15,8 @@ interface UseGenerationsResult {
   loadingMore: boolean;
   error: string | null;
   hasMore: boolean;
+  /** Total matches for the current filter, across all pages */
+  total: number;
   refresh: () => Promise<void>;
   loadMore: () => Promise<void>;
   search: (query: string) => Promise<void>;
@@ -60,11 +62,11 @@ function hasGenerationChanged(a: Generation, b: Generation): boolean {
 }
 
 /**
- * Create a stable key for filter dependencies, excluding limit/offset
- * so pagination doesn't trigger a full reset
+ * Create a stable key for filter dependencies, excluding pagination fields
+ * so loading more doesn't trigger a full reset
  */
 function getFilterDepsKey(filter: ListFilter): string {
-  const { limit: _limit, offset: _offset, ...rest } = filter;
+  const { limit: _limit, offset: _offset, cursor: _cursor, ...rest } = filter;
   return JSON.stringify(rest);
 }
 
@@ -75,8 +77,10 @@ export function useGenerations(options: UseGenerationsOptions = {}): UseGenerati
   const [loadingMore, setLoadingMore] = useState(false);
   const [error, setError] = useState<string | null>(null);
   const [hasMore, setHasMore] = useState(true);
+  const [total, setTotal] = useState(0);
   const isInitialLoad = useRef(true);
-  const offsetRef = useRef(0);
+  // Keyset cursor for the next page; null once the last page is loaded
+  const cursorRef = useRef<string | null>(null);
 
   // Stable filter key excluding pagination params
   const filterDepsKey = getFilterDepsKey(filter);
@@ -90,31 +94,28 @@ export function useGenerations(options: UseGenerationsOptions = {}): UseGenerati
       }
       setError(null);
 
-      const currentOffset = append ? offsetRef.current : 0;
       const fetchFilter: ListFilter = {
         ...filter,
-        limit: PAGE_SIZE,
-        offset: currentOffset,
+        cursor: append ? cursorRef.current ?? undefined : undefined,
       };
 
-      const data = await api.listGenerations(fetchFilter);
+      const page = await api.listGenerationsPage(fetchFilter, PAGE_SIZE);
 
-      // Determine if there are more results
-      setHasMore(data.length === PAGE_SIZE);
+      cursorRef.current = page.next_cursor;
+      setHasMore(page.next_cursor !== null);
+      setTotal(page.total);
 
       if (append) {
         // Append to existing data
-        setGenerations(prev => [...prev, ...data]);
-        offsetRef.current = currentOffset + data.length;
+        setGenerations(prev => [...prev, ...page.items]);
       } else {
         // Replace data (initial load or filter change)
-        setGenerations(prev => mergeGenerations(prev, data));
-        offsetRef.current = data.length;
+        setGenerations(prev => mergeGenerations(prev, page.items));
       }
 
       isInitialLoad.current = false;
     } catch (e) {
-      setError(e instanceof Error ? e.message : 'Failed to load generations');
+      setError(api.errorMessage(e));
     } finally {
       setLoading(false);
       setLoadingMore(false);
@@ -128,7 +129,7 @@ export function useGenerations(options: UseGenerationsOptions = {}): UseGenerati
 
   const refresh = useCallback(async () => {
     // Reset pagination state
-    offsetRef.current = 0;
+    cursorRef.current = null;
     setHasMore(true);
     await fetchGenerations(false);
   }, [fetchGenerations]);
@@ -140,9 +141,10 @@ export function useGenerations(options: UseGenerationsOptions = {}): UseGenerati
       const data = await api.searchGenerations(query);
       setGenerations(data);
       setHasMore(false); // Search doesn't support pagination
-      offsetRef.current = data.length;
+      setTotal(data.length);
+      cursorRef.current = null;
     } catch (e) {
-      setError(e instanceof Error ? e.message : 'Search failed');
+      setError(api.errorMessage(e));
     } finally {
       setLoading(false);
     }
@@ -150,7 +152,7 @@ export function useGenerations(options: UseGenerationsOptions = {}): UseGenerati
 
   // Reset and fetch when filter changes (excluding pagination)
   useEffect(() => {
-    offsetRef.current = 0;
+    cursorRef.current = null;
     setHasMore(true);
     isInitialLoad.current = true;
     fetchGenerations(false);
@@ -168,6 +170,7 @@ export function useGenerations(options: UseGenerationsOptions = {}): UseGenerati
     loadingMore,
     error,
     hasMore,
+    total,
     refresh,
     loadMore,
     search,
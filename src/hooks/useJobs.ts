@@ -1,23 +1,29 @@
 import { useState, useEffect, useRef, useCallback } from 'react';
-import type { Job } from '../lib/types';
+import { listen } from '@tauri-apps/api/event';
+import type { Job, GenerationProgress, RetryReport } from '../lib/types';
 import * as api from '../lib/api';
 
 const POLL_INTERVAL_ACTIVE = 500; // ms - poll fast when jobs are active
 const POLL_INTERVAL_IDLE = 2000; // ms - poll slower when no jobs
 const FAILED_JOBS_POLL_INTERVAL = 5000; // ms - poll failed jobs less frequently
+const FAILED_JOBS_WINDOW_HOURS = 2; // matches list_failed_jobs
 
 interface UseJobsResult {
   jobs: Job[];
   failedJobs: Job[];
   activeCount: number;
   failedCount: number;
+  progress: Record<number, GenerationProgress>;
   dismissFailedJob: (id: number) => void;
+  cancelJob: (id: number) => void;
+  retryFailedJobs: () => Promise<RetryReport>;
 }
 
 export function useJobs(): UseJobsResult {
   const [jobs, setJobs] = useState<Job[]>([]);
   const [failedJobs, setFailedJobs] = useState<Job[]>([]);
   const [dismissedIds, setDismissedIds] = useState<Set<number>>(new Set());
+  const [progress, setProgress] = useState<Record<number, GenerationProgress>>({});
   const pollTimeoutRef = useRef<number | null>(null);
   const failedPollTimeoutRef = useRef<number | null>(null);
   const mountedRef = useRef<boolean>(true);
@@ -26,6 +32,24 @@ export function useJobs(): UseJobsResult {
     setDismissedIds(prev => new Set(prev).add(id));
   }, []);
 
+  // Retries show up as active jobs while they run; re-run failures drop out of the list
+  const retryFailedJobs = useCallback(() => api.retryFailedJobs(FAILED_JOBS_WINDOW_HOURS), []);
+
+  // The job disappears from the active list on the next poll once it's marked cancelled
+  const cancelJob = useCallback((id: number) => {
+    api.cancelJob(id).catch(e => console.error('Failed to cancel job:', e));
+  }, []);
+
+  // Latest progress stage per job, streamed from GUI generations
+  useEffect(() => {
+    const unlisten = listen<GenerationProgress>('generation-progress', (event) => {
+      setProgress(prev => ({ ...prev, [event.payload.job_id]: event.payload }));
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   useEffect(() => {
     mountedRef.current = true;
 
@@ -81,13 +105,16 @@ export function useJobs(): UseJobsResult {
   }, []);
 
   const activeCount = jobs.filter(j => j.status === 'pending' || j.status === 'running').length;
-  const visibleFailedJobs = failedJobs.filter(j => !dismissedIds.has(j.id));
+  const visibleFailedJobs = failedJobs.filter(j => !dismissedIds.has(j.id) && j.retry_job_id === null);
 
   return {
     jobs,
     failedJobs: visibleFailedJobs,
     activeCount,
     failedCount: visibleFailedJobs.length,
+    progress,
     dismissFailedJob,
+    cancelJob,
+    retryFailedJobs,
   };
 }
@@ -23,7 +23,7 @@ export function useTags(): UseTagsResult {
       const data = await api.listTags();
       setTags(data);
     } catch (e) {
-      setError(e instanceof Error ? e.message : 'Failed to load tags');
+      setError(api.errorMessage(e));
     } finally {
       setLoading(false);
     }
@@ -3,15 +3,47 @@ import type {
   Generation,
   GenerateParams,
   ListFilter,
+  GenerationPage,
+  FilterFacets,
+  Stats,
   TagCount,
   ModelInfo,
+  ModelCatalog,
+  ModelQuota,
+  Preset,
   CostSummary,
+  CurrencySettings,
+  DisplayCurrency,
   Reference,
+  ReferenceInfo,
   Job,
+  RetryReport,
+  NotifySettings,
+  NsfwSettings,
   SelfHostedStatus,
+  Slideshow,
+  WarmupResult,
   Collection,
+  SmartCollection,
+  Experiment,
+  ActivityEvent,
+  MaintenanceStatus,
+  ImageSize,
+  PixeryError,
 } from './types';
 
+// Errors
+
+export function isPixeryError(e: unknown): e is PixeryError {
+  return typeof e === 'object' && e !== null && 'code' in e && 'message' in e;
+}
+
+// Text for any rejection: a command's PixeryError, a JS Error, or anything else
+export function errorMessage(e: unknown): string {
+  if (isPixeryError(e) || e instanceof Error) return e.message;
+  return String(e);
+}
+
 export async function generateImage(params: GenerateParams): Promise<Generation> {
   return invoke('generate_image', { params });
 }
@@ -20,6 +52,22 @@ export async function listGenerations(filter: ListFilter = {}): Promise<Generati
   return invoke('list_generations', { filter });
 }
 
+export async function listGenerationsPage(filter: ListFilter = {}, pageSize: number = 50): Promise<GenerationPage> {
+  return invoke('list_generations_page', { filter, pageSize });
+}
+
+export async function randomGenerations(filter: ListFilter = {}, count: number = 5): Promise<Generation[]> {
+  return invoke('random_generations', { filter, count });
+}
+
+export async function countGenerations(filter: ListFilter = {}): Promise<number> {
+  return invoke('count_generations', { filter });
+}
+
+export async function getFilterFacets(filter: ListFilter = {}): Promise<FilterFacets> {
+  return invoke('get_filter_facets', { filter });
+}
+
 export async function searchGenerations(query: string, limit: number = 20): Promise<Generation[]> {
   return invoke('search_generations', { query, limit });
 }
@@ -32,6 +80,14 @@ export async function toggleStarred(id: number): Promise<boolean> {
   return invoke('toggle_starred', { id });
 }
 
+export async function setLocked(id: number, locked: boolean): Promise<boolean> {
+  return invoke('set_locked', { id, locked });
+}
+
+export async function setNsfw(id: number, nsfw: boolean): Promise<boolean> {
+  return invoke('set_nsfw', { id, nsfw });
+}
+
 export async function trashGeneration(id: number): Promise<boolean> {
   return invoke('trash_generation', { id });
 }
@@ -56,6 +112,19 @@ export async function updateTitle(id: number, title: string | null): Promise<voi
   return invoke('update_title', { id, title });
 }
 
+export async function captionGeneration(id: number): Promise<Generation> {
+  return invoke('caption_generation', { id });
+}
+
+// Store a dropped image file as a reference; returns its path for reference_paths
+export async function stageReference(file: File): Promise<string> {
+  return invoke('stage_reference', new Uint8Array(await file.arrayBuffer()));
+}
+
+export async function copyImageToClipboard(id: number): Promise<void> {
+  return invoke('copy_image_to_clipboard', { id });
+}
+
 export async function addTags(id: number, tags: string[]): Promise<void> {
   return invoke('add_tags', { id, tags });
 }
@@ -72,19 +141,53 @@ export async function listModels(): Promise<ModelInfo[]> {
   return invoke('list_models');
 }
 
+export async function getModelQuotas(): Promise<ModelQuota[]> {
+  return invoke('get_model_quotas');
+}
+
+export async function getModelCatalog(): Promise<ModelCatalog> {
+  return invoke('get_model_catalog');
+}
+
+export async function refreshModelCatalog(): Promise<ModelCatalog> {
+  return invoke('refresh_model_catalog');
+}
+
+export async function listPresets(): Promise<Preset[]> {
+  return invoke('list_presets');
+}
+
 export async function getCostSummary(since?: string): Promise<CostSummary> {
   return invoke('get_cost_summary', { since });
 }
 
+export async function getStats(since?: string): Promise<Stats> {
+  return invoke('get_stats', { since });
+}
+
 export async function getReferences(id: number): Promise<Reference[]> {
   return invoke('get_references', { id });
 }
 
+export async function listReferences(): Promise<ReferenceInfo[]> {
+  return invoke('list_references');
+}
+
+export async function getReferenceGenerations(id: number): Promise<Generation[]> {
+  return invoke('get_reference_generations', { id });
+}
+
 export function getImageUrl(path: string): string {
   // Use Tauri's convertFileSrc to load local files
   return convertFileSrc(path);
 }
 
+/** A generation's image through the pixery:// protocol: served with caching
+ * headers, and thumbnails not written yet are created on the spot */
+export function getGenerationImageUrl(id: number, size: ImageSize): string {
+  return convertFileSrc(`${id}/${size}`, 'pixery');
+}
+
 export async function listJobs(): Promise<Job[]> {
   return invoke('list_jobs');
 }
@@ -93,6 +196,66 @@ export async function listFailedJobs(limit?: number): Promise<Job[]> {
   return invoke('list_failed_jobs', { limit });
 }
 
+export async function cancelJob(jobId: number): Promise<void> {
+  return invoke('cancel_job', { jobId });
+}
+
+export async function getNotifySettings(): Promise<NotifySettings> {
+  return invoke('get_notify_settings');
+}
+
+export async function setNotifySettings(settings: NotifySettings): Promise<void> {
+  return invoke('set_notify_settings', { settings });
+}
+
+export async function getNsfwSettings(): Promise<NsfwSettings> {
+  return invoke('get_nsfw_settings');
+}
+
+export async function setNsfwSettings(settings: NsfwSettings): Promise<void> {
+  return invoke('set_nsfw_settings', { settings });
+}
+
+export async function getCurrencySettings(): Promise<CurrencySettings> {
+  return invoke('get_currency_settings');
+}
+
+export async function setCurrencySettings(settings: CurrencySettings): Promise<DisplayCurrency> {
+  return invoke('set_currency_settings', { settings });
+}
+
+export async function getDisplayCurrency(): Promise<DisplayCurrency> {
+  return invoke('get_display_currency');
+}
+
+export async function refreshExchangeRate(): Promise<DisplayCurrency> {
+  return invoke('refresh_exchange_rate');
+}
+
+// Guest mode: the backend refuses anything that would change the archive
+export async function getReadOnly(): Promise<boolean> {
+  return invoke('get_read_only');
+}
+
+export async function getSlideshow(): Promise<Slideshow | null> {
+  return invoke('get_slideshow');
+}
+
+// Generation a pixery:// link asked to show; null once taken
+export async function takeFocusGeneration(): Promise<number | null> {
+  return invoke('take_focus_generation');
+}
+
+export async function retryFailedJobs(hours?: number, maxCost?: number): Promise<RetryReport> {
+  return invoke('retry_failed_jobs', { hours, maxCost });
+}
+
+// Maintenance
+
+export async function getMaintenanceStatus(): Promise<MaintenanceStatus> {
+  return invoke('get_maintenance_status');
+}
+
 // Self-hosted server settings
 
 export async function getSelfhostedUrl(): Promise<string | null> {
@@ -107,6 +270,14 @@ export async function checkSelfhostedHealth(): Promise<SelfHostedStatus> {
   return invoke('check_selfhosted_health');
 }
 
+export async function switchSelfhostedModel(model: string): Promise<void> {
+  return invoke('switch_selfhosted_model', { model });
+}
+
+export async function warmupSelfhosted(model?: string): Promise<WarmupResult> {
+  return invoke('warmup_selfhosted', { model: model ?? null });
+}
+
 // Prompt history
 
 export async function promptHistory(limit: number): Promise<[number, string, string][]> {
@@ -131,6 +302,53 @@ export async function removeFromCollection(generationId: number, collectionName:
   return invoke('remove_from_collection', { generationId, collectionName });
 }
 
-export async function deleteCollection(name: string): Promise<boolean> {
-  return invoke('delete_collection', { name });
+// Fails for a collection with many memberships unless force is set
+export async function deleteCollection(name: string, force = false): Promise<boolean> {
+  return invoke('delete_collection', { name, force });
+}
+
+export async function restoreCollection(name: string): Promise<number> {
+  return invoke('restore_collection', { name });
+}
+
+export async function reorderCollection(name: string, ids: number[]): Promise<void> {
+  return invoke('reorder_collection', { name, ids });
+}
+
+export async function setCollectionCover(name: string, generationId: number | null): Promise<void> {
+  return invoke('set_collection_cover', { name, generationId });
+}
+
+export async function listSmartCollections(): Promise<SmartCollection[]> {
+  return invoke('list_smart_collections');
+}
+
+export async function createSmartCollection(name: string, filter: string, description?: string): Promise<number> {
+  return invoke('create_smart_collection', { name, filter, description });
+}
+
+export async function deleteSmartCollection(name: string): Promise<boolean> {
+  return invoke('delete_smart_collection', { name });
+}
+
+export async function listExperiments(): Promise<Experiment[]> {
+  return invoke('list_experiments');
+}
+
+export async function getExperiment(name: string): Promise<Experiment | null> {
+  return invoke('get_experiment', { name });
+}
+
+export async function activityFeed(limit?: number, generationId?: number): Promise<ActivityEvent[]> {
+  return invoke('activity_feed', { limit, generationId });
+}
+
+/** Reverse an activity event; resolves to what was done, e.g. "Restored 12" */
+export async function undoEvent(id: number): Promise<string> {
+  return invoke('undo_event', { id });
+}
+
+/** Undo the last destructive change; null when there's nothing to undo */
+export async function undoLast(): Promise<string | null> {
+  return invoke('undo_last');
 }
@@ -0,0 +1,21 @@
+import type { DisplayCurrency } from './types';
+
+const SYMBOLS: Record<string, string> = { USD: '$', EUR: '€', GBP: '£', JPY: '¥' };
+
+let current: DisplayCurrency = { code: 'USD', rate: 1, source: '' };
+
+// Set once the backend answers (App), and again when Settings changes it
+export function setDisplayCurrency(currency: DisplayCurrency) {
+  current = currency;
+}
+
+export function displayCurrency(): DisplayCurrency {
+  return current;
+}
+
+// A USD amount in the display currency: "$1.23", "€1.23", "1.23 CHF"
+export function formatCost(usd: number, digits = 2): string {
+  const amount = (usd * current.rate).toFixed(digits);
+  const symbol = SYMBOLS[current.code];
+  return symbol ? `${symbol}${amount}` : `${amount} ${current.code}`;
+}
@@ -20,11 +20,39 @@ export interface Generation {
   trashed_at: string | null;
   title: string | null;
   negative_prompt: string | null;
+  locked: boolean;
+  caption: string | null;
+  /** Who asked for it through an integration, e.g. `discord:alice` */
+  requested_by: string | null;
+  /** Resolved request it was made from; null for imports and older generations */
+  params: Record<string, unknown> | null;
+  media_type: MediaType;
+  /** Clip length, for videos */
+  duration_seconds: number | null;
+  fps: number | null;
+  /** Animated GIF/WebP/APNG: the thumbnails are still, the full image loops */
+  animated: boolean;
+  /** Hidden unless NSFW is shown in settings; blurred in the grid then */
+  nsfw: boolean;
   tags: string[];
   references: Reference[];
   collection_names: string[];
+  /** Dominant colors, largest share first; empty until extracted */
+  palette: Swatch[];
 }
 
+export interface Swatch {
+  /** "#rrggbb" */
+  hex: string;
+  /** Fraction of the image, 0-1 */
+  share: number;
+}
+
+export type MediaType = 'image' | 'video';
+
+/** 'grid' is the 400px gallery thumbnail (thumb_path), 'preview' the 800px details one */
+export type ImageSize = 'grid' | 'preview' | 'full';
+
 export interface GenerateParams {
   prompt: string;
   model: string;
@@ -34,6 +62,22 @@ export interface GenerateParams {
   negative_prompt: string | null;
   width: number | null;
   height: number | null;
+  /** Preset whose settings fill in anything left unset */
+  preset?: string | null;
+  /** Transparent background PNG, tagged `transparent` */
+  transparent?: boolean;
+}
+
+export interface Preset {
+  id: number;
+  name: string;
+  model: string;
+  negative_prompt: string | null;
+  width: number | null;
+  height: number | null;
+  cfg_scale: number | null;
+  tags: string[];
+  created_at: string;
 }
 
 export interface Collection {
@@ -42,25 +86,109 @@ export interface Collection {
   description: string | null;
   created_at: string;
   count: number;
+  /** Explicit cover generation */
+  cover_id: number | null;
+  /** Thumbnail of the cover, or of the first member when none is set */
+  cover_path: string | null;
+  /** Enclosing collection; `name` is the full "parent/child" path */
+  parent_id: number | null;
+  /** Generations here or in any subcollection */
+  total_count: number;
+}
+
+/** Saved search; `filter` is an expression like "model=noobai AND starred" */
+export interface SmartCollection {
+  id: number;
+  name: string;
+  description: string | null;
+  filter: string;
+  created_at: string;
+  count: number;
+}
+
+/** A `pixery ab` or `pixery batch` run; `params` holds its options as given */
+export interface Experiment {
+  id: number;
+  name: string;
+  kind: 'ab' | 'batch';
+  prompt: string;
+  params: Record<string, unknown>;
+  created_at: string;
+  count: number;
+  total_cost: number;
+}
+
+export type EventAction =
+  | 'generate'
+  | 'star'
+  | 'unstar'
+  | 'tag'
+  | 'untag'
+  | 'trash'
+  | 'restore'
+  | 'delete'
+  | 'edit_prompt'
+  | 'edit_title'
+  | 'collection_add'
+  | 'collection_remove';
+
+/** An activity log entry; `detail` has `tags`, `before`/`after` for edits, or `collection` */
+export interface ActivityEvent {
+  id: number;
+  generation_id: number;
+  action: EventAction;
+  detail: Record<string, unknown>;
+  created_at: string;
+  /** Set on the events an undo made: the event it reversed */
+  undo_of: number | null;
+  undone: boolean;
 }
 
 export interface ListFilter {
   limit?: number;
   offset?: number;
+  /** Keyset cursor from GenerationPage.next_cursor */
+  cursor?: string;
   tags?: string[];
   exclude_tags?: string[];
   model?: string;
   starred_only?: boolean;
+  /** Always applied by the backend unless NSFW is shown in settings */
+  hide_nsfw?: boolean;
+  nsfw_only?: boolean;
   search?: string;
   since?: string;
+  /** Last date included (YYYY-MM-DD) */
+  until?: string;
+  min_width?: number;
+  min_height?: number;
+  orientation?: 'portrait' | 'landscape' | 'square';
+  has_reference?: boolean;
+  media_type?: MediaType;
+  /** "#rrggbb" colors each needing a palette color near them */
+  colors?: string[];
+  /** CIE76 difference counted as near; defaults to 20 */
+  color_tolerance?: number;
+  /** Omit for newest first */
+  sort?: SortKey;
+  sort_desc?: boolean;
   collection_id?: number;
+  /** Also apply this smart collection's stored filter */
+  smart_collection_id?: number;
+  /** Only generations from this experiment (pixery ab / batch run) */
+  experiment_id?: number;
   show_trashed?: boolean;
   uncategorized?: boolean;
 }
 
+/** 'position' is a collection's own order and needs collection_id */
+export type SortKey = 'timestamp' | 'cost' | 'generation_time' | 'file_size' | 'starred' | 'random' | 'position';
+
 export interface TagCount {
   name: string;
   count: number;
+  /** "character" for "character:kira" */
+  namespace?: string | null;
 }
 
 export interface ModelInfo {
@@ -71,6 +199,18 @@ export interface ModelInfo {
   max_refs?: number;
 }
 
+export interface CatalogModel {
+  endpoint_id: string;
+  display_name: string;
+  unit_price: number | null;
+  unit: string | null;
+}
+
+export interface ModelCatalog {
+  fetched_at: string;
+  models: CatalogModel[];
+}
+
 export interface SelfHostedStatus {
   connected: boolean;
   url: string | null;
@@ -80,11 +220,92 @@ export interface SelfHostedStatus {
   error: string | null;
 }
 
+export interface WarmupResult {
+  model: string;
+  load_seconds: number;
+  warmup_seconds: number;
+}
+
+export interface FacetCount {
+  name: string;
+  count: number;
+}
+
+/** Result counts per filter value; model/collection counts ignore their own selection */
+export interface FilterFacets {
+  total: number;
+  tags: FacetCount[];
+  models: FacetCount[];
+  collections: FacetCount[];
+}
+
+export interface GenerationPage {
+  items: Generation[];
+  /** Pass back as ListFilter.cursor for the next page; null on the last page */
+  next_cursor: string | null;
+  /** Total matches for the filter across all pages */
+  total: number;
+}
+
+export interface PeriodStat {
+  /** YYYY-MM-DD for days, YYYY-Www for weeks */
+  period: string;
+  count: number;
+  cost_usd: number;
+}
+
+export interface ModelStat {
+  model: string;
+  count: number;
+  cost_usd: number;
+  avg_generation_seconds: number | null;
+}
+
+export interface Stats {
+  total_generations: number;
+  total_cost_usd: number;
+  /** Oldest first */
+  per_day: PeriodStat[];
+  per_week: PeriodStat[];
+  jobs_completed: number;
+  jobs_failed: number;
+  success_rate: number | null;
+  by_model: ModelStat[];
+  top_tags: TagCount[];
+}
+
+// Amounts are USD; `currency` is how to show them
 export interface CostSummary {
   total_usd: number;
   by_model: [string, number][];
   by_day: [string, number][];
+  by_collection: [string, number][];
+  by_tag: [string, number][];
   count: number;
+  currency: DisplayCurrency;
+}
+
+// The currency costs are shown in: USD amounts times `rate`
+// A window opened by `pixery slideshow`
+export interface Slideshow {
+  ids: number[];
+  interval_seconds: number;
+  fullscreen: boolean;
+}
+
+export interface DisplayCurrency {
+  code: string;
+  rate: number;
+  // "manual", "fetched YYYY-MM-DD", or "" for USD
+  source: string;
+}
+
+// currency.json
+export interface CurrencySettings {
+  code: string;
+  manual_rate: number | null;
+  rates_url: string | null;
+  fetched: { code: string; rate: number; fetched_at: string } | null;
 }
 
 export interface TodayCost {
@@ -99,8 +320,41 @@ export interface Reference {
   created_at: string;
 }
 
-export type JobStatus = 'pending' | 'running' | 'completed' | 'failed';
-export type JobSource = 'cli' | 'gui';
+export interface ReferenceInfo extends Reference {
+  use_count: number;
+}
+
+export type JobStatus = 'pending' | 'running' | 'completed' | 'failed' | 'cancelled';
+export type JobSource = 'cli' | 'gui' | 'schedule';
+
+export type WebhookFormat = 'json' | 'discord' | 'slack' | 'ntfy';
+
+// nsfw.json, same as `pixery nsfw settings`
+export interface NsfwSettings {
+  /** List flagged generations (blurred in the grid) */
+  show: boolean;
+  /** Flag new generations with a rating tag in the prompt */
+  from_prompt: boolean;
+  /** Ask the caption vision model about each new image */
+  classify: boolean;
+}
+
+export interface NotifySettings extends Record<JobSource, boolean> {
+  webhook_url: string | null;
+  /** Guessed from the URL when null */
+  webhook_format: WebhookFormat | null;
+}
+
+export type ProgressStage =
+  | { stage: 'queued' }
+  | { stage: 'request_sent' }
+  | { stage: 'in_queue'; position: number | null }
+  | { stage: 'step'; step: number; total: number }
+  | { stage: 'downloading' }
+  | { stage: 'saving' }
+  | { stage: 'thumbnail_done' };
+
+export type GenerationProgress = { job_id: number } & ProgressStage;
 
 export interface Job {
   id: number;
@@ -115,4 +369,62 @@ export interface Job {
   completed_at: string | null;
   generation_id: number | null;
   error: string | null;
+  retry_count: number;
+  retry_job_id: number | null;
+  // Error category of a failed job; null before categories were recorded
+  failure: ErrorCode | null;
+}
+
+// A model's daily job quota from models.json and today's use
+export interface ModelQuota {
+  model: string;
+  limit: number;
+  used: number;
+  remaining: number;
+}
+
+export interface RetryReport {
+  retried: [number, number][];
+  failed: [number, string][];
+  skipped: number[];
+  blocked: number[];
+  over_budget: number[];
+  cost: number;
+}
+
+export interface MaintenanceReport {
+  stalled_jobs: number;
+  old_jobs: number;
+  trash_purged: number;
+  collections_purged: number;
+  previews_pruned: number;
+  refs_removed: number;
+}
+
+// What a failed command was about; tells a missing key from a rate limit
+export type ErrorCode =
+  | 'missing_api_key'
+  | 'auth_failed'
+  | 'rate_limited'
+  | 'quota_exceeded'
+  | 'content_policy'
+  | 'provider_error'
+  | 'network'
+  | 'file_missing'
+  | 'not_found'
+  | 'locked'
+  | 'cancelled'
+  | 'read_only'
+  | 'other';
+
+// Commands reject with this instead of a bare string
+export interface PixeryError {
+  code: ErrorCode;
+  message: string;
+}
+
+export interface MaintenanceStatus {
+  last_run: string | null;
+  last_report: MaintenanceReport | null;
+  last_error: string | null;
 }
@@ -1,10 +1,18 @@
 import React from 'react';
 import ReactDOM from 'react-dom/client';
 import App from './App';
+import { Slideshow } from './components/Slideshow';
+import { getSlideshow } from './lib/api';
+import type { Slideshow as SlideshowConfig } from './lib/types';
 import './styles/index.css';
 
-ReactDOM.createRoot(document.getElementById('root')!).render(
-  <React.StrictMode>
-    <App />
-  </React.StrictMode>
-);
+// `pixery slideshow` opens the window as a slideshow instead of the gallery
+function render(show: SlideshowConfig | null) {
+  ReactDOM.createRoot(document.getElementById('root')!).render(
+    <React.StrictMode>
+      {show ? <Slideshow show={show} /> : <App />}
+    </React.StrictMode>
+  );
+}
+
+getSlideshow().then(render).catch(() => render(null));